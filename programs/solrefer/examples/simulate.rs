@@ -0,0 +1,539 @@
+//! Models a referral program's budget off-chain by replaying a scenario through the program's own reward logic.
+//!
+//! A scenario is a JSON file with the program's settings and a timeline of deposits, joins, referrals, purchases,
+//! claims and cranks. Every credit and payout is worked out by the same functions the instruction handlers call
+//! (`referral_credit`, `credit_purchase`, `trailing_commission_due`, `claim_eligibility`, `settle_claim`, ...), so
+//! the summary matches what the deployed program would do with the same inputs. Account checks that need a chain
+//! (signers, invites, collection and token gates, payout splits, boosts) are left out.
+//!
+//! ```text
+//! cargo run -p solrefer --example simulate -- programs/solrefer/examples/scenarios/budget_500_sol.json
+//! ```
+//!
+//! Amounts are in raw units (lamports for a SOL program) and times in seconds from the start of the scenario.
+
+use std::{collections::HashMap, error::Error, fmt};
+
+use anchor_lang::prelude::Pubkey;
+use serde::Deserialize;
+use solrefer::{
+    constants::MAX_MILESTONES,
+    instructions::{
+        claim_eligibility, credit_purchase, referral_credit, settle_claim, trailing_commission_due, ClaimAccounts,
+        PayoutKind,
+    },
+    state::{
+        newly_reached_milestones, validate_milestones, EligibilityCriteria, Milestone, Participant, ReferralProgram,
+    },
+};
+
+/// A scenario file: the program's settings and what happens to it.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Scenario {
+    #[serde(default)]
+    name: String,
+    settings: Settings,
+    timeline: Vec<Event>,
+}
+
+/// The settings of the simulated program, named as in `ProgramSettings`; anything left out is off.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct Settings {
+    fixed_reward_amount: u64,
+    locked_period: i64,
+    program_end_time: Option<i64>,
+    max_reward_cap: u64,
+    max_depth: u16,
+    milestones: Vec<MilestoneSettings>,
+    revenue_share_percent: u64,
+    referee_reward_amount: u64,
+    reserve_bps: u64,
+    max_referrals_per_window: u32,
+    referral_window_seconds: i64,
+    trailing_commission_bps: u64,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+struct MilestoneSettings {
+    threshold: u64,
+    bonus: u64,
+}
+
+#[derive(Deserialize, Clone)]
+struct Event {
+    /// When the event happens; events are replayed in time order, ties in file order
+    at: i64,
+    #[serde(flatten)]
+    action: Action,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Action {
+    /// The authority deposits into the vault
+    Deposit { amount: u64 },
+    /// A participant joins without a referral
+    Join { participant: String },
+    /// `referee` joins through `referrer`
+    Referral { referrer: String, referee: String },
+    /// `count` referees named `<referrer>/<n>` join through `referrer`, `interval` seconds apart
+    Referrals { referrer: String, count: u64, interval: i64 },
+    /// The authority records a purchase by `buyer`, crediting its referrer
+    Purchase { buyer: String, amount: u64 },
+    /// A participant claims its pending rewards
+    Claim { participant: String },
+    /// A keeper runs `crank_maintenance`
+    Crank,
+}
+
+/// What a scenario did to the program.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Summary {
+    participants: u64,
+    /// Joins through a referral, credited or not
+    referrals: u64,
+    credited_referrals: u64,
+    /// Referrals that credited nothing because the program was not accepting referrals
+    underfunded_referrals: u64,
+    /// Referrals that credited nothing because the referrer's rate-limit window was full
+    rate_limited_referrals: u64,
+    /// Referrals that credited nothing because they were past the max depth
+    beyond_depth_referrals: u64,
+    /// Joins and purchases rejected because the program had ended
+    rejected: u64,
+    referral_rewards: u64,
+    referee_rewards: u64,
+    milestone_bonuses: u64,
+    /// How many referrers reached each milestone with its bonus paid
+    milestones_reached: [u64; MAX_MILESTONES],
+    purchase_rewards: u64,
+    trailing_commissions: u64,
+    /// Purchase rewards withheld by the reward cap or the program's headroom
+    cap_clamped: u64,
+    claims: u64,
+    /// Claims refused by a claim gate, such as rewards still locked
+    blocked_claims: u64,
+    /// Claims that failed because the program had committed more than it holds
+    failed_claims: u64,
+    total_paid: u64,
+    total_deposited: u64,
+    total_available: u64,
+    total_committed: u64,
+    /// When the program first stopped accepting referrals for lack of funds
+    exhausted_at: Option<i64>,
+}
+
+impl Summary {
+    fn zero_credit_referrals(&self) -> u64 {
+        self.referrals - self.credited_referrals
+    }
+
+    fn total_credited(&self) -> u64 {
+        self.referral_rewards
+            + self.referee_rewards
+            + self.milestone_bonuses
+            + self.purchase_rewards
+            + self.trailing_commissions
+    }
+}
+
+/// A scenario that cannot be replayed.
+#[derive(Debug)]
+struct ScenarioError(String);
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for ScenarioError {}
+
+fn scenario_error(message: impl Into<String>) -> Box<dyn Error> {
+    Box::new(ScenarioError(message.into()))
+}
+
+/// Maps a program error to a scenario error naming the event it failed at.
+fn program_error(at: i64) -> impl Fn(anchor_lang::error::Error) -> Box<dyn Error> {
+    move |error| scenario_error(format!("at {}: {}", at, error))
+}
+
+/// The simulated program and its participants, keyed by the names the scenario gives them.
+struct Simulation {
+    program: ReferralProgram,
+    criteria: EligibilityCriteria,
+    names: HashMap<String, Pubkey>,
+    participants: HashMap<Pubkey, Participant>,
+    summary: Summary,
+}
+
+impl Simulation {
+    fn new(settings: &Settings) -> Result<Self, Box<dyn Error>> {
+        if settings.milestones.len() > MAX_MILESTONES {
+            return Err(scenario_error(format!("at most {} milestones are supported", MAX_MILESTONES)));
+        }
+        let mut milestones = [Milestone::default(); MAX_MILESTONES];
+        for (milestone, config) in milestones.iter_mut().zip(&settings.milestones) {
+            *milestone = Milestone { threshold: config.threshold, bonus: config.bonus };
+        }
+        validate_milestones(&milestones).map_err(program_error(0))?;
+
+        let program = ReferralProgram {
+            fixed_reward_amount: settings.fixed_reward_amount,
+            locked_period: settings.locked_period,
+            program_end_time: settings.program_end_time,
+            max_depth: settings.max_depth,
+            referee_reward_amount: settings.referee_reward_amount,
+            reserve_bps: settings.reserve_bps,
+            is_active: true,
+            accepting_referrals: true,
+            ..Default::default()
+        };
+        let criteria = EligibilityCriteria {
+            max_reward_cap: settings.max_reward_cap,
+            revenue_share_percent: settings.revenue_share_percent,
+            program_end_time: settings.program_end_time,
+            milestones,
+            max_referrals_per_window: settings.max_referrals_per_window,
+            referral_window_seconds: settings.referral_window_seconds,
+            trailing_commission_bps: settings.trailing_commission_bps,
+            ..Default::default()
+        };
+        Ok(Self { program, criteria, names: HashMap::new(), participants: HashMap::new(), summary: Summary::default() })
+    }
+
+    fn key(&self, name: &str) -> Result<Pubkey, Box<dyn Error>> {
+        self.names.get(name).copied().ok_or_else(|| scenario_error(format!("unknown participant {:?}", name)))
+    }
+
+    /// Adds a participant joining at `now`, through `referrer` if given
+    fn add_participant(
+        &mut self,
+        name: &str,
+        referrer: Option<&Participant>,
+        now: i64,
+    ) -> Result<Pubkey, Box<dyn Error>> {
+        if self.names.contains_key(name) {
+            return Err(scenario_error(format!("at {}: {:?} has already joined", now, name)));
+        }
+        let key = Pubkey::new_unique();
+        let participant = Participant {
+            owner: key,
+            join_time: now,
+            referrer: referrer.map(|referrer| referrer.owner),
+            referral_depth: referrer.map_or(0, |referrer| referrer.referral_depth.saturating_add(1)),
+            ..Default::default()
+        };
+        self.names.insert(name.to_string(), key);
+        self.participants.insert(key, participant);
+        self.program.total_participants += 1;
+        self.summary.participants += 1;
+        Ok(key)
+    }
+
+    /// Credits `commission` to the referrer of a participant whose `referrer` field is `upline`, as
+    /// `pay_trailing_commission` does
+    fn pay_commission(&mut self, upline: Option<Pubkey>, commission: u64, at: i64) -> Result<(), Box<dyn Error>> {
+        let Some(upline) = upline.filter(|_| commission > 0) else {
+            return Ok(());
+        };
+        let upline = self.participants.get_mut(&upline).ok_or_else(|| scenario_error("missing upline"))?;
+        upline.credit_reward(commission).map_err(program_error(at))?;
+        self.program.total_committed += commission;
+        self.summary.trailing_commissions += commission;
+        Ok(())
+    }
+
+    /// Stops referrals once the uncommitted funds no longer cover a reward, as `check_referral_funding` does
+    fn check_funding(&mut self, now: i64) -> Result<(), Box<dyn Error>> {
+        if self.program.accepting_referrals && !self.program.can_fund_referral().map_err(program_error(now))? {
+            self.program.accepting_referrals = false;
+            self.summary.exhausted_at.get_or_insert(now);
+        }
+        Ok(())
+    }
+
+    /// Resumes referrals once the uncommitted funds cover a reward again, as `restore_referral_funding` does
+    fn restore_funding(&mut self, now: i64) -> Result<(), Box<dyn Error>> {
+        if !self.program.accepting_referrals && self.program.can_fund_referral().map_err(program_error(now))? {
+            self.program.accepting_referrals = true;
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, event: &Event) -> Result<(), Box<dyn Error>> {
+        let now = event.at;
+        match &event.action {
+            Action::Deposit { amount } => {
+                self.program.credit_deposit(*amount).map_err(program_error(now))?;
+                self.restore_funding(now)
+            }
+            Action::Join { participant } => {
+                if self.program.has_ended(now) {
+                    self.summary.rejected += 1;
+                    return Ok(());
+                }
+                self.add_participant(participant, None, now).map(|_| ())
+            }
+            Action::Referral { referrer, referee } => self.refer(referrer, referee, now),
+            Action::Referrals { .. } => unreachable!("expanded before replaying"),
+            Action::Purchase { buyer, amount } => self.purchase(buyer, *amount, now),
+            Action::Claim { participant } => self.claim(participant, now),
+            Action::Crank => {
+                if self.program.accepting_referrals {
+                    self.check_funding(now)?;
+                    if self.program.has_ended(now) {
+                        self.program.accepting_referrals = false;
+                    }
+                } else if !self.program.has_ended(now) {
+                    self.restore_funding(now)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Joins `referee` through `referrer` and applies the credit `referral_credit` works out, in the order
+    /// `join_through_referral` applies it
+    fn refer(&mut self, referrer: &str, referee: &str, now: i64) -> Result<(), Box<dyn Error>> {
+        if self.program.has_ended(now) {
+            self.summary.rejected += 1;
+            return Ok(());
+        }
+        let referrer_key = self.key(referrer)?;
+        let mut referrer = self.participants.remove(&referrer_key).expect("named participants exist");
+        let referee_key = self.add_participant(referee, Some(&referrer), now);
+        let result = referee_key.and_then(|referee_key| self.credit_referral(&mut referrer, referee_key, now));
+        self.participants.insert(referrer_key, referrer);
+        result
+    }
+
+    fn credit_referral(&mut self, referrer: &mut Participant, referee: Pubkey, now: i64) -> Result<(), Box<dyn Error>> {
+        let error = program_error(now);
+        referrer.raw_referrals += 1;
+        self.program.total_referrals_raw += 1;
+        self.summary.referrals += 1;
+
+        let credit = referral_credit(&self.program, &self.criteria, referrer, now).map_err(&error)?;
+        if credit.beyond_max_depth {
+            self.summary.beyond_depth_referrals += 1;
+            return Ok(());
+        }
+        if credit.rate_limited {
+            self.summary.rate_limited_referrals += 1;
+            return Ok(());
+        }
+        if credit.program_underfunded {
+            self.summary.underfunded_referrals += 1;
+            return Ok(());
+        }
+        referrer.record_window_referral(&self.criteria, now);
+        referrer.total_referrals += 1;
+        referrer.record_count += 1;
+        referrer.credit_reward(credit.referrer_share).map_err(&error)?;
+        self.program.total_referrals_credited += 1;
+        self.program.total_committed += credit.reward_amount;
+        self.summary.credited_referrals += 1;
+        self.summary.referral_rewards += credit.reward_amount;
+
+        let milestones = self.criteria.milestones;
+        let reached: Vec<usize> =
+            newly_reached_milestones(&milestones, referrer.total_referrals, referrer.milestones_claimed_bitmap)
+                .filter(|index| credit.milestones_paid & (1 << index) != 0)
+                .collect();
+        for index in reached {
+            referrer.credit_reward(milestones[index].bonus).map_err(&error)?;
+            referrer.milestones_claimed_bitmap |= 1 << index;
+            self.program.total_committed += milestones[index].bonus;
+            self.summary.milestones_reached[index] += 1;
+        }
+        self.summary.milestone_bonuses += credit.milestone_bonus;
+        self.pay_commission(referrer.referrer, credit.milestone_commission, now)?;
+
+        let referee = self.participants.get_mut(&referee).expect("the referee was just added");
+        referee.credit_reward(credit.referee_reward).map_err(&error)?;
+        self.program.total_committed += credit.referee_reward;
+        self.summary.referee_rewards += credit.referee_reward;
+        if credit.referee_commission > 0 {
+            referrer.credit_reward(credit.referee_commission).map_err(&error)?;
+            self.program.total_committed += credit.referee_commission;
+            self.summary.trailing_commissions += credit.referee_commission;
+        }
+        Ok(())
+    }
+
+    /// Credits a purchase by `buyer` to its referrer, as `record_purchase` does
+    fn purchase(&mut self, buyer: &str, amount: u64, now: i64) -> Result<(), Box<dyn Error>> {
+        if self.program.has_ended(now) {
+            self.summary.rejected += 1;
+            return Ok(());
+        }
+        let buyer = &self.participants[&self.key(buyer)?];
+        let referrer_key =
+            buyer.referrer.ok_or_else(|| scenario_error(format!("at {}: the buyer has no referrer", now)))?;
+        let referrer = self.participants.get_mut(&referrer_key).expect("referrers exist");
+        let clamped_before = referrer.cap_clamped;
+
+        let reward =
+            credit_purchase(&mut self.program, &self.criteria, referrer, amount).map_err(program_error(now))?;
+        let commission =
+            trailing_commission_due(&self.program, &self.criteria, referrer, reward).map_err(program_error(now))?;
+        self.summary.purchase_rewards += reward;
+        self.summary.cap_clamped += referrer.cap_clamped - clamped_before;
+        let upline = referrer.referrer;
+        self.pay_commission(upline, commission, now)
+    }
+
+    /// Pays out a participant's pending rewards if every claim gate allows it, as `claim_rewards` does
+    fn claim(&mut self, participant: &str, now: i64) -> Result<(), Box<dyn Error>> {
+        let participant = self.participants.get_mut(&self.key(participant)?).expect("named participants exist");
+        if !claim_eligibility(&self.program, participant, now, ClaimAccounts::default()).is_claimable() {
+            self.summary.blocked_claims += 1;
+            return Ok(());
+        }
+        let vault_balance = self.program.total_available + self.program.reserved_balance;
+        let Ok(paid) = settle_claim(&mut self.program, participant, vault_balance, None, PayoutKind::Sol, |_| Ok(()))
+        else {
+            self.summary.failed_claims += 1;
+            return Ok(());
+        };
+        self.summary.claims += 1;
+        self.summary.total_paid += paid;
+        self.check_funding(now)
+    }
+}
+
+/// Replays `scenario` from a fresh program and returns what it did.
+fn simulate(scenario: &Scenario) -> Result<Summary, Box<dyn Error>> {
+    let mut simulation = Simulation::new(&scenario.settings)?;
+
+    let mut events = Vec::with_capacity(scenario.timeline.len());
+    for event in &scenario.timeline {
+        match &event.action {
+            Action::Referrals { referrer, count, interval } => {
+                events.extend((0..*count).map(|n| Event {
+                    at: event.at.saturating_add(interval.saturating_mul(n as i64)),
+                    action: Action::Referral { referrer: referrer.clone(), referee: format!("{}/{}", referrer, n) },
+                }));
+            }
+            _ => events.push(event.clone()),
+        }
+    }
+    events.sort_by_key(|event| event.at);
+    for event in &events {
+        simulation.apply(event)?;
+    }
+
+    let program = &simulation.program;
+    let summary = &mut simulation.summary;
+    summary.total_deposited = program.total_deposited;
+    summary.total_available = program.total_available;
+    summary.total_committed = program.total_committed;
+    Ok(simulation.summary)
+}
+
+fn print_summary(name: &str, summary: &Summary, milestones: &[MilestoneSettings]) {
+    println!("Scenario: {}", name);
+    println!("Participants: {}", summary.participants);
+    println!(
+        "Referrals: {} ({} credited, {} zero-credit: {} underfunded, {} rate-limited, {} past max depth)",
+        summary.referrals,
+        summary.credited_referrals,
+        summary.zero_credit_referrals(),
+        summary.underfunded_referrals,
+        summary.rate_limited_referrals,
+        summary.beyond_depth_referrals
+    );
+    println!("Rejected after the end time: {}", summary.rejected);
+    println!("Credited: {}", summary.total_credited());
+    println!("  referral rewards: {}", summary.referral_rewards);
+    println!("  referee rewards: {}", summary.referee_rewards);
+    println!("  milestone bonuses: {}", summary.milestone_bonuses);
+    for (index, milestone) in milestones.iter().enumerate() {
+        println!(
+            "    at {} referrals: {} referrers x {}",
+            milestone.threshold, summary.milestones_reached[index], milestone.bonus
+        );
+    }
+    println!("  purchase rewards: {} ({} withheld by caps)", summary.purchase_rewards, summary.cap_clamped);
+    println!("  trailing commissions: {}", summary.trailing_commissions);
+    println!(
+        "Paid: {} in {} claims ({} blocked by claim gates, {} failed for lack of funds)",
+        summary.total_paid, summary.claims, summary.blocked_claims, summary.failed_claims
+    );
+    println!(
+        "Budget: {} deposited, {} available, {} committed",
+        summary.total_deposited, summary.total_available, summary.total_committed
+    );
+    if summary.total_committed > summary.total_available {
+        println!("Overcommitted by: {}", summary.total_committed - summary.total_available);
+    }
+    match summary.exhausted_at {
+        Some(at) => println!("Budget exhausted at: {}", at),
+        None => println!("Budget exhausted at: never"),
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let path = std::env::args().nth(1).ok_or_else(|| scenario_error("usage: simulate <scenario.json>"))?;
+    let scenario: Scenario = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+    let summary = simulate(&scenario)?;
+    print_summary(&scenario.name, &summary, &scenario.settings.milestones);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(json: &str) -> Scenario {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_small_scenario_matches_hand_computed_summary() {
+        let summary = simulate(&load(include_str!("scenarios/small.json"))).unwrap();
+        assert_eq!(
+            summary,
+            Summary {
+                participants: 5,
+                referrals: 4,
+                credited_referrals: 3,
+                underfunded_referrals: 1,
+                referral_rewards: 3_000_000,
+                referee_rewards: 600_000,
+                milestone_bonuses: 500_000,
+                milestones_reached: [1, 0, 0, 0],
+                purchase_rewards: 460_000,
+                trailing_commissions: 60_000,
+                cap_clamped: 540_000,
+                claims: 2,
+                blocked_claims: 1,
+                total_paid: 4_220_000,
+                total_deposited: 6_000_000,
+                total_available: 1_780_000,
+                total_committed: 400_000,
+                exhausted_at: Some(120),
+                ..Default::default()
+            }
+        );
+        assert_eq!(summary.total_credited(), summary.total_paid + summary.total_committed);
+    }
+
+    #[test]
+    fn test_budget_scenario_runs() {
+        let summary = simulate(&load(include_str!("scenarios/budget_500_sol.json"))).unwrap();
+        assert_eq!(summary.referrals, 10_000);
+        assert_eq!(summary.total_credited(), summary.total_paid + summary.total_committed);
+        assert!(summary.total_paid <= summary.total_deposited);
+    }
+
+    #[test]
+    fn test_unknown_participant_is_rejected() {
+        let scenario = load(r#"{"settings": {}, "timeline": [{"at": 0, "kind": "claim", "participant": "nobody"}]}"#);
+        assert!(simulate(&scenario).is_err());
+    }
+}
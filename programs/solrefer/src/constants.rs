@@ -1,3 +1,5 @@
+use anchor_lang::prelude::{pubkey, Pubkey};
+
 /// The seed used for the referral program's Pubkey.
 pub const REFERRAL_PROGRAM_SEED: &[u8] = b"referral_program";
 /// The minimum reward amount for the referral program.
@@ -13,3 +15,387 @@ pub const MIN_LOCKED_PERIOD: i64 = 86400;
 
 /// The maximum locked period for rewards in seconds (365 days).
 pub const MAX_LOCKED_PERIOD: i64 = 31536000;
+
+/// The maximum duration of a referral program in seconds (5 years), measured from the time of validation.
+pub const MAX_PROGRAM_DURATION: i64 = 157680000;
+
+/// The lowest minimum locked period a network config may set, in seconds (1 minute).
+pub const MIN_LOCKED_PERIOD_FLOOR: i64 = 60;
+
+/// The seed used for deriving the cluster-wide network config PDA.
+pub const NETWORK_CONFIG_SEED: &[u8] = b"network_config";
+
+/// The Metaplex Token Metadata program, owner of the NFT metadata accounts collection gates read.
+pub const TOKEN_METADATA_PROGRAM_ID: Pubkey = pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+/// The seed the Token Metadata program derives metadata PDAs with.
+pub const METADATA_SEED: &[u8] = b"metadata";
+
+/// The seed used for deriving referee receipt PDAs.
+pub const REFEREE_RECEIPT_SEED: &[u8] = b"referee";
+
+/// Reward amounts are expressed in raw units (lamports or base token units).
+pub const REWARD_DENOMINATION_RAW: u8 = 0;
+
+/// Reward amounts are expressed in US cents and converted using the stable mint's decimals.
+pub const REWARD_DENOMINATION_USD_CENTS: u8 = 1;
+
+/// Decimals of SOL, which the display totals of SOL programs are converted with.
+pub const SOL_DECIMALS: u8 = 9;
+
+/// The seed used for deriving the event queue PDA.
+pub const EVENT_QUEUE_SEED: &[u8] = b"events";
+
+/// The maximum share of a referrer's rewards that can be split to another participant, in basis points (50%).
+pub const MAX_PAYOUT_SPLIT_BPS: u16 = 5_000;
+
+/// The number of milestone slots in the eligibility criteria.
+pub const MAX_MILESTONES: usize = 4;
+
+/// The number of runway alert thresholds a program can set.
+pub const RUNWAY_ALERT_SLOTS: usize = 3;
+
+/// The number of embargoed region slots in the eligibility criteria.
+pub const MAX_EMBARGOED_REGIONS: usize = 8;
+
+/// The seed used for deriving invite PDAs.
+pub const INVITE_SEED: &[u8] = b"invite";
+
+/// The maximum number of invites minted by a single `mint_invites` call.
+pub const MAX_INVITES_PER_MINT: u8 = 8;
+
+/// The seed used for deriving the protocol-wide fee config PDA.
+pub const FEE_CONFIG_SEED: &[u8] = b"fee_config";
+
+/// The seed used for deriving the per-authority metadata PDA.
+pub const AUTHORITY_META_SEED: &[u8] = b"authority_meta";
+
+/// The number of authorities the fee config can exempt from the creation fee.
+pub const MAX_FEE_EXEMPT_AUTHORITIES: usize = 4;
+
+/// The seed used for deriving the contest PDA.
+pub const CONTEST_SEED: &[u8] = b"contest";
+
+/// The seed used for deriving the contest prize escrow PDA.
+pub const CONTEST_ESCROW_SEED: &[u8] = b"contest_escrow";
+
+/// The number of ranked prizes a contest can award.
+pub const MAX_CONTEST_PRIZES: usize = 5;
+
+/// The longest dispute window between finalizing a contest and its prizes becoming claimable (30 days).
+pub const MAX_CONTEST_DISPUTE_WINDOW: i64 = 2592000;
+
+/// The length in bytes of a referee's source tag; shorter tags are zero-padded.
+pub const SOURCE_TAG_LEN: usize = 16;
+
+/// The length in bytes of the note the authority keeps on a participant; shorter notes are zero-padded.
+pub const PARTICIPANT_NOTE_LEN: usize = 64;
+
+/// The length in bytes of a referral link slug, a base58 owner key; shorter slugs are zero-padded.
+pub const LINK_SLUG_LEN: usize = 44;
+
+/// The length in bytes of a referrer's landing parameters; shorter parameters are zero-padded.
+pub const LINK_PARAMS_LEN: usize = 32;
+
+/// The version of the referral payloads `linkcodec` encodes.
+pub const LINK_PAYLOAD_VERSION: u8 = 1;
+
+/// The longest campaign code a referral payload can carry, keeping the payload within 80 bytes.
+pub const MAX_LINK_CODE_LEN: usize = 12;
+
+/// Participants store their full referral link, URL prefix included, in `referral_link`.
+pub const LINK_FORMAT_LEGACY: u8 = 0;
+
+/// Participants store only the slug of their referral link; `referral_url` joins it to the program's base URL.
+pub const LINK_FORMAT_SLUG: u8 = 1;
+
+/// The number of distinct source tags counted per program before new tags fall into the "other" bucket.
+pub const MAX_SOURCE_TAG_SLOTS: usize = 8;
+
+/// The user whose action creates an auxiliary account, such as a referee receipt, pays its rent.
+pub const RENT_PAYER_USER: u8 = 0;
+
+/// The program's sponsor vault pays the rent of auxiliary accounts on the user's behalf.
+pub const RENT_PAYER_SPONSOR: u8 = 1;
+
+/// Joins emit each of their events as it occurs.
+pub const EVENT_MODE_VERBOSE: u8 = 0;
+
+/// Joins pack their events into one `JoinDigest`, emitted at the end of the instruction.
+pub const EVENT_MODE_DIGEST: u8 = 1;
+
+/// The seed used for deriving the sponsor vault PDA, which holds the SOL that pays sponsored rent.
+pub const SPONSOR_VAULT_SEED: &[u8] = b"sponsor_vault";
+
+/// The grace window between requesting a program's closure and being able to finalize it (72 hours).
+pub const CLOSURE_GRACE_PERIOD: i64 = 259200;
+
+/// The most purchases `record_purchases_batch` records in one transaction.
+pub const MAX_PURCHASE_BATCH: usize = 10;
+
+/// The longest window a referrer has to contest a clawback (30 days).
+pub const MAX_CLAWBACK_DISPUTE_WINDOW: i64 = 2592000;
+
+/// How long the authority has to resolve a contested clawback before anyone can resolve it for the referrer
+/// (14 days).
+pub const CLAWBACK_RESOLUTION_TIMEOUT: i64 = 1209600;
+
+/// How long a program's authority must go without acting before anyone can declare the program abandoned, when
+/// its settings leave the dormancy period at 0 (365 days).
+pub const DEFAULT_DORMANCY_PERIOD: i64 = 31536000;
+
+/// The shortest dormancy period a program can set (90 days).
+pub const MIN_DORMANCY_PERIOD: i64 = 7776000;
+
+/// The longest window after joining directly in which a participant can still name its referrer (30 days).
+pub const MAX_ATTRIBUTION_GRACE: i64 = 2592000;
+
+/// The longest a program can make its authority wait before reclaiming an untouched seeded participant (365 days).
+pub const MAX_SEED_RECLAIM_PERIOD: i64 = 31536000;
+
+/// The seed used for deriving a program's queued withdrawal PDA.
+pub const WITHDRAWAL_REQUEST_SEED: &[u8] = b"withdrawal";
+
+/// How long a withdrawal above a guarded program's delay threshold waits before it can execute (24 hours).
+pub const WITHDRAWAL_DELAY: i64 = 86400;
+
+/// How many addresses besides the authority a program can allow its withdrawals to pay out to.
+pub const MAX_WITHDRAWAL_DESTINATIONS: usize = 4;
+
+/// The length of the idempotency keys authority mutations can carry.
+pub const IDEMPOTENCY_KEY_LEN: usize = 16;
+
+/// How many of its latest idempotency keys a program remembers.
+pub const IDEMPOTENCY_LOG_LEN: usize = 8;
+
+/// The largest share of each deposit a program can ring-fence as an insurance reserve, in basis points (20%).
+pub const MAX_RESERVE_BPS: u64 = 2_000;
+
+/// The largest trailing commission a referrer can earn on its referees' credits, in basis points (20%).
+pub const MAX_TRAILING_COMMISSION_BPS: u64 = 2_000;
+
+/// The largest bridge credit a program can pay, in basis points of its base reward (100%).
+pub const MAX_BRIDGE_BPS: u64 = 10_000;
+
+/// The seed used for deriving a referrer's boost escrow PDA.
+pub const BOOST_SEED: &[u8] = b"boost";
+
+/// The seed used for deriving a sponsor's match offer PDA.
+pub const MATCH_OFFER_SEED: &[u8] = b"match";
+
+/// The largest match a sponsor can offer, in basis points of the matched reward (1:1).
+pub const MAX_MATCH_BPS: u16 = 10_000;
+
+/// The seed used for deriving a governed program's pending settings change PDA.
+pub const PENDING_CHANGE_SEED: &[u8] = b"pending_change";
+
+/// The seed used for deriving a participant's vote on a pending settings change.
+pub const CHANGE_VOTE_SEED: &[u8] = b"change_vote";
+
+/// How long participants can vote on a pending settings change (7 days).
+pub const CHANGE_VOTING_PERIOD: i64 = 604800;
+
+/// The longest a program can lock its settings for at creation, from its creation time (90 days).
+pub const MAX_SETTINGS_LOCK: i64 = 7776000;
+
+/// The seed used for deriving a participant's claim splitter PDA.
+pub const SPLITTER_SEED: &[u8] = b"splitter";
+
+/// The most destinations a claim splitter can pay.
+pub const MAX_SPLITTER_ENTRIES: usize = 5;
+
+/// The most auxiliary accounts a single `cleanup` call can close.
+pub const MAX_CLEANUP_BATCH: usize = 10;
+
+/// The share of the reclaimed rent `cleanup` pays its caller, in basis points, when a program's settings leave the
+/// cleanup bounty at 0 (10%).
+pub const DEFAULT_CLEANUP_BOUNTY_BPS: u16 = 1_000;
+
+/// The largest cleanup bounty a program can set, in basis points of the reclaimed rent.
+pub const MAX_CLEANUP_BOUNTY_BPS: u16 = 10_000;
+
+/// The seed used for deriving the final report PDA a closed program leaves behind.
+pub const FINAL_REPORT_SEED: &[u8] = b"final_report";
+
+/// The seed used for deriving the PDA recording one change to a program's settings.
+pub const SETTINGS_CHANGE_SEED: &[u8] = b"settings_change";
+
+// Maintenance actions reported in the `actions_bitmask` of `MaintenancePerformed`.
+
+/// The program stopped accepting referrals because its uncommitted funds no longer cover a referral reward.
+pub const MAINTENANCE_UNDERFUNDED: u8 = 1 << 0;
+/// The program accepts referrals again because its uncommitted funds cover a referral reward.
+pub const MAINTENANCE_REFUNDED: u8 = 1 << 1;
+/// The program stopped accepting referrals because its end time passed.
+pub const MAINTENANCE_EXPIRED: u8 = 1 << 2;
+/// Runway alerts were emitted for thresholds the available funds had fallen below.
+pub const MAINTENANCE_RUNWAY_ALERTS: u8 = 1 << 3;
+/// A closure past its grace period was finalized.
+pub const MAINTENANCE_CLOSURE_FINALIZED: u8 = 1 << 4;
+
+// Invariants reported violated in the bitmask `audit_program` returns.
+
+/// The vault holds less than the program's available funds and reserve, beyond `AUDIT_DUST_TOLERANCE`.
+pub const AUDIT_VAULT_INSOLVENT: u8 = 1 << 0;
+/// The available funds, reserve and distributed rewards add up to more than was ever deposited.
+pub const AUDIT_FUNDS_UNBALANCED: u8 = 1 << 1;
+/// The referral counters disagree: more credited referrals than raw ones, or source tag buckets that do not add
+/// up to the raw count.
+pub const AUDIT_REFERRAL_COUNTS: u8 = 1 << 2;
+/// The program's cached end time differs from its eligibility criteria's.
+pub const AUDIT_END_TIME_STALE: u8 = 1 << 3;
+
+/// How far, in lamports or token units, a vault may fall short of the funds recorded against it before
+/// `audit_program` reports it insolvent.
+pub const AUDIT_DUST_TOLERANCE: u64 = 10;
+
+/// The most referee receipts `recount_referrals` checks in one transaction.
+pub const MAX_RECOUNT_BATCH: usize = 20;
+
+/// The most referee receipts `get_participant_history` takes in one call.
+pub const MAX_HISTORY_BATCH: usize = 20;
+
+// Feature bits reported by `get_program_version`. Adding a feature takes a new bit here and its inclusion in
+// `SUPPORTED_FEATURES`; bits are never reused.
+
+/// Token-denominated programs with a token vault and token deposits.
+pub const FEATURE_TOKEN_PROGRAMS: u64 = 1 << 0;
+/// Payout splits routing part of a referrer's rewards to another participant.
+pub const FEATURE_PAYOUT_SPLITS: u64 = 1 << 1;
+/// Invite-only programs and invite minting.
+pub const FEATURE_INVITES: u64 = 1 << 2;
+/// Moving a participant to a new wallet.
+pub const FEATURE_OWNER_ROTATION: u64 = 1 << 3;
+/// Referral contests with ranked prizes.
+pub const FEATURE_CONTESTS: u64 = 1 << 4;
+/// Purchase attribution and revenue share, singly or in batches.
+pub const FEATURE_PURCHASES: u64 = 1 << 5;
+/// Insurance reserves ring-fenced from deposits.
+pub const FEATURE_RESERVE: u64 = 1 << 6;
+/// Referrer-funded referee boosts.
+pub const FEATURE_REFEREE_BOOSTS: u64 = 1 << 7;
+/// The per-program event queue.
+pub const FEATURE_EVENT_QUEUE: u64 = 1 << 8;
+/// Closing programs after a grace period.
+pub const FEATURE_PROGRAM_CLOSURE: u64 = 1 << 9;
+/// On-chain setup-state tracking.
+pub const FEATURE_SETUP_STATE: u64 = 1 << 10;
+/// Per-participant reward statements.
+pub const FEATURE_REWARD_STATEMENTS: u64 = 1 << 11;
+/// Per-referrer referral rate limits.
+pub const FEATURE_RATE_LIMITS: u64 = 1 << 12;
+/// The test-only instructions are enabled; never set on deployed builds.
+pub const FEATURE_TEST_UTILS: u64 = 1 << 13;
+/// Cluster-wide overrides of the validation limits.
+pub const FEATURE_NETWORK_CONFIG: u64 = 1 << 14;
+/// Gating referrers on holding an NFT of a verified collection.
+pub const FEATURE_COLLECTION_GATE: u64 = 1 << 15;
+/// Permissionless correction of referral counts from the referee receipts.
+pub const FEATURE_REFERRAL_RECOUNT: u64 = 1 << 16;
+/// Transfers of pending rewards between participants.
+pub const FEATURE_PENDING_TRANSFERS: u64 = 1 << 17;
+/// Read-only previews of what a referral would credit.
+pub const FEATURE_REFERRAL_PREVIEW: u64 = 1 << 18;
+/// Authority-written notes on participants.
+pub const FEATURE_PARTICIPANT_NOTES: u64 = 1 << 19;
+/// Clawbacks referrers can contest before they are final.
+pub const FEATURE_CLAWBACK_DISPUTES: u64 = 1 << 20;
+/// Guardian keys that can freeze a program and cancel its delayed withdrawals.
+pub const FEATURE_GUARDIAN: u64 = 1 << 21;
+/// Claiming rewards from a token program's vault.
+pub const FEATURE_TOKEN_CLAIMS: u64 = 1 << 22;
+/// Programs paying the rent of auxiliary accounts from a sponsor vault.
+pub const FEATURE_SPONSORED_RENT: u64 = 1 << 23;
+/// Read-only exports of a participant's history from its referee receipts.
+pub const FEATURE_PARTICIPANT_HISTORY: u64 = 1 << 24;
+/// Referral links signed by a program's link signer and verified at join time.
+pub const FEATURE_LINK_PROOFS: u64 = 1 << 25;
+/// Permissionless abandonment of programs whose authority stopped acting.
+pub const FEATURE_ABANDONMENT: u64 = 1 << 26;
+/// Region embargoes enforced through a region attestor's per-wallet attestations.
+pub const FEATURE_REGION_EMBARGO: u64 = 1 << 27;
+/// Bridge credits for referrers whose referees in a bridge source program join another program directly.
+pub const FEATURE_REFERRAL_BRIDGE: u64 = 1 << 28;
+/// The read-only `audit_program` instruction checking a program's accounting invariants.
+pub const FEATURE_AUDIT: u64 = 1 << 29;
+/// Landing parameters referrers set on their participant account, previewed and snapshotted at join.
+pub const FEATURE_LINK_PARAMS: u64 = 1 << 30;
+/// Sponsor match offers paying referrers a share of their referral rewards on top.
+pub const FEATURE_REWARD_MATCHING: u64 = 1 << 31;
+/// Permissionless cleanup of spent auxiliary accounts for a share of their rent.
+pub const FEATURE_CLEANUP_BOUNTY: u64 = 1 << 32;
+/// SOL rewards claimed as wrapped SOL into the participant's token account.
+pub const FEATURE_WRAPPED_CLAIMS: u64 = 1 << 33;
+/// Participant approval of reward cuts once a program opts into governance.
+pub const FEATURE_GOVERNANCE: u64 = 1 << 34;
+/// Claims split across up to five destinations in fixed proportions.
+pub const FEATURE_CLAIM_SPLITTER: u64 = 1 << 35;
+/// Settings time-locked by the creator for a launch window.
+pub const FEATURE_SETTINGS_TIMELOCK: u64 = 1 << 36;
+/// Joins through the compact binary referral payloads of QR codes.
+pub const FEATURE_LINK_PAYLOADS: u64 = 1 << 37;
+/// Participants leaving a program, held back while referees still reference them.
+pub const FEATURE_LEAVE_PROGRAM: u64 = 1 << 38;
+/// Joins reporting their events in a single `JoinDigest` for programs in `EVENT_MODE_DIGEST`.
+pub const FEATURE_JOIN_DIGEST: u64 = 1 << 39;
+/// Participants that joined directly naming their referrer within the program's attribution grace window.
+pub const FEATURE_LATE_ATTRIBUTION: u64 = 1 << 40;
+/// The flat referral reward stepping down through the tier and base rewards as a program's uncommitted funds run low.
+pub const FEATURE_AUTO_DOWNGRADE: u64 = 1 << 41;
+/// Participants seeded by the authority for partner wallets, claiming once their owner activates them.
+pub const FEATURE_SEEDED_PARTICIPANTS: u64 = 1 << 42;
+/// Claims of locked rewards with `early_claim_rewards` or `early_claim_token_rewards`, forfeiting the program's
+/// early redemption fee.
+pub const FEATURE_EARLY_REDEMPTION: u64 = 1 << 43;
+/// Pausing and unpausing a live program with `set_program_status`, leaving earned rewards claimable.
+pub const FEATURE_PROGRAM_STATUS: u64 = 1 << 44;
+/// Several programs per authority, told apart by the `program_index` in their seeds.
+pub const FEATURE_PROGRAM_INDEX: u64 = 1 << 45;
+
+/// Every feature this build supports.
+pub const SUPPORTED_FEATURES: u64 = FEATURE_TOKEN_PROGRAMS
+    | FEATURE_PAYOUT_SPLITS
+    | FEATURE_INVITES
+    | FEATURE_OWNER_ROTATION
+    | FEATURE_CONTESTS
+    | FEATURE_PURCHASES
+    | FEATURE_RESERVE
+    | FEATURE_REFEREE_BOOSTS
+    | FEATURE_EVENT_QUEUE
+    | FEATURE_PROGRAM_CLOSURE
+    | FEATURE_SETUP_STATE
+    | FEATURE_REWARD_STATEMENTS
+    | FEATURE_RATE_LIMITS
+    | FEATURE_NETWORK_CONFIG
+    | FEATURE_COLLECTION_GATE
+    | FEATURE_REFERRAL_RECOUNT
+    | FEATURE_PENDING_TRANSFERS
+    | FEATURE_REFERRAL_PREVIEW
+    | FEATURE_PARTICIPANT_NOTES
+    | FEATURE_CLAWBACK_DISPUTES
+    | FEATURE_GUARDIAN
+    | FEATURE_TOKEN_CLAIMS
+    | FEATURE_SPONSORED_RENT
+    | FEATURE_PARTICIPANT_HISTORY
+    | FEATURE_LINK_PROOFS
+    | FEATURE_ABANDONMENT
+    | FEATURE_REGION_EMBARGO
+    | FEATURE_REFERRAL_BRIDGE
+    | FEATURE_AUDIT
+    | FEATURE_LINK_PARAMS
+    | FEATURE_REWARD_MATCHING
+    | FEATURE_CLEANUP_BOUNTY
+    | FEATURE_WRAPPED_CLAIMS
+    | FEATURE_GOVERNANCE
+    | FEATURE_CLAIM_SPLITTER
+    | FEATURE_SETTINGS_TIMELOCK
+    | FEATURE_LINK_PAYLOADS
+    | FEATURE_LEAVE_PROGRAM
+    | FEATURE_JOIN_DIGEST
+    | FEATURE_LATE_ATTRIBUTION
+    | FEATURE_AUTO_DOWNGRADE
+    | FEATURE_SEEDED_PARTICIPANTS
+    | FEATURE_EARLY_REDEMPTION
+    | FEATURE_PROGRAM_STATUS
+    | FEATURE_PROGRAM_INDEX
+    | if cfg!(feature = "test-utils") { FEATURE_TEST_UTILS } else { 0 };
@@ -62,4 +62,280 @@ pub enum ReferralError {
     InsufficientFunds,
     #[msg("Lock period has not elapsed yet")]
     LockPeriodNotElapsed,
+    #[msg("Program end time exceeds MAX_PROGRAM_DURATION from now")]
+    ProgramDurationTooLong,
+    #[msg("Invalid reward denomination")]
+    InvalidRewardDenomination,
+    #[msg("Token mint has too few decimals for USD cent rewards")]
+    InsufficientMintDecimals,
+    #[msg("Program setup is incomplete")]
+    ProgramSetupIncomplete,
+    #[msg("Payout split exceeds MAX_PAYOUT_SPLIT_BPS")]
+    InvalidPayoutSplit,
+    #[msg("Payout split recipient must be another participant of the program")]
+    InvalidSplitRecipient,
+    #[msg("Participant cannot be rotated to this owner")]
+    InvalidRotation,
+    #[msg("Participant account has been rotated to a new owner")]
+    ParticipantRotated,
+    #[msg("Milestone thresholds must be strictly ascending")]
+    InvalidMilestones,
+    #[msg("Referral has already been clawed back")]
+    AlreadyClawedBack,
+    #[msg("Program is invite-only and no invite was provided")]
+    InviteRequired,
+    #[msg("Invite has already been claimed")]
+    InviteAlreadyClaimed,
+    #[msg("Invite account does not belong to this program")]
+    InvalidInviteAccount,
+    #[msg("Invite count must be between 1 and MAX_INVITES_PER_MINT")]
+    InvalidInviteCount,
+    #[msg("Test-only instructions are disabled in this build")]
+    TestUtilsDisabled,
+    #[msg("The protocol creation fee must be paid to the fee config treasury")]
+    CreationFeeRequired,
+    #[msg("Authority has reached the maximum number of referral programs")]
+    TooManyPrograms,
+    #[msg("Contest prizes must be non-zero up to the first unused rank and the dispute window within bounds")]
+    InvalidContestConfig,
+    #[msg("The contest cannot be finalized before the program ends")]
+    ContestNotEnded,
+    #[msg("The contest has already been settled")]
+    ContestAlreadySettled,
+    #[msg("Contest winners must be distinct participants in non-increasing order of referrals")]
+    InvalidContestRanking,
+    #[msg("The contest has not been finalized")]
+    ContestNotFinalized,
+    #[msg("Contest prizes are not claimable until the dispute window closes")]
+    ContestDisputeWindowOpen,
+    #[msg("Signer is not the winner of this contest rank")]
+    InvalidContestWinner,
+    #[msg("This contest prize has already been claimed")]
+    PrizeAlreadyClaimed,
+    #[msg("Purchase amount must be greater than zero")]
+    InvalidPurchaseAmount,
+    #[msg("The referral program must be inactive or ended")]
+    ProgramStillActive,
+    #[msg("Credited rewards are still outstanding")]
+    OutstandingRewards,
+    #[msg("The token vault still holds tokens; provide a destination to sweep them")]
+    TokenVaultNotEmpty,
+    #[msg("Source tags must be ASCII")]
+    InvalidSourceTag,
+    #[msg("The referral program has ended")]
+    ProgramEnded,
+    #[msg("The token vault has not been initialized")]
+    TokenVaultNotInitialized,
+    #[msg("The referral program is pending closure")]
+    ProgramClosing,
+    #[msg("The closure grace period has not elapsed")]
+    ClosureGracePeriodActive,
+    #[msg("No closure has been requested")]
+    NoClosurePending,
+    #[msg("The token vault must be closed first")]
+    TokenVaultStillOpen,
+    #[msg("The accepted terms do not match the program's current terms")]
+    TermsMismatch,
+    #[msg("A purchase batch must hold between 1 and 10 purchases of buyers passed with it")]
+    InvalidPurchaseBatch,
+    #[msg("The deposit must equal the sum of the batch's purchase amounts")]
+    PurchaseDepositMismatch,
+    #[msg("The reserve share cannot exceed 20% of deposits")]
+    InvalidReserveBps,
+    #[msg("The reserve cannot be released before the program ends")]
+    ReserveLocked,
+    #[msg("Boost amounts must be greater than zero")]
+    InvalidBoostAmount,
+    #[msg("The boost escrow does not belong to this referrer")]
+    InvalidBoostEscrow,
+    #[msg("The boost escrow holds less than the requested amount")]
+    InsufficientBoostBalance,
+    #[msg("The program's criteria have not been set; call update_program_settings first")]
+    CriteriaNotSet,
+    #[msg("The referrer has reached its referral limit for the current window")]
+    ReferralRateLimited,
+    #[msg("A referral rate limit needs both a per-window cap and a positive window")]
+    InvalidRateLimit,
+    #[msg("A claim moved lamports other than its payout from the vault to the claimant")]
+    ClaimGuardViolation,
+    #[msg("Network config limit is outside its absolute bound")]
+    InvalidNetworkConfig,
+    #[msg("The program requires an NFT of its collection but none was supplied")]
+    CollectionNftRequired,
+    #[msg("The NFT metadata account is not the Metaplex metadata of the supplied NFT")]
+    InvalidCollectionMetadata,
+    #[msg("The NFT is not a verified member of the program's collection")]
+    CollectionNotVerified,
+    #[msg("The NFT is not held by the participant")]
+    CollectionNftNotHeld,
+    #[msg("A collection gate on credits requires a collection")]
+    InvalidCollectionGate,
+    #[msg("A recount batch holds at most 20 distinct referee receipts")]
+    InvalidRecountBatch,
+    #[msg("The referee receipt does not record a referral counted for this participant")]
+    InvalidReferralRecord,
+    #[msg("Pending reward transfers are disabled for this program")]
+    TransfersDisabled,
+    #[msg("Transfer amount must be positive and at most the pending rewards")]
+    InvalidTransferAmount,
+    #[msg("The destination must be another active participant of the same program")]
+    InvalidTransferDestination,
+    #[msg("The transfer would take the destination's rewards past the program's reward cap")]
+    RewardCapExceeded,
+    #[msg("Participant notes are at most 64 bytes")]
+    InvalidParticipantNote,
+    #[msg("Clawback dispute window must be between 0 and 30 days")]
+    InvalidDisputeWindow,
+    #[msg("The clawback is not at the dispute step this instruction handles")]
+    InvalidClawbackState,
+    #[msg("The clawback's deadline for this step has passed")]
+    ClawbackDeadlinePassed,
+    #[msg("The clawback's deadline for this step has not passed yet")]
+    ClawbackDeadlineNotReached,
+    #[msg("The signer is not the program's guardian, or the guardian is invalid")]
+    InvalidGuardian,
+    #[msg("The program is frozen by its guardian")]
+    ProgramFrozen,
+    #[msg("Withdrawals above the delay threshold must be queued")]
+    WithdrawalDelayRequired,
+    #[msg("The queued withdrawal's delay has not elapsed")]
+    WithdrawalNotReady,
+    #[msg("Rent payer mode must be 0 (user pays) or 1 (sponsor vault pays)")]
+    InvalidRentPayerMode,
+    #[msg("The program pays rent from its sponsor vault, which was not supplied or cannot cover it")]
+    SponsorVaultUnderfunded,
+    #[msg("Minimum account age cannot be negative")]
+    InvalidJoinRequirements,
+    #[msg("The joining wallet holds less than the program's minimum balance")]
+    JoinerBalanceTooLow,
+    #[msg("The joining wallet has not been a participant for the program's minimum account age")]
+    JoinerAccountTooNew,
+    #[msg("The account age reference is not a participant account of the joining wallet")]
+    InvalidAgeReference,
+    #[msg("Runway alert thresholds must be percentages of at most 100")]
+    InvalidAlertThresholds,
+    #[msg("A token requirement must ask for a non-zero amount")]
+    InvalidTokenRequirement,
+    #[msg("The referrer does not hold the program's required token amount")]
+    ReferrerRequirementNotMet,
+    #[msg("The referee does not hold the program's required token amount")]
+    RefereeRequirementNotMet,
+    #[msg("The program only accepts claims invoked directly by the transaction, not through another program")]
+    CpiClaimNotAllowed,
+    #[msg("A final report already exists for this program address")]
+    FinalReportExists,
+    #[msg("Trailing commission cannot exceed the maximum commission")]
+    InvalidTrailingCommission,
+    #[msg("A trailing commission is due but the earner's referrer account was not supplied")]
+    ReferrerAccountRequired,
+    #[msg("Withdrawals can only pay out to the authority or an address on the program's allow-list")]
+    DestinationNotAllowed,
+    #[msg("A history batch holds at most 20 distinct referee receipts")]
+    InvalidHistoryBatch,
+    #[msg("The idempotency key was already used by one of the program's recent authority mutations")]
+    DuplicateIdempotencyKey,
+    #[msg("An idempotency key cannot be all zeros")]
+    InvalidIdempotencyKey,
+    #[msg("The program only accepts joins through a referral link signed by its link signer")]
+    LinkProofRequired,
+    #[msg("The referral link has expired")]
+    LinkProofExpired,
+    #[msg("The transaction does not verify the link signer's signature of the referral link")]
+    InvalidLinkProof,
+    #[msg("Dormancy period must be 0 for the default or at least the minimum dormancy period")]
+    InvalidDormancyPeriod,
+    #[msg("The program was declared abandoned and its authority can no longer act on it")]
+    ProgramAbandoned,
+    #[msg("The program's authority acted within its dormancy period")]
+    ProgramNotDormant,
+    #[msg("The program requires a region attestation of the wallet from its region attestor")]
+    RegionAttestationRequired,
+    #[msg("Region attestation must be issued by the program's region attestor for this wallet")]
+    InvalidRegionAttestation,
+    #[msg("The wallet's attested region is embargoed by the program")]
+    RegionEmbargoed,
+    #[msg("Bridge credit must be at most 100% of the base reward and needs a bridge source program")]
+    InvalidBridge,
+    #[msg("Bridge accounts must be the user's participant in the bridge source and its referrer's in this program")]
+    InvalidBridgeAccounts,
+    #[msg("Link parameters must be at most 32 bytes")]
+    InvalidLinkParams,
+    #[msg("Match must be between 1 and 10_000 basis points")]
+    InvalidMatchBps,
+    #[msg("Match offer must belong to the program and be passed with the referrer's wallet")]
+    InvalidMatchOffer,
+    #[msg("Cleanup bounty must be at most 10_000 basis points")]
+    InvalidCleanupBounty,
+    #[msg("Cleanup batch must hold 1 to 10 distinct accounts, each followed by its rent payer")]
+    InvalidCleanupBatch,
+    #[msg("Account is not a spent auxiliary account of the program")]
+    AccountNotClosable,
+    #[msg("Governance is already enabled for this program")]
+    GovernanceAlreadyEnabled,
+    #[msg("Approval threshold must be between 1 and 99 percent")]
+    InvalidApprovalThreshold,
+    #[msg("Cutting rewards or shortening the program needs participant approval through a pending change")]
+    PendingChangeRequired,
+    #[msg("A settings change is already awaiting participant approval")]
+    ChangeAlreadyPending,
+    #[msg("Voting on this change has closed")]
+    VotingClosed,
+    #[msg("Only participants with credited referrals can vote")]
+    NoVotingWeight,
+    #[msg("The change has not been approved by participants")]
+    ChangeNotApproved,
+    #[msg("The change can still be approved")]
+    ChangeNotVoid,
+    #[msg("Splitter must pay 1 to 5 distinct destinations whose shares sum to exactly 10_000 basis points")]
+    InvalidClaimSplitter,
+    #[msg("Claim must pass the participant's splitter and its destinations in order")]
+    ClaimSplitterMismatch,
+    #[msg("Settings lock must end after creation, by the program end time and within the maximum lock")]
+    InvalidSettingsLock,
+    #[msg("The program's settings are time-locked by its creator")]
+    SettingsTimelocked,
+    #[msg("Referral payload is malformed or of an unsupported version")]
+    InvalidReferralPayload,
+    #[msg("Referral payload failed its checksum")]
+    ReferralPayloadChecksum,
+    #[msg("Participant still has active referees; leave with force to keep the account as a tombstone")]
+    ActiveRefereesRemain,
+    #[msg("Claim or transfer the pending rewards before leaving the program")]
+    PendingRewardsRemain,
+    #[msg("The participant has left the program")]
+    ParticipantLeft,
+    #[msg("Event mode must be one of the EVENT_MODE_* constants")]
+    InvalidEventMode,
+    #[msg("Attribution grace period must be between 0 and MAX_ATTRIBUTION_GRACE")]
+    InvalidAttributionGrace,
+    #[msg("The attribution grace window after joining has closed")]
+    AttributionWindowClosed,
+    #[msg("The participant already has a referrer or has earned rewards, so its referrer cannot be set")]
+    AttributionNotAllowed,
+    #[msg("Auto downgrade needs a min_remaining_credits of at least 1")]
+    InvalidAutoDowngrade,
+    #[msg("Seed reclaim period must be between 0 and MAX_SEED_RECLAIM_PERIOD")]
+    InvalidSeedReclaimPeriod,
+    #[msg("The participant is not a seeded participant awaiting activation")]
+    NotAwaitingActivation,
+    #[msg("A seeded participant must be activated by its owner before claiming")]
+    ParticipantNotActivated,
+    #[msg("The seeded participant has been used or its reclaim period has not passed")]
+    SeedNotReclaimable,
+    #[msg("The program does not allow early redemption of locked rewards")]
+    EarlyRedemptionDisabled,
+    #[msg("A new program's settings must repeat its fixed reward amount and end time")]
+    CreationSettingsMismatch,
+    #[msg("The program's uncommitted funds cannot cover the referral's rewards")]
+    ProgramUnderfunded,
+    #[msg("The program's token vault was closed and cannot be reopened")]
+    TokenVaultClosed,
+    #[msg("The contest escrow still holds prizes that have not been claimed or funds that have not been withdrawn")]
+    ContestPrizesOutstanding,
+    #[msg("Withdrawal amount must be positive")]
+    InvalidWithdrawalAmount,
+    #[msg("A queued withdrawal must be executed or cancelled first")]
+    WithdrawalPending,
+    #[msg("The program's boost escrows and match offers must be closed with cleanup first")]
+    EscrowsOutstanding,
 }
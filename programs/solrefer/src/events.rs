@@ -0,0 +1,733 @@
+use crate::{
+    constants::{EVENT_MODE_DIGEST, MAX_MILESTONES, MAX_WITHDRAWAL_DESTINATIONS},
+    error::ReferralError,
+};
+use anchor_lang::prelude::*;
+
+/// Emitted when a wallet that has already been credited as a referee joins through another referral.
+/// The join succeeds but the referrer is not credited.
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AlreadyReferredNoCredit {
+    /// The referral program being joined
+    pub referral_program: Pubkey,
+    /// The wallet joining the program
+    pub referee: Pubkey,
+    /// The referrer participant account recorded on the referee's receipt
+    pub original_referrer: Pubkey,
+    /// The referrer participant account passed to this join
+    pub attempted_referrer: Pubkey,
+}
+
+/// Emitted when a referral pushes a referrer across a milestone and its one-time bonus is credited.
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MilestoneReached {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The referrer participant account credited with the bonus
+    pub participant: Pubkey,
+    /// Index of the milestone in the eligibility criteria
+    pub milestone_index: u8,
+    /// The referral count the milestone requires
+    pub threshold: u64,
+    /// The bonus credited to pending rewards
+    pub bonus: u64,
+}
+
+/// Emitted when the authority reverses a credited referral.
+#[event]
+pub struct ReferralClawedBack {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The referred wallet
+    pub referee: Pubkey,
+    /// The referrer participant account that was credited
+    pub referrer: Pubkey,
+    /// The amount removed from the referrer's pending rewards
+    pub amount: u64,
+    /// The referrer can contest the clawback until this time; 0 when the program allows no dispute
+    pub contest_deadline: i64,
+}
+
+/// Emitted when a referrer contests a clawback of one of its referrals.
+#[event]
+pub struct ClawbackContested {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The referred wallet
+    pub referee: Pubkey,
+    /// The referrer participant account contesting
+    pub referrer: Pubkey,
+    /// Hash of the referrer's off-chain evidence; zeros when none was attached
+    pub evidence_hash: [u8; 32],
+}
+
+/// Emitted when a clawback becomes final, either upheld or overturned.
+#[event]
+pub struct ClawbackResolved {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The referred wallet
+    pub referee: Pubkey,
+    /// The referrer participant account
+    pub referrer: Pubkey,
+    /// The amount under dispute
+    pub amount: u64,
+    /// True if the amount was released back to the vault, false if it was restored to the referrer
+    pub upheld: bool,
+    /// True if the outcome came from a deadline passing rather than the authority
+    pub by_default: bool,
+}
+
+/// Emitted when `recount_referrals` corrects a participant's referral count from its referee receipts.
+#[event]
+pub struct ReferralsRecounted {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The participant account whose count was corrected
+    pub participant: Pubkey,
+    /// The count before the correction
+    pub old: u64,
+    /// The count verified against the receipts
+    pub new: u64,
+}
+
+/// Emitted when a participant transfers pending rewards to another participant.
+#[event]
+pub struct PendingRewardsTransferred {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The participant account the rewards left
+    pub from: Pubkey,
+    /// The participant account the rewards were credited to
+    pub to: Pubkey,
+    /// The amount transferred
+    pub amount: u64,
+    /// When the transferred rewards unlocked at the source, now the earliest the destination's can unlock
+    pub unlocks_at: i64,
+}
+
+/// Emitted when a claim or withdrawal leaves the program's uncommitted funds short of a referral reward; new
+/// referrals credit nothing until a deposit restores them.
+#[event]
+pub struct ProgramUnderfundedForNewReferrals {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The uncommitted funds left, `total_available - total_committed`
+    pub headroom: u64,
+}
+
+/// Emitted when a deposit lets an underfunded program fund a referral reward again.
+#[event]
+pub struct ProgramFundedForNewReferrals {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The uncommitted funds after the deposit, `total_available - total_committed`
+    pub headroom: u64,
+}
+
+/// Emitted when the authority takes uncommitted funds out of the vault.
+#[event]
+pub struct FundsWithdrawn {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The amount withdrawn
+    pub amount: u64,
+    /// The wallet paid, or the owner of the token account paid
+    pub destination: Pubkey,
+    /// Whether the withdrawal waited out the delay in a `WithdrawalRequest`
+    pub queued: bool,
+}
+
+/// Emitted when the authority queues a withdrawal above the delay threshold.
+#[event]
+pub struct WithdrawalQueued {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The amount to withdraw
+    pub amount: u64,
+    /// The wallet the withdrawal will pay out to
+    pub destination: Pubkey,
+    /// When the withdrawal can execute
+    pub executable_at: i64,
+}
+
+/// Emitted when the guardian cancels a queued withdrawal.
+#[event]
+pub struct WithdrawalCancelled {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The amount that was queued
+    pub amount: u64,
+}
+
+/// Emitted when the guardian freezes a program, or the authority and guardian together unfreeze it.
+#[event]
+pub struct ProgramFreezeChanged {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// Whether the program is now frozen
+    pub frozen: bool,
+}
+
+/// Emitted when the authority pauses or unpauses a program with `set_program_status`.
+#[event]
+pub struct ProgramStatusChanged {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// Whether the program now takes joins and deposits
+    pub active: bool,
+}
+
+/// Emitted when a program whose authority stopped acting is declared abandoned.
+#[event]
+pub struct ProgramAbandoned {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The last time the authority acted on the program
+    pub last_authority_action: i64,
+    /// When the program was declared abandoned
+    pub abandoned_at: i64,
+}
+
+/// Emitted when the authority and guardian together replace a program's withdrawal allow-list.
+#[event]
+pub struct WithdrawalDestinationsChanged {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The new allow-list; all-default leaves withdrawals unrestricted
+    pub destinations: [Pubkey; MAX_WITHDRAWAL_DESTINATIONS],
+}
+
+/// Emitted when the authority writes the note on a participant.
+#[event]
+pub struct NoteUpdated {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The participant account the note is on
+    pub participant: Pubkey,
+    /// SHA-256 of the note as written, unpadded; zeros when the note was cleared
+    pub note_hash: [u8; 32],
+}
+
+/// Emitted when the authority seeds a participant account for a partner wallet.
+#[event]
+pub struct ParticipantSeeded {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The seeded participant account
+    pub participant: Pubkey,
+    /// The partner wallet that owns the participant and must activate it to claim
+    pub owner: Pubkey,
+}
+
+/// Emitted when the authority reclaims the rent of a seeded participant its owner never activated.
+#[event]
+pub struct SeededParticipantReclaimed {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The closed participant account
+    pub participant: Pubkey,
+    /// The partner wallet the participant was seeded for
+    pub owner: Pubkey,
+}
+
+/// Emitted when the authority finalizes the ranking of a contest.
+#[event]
+pub struct ContestFinalized {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// Winning wallets, best rank first
+    pub winners: Vec<Pubkey>,
+    /// When the prizes become claimable
+    pub claimable_at: i64,
+}
+
+/// Emitted when the authority records a purchase attributed to a referrer.
+#[event]
+pub struct PurchaseRecorded {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The referrer participant account the purchase is attributed to
+    pub referrer: Pubkey,
+    /// The buyer's participant account
+    pub buyer: Pubkey,
+    /// The full purchase amount
+    pub amount: u64,
+    /// The reward credited to the referrer, after the cap and vault headroom
+    pub reward: u64,
+}
+
+/// Emitted when a referee joining through a link is paid the referrer's boost on top of the sign-up bonus.
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RefereeBoosted {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The referrer participant account whose escrow paid the boost
+    pub referrer: Pubkey,
+    /// The referred wallet
+    pub referee: Pubkey,
+    /// Lamports paid to the referee
+    pub amount: u64,
+    /// Lamports left in the escrow
+    pub remaining: u64,
+}
+
+/// Emitted when a sponsor's match offer pays a referrer a share of a referral reward on top of it.
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RewardMatched {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The wallet whose offer paid the match
+    pub sponsor: Pubkey,
+    /// The referrer participant account whose reward was matched
+    pub referrer: Pubkey,
+    /// Lamports paid to the referrer's wallet
+    pub amount: u64,
+    /// Lamports left in the offer
+    pub remaining: u64,
+}
+
+/// Emitted when a program with auto downgrade credits a referral less than its full reward, because its uncommitted
+/// funds could not sustain it.
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RewardDowngraded {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The referrer participant account credited
+    pub referrer: Pubkey,
+    /// The full referral reward
+    pub requested: u64,
+    /// The reward credited instead, zero when not even the lowest reward fits
+    pub paid: u64,
+}
+
+/// Emitted when a participant claims its locked rewards early, forfeiting the program's early redemption fee.
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EarlyRedemption {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The participant account that claimed
+    pub participant: Pubkey,
+    /// The amount paid out
+    pub paid: u64,
+    /// The fee forfeited, left in the vault as uncommitted funds
+    pub fee: u64,
+}
+
+/// Emitted once per join in programs in `EVENT_MODE_DIGEST`, in place of the events the join would emit.
+///
+/// Fields the separate events would repeat are carried once; `parse_digest` expands a digest back into them.
+#[event]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct JoinDigest {
+    /// The referral program joined
+    pub referral_program: Pubkey,
+    /// The wallet joining the program
+    pub referee: Pubkey,
+    /// The referrer participant account the join went through, after following any rotation
+    pub referrer: Pubkey,
+    /// Which events the digest stands for, a combination of the `JoinDigest::*` flags
+    pub kinds: u8,
+    /// The original referrer of an `ALREADY_REFERRED` join, or the sponsor of a `REWARD_MATCHED` one
+    pub counterparty: Pubkey,
+    /// Bit `i` is set for each milestone reached, whose threshold and bonus are at index `i` below
+    pub milestones_reached: u8,
+    pub milestone_thresholds: [u64; MAX_MILESTONES],
+    pub milestone_bonuses: [u64; MAX_MILESTONES],
+    /// Lamports matched by the sponsor, and left in its offer
+    pub matched: u64,
+    pub match_remaining: u64,
+    /// Lamports boosted to the referee, and left in the referrer's escrow
+    pub boosted: u64,
+    pub boost_remaining: u64,
+    /// The full referral reward of a `REWARD_DOWNGRADED` join, and the reward it credited instead
+    pub downgrade_requested: u64,
+    pub downgrade_paid: u64,
+}
+
+impl JoinDigest {
+    /// The join stands for an `AlreadyReferredNoCredit`
+    pub const ALREADY_REFERRED: u8 = 1 << 0;
+    /// The join stands for a `RewardMatched`
+    pub const REWARD_MATCHED: u8 = 1 << 1;
+    /// The join stands for a `MilestoneReached` per bit of `milestones_reached`
+    pub const MILESTONES_REACHED: u8 = 1 << 2;
+    /// The join stands for a `RefereeBoosted`
+    pub const REFEREE_BOOSTED: u8 = 1 << 3;
+    /// The join stands for a `RewardDowngraded`
+    pub const REWARD_DOWNGRADED: u8 = 1 << 4;
+}
+
+/// Emitted when a governed program's settings update is held for participant approval.
+#[event]
+pub struct SettingsChangeProposed {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The position of the change among the program's proposals
+    pub index: u64,
+    /// Votes are accepted until this time
+    pub voting_ends_at: i64,
+}
+
+/// Emitted when a participant votes on a pending settings change.
+#[event]
+pub struct ChangeVoted {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The position of the change among the program's proposals
+    pub index: u64,
+    /// The participant account that voted
+    pub participant: Pubkey,
+    /// Whether the vote approved the change
+    pub approve: bool,
+    /// The participant's voting weight, its credited referrals
+    pub weight: u64,
+}
+
+/// Emitted when a pending settings change is executed or voided.
+#[event]
+pub struct PendingChangeResolved {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The position of the change among the program's proposals
+    pub index: u64,
+    /// Whether the change was applied; false when it was voided
+    pub executed: bool,
+}
+
+/// Emitted when `cleanup` closes a batch of spent auxiliary accounts.
+#[event]
+pub struct AccountsCleanedUp {
+    /// The referral program the accounts belonged to
+    pub referral_program: Pubkey,
+    /// The wallet that called `cleanup` and received the bounty
+    pub caller: Pubkey,
+    /// Number of accounts closed
+    pub accounts_closed: u8,
+    /// Lamports paid to the caller
+    pub bounty: u64,
+    /// Lamports returned to the accounts' rent payers
+    pub refunded: u64,
+}
+
+/// Emitted once when a claim, withdrawal or maintenance crank finds the program's available funds below one of its
+/// alert thresholds, and again only after a deposit lifts them back above it.
+#[event]
+pub struct RunwayAlert {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The crossed threshold, in percent of the program's peak available funds
+    pub threshold_pct: u8,
+    /// The funds left available to claims
+    pub total_available: u64,
+    /// How many more referral rewards the uncommitted funds cover
+    pub estimated_referrals_remaining: u64,
+    /// `total_available` in hundredths of a whole token, for indexers that do not know the mint
+    pub total_available_ui: u64,
+}
+
+/// Emitted by every `crank_maintenance` call, naming the maintenance actions it performed.
+#[event]
+pub struct MaintenancePerformed {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// The wallet that cranked
+    pub cranker: Pubkey,
+    /// Bitmask of the `MAINTENANCE_*` actions performed; zero when nothing was due
+    pub actions_bitmask: u8,
+}
+
+/// Emitted by `audit_program` when a program violates one of the accounting invariants it checks.
+#[event]
+pub struct AuditFailed {
+    /// The referral program
+    pub referral_program: Pubkey,
+    /// Bitmask of the `AUDIT_*` invariants violated
+    pub violations: u8,
+    /// The vault balance the program was audited against
+    pub vault_balance: u64,
+}
+
+/// Emitted just before an instruction rejects a program parameter, so clients simulating the transaction can
+/// point at the offending input.
+#[event]
+pub struct ValidationFailure {
+    /// The rejected parameter, as a `ProgramField`
+    pub field: u8,
+    /// Why it was rejected, as a `ValidationCode`
+    pub code: u8,
+}
+
+/// Program parameters that can be reported in a `ValidationFailure` or recorded in a `SettingsChangeRecord`.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgramField {
+    /// `fixed_reward_amount` of `create_referral_program` and `ProgramSettings`
+    FixedRewardAmount = 0,
+    /// `base_reward` of `set_eligibility_criteria` and `ProgramSettings`
+    BaseReward = 1,
+    /// `max_reward_cap` of `set_eligibility_criteria` and `ProgramSettings`
+    MaxRewardCap = 2,
+    /// `locked_period` of `ProgramSettings`
+    LockedPeriod = 3,
+    /// `program_end_time` of every instruction that takes one
+    ProgramEndTime = 4,
+    /// `milestones` of `ProgramSettings`
+    Milestones = 5,
+    /// `reward_denomination` of `create_referral_program`
+    RewardDenomination = 6,
+    /// `tier1_reward` of `set_eligibility_criteria` and `ProgramSettings`
+    Tier1Reward = 7,
+    /// `tier2_reward` of `set_eligibility_criteria` and `ProgramSettings`
+    Tier2Reward = 8,
+    /// `tier2_threshold` of `set_eligibility_criteria`
+    Tier2Threshold = 9,
+    /// `revenue_share_percent` of `set_eligibility_criteria` and `ProgramSettings`
+    RevenueSharePercent = 10,
+    /// `reserve_bps` of `ProgramSettings`
+    ReserveBps = 11,
+    /// `max_referrals_per_window` and `referral_window_seconds` of `ProgramSettings`
+    ReferralRateLimit = 12,
+    /// `required_collection` and `collection_gates_credits` of `ProgramSettings`
+    RequiredCollection = 13,
+    /// `dispute_window_seconds` of `ProgramSettings`
+    DisputeWindow = 14,
+    /// `rent_payer_mode` of `ProgramSettings`
+    RentPayerMode = 15,
+    /// `min_joiner_balance` and `min_account_age_seconds` of `ProgramSettings`
+    JoinRequirements = 16,
+    /// `alert_thresholds` of `ProgramSettings`
+    AlertThresholds = 17,
+    /// `referrer_requirement` and `referee_requirement` of `set_eligibility_criteria` and `ProgramSettings`
+    TokenRequirements = 18,
+    /// `trailing_commission_bps` of `ProgramSettings`
+    TrailingCommission = 19,
+    /// `max_depth` of `ProgramSettings`
+    MaxDepth = 20,
+    /// `invite_only` of `ProgramSettings`
+    InviteOnly = 21,
+    /// `referee_reward_amount` of `ProgramSettings`
+    RefereeReward = 22,
+    /// `referee_rewards_locked` of `ProgramSettings`
+    RefereeRewardsLocked = 23,
+    /// `transfers_enabled` of `ProgramSettings`
+    TransfersEnabled = 24,
+    /// `direct_claims_only` of `ProgramSettings`
+    DirectClaimsOnly = 25,
+    /// `link_signer` of `ProgramSettings`
+    LinkSigner = 26,
+    /// `dormancy_period_seconds` of `ProgramSettings`
+    DormancyPeriod = 27,
+    /// `region_attestor`, `embargoed_regions` and `region_checked_on_claim` of `ProgramSettings`
+    RegionEmbargo = 28,
+    /// `bridge_source_program` and `bridge_bps` of `ProgramSettings`
+    Bridge = 29,
+    /// `cleanup_bounty_bps` of `ProgramSettings`
+    CleanupBounty = 30,
+    /// `settings_locked_until` of `create_referral_program`
+    SettingsLockedUntil = 31,
+    /// `event_mode` of `ProgramSettings`
+    EventMode = 32,
+    /// `attribution_grace_seconds` of `ProgramSettings`
+    AttributionGrace = 33,
+    /// `auto_downgrade` and `min_remaining_credits` of `ProgramSettings`
+    AutoDowngrade = 34,
+    /// `seed_reclaim_seconds` of `ProgramSettings`
+    SeedReclaim = 35,
+    /// `early_redemption_fee` of `ProgramSettings`
+    EarlyRedemptionFee = 36,
+}
+
+/// Reasons a parameter can be reported in a `ValidationFailure`.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidationCode {
+    /// The value is below its minimum
+    TooLow = 0,
+    /// The value is above its maximum
+    TooHigh = 1,
+    /// The value conflicts with another parameter
+    Relationship = 2,
+    /// The value is not one of the accepted options
+    Unsupported = 3,
+}
+
+/// Emits a `ValidationFailure` for `field` and returns `error` unless `condition` holds.
+pub fn check_field(condition: bool, field: ProgramField, code: ValidationCode, error: ReferralError) -> Result<()> {
+    if !condition {
+        emit!(ValidationFailure { field: field as u8, code: code as u8 });
+        return Err(error.into());
+    }
+    Ok(())
+}
+
+/// Emits a `ValidationFailure` for `field` when a validator fails, passing its result through.
+pub fn flag_field<T>(result: Result<T>, field: ProgramField, code: ValidationCode) -> Result<T> {
+    if result.is_err() {
+        emit!(ValidationFailure { field: field as u8, code: code as u8 });
+    }
+    result
+}
+
+/// An event a join emits, as expanded from a `JoinDigest` by `parse_digest`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JoinEvent {
+    AlreadyReferredNoCredit(AlreadyReferredNoCredit),
+    RewardDowngraded(RewardDowngraded),
+    RewardMatched(RewardMatched),
+    MilestoneReached(MilestoneReached),
+    RefereeBoosted(RefereeBoosted),
+}
+
+impl JoinEvent {
+    /// Returns the event as it is logged: its discriminator followed by its fields.
+    pub fn data(&self) -> Vec<u8> {
+        match self {
+            JoinEvent::AlreadyReferredNoCredit(event) => anchor_lang::Event::data(event),
+            JoinEvent::RewardDowngraded(event) => anchor_lang::Event::data(event),
+            JoinEvent::RewardMatched(event) => anchor_lang::Event::data(event),
+            JoinEvent::MilestoneReached(event) => anchor_lang::Event::data(event),
+            JoinEvent::RefereeBoosted(event) => anchor_lang::Event::data(event),
+        }
+    }
+}
+
+/// Expands a `JoinDigest` into the events the join emits in `EVENT_MODE_VERBOSE`, in the order it emits them.
+pub fn parse_digest(digest: &JoinDigest) -> Vec<JoinEvent> {
+    let mut events = Vec::new();
+    if digest.kinds & JoinDigest::ALREADY_REFERRED != 0 {
+        events.push(JoinEvent::AlreadyReferredNoCredit(AlreadyReferredNoCredit {
+            referral_program: digest.referral_program,
+            referee: digest.referee,
+            original_referrer: digest.counterparty,
+            attempted_referrer: digest.referrer,
+        }));
+    }
+    if digest.kinds & JoinDigest::REWARD_DOWNGRADED != 0 {
+        events.push(JoinEvent::RewardDowngraded(RewardDowngraded {
+            referral_program: digest.referral_program,
+            referrer: digest.referrer,
+            requested: digest.downgrade_requested,
+            paid: digest.downgrade_paid,
+        }));
+    }
+    if digest.kinds & JoinDigest::REWARD_MATCHED != 0 {
+        events.push(JoinEvent::RewardMatched(RewardMatched {
+            referral_program: digest.referral_program,
+            sponsor: digest.counterparty,
+            referrer: digest.referrer,
+            amount: digest.matched,
+            remaining: digest.match_remaining,
+        }));
+    }
+    if digest.kinds & JoinDigest::MILESTONES_REACHED != 0 {
+        for index in (0..MAX_MILESTONES).filter(|index| digest.milestones_reached & (1 << index) != 0) {
+            events.push(JoinEvent::MilestoneReached(MilestoneReached {
+                referral_program: digest.referral_program,
+                participant: digest.referrer,
+                milestone_index: index as u8,
+                threshold: digest.milestone_thresholds[index],
+                bonus: digest.milestone_bonuses[index],
+            }));
+        }
+    }
+    if digest.kinds & JoinDigest::REFEREE_BOOSTED != 0 {
+        events.push(JoinEvent::RefereeBoosted(RefereeBoosted {
+            referral_program: digest.referral_program,
+            referrer: digest.referrer,
+            referee: digest.referee,
+            amount: digest.boosted,
+            remaining: digest.boost_remaining,
+        }));
+    }
+    events
+}
+
+/// Reports the events of one join as its program's `event_mode` says: each emitted as it occurs, or packed into a
+/// `JoinDigest` emitted by `finish`.
+pub struct JoinEvents {
+    digest: Option<JoinDigest>,
+}
+
+impl JoinEvents {
+    pub fn new(event_mode: u8, referral_program: Pubkey, referee: Pubkey) -> Self {
+        let digest =
+            (event_mode == EVENT_MODE_DIGEST).then(|| JoinDigest { referral_program, referee, ..Default::default() });
+        Self { digest }
+    }
+
+    pub fn already_referred(&mut self, event: AlreadyReferredNoCredit) {
+        match self.digest.as_mut() {
+            Some(digest) => {
+                digest.kinds |= JoinDigest::ALREADY_REFERRED;
+                digest.referrer = event.attempted_referrer;
+                digest.counterparty = event.original_referrer;
+            }
+            None => emit!(event),
+        }
+    }
+
+    pub fn reward_downgraded(&mut self, event: RewardDowngraded) {
+        match self.digest.as_mut() {
+            Some(digest) => {
+                digest.kinds |= JoinDigest::REWARD_DOWNGRADED;
+                digest.referrer = event.referrer;
+                digest.downgrade_requested = event.requested;
+                digest.downgrade_paid = event.paid;
+            }
+            None => emit!(event),
+        }
+    }
+
+    pub fn reward_matched(&mut self, event: RewardMatched) {
+        match self.digest.as_mut() {
+            Some(digest) => {
+                digest.kinds |= JoinDigest::REWARD_MATCHED;
+                digest.referrer = event.referrer;
+                digest.counterparty = event.sponsor;
+                digest.matched = event.amount;
+                digest.match_remaining = event.remaining;
+            }
+            None => emit!(event),
+        }
+    }
+
+    pub fn milestone_reached(&mut self, event: MilestoneReached) {
+        match self.digest.as_mut() {
+            Some(digest) => {
+                let index = usize::from(event.milestone_index);
+                digest.kinds |= JoinDigest::MILESTONES_REACHED;
+                digest.referrer = event.participant;
+                digest.milestones_reached |= 1 << index;
+                digest.milestone_thresholds[index] = event.threshold;
+                digest.milestone_bonuses[index] = event.bonus;
+            }
+            None => emit!(event),
+        }
+    }
+
+    pub fn referee_boosted(&mut self, event: RefereeBoosted) {
+        match self.digest.as_mut() {
+            Some(digest) => {
+                digest.kinds |= JoinDigest::REFEREE_BOOSTED;
+                digest.referrer = event.referrer;
+                digest.boosted = event.amount;
+                digest.boost_remaining = event.remaining;
+            }
+            None => emit!(event),
+        }
+    }
+
+    /// Emits the digest of a join in `EVENT_MODE_DIGEST`, unless the join had no events to report.
+    pub fn finish(self) {
+        if let Some(digest) = self.digest.filter(|digest| digest.kinds != 0) {
+            emit!(digest);
+        }
+    }
+}
@@ -0,0 +1,47 @@
+//! Abandonment of programs whose authority stopped acting.
+//!
+//! Claims never need the authority: they only read the program, its criteria, its vault and the participant, so
+//! a lost authority key cannot hold earned rewards hostage. What a lost key does leave behind is a program whose
+//! settings nobody can change and whose remaining funds nobody can withdraw, and, if the guardian froze it, one
+//! that nobody can unfreeze. Once the authority has not acted for the program's dormancy period anyone can declare
+//! the program abandoned: its settings are frozen at their current values for good, the authority is locked out
+//! should it come back, and a guardian freeze is lifted so claims run until the funds or the program run out.
+use crate::{error::ReferralError, events::ProgramAbandoned, state::*};
+use anchor_lang::prelude::*;
+
+/// Accounts required for declaring a program abandoned.
+#[derive(Accounts)]
+pub struct DeclareAbandoned<'info> {
+    #[account(mut)]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    /// Anyone may declare a dormant program abandoned
+    pub caller: Signer<'info>,
+}
+
+/// Declares a program abandoned once its authority has not acted for its dormancy period.
+///
+/// From then on every instruction signed by the authority fails with `ProgramAbandoned`, while joins and claims go
+/// on as before. A frozen program is unfrozen, since unfreezing takes the authority's signature.
+///
+/// # Arguments
+/// * `ctx` - The context for the DeclareAbandoned instruction
+///
+/// # Errors
+/// * `ProgramAbandoned` - If the program was already declared abandoned
+/// * `ProgramNotDormant` - If the authority acted within the program's dormancy period
+pub fn declare_abandoned(ctx: Context<DeclareAbandoned>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let referral_program = &mut ctx.accounts.referral_program;
+    require!(!referral_program.is_abandoned(), ReferralError::ProgramAbandoned);
+    require!(now >= referral_program.dormant_at(), ReferralError::ProgramNotDormant);
+
+    referral_program.abandoned_at = now;
+    referral_program.frozen = false;
+    emit!(ProgramAbandoned {
+        referral_program: referral_program.key(),
+        last_authority_action: referral_program.last_authority_action,
+        abandoned_at: now,
+    });
+    Ok(())
+}
@@ -0,0 +1,138 @@
+//! Accounting invariants of a referral program, checked on demand by `audit_program` and after mutations in debug
+//! builds.
+//!
+//! Each invariant is a named predicate over the program account, so the read-only audit keepers run on deployed
+//! programs and the debug assertions of the mutating handlers can never check different things. The funds recorded
+//! against a program are its `total_available` and `reserved_balance`; rewards credited to participants stay part of
+//! `total_available` until claimed, and funds may be overcommitted by design, so `total_committed` is not bounded by
+//! them.
+use crate::{
+    constants::{
+        AUDIT_DUST_TOLERANCE, AUDIT_END_TIME_STALE, AUDIT_FUNDS_UNBALANCED, AUDIT_REFERRAL_COUNTS,
+        AUDIT_VAULT_INSOLVENT,
+    },
+    error::ReferralError,
+    events::AuditFailed,
+    instructions::{TOKEN_VAULT_SEED, VAULT_SEED},
+    state::*,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
+
+/// Returns true if the vault holds the program's available funds and reserve, give or take `AUDIT_DUST_TOLERANCE`.
+pub fn vault_is_solvent(program: &ReferralProgram, vault_balance: u64) -> bool {
+    let recorded = u128::from(program.total_available) + u128::from(program.reserved_balance);
+    u128::from(vault_balance) + u128::from(AUDIT_DUST_TOLERANCE) >= recorded
+}
+
+/// Returns true if the available funds, reserve and distributed rewards add up to no more than every deposit.
+///
+/// Deposits only ever move between those three, so anything short of the deposits was withdrawn.
+pub fn funds_are_balanced(program: &ReferralProgram) -> bool {
+    let accounted = u128::from(program.total_available)
+        + u128::from(program.reserved_balance)
+        + u128::from(program.total_rewards_distributed);
+    accounted <= u128::from(program.total_deposited)
+}
+
+/// Returns true if no more referrals were credited than made, and the source tag buckets count every raw referral
+/// exactly once.
+pub fn referral_counts_agree(program: &ReferralProgram) -> bool {
+    let tagged: u128 = program.source_tag_counts.iter().map(|slot| u128::from(slot.count)).sum();
+    let bucketed = tagged + u128::from(program.other_tag_joins) + u128::from(program.untagged_joins);
+    program.total_referrals_credited <= program.total_referrals_raw
+        && bucketed == u128::from(program.total_referrals_raw)
+}
+
+/// Returns true if the program's cached end time matches the eligibility criteria, its source of truth.
+pub fn end_time_is_cached(program: &ReferralProgram, criteria: &EligibilityCriteria) -> bool {
+    program.program_end_time == criteria.program_end_time
+}
+
+/// Checks every invariant against a vault holding `vault_balance` and returns the bitmask of the `AUDIT_*`
+/// invariants violated; zero when the program is sound.
+pub fn audit(program: &ReferralProgram, criteria: &EligibilityCriteria, vault_balance: u64) -> u8 {
+    let mut violations = 0;
+    if !vault_is_solvent(program, vault_balance) {
+        violations |= AUDIT_VAULT_INSOLVENT;
+    }
+    if !funds_are_balanced(program) {
+        violations |= AUDIT_FUNDS_UNBALANCED;
+    }
+    if !referral_counts_agree(program) {
+        violations |= AUDIT_REFERRAL_COUNTS;
+    }
+    if !end_time_is_cached(program, criteria) {
+        violations |= AUDIT_END_TIME_STALE;
+    }
+    violations
+}
+
+/// Checks in debug builds that the vault holds the program's funds and that they balance against the deposits.
+pub fn debug_assert_funds(program: &ReferralProgram, vault_balance: u64) {
+    debug_assert!(vault_is_solvent(program, vault_balance), "vault of {vault_balance} below the recorded funds");
+    debug_assert!(funds_are_balanced(program), "funds exceed the deposits");
+}
+
+/// Checks in debug builds that the referral counters agree with each other.
+pub fn debug_assert_referral_counts(program: &ReferralProgram) {
+    debug_assert!(referral_counts_agree(program), "referral counters disagree");
+}
+
+/// Checks in debug builds that the cached end time matches the eligibility criteria.
+pub fn debug_assert_end_time_cached(program: &ReferralProgram, criteria: &EligibilityCriteria) {
+    debug_assert!(end_time_is_cached(program, criteria), "stale program end time cache");
+}
+
+/// Accounts required for the read-only `audit_program` instruction.
+#[derive(Accounts)]
+pub struct AuditProgram<'info> {
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        seeds = [b"eligibility_criteria", referral_program.key().as_ref()],
+        bump
+    )]
+    pub eligibility_criteria: Account<'info, EligibilityCriteria>,
+
+    /// PDA with seeds: ["vault", referral_program.key()]; holds a SOL program's funds
+    #[account(
+        seeds = [VAULT_SEED, referral_program.key().as_ref()],
+        bump,
+    )]
+    pub vault: SystemAccount<'info>,
+
+    /// PDA with seeds: ["token_vault", referral_program.key()]; required to audit a token program once its token
+    /// vault was initialized
+    #[account(
+        seeds = [TOKEN_VAULT_SEED, referral_program.key().as_ref()],
+        bump,
+    )]
+    pub token_vault: Option<Account<'info, TokenAccount>>,
+}
+
+/// Audits a program's accounting against its vault without mutating anything, emitting `AuditFailed` when an
+/// invariant is violated.
+///
+/// Returns the bitmask of the `AUDIT_*` invariants violated.
+///
+/// # Errors
+/// * `InvalidTokenAccounts` - If the program is a token program with an initialized token vault and no token vault
+///   was passed
+pub fn audit_program(ctx: Context<AuditProgram>) -> Result<u8> {
+    let program = &ctx.accounts.referral_program;
+    let vault_balance = if program.token_mint == Pubkey::default() {
+        ctx.accounts.vault.lamports()
+    } else if program.token_vault_initialized {
+        ctx.accounts.token_vault.as_ref().ok_or(ReferralError::InvalidTokenAccounts)?.amount
+    } else {
+        0
+    };
+
+    let violations = audit(program, &ctx.accounts.eligibility_criteria, vault_balance);
+    if violations != 0 {
+        msg!("Referral program {} failed its audit: {:#06b}", program.key(), violations);
+        emit!(AuditFailed { referral_program: program.key(), violations, vault_balance });
+    }
+    Ok(violations)
+}
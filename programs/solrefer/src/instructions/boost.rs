@@ -0,0 +1,175 @@
+use crate::{
+    constants::BOOST_SEED,
+    error::ReferralError,
+    events::{JoinEvents, RefereeBoosted},
+    state::*,
+    validation::require_nonzero_amount,
+};
+use anchor_lang::{
+    prelude::*,
+    system_program::{self, Transfer},
+};
+
+/// Accounts required for funding a referrer's boost escrow.
+#[derive(Accounts)]
+pub struct FundRefereeBoost<'info> {
+    #[account(mut)]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        seeds = [
+            b"participant",
+            referral_program.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump
+    )]
+    pub participant: Account<'info, Participant>,
+
+    /// PDA with seeds: ["boost", referral_program.key(), participant.key()]
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + BoostEscrow::SIZE,
+        seeds = [BOOST_SEED, referral_program.key().as_ref(), participant.key().as_ref()],
+        bump
+    )]
+    pub boost_escrow: Account<'info, BoostEscrow>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Deposits `amount` lamports into the signer's boost escrow and sets the boost each referee receives.
+///
+/// Every referee joining through the signer's link while the program pays sign-up bonuses is paid
+/// `boost_per_referee` from the escrow, until the escrow holds less than that. Funding again tops the escrow up
+/// and replaces the boost per referee.
+///
+/// # Arguments
+/// * `ctx` - The context for the FundRefereeBoost instruction
+/// * `amount` - Lamports to deposit
+/// * `boost_per_referee` - Lamports paid to each boosted referee
+///
+/// # Errors
+/// * `InsufficientDeposit` - If the amount is zero
+/// * `InvalidBoostAmount` - If the boost per referee is zero
+/// * `ParticipantRotated` - If the signer's participant account was rotated to a new owner
+/// * `ParticipantLeft` - If the signer's participant left the program
+/// * `ProgramEnded` - If the program's end time has passed
+/// * `ProgramClosing` - If the program is pending closure
+pub fn fund_referee_boost(ctx: Context<FundRefereeBoost>, amount: u64, boost_per_referee: u64) -> Result<()> {
+    require_nonzero_amount(amount, ReferralError::InsufficientDeposit)?;
+    require!(boost_per_referee > 0, ReferralError::InvalidBoostAmount);
+    ctx.accounts.participant.require_current()?;
+    let referral_program = &ctx.accounts.referral_program;
+    require!(!referral_program.has_ended(Clock::get()?.unix_timestamp), ReferralError::ProgramEnded);
+    require!(!referral_program.is_closing(), ReferralError::ProgramClosing);
+
+    system_program::transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            Transfer { from: ctx.accounts.user.to_account_info(), to: ctx.accounts.boost_escrow.to_account_info() },
+        ),
+        amount,
+    )?;
+
+    let referral_program = &mut ctx.accounts.referral_program;
+    let boost_escrow = &mut ctx.accounts.boost_escrow;
+    if boost_escrow.program == Pubkey::default() {
+        referral_program.open_boost_escrows =
+            referral_program.open_boost_escrows.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+    }
+    boost_escrow.program = referral_program.key();
+    boost_escrow.participant = ctx.accounts.participant.key();
+    boost_escrow.owner = ctx.accounts.user.key();
+    boost_escrow.boost_per_referee = boost_per_referee;
+    boost_escrow.balance = boost_escrow.balance.checked_add(amount).ok_or(ReferralError::NumericOverflow)?;
+    boost_escrow.bump = ctx.bumps.boost_escrow;
+    Ok(())
+}
+
+/// Accounts required for withdrawing from a referrer's boost escrow.
+#[derive(Accounts)]
+pub struct WithdrawRefereeBoost<'info> {
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        seeds = [
+            b"participant",
+            referral_program.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump
+    )]
+    pub participant: Account<'info, Participant>,
+
+    /// PDA with seeds: ["boost", referral_program.key(), participant.key()]
+    #[account(
+        mut,
+        seeds = [BOOST_SEED, referral_program.key().as_ref(), participant.key().as_ref()],
+        bump = boost_escrow.bump,
+    )]
+    pub boost_escrow: Account<'info, BoostEscrow>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+/// Withdraws `amount` unused lamports from the signer's boost escrow; allowed at any time.
+///
+/// # Errors
+/// * `InvalidBoostAmount` - If the amount is zero
+/// * `InsufficientBoostBalance` - If the escrow holds less than `amount`
+pub fn withdraw_referee_boost(ctx: Context<WithdrawRefereeBoost>, amount: u64) -> Result<()> {
+    require_nonzero_amount(amount, ReferralError::InvalidBoostAmount)?;
+    let boost_escrow = &mut ctx.accounts.boost_escrow;
+    require!(amount <= boost_escrow.balance, ReferralError::InsufficientBoostBalance);
+
+    boost_escrow.balance -= amount;
+    boost_escrow.sub_lamports(amount)?;
+    ctx.accounts.user.add_lamports(amount)?;
+    msg!("Withdrew {} lamports of boost; {} left", amount, boost_escrow.balance);
+    Ok(())
+}
+
+/// Pays `referee` the next boost from `boost_escrow`, returning the amount paid.
+///
+/// The escrow must belong to `referrer` in `program`. Nothing is paid once the escrow holds less than a full
+/// boost; the remainder stays withdrawable. The payment is reported through `events`.
+pub fn pay_referee_boost<'info>(
+    boost_escrow: &mut Account<'info, BoostEscrow>,
+    program: Pubkey,
+    referrer: Pubkey,
+    referee: &AccountInfo<'info>,
+    events: &mut JoinEvents,
+) -> Result<u64> {
+    require!(
+        boost_escrow.program == program && boost_escrow.participant == referrer,
+        ReferralError::InvalidBoostEscrow
+    );
+    let amount = boost_escrow.next_boost();
+    if amount == 0 {
+        msg!("Boost escrow exhausted; no boost paid");
+        return Ok(0);
+    }
+
+    boost_escrow.balance -= amount;
+    boost_escrow.total_boosted =
+        boost_escrow.total_boosted.checked_add(amount).ok_or(ReferralError::NumericOverflow)?;
+    boost_escrow.referees_boosted =
+        boost_escrow.referees_boosted.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+    boost_escrow.sub_lamports(amount)?;
+    referee.add_lamports(amount)?;
+
+    events.referee_boosted(RefereeBoosted {
+        referral_program: program,
+        referrer,
+        referee: referee.key(),
+        amount,
+        remaining: boost_escrow.balance,
+    });
+    Ok(amount)
+}
@@ -0,0 +1,66 @@
+//! Referral bridges: cross-program credit for a referrer whose referee joins another program directly.
+//!
+//! A program with a `bridge_source_program` credits `bridge_bps` of its base reward to the wallet that referred a
+//! joining user into the source program, provided that wallet is a participant of this program too. The credit is
+//! paid from this program's own funds like any other; nothing is read from or moved out of the source program but
+//! the user's participant account there. A wallet joins a program once, so each user bridges at most one credit.
+use crate::{
+    error::ReferralError,
+    instructions::{can_fund_next_referral, check_credit_funding},
+    state::{participant::Participant, referral_program::*},
+};
+use anchor_lang::prelude::*;
+
+/// Credits the referrer bridged from `source_participant`, the joining `user`'s participant account in the program's
+/// bridge source program, to `referrer`, the participant account in this program of the wallet that referred the
+/// user there. Returns the amount credited.
+///
+/// The bridge is skipped, crediting nothing, when the program has no bridge source, either account is missing, the
+/// user joined the source program directly, the program stopped accepting referrals or cannot fund the next
+/// referral, or the referrer was rotated to a new wallet. The credit is clamped to the program's uncommitted funds,
+/// and a credit that leaves them short of the next referral stops the program accepting referrals.
+///
+/// # Errors
+/// * `InvalidBridgeAccounts` - If `source_participant` is not the user's in the bridge source program, or
+///   `referrer` is not the participant in this program of the wallet that referred the user there
+pub fn credit_bridge_referrer(
+    program: &mut Account<ReferralProgram>,
+    criteria: &EligibilityCriteria,
+    user: &Pubkey,
+    source_participant: Option<&Account<Participant>>,
+    referrer: Option<&mut Account<Participant>>,
+) -> Result<u64> {
+    let (Some(source_program), Some(source_participant), Some(referrer)) =
+        (criteria.bridge_source_program, source_participant, referrer)
+    else {
+        return Ok(0);
+    };
+    require!(
+        source_participant.program == source_program && source_participant.owner == *user,
+        ReferralError::InvalidBridgeAccounts
+    );
+    let Some(source_referrer) = source_participant.referrer else {
+        return Ok(0);
+    };
+    require!(
+        referrer.program == program.key() && Participant::address(&source_program, &referrer.owner) == source_referrer,
+        ReferralError::InvalidBridgeAccounts
+    );
+    if !program.accepting_referrals
+        || !can_fund_next_referral(program, criteria)?
+        || referrer.rotated_to.is_some()
+        || referrer.has_left()
+    {
+        return Ok(0);
+    }
+
+    let credit = criteria.bridge_credit()?.min(program.headroom());
+    if credit == 0 {
+        return Ok(0);
+    }
+    referrer.credit_reward(credit)?;
+    program.total_committed = program.total_committed.checked_add(credit).ok_or(ReferralError::NumericOverflow)?;
+    msg!("Credited a bridge credit of {} to {}", credit, referrer.key());
+    check_credit_funding(program, criteria)?;
+    Ok(credit)
+}
@@ -0,0 +1,144 @@
+use crate::error::ReferralError;
+use anchor_lang::{
+    prelude::*,
+    solana_program::{
+        instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT},
+        sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+    },
+};
+use anchor_spl::token::accessor;
+
+/// Balances of the accounts a claim touches: the participant's lamports, and the vault's and destination's
+/// lamports or tokens, whichever the claim pays out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ClaimBalances {
+    pub participant: u64,
+    pub vault: u64,
+    pub destination: u64,
+}
+
+impl ClaimBalances {
+    /// Checks that going from `self` to `after` paid exactly `paid` from the vault to the destination and left the
+    /// participant account's lamports untouched.
+    ///
+    /// # Errors
+    /// * `ClaimGuardViolation` - If any balance moved differently; the failed check is logged
+    pub fn check_payout(&self, after: &ClaimBalances, paid: u64) -> Result<()> {
+        if after.participant != self.participant {
+            msg!("Claim guard: participant lamports went from {} to {}", self.participant, after.participant);
+            return err!(ReferralError::ClaimGuardViolation);
+        }
+        if self.vault.checked_sub(paid) != Some(after.vault) {
+            msg!("Claim guard: vault went from {} to {} paying {}", self.vault, after.vault, paid);
+            return err!(ReferralError::ClaimGuardViolation);
+        }
+        if self.destination.checked_add(paid) != Some(after.destination) {
+            msg!("Claim guard: destination went from {} to {} paying {}", self.destination, after.destination, paid);
+            return err!(ReferralError::ClaimGuardViolation);
+        }
+        Ok(())
+    }
+}
+
+/// What a guarded claim pays out, and so which balance of its vault and destinations is watched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Payout {
+    Lamports,
+    Tokens,
+}
+
+/// Post-conditions of a claim paying rewards from a vault to a wallet.
+///
+/// Created at the start of a claim handler, before anything moves, and consumed by [`ClaimGuard::finish`] once
+/// the payout is done: the participant PDA must hold exactly the lamports it started with, the vault must have
+/// paid exactly the claimed amount and the destination received exactly it. Rewards can therefore only ever
+/// come out of the vault, whatever a later refactor does to the transfer. A claim split across several
+/// destinations checks what they received together. Token claims watch the token balances of the vault and
+/// destinations instead of their lamports; the participant PDA's lamports are watched either way.
+pub struct ClaimGuard<'info> {
+    participant: AccountInfo<'info>,
+    vault: AccountInfo<'info>,
+    destinations: Vec<AccountInfo<'info>>,
+    payout: Payout,
+    before: ClaimBalances,
+}
+
+impl<'info> ClaimGuard<'info> {
+    /// Snapshots the lamports of the claim's participant PDA, vault and destination wallet.
+    pub fn new(
+        participant: AccountInfo<'info>,
+        vault: AccountInfo<'info>,
+        destination: AccountInfo<'info>,
+    ) -> Result<Self> {
+        Self::new_split(participant, vault, vec![destination])
+    }
+
+    /// Snapshots the lamports of the claim's participant PDA, vault and the distinct destinations it is split
+    /// across.
+    pub fn new_split(
+        participant: AccountInfo<'info>,
+        vault: AccountInfo<'info>,
+        destinations: Vec<AccountInfo<'info>>,
+    ) -> Result<Self> {
+        Self::snapshot(participant, vault, destinations, Payout::Lamports)
+    }
+
+    /// Snapshots the lamports of the claim's participant PDA and the token balances of its token vault and the
+    /// distinct token accounts it is split across.
+    pub fn new_token_split(
+        participant: AccountInfo<'info>,
+        token_vault: AccountInfo<'info>,
+        destinations: Vec<AccountInfo<'info>>,
+    ) -> Result<Self> {
+        Self::snapshot(participant, token_vault, destinations, Payout::Tokens)
+    }
+
+    fn snapshot(
+        participant: AccountInfo<'info>,
+        vault: AccountInfo<'info>,
+        destinations: Vec<AccountInfo<'info>>,
+        payout: Payout,
+    ) -> Result<Self> {
+        let mut guard = Self { participant, vault, destinations, payout, before: ClaimBalances::default() };
+        guard.before = guard.balances()?;
+        Ok(guard)
+    }
+
+    fn balance(&self, account: &AccountInfo) -> Result<u64> {
+        match self.payout {
+            Payout::Lamports => Ok(account.lamports()),
+            Payout::Tokens => accessor::amount(account),
+        }
+    }
+
+    fn balances(&self) -> Result<ClaimBalances> {
+        let mut destination = 0u64;
+        for account in &self.destinations {
+            destination = destination.checked_add(self.balance(account)?).ok_or(ReferralError::ClaimGuardViolation)?;
+        }
+        Ok(ClaimBalances { participant: self.participant.lamports(), vault: self.balance(&self.vault)?, destination })
+    }
+
+    /// Checks the claim's post-conditions after `paid` was paid out.
+    ///
+    /// # Errors
+    /// * `ClaimGuardViolation` - If the participant's lamports changed or the vault and destination did not move
+    ///   by exactly `paid`
+    pub fn finish(self, paid: u64) -> Result<()> {
+        self.before.check_payout(&self.balances()?, paid)
+    }
+}
+
+/// Returns true if the running instruction is a top-level instruction of its transaction, not a CPI.
+///
+/// The current top-level instruction read from `instructions_sysvar` must be this program's, so no other program
+/// wrapped the call, and the stack height must be the transaction's own, so this program did not reach the handler
+/// through a CPI of its own either. Without the sysvar the invocation cannot be shown to be direct.
+pub fn is_direct_invocation(instructions_sysvar: Option<&AccountInfo>) -> Result<bool> {
+    let Some(instructions_sysvar) = instructions_sysvar else {
+        return Ok(false);
+    };
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+    let current = load_instruction_at_checked(usize::from(current_index), instructions_sysvar)?;
+    Ok(current.program_id == crate::ID && get_stack_height() == TRANSACTION_LEVEL_STACK_HEIGHT)
+}
@@ -0,0 +1,203 @@
+use crate::{
+    constants::{MAX_SPLITTER_ENTRIES, SPLITTER_SEED},
+    error::ReferralError,
+    state::*,
+};
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
+use anchor_spl::token::TokenAccount;
+
+/// Accounts required for configuring a participant's claim splitter.
+#[derive(Accounts)]
+pub struct ConfigureSplitter<'info> {
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"participant",
+            referral_program.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump
+    )]
+    pub participant: Account<'info, Participant>,
+
+    /// PDA with seeds: ["splitter", participant.key()]
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + ClaimSplitter::SIZE,
+        seeds = [SPLITTER_SEED, participant.key().as_ref()],
+        bump
+    )]
+    pub claim_splitter: Account<'info, ClaimSplitter>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Splits every future claim of the signer's participant account across `entries`, replacing any split it had.
+///
+/// Each destination is paid its share of a claim, rounded down, and the first destination also receives what the
+/// rounding leaves over. SOL claims pay the destination wallets themselves and token claims a token account each
+/// destination owns, passed in the claim's remaining accounts in the order of `entries`.
+///
+/// # Arguments
+/// * `ctx` - The context for the ConfigureSplitter instruction
+/// * `entries` - The destinations and their shares in basis points
+///
+/// # Errors
+/// * `ParticipantRotated` - If the signer's participant account was rotated to a new owner
+/// * `ParticipantLeft` - If the signer's participant left the program
+/// * `InvalidClaimSplitter` - If there are no entries or more than `MAX_SPLITTER_ENTRIES`, a destination repeats,
+///   a share is zero or the shares do not sum to 10_000 basis points
+pub fn configure_splitter(ctx: Context<ConfigureSplitter>, entries: Vec<SplitterEntry>) -> Result<()> {
+    let participant = &mut ctx.accounts.participant;
+    participant.require_current()?;
+    require!(!entries.is_empty() && entries.len() <= MAX_SPLITTER_ENTRIES, ReferralError::InvalidClaimSplitter);
+    let distinct = entries
+        .iter()
+        .enumerate()
+        .all(|(index, entry)| entries[..index].iter().all(|previous| previous.destination != entry.destination));
+    let total_bps = entries.iter().map(|entry| u32::from(entry.bps)).sum::<u32>();
+    require!(
+        distinct && total_bps == 10_000 && entries.iter().all(|entry| entry.bps > 0),
+        ReferralError::InvalidClaimSplitter
+    );
+
+    let claim_splitter = &mut ctx.accounts.claim_splitter;
+    claim_splitter.participant = participant.key();
+    claim_splitter.entry_count = entries.len() as u8;
+    claim_splitter.entries = [SplitterEntry::default(); MAX_SPLITTER_ENTRIES];
+    claim_splitter.entries[..entries.len()].copy_from_slice(&entries);
+    claim_splitter.bump = ctx.bumps.claim_splitter;
+    participant.has_claim_splitter = true;
+    Ok(())
+}
+
+/// Accounts required for removing a participant's claim splitter.
+#[derive(Accounts)]
+pub struct RemoveSplitter<'info> {
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"participant",
+            referral_program.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump
+    )]
+    pub participant: Account<'info, Participant>,
+
+    /// PDA with seeds: ["splitter", participant.key()]
+    #[account(
+        mut,
+        close = user,
+        seeds = [SPLITTER_SEED, participant.key().as_ref()],
+        bump = claim_splitter.bump,
+    )]
+    pub claim_splitter: Account<'info, ClaimSplitter>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+/// Closes the signer's claim splitter, returning its rent; claims pay the owner alone again.
+pub fn remove_splitter(ctx: Context<RemoveSplitter>) -> Result<()> {
+    ctx.accounts.participant.has_claim_splitter = false;
+    Ok(())
+}
+
+/// Returns the splitter a claim of `participant` is split by, if any, checking the claim passed it along with one
+/// destination account per entry, and no destination accounts when the participant has no splitter.
+///
+/// # Errors
+/// * `ClaimSplitterMismatch` - If the splitter or the number of destinations does not match the participant's
+pub fn claim_split<'a>(
+    participant: &Participant,
+    claim_splitter: Option<&'a ClaimSplitter>,
+    destinations: &[AccountInfo],
+) -> Result<Option<&'a ClaimSplitter>> {
+    match (participant.has_claim_splitter, claim_splitter) {
+        (false, None) if destinations.is_empty() => Ok(None),
+        (true, Some(claim_splitter)) if destinations.len() == claim_splitter.entries().len() => {
+            Ok(Some(claim_splitter))
+        }
+        _ => err!(ReferralError::ClaimSplitterMismatch),
+    }
+}
+
+/// Checks each destination wallet of a split SOL claim is the destination of its entry.
+///
+/// # Errors
+/// * `ClaimSplitterMismatch` - If a wallet differs from its entry's destination
+pub fn require_split_wallets(claim_splitter: &ClaimSplitter, destinations: &[AccountInfo]) -> Result<()> {
+    let matches =
+        claim_splitter.entries().iter().zip(destinations).all(|(entry, wallet)| wallet.key() == entry.destination);
+    require!(matches, ReferralError::ClaimSplitterMismatch);
+    Ok(())
+}
+
+/// Loads the destination token accounts of a split token claim, checking each holds `mint` and is owned by the
+/// destination of its entry.
+///
+/// # Errors
+/// * `ClaimSplitterMismatch` - If an account is not a token account of `mint` owned by its entry's destination
+pub fn split_token_accounts<'info>(
+    claim_splitter: &ClaimSplitter,
+    destinations: &'info [AccountInfo<'info>],
+    mint: Pubkey,
+) -> Result<Vec<Account<'info, TokenAccount>>> {
+    claim_splitter
+        .entries()
+        .iter()
+        .zip(destinations)
+        .map(|(entry, info)| {
+            let token_account =
+                Account::<TokenAccount>::try_from(info).map_err(|_| error!(ReferralError::ClaimSplitterMismatch))?;
+            require!(
+                token_account.mint == mint && token_account.owner == entry.destination,
+                ReferralError::ClaimSplitterMismatch
+            );
+            Ok(token_account)
+        })
+        .collect()
+}
+
+/// Pays each destination its share of a SOL claim from the vault, signing with the vault's `signer` seeds and
+/// skipping shares rounded down to zero.
+pub fn pay_sol_shares<'info>(
+    vault: &AccountInfo<'info>,
+    destinations: &[AccountInfo<'info>],
+    shares: &[u64],
+    signer: &[&[&[u8]]],
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    for (destination, &share) in destinations.iter().zip(shares).filter(|(_, &share)| share > 0) {
+        let transfer_ctx = CpiContext::new_with_signer(
+            system_program.clone(),
+            Transfer { from: vault.clone(), to: destination.clone() },
+            signer,
+        );
+        transfer(transfer_ctx, share)?;
+    }
+    Ok(())
+}
+
+/// Reloads the destination token accounts of a claim and returns true if each rose from its balance in `before`
+/// by exactly its share.
+pub fn token_shares_paid(destinations: &mut [Account<TokenAccount>], before: &[u64], shares: &[u64]) -> Result<bool> {
+    let mut paid_exactly = true;
+    for ((destination, before), share) in destinations.iter_mut().zip(before).zip(shares) {
+        destination.reload()?;
+        paid_exactly &= before.checked_add(*share) == Some(destination.amount);
+    }
+    Ok(paid_exactly)
+}
@@ -0,0 +1,336 @@
+use crate::{
+    constants::{IDEMPOTENCY_KEY_LEN, REFEREE_RECEIPT_SEED},
+    error::ReferralError,
+    events::{ClawbackContested, ClawbackResolved, ReferralClawedBack},
+    instructions::restore_referral_funding,
+    state::*,
+};
+use anchor_lang::prelude::*;
+
+/// Accounts required for clawing back a credited referral.
+#[derive(Accounts)]
+pub struct ClawbackReferral<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    /// The receipt of the referral being reversed
+    /// PDA with seeds: ["referee", referral_program.key(), referee_receipt.referee]
+    #[account(
+        mut,
+        seeds = [REFEREE_RECEIPT_SEED, referral_program.key().as_ref(), referee_receipt.referee.as_ref()],
+        bump = referee_receipt.bump,
+        constraint = !referee_receipt.clawed_back @ ReferralError::AlreadyClawedBack,
+        constraint = referee_receipt.counted @ ReferralError::InvalidReferralRecord,
+    )]
+    pub referee_receipt: Account<'info, RefereeReceipt>,
+
+    /// The participant account credited for the referral, or the account it was rotated to
+    #[account(
+        mut,
+        constraint = is_credited_referrer(&referrer, &referee_receipt) @ ReferralError::InvalidReferrer,
+    )]
+    pub referrer: Account<'info, Participant>,
+
+    pub authority: Signer<'info>,
+}
+
+/// Reverses a credited referral, e.g. after the referred purchase was refunded.
+///
+/// The referrer loses the referral from `total_referrals`, the program from `total_referrals_credited`, and the
+/// referrer loses as much of the credited reward as is still pending; anything already claimed stays claimed. The
+/// raw referral counts keep it. A referee still in the program stops counting toward the referrer's
+/// `active_referee_count`, so it no longer holds the referrer in the program. A payout-split share stays with its recipient, and
+/// milestone bonuses already paid are not revoked (nor can they be earned again).
+///
+/// Programs with a `dispute_window_seconds` hold the removed rewards, still committed, while the referrer can
+/// contest the clawback with `contest_clawback`; an uncontested clawback is made final by `finalize_clawback`
+/// once the window closes. Without a window the clawback is final at once and the rewards go back to the vault.
+///
+/// # Arguments
+/// * `ctx` - The context for the ClawbackReferral instruction
+/// * `idempotency_key` - Optional key recorded on the program; a call repeating one of its recent keys fails
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `AlreadyClawedBack` - If the referral was already clawed back, or a clawback of it was overturned
+/// * `InvalidReferralRecord` - If the referral was never counted, e.g. it was rate-limited
+/// * `InvalidReferrer` - If the referrer account is not the current account of the referrer credited on the receipt
+/// * `DuplicateIdempotencyKey` - If the key was used by one of the program's recent authority mutations
+pub fn clawback_referral(
+    ctx: Context<ClawbackReferral>,
+    idempotency_key: Option<[u8; IDEMPOTENCY_KEY_LEN]>,
+) -> Result<()> {
+    let referral_program = &mut ctx.accounts.referral_program;
+    let now = Clock::get()?.unix_timestamp;
+    referral_program.record_authority_action(now)?;
+    referral_program.require_settings_unlocked(now)?;
+    referral_program.record_idempotency_key(idempotency_key)?;
+    let receipt = &mut ctx.accounts.referee_receipt;
+    let referrer = &mut ctx.accounts.referrer;
+    require!(receipt.clawback_status == RefereeReceipt::CLAWBACK_NONE, ReferralError::AlreadyClawedBack);
+
+    let amount = receipt.credited_amount.min(referrer.pending_rewards);
+    referrer.pending_rewards -= amount;
+    referrer.clawed_back = referrer.clawed_back.checked_add(amount).ok_or(ReferralError::NumericOverflow)?;
+    referrer.total_referrals = referrer.total_referrals.saturating_sub(1);
+    if receipt.referee_active {
+        referrer.active_referee_count = referrer.active_referee_count.saturating_sub(1);
+    }
+    receipt.clawed_back = true;
+    receipt.disputed_amount = amount;
+
+    let now = Clock::get()?.unix_timestamp;
+    let referral_program = &mut ctx.accounts.referral_program;
+    referral_program.total_referrals_credited = referral_program.total_referrals_credited.saturating_sub(1);
+    let dispute_window = referral_program.dispute_window_seconds;
+    let contest_deadline = if dispute_window > 0 { now.saturating_add(dispute_window) } else { 0 };
+    receipt.dispute_opened_at = now;
+    receipt.contest_deadline = contest_deadline;
+    receipt.clawback_status = RefereeReceipt::CLAWBACK_OPEN;
+
+    emit!(ReferralClawedBack {
+        referral_program: referral_program.key(),
+        referee: receipt.referee,
+        referrer: referrer.key(),
+        amount,
+        contest_deadline,
+    });
+
+    if dispute_window == 0 {
+        uphold_clawback(referral_program, receipt, false)?;
+    }
+    Ok(())
+}
+
+/// Accounts required for a referrer contesting a clawback of one of its referrals.
+#[derive(Accounts)]
+pub struct ContestClawback<'info> {
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    /// The receipt of the clawed-back referral
+    /// PDA with seeds: ["referee", referral_program.key(), referee_receipt.referee]
+    #[account(
+        mut,
+        seeds = [REFEREE_RECEIPT_SEED, referral_program.key().as_ref(), referee_receipt.referee.as_ref()],
+        bump = referee_receipt.bump,
+    )]
+    pub referee_receipt: Account<'info, RefereeReceipt>,
+
+    /// The participant account credited for the referral, or the account it was rotated to, owned by the signer
+    #[account(
+        constraint = is_credited_referrer(&referrer, &referee_receipt) @ ReferralError::InvalidReferrer,
+        constraint = referrer.owner == user.key() @ ReferralError::InvalidReferrer,
+    )]
+    pub referrer: Account<'info, Participant>,
+
+    pub user: Signer<'info>,
+}
+
+/// Contests an open clawback of one of the signer's referrals, optionally attaching the hash of off-chain
+/// evidence. The authority then rules on it with `resolve_clawback`.
+///
+/// # Arguments
+/// * `ctx` - The context for the ContestClawback instruction
+/// * `evidence_hash` - Hash of the referrer's off-chain evidence, if any
+///
+/// # Errors
+/// * `InvalidReferrer` - If the referrer is not the current account of the referrer credited on the receipt or not
+///   owned by the signer
+/// * `InvalidClawbackState` - If the referral has no open, uncontested clawback
+/// * `ClawbackDeadlinePassed` - If the dispute window has closed
+pub fn contest_clawback(ctx: Context<ContestClawback>, evidence_hash: Option<[u8; 32]>) -> Result<()> {
+    let receipt = &mut ctx.accounts.referee_receipt;
+    require!(receipt.clawback_status == RefereeReceipt::CLAWBACK_OPEN, ReferralError::InvalidClawbackState);
+    let now = Clock::get()?.unix_timestamp;
+    require!(now < receipt.contest_deadline, ReferralError::ClawbackDeadlinePassed);
+
+    receipt.clawback_status = RefereeReceipt::CLAWBACK_CONTESTED;
+    receipt.contested_at = now;
+    receipt.evidence_hash = evidence_hash.unwrap_or_default();
+
+    emit!(ClawbackContested {
+        referral_program: ctx.accounts.referral_program.key(),
+        referee: receipt.referee,
+        referrer: receipt.referrer,
+        evidence_hash: receipt.evidence_hash,
+    });
+    Ok(())
+}
+
+/// Accounts required for the authority ruling on a contested clawback.
+#[derive(Accounts)]
+pub struct ResolveClawback<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    /// The receipt of the contested referral
+    /// PDA with seeds: ["referee", referral_program.key(), referee_receipt.referee]
+    #[account(
+        mut,
+        seeds = [REFEREE_RECEIPT_SEED, referral_program.key().as_ref(), referee_receipt.referee.as_ref()],
+        bump = referee_receipt.bump,
+    )]
+    pub referee_receipt: Account<'info, RefereeReceipt>,
+
+    /// The participant account credited for the referral, or the account it was rotated to
+    #[account(
+        mut,
+        constraint = is_credited_referrer(&referrer, &referee_receipt) @ ReferralError::InvalidReferrer,
+    )]
+    pub referrer: Account<'info, Participant>,
+
+    pub authority: Signer<'info>,
+}
+
+/// Rules on a contested clawback: upholding it releases the held rewards back to the vault, overturning it
+/// restores them and the referral to the referrer.
+///
+/// # Arguments
+/// * `ctx` - The context for the ResolveClawback instruction
+/// * `uphold` - Whether the clawback stands
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `InvalidReferrer` - If the referrer account is not the current account of the referrer credited on the receipt
+/// * `InvalidClawbackState` - If the clawback was not contested or is already resolved
+/// * `ClawbackDeadlinePassed` - If the resolution timeout has passed; only `default_resolve` can rule now
+pub fn resolve_clawback(ctx: Context<ResolveClawback>, uphold: bool) -> Result<()> {
+    let receipt = &mut ctx.accounts.referee_receipt;
+    require!(receipt.clawback_status == RefereeReceipt::CLAWBACK_CONTESTED, ReferralError::InvalidClawbackState);
+    let now = Clock::get()?.unix_timestamp;
+    require!(now < receipt.resolution_deadline(), ReferralError::ClawbackDeadlinePassed);
+    ctx.accounts.referral_program.record_authority_action(now)?;
+
+    if uphold {
+        uphold_clawback(&mut ctx.accounts.referral_program, receipt, false)
+    } else {
+        overturn_clawback(&mut ctx.accounts.referral_program, receipt, &mut ctx.accounts.referrer, false)
+    }
+}
+
+/// Accounts required for settling a clawback whose deadline passed; anyone can submit it.
+#[derive(Accounts)]
+pub struct SettleClawback<'info> {
+    #[account(mut)]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    /// The receipt of the clawed-back referral
+    /// PDA with seeds: ["referee", referral_program.key(), referee_receipt.referee]
+    #[account(
+        mut,
+        seeds = [REFEREE_RECEIPT_SEED, referral_program.key().as_ref(), referee_receipt.referee.as_ref()],
+        bump = referee_receipt.bump,
+    )]
+    pub referee_receipt: Account<'info, RefereeReceipt>,
+
+    /// The participant account credited for the referral, or the account it was rotated to
+    #[account(
+        mut,
+        constraint = is_credited_referrer(&referrer, &referee_receipt) @ ReferralError::InvalidReferrer,
+    )]
+    pub referrer: Account<'info, Participant>,
+}
+
+/// Makes an uncontested clawback final once its dispute window has closed, releasing the held rewards back to
+/// the vault.
+///
+/// # Arguments
+/// * `ctx` - The context for the SettleClawback instruction
+///
+/// # Errors
+/// * `InvalidReferrer` - If the referrer account is not the current account of the referrer credited on the receipt
+/// * `InvalidClawbackState` - If the referral has no open, uncontested clawback
+/// * `ClawbackDeadlineNotReached` - If the referrer can still contest the clawback
+pub fn finalize_clawback(ctx: Context<SettleClawback>) -> Result<()> {
+    let receipt = &mut ctx.accounts.referee_receipt;
+    require!(receipt.clawback_status == RefereeReceipt::CLAWBACK_OPEN, ReferralError::InvalidClawbackState);
+    let now = Clock::get()?.unix_timestamp;
+    require!(now >= receipt.contest_deadline, ReferralError::ClawbackDeadlineNotReached);
+    uphold_clawback(&mut ctx.accounts.referral_program, receipt, true)
+}
+
+/// Rules a contested clawback for the referrer once the authority let `CLAWBACK_RESOLUTION_TIMEOUT` pass
+/// without resolving it.
+///
+/// # Arguments
+/// * `ctx` - The context for the SettleClawback instruction
+///
+/// # Errors
+/// * `InvalidReferrer` - If the referrer account is not the current account of the referrer credited on the receipt
+/// * `InvalidClawbackState` - If the clawback was not contested or is already resolved
+/// * `ClawbackDeadlineNotReached` - If the authority can still resolve the clawback
+pub fn default_resolve(ctx: Context<SettleClawback>) -> Result<()> {
+    let receipt = &mut ctx.accounts.referee_receipt;
+    require!(receipt.clawback_status == RefereeReceipt::CLAWBACK_CONTESTED, ReferralError::InvalidClawbackState);
+    let now = Clock::get()?.unix_timestamp;
+    require!(now >= receipt.resolution_deadline(), ReferralError::ClawbackDeadlineNotReached);
+    overturn_clawback(&mut ctx.accounts.referral_program, receipt, &mut ctx.accounts.referrer, true)
+}
+
+/// Returns true if `referrer` is the live participant account of the referrer credited on `receipt`: the account the
+/// referral was credited to or, once that was rotated to a new owner, the account it was rotated to.
+fn is_credited_referrer(referrer: &Account<Participant>, receipt: &RefereeReceipt) -> bool {
+    referrer.rotated_to.is_none()
+        && (referrer.key() == receipt.referrer || referrer.rotated_from == Some(receipt.referrer))
+}
+
+/// Makes a clawback final: the held rewards stop being committed and become available for other rewards.
+fn uphold_clawback(
+    referral_program: &mut Account<ReferralProgram>,
+    receipt: &mut RefereeReceipt,
+    by_default: bool,
+) -> Result<()> {
+    let amount = receipt.disputed_amount;
+    referral_program.total_committed =
+        referral_program.total_committed.checked_sub(amount).ok_or(ReferralError::NumericOverflow)?;
+    receipt.clawback_status = RefereeReceipt::CLAWBACK_UPHELD;
+    restore_referral_funding(referral_program)?;
+
+    emit!(ClawbackResolved {
+        referral_program: referral_program.key(),
+        referee: receipt.referee,
+        referrer: receipt.referrer,
+        amount,
+        upheld: true,
+        by_default,
+    });
+    Ok(())
+}
+
+/// Reverses a clawback: the referrer and the program get back the referral, the referrer the held rewards and the
+/// referee if it is still in the program, and the receipt counts again.
+fn overturn_clawback(
+    referral_program: &mut Account<ReferralProgram>,
+    receipt: &mut RefereeReceipt,
+    referrer: &mut Participant,
+    by_default: bool,
+) -> Result<()> {
+    let amount = receipt.disputed_amount;
+    referrer.pending_rewards = referrer.pending_rewards.checked_add(amount).ok_or(ReferralError::NumericOverflow)?;
+    referrer.clawed_back = referrer.clawed_back.checked_sub(amount).ok_or(ReferralError::NumericOverflow)?;
+    referrer.total_referrals = referrer.total_referrals.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+    if receipt.referee_active {
+        referrer.active_referee_count =
+            referrer.active_referee_count.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+    }
+    referral_program.total_referrals_credited =
+        referral_program.total_referrals_credited.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+    receipt.clawed_back = false;
+    receipt.clawback_status = RefereeReceipt::CLAWBACK_OVERTURNED;
+
+    emit!(ClawbackResolved {
+        referral_program: referral_program.key(),
+        referee: receipt.referee,
+        referrer: receipt.referrer,
+        amount,
+        upheld: false,
+        by_default,
+    });
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use crate::{constants::MAX_CLEANUP_BATCH, error::ReferralError, events::*, state::*};
+use anchor_lang::{prelude::*, Discriminator};
+
+/// Accounts required for the permissionless `cleanup` instruction. The accounts to close follow in the remaining
+/// accounts, each followed by the wallet that paid its rent.
+#[derive(Accounts)]
+pub struct Cleanup<'info> {
+    #[account(mut)]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    /// Anyone; receives the cleanup bounty
+    #[account(mut)]
+    pub caller: Signer<'info>,
+}
+
+/// An auxiliary account `cleanup` knows how to close.
+enum ClosableAccount<'info> {
+    Invite(Account<'info, Invite>),
+    MatchOffer(Account<'info, MatchOffer>),
+    BoostEscrow(Account<'info, BoostEscrow>),
+}
+
+impl<'info> ClosableAccount<'info> {
+    /// Loads `info` if it is an auxiliary account of `program` that has served its purpose.
+    ///
+    /// A claimed invite has admitted its one wallet and an exhausted match offer has nothing left to pay. Once the
+    /// program is closing, every match offer and boost escrow has served its purpose too: no referral can be
+    /// credited any more, and their funds could not be reached after the program is gone. Referee receipts, which
+    /// also carry their clawback disputes, are never closable: they are what keeps a wallet from being credited
+    /// twice.
+    fn load(info: &'info AccountInfo<'info>, program: Pubkey, closing: bool) -> Result<Option<Self>> {
+        if info.owner != &crate::ID || info.data_len() < 8 {
+            return Ok(None);
+        }
+        let discriminator: [u8; 8] = info.try_borrow_data()?[..8].try_into().unwrap();
+        let closable = if discriminator == Invite::DISCRIMINATOR {
+            let invite = Account::<Invite>::try_from(info)?;
+            (invite.program == program && invite.claimed).then_some(ClosableAccount::Invite(invite))
+        } else if discriminator == MatchOffer::DISCRIMINATOR {
+            let match_offer = Account::<MatchOffer>::try_from(info)?;
+            (match_offer.program == program && (match_offer.balance == 0 || closing))
+                .then_some(ClosableAccount::MatchOffer(match_offer))
+        } else if discriminator == BoostEscrow::DISCRIMINATOR {
+            let boost_escrow = Account::<BoostEscrow>::try_from(info)?;
+            (boost_escrow.program == program && closing).then_some(ClosableAccount::BoostEscrow(boost_escrow))
+        } else {
+            None
+        };
+        Ok(closable)
+    }
+
+    /// The wallet the account's rent goes back to
+    fn rent_payer(&self) -> Pubkey {
+        match self {
+            ClosableAccount::Invite(invite) => invite.rent_payer,
+            ClosableAccount::MatchOffer(match_offer) => match_offer.sponsor,
+            ClosableAccount::BoostEscrow(boost_escrow) => boost_escrow.owner,
+        }
+    }
+
+    /// The funds the account escrows for its owner, which go back to them in full
+    fn escrowed(&self) -> u64 {
+        match self {
+            ClosableAccount::Invite(_) => 0,
+            ClosableAccount::MatchOffer(match_offer) => match_offer.balance,
+            ClosableAccount::BoostEscrow(boost_escrow) => boost_escrow.balance,
+        }
+    }
+
+    /// Drops the account from the program's count of open accounts of its kind
+    fn release(&self, referral_program: &mut ReferralProgram) {
+        match self {
+            ClosableAccount::Invite(_) => {}
+            ClosableAccount::MatchOffer(_) => {
+                referral_program.open_match_offers = referral_program.open_match_offers.saturating_sub(1)
+            }
+            ClosableAccount::BoostEscrow(_) => {
+                referral_program.open_boost_escrows = referral_program.open_boost_escrows.saturating_sub(1)
+            }
+        }
+    }
+
+    /// Closes the account, sending its lamports to `destination`
+    fn close(self, destination: AccountInfo<'info>) -> Result<()> {
+        match self {
+            ClosableAccount::Invite(invite) => invite.close(destination),
+            ClosableAccount::MatchOffer(match_offer) => match_offer.close(destination),
+            ClosableAccount::BoostEscrow(boost_escrow) => boost_escrow.close(destination),
+        }
+    }
+}
+
+/// Returns the caller's share of `lamports` reclaimed by `cleanup` at a bounty of `bounty_bps` basis points.
+pub fn cleanup_bounty(lamports: u64, bounty_bps: u16) -> u64 {
+    (u128::from(lamports) * u128::from(bounty_bps) / 10_000) as u64
+}
+
+/// Closes spent auxiliary accounts of the program, paying the caller a share of their rent and returning the
+/// rest to whoever paid it.
+///
+/// Claimed invites and exhausted match offers can be closed, and once the program is closing its remaining match
+/// offers and boost escrows too, which `close_referral_program` waits for. The caller receives the program's
+/// `cleanup_bounty_bps` of each account's rent and the rent payer recorded on the account the remainder, along
+/// with any funds the account still escrows. An account that is not closable fails the whole batch, so nothing is
+/// closed.
+///
+/// # Arguments
+/// * `ctx` - The context for the Cleanup instruction, with each account to close followed by its rent payer in the
+///   remaining accounts
+///
+/// # Errors
+/// * `InvalidCleanupBatch` - If the batch is empty, holds more than `MAX_CLEANUP_BATCH` accounts, repeats one or
+///   pairs one with a wallet other than its rent payer
+/// * `AccountNotClosable` - If an account is not a closable auxiliary account of the program; the log names its
+///   position in the batch
+pub fn cleanup<'info>(ctx: Context<'_, '_, 'info, 'info, Cleanup<'info>>) -> Result<()> {
+    let batch = ctx.remaining_accounts;
+    require!(
+        !batch.is_empty() && batch.len().is_multiple_of(2) && batch.len() <= 2 * MAX_CLEANUP_BATCH,
+        ReferralError::InvalidCleanupBatch
+    );
+
+    let program_key = ctx.accounts.referral_program.key();
+    let closing = ctx.accounts.referral_program.is_closing();
+    let bounty_bps = ctx.accounts.referral_program.cleanup_bounty_bps();
+    let caller = ctx.accounts.caller.to_account_info();
+    let (mut bounty, mut refunded) = (0u64, 0u64);
+    for (index, pair) in batch.chunks_exact(2).enumerate() {
+        let (account_info, rent_payer_info) = (&pair[0], &pair[1]);
+        require!(
+            !batch[..2 * index].iter().step_by(2).any(|previous| previous.key == account_info.key),
+            ReferralError::InvalidCleanupBatch
+        );
+        let Some(account) = ClosableAccount::load(account_info, program_key, closing)? else {
+            msg!("Account {} at index {} is not closable", account_info.key(), index);
+            return err!(ReferralError::AccountNotClosable);
+        };
+        require_keys_eq!(rent_payer_info.key(), account.rent_payer(), ReferralError::InvalidCleanupBatch);
+
+        let lamports = account_info.lamports();
+        let share = cleanup_bounty(lamports.saturating_sub(account.escrowed()), bounty_bps);
+        account_info.sub_lamports(share)?;
+        caller.add_lamports(share)?;
+        account.release(&mut ctx.accounts.referral_program);
+        account.close(rent_payer_info.clone())?;
+
+        bounty = bounty.checked_add(share).ok_or(ReferralError::NumericOverflow)?;
+        refunded = refunded.checked_add(lamports - share).ok_or(ReferralError::NumericOverflow)?;
+    }
+
+    emit!(AccountsCleanedUp {
+        referral_program: program_key,
+        caller: caller.key(),
+        accounts_closed: (batch.len() / 2) as u8,
+        bounty,
+        refunded,
+    });
+    Ok(())
+}
@@ -0,0 +1,276 @@
+use crate::{
+    constants::{AUTHORITY_META_SEED, CLOSURE_GRACE_PERIOD, FINAL_REPORT_SEED, SPONSOR_VAULT_SEED},
+    error::ReferralError,
+    instructions::{create_aux_account, RentPayer, VAULT_SEED},
+    state::*,
+};
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
+
+/// Accounts required for closing a referral program.
+#[derive(Accounts)]
+pub struct CloseReferralProgram<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        mut,
+        seeds = [b"eligibility_criteria", referral_program.key().as_ref()],
+        bump
+    )]
+    pub eligibility_criteria: Account<'info, EligibilityCriteria>,
+
+    /// PDA with seeds: ["vault", referral_program.key()]
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, referral_program.key().as_ref()],
+        bump,
+    )]
+    pub vault: SystemAccount<'info>,
+
+    /// The vault that pays sponsored rent, swept to the authority when the closure is finalized
+    /// PDA with seeds: ["sponsor_vault", referral_program.key()]
+    #[account(
+        mut,
+        seeds = [SPONSOR_VAULT_SEED, referral_program.key().as_ref()],
+        bump,
+    )]
+    pub sponsor_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [AUTHORITY_META_SEED, authority.key().as_ref()],
+        bump = authority_meta.bump,
+    )]
+    pub authority_meta: Account<'info, AuthorityMeta>,
+
+    /// CHECK: The final report PDA, created in the final phase; checked by seeds and required to be uncreated
+    #[account(
+        mut,
+        seeds = [FINAL_REPORT_SEED, referral_program.key().as_ref()],
+        bump
+    )]
+    pub final_report: UncheckedAccount<'info>,
+
+    /// Receives the vault's lamports and the closed accounts' rent, and pays the final report's rent
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// What a call to `close_referral_program` does for a program in a given state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClosurePhase {
+    /// No closure is pending: start the grace period
+    Request,
+    /// The grace period has elapsed: sweep the vaults and close the accounts
+    Finalize,
+}
+
+/// Decides the phase of a closure at `now`, rejecting a final closure of a program that is still running or that
+/// would orphan rewards, a token vault, contest prizes, a queued withdrawal or the accounts escrowing boosts and
+/// matches for it.
+pub fn closure_phase(referral_program: &ReferralProgram, now: i64) -> Result<ClosurePhase> {
+    let Some(effective_at) = referral_program.closure_effective_at() else {
+        return Ok(ClosurePhase::Request);
+    };
+    require!(now >= effective_at, ReferralError::ClosureGracePeriodActive);
+    require!(!referral_program.is_active || referral_program.has_ended(now), ReferralError::ProgramStillActive);
+    require!(referral_program.total_committed == 0, ReferralError::OutstandingRewards);
+    require!(!referral_program.frozen, ReferralError::ProgramFrozen);
+    require!(!referral_program.token_vault_initialized, ReferralError::TokenVaultStillOpen);
+    require!(referral_program.contest_escrowed == 0, ReferralError::ContestPrizesOutstanding);
+    require!(!referral_program.withdrawal_pending, ReferralError::WithdrawalPending);
+    require!(
+        referral_program.open_boost_escrows == 0 && referral_program.open_match_offers == 0,
+        ReferralError::EscrowsOutstanding
+    );
+    Ok(ClosurePhase::Finalize)
+}
+
+/// Closes a referral program in two phases.
+///
+/// The first call only records the request: joins and deposits stop, claims continue, and `cancel_closure`
+/// restores normal operation. Meanwhile anyone can close the program's boost escrows and match offers with
+/// `cleanup`, refunding their owners. Once `CLOSURE_GRACE_PERIOD` has elapsed and the program has been paused or
+/// has ended, a second call sweeps the vault and the sponsor vault to the authority, writes the program's
+/// `FinalReport` and closes the program and criteria accounts, returning their rent.
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `ClosureGracePeriodActive` - If closure was requested less than `CLOSURE_GRACE_PERIOD` ago
+/// * `ProgramStillActive` - If the grace period has elapsed but the program is active and has not ended
+/// * `OutstandingRewards` - If credited rewards have not been claimed or clawed back
+/// * `ProgramFrozen` - If the guardian froze the program
+/// * `TokenVaultStillOpen` - If the program's token vault has not been closed with `close_token_vault`
+/// * `ContestPrizesOutstanding` - If contest prizes have not been claimed, or the contest escrow's other funds
+///   not withdrawn with `withdraw_contest_leftover`
+/// * `WithdrawalPending` - If a queued withdrawal has not been executed or cancelled
+/// * `EscrowsOutstanding` - If boost escrows or match offers of the program have not been closed with `cleanup`
+/// * `FinalReportExists` - If a program closed earlier at the same address already left a report
+pub fn close_referral_program(ctx: Context<CloseReferralProgram>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    ctx.accounts.referral_program.record_authority_action(now)?;
+    ctx.accounts.referral_program.require_settings_unlocked(now)?;
+    if closure_phase(&ctx.accounts.referral_program, now)? == ClosurePhase::Request {
+        let referral_program = &mut ctx.accounts.referral_program;
+        referral_program.closure_requested_at = now;
+        msg!(
+            "Closure of referral program {} requested; effective at {}",
+            referral_program.key(),
+            now.saturating_add(CLOSURE_GRACE_PERIOD)
+        );
+        return Ok(());
+    }
+    let accounts = &mut *ctx.accounts;
+    finalize_closure(
+        ClosureAccounts {
+            referral_program: &mut accounts.referral_program,
+            eligibility_criteria: &mut accounts.eligibility_criteria,
+            vault: (accounts.vault.to_account_info(), ctx.bumps.vault),
+            sponsor_vault: (accounts.sponsor_vault.to_account_info(), ctx.bumps.sponsor_vault),
+            authority_meta: &mut accounts.authority_meta,
+            final_report: (accounts.final_report.to_account_info(), ctx.bumps.final_report),
+            authority: accounts.authority.to_account_info(),
+            report_payer: accounts.authority.to_account_info(),
+            system_program: accounts.system_program.to_account_info(),
+        },
+        now,
+    )
+}
+
+/// The accounts the final phase of a closure touches, whoever triggers it.
+pub struct ClosureAccounts<'a, 'info> {
+    pub referral_program: &'a mut Account<'info, ReferralProgram>,
+    pub eligibility_criteria: &'a mut Account<'info, EligibilityCriteria>,
+    /// The SOL vault PDA and its bump
+    pub vault: (AccountInfo<'info>, u8),
+    /// The sponsor vault PDA and its bump
+    pub sponsor_vault: (AccountInfo<'info>, u8),
+    pub authority_meta: &'a mut Account<'info, AuthorityMeta>,
+    /// The final report PDA and its bump
+    pub final_report: (AccountInfo<'info>, u8),
+    /// The program authority, receiving the vaults' lamports and the closed accounts' rent
+    pub authority: AccountInfo<'info>,
+    /// Pays the final report's rent; must have signed
+    pub report_payer: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+}
+
+/// Sweeps the vault and the sponsor vault to the authority, writes the program's final report and closes the
+/// program and criteria accounts, once `closure_phase` has returned `ClosurePhase::Finalize`.
+///
+/// # Errors
+/// * `FinalReportExists` - If a program closed earlier at the same address already left a report
+pub fn finalize_closure(accounts: ClosureAccounts, now: i64) -> Result<()> {
+    let (final_report, _) = &accounts.final_report;
+    require!(final_report.data_is_empty(), ReferralError::FinalReportExists);
+
+    // Sweep whatever the vaults hold back to the authority, an unreleased insurance reserve included; neither can
+    // be withdrawn from once the program account is gone
+    let reserved_balance = accounts.referral_program.reserved_balance;
+    let program_key = accounts.referral_program.key();
+    let vault_balance = sweep_to_authority(&accounts, &accounts.vault, VAULT_SEED)?;
+    let sponsor_balance = sweep_to_authority(&accounts, &accounts.sponsor_vault, SPONSOR_VAULT_SEED)?;
+
+    write_final_report(&accounts, now)?;
+    accounts.authority_meta.release_program();
+    accounts.eligibility_criteria.close(accounts.authority.clone())?;
+    accounts.referral_program.close(accounts.authority.clone())?;
+
+    msg!(
+        "Closed referral program {}, swept {} lamports ({} of them reserved) and {} from the sponsor vault",
+        program_key,
+        vault_balance,
+        reserved_balance,
+        sponsor_balance
+    );
+    Ok(())
+}
+
+/// Moves every lamport of `vault`, a PDA of the program derived from `seed` and the program key, to the authority,
+/// returning the amount moved.
+fn sweep_to_authority<'info>(
+    accounts: &ClosureAccounts<'_, 'info>,
+    vault: &(AccountInfo<'info>, u8),
+    seed: &[u8],
+) -> Result<u64> {
+    let (vault, vault_bump) = vault;
+    let balance = vault.lamports();
+    if balance > 0 {
+        let program_key = accounts.referral_program.key();
+        let seeds = &[seed, program_key.as_ref(), &[*vault_bump]];
+        transfer(
+            CpiContext::new_with_signer(
+                accounts.system_program.clone(),
+                Transfer { from: vault.clone(), to: accounts.authority.clone() },
+                &[&seeds[..]],
+            ),
+            balance,
+        )?;
+    }
+    Ok(balance)
+}
+
+/// Creates the program's final report, its rent paid by the report payer, and records the program's figures in it.
+fn write_final_report(accounts: &ClosureAccounts, now: i64) -> Result<()> {
+    let referral_program = &accounts.referral_program;
+    let criteria = &accounts.eligibility_criteria;
+    let program_key = referral_program.key();
+    let (report_info, report_bump) = &accounts.final_report;
+    create_aux_account(
+        report_info,
+        &[FINAL_REPORT_SEED, program_key.as_ref(), &[*report_bump]],
+        8 + FinalReport::SIZE,
+        &RentPayer::Signer(&accounts.report_payer),
+        &accounts.system_program,
+    )?;
+
+    let report = FinalReport {
+        referral_program: program_key,
+        authority: referral_program.authority,
+        token_mint: referral_program.token_mint,
+        total_deposited: referral_program.total_deposited,
+        total_distributed: referral_program.total_rewards_distributed,
+        total_fees: referral_program.total_fees_paid,
+        total_participants: referral_program.total_participants,
+        total_referrals_credited: referral_program.total_referrals_credited,
+        total_referrals_raw: referral_program.total_referrals_raw,
+        program_start_time: criteria.program_start_time,
+        program_end_time: criteria.program_end_time,
+        closed_at: now,
+        bump: *report_bump,
+    };
+    report.try_serialize(&mut &mut report_info.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+/// Accounts required for cancelling a pending closure.
+#[derive(Accounts)]
+pub struct CancelClosure<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+    pub authority: Signer<'info>,
+}
+
+/// Cancels a requested closure, restoring normal operation.
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `NoClosurePending` - If no closure was requested
+pub fn cancel_closure(ctx: Context<CancelClosure>) -> Result<()> {
+    let referral_program = &mut ctx.accounts.referral_program;
+    referral_program.record_authority_action(Clock::get()?.unix_timestamp)?;
+    require!(referral_program.is_closing(), ReferralError::NoClosurePending);
+    referral_program.closure_requested_at = 0;
+    Ok(())
+}
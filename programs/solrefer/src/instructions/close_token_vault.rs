@@ -0,0 +1,141 @@
+use crate::{
+    error::ReferralError,
+    instructions::{check_referral_funding, TOKEN_VAULT_SEED},
+    state::*,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, CloseAccount, Token, TokenAccount};
+
+/// Accounts required for closing the token vault of a wound-down token program.
+#[derive(Accounts)]
+pub struct CloseTokenVault<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+        constraint = referral_program.token_mint != Pubkey::default() @ ReferralError::InvalidTokenMint,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        seeds = [b"eligibility_criteria", referral_program.key().as_ref()],
+        bump
+    )]
+    pub eligibility_criteria: Account<'info, EligibilityCriteria>,
+
+    /// PDA with seeds: ["token_vault", referral_program.key()]
+    #[account(
+        mut,
+        seeds = [TOKEN_VAULT_SEED, referral_program.key().as_ref()],
+        bump,
+        token::authority = referral_program,
+    )]
+    pub token_vault: Account<'info, TokenAccount>,
+
+    /// Authority token account receiving any tokens left in the vault; required when the vault is not empty
+    #[account(
+        mut,
+        constraint = destination_token_account.mint == referral_program.token_mint &&
+                     destination_token_account.owner == authority.key() @ ReferralError::InvalidTokenAccounts
+    )]
+    pub destination_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Receives the vault's rent
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Checks that a token program can give up its vault: it is inactive or past its end time, owes no credited
+/// rewards, and its vault is empty unless the remaining tokens are being swept.
+///
+/// An open-ended program (`program_end_time` of `None`) never passes its end time, so it must be deactivated.
+pub fn check_token_vault_closable(
+    referral_program: &ReferralProgram,
+    program_end_time: Option<i64>,
+    now: i64,
+    vault_balance: u64,
+    sweeping: bool,
+) -> Result<()> {
+    let ended = program_end_time.is_some_and(|end| end <= now);
+    require!(!referral_program.is_active || ended, ReferralError::ProgramStillActive);
+    require!(referral_program.total_committed == 0, ReferralError::OutstandingRewards);
+    require!(!referral_program.frozen, ReferralError::ProgramFrozen);
+    require!(vault_balance == 0 || sweeping, ReferralError::TokenVaultNotEmpty);
+    Ok(())
+}
+
+/// Sweeps any remaining tokens, including an unreleased insurance reserve, to the authority and closes the
+/// token vault, returning its rent.
+///
+/// Afterwards the program keeps its mint and decimals, so its totals stay in token units, and is marked
+/// `token_vault_closed`: the SOL paths still reject it as a token program, and its vault cannot be reopened.
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `InvalidTokenMint` - If the program is not a token program
+/// * `ProgramStillActive` - If the program is active and has not ended
+/// * `OutstandingRewards` - If credited rewards have not been claimed or clawed back
+/// * `TokenVaultNotEmpty` - If the vault holds tokens and no destination account was provided
+/// * `InvalidTokenAccounts` - If the destination is not an authority token account of the program's mint
+pub fn close_token_vault(ctx: Context<CloseTokenVault>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    ctx.accounts.referral_program.record_authority_action(now)?;
+    let vault_balance = ctx.accounts.token_vault.amount;
+    check_token_vault_closable(
+        &ctx.accounts.referral_program,
+        ctx.accounts.eligibility_criteria.program_end_time,
+        now,
+        vault_balance,
+        ctx.accounts.destination_token_account.is_some(),
+    )?;
+
+    let referral_program = &ctx.accounts.referral_program;
+    let index_bytes = referral_program.program_index.to_le_bytes();
+    let seeds = referral_program.signer_seeds(&index_bytes);
+    let signer = &[&seeds[..]];
+
+    if vault_balance > 0 {
+        let destination = ctx.accounts.destination_token_account.as_ref().ok_or(ReferralError::TokenVaultNotEmpty)?;
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.token_vault.to_account_info(),
+                    to: destination.to_account_info(),
+                    authority: referral_program.to_account_info(),
+                },
+                signer,
+            ),
+            vault_balance,
+        )?;
+    }
+
+    token::close_account(CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        CloseAccount {
+            account: ctx.accounts.token_vault.to_account_info(),
+            destination: ctx.accounts.authority.to_account_info(),
+            authority: referral_program.to_account_info(),
+        },
+        signer,
+    ))?;
+
+    let referral_program = &mut ctx.accounts.referral_program;
+    referral_program.token_vault_initialized = false;
+    referral_program.token_vault_closed = true;
+    referral_program.setup_state &= !ReferralProgram::SETUP_VAULT_INITIALIZED;
+    referral_program.total_available = 0;
+    referral_program.total_available_ui = 0;
+    check_referral_funding(referral_program)?;
+    let reserved_balance = std::mem::take(&mut referral_program.reserved_balance);
+    referral_program.is_active = false;
+
+    msg!(
+        "Closed token vault of referral program {}, swept {} tokens ({} of them reserved)",
+        referral_program.key(),
+        vault_balance,
+        reserved_balance
+    );
+    Ok(())
+}
@@ -0,0 +1,134 @@
+//! Checks that a wallet holds an NFT of a verified Metaplex collection.
+//!
+//! The metadata account is parsed by hand rather than through the Metaplex crate: only the mint and the
+//! trailing collection field are read, skipping the variable-length fields in between.
+use crate::{
+    constants::{METADATA_SEED, TOKEN_METADATA_PROGRAM_ID},
+    error::ReferralError,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
+
+/// The `key` byte of a Metaplex `MetadataV1` account.
+pub const METADATA_V1_KEY: u8 = 4;
+
+/// The fields of a Metaplex metadata account a collection gate reads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NftMetadata {
+    /// The NFT's mint
+    pub mint: Pubkey,
+    /// The collection the NFT claims membership of and whether the collection authority verified it
+    pub collection: Option<(Pubkey, bool)>,
+}
+
+/// Sequential reader over Borsh-encoded account data that fails on truncated input.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.data.len() < len {
+            return None;
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4).map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn pubkey(&mut self) -> Option<Pubkey> {
+        self.take(32).map(|bytes| Pubkey::try_from(bytes).unwrap())
+    }
+
+    fn skip_string(&mut self) -> Option<()> {
+        let len = self.u32()?;
+        self.take(len as usize).map(|_| ())
+    }
+
+    /// Reads the tag of an `Option<T>`, returning whether it is `Some`.
+    fn option(&mut self) -> Option<bool> {
+        match self.u8()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+
+    /// Skips an `Option<T>` whose payload is `len` bytes.
+    fn skip_option(&mut self, len: usize) -> Option<()> {
+        if self.option()? {
+            self.take(len)?;
+        }
+        Some(())
+    }
+}
+
+/// Parses the mint and collection of a `MetadataV1` account, or returns `None` if the data is not one.
+pub fn parse_nft_metadata(data: &[u8]) -> Option<NftMetadata> {
+    let mut reader = Reader { data };
+    if reader.u8()? != METADATA_V1_KEY {
+        return None;
+    }
+    reader.take(32)?; // update_authority
+    let mint = reader.pubkey()?;
+    reader.skip_string()?; // name
+    reader.skip_string()?; // symbol
+    reader.skip_string()?; // uri
+    reader.take(2)?; // seller_fee_basis_points
+    if reader.option()? {
+        // creators: a vector of (address, verified, share)
+        let count = reader.u32()?;
+        reader.take(count as usize * 34)?;
+    }
+    reader.take(2)?; // primary_sale_happened, is_mutable
+    reader.skip_option(1)?; // edition_nonce
+    reader.skip_option(1)?; // token_standard
+    let collection = if reader.option()? {
+        let verified = reader.u8()? != 0;
+        Some((reader.pubkey()?, verified))
+    } else {
+        None
+    };
+    Some(NftMetadata { mint, collection })
+}
+
+/// Requires `nft` to be a single NFT held by `holder` whose `metadata` names it a verified member of
+/// `collection`.
+///
+/// # Errors
+/// * `CollectionNftRequired` - If either account is missing
+/// * `InvalidCollectionMetadata` - If `metadata` is not the Metaplex metadata PDA of the NFT's mint
+/// * `CollectionNotVerified` - If the NFT is not a verified member of `collection`
+/// * `CollectionNftNotHeld` - If the token account does not hold exactly one of the NFT or is not `holder`'s
+pub fn require_collection_nft(
+    metadata: Option<&AccountInfo>,
+    nft: Option<&Account<TokenAccount>>,
+    collection: &Pubkey,
+    holder: &Pubkey,
+) -> Result<()> {
+    let (Some(metadata), Some(nft)) = (metadata, nft) else {
+        return err!(ReferralError::CollectionNftRequired);
+    };
+    require!(nft.owner == *holder && nft.amount == 1, ReferralError::CollectionNftNotHeld);
+
+    let (expected_metadata, _) = Pubkey::find_program_address(
+        &[METADATA_SEED, TOKEN_METADATA_PROGRAM_ID.as_ref(), nft.mint.as_ref()],
+        &TOKEN_METADATA_PROGRAM_ID,
+    );
+    require!(
+        metadata.key() == expected_metadata && *metadata.owner == TOKEN_METADATA_PROGRAM_ID,
+        ReferralError::InvalidCollectionMetadata
+    );
+    let parsed = parse_nft_metadata(&metadata.try_borrow_data()?).ok_or(ReferralError::InvalidCollectionMetadata)?;
+    require!(parsed.mint == nft.mint, ReferralError::InvalidCollectionMetadata);
+    require!(parsed.collection == Some((*collection, true)), ReferralError::CollectionNotVerified);
+    Ok(())
+}
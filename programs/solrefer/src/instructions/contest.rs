@@ -0,0 +1,326 @@
+use crate::{
+    constants::{CONTEST_ESCROW_SEED, CONTEST_SEED, MAX_CONTEST_DISPUTE_WINDOW, MAX_CONTEST_PRIZES},
+    error::ReferralError,
+    events::ContestFinalized,
+    state::*,
+    validation::require_nonzero_amount,
+};
+use anchor_lang::{
+    prelude::*,
+    system_program::{self, Transfer},
+};
+
+/// Accounts required for configuring a contest.
+#[derive(Accounts)]
+pub struct ConfigureContest<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    /// PDA with seeds: ["contest", referral_program.key()]
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + Contest::SIZE,
+        seeds = [CONTEST_SEED, referral_program.key().as_ref()],
+        bump
+    )]
+    pub contest: Account<'info, Contest>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Sets the prizes and dispute window of the program's contest; allowed until the contest is settled.
+///
+/// # Arguments
+/// * `ctx` - The context for the ConfigureContest instruction
+/// * `prizes` - Prize for each rank, best first; unused ranks are zero and must come last
+/// * `dispute_window` - Seconds between finalization and prizes becoming claimable
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `ContestAlreadySettled` - If the contest was already finalized
+/// * `InvalidContestConfig` - If the prizes or dispute window are invalid
+pub fn configure_contest(
+    ctx: Context<ConfigureContest>,
+    prizes: [u64; MAX_CONTEST_PRIZES],
+    dispute_window: i64,
+) -> Result<()> {
+    ctx.accounts.referral_program.record_authority_action(Clock::get()?.unix_timestamp)?;
+    let contest = &mut ctx.accounts.contest;
+    require!(!contest.settled, ReferralError::ContestAlreadySettled);
+    validate_contest_prizes(&prizes)?;
+    require!((0..=MAX_CONTEST_DISPUTE_WINDOW).contains(&dispute_window), ReferralError::InvalidContestConfig);
+
+    contest.program = ctx.accounts.referral_program.key();
+    contest.prizes = prizes;
+    contest.dispute_window = dispute_window;
+    contest.bump = ctx.bumps.contest;
+    Ok(())
+}
+
+/// Accounts required for funding the contest prize escrow.
+#[derive(Accounts)]
+pub struct FundContest<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        mut,
+        seeds = [CONTEST_SEED, referral_program.key().as_ref()],
+        bump = contest.bump,
+    )]
+    pub contest: Account<'info, Contest>,
+
+    /// The escrow holding the prizes
+    /// PDA with seeds: ["contest_escrow", referral_program.key()]
+    #[account(
+        mut,
+        seeds = [CONTEST_ESCROW_SEED, referral_program.key().as_ref()],
+        bump,
+    )]
+    pub escrow: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Deposits SOL into the contest prize escrow. The program cannot be closed until the deposit has been paid out as
+/// prizes or withdrawn with `withdraw_contest_leftover`.
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `InsufficientDeposit` - If the amount is zero
+/// * `ContestAlreadySettled` - If the contest was already finalized
+pub fn fund_contest(ctx: Context<FundContest>, amount: u64) -> Result<()> {
+    require_nonzero_amount(amount, ReferralError::InsufficientDeposit)?;
+    require!(!ctx.accounts.contest.settled, ReferralError::ContestAlreadySettled);
+    ctx.accounts.referral_program.record_authority_action(Clock::get()?.unix_timestamp)?;
+
+    system_program::transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            Transfer { from: ctx.accounts.authority.to_account_info(), to: ctx.accounts.escrow.to_account_info() },
+        ),
+        amount,
+    )?;
+
+    let contest = &mut ctx.accounts.contest;
+    contest.funded_amount = contest.funded_amount.checked_add(amount).ok_or(ReferralError::NumericOverflow)?;
+    let referral_program = &mut ctx.accounts.referral_program;
+    referral_program.contest_escrowed =
+        referral_program.contest_escrowed.checked_add(amount).ok_or(ReferralError::NumericOverflow)?;
+    Ok(())
+}
+
+/// Accounts required for finalizing a contest. The winning participant accounts are passed as remaining
+/// accounts, best rank first.
+#[derive(Accounts)]
+pub struct FinalizeContest<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        seeds = [b"eligibility_criteria", referral_program.key().as_ref()],
+        bump
+    )]
+    pub eligibility_criteria: Account<'info, EligibilityCriteria>,
+
+    #[account(
+        mut,
+        seeds = [CONTEST_SEED, referral_program.key().as_ref()],
+        bump = contest.bump,
+    )]
+    pub contest: Account<'info, Contest>,
+
+    pub authority: Signer<'info>,
+}
+
+/// Records the contest ranking once the program has ended and opens the dispute window.
+///
+/// Every passed participant must have at least as many referrals as each one ranked after it. The check only
+/// covers the participants passed in: leaving out a higher scorer is visible on-chain during the dispute window.
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `ContestNotEnded` - If the program end time has not passed; an open-ended program's contest can only be
+///   finalized once `extend_program` has given it an end time
+/// * `ContestAlreadySettled` - If the contest was already finalized
+/// * `InvalidContestRanking` - If the winners are missing, too many, duplicated, foreign or out of order
+/// * `InsufficientFunds` - If the escrow does not cover the awarded prizes
+pub fn finalize_contest<'info>(ctx: Context<'_, '_, 'info, 'info, FinalizeContest<'info>>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let ended = ctx.accounts.eligibility_criteria.program_end_time.is_some_and(|end| now >= end);
+    require!(ended, ReferralError::ContestNotEnded);
+    ctx.accounts.referral_program.record_authority_action(now)?;
+
+    let contest = &mut ctx.accounts.contest;
+    require!(!contest.settled, ReferralError::ContestAlreadySettled);
+
+    let ranks = ctx.remaining_accounts.len();
+    require!(ranks > 0 && ranks <= contest.prize_count(), ReferralError::InvalidContestRanking);
+    require!(contest.funded_amount >= contest.total_prizes(ranks)?, ReferralError::InsufficientFunds);
+
+    let program_key = ctx.accounts.referral_program.key();
+    let mut referral_counts = [0u64; MAX_CONTEST_PRIZES];
+    for (rank, participant_info) in ctx.remaining_accounts.iter().enumerate() {
+        let participant = Account::<Participant>::try_from(participant_info)?;
+        require!(participant.program == program_key, ReferralError::InvalidContestRanking);
+        require!(!contest.winners[..rank].contains(&participant.owner), ReferralError::InvalidContestRanking);
+        contest.winners[rank] = participant.owner;
+        referral_counts[rank] = participant.total_referrals;
+    }
+    validate_contest_ranking(&referral_counts[..ranks])?;
+
+    contest.settled = true;
+    contest.finalized_at = now;
+
+    emit!(ContestFinalized {
+        referral_program: program_key,
+        winners: contest.winners[..ranks].to_vec(),
+        claimable_at: contest.claimable_at(),
+    });
+    Ok(())
+}
+
+/// Accounts required for claiming a contest prize.
+#[derive(Accounts)]
+pub struct ClaimContestPrize<'info> {
+    #[account(mut)]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        mut,
+        seeds = [CONTEST_SEED, referral_program.key().as_ref()],
+        bump = contest.bump,
+    )]
+    pub contest: Account<'info, Contest>,
+
+    #[account(
+        mut,
+        seeds = [CONTEST_ESCROW_SEED, referral_program.key().as_ref()],
+        bump,
+    )]
+    pub escrow: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub winner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Pays the prize of `rank` to its winner once the dispute window has closed.
+///
+/// # Errors
+/// * `ContestNotFinalized` - If the contest has not been finalized
+/// * `ContestDisputeWindowOpen` - If the dispute window has not closed
+/// * `InvalidContestWinner` - If the signer did not win `rank`
+/// * `PrizeAlreadyClaimed` - If the prize was already paid
+pub fn claim_contest_prize(ctx: Context<ClaimContestPrize>, rank: u8) -> Result<()> {
+    let contest = &mut ctx.accounts.contest;
+    require!(contest.settled, ReferralError::ContestNotFinalized);
+    require!(Clock::get()?.unix_timestamp >= contest.claimable_at(), ReferralError::ContestDisputeWindowOpen);
+
+    let rank = usize::from(rank);
+    require!(
+        rank < MAX_CONTEST_PRIZES && contest.winners[rank] == ctx.accounts.winner.key(),
+        ReferralError::InvalidContestWinner
+    );
+    require!(contest.claimed_bitmap & (1 << rank) == 0, ReferralError::PrizeAlreadyClaimed);
+
+    let prize = contest.prizes[rank];
+    contest.claimed_bitmap |= 1 << rank;
+    let referral_program = &mut ctx.accounts.referral_program;
+    referral_program.contest_escrowed = referral_program.contest_escrowed.saturating_sub(prize);
+
+    let program_key = ctx.accounts.referral_program.key();
+    let seeds = &[CONTEST_ESCROW_SEED, program_key.as_ref(), &[ctx.bumps.escrow]];
+    system_program::transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.system_program.to_account_info(),
+            Transfer { from: ctx.accounts.escrow.to_account_info(), to: ctx.accounts.winner.to_account_info() },
+            &[&seeds[..]],
+        ),
+        prize,
+    )?;
+
+    msg!("Paid contest prize of {} lamports for rank {}", prize, rank);
+    Ok(())
+}
+
+/// Accounts required for withdrawing what the contest escrow holds beyond the unclaimed prizes.
+#[derive(Accounts)]
+pub struct WithdrawContestLeftover<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        seeds = [CONTEST_SEED, referral_program.key().as_ref()],
+        bump = contest.bump,
+    )]
+    pub contest: Account<'info, Contest>,
+
+    /// The escrow holding the prizes
+    /// PDA with seeds: ["contest_escrow", referral_program.key()]
+    #[account(
+        mut,
+        seeds = [CONTEST_ESCROW_SEED, referral_program.key().as_ref()],
+        bump,
+    )]
+    pub escrow: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Returns to the authority everything the escrow of a finalized contest holds beyond the prizes its winners have
+/// yet to claim: the funding of ranks left without a winner and any excess deposit.
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `ContestNotFinalized` - If the contest has not been finalized
+/// * `InsufficientFunds` - If the escrow holds nothing beyond the unclaimed prizes
+/// * `ProgramFrozen` - If the guardian froze the program
+pub fn withdraw_contest_leftover(ctx: Context<WithdrawContestLeftover>) -> Result<()> {
+    let contest = &ctx.accounts.contest;
+    require!(contest.settled, ReferralError::ContestNotFinalized);
+    require!(!ctx.accounts.referral_program.frozen, ReferralError::ProgramFrozen);
+    ctx.accounts.referral_program.record_authority_action(Clock::get()?.unix_timestamp)?;
+
+    let unclaimed = contest.unclaimed_prizes()?;
+    let leftover = ctx.accounts.escrow.lamports().saturating_sub(unclaimed);
+    require_nonzero_amount(leftover, ReferralError::InsufficientFunds)?;
+
+    let program_key = ctx.accounts.referral_program.key();
+    let seeds = &[CONTEST_ESCROW_SEED, program_key.as_ref(), &[ctx.bumps.escrow]];
+    system_program::transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.system_program.to_account_info(),
+            Transfer { from: ctx.accounts.escrow.to_account_info(), to: ctx.accounts.authority.to_account_info() },
+            &[&seeds[..]],
+        ),
+        leftover,
+    )?;
+    ctx.accounts.referral_program.contest_escrowed = unclaimed;
+
+    msg!("Withdrew {} lamports of contest leftover from referral program {}", leftover, program_key);
+    Ok(())
+}
@@ -1,4 +1,10 @@
-use crate::{error::ReferralError, state::referral_program::*};
+use crate::{
+    constants::EVENT_QUEUE_SEED,
+    error::ReferralError,
+    instructions::{debug_assert_funds, restore_referral_funding},
+    state::{event_queue::*, referral_program::*},
+    validation::require_nonzero_amount,
+};
 use anchor_lang::{
     prelude::*,
     system_program::{self, System, Transfer},
@@ -34,6 +40,14 @@ pub struct DepositSol<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
 
+    /// Optional event queue of the program; appended to when supplied
+    #[account(
+        mut,
+        seeds = [EVENT_QUEUE_SEED, referral_program.key().as_ref()],
+        bump = event_queue.bump,
+    )]
+    pub event_queue: Option<Box<Account<'info, EventQueue>>>,
+
     pub system_program: Program<'info, System>,
 }
 
@@ -47,8 +61,10 @@ pub struct DepositSol<'info> {
 /// * `ProgramInactive` - If the referral program is not active
 /// * `InvalidAuthority` - If the signer is not the program authority
 /// * `InsufficientDeposit` - If the deposit amount is zero
+/// * `ProgramClosing` - If the program is pending closure
 pub fn deposit_sol(ctx: Context<DepositSol>, amount: u64) -> Result<()> {
-    require!(amount > 0, ReferralError::InsufficientDeposit);
+    require_nonzero_amount(amount, ReferralError::InsufficientDeposit)?;
+    require!(!ctx.accounts.referral_program.is_closing(), ReferralError::ProgramClosing);
 
     let referral_program = &mut ctx.accounts.referral_program;
 
@@ -70,11 +86,18 @@ pub fn deposit_sol(ctx: Context<DepositSol>, amount: u64) -> Result<()> {
     )?;
 
     referral_program.reload()?;
+    referral_program.record_authority_action(Clock::get()?.unix_timestamp)?;
+
+    // Update total available rewards, ring-fencing the reserve share
+    referral_program.credit_deposit(amount)?;
+    restore_referral_funding(referral_program)?;
 
-    // Update total available rewards
-    referral_program.total_available =
-        referral_program.total_available.checked_add(amount).ok_or(ReferralError::NumericOverflow)?;
+    if let Some(event_queue) = ctx.accounts.event_queue.as_mut() {
+        let now = Clock::get()?.unix_timestamp;
+        event_queue.push(EventRecord::KIND_DEPOSIT, ctx.accounts.authority.key(), amount, now);
+    }
 
+    debug_assert_funds(referral_program, ctx.accounts.vault.lamports());
     msg!("Deposited {} lamports to referral program", amount);
     Ok(())
 }
@@ -91,14 +114,14 @@ pub struct DepositToken<'info> {
 
     /// Token account vault that holds deposited tokens
     /// PDA with seeds: ["token_vault", referral_program.key()]
+    /// CHECK: Deserialized as a token account of `token_mint` owned by the program in the handler, once the
+    /// program records the vault as initialized
     #[account(
         mut,
         seeds = [TOKEN_VAULT_SEED, referral_program.key().as_ref()],
         bump,
-        token::mint = token_mint,
-        token::authority = referral_program,
     )]
-    pub token_vault: Account<'info, TokenAccount>,
+    pub token_vault: UncheckedAccount<'info>,
 
     /// The mint of the token for deposits
     #[account(
@@ -118,6 +141,14 @@ pub struct DepositToken<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
 
+    /// Optional event queue of the program; appended to when supplied
+    #[account(
+        mut,
+        seeds = [EVENT_QUEUE_SEED, referral_program.key().as_ref()],
+        bump = event_queue.bump,
+    )]
+    pub event_queue: Option<Box<Account<'info, EventQueue>>>,
+
     pub token_program: Program<'info, Token>,
 }
 
@@ -134,8 +165,11 @@ pub struct DepositToken<'info> {
 /// * `InvalidTokenMint` - If the token mint doesn't match the program's configuration
 /// * `InvalidTokenAccounts` - If the token accounts are invalid
 /// * `InsufficientDeposit` - If the deposit amount is zero
+/// * `TokenVaultNotInitialized` - If `initialize_token_vault` has not been called yet
+/// * `ProgramClosing` - If the program is pending closure
 pub fn deposit_token(ctx: Context<DepositToken>, amount: u64) -> Result<()> {
-    require!(amount > 0, ReferralError::InsufficientDeposit);
+    require_nonzero_amount(amount, ReferralError::InsufficientDeposit)?;
+    require!(!ctx.accounts.referral_program.is_closing(), ReferralError::ProgramClosing);
 
     let referral_program = &mut ctx.accounts.referral_program;
 
@@ -144,6 +178,15 @@ pub fn deposit_token(ctx: Context<DepositToken>, amount: u64) -> Result<()> {
         return err!(ReferralError::TokenDepositToSolProgram);
     }
 
+    // The vault must exist and hold the program's token under the program's authority
+    require!(referral_program.token_vault_initialized, ReferralError::TokenVaultNotInitialized);
+    require!(ctx.accounts.token_vault.owner == &token::ID, ReferralError::TokenVaultNotInitialized);
+    let token_vault = TokenAccount::try_deserialize(&mut &ctx.accounts.token_vault.try_borrow_data()?[..])?;
+    require!(
+        token_vault.mint == ctx.accounts.token_mint.key() && token_vault.owner == referral_program.key(),
+        ReferralError::InvalidTokenAccounts
+    );
+
     // Token deposit
     token::transfer(
         CpiContext::new(
@@ -158,10 +201,16 @@ pub fn deposit_token(ctx: Context<DepositToken>, amount: u64) -> Result<()> {
     )?;
 
     referral_program.reload()?;
+    referral_program.record_authority_action(Clock::get()?.unix_timestamp)?;
+
+    // Update total available rewards, ring-fencing the reserve share
+    referral_program.credit_deposit(amount)?;
+    restore_referral_funding(referral_program)?;
 
-    // Update total available rewards
-    referral_program.total_available =
-        referral_program.total_available.checked_add(amount).ok_or(ReferralError::NumericOverflow)?;
+    if let Some(event_queue) = ctx.accounts.event_queue.as_mut() {
+        let now = Clock::get()?.unix_timestamp;
+        event_queue.push(EventRecord::KIND_DEPOSIT, ctx.accounts.authority.key(), amount, now);
+    }
 
     msg!("Deposited {} tokens to referral program", amount);
     Ok(())
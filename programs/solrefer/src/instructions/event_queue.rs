@@ -0,0 +1,47 @@
+use crate::{constants::EVENT_QUEUE_SEED, error::ReferralError, state::*};
+use anchor_lang::prelude::*;
+
+/// Accounts required for creating the event queue of a referral program.
+#[derive(Accounts)]
+pub struct InitializeEventQueue<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    /// The ring buffer of recent program events
+    /// PDA with seeds: ["events", referral_program.key()]
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + EventQueue::SIZE,
+        seeds = [EVENT_QUEUE_SEED, referral_program.key().as_ref()],
+        bump,
+    )]
+    pub event_queue: Box<Account<'info, EventQueue>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Creates the event queue of a referral program.
+///
+/// Once the queue exists, every state-changing instruction that is passed it appends a record, so
+/// clients can poll this single account instead of subscribing to transaction logs.
+///
+/// # Arguments
+/// * `ctx` - The context containing the referral program, the event queue PDA and the authority
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+pub fn initialize_event_queue(ctx: Context<InitializeEventQueue>) -> Result<()> {
+    ctx.accounts.referral_program.record_authority_action(Clock::get()?.unix_timestamp)?;
+    let event_queue = &mut ctx.accounts.event_queue;
+    event_queue.program = ctx.accounts.referral_program.key();
+    event_queue.head = 0;
+    event_queue.bump = ctx.bumps.event_queue;
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use crate::{
+    constants::{NETWORK_CONFIG_SEED, SETTINGS_CHANGE_SEED},
+    error::ReferralError,
+    events::ProgramField,
+    instructions::{end_time_value, record_settings_change, shortens_program, validate_end_time},
+    state::*,
+};
+use anchor_lang::prelude::*;
+
+/// Accounts required for moving a program's end time.
+#[derive(Accounts)]
+pub struct ExtendProgram<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        mut,
+        seeds = [b"eligibility_criteria", referral_program.key().as_ref()],
+        bump
+    )]
+    pub eligibility_criteria: Account<'info, EligibilityCriteria>,
+
+    /// CHECK: The network config PDA; the compile-time limits apply while it is uninitialized, and it is
+    /// deserialized in the handler once this program owns it
+    #[account(seeds = [NETWORK_CONFIG_SEED], bump)]
+    pub network_config: UncheckedAccount<'info>,
+
+    /// Pays the rent of the settings change record
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: The PDA recording this change, created when the end time moves; checked by seeds
+    #[account(
+        mut,
+        seeds = [
+            SETTINGS_CHANGE_SEED,
+            referral_program.key().as_ref(),
+            &referral_program.settings_change_count.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub settings_change: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Moves the program's end time, or makes the program open-ended when `program_end_time` is `None`.
+///
+/// A dated end is validated as by `update_program_settings`: it must fall after the program's locked period
+/// and within the network's maximum program duration. The criteria and the program's cached copy are updated together.
+/// A move is recorded in the program's next `SettingsChangeRecord`, whose rent the authority pays.
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `ProgramClosing` - If the program is pending closure
+/// * `PendingChangeRequired` - If the program is governed and the end time moves earlier, which only
+///   `update_program_settings` can propose
+/// * `InvalidProgramEndTime` - If the end time does not fall after the locked period
+/// * `ProgramDurationTooLong` - If the end time is beyond the maximum program duration from now
+pub fn extend_program(ctx: Context<ExtendProgram>, program_end_time: Option<i64>) -> Result<()> {
+    let current_time = Clock::get()?.unix_timestamp;
+    let referral_program = &mut ctx.accounts.referral_program;
+    referral_program.record_authority_action(current_time)?;
+    referral_program.require_settings_unlocked(current_time)?;
+    require!(!referral_program.is_closing(), ReferralError::ProgramClosing);
+    require!(
+        !(referral_program.governance_mode && shortens_program(referral_program.program_end_time, program_end_time)),
+        ReferralError::PendingChangeRequired
+    );
+    let limits = NetworkLimits::load(&ctx.accounts.network_config, ctx.program_id)?;
+    validate_end_time(program_end_time, referral_program.locked_period, current_time, &limits)?;
+
+    let old_end_time = referral_program.program_end_time;
+    referral_program.program_end_time = program_end_time;
+    let criteria = &mut ctx.accounts.eligibility_criteria;
+    criteria.program_end_time = program_end_time;
+    criteria.last_updated = current_time;
+
+    let mut changes = Vec::new();
+    if old_end_time != program_end_time {
+        changes.push(FieldChange {
+            field: ProgramField::ProgramEndTime as u8,
+            old_value: end_time_value(old_end_time),
+            new_value: end_time_value(program_end_time),
+        });
+    }
+    record_settings_change(
+        &mut ctx.accounts.referral_program,
+        (&ctx.accounts.settings_change.to_account_info(), ctx.bumps.settings_change),
+        changes,
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        current_time,
+    )?;
+    let referral_program = &ctx.accounts.referral_program;
+
+    match program_end_time {
+        Some(end_time) => msg!("Referral program {} now ends at {}", referral_program.key(), end_time),
+        None => msg!("Referral program {} is now open-ended", referral_program.key()),
+    }
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use crate::{
+    constants::{FEE_CONFIG_SEED, MAX_FEE_EXEMPT_AUTHORITIES},
+    error::ReferralError,
+    program::Solrefer,
+    state::*,
+};
+use anchor_lang::prelude::*;
+
+/// Settings of the protocol-wide fee config
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct FeeConfigSettings {
+    /// The account receiving creation fees
+    pub treasury: Pubkey,
+    /// Lamports charged for every program created (0 disables the fee)
+    pub creation_fee_lamports: u64,
+    /// The number of live programs an authority may hold (0 disables the limit)
+    pub max_programs_per_authority: u8,
+    /// Authorities exempt from the creation fee; unused slots are the default pubkey
+    pub exempt_authorities: [Pubkey; MAX_FEE_EXEMPT_AUTHORITIES],
+}
+
+impl FeeConfigSettings {
+    fn apply(self, fee_config: &mut FeeConfig) {
+        fee_config.treasury = self.treasury;
+        fee_config.creation_fee_lamports = self.creation_fee_lamports;
+        fee_config.max_programs_per_authority = self.max_programs_per_authority;
+        fee_config.exempt_authorities = self.exempt_authorities;
+    }
+}
+
+/// Accounts required for initializing the fee config. Only the program's upgrade authority may do so.
+#[derive(Accounts)]
+pub struct InitializeFeeConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + FeeConfig::SIZE,
+        seeds = [FEE_CONFIG_SEED],
+        bump
+    )]
+    pub fee_config: Account<'info, FeeConfig>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, Solrefer>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key()) @ ReferralError::InvalidAuthority,
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Creates the fee config with the signer as its admin.
+pub fn initialize_fee_config(ctx: Context<InitializeFeeConfig>, settings: FeeConfigSettings) -> Result<()> {
+    let fee_config = &mut ctx.accounts.fee_config;
+    fee_config.admin = ctx.accounts.admin.key();
+    fee_config.bump = ctx.bumps.fee_config;
+    settings.apply(fee_config);
+
+    msg!("Initialized fee config with admin {}", fee_config.admin);
+    Ok(())
+}
+
+/// Accounts required for updating the fee config
+#[derive(Accounts)]
+pub struct UpdateFeeConfig<'info> {
+    #[account(
+        mut,
+        seeds = [FEE_CONFIG_SEED],
+        bump = fee_config.bump,
+        has_one = admin @ ReferralError::InvalidAuthority,
+    )]
+    pub fee_config: Account<'info, FeeConfig>,
+
+    pub admin: Signer<'info>,
+}
+
+/// Replaces the fee config settings.
+pub fn update_fee_config(ctx: Context<UpdateFeeConfig>, settings: FeeConfigSettings) -> Result<()> {
+    settings.apply(&mut ctx.accounts.fee_config);
+    Ok(())
+}
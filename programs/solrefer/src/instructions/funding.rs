@@ -0,0 +1,86 @@
+use crate::{
+    constants::RUNWAY_ALERT_SLOTS,
+    error::ReferralError,
+    events::{ProgramFundedForNewReferrals, ProgramUnderfundedForNewReferrals, RunwayAlert},
+    state::*,
+};
+use anchor_lang::prelude::*;
+
+/// Stops a program from accepting referrals once a claim or withdrawal leaves its uncommitted funds short of a
+/// referral reward, emitting `ProgramUnderfundedForNewReferrals`.
+///
+/// Every claim and withdrawal ends here, so it also emits a `RunwayAlert` for each alert threshold the payout
+/// crossed.
+pub fn check_referral_funding(referral_program: &mut Account<ReferralProgram>) -> Result<()> {
+    emit_runway_alerts(referral_program)?;
+    if !referral_program.accepting_referrals || referral_program.can_fund_referral()? {
+        return Ok(());
+    }
+    stop_accepting_referrals(referral_program);
+    Ok(())
+}
+
+/// Stops a program from accepting referrals once a credit leaves its uncommitted funds short of what the next
+/// referral credits, emitting `ProgramUnderfundedForNewReferrals`.
+pub fn check_credit_funding(
+    referral_program: &mut Account<ReferralProgram>,
+    criteria: &EligibilityCriteria,
+) -> Result<()> {
+    if !referral_program.accepting_referrals || can_fund_next_referral(referral_program, criteria)? {
+        return Ok(());
+    }
+    stop_accepting_referrals(referral_program);
+    Ok(())
+}
+
+/// Returns true if the program's uncommitted funds cover what the next referral credits: the referral reward,
+/// stepped down by the program's auto downgrade, and the referee's sign-up bonus.
+pub fn can_fund_next_referral(program: &ReferralProgram, criteria: &EligibilityCriteria) -> Result<bool> {
+    let headroom = program.headroom();
+    let reward = criteria.downgraded_reward(program.referral_reward_amount()?, headroom);
+    Ok(reward.checked_add(program.referee_reward_amount).ok_or(ReferralError::NumericOverflow)? <= headroom)
+}
+
+fn stop_accepting_referrals(referral_program: &mut Account<ReferralProgram>) {
+    referral_program.accepting_referrals = false;
+    msg!("Referral program {} can no longer fund a referral reward", referral_program.key());
+    emit!(ProgramUnderfundedForNewReferrals {
+        referral_program: referral_program.key(),
+        headroom: referral_program.headroom(),
+    });
+}
+
+/// Lets an underfunded program accept referrals again once a deposit covers a referral reward, emitting
+/// `ProgramFundedForNewReferrals`.
+pub fn restore_referral_funding(referral_program: &mut Account<ReferralProgram>) -> Result<()> {
+    if referral_program.accepting_referrals || !referral_program.can_fund_referral()? {
+        return Ok(());
+    }
+    referral_program.accepting_referrals = true;
+    emit!(ProgramFundedForNewReferrals {
+        referral_program: referral_program.key(),
+        headroom: referral_program.headroom()
+    });
+    Ok(())
+}
+
+/// Emits a `RunwayAlert` for each armed alert threshold the program's available funds have fallen below.
+///
+/// Returns the bitmask of the alerts emitted.
+pub fn emit_runway_alerts(referral_program: &mut Account<ReferralProgram>) -> Result<u8> {
+    let due = referral_program.take_runway_alerts();
+    if due == 0 {
+        return Ok(0);
+    }
+    let estimated_referrals_remaining = referral_program.estimated_referrals_remaining()?;
+    for index in (0..RUNWAY_ALERT_SLOTS).filter(|index| due & (1 << index) != 0) {
+        emit!(RunwayAlert {
+            referral_program: referral_program.key(),
+            threshold_pct: referral_program.alert_thresholds[index],
+            total_available: referral_program.total_available,
+            estimated_referrals_remaining,
+            total_available_ui: referral_program.total_available_ui,
+        });
+    }
+    Ok(due)
+}
@@ -0,0 +1,357 @@
+use crate::{
+    constants::{
+        CHANGE_VOTE_SEED, CHANGE_VOTING_PERIOD, EVENT_QUEUE_SEED, NETWORK_CONFIG_SEED, PENDING_CHANGE_SEED,
+        SETTINGS_CHANGE_SEED,
+    },
+    error::ReferralError,
+    events::*,
+    instructions::{
+        apply_program_settings, create_aux_account, current_settings, record_settings_change, settings_changes,
+        validate_program_settings, ProgramSettings, RentPayer,
+    },
+    state::*,
+};
+use anchor_lang::prelude::*;
+
+/// Returns true if moving a program from `old` to `new` settings cuts what referrers or referees are paid or
+/// ends the program sooner, which a governed program holds for participant approval.
+///
+/// A lower reward, referee reward, revenue share or trailing commission is a cut, as is a new or lower reward
+/// cap, a new or earlier end time, and turning on auto downgrade, raising its `min_remaining_credits` or lowering
+/// the tier rewards it steps down to, and raising an early redemption fee already charged. Raising rewards, lifting
+/// the cap and extending the program apply at once.
+pub fn cuts_rewards(old: &ProgramSettings, new: &ProgramSettings) -> bool {
+    let cap_cut = new.max_reward_cap != 0 && (old.max_reward_cap == 0 || new.max_reward_cap < old.max_reward_cap);
+    let early_fee_cut = old.early_redemption_fee != 0 && new.early_redemption_fee > old.early_redemption_fee;
+    let downgrade_cut = new.auto_downgrade
+        && (!old.auto_downgrade
+            || new.min_remaining_credits > old.min_remaining_credits
+            || new.tier1_reward < old.tier1_reward
+            || new.tier2_reward < old.tier2_reward);
+    new.fixed_reward_amount < old.fixed_reward_amount
+        || new.base_reward < old.base_reward
+        || new.referee_reward_amount < old.referee_reward_amount
+        || new.revenue_share_percent < old.revenue_share_percent
+        || new.trailing_commission_bps < old.trailing_commission_bps
+        || cap_cut
+        || downgrade_cut
+        || early_fee_cut
+        || shortens_program(old.program_end_time, new.program_end_time)
+}
+
+/// Returns true if moving a program's end time from `old` to `new` ends it sooner: a new end for an open-ended
+/// program, or an earlier one.
+pub fn shortens_program(old: Option<i64>, new: Option<i64>) -> bool {
+    match (old, new) {
+        (None, Some(_)) => true,
+        (Some(old_end), Some(new_end)) => new_end < old_end,
+        (_, None) => false,
+    }
+}
+
+/// Holds `settings` in the program's pending change for participants to vote on until `CHANGE_VOTING_PERIOD` after
+/// `now`, its rent paid by `proposer`.
+///
+/// `pending_change` must be the program's pending change PDA, with its bump. The vote is weighed against the
+/// program's credited referrals and approval threshold as they stand now.
+///
+/// # Errors
+/// * `ChangeAlreadyPending` - If another change of the program is awaiting approval
+pub fn propose_settings_change<'info>(
+    referral_program: &mut Account<'info, ReferralProgram>,
+    pending_change: (&AccountInfo<'info>, u8),
+    settings: ProgramSettings,
+    proposer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    now: i64,
+) -> Result<()> {
+    let (pending_info, bump) = pending_change;
+    require!(pending_info.data_is_empty(), ReferralError::ChangeAlreadyPending);
+    let program_key = referral_program.key();
+    create_aux_account(
+        pending_info,
+        &[PENDING_CHANGE_SEED, program_key.as_ref(), &[bump]],
+        8 + PendingChange::size(settings.try_to_vec()?.len()),
+        &RentPayer::Signer(proposer),
+        system_program,
+    )?;
+
+    let index = referral_program.change_proposal_count;
+    let voting_ends_at = now.checked_add(CHANGE_VOTING_PERIOD).ok_or(ReferralError::NumericOverflow)?;
+    let change = PendingChange {
+        referral_program: program_key,
+        index,
+        proposer: proposer.key(),
+        proposed_at: now,
+        voting_ends_at,
+        referrals_at_proposal: referral_program.total_referrals_credited,
+        approval_threshold_percent: referral_program.approval_threshold_percent,
+        approvals: 0,
+        rejections: 0,
+        bump,
+        settings,
+    };
+    change.try_serialize(&mut &mut pending_info.try_borrow_mut_data()?[..])?;
+    referral_program.change_proposal_count = index + 1;
+
+    emit!(SettingsChangeProposed { referral_program: program_key, index, voting_ends_at });
+    Ok(())
+}
+
+/// Accounts required for putting a program under participant governance.
+#[derive(Accounts)]
+pub struct EnableGovernance<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    pub authority: Signer<'info>,
+}
+
+/// Makes settings updates that cut rewards or shorten the program wait for participant approval.
+///
+/// The switch is one-way, so participants can rely on it. Once on, such an update is held as the program's pending
+/// change until participants holding more than `approval_threshold_percent` of the program's credited referrals
+/// approve it; every other update still applies at once.
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `GovernanceAlreadyEnabled` - If the program is already governed
+/// * `InvalidApprovalThreshold` - If the threshold is not between 1 and 99 percent
+pub fn enable_governance(ctx: Context<EnableGovernance>, approval_threshold_percent: u8) -> Result<()> {
+    let referral_program = &mut ctx.accounts.referral_program;
+    referral_program.record_authority_action(Clock::get()?.unix_timestamp)?;
+    require!(!referral_program.governance_mode, ReferralError::GovernanceAlreadyEnabled);
+    require!((1..=99).contains(&approval_threshold_percent), ReferralError::InvalidApprovalThreshold);
+
+    referral_program.governance_mode = true;
+    referral_program.approval_threshold_percent = approval_threshold_percent;
+    msg!("Referral program {} is now governed at {}%", referral_program.key(), approval_threshold_percent);
+    Ok(())
+}
+
+/// Accounts required for voting on a pending settings change.
+#[derive(Accounts)]
+pub struct VoteChange<'info> {
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        mut,
+        seeds = [PENDING_CHANGE_SEED, referral_program.key().as_ref()],
+        bump = pending_change.bump,
+    )]
+    pub pending_change: Account<'info, PendingChange>,
+
+    #[account(
+        seeds = [
+            b"participant",
+            referral_program.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump
+    )]
+    pub participant: Account<'info, Participant>,
+
+    /// PDA with seeds: ["change_vote", referral_program.key(), pending_change.index.to_le_bytes(), participant.key()]
+    #[account(
+        init,
+        payer = user,
+        space = 8 + ChangeVote::SIZE,
+        seeds = [
+            CHANGE_VOTE_SEED,
+            referral_program.key().as_ref(),
+            &pending_change.index.to_le_bytes(),
+            participant.key().as_ref(),
+        ],
+        bump
+    )]
+    pub change_vote: Account<'info, ChangeVote>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Votes the signer's participant account for or against the program's pending change, weighed by its credited
+/// referrals.
+///
+/// Each participant votes once per change. Voting closes at the change's deadline or as soon as the change is
+/// approved or can no longer be.
+///
+/// # Errors
+/// * `VotingClosed` - If the change is past its deadline, approved or rejected
+/// * `ParticipantRotated` - If the participant account was rotated to a new owner
+/// * `ParticipantLeft` - If the participant left the program
+/// * `NoVotingWeight` - If the participant has no credited referrals
+pub fn vote_change(ctx: Context<VoteChange>, approve: bool) -> Result<()> {
+    let pending_change = &mut ctx.accounts.pending_change;
+    require!(pending_change.is_open(Clock::get()?.unix_timestamp), ReferralError::VotingClosed);
+    let participant = &ctx.accounts.participant;
+    participant.require_current()?;
+    let weight = participant.total_referrals;
+    require!(weight > 0, ReferralError::NoVotingWeight);
+
+    let tally = if approve { &mut pending_change.approvals } else { &mut pending_change.rejections };
+    *tally = tally.checked_add(weight).ok_or(ReferralError::NumericOverflow)?;
+
+    let change_vote = &mut ctx.accounts.change_vote;
+    change_vote.referral_program = ctx.accounts.referral_program.key();
+    change_vote.change_index = pending_change.index;
+    change_vote.participant = participant.key();
+    change_vote.approve = approve;
+    change_vote.weight = weight;
+    change_vote.bump = ctx.bumps.change_vote;
+
+    emit!(ChangeVoted {
+        referral_program: change_vote.referral_program,
+        index: pending_change.index,
+        participant: participant.key(),
+        approve,
+        weight,
+    });
+    Ok(())
+}
+
+/// Accounts required for executing an approved settings change.
+#[derive(Accounts)]
+pub struct ExecuteChange<'info> {
+    #[account(mut)]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        mut,
+        seeds = [b"eligibility_criteria", referral_program.key().as_ref()],
+        bump
+    )]
+    pub eligibility_criteria: Account<'info, EligibilityCriteria>,
+
+    /// CHECK: The network config PDA; the compile-time limits apply while it is uninitialized, and it is
+    /// deserialized in the handler once this program owns it
+    #[account(seeds = [NETWORK_CONFIG_SEED], bump)]
+    pub network_config: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = proposer,
+        seeds = [PENDING_CHANGE_SEED, referral_program.key().as_ref()],
+        bump = pending_change.bump,
+    )]
+    pub pending_change: Account<'info, PendingChange>,
+
+    /// CHECK: The authority that proposed the change; receives its rent
+    #[account(mut, address = pending_change.proposer @ ReferralError::InvalidAuthority)]
+    pub proposer: UncheckedAccount<'info>,
+
+    /// CHECK: The PDA recording this change, created when the change differs from the current settings; checked by
+    /// seeds
+    #[account(
+        mut,
+        seeds = [
+            SETTINGS_CHANGE_SEED,
+            referral_program.key().as_ref(),
+            &referral_program.settings_change_count.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub settings_change: UncheckedAccount<'info>,
+
+    /// Anyone; pays the rent of the settings change record
+    #[account(mut)]
+    pub caller: Signer<'info>,
+
+    /// Optional event queue of the program; appended to when supplied
+    #[account(
+        mut,
+        seeds = [EVENT_QUEUE_SEED, referral_program.key().as_ref()],
+        bump = event_queue.bump,
+    )]
+    pub event_queue: Option<Box<Account<'info, EventQueue>>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Applies an approved pending change to the program and closes it, returning its rent to the proposer; anyone
+/// can call it.
+///
+/// The proposed settings replace the program's settings in full, as `update_program_settings` would have applied
+/// them, and are validated again against the current time and network limits.
+///
+/// # Errors
+/// * `ChangeNotApproved` - If participants have not approved the change
+/// * Any error of `validate_program_settings` - If the settings are no longer valid
+pub fn execute_change(ctx: Context<ExecuteChange>) -> Result<()> {
+    let current_time = Clock::get()?.unix_timestamp;
+    let pending_change = &ctx.accounts.pending_change;
+    require!(pending_change.is_approved(), ReferralError::ChangeNotApproved);
+    let limits = NetworkLimits::load(&ctx.accounts.network_config, ctx.program_id)?;
+    let new_settings = pending_change.settings.clone();
+    validate_program_settings(&new_settings, current_time, &limits)?;
+    let old_settings = current_settings(&ctx.accounts.referral_program, &ctx.accounts.eligibility_criteria);
+    let changes = settings_changes(&old_settings, &new_settings)?;
+
+    apply_program_settings(
+        &mut ctx.accounts.referral_program,
+        &mut ctx.accounts.eligibility_criteria,
+        &new_settings,
+        current_time,
+    )?;
+    record_settings_change(
+        &mut ctx.accounts.referral_program,
+        (&ctx.accounts.settings_change.to_account_info(), ctx.bumps.settings_change),
+        changes,
+        &ctx.accounts.caller.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        current_time,
+    )?;
+
+    if let Some(event_queue) = ctx.accounts.event_queue.as_mut() {
+        event_queue.push(EventRecord::KIND_SETTINGS_CHANGE, ctx.accounts.caller.key(), 0, current_time);
+    }
+
+    emit!(PendingChangeResolved {
+        referral_program: ctx.accounts.referral_program.key(),
+        index: ctx.accounts.pending_change.index,
+        executed: true,
+    });
+    Ok(())
+}
+
+/// Accounts required for voiding a settings change that can no longer pass.
+#[derive(Accounts)]
+pub struct VoidChange<'info> {
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        mut,
+        close = proposer,
+        seeds = [PENDING_CHANGE_SEED, referral_program.key().as_ref()],
+        bump = pending_change.bump,
+    )]
+    pub pending_change: Account<'info, PendingChange>,
+
+    /// CHECK: The authority that proposed the change; receives its rent
+    #[account(mut, address = pending_change.proposer @ ReferralError::InvalidAuthority)]
+    pub proposer: UncheckedAccount<'info>,
+
+    /// Anyone
+    pub caller: Signer<'info>,
+}
+
+/// Discards a pending change that was rejected or missed its deadline, returning its rent to the proposer and
+/// leaving the program's settings as they are; anyone can call it.
+///
+/// # Errors
+/// * `ChangeNotVoid` - If the change is approved or can still be approved
+pub fn void_change(ctx: Context<VoidChange>) -> Result<()> {
+    let pending_change = &ctx.accounts.pending_change;
+    require!(pending_change.is_void(Clock::get()?.unix_timestamp), ReferralError::ChangeNotVoid);
+    emit!(PendingChangeResolved {
+        referral_program: ctx.accounts.referral_program.key(),
+        index: pending_change.index,
+        executed: false,
+    });
+    Ok(())
+}
@@ -0,0 +1,452 @@
+use crate::{
+    constants::{MAX_WITHDRAWAL_DESTINATIONS, WITHDRAWAL_DELAY, WITHDRAWAL_REQUEST_SEED},
+    error::ReferralError,
+    events::{
+        FundsWithdrawn, ProgramFreezeChanged, WithdrawalCancelled, WithdrawalDestinationsChanged, WithdrawalQueued,
+    },
+    instructions::{check_referral_funding, TOKEN_VAULT_SEED, VAULT_SEED},
+    state::*,
+    validation::require_nonzero_amount,
+};
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
+use anchor_spl::token::{self, Token, TokenAccount};
+
+/// A guardian key registered when a program is created, and the withdrawals it can hold up.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GuardianConfig {
+    /// The key that can freeze the program and cancel queued withdrawals; it can never move funds
+    pub guardian: Pubkey,
+    /// Withdrawals above this amount must be queued for `WITHDRAWAL_DELAY` (0 = every withdrawal is queued)
+    pub withdrawal_delay_threshold: u64,
+}
+
+/// Checks a guardian config given at creation: the guardian must be a real key other than the authority, or
+/// it would fall to the same key it guards against.
+pub fn validate_guardian(config: &GuardianConfig, authority: Pubkey) -> Result<()> {
+    require!(config.guardian != Pubkey::default() && config.guardian != authority, ReferralError::InvalidGuardian);
+    Ok(())
+}
+
+/// Returns whether withdrawing `amount` from `referral_program` must go through `queue_withdrawal`.
+pub fn requires_withdrawal_delay(referral_program: &ReferralProgram, amount: u64) -> bool {
+    referral_program.has_guardian() && amount > referral_program.withdrawal_delay_threshold
+}
+
+/// Accounts required for the authority withdrawing uncommitted funds.
+#[derive(Accounts)]
+pub struct WithdrawFunds<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    /// PDA with seeds: ["vault", referral_program.key()]
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, referral_program.key().as_ref()],
+        bump,
+    )]
+    pub vault: SystemAccount<'info>,
+
+    /// PDA with seeds: ["token_vault", referral_program.key()]; required to withdraw from a token program
+    #[account(
+        mut,
+        seeds = [TOKEN_VAULT_SEED, referral_program.key().as_ref()],
+        bump,
+        token::authority = referral_program,
+    )]
+    pub token_vault: Option<Account<'info, TokenAccount>>,
+
+    /// Token account receiving a token program's withdrawal, owned by the authority or an allowed destination
+    #[account(
+        mut,
+        constraint = destination_token_account.mint == referral_program.token_mint
+            @ ReferralError::InvalidTokenAccounts,
+        constraint = referral_program.is_withdrawal_destination_allowed(destination_token_account.owner)
+            @ ReferralError::DestinationNotAllowed,
+    )]
+    pub destination_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Wallet receiving a SOL program's withdrawal instead of the authority; must be an allowed destination
+    #[account(
+        mut,
+        constraint = referral_program.is_withdrawal_destination_allowed(destination.key())
+            @ ReferralError::DestinationNotAllowed,
+    )]
+    pub destination: Option<SystemAccount<'info>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+impl<'info> WithdrawFunds<'info> {
+    /// Returns the wallet a withdrawal pays out to: the owner of the destination token account for a token
+    /// program, otherwise the destination wallet, defaulting to the authority.
+    fn destination_wallet(&self) -> Pubkey {
+        if self.referral_program.token_mint != Pubkey::default() {
+            if let Some(destination) = &self.destination_token_account {
+                return destination.owner;
+            }
+        }
+        self.destination.as_ref().map_or(self.authority.key(), |destination| destination.key())
+    }
+
+    /// Pays `amount` of the program's uncommitted funds out of the vault to the destination wallet.
+    fn pay_out(&mut self, vault_bump: u8, amount: u64, queued: bool) -> Result<()> {
+        let destination_wallet = self.destination_wallet();
+        let referral_program = &mut self.referral_program;
+        let now = Clock::get()?.unix_timestamp;
+        referral_program.record_authority_action(now)?;
+        referral_program.require_settings_unlocked(now)?;
+        require!(!referral_program.frozen, ReferralError::ProgramFrozen);
+        require!(amount <= referral_program.headroom(), ReferralError::InsufficientFunds);
+        referral_program.total_available =
+            referral_program.total_available.checked_sub(amount).ok_or(ReferralError::NumericOverflow)?;
+        referral_program.refresh_ui_totals();
+
+        let program_key = referral_program.key();
+        if referral_program.token_mint == Pubkey::default() {
+            let seeds = &[VAULT_SEED, program_key.as_ref(), &[vault_bump]];
+            let to = match &self.destination {
+                Some(destination) => destination.to_account_info(),
+                None => self.authority.to_account_info(),
+            };
+            transfer(
+                CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    Transfer { from: self.vault.to_account_info(), to },
+                    &[&seeds[..]],
+                ),
+                amount,
+            )?;
+        } else {
+            let (Some(token_vault), Some(destination), Some(token_program)) =
+                (self.token_vault.as_ref(), self.destination_token_account.as_ref(), self.token_program.as_ref())
+            else {
+                return err!(ReferralError::InvalidTokenAccounts);
+            };
+            let index_bytes = referral_program.program_index.to_le_bytes();
+            let seeds = referral_program.signer_seeds(&index_bytes);
+            token::transfer(
+                CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    token::Transfer {
+                        from: token_vault.to_account_info(),
+                        to: destination.to_account_info(),
+                        authority: referral_program.to_account_info(),
+                    },
+                    &[&seeds[..]],
+                ),
+                amount,
+            )?;
+        }
+        check_referral_funding(referral_program)?;
+
+        emit!(FundsWithdrawn { referral_program: program_key, amount, destination: destination_wallet, queued });
+        Ok(())
+    }
+}
+
+/// Withdraws uncommitted funds to the authority, or an allowed destination, at once.
+///
+/// Only `total_available - total_committed` can be withdrawn: credited rewards and the insurance reserve stay
+/// in the vault. A program with a guardian only pays out amounts up to its `withdrawal_delay_threshold` this
+/// way; larger withdrawals go through `queue_withdrawal`.
+///
+/// Funds go to the authority unless a destination wallet, or a token account owned by one, is passed; a program
+/// with an allow-list only pays the authority and the addresses on it.
+///
+/// # Arguments
+/// * `ctx` - The context for the WithdrawFunds instruction
+/// * `amount` - The amount to withdraw, in lamports for SOL programs or token units for token programs
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `DestinationNotAllowed` - If the destination is neither the authority nor on the program's allow-list
+/// * `WithdrawalDelayRequired` - If the program has a guardian and the amount is above its delay threshold
+/// * `ProgramFrozen` - If the guardian froze the program
+/// * `InvalidWithdrawalAmount` - If the amount is zero
+/// * `InsufficientFunds` - If the amount is more than the uncommitted funds
+/// * `InvalidTokenAccounts` - If a token program's funds are withdrawn without the token accounts
+pub fn withdraw_funds(ctx: Context<WithdrawFunds>, amount: u64) -> Result<()> {
+    require_nonzero_amount(amount, ReferralError::InvalidWithdrawalAmount)?;
+    require!(
+        !requires_withdrawal_delay(&ctx.accounts.referral_program, amount),
+        ReferralError::WithdrawalDelayRequired
+    );
+    ctx.accounts.pay_out(ctx.bumps.vault, amount, false)
+}
+
+/// Accounts required for queuing a delayed withdrawal.
+#[derive(Accounts)]
+pub struct QueueWithdrawal<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    /// PDA with seeds: ["withdrawal", referral_program.key()]; one withdrawal can be queued at a time
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + WithdrawalRequest::SIZE,
+        seeds = [WITHDRAWAL_REQUEST_SEED, referral_program.key().as_ref()],
+        bump,
+    )]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+
+    /// CHECK: Wallet the withdrawal will pay out to, or whose token account it will pay, instead of the authority;
+    /// only its address is recorded, and it must be an allowed destination
+    #[account(
+        constraint = referral_program.is_withdrawal_destination_allowed(destination.key())
+            @ ReferralError::DestinationNotAllowed,
+    )]
+    pub destination: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Queues a withdrawal that `execute_withdrawal` can pay out once `WITHDRAWAL_DELAY` has elapsed, giving the
+/// guardian time to cancel it if the authority key was compromised.
+///
+/// The destination is recorded with the request, so the withdrawal can only execute to the wallet it was queued
+/// for.
+///
+/// # Arguments
+/// * `ctx` - The context for the QueueWithdrawal instruction
+/// * `amount` - The amount to withdraw
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `DestinationNotAllowed` - If the destination is neither the authority nor on the program's allow-list
+/// * `ProgramFrozen` - If the guardian froze the program
+/// * `InvalidWithdrawalAmount` - If the amount is zero
+/// * `InsufficientFunds` - If the amount is more than the uncommitted funds
+pub fn queue_withdrawal(ctx: Context<QueueWithdrawal>, amount: u64) -> Result<()> {
+    require_nonzero_amount(amount, ReferralError::InvalidWithdrawalAmount)?;
+    let now = Clock::get()?.unix_timestamp;
+    let referral_program = &mut ctx.accounts.referral_program;
+    referral_program.record_authority_action(now)?;
+    referral_program.require_settings_unlocked(now)?;
+    require!(!referral_program.frozen, ReferralError::ProgramFrozen);
+    require!(amount <= referral_program.headroom(), ReferralError::InsufficientFunds);
+
+    let request = &mut ctx.accounts.withdrawal_request;
+    request.referral_program = referral_program.key();
+    request.amount = amount;
+    request.destination = ctx.accounts.destination.as_ref().map_or(referral_program.authority, |d| d.key());
+    request.requested_at = now;
+    request.executable_at = now.saturating_add(WITHDRAWAL_DELAY);
+    request.bump = ctx.bumps.withdrawal_request;
+    referral_program.withdrawal_pending = true;
+
+    emit!(WithdrawalQueued {
+        referral_program: request.referral_program,
+        amount,
+        destination: request.destination,
+        executable_at: request.executable_at
+    });
+    Ok(())
+}
+
+/// Accounts required for executing a queued withdrawal.
+#[derive(Accounts)]
+pub struct ExecuteWithdrawal<'info> {
+    pub withdraw: WithdrawFunds<'info>,
+
+    /// PDA with seeds: ["withdrawal", referral_program.key()]; closed to the authority
+    #[account(
+        mut,
+        seeds = [WITHDRAWAL_REQUEST_SEED, withdraw.referral_program.key().as_ref()],
+        bump = withdrawal_request.bump,
+    )]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+}
+
+/// Pays out a queued withdrawal once its delay has elapsed and closes the request.
+///
+/// The amount is checked against the uncommitted funds again, since rewards credited while it waited are no
+/// longer withdrawable, and the destination must be the one it was queued for and still be allowed.
+///
+/// # Arguments
+/// * `ctx` - The context for the ExecuteWithdrawal instruction
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `WithdrawalNotReady` - If `WITHDRAWAL_DELAY` has not elapsed since the withdrawal was queued
+/// * `DestinationNotAllowed` - If the destination is not the one queued, or is no longer allowed
+/// * `ProgramFrozen` - If the guardian froze the program
+/// * `InsufficientFunds` - If the amount is now more than the uncommitted funds
+/// * `InvalidTokenAccounts` - If a token program's funds are withdrawn without the token accounts
+pub fn execute_withdrawal(ctx: Context<ExecuteWithdrawal>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let request = &ctx.accounts.withdrawal_request;
+    require!(now >= request.executable_at, ReferralError::WithdrawalNotReady);
+    require_keys_eq!(
+        ctx.accounts.withdraw.destination_wallet(),
+        request.destination,
+        ReferralError::DestinationNotAllowed
+    );
+
+    let amount = request.amount;
+    ctx.accounts.withdraw.pay_out(ctx.bumps.withdraw.vault, amount, true)?;
+    ctx.accounts.withdraw.referral_program.withdrawal_pending = false;
+    ctx.accounts.withdrawal_request.close(ctx.accounts.withdraw.authority.to_account_info())
+}
+
+/// Accounts required for the guardian cancelling a queued withdrawal.
+#[derive(Accounts)]
+pub struct GuardianCancelWithdrawal<'info> {
+    #[account(
+        mut,
+        has_one = guardian @ ReferralError::InvalidGuardian,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    /// PDA with seeds: ["withdrawal", referral_program.key()]
+    #[account(
+        mut,
+        seeds = [WITHDRAWAL_REQUEST_SEED, referral_program.key().as_ref()],
+        bump = withdrawal_request.bump,
+        close = authority,
+    )]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+
+    /// CHECK: The program authority, who paid for the request and gets its rent back
+    #[account(
+        mut,
+        address = referral_program.authority @ ReferralError::InvalidAuthority,
+    )]
+    pub authority: UncheckedAccount<'info>,
+
+    pub guardian: Signer<'info>,
+}
+
+/// Cancels the program's queued withdrawal; the request's rent goes back to the authority.
+///
+/// # Arguments
+/// * `ctx` - The context for the GuardianCancelWithdrawal instruction
+///
+/// # Errors
+/// * `InvalidGuardian` - If the signer is not the program's guardian
+/// * `InvalidAuthority` - If the rent recipient is not the program authority
+pub fn guardian_cancel_withdrawal(ctx: Context<GuardianCancelWithdrawal>) -> Result<()> {
+    ctx.accounts.referral_program.withdrawal_pending = false;
+    emit!(WithdrawalCancelled {
+        referral_program: ctx.accounts.referral_program.key(),
+        amount: ctx.accounts.withdrawal_request.amount,
+    });
+    Ok(())
+}
+
+/// Accounts required for the guardian freezing a program.
+#[derive(Accounts)]
+pub struct GuardianFreeze<'info> {
+    #[account(
+        mut,
+        has_one = guardian @ ReferralError::InvalidGuardian,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    pub guardian: Signer<'info>,
+}
+
+/// Freezes the program: claims and every withdrawal to the authority stop until it is unfrozen.
+///
+/// An abandoned program cannot be frozen, since nobody could unfreeze it.
+///
+/// # Arguments
+/// * `ctx` - The context for the GuardianFreeze instruction
+///
+/// # Errors
+/// * `InvalidGuardian` - If the signer is not the program's guardian
+/// * `ProgramAbandoned` - If the program was declared abandoned
+pub fn guardian_freeze(ctx: Context<GuardianFreeze>) -> Result<()> {
+    let referral_program = &mut ctx.accounts.referral_program;
+    require!(!referral_program.is_abandoned(), ReferralError::ProgramAbandoned);
+    referral_program.frozen = true;
+    emit!(ProgramFreezeChanged { referral_program: referral_program.key(), frozen: true });
+    Ok(())
+}
+
+/// Accounts required for unfreezing a program, signed by both its authority and its guardian.
+#[derive(Accounts)]
+pub struct UnfreezeProgram<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+        has_one = guardian @ ReferralError::InvalidGuardian,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    pub authority: Signer<'info>,
+    pub guardian: Signer<'info>,
+}
+
+/// Lifts a guardian freeze. Needing both keys keeps either one alone from undoing the other's decision.
+///
+/// # Arguments
+/// * `ctx` - The context for the UnfreezeProgram instruction
+///
+/// # Errors
+/// * `InvalidAuthority` - If the authority signer is not the program authority
+/// * `InvalidGuardian` - If the guardian signer is not the program's guardian
+pub fn unfreeze_program(ctx: Context<UnfreezeProgram>) -> Result<()> {
+    let referral_program = &mut ctx.accounts.referral_program;
+    referral_program.record_authority_action(Clock::get()?.unix_timestamp)?;
+    referral_program.frozen = false;
+    emit!(ProgramFreezeChanged { referral_program: referral_program.key(), frozen: false });
+    Ok(())
+}
+
+/// Accounts required for replacing a program's withdrawal allow-list, signed by both its authority and its
+/// guardian.
+#[derive(Accounts)]
+pub struct SetWithdrawalDestinations<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+        has_one = guardian @ ReferralError::InvalidGuardian,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    pub authority: Signer<'info>,
+    pub guardian: Signer<'info>,
+}
+
+/// Replaces the addresses besides the authority that withdrawals may pay out to.
+///
+/// A compromised authority key alone must not be able to add its own wallet, so the guardian has to sign too;
+/// a program without a guardian keeps the list it was created with.
+///
+/// # Arguments
+/// * `ctx` - The context for the SetWithdrawalDestinations instruction
+/// * `destinations` - The new allow-list; all-default leaves withdrawals unrestricted
+///
+/// # Errors
+/// * `InvalidAuthority` - If the authority signer is not the program authority
+/// * `InvalidGuardian` - If the guardian signer is not the program's guardian
+/// * `SettingsTimelocked` - If the creator's settings lock has not lapsed yet
+pub fn set_withdrawal_destinations(
+    ctx: Context<SetWithdrawalDestinations>,
+    destinations: [Pubkey; MAX_WITHDRAWAL_DESTINATIONS],
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let referral_program = &mut ctx.accounts.referral_program;
+    referral_program.record_authority_action(now)?;
+    referral_program.require_settings_unlocked(now)?;
+    referral_program.allowed_withdrawal_destinations = destinations;
+    emit!(WithdrawalDestinationsChanged { referral_program: referral_program.key(), destinations });
+    Ok(())
+}
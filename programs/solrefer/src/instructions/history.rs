@@ -0,0 +1,65 @@
+use crate::{constants::MAX_HISTORY_BATCH, error::ReferralError, state::*};
+use anchor_lang::prelude::*;
+
+/// Accounts required for the read-only `get_participant_history` instruction; the participant's referee receipts
+/// follow in the remaining accounts.
+#[derive(Accounts)]
+pub struct GetParticipantHistory<'info> {
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        constraint = participant.program == referral_program.key() @ ReferralError::InvalidReferralRecord
+    )]
+    pub participant: Account<'info, Participant>,
+}
+
+/// Returns a participant's headline figures and the supplied referee receipts in one `ParticipantHistoryV1`,
+/// without mutating any state.
+///
+/// Each receipt must record either a referral the participant made, counting those credited to the account it
+/// was rotated from, or the participant's own join. Entries are sorted by when they were credited, and those
+/// past `ParticipantHistoryV1::MAX_ENTRIES` are left out with `truncated` set.
+///
+/// # Arguments
+/// * `ctx` - The context for the GetParticipantHistory instruction, with the receipts as remaining accounts
+///
+/// # Errors
+/// * `ParticipantRotated` - If the participant account was rotated to a new owner
+/// * `ParticipantLeft` - If the participant left the program
+/// * `InvalidHistoryBatch` - If the batch holds more than `MAX_HISTORY_BATCH` receipts or repeats one
+/// * `InvalidReferralRecord` - If a receipt is of another program or names the participant on neither side
+pub fn get_participant_history<'info>(
+    ctx: Context<'_, '_, 'info, 'info, GetParticipantHistory<'info>>,
+) -> Result<ParticipantHistoryV1> {
+    let participant = &ctx.accounts.participant;
+    participant.require_current()?;
+
+    let records = ctx.remaining_accounts;
+    require!(records.len() <= MAX_HISTORY_BATCH, ReferralError::InvalidHistoryBatch);
+
+    let program_key = ctx.accounts.referral_program.key();
+    let participant_key = participant.key();
+    let mut entries = Vec::with_capacity(records.len());
+    for (index, record_info) in records.iter().enumerate() {
+        require!(
+            !records[..index].iter().any(|previous| previous.key == record_info.key),
+            ReferralError::InvalidHistoryBatch
+        );
+        let receipt = Account::<RefereeReceipt>::try_from(record_info)?;
+        require_keys_eq!(receipt.program, program_key, ReferralError::InvalidReferralRecord);
+        let kind = if receipt.referrer == participant_key || Some(receipt.referrer) == participant.rotated_from {
+            ParticipantHistoryV1::KIND_REFERRED
+        } else if receipt.referee == participant.owner {
+            ParticipantHistoryV1::KIND_JOINED
+        } else {
+            return err!(ReferralError::InvalidReferralRecord);
+        };
+        entries.push(HistoryEntry::from_receipt(&receipt, kind));
+    }
+
+    let history = ParticipantHistoryV1::new(participant, entries);
+    if history.truncated {
+        msg!("Packed {} of {} referee receipts", history.entries.len(), records.len());
+    }
+    Ok(history)
+}
@@ -0,0 +1,113 @@
+use crate::{
+    constants::{INVITE_SEED, MAX_INVITES_PER_MINT},
+    error::ReferralError,
+    instructions::{create_aux_account, RentPayer},
+    state::*,
+};
+use anchor_lang::prelude::*;
+
+/// Accounts required for minting invites. The invite PDAs to create are passed as remaining accounts,
+/// in index order starting at `referral_program.invite_count`.
+#[derive(Accounts)]
+pub struct MintInvites<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Creates `count` single-use invites for the program, paid for by the authority.
+///
+/// # Arguments
+/// * `ctx` - The context for the MintInvites instruction, with one invite PDA per invite in the remaining accounts
+/// * `count` - The number of invites to mint, at most `MAX_INVITES_PER_MINT`
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `InvalidInviteCount` - If `count` is zero, above the limit, or does not match the remaining accounts
+/// * `InvalidInviteAccount` - If a remaining account is not the next invite PDA
+pub fn mint_invites<'info>(ctx: Context<'_, '_, 'info, 'info, MintInvites<'info>>, count: u8) -> Result<()> {
+    require!(count > 0 && count <= MAX_INVITES_PER_MINT, ReferralError::InvalidInviteCount);
+    require!(ctx.remaining_accounts.len() == usize::from(count), ReferralError::InvalidInviteCount);
+    ctx.accounts.referral_program.record_authority_action(Clock::get()?.unix_timestamp)?;
+
+    let program_key = ctx.accounts.referral_program.key();
+    let authority = ctx.accounts.authority.to_account_info();
+    let system_program = ctx.accounts.system_program.to_account_info();
+
+    for invite_info in ctx.remaining_accounts.iter() {
+        let index = ctx.accounts.referral_program.invite_count;
+        let index_bytes = index.to_le_bytes();
+        let (expected, bump) =
+            Pubkey::find_program_address(&[INVITE_SEED, program_key.as_ref(), &index_bytes], ctx.program_id);
+        require_keys_eq!(invite_info.key(), expected, ReferralError::InvalidInviteAccount);
+
+        create_aux_account(
+            invite_info,
+            &[INVITE_SEED, program_key.as_ref(), &index_bytes, &[bump]],
+            8 + Invite::SIZE,
+            &RentPayer::Signer(&authority),
+            &system_program,
+        )?;
+
+        let invite = Invite {
+            program: program_key,
+            index,
+            claimed: false,
+            claimer: Pubkey::default(),
+            bump,
+            rent_payer: authority.key(),
+        };
+        invite.try_serialize(&mut &mut invite_info.try_borrow_mut_data()?[..])?;
+
+        let referral_program = &mut ctx.accounts.referral_program;
+        referral_program.invite_count =
+            referral_program.invite_count.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+    }
+
+    msg!("Minted {} invites for referral program {}", count, program_key);
+    Ok(())
+}
+
+/// Accounts required for revoking an unclaimed invite.
+#[derive(Accounts)]
+pub struct RevokeInvite<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    /// The invite to close; its rent goes back to the authority
+    #[account(
+        mut,
+        close = authority,
+        seeds = [INVITE_SEED, referral_program.key().as_ref(), &invite.index.to_le_bytes()],
+        bump = invite.bump,
+        constraint = !invite.claimed @ ReferralError::InviteAlreadyClaimed,
+    )]
+    pub invite: Account<'info, Invite>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+/// Closes an unclaimed invite and returns its rent to the authority.
+///
+/// # Arguments
+/// * `ctx` - The context for the RevokeInvite instruction
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `InviteAlreadyClaimed` - If the invite has already been used
+pub fn revoke_invite(ctx: Context<RevokeInvite>) -> Result<()> {
+    ctx.accounts.referral_program.record_authority_action(Clock::get()?.unix_timestamp)?;
+    msg!("Revoked invite {} of referral program {}", ctx.accounts.invite.index, ctx.accounts.referral_program.key());
+    Ok(())
+}
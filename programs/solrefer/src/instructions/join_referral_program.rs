@@ -1,35 +1,104 @@
 use crate::{
+    constants::EVENT_QUEUE_SEED,
     error::ReferralError,
-    state::{participant::*, referral_program::*},
+    instructions::{
+        balance_before_join, check_join_requirements, credit_bridge_referrer, meets_token_requirement,
+        require_allowed_region, require_collection_nft,
+    },
+    state::{event_queue::*, invite::*, participant::*, referral_program::*},
 };
 use anchor_lang::{prelude::*, system_program::System};
-use std::mem::size_of;
+use anchor_spl::token::TokenAccount;
 
 /// Join a referral program as a new participant who wants to refer others.
 /// This creates their participant account and generates their unique referral link
 /// that they can share with others.
-pub fn join_referral_program(ctx: Context<JoinReferralProgram>) -> Result<()> {
-    // 1. Verify program is active
+///
+/// The user must present the hash of the program's current terms, which is recorded on their account. When the
+/// program requires an NFT collection, the user must also present an NFT of it they hold. Programs with a minimum
+/// joiner balance or account age check the user against them, programs requiring a token holding of referrers
+/// check the user's token account, and programs with a region attestor require the user's attestation to name a
+/// region they do not embargo. Programs with a bridge source credit the wallet that referred the user into it, see
+/// `credit_bridge_referrer`.
+pub fn join_referral_program(ctx: Context<JoinReferralProgram>, accepted_terms_hash: [u8; 32]) -> Result<()> {
+    // 1. Verify program is active, has not ended and is not closing
     require!(ctx.accounts.referral_program.is_active, ReferralError::ProgramInactive);
+    let current_time = Clock::get()?.unix_timestamp;
+    require!(!ctx.accounts.referral_program.has_ended(current_time), ReferralError::ProgramEnded);
+    require!(!ctx.accounts.referral_program.is_closing(), ReferralError::ProgramClosing);
+    ctx.accounts.referral_program.require_current_terms(&accepted_terms_hash)?;
+    check_join_requirements(
+        &ctx.accounts.eligibility_criteria,
+        &ctx.accounts.user.key(),
+        balance_before_join(&ctx.accounts.user)?,
+        ctx.accounts.age_reference.as_deref(),
+        current_time,
+    )?;
+    require_allowed_region(
+        &ctx.accounts.eligibility_criteria,
+        &ctx.accounts.user.key(),
+        ctx.accounts.region_attestation.as_ref().map(|attestation| attestation.as_ref()),
+    )?;
 
-    // 2. Create participant account
+    // Invite-only programs admit holders of an unclaimed invite
+    redeem_invite(
+        ctx.accounts.referral_program.invite_only,
+        ctx.accounts.invite.as_deref_mut(),
+        ctx.accounts.user.key(),
+    )?;
+
+    // Collection-gated programs admit holders of a verified NFT of the collection as referrers
+    if let Some(collection) = ctx.accounts.eligibility_criteria.required_collection {
+        require_collection_nft(
+            ctx.accounts.collection_metadata.as_ref().map(|metadata| metadata.as_ref()),
+            ctx.accounts.collection_nft.as_ref(),
+            &collection,
+            &ctx.accounts.user.key(),
+        )?;
+    }
+
+    // Programs requiring a token holding of referrers admit holders of enough of it
+    require!(
+        meets_token_requirement(
+            ctx.accounts.eligibility_criteria.referrer_requirement.as_ref(),
+            ctx.accounts.referrer_token_account.as_ref(),
+            &ctx.accounts.user.key(),
+        ),
+        ReferralError::ReferrerRequirementNotMet
+    );
+
+    // 2. Create participant account, counting it toward the program's participants
+    let referral_program = &mut ctx.accounts.referral_program;
+    referral_program.total_participants =
+        referral_program.total_participants.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
     let participant = &mut ctx.accounts.participant;
     participant.owner = ctx.accounts.user.key();
     participant.program = ctx.accounts.referral_program.key();
-    participant.join_time = Clock::get()?.unix_timestamp;
+    participant.join_time = current_time;
     participant.total_referrals = 0;
     participant.total_rewards = 0;
     participant.referrer = None; // They are joining directly, not through a referral
+    participant.accepted_terms_hash = accepted_terms_hash;
+    participant.accepted_terms_version = ctx.accounts.referral_program.terms_version;
 
     // Create referral link
-    let referral_link = format!("https://solrefer.io/ref/{}", ctx.accounts.user.key());
-    let mut referral_link_bytes = [0u8; 100];
-    let bytes = referral_link.as_bytes();
-    referral_link_bytes[..bytes.len()].copy_from_slice(bytes);
-    participant.referral_link = referral_link_bytes;
+    participant.set_referral_link(&ctx.accounts.user.key(), ctx.accounts.referral_program.link_format);
+
+    // Programs with a bridge source credit the wallet that referred the user there
+    credit_bridge_referrer(
+        &mut ctx.accounts.referral_program,
+        &ctx.accounts.eligibility_criteria,
+        &ctx.accounts.user.key(),
+        ctx.accounts.bridge_participant.as_deref(),
+        ctx.accounts.bridge_referrer.as_deref_mut(),
+    )?;
+
+    if let Some(event_queue) = ctx.accounts.event_queue.as_mut() {
+        event_queue.push(EventRecord::KIND_JOIN, ctx.accounts.user.key(), 0, current_time);
+    }
 
     // Log the referral link for frontend to pick up
-    msg!("referral_link:{}", referral_link);
+    log_referral_link(participant);
 
     Ok(())
 }
@@ -39,10 +108,16 @@ pub struct JoinReferralProgram<'info> {
     #[account(mut)]
     pub referral_program: Account<'info, ReferralProgram>,
 
+    #[account(
+        seeds = [b"eligibility_criteria", referral_program.key().as_ref()],
+        bump
+    )]
+    pub eligibility_criteria: Account<'info, EligibilityCriteria>,
+
     #[account(
         init,
         payer = user,
-        space = 8 + size_of::<Participant>(),
+        space = 8 + Participant::SIZE,
         seeds = [
             b"participant",
             referral_program.key().as_ref(),
@@ -52,9 +127,50 @@ pub struct JoinReferralProgram<'info> {
     )]
     pub participant: Account<'info, Participant>,
 
+    /// An unclaimed invite; required when the program is invite-only
+    #[account(
+        mut,
+        constraint = invite.program == referral_program.key() @ ReferralError::InvalidInviteAccount,
+    )]
+    pub invite: Option<Account<'info, Invite>>,
+
+    /// CHECK: The Metaplex metadata of `collection_nft`; its address, owner and contents are checked in the
+    /// handler. Required when the program requires an NFT collection
+    pub collection_metadata: Option<UncheckedAccount<'info>>,
+
+    /// The user's token account holding an NFT of the program's required collection
+    pub collection_nft: Option<Account<'info, TokenAccount>>,
+
+    /// A participant account of the user in any program, showing how long ago they first joined; required when
+    /// the program has a minimum account age
+    pub age_reference: Option<Account<'info, Participant>>,
+
+    /// CHECK: The region attestor's attestation of the user; its owner and contents are checked in the handler.
+    /// Required when the program has a region attestor
+    pub region_attestation: Option<UncheckedAccount<'info>>,
+
+    /// The user's token account of the mint the program requires referrers to hold; required when it has a
+    /// referrer requirement
+    pub referrer_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// The user's participant account in the program's bridge source program; with `bridge_referrer`, credits the
+    /// wallet that referred the user there. The bridge is skipped when either is missing
+    pub bridge_participant: Option<Box<Account<'info, Participant>>>,
+
+    /// The participant account in this program of the wallet that referred the user into the bridge source program
+    #[account(mut)]
+    pub bridge_referrer: Option<Box<Account<'info, Participant>>>,
+
     #[account(mut)]
     pub user: Signer<'info>,
 
+    /// Optional event queue of the program; appended to when supplied
+    #[account(
+        mut,
+        seeds = [EVENT_QUEUE_SEED, referral_program.key().as_ref()],
+        bump = event_queue.bump,
+    )]
+    pub event_queue: Option<Box<Account<'info, EventQueue>>>,
+
     pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
 }
@@ -0,0 +1,50 @@
+//! Anti-sybil heuristics checked when a wallet joins a program.
+//!
+//! The runtime records no creation time for accounts, so a wallet's age can only be shown by a record that was
+//! dated when something happened to it. The one such record this program can trust is a participant account it
+//! created itself: `join_time` is taken from the clock when the wallet joins a program and nobody else can write
+//! it. A wallet that never joined any program of this deployment cannot prove its age, however old it is. Other
+//! datable accounts, such as a stake account's activation epoch, are not accepted.
+use crate::{
+    error::ReferralError,
+    state::{participant::Participant, referral_program::EligibilityCriteria},
+};
+use anchor_lang::prelude::*;
+
+/// The joining wallet's balance before it paid for its participant account.
+///
+/// Anchor's `init` has already charged the participant account's rent by the time the handler runs, so it is added
+/// back. The transaction fee is not, and rent a handler charges later in the instruction is not charged yet.
+pub fn balance_before_join(user: &AccountInfo) -> Result<u64> {
+    let participant_rent = Rent::get()?.minimum_balance(8 + Participant::SIZE);
+    user.lamports().checked_add(participant_rent).ok_or(ReferralError::NumericOverflow.into())
+}
+
+/// Checks a joining wallet against the program's minimum balance and account age.
+///
+/// `balance` is the wallet's balance before the join, see `balance_before_join`. The age is shown with
+/// `age_reference`, a participant account of the wallet in any program, whose `join_time` must be at least
+/// `min_account_age_seconds` before `now`.
+///
+/// # Errors
+/// * `JoinerBalanceTooLow` - If the balance is below `min_joiner_balance`
+/// * `InvalidAgeReference` - If the age reference is not owned by the joining wallet
+/// * `JoinerAccountTooNew` - If an age is required and the reference is missing or too recent
+pub fn check_join_requirements(
+    criteria: &EligibilityCriteria,
+    user: &Pubkey,
+    balance: u64,
+    age_reference: Option<&Participant>,
+    now: i64,
+) -> Result<()> {
+    require!(balance >= criteria.min_joiner_balance, ReferralError::JoinerBalanceTooLow);
+    if criteria.min_account_age_seconds == 0 {
+        return Ok(());
+    }
+
+    let reference = age_reference.ok_or(ReferralError::JoinerAccountTooNew)?;
+    require!(reference.owner == *user, ReferralError::InvalidAgeReference);
+    let age = now.saturating_sub(reference.join_time);
+    require!(age >= criteria.min_account_age_seconds, ReferralError::JoinerAccountTooNew);
+    Ok(())
+}
@@ -1,52 +1,560 @@
 use crate::{
+    constants::{EVENT_QUEUE_SEED, REFEREE_RECEIPT_SEED, SOURCE_TAG_LEN, SPONSOR_VAULT_SEED},
     error::ReferralError,
-    state::{participant::*, referral_program::*},
+    events::{AlreadyReferredNoCredit, JoinEvents, MilestoneReached, RewardDowngraded},
+    instructions::{
+        balance_before_join, can_fund_next_referral, check_credit_funding, check_join_requirements,
+        check_referral_funding, create_aux_account, debug_assert_end_time_cached, debug_assert_referral_counts,
+        is_direct_invocation, meets_token_requirement, pay_referee_boost, pay_reward_match, pay_trailing_commission,
+        require_allowed_region, require_collection_nft, settle_claim, verify_link_proof, ClaimGuard, LinkProof,
+        PayoutKind, ProgramStats, RentPayer, VAULT_SEED,
+    },
+    linkcodec::decode_referral,
+    state::{
+        boost::*, event_queue::*, invite::*, match_offer::*, participant::*, referee_receipt::*, referral_program::*,
+    },
 };
-use anchor_lang::{prelude::*, system_program::System};
-use std::mem::size_of;
+use anchor_lang::{
+    prelude::*,
+    solana_program::sysvar,
+    system_program::{transfer, System, Transfer},
+};
+use anchor_spl::token::TokenAccount;
 
-pub fn join_through_referral(ctx: Context<JoinThroughReferral>) -> Result<()> {
-    // 1. Verify program is active
-    require!(ctx.accounts.referral_program.is_active, ReferralError::ProgramInactive);
+pub fn join_through_referral(
+    ctx: Context<JoinThroughReferral>,
+    source_tag: Option<[u8; SOURCE_TAG_LEN]>,
+    accepted_terms_hash: [u8; 32],
+    link_proof: Option<LinkProof>,
+) -> Result<()> {
+    process_join_through_referral(ctx.accounts, &ctx.bumps, source_tag, accepted_terms_hash, link_proof)?;
+    Ok(())
+}
+
+/// Joins the user through the referrer named by a referral payload, such as a scanned QR code.
+///
+/// The payload must name this program and the referrer's owner; its campaign code, if any, is recorded as the
+/// join's source tag. The join then proceeds as `join_through_referral` without a link proof.
+///
+/// # Errors
+/// * `InvalidReferralPayload` - If the payload is malformed or names another program
+/// * `ReferralPayloadChecksum` - If the payload fails its checksum
+/// * `InvalidReferrer` - If the referrer account is not owned by the payload's referrer
+pub fn join_with_payload(
+    ctx: Context<JoinThroughReferral>,
+    payload: Vec<u8>,
+    accepted_terms_hash: [u8; 32],
+) -> Result<()> {
+    let referral = decode_referral(&payload)?;
+    require_keys_eq!(referral.program, ctx.accounts.referral_program.key(), ReferralError::InvalidReferralPayload);
+    require_keys_eq!(referral.referrer_owner, ctx.accounts.referrer.owner, ReferralError::InvalidReferrer);
+    process_join_through_referral(ctx.accounts, &ctx.bumps, referral.source_tag(), accepted_terms_hash, None)?;
+    Ok(())
+}
+
+/// Joins the user through the referrer and credits the referral.
+///
+/// The user must present the hash of the program's current terms, which is recorded on their account. Programs
+/// with a minimum joiner balance, account age or referee token requirement check the user against them before the
+/// referee receipt is paid for. Programs with a referrer token requirement check the referrer's holding on every
+/// referral, since a referrer that joined through a referral was never checked as one. Programs with a link signer
+/// only admit joins presenting a link it signed for the referrer, see `verify_link_proof`, and programs with a
+/// region attestor only admit users attested outside its embargoed regions. A sponsor's match offer passed with the
+/// referrer's wallet pays the referrer its match on the reward, see `pay_reward_match`. Programs in
+/// `EVENT_MODE_DIGEST` report the join's events in a single `JoinDigest` once it succeeds.
+///
+/// Returns the sign-up bonus credited to the referee, which is zero when the referral earned no credit.
+pub fn process_join_through_referral(
+    accounts: &mut JoinThroughReferral,
+    bumps: &JoinThroughReferralBumps,
+    source_tag: Option<[u8; SOURCE_TAG_LEN]>,
+    accepted_terms_hash: [u8; 32],
+    link_proof: Option<LinkProof>,
+) -> Result<u64> {
+    check_join_requirements(
+        &accounts.eligibility_criteria,
+        &accounts.user.key(),
+        balance_before_join(&accounts.user)?,
+        accounts.age_reference.as_deref(),
+        Clock::get()?.unix_timestamp,
+    )?;
+    require_allowed_region(
+        &accounts.eligibility_criteria,
+        &accounts.user.key(),
+        accounts.region_attestation.as_ref().map(|attestation| attestation.as_ref()),
+    )?;
+    require!(
+        meets_token_requirement(
+            accounts.eligibility_criteria.referee_requirement.as_ref(),
+            accounts.referee_token_account.as_deref(),
+            &accounts.user.key(),
+        ),
+        ReferralError::RefereeRequirementNotMet
+    );
+    let mut receipt = open_referee_receipt(accounts, bumps)?;
+    let mut events =
+        JoinEvents::new(accounts.referral_program.event_mode, accounts.referral_program.key(), accounts.user.key());
+    let referee_reward = credit_join(
+        accounts,
+        &mut receipt,
+        bumps.referee_receipt,
+        source_tag,
+        accepted_terms_hash,
+        link_proof,
+        &mut events,
+    )?;
+    events.finish();
+    receipt.try_serialize(&mut &mut accounts.referee_receipt.try_borrow_mut_data()?[..])?;
+    debug_assert_referral_counts(&accounts.referral_program);
+    Ok(referee_reward)
+}
+
+/// Loads the user's referee receipt, creating it first when the user has never joined this program through a
+/// referral; its rent is paid as the program's `rent_payer_mode` says.
+fn open_referee_receipt(accounts: &JoinThroughReferral, bumps: &JoinThroughReferralBumps) -> Result<RefereeReceipt> {
+    let receipt_info = accounts.referee_receipt.to_account_info();
+    if receipt_info.owner == &crate::ID {
+        return RefereeReceipt::try_deserialize(&mut &receipt_info.try_borrow_data()?[..]);
+    }
+
+    let user = accounts.user.to_account_info();
+    let sponsor_vault = accounts.sponsor_vault.as_ref().map(|vault| vault.to_account_info());
+    let rent_payer =
+        RentPayer::for_program(&accounts.referral_program, &user, sponsor_vault.as_ref().zip(bumps.sponsor_vault))?;
+    let program_key = accounts.referral_program.key();
+    create_aux_account(
+        &receipt_info,
+        &[REFEREE_RECEIPT_SEED, program_key.as_ref(), user.key.as_ref(), &[bumps.referee_receipt]],
+        8 + RefereeReceipt::SIZE,
+        &rent_payer,
+        &accounts.system_program.to_account_info(),
+    )?;
+    Ok(RefereeReceipt::default())
+}
+
+/// Joins the user and credits the referral, recording it on `receipt`; see `process_join_through_referral`.
+fn credit_join(
+    accounts: &mut JoinThroughReferral,
+    receipt: &mut RefereeReceipt,
+    referee_receipt_bump: u8,
+    source_tag: Option<[u8; SOURCE_TAG_LEN]>,
+    accepted_terms_hash: [u8; 32],
+    link_proof: Option<LinkProof>,
+    events: &mut JoinEvents,
+) -> Result<u64> {
+    // 1. Verify program is active, has not ended and is not closing
+    require!(accounts.referral_program.is_active, ReferralError::ProgramInactive);
+    let current_time = Clock::get()?.unix_timestamp;
+    debug_assert_end_time_cached(&accounts.referral_program, &accounts.eligibility_criteria);
+    require!(!accounts.referral_program.has_ended(current_time), ReferralError::ProgramEnded);
+    require!(!accounts.referral_program.is_closing(), ReferralError::ProgramClosing);
+    accounts.referral_program.require_current_terms(&accepted_terms_hash)?;
 
     // 2. Verify referrer exists and is valid
-    require!(ctx.accounts.referrer.program == ctx.accounts.referral_program.key(), ReferralError::InvalidReferrer);
+    require!(accounts.referrer.program == accounts.referral_program.key(), ReferralError::InvalidReferrer);
+    verify_link_proof(
+        accounts.eligibility_criteria.link_signer,
+        &accounts.referral_program.key(),
+        link_proof.as_ref(),
+        &accounts.referrer.owner,
+        accounts.instructions_sysvar.as_ref().map(|sysvar| sysvar.as_ref()),
+        current_time,
+    )?;
+
+    // Invite-only programs admit holders of an unclaimed invite
+    redeem_invite(accounts.referral_program.invite_only, accounts.invite.as_deref_mut(), accounts.user.key())?;
 
-    // 3. Create participant account
-    let participant = &mut ctx.accounts.participant;
-    participant.owner = ctx.accounts.user.key();
-    participant.program = ctx.accounts.referral_program.key();
-    participant.join_time = Clock::get()?.unix_timestamp;
+    // A rotated referrer is followed one hop to the participant account it was rotated to; one that left the
+    // program has nowhere to follow
+    require!(!accounts.referrer.has_left(), ReferralError::ParticipantLeft);
+    let referrer = match accounts.referrer.rotated_to {
+        Some(rotated_to) => {
+            let rotated_referrer = accounts.rotated_referrer.as_mut().ok_or(ReferralError::ParticipantRotated)?;
+            require!(rotated_referrer.key() == rotated_to, ReferralError::ParticipantRotated);
+            rotated_referrer
+        }
+        None => &mut accounts.referrer,
+    };
+    let referrer_key = referrer.key();
+
+    // Programs gating credits on their collection only let referrers holding a verified NFT of it refer
+    let criteria = &accounts.eligibility_criteria;
+    if let (Some(collection), true) = (criteria.required_collection, criteria.collection_gates_credits) {
+        require_collection_nft(
+            accounts.referrer_collection_metadata.as_ref().map(|metadata| metadata.as_ref()),
+            accounts.referrer_collection_nft.as_ref(),
+            &collection,
+            &referrer.owner,
+        )?;
+    }
+    require!(
+        meets_token_requirement(
+            criteria.referrer_requirement.as_ref(),
+            accounts.referrer_token_account.as_deref(),
+            &referrer.owner,
+        ),
+        ReferralError::ReferrerRequirementNotMet
+    );
+
+    // Every join counts toward its campaign tag and the raw referral counts, whether or not it earns the referrer
+    // anything
+    let source_tag = source_tag.unwrap_or_default();
+    validate_source_tag(&source_tag)?;
+    accounts.referral_program.record_source_tag(&source_tag);
+    referrer.raw_referrals = referrer.raw_referrals.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+    referrer.active_referee_count =
+        referrer.active_referee_count.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+    let referral_program = &mut accounts.referral_program;
+    referral_program.total_referrals_raw =
+        referral_program.total_referrals_raw.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+
+    // 3. Create participant account, counting it toward the program's participants
+    referral_program.total_participants =
+        referral_program.total_participants.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+    let participant = &mut accounts.participant;
+    participant.owner = accounts.user.key();
+    participant.program = accounts.referral_program.key();
+    participant.join_time = current_time;
     participant.total_referrals = 0;
     participant.total_rewards = 0;
-    participant.referrer = Some(ctx.accounts.referrer.key());
+    participant.referrer = Some(referrer_key);
+    participant.source_tag = source_tag;
+    participant.accepted_terms_hash = accepted_terms_hash;
+    participant.accepted_terms_version = accounts.referral_program.terms_version;
+
+    // Set the tree depth before any credit decision so the depth cap applies to this referral
+    let referral_depth = referrer.referral_depth.saturating_add(1);
+    participant.referral_depth = referral_depth;
+    let referral_program = &mut accounts.referral_program;
+    referral_program.max_observed_depth = referral_program.max_observed_depth.max(referral_depth);
 
     // Create referral link
-    let referral_link = format!("https://solrefer.io/ref/{}", ctx.accounts.user.key());
-    let mut referral_link_bytes = [0u8; 100];
-    let bytes = referral_link.as_bytes();
-    referral_link_bytes[..bytes.len()].copy_from_slice(bytes);
-    participant.referral_link = referral_link_bytes;
+    participant.set_referral_link(&accounts.user.key(), accounts.referral_program.link_format);
+
+    // 4. A wallet is credited as a referee at most once per program
+    if receipt.referee != Pubkey::default() {
+        events.already_referred(AlreadyReferredNoCredit {
+            referral_program: accounts.referral_program.key(),
+            referee: accounts.user.key(),
+            original_referrer: receipt.referrer,
+            attempted_referrer: referrer_key,
+        });
+        if let Some(event_queue) = accounts.event_queue.as_mut() {
+            event_queue.push(EventRecord::KIND_REFERRAL, accounts.user.key(), 0, current_time);
+        }
+        log_referral_link(&accounts.participant);
+        return Ok(0);
+    }
+    receipt.program = accounts.referral_program.key();
+    receipt.referee = accounts.user.key();
+    receipt.referrer = referrer_key;
+    receipt.referee_active = true;
+    receipt.credited_at = current_time;
+    receipt.source_tag = source_tag;
+    receipt.referrer_link_params = referrer.link_params;
+    receipt.bump = referee_receipt_bump;
+
+    // 5. Work out what the referral credits with the same function `preview_referral` reports
+    let credit = referral_credit(&accounts.referral_program, &accounts.eligibility_criteria, referrer, current_time)?;
+
+    // Referrals beyond the program's max depth still join but earn the referrer nothing
+    if credit.beyond_max_depth {
+        msg!(
+            "Referral depth {} exceeds max depth {}; no reward credited",
+            referral_depth,
+            accounts.referral_program.max_depth
+        );
+        if let Some(event_queue) = accounts.event_queue.as_mut() {
+            event_queue.push(EventRecord::KIND_REFERRAL, accounts.user.key(), 0, current_time);
+        }
+        log_referral_link(&accounts.participant);
+        return Ok(0);
+    }
+
+    // 6. Referrers over their rate limit get nothing for the referral, or the join is rejected in strict mode
+    let criteria = &accounts.eligibility_criteria;
+    if credit.rate_limited {
+        require!(!criteria.rate_limit_strict, ReferralError::ReferralRateLimited);
+        msg!(
+            "Referrer reached its limit of {} referrals per window; no reward credited",
+            criteria.max_referrals_per_window
+        );
+        if let Some(event_queue) = accounts.event_queue.as_mut() {
+            event_queue.push(EventRecord::KIND_REFERRAL, accounts.user.key(), 0, current_time);
+        }
+        log_referral_link(&accounts.participant);
+        return Ok(0);
+    }
+
+    // Programs whose uncommitted funds cannot cover the referral's rewards take no referrals until a deposit
+    require!(!credit.program_underfunded, ReferralError::ProgramUnderfunded);
+    let recorded = referrer.record_window_referral(criteria, current_time);
+    debug_assert!(recorded, "referral_credit let a rate-limited referral through");
+
+    // 7. Update referrer's stats and credit the referral reward, routing the split share if one is set
+    credit_referrer_reward(
+        &mut accounts.referral_program,
+        referrer,
+        accounts.split_recipient.as_mut(),
+        receipt,
+        &credit,
+        events,
+    )?;
+
+    // A sponsor's match is paid straight from its offer to the referrer's wallet, outside the program's accounting
+    if let Some(match_offer) = accounts.match_offer.as_deref_mut() {
+        let referrer_wallet = accounts.referrer_wallet.as_ref().ok_or(ReferralError::InvalidMatchOffer)?;
+        let program_key = accounts.referral_program.key();
+        let matched =
+            pay_reward_match(match_offer, program_key, referrer, referrer_wallet, credit.referrer_share, events)?;
+        referrer.match_received = referrer.match_received.checked_add(matched).ok_or(ReferralError::NumericOverflow)?;
+    }
+
+    // 8. Pay the milestone bonuses, then 9. credit the referee's sign-up bonus
+    credit_milestones_and_referee(
+        &mut accounts.referral_program,
+        &accounts.eligibility_criteria,
+        referrer,
+        accounts.referrer_upline.as_deref_mut(),
+        &mut accounts.participant,
+        &credit,
+        events,
+    )?;
+    let referee_reward = credit.referee_reward;
 
-    // 4. Update referrer's stats
-    let referrer = &mut ctx.accounts.referrer;
-    referrer.total_referrals = referrer.total_referrals.checked_add(1).unwrap();
+    // A credit that leaves the uncommitted funds short of the next referral stops the program taking referrals
+    check_credit_funding(&mut accounts.referral_program, &accounts.eligibility_criteria)?;
+
+    // 10. Top the referee up from the referrer's boost escrow, paid straight from the escrow to the wallet
+    if referee_reward > 0 {
+        if let Some(boost_escrow) = accounts.boost_escrow.as_mut() {
+            let program_key = accounts.referral_program.key();
+            let boost =
+                pay_referee_boost(boost_escrow, program_key, referrer_key, &accounts.user.to_account_info(), events)?;
+            let participant = &mut accounts.participant;
+            participant.boost_received =
+                participant.boost_received.checked_add(boost).ok_or(ReferralError::NumericOverflow)?;
+        }
+    }
+
+    if let Some(event_queue) = accounts.event_queue.as_mut() {
+        event_queue.push(EventRecord::KIND_REFERRAL, accounts.user.key(), credit.reward_amount, current_time);
+    }
 
     // Log the referral link for frontend to pick up
-    msg!("referral_link:{}", referral_link);
+    log_referral_link(&accounts.participant);
+
+    Ok(referee_reward)
+}
+
+/// Credits the referrer of a referral earning credit its reward, routing the payout-split share to
+/// `split_recipient`, and records the credit on `receipt` and the program. A reward stepped down by auto downgrade
+/// is reported as a `RewardDowngraded`.
+pub(crate) fn credit_referrer_reward(
+    referral_program: &mut Account<ReferralProgram>,
+    referrer: &mut Account<Participant>,
+    split_recipient: Option<&mut Account<Participant>>,
+    receipt: &mut RefereeReceipt,
+    credit: &ReferralCredit,
+    events: &mut JoinEvents,
+) -> Result<()> {
+    if credit.reward_amount < credit.requested_reward {
+        events.reward_downgraded(RewardDowngraded {
+            referral_program: referral_program.key(),
+            referrer: referrer.key(),
+            requested: credit.requested_reward,
+            paid: credit.reward_amount,
+        });
+    }
+    referrer.total_referrals = referrer.total_referrals.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+    referrer.record_count = referrer.record_count.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+    if let Some(split) = referrer.payout_split {
+        let recipient = split_recipient.ok_or(ReferralError::InvalidSplitRecipient)?;
+        require!(
+            recipient.owner == split.recipient && recipient.program == referral_program.key(),
+            ReferralError::InvalidSplitRecipient
+        );
+        recipient.credit_reward(credit.reward_amount - credit.referrer_share)?;
+    }
+    referrer.credit_reward(credit.referrer_share)?;
+
+    receipt.credited_amount = credit.referrer_share;
+    receipt.counted = true;
 
+    referral_program.total_referrals_credited =
+        referral_program.total_referrals_credited.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+    referral_program.total_committed =
+        referral_program.total_committed.checked_add(credit.reward_amount).ok_or(ReferralError::NumericOverflow)?;
     Ok(())
 }
 
+/// Pays the referrer the one-time bonus of every milestone a credited referral reached while the vault has
+/// headroom, with its upline's trailing commission on them, then credits the referee's sign-up bonus and the
+/// referrer its trailing commission on it.
+pub(crate) fn credit_milestones_and_referee(
+    referral_program: &mut Account<ReferralProgram>,
+    criteria: &EligibilityCriteria,
+    referrer: &mut Account<Participant>,
+    referrer_upline: Option<&mut Account<Participant>>,
+    referee: &mut Participant,
+    credit: &ReferralCredit,
+    events: &mut JoinEvents,
+) -> Result<()> {
+    let referrer_key = referrer.key();
+    let milestones = criteria.milestones;
+    for index in newly_reached_milestones(&milestones, referrer.total_referrals, referrer.milestones_claimed_bitmap) {
+        if credit.milestones_paid & (1 << index) == 0 {
+            msg!("Milestone {} reached but the vault lacks headroom for its bonus", index);
+            continue;
+        }
+        let milestone = milestones[index];
+        referrer.credit_reward(milestone.bonus)?;
+        referrer.milestones_claimed_bitmap |= 1 << index;
+        referral_program.total_committed =
+            referral_program.total_committed.checked_add(milestone.bonus).ok_or(ReferralError::NumericOverflow)?;
+        events.milestone_reached(MilestoneReached {
+            referral_program: referral_program.key(),
+            participant: referrer_key,
+            milestone_index: index as u8,
+            threshold: milestone.threshold,
+            bonus: milestone.bonus,
+        });
+    }
+    pay_trailing_commission(referral_program, referrer.referrer, referrer_upline, credit.milestone_commission)?;
+
+    referee.credit_reward(credit.referee_reward)?;
+    referral_program.total_committed =
+        referral_program.total_committed.checked_add(credit.referee_reward).ok_or(ReferralError::NumericOverflow)?;
+    pay_trailing_commission(referral_program, Some(referrer_key), Some(referrer), credit.referee_commission)
+}
+
+/// What a referral through a referrer credits, worked out before any account is touched.
+///
+/// This is the single source of truth for referral credits: `join_through_referral` applies it and
+/// `preview_referral` reports it, so a preview always matches a join made in the same state.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReferralCredit {
+    /// The referral is past the program's max depth and credits nothing
+    pub beyond_max_depth: bool,
+    /// The referrer's rate-limit window is full, so the referral credits nothing
+    pub rate_limited: bool,
+    /// The program stopped accepting referrals, or its uncommitted funds cannot cover the referral reward and
+    /// sign-up bonus, so the referral is rejected with `ProgramUnderfunded`
+    pub program_underfunded: bool,
+    /// The referral reward including any payout-split share, after any auto downgrade
+    pub reward_amount: u64,
+    /// The full referral reward; above `reward_amount` when the program's auto downgrade stepped it down
+    pub requested_reward: u64,
+    /// The part of `reward_amount` credited to the referrer after its payout split
+    pub referrer_share: u64,
+    /// Bit `i` is set for each milestone the referral reaches whose bonus the vault can cover
+    pub milestones_paid: u8,
+    /// Total of the milestone bonuses in `milestones_paid`
+    pub milestone_bonus: u64,
+    /// Sign-up bonus credited to the referee
+    pub referee_reward: u64,
+    /// Trailing commission credited to the referrer on the referee's sign-up bonus
+    pub referee_commission: u64,
+    /// Trailing commission credited to the referrer's own referrer on the milestone bonuses
+    pub milestone_commission: u64,
+}
+
+impl ReferralCredit {
+    /// Returns true if the referral credits the referrer and the referee.
+    pub fn is_credited(&self) -> bool {
+        !self.beyond_max_depth && !self.rate_limited && !self.program_underfunded
+    }
+}
+
+/// Works out what a new referral through `referrer` credits at `now`.
+///
+/// Referrals past the max depth or over the referrer's rate limit credit nothing. Referrals into a program that
+/// stopped accepting referrals, or whose uncommitted funds cannot cover the referral reward and the sign-up bonus,
+/// are underfunded and rejected. Otherwise the referrer earns
+/// the referral reward, stepped down by the program's auto downgrade if set, less its payout-split share, plus the
+/// bonus of each milestone reached while the vault has headroom for it after the reward, and the referee earns the
+/// program's sign-up bonus. Trailing commissions on the sign-up bonus and the milestone bonuses come last, each
+/// clamped to the headroom left.
+pub fn referral_credit(
+    program: &ReferralProgram,
+    criteria: &EligibilityCriteria,
+    referrer: &Participant,
+    now: i64,
+) -> Result<ReferralCredit> {
+    let referral_depth = referrer.referral_depth.saturating_add(1);
+    if program.max_depth != 0 && referral_depth > program.max_depth {
+        return Ok(ReferralCredit { beyond_max_depth: true, ..Default::default() });
+    }
+    if referrer.window_is_full(criteria, now) {
+        return Ok(ReferralCredit { rate_limited: true, ..Default::default() });
+    }
+    if !program.accepting_referrals || !can_fund_next_referral(program, criteria)? {
+        return Ok(ReferralCredit { program_underfunded: true, ..Default::default() });
+    }
+
+    let requested_reward = program.referral_reward_amount()?;
+    let reward_amount = criteria.downgraded_reward(requested_reward, program.headroom());
+    let referee_reward = program.referee_reward_amount;
+    let referrer_share = match referrer.payout_split {
+        Some(split) => split.split(reward_amount).0,
+        None => reward_amount,
+    };
+
+    let mut committed = program.total_committed.checked_add(reward_amount).ok_or(ReferralError::NumericOverflow)?;
+    let mut milestones_paid = 0;
+    let mut milestone_bonus = 0u64;
+    let total_referrals = referrer.total_referrals.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+    for index in newly_reached_milestones(&criteria.milestones, total_referrals, referrer.milestones_claimed_bitmap) {
+        let bonus = criteria.milestones[index].bonus;
+        if bonus > program.total_available.saturating_sub(committed) {
+            continue;
+        }
+        committed = committed.checked_add(bonus).ok_or(ReferralError::NumericOverflow)?;
+        milestone_bonus = milestone_bonus.checked_add(bonus).ok_or(ReferralError::NumericOverflow)?;
+        milestones_paid |= 1 << index;
+    }
+
+    committed = committed.checked_add(referee_reward).ok_or(ReferralError::NumericOverflow)?;
+    let referee_commission =
+        criteria.trailing_commission(referee_reward)?.min(program.total_available.saturating_sub(committed));
+    committed = committed.checked_add(referee_commission).ok_or(ReferralError::NumericOverflow)?;
+    let milestone_commission = match referrer.referrer {
+        Some(_) => {
+            criteria.trailing_commission(milestone_bonus)?.min(program.total_available.saturating_sub(committed))
+        }
+        None => 0,
+    };
+
+    Ok(ReferralCredit {
+        beyond_max_depth: false,
+        rate_limited: false,
+        program_underfunded: false,
+        reward_amount,
+        requested_reward,
+        referrer_share,
+        milestones_paid,
+        milestone_bonus,
+        referee_reward,
+        referee_commission,
+        milestone_commission,
+    })
+}
+
 #[derive(Accounts)]
 pub struct JoinThroughReferral<'info> {
     #[account(mut)]
-    pub referral_program: Account<'info, ReferralProgram>,
+    pub referral_program: Box<Account<'info, ReferralProgram>>,
+
+    #[account(
+        seeds = [b"eligibility_criteria", referral_program.key().as_ref()],
+        bump
+    )]
+    pub eligibility_criteria: Account<'info, EligibilityCriteria>,
 
     #[account(
         init,
         payer = user,
-        space = 8 + size_of::<Participant>(),
+        space = 8 + Participant::SIZE,
         seeds = [
             b"participant",
             referral_program.key().as_ref(),
@@ -59,9 +567,175 @@ pub struct JoinThroughReferral<'info> {
     #[account(mut)]
     pub referrer: Account<'info, Participant>,
 
+    /// The participant account the referrer was rotated to; required when the referrer is a rotation tombstone
+    #[account(mut)]
+    pub rotated_referrer: Option<Account<'info, Participant>>,
+
+    /// The participant receiving the referrer's payout split; required when the referrer has one
+    #[account(mut)]
+    pub split_recipient: Option<Account<'info, Participant>>,
+
+    /// The referrer's boost escrow; pays the referee the referrer's boost when supplied and sign-up bonuses are on
+    #[account(mut)]
+    pub boost_escrow: Option<Account<'info, BoostEscrow>>,
+
+    /// A sponsor's match offer; pays the referrer's wallet its match on the referral reward when supplied
+    #[account(mut)]
+    pub match_offer: Option<Box<Account<'info, MatchOffer>>>,
+
+    /// CHECK: The referrer's wallet, checked against the referrer's owner in the handler; required with a match
+    /// offer
+    #[account(mut)]
+    pub referrer_wallet: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Receipt recording the first credited referral of this wallet; never closed. Created by the handler
+    /// through `create_aux_account` when it does not exist yet, and deserialized there otherwise
+    /// PDA with seeds: ["referee", referral_program.key(), user.key()]
+    #[account(
+        mut,
+        seeds = [
+            REFEREE_RECEIPT_SEED,
+            referral_program.key().as_ref(),
+            user.key().as_ref(),
+        ],
+        bump
+    )]
+    pub referee_receipt: UncheckedAccount<'info>,
+
+    /// The program's sponsor vault; pays for the referee receipt instead of the user in `RENT_PAYER_SPONSOR` mode
+    /// PDA with seeds: ["sponsor_vault", referral_program.key()]
+    #[account(
+        mut,
+        seeds = [SPONSOR_VAULT_SEED, referral_program.key().as_ref()],
+        bump,
+    )]
+    pub sponsor_vault: Option<SystemAccount<'info>>,
+
+    /// An unclaimed invite; required when the program is invite-only
+    #[account(
+        mut,
+        constraint = invite.program == referral_program.key() @ ReferralError::InvalidInviteAccount,
+    )]
+    pub invite: Option<Account<'info, Invite>>,
+
+    /// CHECK: The Metaplex metadata of `referrer_collection_nft`; its address, owner and contents are checked
+    /// in the handler. Required when the program gates credits on its collection
+    pub referrer_collection_metadata: Option<UncheckedAccount<'info>>,
+
+    /// The referrer's token account holding an NFT of the program's required collection
+    pub referrer_collection_nft: Option<Account<'info, TokenAccount>>,
+
+    /// A participant account of the user in any program, showing how long ago they first joined; required when
+    /// the program has a minimum account age
+    pub age_reference: Option<Account<'info, Participant>>,
+
+    /// CHECK: The region attestor's attestation of the user; its owner and contents are checked in the handler.
+    /// Required when the program has a region attestor
+    pub region_attestation: Option<UncheckedAccount<'info>>,
+
+    /// The referrer's token account of the mint the program requires referrers to hold; required when it has a
+    /// referrer requirement
+    pub referrer_token_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// The user's token account of the mint the program requires referees to hold; required when it has a
+    /// referee requirement
+    pub referee_token_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// The participant that referred the referrer; required when the referral pays the referrer a milestone bonus
+    /// and the program has a trailing commission
+    #[account(mut)]
+    pub referrer_upline: Option<Box<Account<'info, Participant>>>,
+
     #[account(mut)]
     pub user: Signer<'info>,
 
+    /// Optional event queue of the program; appended to when supplied
+    #[account(
+        mut,
+        seeds = [EVENT_QUEUE_SEED, referral_program.key().as_ref()],
+        bump = event_queue.bump,
+    )]
+    pub event_queue: Option<Box<Account<'info, EventQueue>>>,
+
+    /// CHECK: The instructions sysvar, checked by address; required when the program has a link signer, to read
+    /// the ed25519 instruction verifying the referral link
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
     pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct JoinAndClaimThroughReferral<'info> {
+    pub join: JoinThroughReferral<'info>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, join.referral_program.key().as_ref()],
+        bump
+    )]
+    pub vault: SystemAccount<'info>,
+
+    /// CHECK: The instructions sysvar, checked by address; without it a program that only accepts direct claims
+    /// leaves the bonus pending
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+}
+
+/// Joins through a referral and pays the referee's sign-up bonus from the vault in the same transaction,
+/// unless the program locks referee rewards.
+///
+/// Returns the amount paid to the referee; zero when nothing was credited, the bonus is locked or the program is
+/// frozen, in which case it stays pending like any other reward. A program that only accepts direct claims also
+/// leaves it pending when the join was not invoked directly by the transaction, so the join itself stays
+/// available to other programs.
+pub fn join_and_claim_through_referral(
+    ctx: Context<JoinAndClaimThroughReferral>,
+    source_tag: Option<[u8; SOURCE_TAG_LEN]>,
+    accepted_terms_hash: [u8; 32],
+    link_proof: Option<LinkProof>,
+) -> Result<u64> {
+    let referee_reward = process_join_through_referral(
+        &mut ctx.accounts.join,
+        &ctx.bumps.join,
+        source_tag,
+        accepted_terms_hash,
+        link_proof,
+    )?;
+    let referral_program = &ctx.accounts.join.referral_program;
+    if referee_reward == 0 || referral_program.referee_rewards_locked || referral_program.frozen {
+        return Ok(0);
+    }
+    let instructions_sysvar = ctx.accounts.instructions_sysvar.as_ref().map(|sysvar| sysvar.as_ref());
+    if referral_program.direct_claims_only && !is_direct_invocation(instructions_sysvar)? {
+        return Ok(0);
+    }
+
+    // SOL payouts are only available for SOL programs; the join above already paid for the new accounts
+    let guard = ClaimGuard::new(
+        ctx.accounts.join.participant.to_account_info(),
+        ctx.accounts.vault.to_account_info(),
+        ctx.accounts.join.user.to_account_info(),
+    )?;
+    let join = &mut ctx.accounts.join;
+    require!(join.referral_program.token_mint == Pubkey::default(), ReferralError::InvalidTokenMint);
+
+    let binding = join.referral_program.key();
+    let seeds = &[VAULT_SEED, binding.as_ref(), &[ctx.bumps.vault]];
+    let signer = &[&seeds[..]];
+
+    let vault_balance = ctx.accounts.vault.lamports();
+    let mut stats = ProgramStats::of_queue(join.event_queue.as_deref_mut(), Clock::get()?.unix_timestamp);
+    let (stats, kind) = (stats.as_mut(), PayoutKind::JoinAndClaim);
+    let paid = settle_claim(&mut join.referral_program, &mut join.participant, vault_balance, stats, kind, |amount| {
+        let transfer_ctx = CpiContext::new_with_signer(
+            join.system_program.to_account_info(),
+            Transfer { from: ctx.accounts.vault.to_account_info(), to: join.user.to_account_info() },
+            signer,
+        );
+        transfer(transfer_ctx, amount)
+    })?;
+    guard.finish(paid)?;
+    check_referral_funding(&mut join.referral_program)?;
+    Ok(paid)
 }
@@ -0,0 +1,264 @@
+//! Participants naming their referrer after joining directly.
+//!
+//! A user who followed a referral link but joined with `join_referral_program`, say because a wallet dropped the
+//! link, would leave their referrer uncredited for good. Programs with an attribution grace period let such a
+//! participant name its referrer within that window of joining, as long as it has not earned anything yet: the
+//! referral is then recorded and credited as `join_through_referral` would have, without a sponsor match or
+//! referee boost.
+use crate::{
+    constants::{EVENT_QUEUE_SEED, REFEREE_RECEIPT_SEED, SPONSOR_VAULT_SEED},
+    error::ReferralError,
+    events::JoinEvents,
+    instructions::{
+        check_credit_funding, create_aux_account, credit_milestones_and_referee, credit_referrer_reward,
+        debug_assert_referral_counts, meets_token_requirement, referral_credit, require_collection_nft,
+        verify_link_proof, RentPayer,
+    },
+    state::{event_queue::*, participant::*, referee_receipt::*, referral_program::*},
+};
+use anchor_lang::{prelude::*, system_program::System};
+use anchor_spl::token::TokenAccount;
+
+/// Accounts required for a participant naming its referrer late.
+#[derive(Accounts)]
+pub struct SetReferrerLate<'info> {
+    #[account(mut)]
+    pub referral_program: Box<Account<'info, ReferralProgram>>,
+
+    #[account(
+        seeds = [b"eligibility_criteria", referral_program.key().as_ref()],
+        bump
+    )]
+    pub eligibility_criteria: Account<'info, EligibilityCriteria>,
+
+    /// The signer's participant account, which joined directly
+    #[account(
+        mut,
+        seeds = [
+            b"participant",
+            referral_program.key().as_ref(),
+            user.key().as_ref(),
+        ],
+        bump
+    )]
+    pub participant: Account<'info, Participant>,
+
+    /// The participant the signer names as its referrer
+    #[account(mut)]
+    pub referrer: Account<'info, Participant>,
+
+    /// The participant receiving the referrer's payout split; required when the referrer has one
+    #[account(mut)]
+    pub split_recipient: Option<Account<'info, Participant>>,
+
+    /// CHECK: Receipt recording the referral; must not exist yet, since a wallet is credited as a referee at most
+    /// once per program. Created by the handler through `create_aux_account`
+    /// PDA with seeds: ["referee", referral_program.key(), user.key()]
+    #[account(
+        mut,
+        seeds = [
+            REFEREE_RECEIPT_SEED,
+            referral_program.key().as_ref(),
+            user.key().as_ref(),
+        ],
+        bump
+    )]
+    pub referee_receipt: UncheckedAccount<'info>,
+
+    /// The program's sponsor vault; pays for the referee receipt instead of the user in `RENT_PAYER_SPONSOR` mode
+    /// PDA with seeds: ["sponsor_vault", referral_program.key()]
+    #[account(
+        mut,
+        seeds = [SPONSOR_VAULT_SEED, referral_program.key().as_ref()],
+        bump,
+    )]
+    pub sponsor_vault: Option<SystemAccount<'info>>,
+
+    /// CHECK: The Metaplex metadata of `referrer_collection_nft`; its address, owner and contents are checked
+    /// in the handler. Required when the program gates credits on its collection
+    pub referrer_collection_metadata: Option<UncheckedAccount<'info>>,
+
+    /// The referrer's token account holding an NFT of the program's required collection
+    pub referrer_collection_nft: Option<Account<'info, TokenAccount>>,
+
+    /// The referrer's token account of the mint the program requires referrers to hold; required when it has a
+    /// referrer requirement
+    pub referrer_token_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// The user's token account of the mint the program requires referees to hold; required when it has a
+    /// referee requirement
+    pub referee_token_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// The participant that referred the referrer; required when the referral pays the referrer a milestone bonus
+    /// and the program has a trailing commission
+    #[account(mut)]
+    pub referrer_upline: Option<Box<Account<'info, Participant>>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// Optional event queue of the program; appended to when supplied
+    #[account(
+        mut,
+        seeds = [EVENT_QUEUE_SEED, referral_program.key().as_ref()],
+        bump = event_queue.bump,
+    )]
+    pub event_queue: Option<Box<Account<'info, EventQueue>>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Records `referrer` as the referrer of the signer, which joined directly, and credits the referral.
+///
+/// Only a participant without a referrer that has earned nothing yet (see `Participant::has_earned`) can name one,
+/// within the program's `attribution_grace_seconds` of joining. The referrer must pass the checks a referrer of
+/// `join_through_referral` does, and the referral counts and credits as one made by that join in the same state,
+/// see `referral_credit`; referrals that credit nothing are still recorded. Programs with a link signer reject late
+/// attributions, since no link was followed to present.
+///
+/// # Errors
+/// * `ProgramInactive`, `ProgramEnded`, `ProgramClosing` - If the program no longer takes referrals
+/// * `AttributionNotAllowed` - If the participant has a referrer, has earned anything, or was already credited as
+///   a referee in this program
+/// * `AttributionWindowClosed` - If the program has no grace period or it has passed since the participant joined
+/// * `InvalidReferrer` - If the referrer belongs to another program or is the participant itself
+/// * `ParticipantLeft`, `ParticipantRotated` - If the referrer left the program or was rotated to another wallet
+/// * `LinkProofRequired` - If the program has a link signer
+/// * `CollectionNftRequired`, `ReferrerRequirementNotMet`, `RefereeRequirementNotMet` - As for
+///   `join_through_referral`
+/// * `ReferralRateLimited` - If the referrer's rate-limit window is full and the program's rate limit is strict
+/// * `ProgramUnderfunded` - As for `join_through_referral`
+pub fn set_referrer_late(ctx: Context<SetReferrerLate>) -> Result<()> {
+    let accounts = ctx.accounts;
+    require!(accounts.referral_program.is_active, ReferralError::ProgramInactive);
+    let current_time = Clock::get()?.unix_timestamp;
+    require!(!accounts.referral_program.has_ended(current_time), ReferralError::ProgramEnded);
+    require!(!accounts.referral_program.is_closing(), ReferralError::ProgramClosing);
+
+    // Only a participant that joined directly and has earned nothing can still name its referrer
+    let participant = &accounts.participant;
+    require!(participant.referrer.is_none() && !participant.has_earned(), ReferralError::AttributionNotAllowed);
+    participant.require_current()?;
+    let grace = accounts.referral_program.attribution_grace_seconds;
+    require!(
+        grace > 0 && current_time <= participant.join_time.saturating_add(grace),
+        ReferralError::AttributionWindowClosed
+    );
+    require!(accounts.referee_receipt.owner != &crate::ID, ReferralError::AttributionNotAllowed);
+
+    // The referrer is checked as `join_through_referral` checks it; with no referrals of its own the participant
+    // cannot be upstream of it
+    let referral_program_key = accounts.referral_program.key();
+    let referrer = &mut accounts.referrer;
+    require!(referrer.program == referral_program_key, ReferralError::InvalidReferrer);
+    require!(referrer.key() != accounts.participant.key(), ReferralError::InvalidReferrer);
+    require!(!referrer.has_left(), ReferralError::ParticipantLeft);
+    require!(referrer.rotated_to.is_none(), ReferralError::ParticipantRotated);
+    let criteria = &accounts.eligibility_criteria;
+    verify_link_proof(criteria.link_signer, &referral_program_key, None, &referrer.owner, None, current_time)?;
+    if let (Some(collection), true) = (criteria.required_collection, criteria.collection_gates_credits) {
+        require_collection_nft(
+            accounts.referrer_collection_metadata.as_ref().map(|metadata| metadata.as_ref()),
+            accounts.referrer_collection_nft.as_ref(),
+            &collection,
+            &referrer.owner,
+        )?;
+    }
+    require!(
+        meets_token_requirement(
+            criteria.referrer_requirement.as_ref(),
+            accounts.referrer_token_account.as_deref(),
+            &referrer.owner,
+        ),
+        ReferralError::ReferrerRequirementNotMet
+    );
+    require!(
+        meets_token_requirement(
+            criteria.referee_requirement.as_ref(),
+            accounts.referee_token_account.as_deref(),
+            &accounts.user.key(),
+        ),
+        ReferralError::RefereeRequirementNotMet
+    );
+
+    // The referral counts toward the referrer and the program's raw referrals under the participant's join tag
+    let referrer_key = referrer.key();
+    referrer.raw_referrals = referrer.raw_referrals.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+    referrer.active_referee_count =
+        referrer.active_referee_count.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+    let referral_program = &mut accounts.referral_program;
+    referral_program.record_source_tag(&accounts.participant.source_tag);
+    referral_program.total_referrals_raw =
+        referral_program.total_referrals_raw.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+
+    let participant = &mut accounts.participant;
+    participant.referrer = Some(referrer_key);
+    participant.referral_depth = referrer.referral_depth.saturating_add(1);
+    referral_program.max_observed_depth = referral_program.max_observed_depth.max(participant.referral_depth);
+
+    let user = accounts.user.to_account_info();
+    let sponsor_vault = accounts.sponsor_vault.as_ref().map(|vault| vault.to_account_info());
+    let rent_payer =
+        RentPayer::for_program(referral_program, &user, sponsor_vault.as_ref().zip(ctx.bumps.sponsor_vault))?;
+    let receipt_info = accounts.referee_receipt.to_account_info();
+    create_aux_account(
+        &receipt_info,
+        &[REFEREE_RECEIPT_SEED, referral_program_key.as_ref(), user.key.as_ref(), &[ctx.bumps.referee_receipt]],
+        8 + RefereeReceipt::SIZE,
+        &rent_payer,
+        &accounts.system_program.to_account_info(),
+    )?;
+    let mut receipt = RefereeReceipt {
+        program: referral_program_key,
+        referee: accounts.user.key(),
+        referrer: referrer_key,
+        referee_active: true,
+        credited_at: current_time,
+        source_tag: accounts.participant.source_tag,
+        referrer_link_params: referrer.link_params,
+        bump: ctx.bumps.referee_receipt,
+        ..Default::default()
+    };
+
+    // Credit the referral as a join in the same state would, unless it credits nothing
+    let credit = referral_credit(&accounts.referral_program, &accounts.eligibility_criteria, referrer, current_time)?;
+    let mut reward_amount = 0;
+    if credit.rate_limited {
+        require!(!accounts.eligibility_criteria.rate_limit_strict, ReferralError::ReferralRateLimited);
+    }
+    require!(!credit.program_underfunded, ReferralError::ProgramUnderfunded);
+    if credit.is_credited() {
+        let recorded = referrer.record_window_referral(&accounts.eligibility_criteria, current_time);
+        debug_assert!(recorded, "referral_credit let a rate-limited referral through");
+        let mut events = JoinEvents::new(accounts.referral_program.event_mode, referral_program_key, user.key());
+        credit_referrer_reward(
+            &mut accounts.referral_program,
+            referrer,
+            accounts.split_recipient.as_mut(),
+            &mut receipt,
+            &credit,
+            &mut events,
+        )?;
+        credit_milestones_and_referee(
+            &mut accounts.referral_program,
+            &accounts.eligibility_criteria,
+            referrer,
+            accounts.referrer_upline.as_deref_mut(),
+            &mut accounts.participant,
+            &credit,
+            &mut events,
+        )?;
+        events.finish();
+        reward_amount = credit.reward_amount;
+        check_credit_funding(&mut accounts.referral_program, &accounts.eligibility_criteria)?;
+    } else {
+        msg!("Referral recorded late; no reward credited");
+    }
+
+    if let Some(event_queue) = accounts.event_queue.as_mut() {
+        event_queue.push(EventRecord::KIND_REFERRAL, accounts.user.key(), reward_amount, current_time);
+    }
+    receipt.try_serialize(&mut &mut receipt_info.try_borrow_mut_data()?[..])?;
+    debug_assert_referral_counts(&accounts.referral_program);
+    Ok(())
+}
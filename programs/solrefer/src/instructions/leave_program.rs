@@ -0,0 +1,141 @@
+//! Participants leaving a program.
+//!
+//! Referees keep referencing their referrer's participant account: trailing commissions on what they earn are
+//! credited to it, and `join_through_referral` follows it when rotated. Each participant therefore counts the
+//! referees that joined through it and are still in the program, and its account cannot be closed while any
+//! remain. Forcing the leave instead keeps the account as a tombstone that is credited nothing, which closes in
+//! turn once its last referee has left.
+use crate::{constants::REFEREE_RECEIPT_SEED, error::ReferralError, state::*};
+use anchor_lang::prelude::*;
+
+/// Accounts required for a participant leaving a program.
+#[derive(Accounts)]
+pub struct LeaveProgram<'info> {
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    /// The signer's participant account, closed to the signer
+    #[account(
+        mut,
+        seeds = [
+            b"participant",
+            referral_program.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump
+    )]
+    pub participant: Account<'info, Participant>,
+
+    /// The participant's referrer, whose active referees it leaves; required when it joined through a referral
+    #[account(mut)]
+    pub referrer: Option<Account<'info, Participant>>,
+
+    /// The participant account the referrer was rotated to, whose active referees it leaves instead; required when
+    /// the referrer is a rotation tombstone
+    #[account(mut)]
+    pub rotated_referrer: Option<Account<'info, Participant>>,
+
+    /// The referee receipt of the wallet that joined: the signer's, or for an account rotated in from another
+    /// wallet that wallet's; required when the participant joined through a referral
+    /// PDA with seeds: ["referee", referral_program.key(), referee_receipt.referee]
+    #[account(
+        mut,
+        seeds = [REFEREE_RECEIPT_SEED, referral_program.key().as_ref(), referee_receipt.referee.as_ref()],
+        bump = referee_receipt.bump,
+    )]
+    pub referee_receipt: Option<Account<'info, RefereeReceipt>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+/// Takes the signer out of the program, closing its participant account and returning its rent.
+///
+/// The participant stops counting toward its referrer's active referees. While referees of its own are still in the
+/// program the account is not closed, unless `force` is set: the account is then kept as a tombstone, marked by
+/// `left`, that is credited no trailing commissions and accepts no referrals. A tombstone is closed by leaving again
+/// once its last referee has left or been clawed back. A referrer rotated to a new owner is followed to the account
+/// it was rotated to, which carries its active referees, and a participant rotated in from another wallet leaves
+/// with the receipt of that wallet.
+///
+/// # Arguments
+/// * `ctx` - The context for the LeaveProgram instruction
+/// * `force` - Whether to leave a tombstone if referees still reference the account
+///
+/// # Errors
+/// * `ParticipantRotated` - If the participant account was rotated to a new owner
+/// * `ActiveRefereesRemain` - If referees still reference the participant and `force` is not set, or it already
+///   left and they remain
+/// * `PendingRewardsRemain` - If the participant has pending rewards
+/// * `InvalidReferrer` - If the participant joined through a referral and its referrer or referee receipt is
+///   missing, or the receipt is not that of the wallet that joined
+/// * `ParticipantRotated` - If the referrer was rotated and the account it was rotated to is missing
+pub fn leave_program(ctx: Context<LeaveProgram>, force: bool) -> Result<()> {
+    let participant = &mut ctx.accounts.participant;
+    let already_left = participant.has_left();
+    require!(participant.rotated_to.is_none(), ReferralError::ParticipantRotated);
+    require!(participant.active_referee_count == 0 || (force && !already_left), ReferralError::ActiveRefereesRemain);
+    require!(participant.pending_rewards == 0, ReferralError::PendingRewardsRemain);
+
+    if !already_left {
+        if let Some(referrer_key) = participant.referrer {
+            let referrer = ctx
+                .accounts
+                .referrer
+                .as_mut()
+                .filter(|referrer| referrer.key() == referrer_key)
+                .ok_or(ReferralError::InvalidReferrer)?;
+            // The active referees of a rotated referrer moved with it to the account it was rotated to
+            let referrer = match referrer.rotated_to {
+                Some(rotated_to) => ctx
+                    .accounts
+                    .rotated_referrer
+                    .as_mut()
+                    .filter(|rotated_referrer| rotated_referrer.key() == rotated_to)
+                    .ok_or(ReferralError::ParticipantRotated)?,
+                None => referrer,
+            };
+            let user = ctx.accounts.user.key();
+            let receipt = ctx
+                .accounts
+                .referee_receipt
+                .as_deref_mut()
+                .filter(|receipt| joined_with(participant, user, receipt))
+                .ok_or(ReferralError::InvalidReferrer)?;
+            release_referrer(referrer, receipt);
+        }
+    }
+
+    if participant.active_referee_count > 0 {
+        participant.left = true;
+        participant.pending_owner = None;
+        msg!("{} referees remain; participant left as a tombstone", participant.active_referee_count);
+        return Ok(());
+    }
+    ctx.accounts.participant.close(ctx.accounts.user.to_account_info())
+}
+
+/// Returns true if `receipt` records the referral `participant`, owned by `user`, joined through: its own, or the
+/// receipt of the wallet it was rotated in from, whose participant account it took over.
+fn joined_with(participant: &Participant, user: Pubkey, receipt: &RefereeReceipt) -> bool {
+    match participant.rotated_from {
+        Some(rotated_from) => Participant::address(&participant.program, &receipt.referee) == rotated_from,
+        None => receipt.referee == user,
+    }
+}
+
+/// Removes a leaving participant from its referrer's active referees.
+///
+/// A participant that joined through the referral on its receipt counted toward the referrer unless the referral
+/// was clawed back, and the receipt records that it left. One that joined again after leaving counted toward its
+/// referrer unconditionally.
+fn release_referrer(referrer: &mut Participant, receipt: &mut RefereeReceipt) {
+    let counted = if receipt.referee_active {
+        receipt.referee_active = false;
+        !receipt.clawed_back
+    } else {
+        true
+    };
+    if counted {
+        referrer.active_referee_count = referrer.active_referee_count.saturating_sub(1);
+    }
+}
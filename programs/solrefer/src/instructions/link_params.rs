@@ -0,0 +1,49 @@
+use crate::{constants::LINK_PARAMS_LEN, error::ReferralError, state::*};
+use anchor_lang::prelude::*;
+
+/// Accounts required for setting a participant's landing parameters.
+#[derive(Accounts)]
+pub struct SetLinkParams<'info> {
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"participant",
+            referral_program.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump
+    )]
+    pub participant: Account<'info, Participant>,
+
+    pub user: Signer<'info>,
+}
+
+/// Zero-pads parameters of at most `LINK_PARAMS_LEN` bytes into the participant's link parameters field.
+pub fn encode_link_params(params: &[u8]) -> Result<[u8; LINK_PARAMS_LEN]> {
+    require!(params.len() <= LINK_PARAMS_LEN, ReferralError::InvalidLinkParams);
+    let mut encoded = [0u8; LINK_PARAMS_LEN];
+    encoded[..params.len()].copy_from_slice(params);
+    Ok(encoded)
+}
+
+/// Replaces the landing parameters returned to clients resolving the signer's referral; empty parameters clear them.
+///
+/// The program never interprets the bytes. Referees that already joined keep the parameters snapshotted on their
+/// receipt, so a change only affects future joins.
+///
+/// # Arguments
+/// * `ctx` - The context for the SetLinkParams instruction
+/// * `params` - The new parameters, at most `LINK_PARAMS_LEN` bytes
+///
+/// # Errors
+/// * `ParticipantRotated` - If the signer's participant account was rotated to a new owner
+/// * `ParticipantLeft` - If the signer's participant left the program
+/// * `InvalidLinkParams` - If the parameters are longer than `LINK_PARAMS_LEN` bytes
+pub fn set_link_params(ctx: Context<SetLinkParams>, params: Vec<u8>) -> Result<()> {
+    let participant = &mut ctx.accounts.participant;
+    participant.require_current()?;
+    participant.link_params = encode_link_params(&params)?;
+    Ok(())
+}
@@ -0,0 +1,111 @@
+//! Signed referral links checked when a wallet joins through a referrer.
+//!
+//! A program with a `link_signer` only credits referrers its backend vouched for: the link carries the referrer's
+//! wallet, an expiry and the signer's ed25519 signature over both and the program. The runtime's ed25519 program
+//! verifies the signature in an instruction placed just before the join, and the join reads that instruction back
+//! from the instructions sysvar to check it signed what the link claims. Verifying through the precompile costs the
+//! join no compute for the signature itself.
+use crate::error::ReferralError;
+use anchor_lang::{
+    prelude::*,
+    solana_program::{
+        ed25519_program,
+        sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+    },
+};
+
+/// The size of the header of an ed25519 program instruction: the signature count and a padding byte.
+const ED25519_HEADER_SIZE: usize = 2;
+/// The size of the offsets an ed25519 program instruction holds for each signature.
+const ED25519_OFFSETS_SIZE: usize = 14;
+/// The instruction index an ed25519 program instruction uses for data held in itself.
+const ED25519_SELF_INDEX: u16 = u16::MAX;
+
+/// The size of a link's signed message: the program, the referrer's wallet and the expiry.
+pub const LINK_MESSAGE_SIZE: usize = 32 + 32 + 8;
+
+/// A signed referral link, presented by a wallet joining through it.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LinkProof {
+    /// The wallet of the referrer the link credits
+    pub referrer_owner: Pubkey,
+    /// When the link stops being accepted
+    pub expiry: i64,
+    /// The program's `link_signer` signature over `link_message(program, referrer_owner, expiry)`
+    pub signature: [u8; 64],
+}
+
+/// Returns the message a link signer signs to vouch for `referrer_owner` in `program` until `expiry`.
+pub fn link_message(program: &Pubkey, referrer_owner: &Pubkey, expiry: i64) -> [u8; LINK_MESSAGE_SIZE] {
+    let mut message = [0u8; LINK_MESSAGE_SIZE];
+    message[..32].copy_from_slice(program.as_ref());
+    message[32..64].copy_from_slice(referrer_owner.as_ref());
+    message[64..].copy_from_slice(&expiry.to_le_bytes());
+    message
+}
+
+/// Returns the public key, signature and message of an ed25519 program instruction verifying one signature over
+/// data held in the instruction itself, or `None` for any other instruction data.
+pub fn parse_ed25519_instruction(data: &[u8]) -> Option<(&[u8], &[u8], &[u8])> {
+    if data.len() < ED25519_HEADER_SIZE + ED25519_OFFSETS_SIZE || data[0] != 1 {
+        return None;
+    }
+    let offsets = &data[ED25519_HEADER_SIZE..ED25519_HEADER_SIZE + ED25519_OFFSETS_SIZE];
+    let field = |index: usize| u16::from_le_bytes([offsets[2 * index], offsets[2 * index + 1]]);
+    let (signature_offset, signature_index) = (field(0), field(1));
+    let (public_key_offset, public_key_index) = (field(2), field(3));
+    let (message_offset, message_size, message_index) = (field(4), field(5), field(6));
+    if [signature_index, public_key_index, message_index].iter().any(|index| *index != ED25519_SELF_INDEX) {
+        return None;
+    }
+    let slice = |offset: u16, len: usize| data.get(usize::from(offset)..usize::from(offset).checked_add(len)?);
+    Some((
+        slice(public_key_offset, 32)?,
+        slice(signature_offset, 64)?,
+        slice(message_offset, usize::from(message_size))?,
+    ))
+}
+
+/// Checks the link a wallet joins `program` through when the program has a `link_signer`.
+///
+/// The instruction right before the join must be the ed25519 program verifying `link_signer`'s signature of the
+/// proof over `link_message(program, referrer_owner, expiry)`, the link must not have expired at `now`, and it must
+/// vouch for `referrer_owner`, the wallet of the referrer account passed to the join. Programs without a link
+/// signer accept any join.
+///
+/// # Errors
+/// * `LinkProofRequired` - If the program has a link signer and no proof or instructions sysvar was supplied
+/// * `LinkProofExpired` - If the link's expiry is past
+/// * `InvalidReferrer` - If the link vouches for another referrer
+/// * `InvalidLinkProof` - If the preceding instruction does not verify the signer's signature of the link
+pub fn verify_link_proof(
+    link_signer: Option<Pubkey>,
+    program: &Pubkey,
+    proof: Option<&LinkProof>,
+    referrer_owner: &Pubkey,
+    instructions_sysvar: Option<&AccountInfo>,
+    now: i64,
+) -> Result<()> {
+    let Some(link_signer) = link_signer else {
+        return Ok(());
+    };
+    let (Some(proof), Some(instructions_sysvar)) = (proof, instructions_sysvar) else {
+        return err!(ReferralError::LinkProofRequired);
+    };
+    require!(now <= proof.expiry, ReferralError::LinkProofExpired);
+    require!(proof.referrer_owner == *referrer_owner, ReferralError::InvalidReferrer);
+
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+    let previous_index = current_index.checked_sub(1).ok_or(ReferralError::InvalidLinkProof)?;
+    let verify_ix = load_instruction_at_checked(usize::from(previous_index), instructions_sysvar)?;
+    require!(verify_ix.program_id == ed25519_program::ID, ReferralError::InvalidLinkProof);
+    let (public_key, signature, message) =
+        parse_ed25519_instruction(&verify_ix.data).ok_or(ReferralError::InvalidLinkProof)?;
+    require!(
+        public_key == link_signer.as_ref()
+            && signature == proof.signature.as_slice()
+            && message == link_message(program, referrer_owner, proof.expiry).as_slice(),
+        ReferralError::InvalidLinkProof
+    );
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use crate::{
+    constants::{
+        AUTHORITY_META_SEED, FINAL_REPORT_SEED, MAINTENANCE_CLOSURE_FINALIZED, MAINTENANCE_EXPIRED,
+        MAINTENANCE_REFUNDED, MAINTENANCE_RUNWAY_ALERTS, MAINTENANCE_UNDERFUNDED, SPONSOR_VAULT_SEED,
+    },
+    error::ReferralError,
+    events::MaintenancePerformed,
+    instructions::{
+        check_referral_funding, closure_phase, emit_runway_alerts, finalize_closure, restore_referral_funding,
+        ClosureAccounts, ClosurePhase, VAULT_SEED,
+    },
+    state::*,
+};
+use anchor_lang::prelude::*;
+
+/// Accounts required for cranking a program's maintenance.
+///
+/// The closure accounts are only written when a closure is finalized, but are always passed so a crank never
+/// has to know in advance what is due.
+#[derive(Accounts)]
+pub struct CrankMaintenance<'info> {
+    #[account(mut)]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        mut,
+        seeds = [b"eligibility_criteria", referral_program.key().as_ref()],
+        bump
+    )]
+    pub eligibility_criteria: Account<'info, EligibilityCriteria>,
+
+    /// PDA with seeds: ["vault", referral_program.key()]
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, referral_program.key().as_ref()],
+        bump,
+    )]
+    pub vault: SystemAccount<'info>,
+
+    /// PDA with seeds: ["sponsor_vault", referral_program.key()]
+    #[account(
+        mut,
+        seeds = [SPONSOR_VAULT_SEED, referral_program.key().as_ref()],
+        bump,
+    )]
+    pub sponsor_vault: SystemAccount<'info>,
+
+    /// CHECK: The program authority, checked by address; receives the vaults and rent when a closure is finalized
+    #[account(
+        mut,
+        address = referral_program.authority @ ReferralError::InvalidAuthority,
+    )]
+    pub authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [AUTHORITY_META_SEED, referral_program.authority.as_ref()],
+        bump = authority_meta.bump,
+    )]
+    pub authority_meta: Account<'info, AuthorityMeta>,
+
+    /// CHECK: The final report PDA, created when a closure is finalized; checked by seeds
+    #[account(
+        mut,
+        seeds = [FINAL_REPORT_SEED, referral_program.key().as_ref()],
+        bump
+    )]
+    pub final_report: UncheckedAccount<'info>,
+
+    /// Anyone may crank; pays the final report's rent when a closure is finalized
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Performs whatever time-based maintenance is due on a program and reports it in one `MaintenancePerformed`
+/// event.
+///
+/// Anyone can call it, as often as they like: every action only fires when due, so a crank with nothing to do
+/// changes nothing and reports an empty bitmask. In order, the crank:
+/// - emits the runway alerts for thresholds the available funds have fallen below (`MAINTENANCE_RUNWAY_ALERTS`)
+/// - stops referrals once the uncommitted funds no longer cover a referral reward (`MAINTENANCE_UNDERFUNDED`)
+/// - stops referrals once the program's end time has passed (`MAINTENANCE_EXPIRED`)
+/// - resumes referrals on a program that has not ended once its funds cover a reward again (`MAINTENANCE_REFUNDED`)
+/// - finalizes a closure whose grace period has elapsed, exactly as `close_referral_program` would
+///   (`MAINTENANCE_CLOSURE_FINALIZED`); a closure that cannot be finalized yet is left pending and logged
+///
+/// Returns the bitmask of the actions performed.
+///
+/// # Arguments
+/// * `ctx` - The context for the CrankMaintenance instruction
+pub fn crank_maintenance(ctx: Context<CrankMaintenance>) -> Result<u8> {
+    let now = Clock::get()?.unix_timestamp;
+    let accounts = &mut *ctx.accounts;
+    let referral_program = &mut accounts.referral_program;
+    let program_key = referral_program.key();
+    let mut actions = 0;
+
+    if emit_runway_alerts(referral_program)? != 0 {
+        actions |= MAINTENANCE_RUNWAY_ALERTS;
+    }
+
+    let was_accepting = referral_program.accepting_referrals;
+    let ended = referral_program.has_ended(now);
+    if was_accepting {
+        check_referral_funding(referral_program)?;
+        if !referral_program.accepting_referrals {
+            actions |= MAINTENANCE_UNDERFUNDED;
+        }
+        if ended {
+            referral_program.accepting_referrals = false;
+            msg!("Referral program {} has ended and no longer accepts referrals", program_key);
+            actions |= MAINTENANCE_EXPIRED;
+        }
+    } else if !ended {
+        restore_referral_funding(referral_program)?;
+        if referral_program.accepting_referrals {
+            actions |= MAINTENANCE_REFUNDED;
+        }
+    }
+
+    if closure_due(referral_program, &accounts.final_report, now) {
+        actions |= MAINTENANCE_CLOSURE_FINALIZED;
+    }
+    emit!(MaintenancePerformed {
+        referral_program: program_key,
+        cranker: accounts.cranker.key(),
+        actions_bitmask: actions
+    });
+
+    if actions & MAINTENANCE_CLOSURE_FINALIZED != 0 {
+        finalize_closure(
+            ClosureAccounts {
+                referral_program: &mut accounts.referral_program,
+                eligibility_criteria: &mut accounts.eligibility_criteria,
+                vault: (accounts.vault.to_account_info(), ctx.bumps.vault),
+                sponsor_vault: (accounts.sponsor_vault.to_account_info(), ctx.bumps.sponsor_vault),
+                authority_meta: &mut accounts.authority_meta,
+                final_report: (accounts.final_report.to_account_info(), ctx.bumps.final_report),
+                authority: accounts.authority.to_account_info(),
+                report_payer: accounts.cranker.to_account_info(),
+                system_program: accounts.system_program.to_account_info(),
+            },
+            now,
+        )?;
+    }
+    Ok(actions)
+}
+
+/// Returns true if the program has a closure pending that can be finalized at `now`, logging why a closure past
+/// its grace period cannot be.
+fn closure_due(referral_program: &ReferralProgram, final_report: &AccountInfo, now: i64) -> bool {
+    let Some(effective_at) = referral_program.closure_effective_at() else {
+        return false;
+    };
+    if now < effective_at {
+        return false;
+    }
+    match closure_phase(referral_program, now) {
+        Ok(ClosurePhase::Finalize) if final_report.data_is_empty() => true,
+        Ok(_) => {
+            msg!("Closure not finalized: the program's final report already exists");
+            false
+        }
+        Err(error) => {
+            msg!("Closure not finalized: {}", error);
+            false
+        }
+    }
+}
@@ -8,3 +8,97 @@ pub mod join_through_referral;
 pub use join_through_referral::*;
 pub mod rewards;
 pub use rewards::*;
+pub mod event_queue;
+pub use event_queue::*;
+pub mod payout_split;
+pub use payout_split::*;
+pub mod owner_rotation;
+pub use owner_rotation::*;
+pub mod clawback;
+pub use clawback::*;
+pub mod invite;
+pub use invite::*;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
+pub mod fee_config;
+pub use fee_config::*;
+pub mod contest;
+pub use contest::*;
+pub mod purchase;
+pub use purchase::*;
+pub mod close_token_vault;
+pub use close_token_vault::*;
+pub mod close_program;
+pub use close_program::*;
+pub mod terms;
+pub use terms::*;
+pub mod reserve;
+pub use reserve::*;
+pub mod extend_program;
+pub use extend_program::*;
+pub mod boost;
+pub use boost::*;
+pub mod setup;
+pub use setup::*;
+pub mod statement;
+pub use statement::*;
+pub mod version;
+pub use version::*;
+pub mod claim_guard;
+pub use claim_guard::*;
+pub mod network_config;
+pub use network_config::*;
+pub mod collection_gate;
+pub use collection_gate::*;
+pub mod recount;
+pub use recount::*;
+pub mod transfer_pending;
+pub use transfer_pending::*;
+pub mod preview;
+pub use preview::*;
+pub mod participant_note;
+pub use participant_note::*;
+pub mod funding;
+pub use funding::*;
+pub mod guardian;
+pub use guardian::*;
+pub mod sponsor;
+pub use sponsor::*;
+pub mod join_requirements;
+pub use join_requirements::*;
+pub mod token_requirement;
+pub use token_requirement::*;
+pub mod trailing_commission;
+pub use trailing_commission::*;
+pub mod maintenance;
+pub use maintenance::*;
+pub mod history;
+pub use history::*;
+pub mod settings_log;
+pub use settings_log::*;
+pub mod link_proof;
+pub use link_proof::*;
+pub mod abandonment;
+pub use abandonment::*;
+pub mod region_attestation;
+pub use region_attestation::*;
+pub mod bridge;
+pub use bridge::*;
+pub mod audit;
+pub use audit::*;
+pub mod link_params;
+pub use link_params::*;
+pub mod sponsor_match;
+pub use sponsor_match::*;
+pub mod cleanup;
+pub use cleanup::*;
+pub mod governance;
+pub use governance::*;
+pub mod claim_splitter;
+pub use claim_splitter::*;
+pub mod leave_program;
+pub use leave_program::*;
+pub mod late_attribution;
+pub use late_attribution::*;
+pub mod seeding;
+pub use seeding::*;
@@ -0,0 +1,63 @@
+use crate::{constants::NETWORK_CONFIG_SEED, error::ReferralError, program::Solrefer, state::*};
+use anchor_lang::prelude::*;
+
+/// Accounts required for initializing the network config. Only the program's upgrade authority may do so.
+#[derive(Accounts)]
+pub struct InitializeNetworkConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + NetworkConfig::SIZE,
+        seeds = [NETWORK_CONFIG_SEED],
+        bump
+    )]
+    pub network_config: Account<'info, NetworkConfig>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, Solrefer>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key()) @ ReferralError::InvalidAuthority,
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Creates the network config with the signer as its admin.
+pub fn initialize_network_config(ctx: Context<InitializeNetworkConfig>, limits: NetworkLimits) -> Result<()> {
+    limits.validate()?;
+    let network_config = &mut ctx.accounts.network_config;
+    network_config.admin = ctx.accounts.admin.key();
+    network_config.limits = limits;
+    network_config.bump = ctx.bumps.network_config;
+
+    msg!("Initialized network config with admin {}", network_config.admin);
+    Ok(())
+}
+
+/// Accounts required for updating the network config
+#[derive(Accounts)]
+pub struct UpdateNetworkConfig<'info> {
+    #[account(
+        mut,
+        seeds = [NETWORK_CONFIG_SEED],
+        bump = network_config.bump,
+        has_one = admin @ ReferralError::InvalidAuthority,
+    )]
+    pub network_config: Account<'info, NetworkConfig>,
+
+    pub admin: Signer<'info>,
+}
+
+/// Replaces the network config's limits.
+///
+/// Programs already configured keep their settings; the new limits apply the next time they are validated.
+pub fn update_network_config(ctx: Context<UpdateNetworkConfig>, limits: NetworkLimits) -> Result<()> {
+    limits.validate()?;
+    ctx.accounts.network_config.limits = limits;
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use crate::{error::ReferralError, state::*};
+use anchor_lang::{prelude::*, system_program::System};
+
+/// Accounts required for starting a participant wallet rotation.
+#[derive(Accounts)]
+pub struct InitiateOwnerRotation<'info> {
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"participant",
+            referral_program.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump
+    )]
+    pub participant: Account<'info, Participant>,
+
+    pub user: Signer<'info>,
+}
+
+/// Nominates `new_owner` to take over the signer's participant account.
+///
+/// Calling it again replaces the nomination. Accounts that are themselves rotation targets cannot be rotated,
+/// so a referrer is never more than one hop away from its current account.
+///
+/// # Arguments
+/// * `ctx` - The context for the InitiateOwnerRotation instruction
+/// * `new_owner` - The wallet that will own the new participant account
+///
+/// # Errors
+/// * `InvalidRotation` - If `new_owner` is the current owner or the account was already rotated to or from another
+/// * `ParticipantLeft` - If the participant left the program
+pub fn initiate_owner_rotation(ctx: Context<InitiateOwnerRotation>, new_owner: Pubkey) -> Result<()> {
+    let participant = &mut ctx.accounts.participant;
+    require!(new_owner != participant.owner, ReferralError::InvalidRotation);
+    require!(participant.rotated_to.is_none(), ReferralError::InvalidRotation);
+    require!(!participant.has_left(), ReferralError::ParticipantLeft);
+    require!(participant.rotated_from.is_none(), ReferralError::InvalidRotation);
+
+    participant.pending_owner = Some(new_owner);
+    Ok(())
+}
+
+/// Accounts required for completing a participant wallet rotation.
+#[derive(Accounts)]
+pub struct CompleteOwnerRotation<'info> {
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    /// The participant account being handed over
+    #[account(
+        mut,
+        constraint = old_participant.program == referral_program.key() @ ReferralError::InvalidRotation,
+        constraint = old_participant.pending_owner == Some(new_owner.key()) @ ReferralError::InvalidRotation,
+    )]
+    pub old_participant: Account<'info, Participant>,
+
+    /// The participant account of the new owner
+    #[account(
+        init,
+        payer = new_owner,
+        space = 8 + Participant::SIZE,
+        seeds = [
+            b"participant",
+            referral_program.key().as_ref(),
+            new_owner.key().as_ref()
+        ],
+        bump
+    )]
+    pub new_participant: Account<'info, Participant>,
+
+    #[account(mut)]
+    pub new_owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Moves a participant to the nominated wallet's PDA, keeping its referral history and pending rewards.
+///
+/// The old account becomes a tombstone pointing at the new one: it can no longer claim, and referees that
+/// still reference it credit the new account instead, which also counts them as its active referees. The new
+/// account gets a referral link for the new owner.
+///
+/// # Arguments
+/// * `ctx` - The context for the CompleteOwnerRotation instruction
+///
+/// # Errors
+/// * `InvalidRotation` - If the signer was not nominated by the old account's owner or it was already rotated
+pub fn complete_owner_rotation(ctx: Context<CompleteOwnerRotation>) -> Result<()> {
+    let old_participant = &mut ctx.accounts.old_participant;
+    require!(old_participant.rotated_to.is_none(), ReferralError::InvalidRotation);
+
+    let new_participant = &mut ctx.accounts.new_participant;
+    new_participant.owner = ctx.accounts.new_owner.key();
+    new_participant.program = old_participant.program;
+    new_participant.join_time = old_participant.join_time;
+    new_participant.total_referrals = old_participant.total_referrals;
+    new_participant.raw_referrals = old_participant.raw_referrals;
+    new_participant.total_rewards = old_participant.total_rewards;
+    new_participant.referrer = old_participant.referrer;
+    new_participant.pending_rewards = old_participant.pending_rewards;
+    new_participant.payout_split = old_participant.payout_split;
+    new_participant.referral_depth = old_participant.referral_depth;
+    new_participant.milestones_claimed_bitmap = old_participant.milestones_claimed_bitmap;
+    new_participant.total_attributed_volume = old_participant.total_attributed_volume;
+    new_participant.source_tag = old_participant.source_tag;
+    new_participant.accepted_terms_hash = old_participant.accepted_terms_hash;
+    new_participant.accepted_terms_version = old_participant.accepted_terms_version;
+    new_participant.gross_credited = old_participant.gross_credited;
+    new_participant.cap_clamped = old_participant.cap_clamped;
+    new_participant.clawed_back = old_participant.clawed_back;
+    new_participant.early_redemption_fees = old_participant.early_redemption_fees;
+    new_participant.boost_received = old_participant.boost_received;
+    new_participant.match_received = old_participant.match_received;
+    new_participant.window_start = old_participant.window_start;
+    new_participant.referrals_in_window = old_participant.referrals_in_window;
+    new_participant.record_count = old_participant.record_count;
+    new_participant.locked_until = old_participant.locked_until;
+    new_participant.transferred_out = old_participant.transferred_out;
+    new_participant.authority_note = old_participant.authority_note;
+    new_participant.link_params = old_participant.link_params;
+    new_participant.active_referee_count = old_participant.active_referee_count;
+    new_participant.pending_owner = None;
+    new_participant.rotated_to = None;
+    new_participant.rotated_from = Some(old_participant.key());
+
+    new_participant.set_referral_link(&ctx.accounts.new_owner.key(), ctx.accounts.referral_program.link_format);
+
+    // Pending rewards and active referees moved with the account; the tombstone keeps its history for reference only
+    old_participant.pending_rewards = 0;
+    old_participant.active_referee_count = 0;
+    old_participant.pending_owner = None;
+    old_participant.rotated_to = Some(new_participant.key());
+
+    log_referral_link(new_participant);
+    Ok(())
+}
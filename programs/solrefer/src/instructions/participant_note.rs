@@ -0,0 +1,55 @@
+use crate::{constants::PARTICIPANT_NOTE_LEN, error::ReferralError, events::NoteUpdated, state::*};
+use anchor_lang::{prelude::*, solana_program::hash::hash};
+
+/// Accounts required for writing the authority's note on a participant.
+#[derive(Accounts)]
+pub struct SetParticipantNote<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        mut,
+        constraint = participant.program == referral_program.key() @ ReferralError::InvalidReferrer
+    )]
+    pub participant: Account<'info, Participant>,
+
+    pub authority: Signer<'info>,
+}
+
+/// Zero-pads a note of at most `PARTICIPANT_NOTE_LEN` bytes into the participant's note field.
+pub fn encode_participant_note(note: &str) -> Result<[u8; PARTICIPANT_NOTE_LEN]> {
+    require!(note.len() <= PARTICIPANT_NOTE_LEN, ReferralError::InvalidParticipantNote);
+    let mut encoded = [0u8; PARTICIPANT_NOTE_LEN];
+    encoded[..note.len()].copy_from_slice(note.as_bytes());
+    Ok(encoded)
+}
+
+/// Replaces the note the program authority keeps on a participant; an empty note clears it.
+///
+/// The note is bookkeeping only: no reward logic reads it and the participant cannot change it. The event
+/// carries a hash of the note rather than the note itself to keep the logs small.
+///
+/// # Arguments
+/// * `ctx` - The context for the SetParticipantNote instruction
+/// * `note` - The new note, at most `PARTICIPANT_NOTE_LEN` bytes of UTF-8
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `InvalidReferrer` - If the participant belongs to another program
+/// * `InvalidParticipantNote` - If the note is longer than `PARTICIPANT_NOTE_LEN` bytes
+pub fn set_participant_note(ctx: Context<SetParticipantNote>, note: String) -> Result<()> {
+    ctx.accounts.referral_program.record_authority_action(Clock::get()?.unix_timestamp)?;
+    let participant = &mut ctx.accounts.participant;
+    participant.authority_note = encode_participant_note(&note)?;
+
+    let note_hash = if note.is_empty() { [0u8; 32] } else { hash(note.as_bytes()).to_bytes() };
+    emit!(NoteUpdated {
+        referral_program: ctx.accounts.referral_program.key(),
+        participant: participant.key(),
+        note_hash,
+    });
+    Ok(())
+}
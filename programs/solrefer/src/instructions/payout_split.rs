@@ -0,0 +1,60 @@
+use crate::{constants::MAX_PAYOUT_SPLIT_BPS, error::ReferralError, state::*};
+use anchor_lang::prelude::*;
+
+/// Accounts required for setting or clearing a participant's payout split.
+#[derive(Accounts)]
+pub struct SetPayoutSplit<'info> {
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"participant",
+            referral_program.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump
+    )]
+    pub participant: Account<'info, Participant>,
+
+    /// The recipient's participant account; required unless the split is being cleared
+    #[account(
+        constraint = recipient_participant.program == referral_program.key() @ ReferralError::InvalidSplitRecipient
+    )]
+    pub recipient_participant: Option<Account<'info, Participant>>,
+
+    pub user: Signer<'info>,
+}
+
+/// Routes `bps` basis points of every future referral reward credited to the signer to `recipient`.
+///
+/// Passing `bps = 0` clears the split. Changing or clearing the split only affects future credits; rewards
+/// that are already pending stay where they were credited.
+///
+/// # Arguments
+/// * `ctx` - The context for the SetPayoutSplit instruction
+/// * `recipient` - The wallet of the participant receiving the share
+/// * `bps` - The share in basis points, at most `MAX_PAYOUT_SPLIT_BPS`
+///
+/// # Errors
+/// * `ParticipantRotated` - If the signer's participant account was rotated to a new owner
+/// * `ParticipantLeft` - If the signer's participant left the program
+/// * `InvalidPayoutSplit` - If `bps` exceeds `MAX_PAYOUT_SPLIT_BPS`
+/// * `InvalidSplitRecipient` - If the recipient is the signer or not a participant of the program
+pub fn set_payout_split(ctx: Context<SetPayoutSplit>, recipient: Pubkey, bps: u16) -> Result<()> {
+    let participant = &mut ctx.accounts.participant;
+    participant.require_current()?;
+    if bps == 0 {
+        participant.payout_split = None;
+        return Ok(());
+    }
+
+    require!(bps <= MAX_PAYOUT_SPLIT_BPS, ReferralError::InvalidPayoutSplit);
+    require!(recipient != ctx.accounts.user.key(), ReferralError::InvalidSplitRecipient);
+    let recipient_participant =
+        ctx.accounts.recipient_participant.as_ref().ok_or(ReferralError::InvalidSplitRecipient)?;
+    require!(recipient_participant.owner == recipient, ReferralError::InvalidSplitRecipient);
+
+    participant.payout_split = Some(PayoutSplit { recipient, bps });
+    Ok(())
+}
@@ -0,0 +1,142 @@
+use crate::{
+    constants::LINK_PARAMS_LEN,
+    error::ReferralError,
+    instructions::{meets_token_requirement, referral_credit, ReferralCredit},
+    state::*,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
+
+/// The outcome of a referral through a referrer if the referee joined now, returned by `preview_referral`.
+///
+/// Fields are only ever appended; a layout change gets a new `ReferralPreview*` type.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReferralPreview {
+    /// Credited to the referrer: its share of the referral reward plus any milestone bonuses the referral pays and
+    /// its trailing commission on the sign-up bonus
+    pub referrer_credit: u64,
+    /// Earned by the referee: the sign-up bonus plus the boost its referrer's escrow pays, when supplied
+    pub referee_credit: u64,
+    /// The referrer's rate-limit window is full, so the referral would credit nothing
+    pub would_be_rate_limited: bool,
+    /// The vault's uncommitted funds cover the referral reward and the sign-up bonus
+    pub budget_sufficient: bool,
+    /// The program is not accepting referrals or cannot fund this one, so the join would be rejected with
+    /// `ProgramUnderfunded`
+    pub program_underfunded: bool,
+    /// The referrer does not hold the token amount the program requires of referrers, so the join would be rejected
+    pub referrer_requirement_failed: bool,
+    /// The referee's token account does not meet the program's referee token requirement, so the join would be
+    /// rejected
+    pub referee_requirement_failed: bool,
+    /// The referrer's landing parameters, for the client to apply at sign-up; zeros when it set none
+    pub link_params: [u8; LINK_PARAMS_LEN],
+}
+
+/// Builds the preview of a referral from its credit, with `boost` being what the referrer's escrow would pay.
+pub fn referral_preview(program: &ReferralProgram, credit: &ReferralCredit, boost: u64) -> Result<ReferralPreview> {
+    let referrer_credit = credit
+        .referrer_share
+        .checked_add(credit.milestone_bonus)
+        .and_then(|credit_so_far| credit_so_far.checked_add(credit.referee_commission))
+        .ok_or(ReferralError::NumericOverflow)?;
+    let referee_credit = credit.referee_reward.checked_add(boost).ok_or(ReferralError::NumericOverflow)?;
+    let commitment = credit.reward_amount.checked_add(credit.referee_reward).ok_or(ReferralError::NumericOverflow)?;
+    Ok(ReferralPreview {
+        referrer_credit,
+        referee_credit,
+        would_be_rate_limited: credit.rate_limited,
+        budget_sufficient: commitment <= program.headroom(),
+        program_underfunded: credit.program_underfunded,
+        referrer_requirement_failed: false,
+        referee_requirement_failed: false,
+        link_params: [0u8; LINK_PARAMS_LEN],
+    })
+}
+
+/// Accounts required for the read-only `preview_referral` instruction.
+#[derive(Accounts)]
+pub struct PreviewReferral<'info> {
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        seeds = [b"eligibility_criteria", referral_program.key().as_ref()],
+        bump
+    )]
+    pub eligibility_criteria: Account<'info, EligibilityCriteria>,
+
+    #[account(
+        constraint = referrer.program == referral_program.key() @ ReferralError::InvalidReferrer
+    )]
+    pub referrer: Account<'info, Participant>,
+
+    /// The referrer's boost escrow; its next boost is included in the referee's credit when supplied
+    pub boost_escrow: Option<Account<'info, BoostEscrow>>,
+
+    /// The referrer's token account of the mint the program requires referrers to hold
+    pub referrer_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// The prospective referee's token account of the mint the program requires referees to hold
+    pub referee_token_account: Option<Account<'info, TokenAccount>>,
+}
+
+/// Returns what a new referee joining through the referrer now would credit, without mutating any state.
+///
+/// The credits come from `referral_credit`, the function `join_through_referral` applies. The preview reports
+/// which side of the referral fails the program's token requirements, checking the referee's holding in the
+/// supplied token account whoever owns it, and returns the referrer's landing parameters as they stand now; the
+/// join snapshots them on the referee receipt. Other checks on the accounts a join presents, such as invites, terms,
+/// collection NFTs or an earlier referral of the same wallet, are not part of the preview.
+///
+/// # Errors
+/// * `ProgramInactive` - If the program is not active
+/// * `ProgramEnded` - If the program's end time has passed
+/// * `ProgramClosing` - If the program is pending closure
+/// * `ParticipantRotated` - If the referrer was rotated; preview the account it was rotated to instead
+/// * `ParticipantLeft` - If the referrer left the program
+/// * `InvalidBoostEscrow` - If the boost escrow does not belong to the referrer
+pub fn preview_referral(ctx: Context<PreviewReferral>) -> Result<ReferralPreview> {
+    let referral_program = &ctx.accounts.referral_program;
+    let now = Clock::get()?.unix_timestamp;
+    require!(referral_program.is_active, ReferralError::ProgramInactive);
+    require!(!referral_program.has_ended(now), ReferralError::ProgramEnded);
+    require!(!referral_program.is_closing(), ReferralError::ProgramClosing);
+    let referrer = &ctx.accounts.referrer;
+    require!(referrer.rotated_to.is_none(), ReferralError::ParticipantRotated);
+    require!(!referrer.has_left(), ReferralError::ParticipantLeft);
+
+    let credit = referral_credit(referral_program, &ctx.accounts.eligibility_criteria, referrer, now)?;
+
+    // The join pays a boost only alongside a sign-up bonus, which only credited referrals earn
+    let boost = match ctx.accounts.boost_escrow.as_ref() {
+        Some(boost_escrow) => {
+            require!(
+                boost_escrow.program == referral_program.key() && boost_escrow.participant == referrer.key(),
+                ReferralError::InvalidBoostEscrow
+            );
+            if credit.is_credited() && credit.referee_reward > 0 {
+                boost_escrow.next_boost()
+            } else {
+                0
+            }
+        }
+        None => 0,
+    };
+    let criteria = &ctx.accounts.eligibility_criteria;
+    let referee_token_account = ctx.accounts.referee_token_account.as_ref();
+    let referee = referee_token_account.map(|account| account.owner).unwrap_or_default();
+    Ok(ReferralPreview {
+        referrer_requirement_failed: !meets_token_requirement(
+            criteria.referrer_requirement.as_ref(),
+            ctx.accounts.referrer_token_account.as_ref(),
+            &referrer.owner,
+        ),
+        referee_requirement_failed: !meets_token_requirement(
+            criteria.referee_requirement.as_ref(),
+            referee_token_account,
+            &referee,
+        ),
+        link_params: referrer.link_params,
+        ..referral_preview(referral_program, &credit, boost)?
+    })
+}
@@ -0,0 +1,293 @@
+use crate::{
+    constants::{IDEMPOTENCY_KEY_LEN, MAX_PURCHASE_BATCH},
+    error::ReferralError,
+    events::PurchaseRecorded,
+    instructions::{
+        debug_assert_end_time_cached, pay_trailing_commission, restore_referral_funding, trailing_commission_due,
+        VAULT_SEED,
+    },
+    state::*,
+    validation::require_nonzero_amount,
+};
+use anchor_lang::{
+    prelude::*,
+    system_program::{self, System, Transfer},
+};
+
+/// Accounts required for recording a purchase made by a referred participant.
+#[derive(Accounts)]
+pub struct RecordPurchase<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        seeds = [b"eligibility_criteria", referral_program.key().as_ref()],
+        bump
+    )]
+    pub eligibility_criteria: Account<'info, EligibilityCriteria>,
+
+    /// The participant who made the purchase
+    #[account(
+        constraint = buyer.program == referral_program.key() @ ReferralError::InvalidReferrer,
+        constraint = buyer.referrer == Some(referrer.key()) @ ReferralError::InvalidReferrer,
+    )]
+    pub buyer: Account<'info, Participant>,
+
+    /// The participant who referred the buyer
+    #[account(mut)]
+    pub referrer: Account<'info, Participant>,
+
+    /// The participant who referred the referrer; required when the program has a trailing commission and the
+    /// purchase credits the referrer a reward
+    #[account(mut)]
+    pub referrer_upline: Option<Account<'info, Participant>>,
+
+    pub authority: Signer<'info>,
+}
+
+/// Attributes a purchase to the buyer's referrer and credits the program's revenue share.
+///
+/// The full purchase amount always counts towards the referrer's and the program's attributed volume; the
+/// credited reward is clamped to the referrer's remaining reward cap and the vault's uncommitted funds, and
+/// may be zero. The referrer's own referrer earns its trailing commission on the reward.
+///
+/// # Arguments
+/// * `ctx` - The context for the RecordPurchase instruction
+/// * `amount` - The purchase amount
+/// * `idempotency_key` - Optional key recorded on the program; a call repeating one of its recent keys fails
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `InvalidReferrer` - If the buyer was not referred by `referrer` in this program
+/// * `InvalidPurchaseAmount` - If the amount is zero
+/// * `ReferrerAccountRequired` - If a trailing commission is due and the referrer's referrer was not supplied
+/// * `DuplicateIdempotencyKey` - If the key was used by one of the program's recent authority mutations
+/// * `NumericOverflow` - If calculations result in overflow
+pub fn record_purchase(
+    ctx: Context<RecordPurchase>,
+    amount: u64,
+    idempotency_key: Option<[u8; IDEMPOTENCY_KEY_LEN]>,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let referral_program = &mut ctx.accounts.referral_program;
+    debug_assert_end_time_cached(referral_program, &ctx.accounts.eligibility_criteria);
+    require!(!referral_program.has_ended(now), ReferralError::ProgramEnded);
+    referral_program.record_authority_action(now)?;
+    referral_program.record_idempotency_key(idempotency_key)?;
+
+    let referrer = &mut ctx.accounts.referrer;
+    let reward = credit_purchase(referral_program, &ctx.accounts.eligibility_criteria, referrer, amount)?;
+    let commission = trailing_commission_due(referral_program, &ctx.accounts.eligibility_criteria, referrer, reward)?;
+    pay_trailing_commission(referral_program, referrer.referrer, ctx.accounts.referrer_upline.as_mut(), commission)?;
+
+    emit!(PurchaseRecorded {
+        referral_program: referral_program.key(),
+        referrer: referrer.key(),
+        buyer: ctx.accounts.buyer.key(),
+        amount,
+        reward,
+    });
+    Ok(())
+}
+
+/// Credits the revenue share of a purchase of `amount` to `referrer`, returning the reward.
+///
+/// The reward is clamped to the referrer's remaining reward cap and the vault's uncommitted funds, and the
+/// withheld part is recorded in the referrer's `cap_clamped`; the full amount counts towards the referrer's and
+/// the program's attributed volume either way.
+pub fn credit_purchase(
+    referral_program: &mut ReferralProgram,
+    criteria: &EligibilityCriteria,
+    referrer: &mut Participant,
+    amount: u64,
+) -> Result<u64> {
+    require_nonzero_amount(amount, ReferralError::InvalidPurchaseAmount)?;
+
+    let earned = referrer.total_rewards.checked_add(referrer.pending_rewards).ok_or(ReferralError::NumericOverflow)?;
+    let headroom = referral_program.headroom();
+    let share = criteria.revenue_share(amount)?;
+    let reward = criteria.purchase_reward(amount, earned)?.min(headroom);
+
+    referrer.credit_reward(reward)?;
+    referrer.record_clamped(share - reward)?;
+    referrer.total_attributed_volume =
+        referrer.total_attributed_volume.checked_add(amount).ok_or(ReferralError::NumericOverflow)?;
+
+    referral_program.total_committed =
+        referral_program.total_committed.checked_add(reward).ok_or(ReferralError::NumericOverflow)?;
+    referral_program.total_attributed_volume =
+        referral_program.total_attributed_volume.checked_add(amount).ok_or(ReferralError::NumericOverflow)?;
+    Ok(reward)
+}
+
+/// One purchase of a batch recorded by `record_purchases_batch`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PurchaseEntry {
+    /// The purchase amount in lamports
+    pub amount: u64,
+    /// Index of the buyer's participant account in the instruction's remaining accounts
+    pub buyer_index: u8,
+}
+
+/// Accounts required for recording a batch of purchases.
+///
+/// The buyers' participant accounts and their referrers' (writable) participant accounts follow as remaining
+/// accounts, each passed once however many purchases reference it. When the program has a trailing commission,
+/// the referrers' own referrers follow too (writable) for every purchase that credits its referrer.
+#[derive(Accounts)]
+pub struct RecordPurchasesBatch<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        seeds = [b"eligibility_criteria", referral_program.key().as_ref()],
+        bump
+    )]
+    pub eligibility_criteria: Account<'info, EligibilityCriteria>,
+
+    /// PDA with seeds: ["vault", referral_program.key()]
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, referral_program.key().as_ref()],
+        bump,
+    )]
+    pub vault: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Records up to `MAX_PURCHASE_BATCH` purchases in one transaction, depositing their total into the vault.
+///
+/// Each purchase is credited exactly as by `record_purchase`, in order, so a referrer backing several buyers
+/// hits its reward cap at the same point. Each referrer account is written once. The batch is atomic: the
+/// first invalid purchase fails the whole transaction and its index is logged.
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `InvalidPurchaseBatch` - If the batch is empty, too large or references a missing account
+/// * `PurchaseDepositMismatch` - If `deposit` is not the sum of the purchase amounts
+/// * `SolDepositToTokenProgram` - If the program pays in tokens
+/// * `ProgramEnded` - If the program's end time has passed
+/// * `ProgramClosing` - If the program is pending closure
+/// * `InvalidReferrer` - If a buyer was not referred within this program or its referrer was not passed writable
+/// * `InvalidPurchaseAmount` - If a purchase amount is zero
+/// * `ReferrerAccountRequired` - If a trailing commission is due and the referrer's referrer was not passed
+pub fn record_purchases_batch<'info>(
+    ctx: Context<'_, '_, 'info, 'info, RecordPurchasesBatch<'info>>,
+    purchases: Vec<PurchaseEntry>,
+    deposit: u64,
+) -> Result<()> {
+    require!(!purchases.is_empty() && purchases.len() <= MAX_PURCHASE_BATCH, ReferralError::InvalidPurchaseBatch);
+    let total = purchases
+        .iter()
+        .try_fold(0u64, |total, purchase| total.checked_add(purchase.amount))
+        .ok_or(ReferralError::NumericOverflow)?;
+    require!(deposit == total, ReferralError::PurchaseDepositMismatch);
+
+    let now = Clock::get()?.unix_timestamp;
+    let referral_program = &mut ctx.accounts.referral_program;
+    require!(referral_program.token_mint == Pubkey::default(), ReferralError::SolDepositToTokenProgram);
+    debug_assert_end_time_cached(referral_program, &ctx.accounts.eligibility_criteria);
+    require!(!referral_program.has_ended(now), ReferralError::ProgramEnded);
+    require!(!referral_program.is_closing(), ReferralError::ProgramClosing);
+    referral_program.record_authority_action(now)?;
+
+    system_program::transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            Transfer { from: ctx.accounts.authority.to_account_info(), to: ctx.accounts.vault.to_account_info() },
+        ),
+        deposit,
+    )?;
+    referral_program.credit_deposit(deposit)?;
+    restore_referral_funding(referral_program)?;
+
+    let program_key = referral_program.key();
+    let mut credited: Vec<Account<'info, Participant>> = Vec::with_capacity(purchases.len());
+    for (index, purchase) in purchases.iter().enumerate() {
+        let (referrer, buyer, reward) = credit_batch_entry(
+            referral_program,
+            &ctx.accounts.eligibility_criteria,
+            program_key,
+            ctx.remaining_accounts,
+            &mut credited,
+            purchase,
+        )
+        .inspect_err(|_| msg!("Purchase {} of the batch rejected", index))?;
+        emit!(PurchaseRecorded { referral_program: program_key, referrer, buyer, amount: purchase.amount, reward });
+    }
+
+    for participant in &credited {
+        participant.exit(ctx.program_id)?;
+    }
+
+    msg!("Recorded {} purchases totalling {} lamports", purchases.len(), deposit);
+    Ok(())
+}
+
+/// Credits one purchase of a batch, loading the buyer's referrer, and the referrer's own referrer when a trailing
+/// commission is due, into `credited` on first use.
+///
+/// Returns the referrer and buyer participant keys and the credited reward.
+fn credit_batch_entry<'info>(
+    referral_program: &mut ReferralProgram,
+    criteria: &EligibilityCriteria,
+    program_key: Pubkey,
+    remaining_accounts: &'info [AccountInfo<'info>],
+    credited: &mut Vec<Account<'info, Participant>>,
+    purchase: &PurchaseEntry,
+) -> Result<(Pubkey, Pubkey, u64)> {
+    let buyer_info =
+        remaining_accounts.get(usize::from(purchase.buyer_index)).ok_or(ReferralError::InvalidPurchaseBatch)?;
+    let buyer = Account::<Participant>::try_from(buyer_info)?;
+    require!(buyer.program == program_key, ReferralError::InvalidReferrer);
+    let referrer_key = buyer.referrer.ok_or(ReferralError::InvalidReferrer)?;
+
+    let position = load_credited(program_key, remaining_accounts, credited, referrer_key)?
+        .ok_or(ReferralError::InvalidPurchaseBatch)?;
+    let reward = credit_purchase(referral_program, criteria, &mut credited[position], purchase.amount)?;
+
+    let referrer = &credited[position];
+    let commission = trailing_commission_due(referral_program, criteria, referrer, reward)?;
+    let upline_key = referrer.referrer;
+    if let (Some(upline_key), true) = (upline_key, commission > 0) {
+        let upline = load_credited(program_key, remaining_accounts, credited, upline_key)?;
+        pay_trailing_commission(
+            referral_program,
+            Some(upline_key),
+            upline.map(|index| &mut credited[index]),
+            commission,
+        )?;
+    }
+    Ok((referrer_key, buyer.key(), reward))
+}
+
+/// Returns the index in `credited` of the writable participant account `key` among the remaining accounts, loading
+/// it on first use so each account is written once; `None` if it was not passed.
+fn load_credited<'info>(
+    program_key: Pubkey,
+    remaining_accounts: &'info [AccountInfo<'info>],
+    credited: &mut Vec<Account<'info, Participant>>,
+    key: Pubkey,
+) -> Result<Option<usize>> {
+    if let Some(position) = credited.iter().position(|participant| participant.key() == key) {
+        return Ok(Some(position));
+    }
+    let Some(info) = remaining_accounts.iter().find(|info| info.key() == key) else {
+        return Ok(None);
+    };
+    require!(info.is_writable, ReferralError::InvalidReferrer);
+    let participant = Account::<Participant>::try_from(info)?;
+    require!(participant.program == program_key, ReferralError::InvalidReferrer);
+    credited.push(participant);
+    Ok(Some(credited.len() - 1))
+}
@@ -0,0 +1,73 @@
+use crate::{constants::MAX_RECOUNT_BATCH, error::ReferralError, events::ReferralsRecounted, state::*};
+use anchor_lang::prelude::*;
+
+/// Accounts required for recounting a participant's referrals; the referee receipts to check follow in the
+/// remaining accounts.
+#[derive(Accounts)]
+pub struct RecountReferrals<'info> {
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        mut,
+        constraint = participant.program == referral_program.key() @ ReferralError::InvalidReferralRecord
+    )]
+    pub participant: Account<'info, Participant>,
+}
+
+/// Checks a batch of a participant's referee receipts and, when the batch covers all of them, corrects its
+/// `total_referrals` to the number that were not clawed back.
+///
+/// Anyone can call it. Receipts credited before a rotation name the account it was rotated from and count for
+/// it too. A batch covers every receipt when it holds `record_count` of them; a partial batch is only checked
+/// and corrects nothing, so only participants with at most `MAX_RECOUNT_BATCH` receipts can be corrected. A
+/// participant without receipts is recounted with an empty batch.
+///
+/// # Arguments
+/// * `ctx` - The context for the RecountReferrals instruction, with the receipts as remaining accounts
+///
+/// # Errors
+/// * `ParticipantRotated` - If the participant account was rotated to a new owner
+/// * `ParticipantLeft` - If the participant left the program
+/// * `InvalidRecountBatch` - If the batch holds more than `MAX_RECOUNT_BATCH` receipts or repeats one
+/// * `InvalidReferralRecord` - If a receipt is of another program or was not counted for this participant
+pub fn recount_referrals<'info>(ctx: Context<'_, '_, 'info, 'info, RecountReferrals<'info>>) -> Result<()> {
+    let participant = &mut ctx.accounts.participant;
+    participant.require_current()?;
+
+    let records = ctx.remaining_accounts;
+    require!(records.len() <= MAX_RECOUNT_BATCH, ReferralError::InvalidRecountBatch);
+
+    let program_key = ctx.accounts.referral_program.key();
+    let participant_key = participant.key();
+    let mut verified = 0u64;
+    for (index, record_info) in records.iter().enumerate() {
+        require!(
+            !records[..index].iter().any(|previous| previous.key == record_info.key),
+            ReferralError::InvalidRecountBatch
+        );
+        let receipt = Account::<RefereeReceipt>::try_from(record_info)?;
+        let referrer_matches =
+            receipt.referrer == participant_key || Some(receipt.referrer) == participant.rotated_from;
+        require!(
+            receipt.program == program_key && receipt.counted && referrer_matches,
+            ReferralError::InvalidReferralRecord
+        );
+        if !receipt.clawed_back {
+            verified += 1;
+        }
+    }
+
+    if records.len() as u64 != participant.record_count {
+        msg!(
+            "Checked {} of {} referee receipts; a partial batch corrects nothing",
+            records.len(),
+            participant.record_count
+        );
+        return Ok(());
+    }
+
+    let old = participant.total_referrals;
+    participant.total_referrals = verified;
+    emit!(ReferralsRecounted { referral_program: program_key, participant: participant_key, old, new: verified });
+    Ok(())
+}
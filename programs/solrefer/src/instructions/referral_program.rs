@@ -1,27 +1,47 @@
-use crate::{constants::*, error::*, state::*};
+use crate::{
+    constants::*,
+    error::*,
+    events::{check_field, flag_field, ProgramField, ProgramStatusChanged, ValidationCode},
+    instructions::{
+        current_settings, cuts_rewards, propose_settings_change, record_settings_change, restore_referral_funding,
+        settings_changes, validate_guardian, GuardianConfig, TOKEN_VAULT_SEED, VAULT_SEED,
+    },
+    state::*,
+};
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Mint, Token, TokenAccount};
+use anchor_lang::system_program::{self, Transfer};
+use anchor_spl::token::{self, Mint, Token, TokenAccount};
 
 /// Accounts for creating a new referral program.
 ///
 /// This struct defines the accounts required for the `create_referral_program` instruction.
 /// It includes the following accounts:
 ///
-/// - `referral_program`: The account that will store the referral program data.
+/// - `referral_program`: The account that will store the referral program data, one per authority and
+///   `program_index`.
 /// - `eligibility_criteria`: The account that will store the eligibility criteria for the referral program.
+/// - `final_report`: The final report PDA of the program's address, which must not have been written.
 /// - `token_mint_info`: An optional account for the token mint to be used for payments. If not provided, the program
 ///   will use native SOL.
+/// - `fee_config`: The protocol fee config PDA, which may not be initialized yet.
+/// - `network_config`: The network config PDA, which may not be initialized yet.
+/// - `authority_meta`: The authority's program counter, created on the authority's first program.
+/// - `treasury`: The fee config treasury, required when the authority owes a creation fee.
 /// - `authority`: The signer account that will create the referral program.
 /// - `system_program`: The system program account.
 /// - `token_program`: An optional token program account.
 #[derive(Accounts)]
-#[instruction(token_mint: Option<Pubkey>)]
+#[instruction(program_index: u64, token_mint: Option<Pubkey>)]
 pub struct CreateReferralProgram<'info> {
     #[account(
         init,
         payer = authority,
         space = 8 + ReferralProgram::SIZE,
-        seeds = [b"referral_program", authority.key().as_ref()],
+        seeds = [
+            REFERRAL_PROGRAM_SEED,
+            authority.key().as_ref(),
+            ReferralProgram::index_seed(&program_index.to_le_bytes()),
+        ],
         bump
     )]
     pub referral_program: Account<'info, ReferralProgram>,
@@ -35,14 +55,44 @@ pub struct CreateReferralProgram<'info> {
     )]
     pub eligibility_criteria: Account<'info, EligibilityCriteria>,
 
+    /// CHECK: The final report a program closed earlier at this address would have left; checked by seeds and
+    /// required to be uncreated, so an index is never reused once its program was closed
+    #[account(
+        seeds = [FINAL_REPORT_SEED, referral_program.key().as_ref()],
+        bump
+    )]
+    pub final_report: UncheckedAccount<'info>,
+
     /// Optional token mint account. If provided, the program will use this token for payments
     /// If not provided (None), the program will use native SOL
     #[account(
         mut,
-        constraint = token_mint.map_or(true, |mint| mint == token_mint_info.key())
+        constraint = token_mint.is_none_or(|mint| mint == token_mint_info.key())
     )]
     pub token_mint_info: Option<Account<'info, Mint>>,
 
+    /// CHECK: The fee config PDA; creation is free and unlimited while it is uninitialized, and it is
+    /// deserialized in the handler once this program owns it
+    #[account(seeds = [FEE_CONFIG_SEED], bump)]
+    pub fee_config: UncheckedAccount<'info>,
+
+    /// CHECK: The network config PDA; the compile-time limits apply while it is uninitialized, and it is
+    /// deserialized in the handler once this program owns it
+    #[account(seeds = [NETWORK_CONFIG_SEED], bump)]
+    pub network_config: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + AuthorityMeta::SIZE,
+        seeds = [AUTHORITY_META_SEED, authority.key().as_ref()],
+        bump
+    )]
+    pub authority_meta: Account<'info, AuthorityMeta>,
+
+    #[account(mut)]
+    pub treasury: Option<SystemAccount<'info>>,
+
     #[account(mut)]
     pub authority: Signer<'info>,
 
@@ -58,64 +108,190 @@ pub struct CreateReferralProgram<'info> {
 ///
 /// # Parameters
 /// - `ctx`: The context for the `CreateReferralProgram` accounts.
+/// - `program_index`: Tells this program apart from the authority's others; any index the authority has not used
+///   yet, a closed program's included since its final report stays at the address. It comes first so the account's
+///   seeds can use it.
 /// - `token_mint`: An optional token mint account to be used for payments. If not provided, the program will use native
 ///   SOL.
-/// - `fixed_reward_amount`: The fixed reward amount for referrals.
-/// - `locked_period`: The locked period for referral rewards.
-/// - `early_redemption_fee`: The fee for early redemption of referral rewards.
-/// - `base_reward`: The base reward amount for referrals.
-/// - `tier1_threshold`: The threshold for the first tier of referral rewards.
-/// - `tier1_reward`: The reward amount for the first tier of referrals.
-/// - `tier2_threshold`: The threshold for the second tier of referral rewards.
-/// - `tier2_reward`: The reward amount for the second tier of referrals.
-/// - `max_reward_cap`: The maximum total reward cap for the referral program.
-/// - `revenue_share_percent`: The percentage of revenue to be shared with referrers.
-/// - `required_token`: An optional token required for eligibility.
-/// - `min_token_amount`: The minimum amount of the required token needed for eligibility.
-/// - `program_end_time`: An optional end time for the referral program.
+/// - `fixed_reward_amount`: The fixed reward amount for referrals, expressed in `reward_denomination`.
+/// - `reward_denomination`: `REWARD_DENOMINATION_RAW` for raw units or `REWARD_DENOMINATION_USD_CENTS` for US cents
+///   converted with the token mint's decimals (token programs only).
+/// - `start_inactive`: If true, the program is created with `is_active = false` so it can be reviewed and funded
+///   before going live through `activate_program`.
+/// - `guardian`: An optional guardian key and withdrawal delay threshold; fixed for the program's lifetime.
+/// - `withdrawal_destinations`: Addresses besides the authority that withdrawals may pay out to; all-default leaves
+///   withdrawals unrestricted. Only `set_withdrawal_destinations`, signed by the guardian too, can change it later.
+/// - `program_end_time`: An optional end time for the referral program; `None` creates an open-ended program.
+/// - `settings_locked_until`: An optional time before which the authority cannot change the program's settings,
+///   criteria or funds; it cannot be moved once the program exists.
+/// - `settings`: Optional settings written to the program and its criteria at creation, such as the locked period,
+///   early redemption fee, reward tiers and token requirements. They pass `validate_program_settings` and must
+///   repeat `fixed_reward_amount` and `program_end_time`.
+///
+/// Once the protocol fee config is initialized, the authority pays its creation fee (unless exempt) into the
+/// treasury and may not hold more live programs than the config allows.
 ///
 /// # Returns
 /// A `Result` indicating whether the referral program was created successfully.
 #[allow(clippy::too_many_arguments)]
 pub fn create_referral_program(
-    ctx: Context<CreateReferralProgram>,
+    mut ctx: Context<CreateReferralProgram>,
+    program_index: u64,
     token_mint: Option<Pubkey>,
     fixed_reward_amount: u64,
-    program_end_time: i64,
+    program_end_time: Option<i64>,
+    reward_denomination: u8,
+    start_inactive: bool,
+    terms_hash: [u8; 32],
+    guardian: Option<GuardianConfig>,
+    withdrawal_destinations: [Pubkey; MAX_WITHDRAWAL_DESTINATIONS],
+    settings_locked_until: Option<i64>,
+    settings: Option<ProgramSettings>,
 ) -> Result<()> {
-    // Validate base parameters
-    require!(fixed_reward_amount >= MIN_REWARD_AMOUNT, ReferralError::InvalidRewardAmount);
+    require!(ctx.accounts.final_report.data_is_empty(), ReferralError::FinalReportExists);
 
+    // Validate base parameters
     let current_time = Clock::get()?.unix_timestamp;
-    require!(program_end_time > current_time, ReferralError::InvalidEndTime);
+    let limits = NetworkLimits::load(&ctx.accounts.network_config, ctx.program_id)?;
+    validate_reward_params(fixed_reward_amount, program_end_time, current_time, &limits)?;
+    if let Some(settings) = &settings {
+        check_field(
+            settings.fixed_reward_amount == fixed_reward_amount,
+            ProgramField::FixedRewardAmount,
+            ValidationCode::Relationship,
+            ReferralError::CreationSettingsMismatch,
+        )?;
+        check_field(
+            settings.program_end_time == program_end_time,
+            ProgramField::ProgramEndTime,
+            ValidationCode::Relationship,
+            ReferralError::CreationSettingsMismatch,
+        )?;
+        validate_program_settings(settings, current_time, &limits)?;
+    }
+    if let Some(locked_until) = settings_locked_until {
+        validate_settings_lock(locked_until, program_end_time, current_time)?;
+    }
+    if let Some(config) = &guardian {
+        validate_guardian(config, ctx.accounts.authority.key())?;
+    }
+
+    // Capture the mint decimals so denominated rewards can be converted at credit time
+    let token_decimals = match (token_mint, &ctx.accounts.token_mint_info) {
+        (Some(_), Some(mint)) => mint.decimals,
+        (Some(_), None) => return err!(ReferralError::InvalidTokenMint),
+        (None, _) => 0,
+    };
+    match reward_denomination {
+        REWARD_DENOMINATION_RAW => {}
+        REWARD_DENOMINATION_USD_CENTS => {
+            check_field(
+                token_mint.is_some(),
+                ProgramField::RewardDenomination,
+                ValidationCode::Relationship,
+                ReferralError::InvalidRewardDenomination,
+            )?;
+            flag_field(
+                usd_cents_to_raw(fixed_reward_amount, token_decimals),
+                ProgramField::FixedRewardAmount,
+                ValidationCode::Relationship,
+            )?;
+        }
+        _ => {
+            return check_field(
+                false,
+                ProgramField::RewardDenomination,
+                ValidationCode::Unsupported,
+                ReferralError::InvalidRewardDenomination,
+            )
+        }
+    }
+
+    collect_creation_fee(&mut ctx)?;
 
     // Set up referral program
     let referral_program = &mut ctx.accounts.referral_program;
     referral_program.authority = ctx.accounts.authority.key();
+    referral_program.program_index = program_index;
     referral_program.token_mint = token_mint.unwrap_or_default();
     referral_program.fixed_reward_amount = fixed_reward_amount;
-    referral_program.is_active = true;
+    referral_program.reward_denomination = reward_denomination;
+    referral_program.token_decimals = token_decimals;
+    referral_program.is_active = !start_inactive;
     referral_program.bump = ctx.bumps.referral_program;
+    referral_program.program_end_time = program_end_time;
+    referral_program.terms_hash = terms_hash;
+    referral_program.link_format = LINK_FORMAT_SLUG;
+    if let Some(config) = guardian {
+        referral_program.guardian = config.guardian;
+        referral_program.withdrawal_delay_threshold = config.withdrawal_delay_threshold;
+    }
+    referral_program.allowed_withdrawal_destinations = withdrawal_destinations;
+    referral_program.settings_locked_until = settings_locked_until.unwrap_or_default();
+    referral_program.last_authority_action = current_time;
+
+    // A program created active goes live with the criteria it was created with; a SOL vault needs no setup
+    referral_program.setup_state = ReferralProgram::SETUP_CREATED;
+    if token_mint.is_none() {
+        referral_program.setup_state |= ReferralProgram::SETUP_VAULT_INITIALIZED;
+    }
+    if !start_inactive {
+        referral_program.setup_state |= ReferralProgram::SETUP_CRITERIA_SET | ReferralProgram::SETUP_ACTIVATED;
+    }
 
     // Set up eligibility criteria
     let criteria = &mut ctx.accounts.eligibility_criteria;
-    let clock = Clock::get()?;
-
-
-    criteria.program_start_time = clock.unix_timestamp;
+    criteria.program_start_time = current_time;
     criteria.program_end_time = program_end_time;
+    criteria.last_updated = current_time;
+    if let Some(settings) = &settings {
+        apply_program_settings(referral_program, criteria, settings, current_time)?;
+    }
 
-    criteria.is_active = true;
-    criteria.last_updated = clock.unix_timestamp;
+    // A new program holds no funds, so it takes referrals only once a deposit covers a reward
+    referral_program.accepting_referrals = referral_program.can_fund_referral()?;
 
     msg!("Created referral program with authority: {:?}", referral_program.authority);
     Ok(())
 }
 
+/// Charges the protocol creation fee and counts the new program against the authority's limit.
+fn collect_creation_fee(ctx: &mut Context<CreateReferralProgram>) -> Result<()> {
+    let fee_config_info = ctx.accounts.fee_config.to_account_info();
+    let fee_config = if fee_config_info.owner == ctx.program_id && !fee_config_info.data_is_empty() {
+        FeeConfig::try_deserialize(&mut &fee_config_info.try_borrow_data()?[..])?
+    } else {
+        FeeConfig::default()
+    };
+
+    let authority_meta = &mut ctx.accounts.authority_meta;
+    authority_meta.authority = ctx.accounts.authority.key();
+    authority_meta.bump = ctx.bumps.authority_meta;
+    authority_meta.register_program(fee_config.max_programs_per_authority)?;
+
+    let fee = fee_config.creation_fee_for(&ctx.accounts.authority.key());
+    if fee == 0 {
+        return Ok(());
+    }
+    let treasury = ctx.accounts.treasury.as_ref().ok_or(ReferralError::CreationFeeRequired)?;
+    require_keys_eq!(treasury.key(), fee_config.treasury, ReferralError::CreationFeeRequired);
+    system_program::transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            Transfer { from: ctx.accounts.authority.to_account_info(), to: treasury.to_account_info() },
+        ),
+        fee,
+    )?;
+    ctx.accounts.referral_program.total_fees_paid = fee;
+    msg!("Collected creation fee of {} lamports", fee);
+    Ok(())
+}
+
 /// Accounts required for the `SetEligibilityCriteria` instruction.
 ///
 /// - `eligibility_criteria`: The account that stores the eligibility criteria for the referral program.
 /// - `referral_program`: The referral program account, which must have the same authority as the signer.
+/// - `network_config`: The network config PDA, which may not be initialized yet.
 /// - `authority`: The signer account that has authority over the referral program.
 /// - `system_program`: The system program account.
 #[derive(Accounts)]
@@ -133,6 +309,11 @@ pub struct SetEligibilityCriteria<'info> {
     )]
     pub referral_program: Account<'info, ReferralProgram>,
 
+    /// CHECK: The network config PDA; the compile-time limits apply while it is uninitialized, and it is
+    /// deserialized in the handler once this program owns it
+    #[account(seeds = [NETWORK_CONFIG_SEED], bump)]
+    pub network_config: UncheckedAccount<'info>,
+
     #[account(mut)]
     pub authority: Signer<'info>,
 
@@ -154,9 +335,9 @@ pub struct SetEligibilityCriteria<'info> {
 /// * `tier2_reward` - The reward amount for the second tier of the referral program.
 /// * `max_reward_cap` - The maximum reward cap for the referral program.
 /// * `revenue_share_percent` - The revenue share percentage for the referral program.
-/// * `required_token` - The token required for participation in the referral program.
-/// * `min_token_amount` - The minimum token amount required for participation in the referral program.
-/// * `program_end_time` - The end time for the referral program.
+/// * `referrer_requirement` - The token holding required of referrers, or `None` for no requirement.
+/// * `referee_requirement` - The token holding required of referees, or `None` for no requirement.
+/// * `program_end_time` - The end time for the referral program, or `None` for an open-ended program.
 ///
 /// # Returns
 /// A `Result` indicating whether the operation was successful.
@@ -170,19 +351,49 @@ pub fn set_eligibility_criteria(
     tier2_reward: u64,
     max_reward_cap: u64,
     revenue_share_percent: u64,
-    required_token: Option<Pubkey>,
-    min_token_amount: u64,
-    program_end_time: i64,
+    referrer_requirement: Option<TokenRequirement>,
+    referee_requirement: Option<TokenRequirement>,
+    program_end_time: Option<i64>,
 ) -> Result<()> {
     let criteria = &mut ctx.accounts.eligibility_criteria;
     let clock = Clock::get()?;
+    let limits = NetworkLimits::load(&ctx.accounts.network_config, ctx.program_id)?;
 
     // Validate parameters
-    require!(base_reward >= MIN_REWARD_AMOUNT, ReferralError::InvalidRewardAmount);
-    require!(tier1_reward >= base_reward, ReferralError::InvalidTierReward);
-    require!(tier2_reward >= tier1_reward, ReferralError::InvalidTierReward);
-    require!(tier2_threshold > tier1_threshold, ReferralError::InvalidTierThreshold);
-    require!(revenue_share_percent <= MAX_FEE_PERCENTAGE, ReferralError::InvalidFeeAmount);
+    check_field(
+        base_reward >= limits.min_reward_amount,
+        ProgramField::BaseReward,
+        ValidationCode::TooLow,
+        ReferralError::InvalidRewardAmount,
+    )?;
+    check_field(
+        tier1_reward >= base_reward,
+        ProgramField::Tier1Reward,
+        ValidationCode::Relationship,
+        ReferralError::InvalidTierReward,
+    )?;
+    check_field(
+        tier2_reward >= tier1_reward,
+        ProgramField::Tier2Reward,
+        ValidationCode::Relationship,
+        ReferralError::InvalidTierReward,
+    )?;
+    check_field(
+        tier2_threshold > tier1_threshold,
+        ProgramField::Tier2Threshold,
+        ValidationCode::Relationship,
+        ReferralError::InvalidTierThreshold,
+    )?;
+    check_field(
+        revenue_share_percent <= limits.max_fee_percentage,
+        ProgramField::RevenueSharePercent,
+        ValidationCode::TooHigh,
+        ReferralError::InvalidFeeAmount,
+    )?;
+    validate_token_requirements(referrer_requirement, referee_requirement)?;
+    if let Some(end_time) = program_end_time {
+        validate_program_duration(end_time, clock.unix_timestamp, &limits)?;
+    }
 
     // Set reward structure
     criteria.base_reward = base_reward;
@@ -194,15 +405,13 @@ pub fn set_eligibility_criteria(
     criteria.revenue_share_percent = revenue_share_percent;
 
     // Set requirements
-    criteria.required_token = required_token;
-    criteria.min_token_amount = min_token_amount;
+    criteria.referrer_requirement = referrer_requirement;
+    criteria.referee_requirement = referee_requirement;
 
     // Set time parameters
     criteria.program_start_time = clock.unix_timestamp;
     criteria.program_end_time = program_end_time;
-
-    // Update status
-    criteria.is_active = true;
+    ctx.accounts.referral_program.program_end_time = program_end_time;
     criteria.last_updated = clock.unix_timestamp;
 
     Ok(())
@@ -215,20 +424,19 @@ pub fn set_eligibility_criteria(
 /// The vault is a Program Derived Address (PDA) with seeds ["token_vault", referral_program.key()].
 ///
 /// Required accounts:
-/// - `referral_program`: The referral program account that must be active and token-based
+/// - `referral_program`: The referral program account that must be token-based; it may still be inactive
 /// - `token_vault`: The PDA token account that will be initialized to store deposited tokens
 /// - `token_mint`: The mint of the token that matches the referral program's configuration
 /// - `authority`: The signer with authority over the referral program
 /// - `system_program`: Required for account creation
 /// - `token_program`: Required for token account initialization
-/// - `rent`: Required for rent-exempt account creation
 #[derive(Accounts)]
 pub struct InitializeTokenVault<'info> {
     #[account(
         mut,
-        constraint = referral_program.is_active @ ReferralError::ProgramInactive,
         has_one = authority @ ReferralError::InvalidAuthority,
         constraint = referral_program.token_mint != Pubkey::default() @ ReferralError::InvalidTokenMint,
+        constraint = !referral_program.token_vault_closed @ ReferralError::TokenVaultClosed,
     )]
     pub referral_program: Account<'info, ReferralProgram>,
 
@@ -255,7 +463,6 @@ pub struct InitializeTokenVault<'info> {
 
     pub system_program: Program<'info, System>,
     pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
 }
 
 /// Initializes the token vault for a token-based referral program.
@@ -263,7 +470,7 @@ pub struct InitializeTokenVault<'info> {
 /// This instruction is a crucial step in setting up a token-based referral program:
 /// 1. It must be called after creating a referral program with a token mint
 /// 2. It creates and initializes a PDA token account that will hold all deposited tokens
-/// 3. It must be completed before any token deposits can be made to the program
+/// 3. It must be completed before any token deposits can be made to the program or the program is activated
 ///
 /// The initialization process:
 /// - Creates a new token account as a PDA (Program Derived Address)
@@ -272,15 +479,15 @@ pub struct InitializeTokenVault<'info> {
 ///
 /// # Arguments
 /// * `ctx` - Contains all required accounts including:
-///   - The referral program that must be active and configured for tokens
+///   - The referral program that must be configured for tokens
 ///   - The token vault PDA that will be initialized
 ///   - The token mint that must match the program's configuration
 ///   - The authority who must be the program's authority
 ///
 /// # Errors
-/// * `ProgramInactive` - If the referral program is not active
 /// * `InvalidAuthority` - If the signer is not the program authority
 /// * `InvalidTokenMint` - If the referral program is not configured for tokens
+/// * `TokenVaultClosed` - If `close_token_vault` already closed the program's vault
 ///
 /// # Example Flow
 /// ```ignore
@@ -289,23 +496,294 @@ pub struct InitializeTokenVault<'info> {
 /// 3. Users can then deposit tokens to the program
 /// ```
 pub fn initialize_token_vault(ctx: Context<InitializeTokenVault>) -> Result<()> {
-    msg!("Initialized token vault for referral program {}", ctx.accounts.referral_program.key());
+    let referral_program = &mut ctx.accounts.referral_program;
+    referral_program.record_authority_action(Clock::get()?.unix_timestamp)?;
+    referral_program.token_vault_initialized = true;
+    referral_program.setup_state |= ReferralProgram::SETUP_VAULT_INITIALIZED;
+    msg!("Initialized token vault for referral program {}", referral_program.key());
+    Ok(())
+}
+
+/// Accounts required for activating a referral program that was created inactive.
+#[derive(Accounts)]
+pub struct ActivateProgram<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        seeds = [b"eligibility_criteria", referral_program.key().as_ref()],
+        bump
+    )]
+    pub eligibility_criteria: Account<'info, EligibilityCriteria>,
+
+    /// The vault that holds SOL deposits
+    /// PDA with seeds: ["vault", referral_program.key()]
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, referral_program.key().as_ref()],
+        bump,
+    )]
+    pub vault: SystemAccount<'info>,
+
+    /// CHECK: The token vault PDA of a token program; checked to be an initialized token account in the handler
+    /// so a missing vault is reported as incomplete setup.
+    #[account(
+        mut,
+        seeds = [TOKEN_VAULT_SEED, referral_program.key().as_ref()],
+        bump,
+    )]
+    pub token_vault: Option<UncheckedAccount<'info>>,
+
+    /// The authority's token account, required for token deposits
+    #[account(
+        mut,
+        constraint = depositor_token_account.mint == referral_program.token_mint &&
+                     depositor_token_account.owner == authority.key() @ ReferralError::InvalidTokenAccounts
+    )]
+    pub depositor_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+/// Activates a referral program, depositing `initial_deposit` into its vault first when non-zero.
+///
+/// Unlike the deposit instructions, a zero `initial_deposit` is accepted and means no deposit.
+///
+/// A program created inactive must have its criteria configured with `update_program_settings` first, and a
+/// token program its vault initialized.
+///
+/// # Arguments
+/// * `ctx` - The context for the ActivateProgram instruction
+/// * `initial_deposit` - The amount to deposit, in lamports for SOL programs or token units for token programs
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `CriteriaNotSet` - If the criteria of a program created inactive were never configured
+/// * `TokenVaultNotInitialized` - If a token program's vault has not been initialized
+/// * `ProgramSetupIncomplete` - If the program's end time has passed; an open-ended program needs no end time
+/// * `InvalidTokenAccounts` - If a token deposit is requested without the depositor token account or token program
+/// * `NumericOverflow` - If the total available rewards overflow
+/// * `ProgramClosing` - If the program is pending closure
+pub fn activate_program(ctx: Context<ActivateProgram>, initial_deposit: u64) -> Result<()> {
+    require!(!ctx.accounts.referral_program.is_closing(), ReferralError::ProgramClosing);
+    let current_time = Clock::get()?.unix_timestamp;
+    ctx.accounts.referral_program.record_authority_action(current_time)?;
+    let is_token_program = ctx.accounts.referral_program.token_mint != Pubkey::default();
+
+    // Mandatory settings must be complete before participants can join
+    let referral_program = &ctx.accounts.referral_program;
+    referral_program.require_setup_step(ReferralProgram::SETUP_CRITERIA_SET, ReferralError::CriteriaNotSet)?;
+    if ctx.accounts.eligibility_criteria.program_end_time.is_some_and(|end| end <= current_time) {
+        msg!("Setup incomplete: eligibility criteria has no program end time in the future");
+        return err!(ReferralError::ProgramSetupIncomplete);
+    }
+    if is_token_program {
+        referral_program
+            .require_setup_step(ReferralProgram::SETUP_VAULT_INITIALIZED, ReferralError::TokenVaultNotInitialized)?;
+        let vault_initialized =
+            ctx.accounts.token_vault.as_ref().is_some_and(|vault| vault.owner == &token::ID && !vault.data_is_empty());
+        require!(vault_initialized, ReferralError::TokenVaultNotInitialized);
+    }
+
+    if initial_deposit > 0 {
+        if is_token_program {
+            let (Some(token_vault), Some(depositor), Some(token_program)) = (
+                ctx.accounts.token_vault.as_ref(),
+                ctx.accounts.depositor_token_account.as_ref(),
+                ctx.accounts.token_program.as_ref(),
+            ) else {
+                return err!(ReferralError::InvalidTokenAccounts);
+            };
+            token::transfer(
+                CpiContext::new(
+                    token_program.to_account_info(),
+                    token::Transfer {
+                        from: depositor.to_account_info(),
+                        to: token_vault.to_account_info(),
+                        authority: ctx.accounts.authority.to_account_info(),
+                    },
+                ),
+                initial_deposit,
+            )?;
+        } else {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.authority.to_account_info(),
+                        to: ctx.accounts.vault.to_account_info(),
+                    },
+                ),
+                initial_deposit,
+            )?;
+        }
+    }
+
+    let referral_program = &mut ctx.accounts.referral_program;
+    referral_program.credit_deposit(initial_deposit)?;
+    restore_referral_funding(referral_program)?;
+    referral_program.is_active = true;
+    referral_program.setup_state |= ReferralProgram::SETUP_ACTIVATED;
+
+    msg!("Activated referral program {} with a deposit of {}", referral_program.key(), initial_deposit);
+    Ok(())
+}
+
+/// Accounts required for the authority pausing or unpausing a referral program.
+#[derive(Accounts)]
+pub struct SetProgramStatus<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        mut,
+        seeds = [b"eligibility_criteria", referral_program.key().as_ref()],
+        bump
+    )]
+    pub eligibility_criteria: Account<'info, EligibilityCriteria>,
+
+    pub authority: Signer<'info>,
+}
+
+/// Pauses (`active` false) or unpauses a program that has gone live.
+///
+/// A paused program takes no joins, late attributions or deposits, but its participants can still claim what
+/// they have earned. Only a program activated before can be unpaused this way; a program created inactive goes
+/// live through `activate_program`.
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `ProgramSetupIncomplete` - If unpausing a program that was never activated
+/// * `ProgramClosing` - If unpausing a program pending closure
+/// * `ProgramAbandoned` - If the program was declared abandoned
+pub fn set_program_status(ctx: Context<SetProgramStatus>, active: bool) -> Result<()> {
+    let current_time = Clock::get()?.unix_timestamp;
+    let referral_program = &mut ctx.accounts.referral_program;
+    referral_program.record_authority_action(current_time)?;
+    if active {
+        referral_program.require_setup_step(ReferralProgram::SETUP_ACTIVATED, ReferralError::ProgramSetupIncomplete)?;
+        require!(!referral_program.is_closing(), ReferralError::ProgramClosing);
+        require!(!referral_program.token_vault_closed, ReferralError::TokenVaultClosed);
+    }
+    referral_program.is_active = active;
+    ctx.accounts.eligibility_criteria.last_updated = current_time;
+
+    emit!(ProgramStatusChanged { referral_program: referral_program.key(), active });
     Ok(())
 }
 
 /// Settings that can be updated for a referral program
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
 pub struct ProgramSettings {
-    /// The fixed reward amount for referrals
+    /// The fixed reward amount for referrals, in the program's existing reward denomination
     pub fixed_reward_amount: u64,
     /// The locked period for referral rewards
     pub locked_period: i64,
-    /// Optional end time for the referral program
-    pub program_end_time: i64,
+    /// Optional end time for the referral program (`None` = open-ended)
+    pub program_end_time: Option<i64>,
     /// The base reward amount for referrals
     pub base_reward: u64,
     /// The maximum reward cap
     pub max_reward_cap: u64,
+    /// Deepest referral depth that still earns the referrer a reward (0 = unlimited)
+    pub max_depth: u16,
+    /// One-time bonuses for crossing referral counts; thresholds must ascend and zeroed entries are ignored
+    pub milestones: [Milestone; MAX_MILESTONES],
+    /// Whether joining requires an invite minted with `mint_invites`
+    pub invite_only: bool,
+    /// Share of each recorded purchase credited to the buyer's referrer, in basis points
+    pub revenue_share_percent: u64,
+    /// Sign-up bonus credited to each credited referee, in raw units (0 = none)
+    pub referee_reward_amount: u64,
+    /// Whether the referee bonus waits out the locked period instead of being paid at join time
+    pub referee_rewards_locked: bool,
+    /// Share of each future deposit ring-fenced as an insurance reserve, in basis points (at most `MAX_RESERVE_BPS`)
+    pub reserve_bps: u64,
+    /// Credited referrals each referrer may make per window (0 = unlimited)
+    pub max_referrals_per_window: u32,
+    /// Length of the rate-limit window in seconds (0 = unlimited)
+    pub referral_window_seconds: i64,
+    /// Whether a join over the rate limit is rejected rather than joining without crediting the referrer
+    pub rate_limit_strict: bool,
+    /// Verified NFT collection a wallet must hold to join as a referrer (`None` = no gate)
+    pub required_collection: Option<Pubkey>,
+    /// Whether referrers must also present their collection NFT for every referral that credits them
+    pub collection_gates_credits: bool,
+    /// Whether participants may transfer pending rewards to other participants
+    pub transfers_enabled: bool,
+    /// How long a referrer can contest a clawback, in seconds (at most `MAX_CLAWBACK_DISPUTE_WINDOW`; 0 = none)
+    pub dispute_window_seconds: i64,
+    /// Who pays the rent of auxiliary accounts such as referee receipts (one of the `RENT_PAYER_*` constants)
+    pub rent_payer_mode: u8,
+    /// Lamports a wallet must hold before paying for its participant account to join (0 = no minimum)
+    pub min_joiner_balance: u64,
+    /// How long ago a joining wallet must have joined some program, shown with an age reference (0 = no minimum)
+    pub min_account_age_seconds: i64,
+    /// Percentages of the peak available funds below which a `RunwayAlert` is emitted (0 disables a slot)
+    pub alert_thresholds: [u8; RUNWAY_ALERT_SLOTS],
+    /// Token holding required of wallets joining as referrers and of referrers when they refer (`None` = none)
+    pub referrer_requirement: Option<TokenRequirement>,
+    /// Token holding required of wallets joining through a referral (`None` = none)
+    pub referee_requirement: Option<TokenRequirement>,
+    /// Whether reward claims are rejected unless invoked directly by the transaction, not through another program
+    pub direct_claims_only: bool,
+    /// Share of each referee bonus, purchase reward and milestone bonus a participant earns that is also credited to
+    /// its referrer, in basis points (at most `MAX_TRAILING_COMMISSION_BPS`; 0 = none)
+    pub trailing_commission_bps: u64,
+    /// Key whose signed referral link a wallet must present to join through a referrer, typically the project's
+    /// backend (`None` = joins need no link)
+    pub link_signer: Option<Pubkey>,
+    /// How long the authority may go without acting before anyone can declare the program abandoned (at least
+    /// `MIN_DORMANCY_PERIOD`; 0 = `DEFAULT_DORMANCY_PERIOD`)
+    pub dormancy_period_seconds: i64,
+    /// Program whose region attestation of a wallet must be presented to join, typically a compliance issuer's
+    /// (`None` = no embargo)
+    pub region_attestor: Option<Pubkey>,
+    /// ISO 3166-1 numeric codes of the regions whose wallets cannot join; zeroed entries are ignored
+    pub embargoed_regions: [u16; MAX_EMBARGOED_REGIONS],
+    /// Whether claims also require an attestation outside the embargoed regions, rather than only joins
+    pub region_checked_on_claim: bool,
+    /// Program whose referrers earn a bridge credit here when a wallet they referred there joins this program
+    /// directly (`None` = no bridge)
+    pub bridge_source_program: Option<Pubkey>,
+    /// Bridge credit paid from this program's funds, in basis points of `base_reward` (at most `MAX_BRIDGE_BPS`)
+    pub bridge_bps: u64,
+    /// Share of the reclaimed rent `cleanup` pays its caller, in basis points (at most `MAX_CLEANUP_BOUNTY_BPS`;
+    /// 0 = `DEFAULT_CLEANUP_BOUNTY_BPS`)
+    pub cleanup_bounty_bps: u16,
+    /// How joins report their events (one of the `EVENT_MODE_*` constants): each as it occurs, or packed into a
+    /// single `JoinDigest` to keep the logs of busy programs short
+    pub event_mode: u8,
+    /// How long after joining directly a participant can still name its referrer with `set_referrer_late`, in
+    /// seconds (at most `MAX_ATTRIBUTION_GRACE`; 0 = never)
+    pub attribution_grace_seconds: i64,
+    /// Reward of the first tier, at least `base_reward`; a step of auto downgrade (0 = no tier)
+    pub tier1_reward: u64,
+    /// Reward of the second tier, at least `tier1_reward` and `base_reward`; a step of auto downgrade (0 = no tier)
+    pub tier2_reward: u64,
+    /// Whether the flat referral reward steps down through the tier and base rewards when the vault's uncommitted
+    /// funds cannot sustain it, instead of being credited in full until the program stops accepting referrals
+    pub auto_downgrade: bool,
+    /// How many more rewards the uncommitted funds must cover for a reward to be sustainable under
+    /// `auto_downgrade` (at least 1 when it is set)
+    pub min_remaining_credits: u32,
+    /// How long a seeded participant must sit untouched, never activated and without rewards, before the authority
+    /// can reclaim its rent with `reclaim_seeded_participant`, in seconds (at most `MAX_SEED_RECLAIM_PERIOD`;
+    /// 0 = never)
+    pub seed_reclaim_seconds: i64,
+    /// Fee forfeited by claims made with `early_claim_rewards` or `early_claim_token_rewards` while rewards are
+    /// locked, in basis points (at most `MAX_EARLY_REDEMPTION_FEE`; 0 = no early redemption)
+    pub early_redemption_fee: u64,
 }
 
 /// Accounts required for updating program settings
@@ -314,7 +792,6 @@ pub struct UpdateProgramSettings<'info> {
     #[account(
         mut,
         constraint = referral_program.authority == authority.key(),
-        constraint = referral_program.is_active @ ReferralError::ProgramInactive,
     )]
     pub referral_program: Account<'info, ReferralProgram>,
 
@@ -325,9 +802,44 @@ pub struct UpdateProgramSettings<'info> {
     )]
     pub eligibility_criteria: Account<'info, EligibilityCriteria>,
 
+    /// CHECK: The network config PDA; the compile-time limits apply while it is uninitialized, and it is
+    /// deserialized in the handler once this program owns it
+    #[account(seeds = [NETWORK_CONFIG_SEED], bump)]
+    pub network_config: UncheckedAccount<'info>,
+
+    /// Pays the rent of the settings change record
     #[account(mut)]
     pub authority: Signer<'info>,
 
+    /// CHECK: The PDA recording this change, created when the update changes a setting; checked by seeds
+    #[account(
+        mut,
+        seeds = [
+            SETTINGS_CHANGE_SEED,
+            referral_program.key().as_ref(),
+            &referral_program.settings_change_count.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub settings_change: UncheckedAccount<'info>,
+
+    /// CHECK: The program's pending change PDA, created when a governed program's update cuts rewards; checked by
+    /// seeds
+    #[account(
+        mut,
+        seeds = [PENDING_CHANGE_SEED, referral_program.key().as_ref()],
+        bump
+    )]
+    pub pending_change: Option<UncheckedAccount<'info>>,
+
+    /// Optional event queue of the program; appended to when supplied
+    #[account(
+        mut,
+        seeds = [EVENT_QUEUE_SEED, referral_program.key().as_ref()],
+        bump = event_queue.bump,
+    )]
+    pub event_queue: Option<Box<Account<'info, EventQueue>>>,
+
     pub system_program: Program<'info, System>,
 }
 
@@ -337,60 +849,464 @@ pub struct UpdateProgramSettings<'info> {
 /// such as reward amounts, locked periods, and fees. It validates the new settings to ensure they
 /// meet the program's requirements.
 ///
+/// It also configures the criteria of a program created inactive, completing the `SETUP_CRITERIA_SET` step
+/// that `activate_program` requires.
+///
+/// The settings that changed are recorded in the program's next `SettingsChangeRecord`, whose rent the
+/// authority pays; an update that changes nothing records nothing.
+///
+/// On a governed program an update that cuts rewards or shortens the program is not applied: it is held in the
+/// program's pending change for participants to approve, and `execute_change` applies it once they have.
+///
 /// # Arguments
 /// * `ctx` - The context for the UpdateProgramSettings instruction
 /// * `new_settings` - The new settings to apply to the program
+/// * `idempotency_key` - Optional key recorded on the program; a call repeating one of its recent keys fails
 ///
 /// # Returns
 /// * `Result<()>` - Returns Ok(()) if successful, or an error if validation fails
 pub fn update_program_settings(
     ctx: Context<UpdateProgramSettings>,
     new_settings: ProgramSettings,
+    idempotency_key: Option<[u8; IDEMPOTENCY_KEY_LEN]>,
 ) -> Result<()> {
     let current_time = Clock::get()?.unix_timestamp;
+    ctx.accounts.referral_program.require_settings_unlocked(current_time)?;
+    let limits = NetworkLimits::load(&ctx.accounts.network_config, ctx.program_id)?;
+    validate_program_settings(&new_settings, current_time, &limits)?;
+    let old_settings = current_settings(&ctx.accounts.referral_program, &ctx.accounts.eligibility_criteria);
+    let changes = settings_changes(&old_settings, &new_settings)?;
 
-    // Core reward amount validations
-    require!(
-        new_settings.fixed_reward_amount >= MIN_REWARD_AMOUNT,
-        ReferralError::InvalidRewardAmount
-    );
-    require!(
-        new_settings.base_reward >= MIN_REWARD_AMOUNT,
-        ReferralError::InvalidRewardAmount
-    );
-    require!(
-        new_settings.max_reward_cap >= new_settings.fixed_reward_amount 
-        && new_settings.max_reward_cap >= new_settings.base_reward,
-        ReferralError::InvalidRewardCap
-    );
+    let program = &mut ctx.accounts.referral_program;
+    program.record_authority_action(current_time)?;
+    program.record_idempotency_key(idempotency_key)?;
 
-    // Time period validations
-    require!(
-        new_settings.locked_period >= MIN_LOCKED_PERIOD && new_settings.locked_period <= MAX_LOCKED_PERIOD,
-        ReferralError::InvalidLockedPeriod
-    );
-    let end_time = new_settings.program_end_time;
-    require!(
-        end_time > current_time,
-        ReferralError::InvalidProgramEndTime
-    );
-    // Ensure end time is after locked period
-    require!(
-        end_time > current_time + new_settings.locked_period,
-        ReferralError::InvalidProgramEndTime
-    );
+    // A governed program holds cuts for participant approval instead of applying them
+    if program.governance_mode && cuts_rewards(&old_settings, &new_settings) {
+        let pending_change = ctx.accounts.pending_change.as_ref().ok_or(ReferralError::PendingChangeRequired)?;
+        let bump = ctx.bumps.pending_change.ok_or(ReferralError::PendingChangeRequired)?;
+        return propose_settings_change(
+            program,
+            (&pending_change.to_account_info(), bump),
+            new_settings,
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            current_time,
+        );
+    }
+
+    apply_program_settings(program, &mut ctx.accounts.eligibility_criteria, &new_settings, current_time)?;
+    record_settings_change(
+        &mut ctx.accounts.referral_program,
+        (&ctx.accounts.settings_change.to_account_info(), ctx.bumps.settings_change),
+        changes,
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        current_time,
+    )?;
+
+    if let Some(event_queue) = ctx.accounts.event_queue.as_mut() {
+        event_queue.push(EventRecord::KIND_SETTINGS_CHANGE, ctx.accounts.authority.key(), 0, current_time);
+    }
 
+    Ok(())
+}
+
+/// Writes `settings` to the program and its criteria, as of `current_time`; the reward denomination is preserved.
+///
+/// The settings must have passed `validate_program_settings`.
+pub fn apply_program_settings(
+    program: &mut ReferralProgram,
+    criteria: &mut EligibilityCriteria,
+    settings: &ProgramSettings,
+    current_time: i64,
+) -> Result<()> {
     // Update core program settings
-    let program = &mut ctx.accounts.referral_program;
-    program.fixed_reward_amount = new_settings.fixed_reward_amount;
-    program.locked_period = new_settings.locked_period;
+    program.fixed_reward_amount = settings.fixed_reward_amount;
+    program.referral_reward_amount()?;
+    program.locked_period = settings.locked_period;
+    program.max_depth = settings.max_depth;
+    program.invite_only = settings.invite_only;
+    program.referee_reward_amount = settings.referee_reward_amount;
+    program.referee_rewards_locked = settings.referee_rewards_locked;
+    program.reserve_bps = settings.reserve_bps;
+    program.dispute_window_seconds = settings.dispute_window_seconds;
+    program.rent_payer_mode = settings.rent_payer_mode;
+    program.set_alert_thresholds(settings.alert_thresholds);
+    program.direct_claims_only = settings.direct_claims_only;
+    program.dormancy_period_seconds = settings.dormancy_period_seconds;
+    program.cleanup_bounty_bps = settings.cleanup_bounty_bps;
+    program.event_mode = settings.event_mode;
+    program.attribution_grace_seconds = settings.attribution_grace_seconds;
+    program.seed_reclaim_seconds = settings.seed_reclaim_seconds;
+    program.early_redemption_fee = settings.early_redemption_fee;
+    program.program_end_time = settings.program_end_time;
+    program.setup_state |= ReferralProgram::SETUP_CRITERIA_SET;
 
     // Update eligibility criteria
-    let criteria = &mut ctx.accounts.eligibility_criteria;
-    criteria.program_end_time = new_settings.program_end_time;
-    criteria.base_reward = new_settings.base_reward;
-    criteria.max_reward_cap = new_settings.max_reward_cap;
+    criteria.program_end_time = settings.program_end_time;
+    criteria.base_reward = settings.base_reward;
+    criteria.max_reward_cap = settings.max_reward_cap;
+    criteria.revenue_share_percent = settings.revenue_share_percent;
+    criteria.milestones = settings.milestones;
+    criteria.max_referrals_per_window = settings.max_referrals_per_window;
+    criteria.referral_window_seconds = settings.referral_window_seconds;
+    criteria.rate_limit_strict = settings.rate_limit_strict;
+    criteria.required_collection = settings.required_collection;
+    criteria.collection_gates_credits = settings.collection_gates_credits;
+    criteria.transfers_enabled = settings.transfers_enabled;
+    criteria.min_joiner_balance = settings.min_joiner_balance;
+    criteria.min_account_age_seconds = settings.min_account_age_seconds;
+    criteria.referrer_requirement = settings.referrer_requirement;
+    criteria.referee_requirement = settings.referee_requirement;
+    criteria.trailing_commission_bps = settings.trailing_commission_bps;
+    criteria.link_signer = settings.link_signer;
+    criteria.region_attestor = settings.region_attestor;
+    criteria.embargoed_regions = settings.embargoed_regions;
+    criteria.region_checked_on_claim = settings.region_checked_on_claim;
+    criteria.bridge_source_program = settings.bridge_source_program;
+    criteria.bridge_bps = settings.bridge_bps;
+    criteria.tier1_reward = settings.tier1_reward;
+    criteria.tier2_reward = settings.tier2_reward;
+    criteria.auto_downgrade = settings.auto_downgrade;
+    criteria.min_remaining_credits = settings.min_remaining_credits;
     criteria.last_updated = current_time;
-
     Ok(())
 }
+
+/// Validates the reward parameters of a new program against the network's `limits`, emitting a
+/// `ValidationFailure` for the first rejected one.
+///
+/// # Errors
+/// * `InvalidRewardAmount` - If the fixed reward is below the minimum reward amount
+/// * `InvalidEndTime` - If the end time is not in the future
+/// * `ProgramDurationTooLong` - If the end time is beyond `current_time` plus the maximum program duration
+///
+/// An open-ended program (`program_end_time` of `None`) passes both end time checks.
+pub fn validate_reward_params(
+    fixed_reward_amount: u64,
+    program_end_time: Option<i64>,
+    current_time: i64,
+    limits: &NetworkLimits,
+) -> Result<()> {
+    check_field(
+        fixed_reward_amount >= limits.min_reward_amount,
+        ProgramField::FixedRewardAmount,
+        ValidationCode::TooLow,
+        ReferralError::InvalidRewardAmount,
+    )?;
+    let Some(program_end_time) = program_end_time else {
+        return Ok(());
+    };
+    check_field(
+        program_end_time > current_time,
+        ProgramField::ProgramEndTime,
+        ValidationCode::TooLow,
+        ReferralError::InvalidEndTime,
+    )?;
+    validate_program_duration(program_end_time, current_time, limits)
+}
+
+/// Validates the time a program's settings are locked until at creation.
+///
+/// # Errors
+/// * `InvalidSettingsLock` - If the lock does not end after `current_time`, ends after the program's end time or
+///   lasts longer than `MAX_SETTINGS_LOCK`
+pub fn validate_settings_lock(locked_until: i64, program_end_time: Option<i64>, current_time: i64) -> Result<()> {
+    check_field(
+        locked_until > current_time,
+        ProgramField::SettingsLockedUntil,
+        ValidationCode::TooLow,
+        ReferralError::InvalidSettingsLock,
+    )?;
+    check_field(
+        locked_until <= current_time.saturating_add(MAX_SETTINGS_LOCK),
+        ProgramField::SettingsLockedUntil,
+        ValidationCode::TooHigh,
+        ReferralError::InvalidSettingsLock,
+    )?;
+    check_field(
+        program_end_time.is_none_or(|end| locked_until <= end),
+        ProgramField::SettingsLockedUntil,
+        ValidationCode::Relationship,
+        ReferralError::InvalidSettingsLock,
+    )
+}
+
+/// Validates the token holdings required of referrers and referees.
+///
+/// # Errors
+/// * `InvalidTokenRequirement` - If a requirement asks for a zero amount, which any token account would meet
+pub fn validate_token_requirements(
+    referrer_requirement: Option<TokenRequirement>,
+    referee_requirement: Option<TokenRequirement>,
+) -> Result<()> {
+    check_field(
+        [referrer_requirement, referee_requirement].iter().flatten().all(|requirement| requirement.min_amount > 0),
+        ProgramField::TokenRequirements,
+        ValidationCode::TooLow,
+        ReferralError::InvalidTokenRequirement,
+    )
+}
+
+/// Validates new program settings against the network's `limits`, emitting a `ValidationFailure` for the first
+/// rejected field.
+///
+/// # Errors
+/// * `InvalidRewardAmount` - If the fixed or base reward is below the minimum reward amount
+/// * `InvalidRewardCap` - If the cap is below the fixed or base reward
+/// * `InvalidFeeAmount` - If the revenue share exceeds the maximum fee percentage
+/// * `InvalidReserveBps` - If the reserve share exceeds `MAX_RESERVE_BPS`
+/// * `InvalidRateLimit` - If only one of the rate-limit cap and window is set, or the window is negative
+/// * `InvalidCollectionGate` - If credits are gated on a collection without one being set
+/// * `InvalidDisputeWindow` - If the clawback dispute window is negative or longer than 30 days
+/// * `InvalidRentPayerMode` - If the rent payer mode is not one of the `RENT_PAYER_*` constants
+/// * `InvalidJoinRequirements` - If the minimum account age is negative
+/// * `InvalidAlertThresholds` - If a runway alert threshold is above 100
+/// * `InvalidTokenRequirement` - If a token requirement asks for a zero amount
+/// * `InvalidTrailingCommission` - If the trailing commission exceeds `MAX_TRAILING_COMMISSION_BPS`
+/// * `InvalidDormancyPeriod` - If the dormancy period is neither 0 nor at least `MIN_DORMANCY_PERIOD`
+/// * `InvalidBridge` - If the bridge credit exceeds `MAX_BRIDGE_BPS` or is set without a bridge source program
+/// * `InvalidCleanupBounty` - If the cleanup bounty exceeds `MAX_CLEANUP_BOUNTY_BPS`
+/// * `InvalidEventMode` - If the event mode is not one of the `EVENT_MODE_*` constants
+/// * `InvalidAttributionGrace` - If the attribution grace period is negative or exceeds `MAX_ATTRIBUTION_GRACE`
+/// * `InvalidTierReward` - If a tier reward is set below the base reward or the tier below it
+/// * `InvalidAutoDowngrade` - If auto downgrade is set with a `min_remaining_credits` of 0
+/// * `InvalidSeedReclaimPeriod` - If the seed reclaim period is negative or exceeds `MAX_SEED_RECLAIM_PERIOD`
+/// * `InvalidEarlyRedemptionFee` - If the early redemption fee exceeds `MAX_EARLY_REDEMPTION_FEE`
+/// * `InvalidLockedPeriod` - If the locked period is out of bounds
+/// * `InvalidProgramEndTime` - If the end time does not fall after the locked period
+/// * `ProgramDurationTooLong` - If the end time is beyond `current_time` plus the maximum program duration
+/// * `InvalidMilestones` - If the milestone thresholds do not ascend
+pub fn validate_program_settings(settings: &ProgramSettings, current_time: i64, limits: &NetworkLimits) -> Result<()> {
+    // Core reward amount validations
+    check_field(
+        settings.fixed_reward_amount >= limits.min_reward_amount,
+        ProgramField::FixedRewardAmount,
+        ValidationCode::TooLow,
+        ReferralError::InvalidRewardAmount,
+    )?;
+    check_field(
+        settings.base_reward >= limits.min_reward_amount,
+        ProgramField::BaseReward,
+        ValidationCode::TooLow,
+        ReferralError::InvalidRewardAmount,
+    )?;
+    check_field(
+        settings.max_reward_cap >= settings.fixed_reward_amount && settings.max_reward_cap >= settings.base_reward,
+        ProgramField::MaxRewardCap,
+        ValidationCode::Relationship,
+        ReferralError::InvalidRewardCap,
+    )?;
+    check_field(
+        settings.revenue_share_percent <= limits.max_fee_percentage,
+        ProgramField::RevenueSharePercent,
+        ValidationCode::TooHigh,
+        ReferralError::InvalidFeeAmount,
+    )?;
+    check_field(
+        settings.reserve_bps <= MAX_RESERVE_BPS,
+        ProgramField::ReserveBps,
+        ValidationCode::TooHigh,
+        ReferralError::InvalidReserveBps,
+    )?;
+    check_field(
+        settings.referral_window_seconds >= 0
+            && (settings.max_referrals_per_window == 0) == (settings.referral_window_seconds == 0),
+        ProgramField::ReferralRateLimit,
+        ValidationCode::Relationship,
+        ReferralError::InvalidRateLimit,
+    )?;
+    check_field(
+        !settings.collection_gates_credits || settings.required_collection.is_some(),
+        ProgramField::RequiredCollection,
+        ValidationCode::Relationship,
+        ReferralError::InvalidCollectionGate,
+    )?;
+    check_field(
+        settings.dispute_window_seconds >= 0,
+        ProgramField::DisputeWindow,
+        ValidationCode::TooLow,
+        ReferralError::InvalidDisputeWindow,
+    )?;
+    check_field(
+        settings.dispute_window_seconds <= MAX_CLAWBACK_DISPUTE_WINDOW,
+        ProgramField::DisputeWindow,
+        ValidationCode::TooHigh,
+        ReferralError::InvalidDisputeWindow,
+    )?;
+    check_field(
+        matches!(settings.rent_payer_mode, RENT_PAYER_USER | RENT_PAYER_SPONSOR),
+        ProgramField::RentPayerMode,
+        ValidationCode::Unsupported,
+        ReferralError::InvalidRentPayerMode,
+    )?;
+    check_field(
+        settings.min_account_age_seconds >= 0,
+        ProgramField::JoinRequirements,
+        ValidationCode::TooLow,
+        ReferralError::InvalidJoinRequirements,
+    )?;
+    check_field(
+        settings.alert_thresholds.iter().all(|&threshold| threshold <= 100),
+        ProgramField::AlertThresholds,
+        ValidationCode::TooHigh,
+        ReferralError::InvalidAlertThresholds,
+    )?;
+    validate_token_requirements(settings.referrer_requirement, settings.referee_requirement)?;
+    check_field(
+        settings.trailing_commission_bps <= MAX_TRAILING_COMMISSION_BPS,
+        ProgramField::TrailingCommission,
+        ValidationCode::TooHigh,
+        ReferralError::InvalidTrailingCommission,
+    )?;
+    check_field(
+        settings.dormancy_period_seconds == 0 || settings.dormancy_period_seconds >= MIN_DORMANCY_PERIOD,
+        ProgramField::DormancyPeriod,
+        ValidationCode::TooLow,
+        ReferralError::InvalidDormancyPeriod,
+    )?;
+    check_field(
+        settings.bridge_bps <= MAX_BRIDGE_BPS,
+        ProgramField::Bridge,
+        ValidationCode::TooHigh,
+        ReferralError::InvalidBridge,
+    )?;
+    check_field(
+        settings.bridge_bps == 0 || settings.bridge_source_program.is_some(),
+        ProgramField::Bridge,
+        ValidationCode::Relationship,
+        ReferralError::InvalidBridge,
+    )?;
+    check_field(
+        settings.cleanup_bounty_bps <= MAX_CLEANUP_BOUNTY_BPS,
+        ProgramField::CleanupBounty,
+        ValidationCode::TooHigh,
+        ReferralError::InvalidCleanupBounty,
+    )?;
+    check_field(
+        matches!(settings.event_mode, EVENT_MODE_VERBOSE | EVENT_MODE_DIGEST),
+        ProgramField::EventMode,
+        ValidationCode::Unsupported,
+        ReferralError::InvalidEventMode,
+    )?;
+    check_field(
+        settings.attribution_grace_seconds >= 0,
+        ProgramField::AttributionGrace,
+        ValidationCode::TooLow,
+        ReferralError::InvalidAttributionGrace,
+    )?;
+    check_field(
+        settings.attribution_grace_seconds <= MAX_ATTRIBUTION_GRACE,
+        ProgramField::AttributionGrace,
+        ValidationCode::TooHigh,
+        ReferralError::InvalidAttributionGrace,
+    )?;
+    check_field(
+        settings.tier1_reward == 0 || settings.tier1_reward >= settings.base_reward,
+        ProgramField::Tier1Reward,
+        ValidationCode::Relationship,
+        ReferralError::InvalidTierReward,
+    )?;
+    check_field(
+        settings.tier2_reward == 0 || settings.tier2_reward >= settings.tier1_reward.max(settings.base_reward),
+        ProgramField::Tier2Reward,
+        ValidationCode::Relationship,
+        ReferralError::InvalidTierReward,
+    )?;
+    check_field(
+        !settings.auto_downgrade || settings.min_remaining_credits > 0,
+        ProgramField::AutoDowngrade,
+        ValidationCode::Relationship,
+        ReferralError::InvalidAutoDowngrade,
+    )?;
+    check_field(
+        settings.seed_reclaim_seconds >= 0,
+        ProgramField::SeedReclaim,
+        ValidationCode::TooLow,
+        ReferralError::InvalidSeedReclaimPeriod,
+    )?;
+    check_field(
+        settings.seed_reclaim_seconds <= MAX_SEED_RECLAIM_PERIOD,
+        ProgramField::SeedReclaim,
+        ValidationCode::TooHigh,
+        ReferralError::InvalidSeedReclaimPeriod,
+    )?;
+    check_field(
+        settings.early_redemption_fee <= MAX_EARLY_REDEMPTION_FEE,
+        ProgramField::EarlyRedemptionFee,
+        ValidationCode::TooHigh,
+        ReferralError::InvalidEarlyRedemptionFee,
+    )?;
+
+    // Time period validations
+    check_field(
+        settings.locked_period >= limits.min_locked_period,
+        ProgramField::LockedPeriod,
+        ValidationCode::TooLow,
+        ReferralError::InvalidLockedPeriod,
+    )?;
+    check_field(
+        settings.locked_period <= limits.max_locked_period,
+        ProgramField::LockedPeriod,
+        ValidationCode::TooHigh,
+        ReferralError::InvalidLockedPeriod,
+    )?;
+    validate_end_time(settings.program_end_time, settings.locked_period, current_time, limits)?;
+    flag_field(validate_milestones(&settings.milestones), ProgramField::Milestones, ValidationCode::Relationship)
+}
+
+/// Validates a new end time against the locked period and the network's maximum program duration.
+///
+/// A dated end must fall after rewards earned now unlock; an open-ended program (`None`) has no end for the
+/// locked period to overrun, so it always passes.
+///
+/// # Errors
+/// * `InvalidProgramEndTime` - If the end time does not fall after `current_time + locked_period`
+/// * `ProgramDurationTooLong` - If the end time is beyond `current_time` plus the maximum program duration
+pub fn validate_end_time(
+    program_end_time: Option<i64>,
+    locked_period: i64,
+    current_time: i64,
+    limits: &NetworkLimits,
+) -> Result<()> {
+    let Some(end_time) = program_end_time else {
+        return Ok(());
+    };
+    check_field(
+        end_time > current_time,
+        ProgramField::ProgramEndTime,
+        ValidationCode::TooLow,
+        ReferralError::InvalidProgramEndTime,
+    )?;
+    // Ensure end time is after locked period
+    let locked_until = current_time.checked_add(locked_period).ok_or(ReferralError::NumericOverflow)?;
+    check_field(
+        end_time > locked_until,
+        ProgramField::ProgramEndTime,
+        ValidationCode::Relationship,
+        ReferralError::InvalidProgramEndTime,
+    )?;
+    validate_program_duration(end_time, current_time, limits)
+}
+
+/// Validates that a program end time is no more than the network's maximum program duration after
+/// `current_time`.
+///
+/// # Arguments
+/// * `program_end_time` - The proposed end time for the referral program
+/// * `current_time` - The current unix timestamp
+/// * `limits` - The network's validation limits
+///
+/// # Errors
+/// * `ProgramDurationTooLong` - If the end time is beyond `current_time` plus the maximum program duration
+/// * `NumericOverflow` - If the maximum end time cannot be represented
+pub fn validate_program_duration(program_end_time: i64, current_time: i64, limits: &NetworkLimits) -> Result<()> {
+    let max_end_time = current_time.checked_add(limits.max_program_duration).ok_or(ReferralError::NumericOverflow)?;
+    check_field(
+        program_end_time <= max_end_time,
+        ProgramField::ProgramEndTime,
+        ValidationCode::TooHigh,
+        ReferralError::ProgramDurationTooLong,
+    )
+}
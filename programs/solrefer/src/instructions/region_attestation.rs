@@ -0,0 +1,73 @@
+//! Region embargoes checked through attestations published by a region attestor.
+//!
+//! A program cannot see where a wallet is used from, so programs that must exclude jurisdictions name a region
+//! attestor: the on-chain program of an issuer publishing one attestation account per wallet. An attestation is
+//! trusted because the attestor program owns it; its address is not checked. Only its first bytes are read, the
+//! attested wallet followed by the region's ISO 3166-1 numeric code as a little-endian `u16`, so issuers may append
+//! whatever else they record.
+use crate::{error::ReferralError, state::referral_program::EligibilityCriteria};
+use anchor_lang::prelude::*;
+
+/// The size of the attestation data read: the attested wallet and its region code.
+pub const REGION_ATTESTATION_SIZE: usize = 32 + 2;
+
+/// The region an attestor attests for a wallet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RegionAttestation {
+    /// The attested wallet
+    pub wallet: Pubkey,
+    /// The ISO 3166-1 numeric code of the wallet's region; 0 attests no region
+    pub region: u16,
+}
+
+impl RegionAttestation {
+    /// Parses the attestation at the start of an account's data, or returns `None` if the data is too short.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let data = data.get(..REGION_ATTESTATION_SIZE)?;
+        let wallet = Pubkey::try_from(&data[..32]).ok()?;
+        let region = u16::from_le_bytes([data[32], data[33]]);
+        Some(Self { wallet, region })
+    }
+}
+
+/// Returns why `wallet` is not attested outside the program's embargoed regions, or `None` when it is or the
+/// program has no region attestor.
+///
+/// * `RegionAttestationRequired` - If the program has a region attestor and no attestation was supplied
+/// * `InvalidRegionAttestation` - If the attestation is not owned by the attestor, is too short, attests another
+///   wallet or attests no region
+/// * `RegionEmbargoed` - If the attested region is one of the program's embargoed regions
+pub fn region_violation(
+    criteria: &EligibilityCriteria,
+    wallet: &Pubkey,
+    attestation: Option<&AccountInfo>,
+) -> Option<ReferralError> {
+    let attestor = criteria.region_attestor?;
+    let Some(attestation) = attestation else {
+        return Some(ReferralError::RegionAttestationRequired);
+    };
+    let attested = (*attestation.owner == attestor)
+        .then(|| attestation.try_borrow_data().ok().and_then(|data| RegionAttestation::parse(&data)))
+        .flatten()
+        .filter(|attested| attested.wallet == *wallet && attested.region != 0);
+    match attested {
+        None => Some(ReferralError::InvalidRegionAttestation),
+        Some(attested) if criteria.embargoed_regions.contains(&attested.region) => Some(ReferralError::RegionEmbargoed),
+        Some(_) => None,
+    }
+}
+
+/// Requires `wallet` to be attested outside the program's embargoed regions when the program has a region attestor.
+///
+/// # Errors
+/// * The error `region_violation` reports, if any
+pub fn require_allowed_region(
+    criteria: &EligibilityCriteria,
+    wallet: &Pubkey,
+    attestation: Option<&AccountInfo>,
+) -> Result<()> {
+    match region_violation(criteria, wallet, attestation) {
+        Some(violation) => Err(error!(violation)),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{
+    error::ReferralError,
+    instructions::{restore_referral_funding, TOKEN_VAULT_SEED, VAULT_SEED},
+    state::*,
+};
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
+use anchor_spl::token::{self, Token, TokenAccount};
+
+/// Accounts required for releasing a program's insurance reserve.
+#[derive(Accounts)]
+pub struct ReleaseReserve<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    /// PDA with seeds: ["vault", referral_program.key()]
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, referral_program.key().as_ref()],
+        bump,
+    )]
+    pub vault: SystemAccount<'info>,
+
+    /// PDA with seeds: ["token_vault", referral_program.key()]; required to pay a token program's reserve out
+    #[account(
+        mut,
+        seeds = [TOKEN_VAULT_SEED, referral_program.key().as_ref()],
+        bump,
+        token::authority = referral_program,
+    )]
+    pub token_vault: Option<Account<'info, TokenAccount>>,
+
+    /// Token account receiving a token program's reserve, owned by the authority or an allowed destination
+    #[account(
+        mut,
+        constraint = destination_token_account.mint == referral_program.token_mint
+            @ ReferralError::InvalidTokenAccounts,
+        constraint = referral_program.is_withdrawal_destination_allowed(destination_token_account.owner)
+            @ ReferralError::DestinationNotAllowed,
+    )]
+    pub destination_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Wallet receiving a SOL program's reserve instead of the authority; must be an allowed destination
+    #[account(
+        mut,
+        constraint = referral_program.is_withdrawal_destination_allowed(destination.key())
+            @ ReferralError::DestinationNotAllowed,
+    )]
+    pub destination: Option<SystemAccount<'info>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+/// Releases the insurance reserve once the program has ended, either back into `total_available` or straight to
+/// the authority.
+///
+/// An open-ended program never ends, so its reserve stays locked until `extend_program` gives it an end time.
+/// A reserve paid out goes to the authority unless an allowed destination wallet, or its token account, is passed.
+///
+/// Returns the amount released; an empty reserve releases nothing.
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `ReserveLocked` - If the program's end time has not passed
+/// * `ProgramFrozen` - If the reserve is paid to the authority of a program its guardian froze
+/// * `DestinationNotAllowed` - If the destination is neither the authority nor on the program's allow-list
+/// * `InvalidTokenAccounts` - If a token program's reserve is paid out without the token accounts
+pub fn release_reserve(ctx: Context<ReleaseReserve>, to_authority: bool) -> Result<u64> {
+    let now = Clock::get()?.unix_timestamp;
+    let referral_program = &mut ctx.accounts.referral_program;
+    referral_program.record_authority_action(now)?;
+    require!(referral_program.has_ended(now), ReferralError::ReserveLocked);
+
+    let amount = referral_program.reserved_balance;
+    referral_program.reserved_balance = 0;
+    if !to_authority {
+        referral_program.total_available =
+            referral_program.total_available.checked_add(amount).ok_or(ReferralError::NumericOverflow)?;
+        referral_program.refresh_ui_totals();
+        restore_referral_funding(referral_program)?;
+        msg!("Released a reserve of {} into the available funds", amount);
+        return Ok(amount);
+    }
+    require!(!referral_program.frozen, ReferralError::ProgramFrozen);
+    if amount == 0 {
+        return Ok(0);
+    }
+
+    let program_key = referral_program.key();
+    if referral_program.token_mint == Pubkey::default() {
+        let seeds = &[VAULT_SEED, program_key.as_ref(), &[ctx.bumps.vault]];
+        let to = match &ctx.accounts.destination {
+            Some(destination) => destination.to_account_info(),
+            None => ctx.accounts.authority.to_account_info(),
+        };
+        transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer { from: ctx.accounts.vault.to_account_info(), to },
+                &[&seeds[..]],
+            ),
+            amount,
+        )?;
+    } else {
+        let (Some(token_vault), Some(destination), Some(token_program)) = (
+            ctx.accounts.token_vault.as_ref(),
+            ctx.accounts.destination_token_account.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+        ) else {
+            return err!(ReferralError::InvalidTokenAccounts);
+        };
+        let index_bytes = referral_program.program_index.to_le_bytes();
+        let seeds = referral_program.signer_seeds(&index_bytes);
+        token::transfer(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                token::Transfer {
+                    from: token_vault.to_account_info(),
+                    to: destination.to_account_info(),
+                    authority: referral_program.to_account_info(),
+                },
+                &[&seeds[..]],
+            ),
+            amount,
+        )?;
+    }
+
+    msg!("Paid a reserve of {} out of the vault", amount);
+    Ok(amount)
+}
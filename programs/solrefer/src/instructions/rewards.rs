@@ -1,13 +1,193 @@
+use crate::constants::{EVENT_QUEUE_SEED, SPLITTER_SEED};
 use crate::error::*;
-use crate::instructions::VAULT_SEED;
+use crate::events::EarlyRedemption;
+use crate::instructions::{
+    check_referral_funding, claim_split, debug_assert_funds, funds_are_balanced, is_direct_invocation, pay_sol_shares,
+    region_violation, require_split_wallets, split_token_accounts, token_shares_paid, ClaimGuard, TOKEN_VAULT_SEED,
+    VAULT_SEED,
+};
 use crate::state::*;
 use anchor_lang::prelude::*;
-use anchor_lang::system_program::{transfer, Transfer};
+use anchor_lang::solana_program::sysvar;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{self, spl_token::native_mint, Mint, SyncNative, Token, TokenAccount},
+};
+
+/// Why a participant can or cannot claim rewards right now.
+///
+/// `blocked` is a bitmask of the `ClaimEligibility::*` flags; zero means the claim would pass every gate.
+/// `claimable_at` is the earliest unix timestamp at which all time-based gates lift (at or before `now`
+/// when none of them block). Gates that are not time-based do not move `claimable_at`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ClaimEligibility {
+    pub blocked: u32,
+    pub claimable_at: i64,
+}
+
+impl ClaimEligibility {
+    /// The referral program is inactive and has never gone live; a paused program still pays out earned rewards
+    pub const PROGRAM_INACTIVE: u32 = 1 << 0;
+    /// The participant has no pending rewards
+    pub const NO_REWARDS: u32 = 1 << 1;
+    /// The participant's rewards are still within the program's locked period
+    pub const REWARDS_LOCKED: u32 = 1 << 2;
+    /// The participant account was rotated to a new owner and can no longer claim
+    pub const PARTICIPANT_ROTATED: u32 = 1 << 3;
+    /// The program's guardian froze it
+    pub const PROGRAM_FROZEN: u32 = 1 << 4;
+    /// The participant was seeded by the authority and its owner has not activated it yet
+    pub const NOT_ACTIVATED: u32 = 1 << 5;
+    /// The program checks regions on claims and no attestation of the claimant was supplied
+    pub const REGION_ATTESTATION_REQUIRED: u32 = 1 << 6;
+    /// The supplied attestation is not the region attestor's, or attests another wallet or no region
+    pub const REGION_ATTESTATION_INVALID: u32 = 1 << 7;
+    /// The claimant is attested in one of the program's embargoed regions
+    pub const REGION_EMBARGOED: u32 = 1 << 8;
+    /// The program only accepts direct claims and the claim was not shown to be invoked directly by its transaction
+    pub const NOT_DIRECT: u32 = 1 << 9;
+    /// The participant left the program and only its tombstone remains
+    pub const PARTICIPANT_LEFT: u32 = 1 << 10;
+
+    /// Returns true if no gate blocks the claim
+    pub fn is_claimable(&self) -> bool {
+        self.blocked == 0
+    }
+
+    /// Returns true if the given flag is set
+    pub fn is_blocked_by(&self, flag: u32) -> bool {
+        self.blocked & flag != 0
+    }
+
+    /// Maps the first set flag to its specific error, or succeeds when nothing blocks the claim.
+    ///
+    /// The gates on how the claim is made are reported first, as they reject it whatever the participant's state:
+    /// a claim that is not direct, then the region gates. A rotated or left account comes next since none of the
+    /// other gates can ever lift for it; the remaining flags are checked from the lowest bit up.
+    pub fn require_claimable(&self) -> Result<()> {
+        if self.is_blocked_by(Self::NOT_DIRECT) {
+            return err!(ReferralError::CpiClaimNotAllowed);
+        }
+        if self.is_blocked_by(Self::REGION_ATTESTATION_REQUIRED) {
+            return err!(ReferralError::RegionAttestationRequired);
+        }
+        if self.is_blocked_by(Self::REGION_ATTESTATION_INVALID) {
+            return err!(ReferralError::InvalidRegionAttestation);
+        }
+        if self.is_blocked_by(Self::REGION_EMBARGOED) {
+            return err!(ReferralError::RegionEmbargoed);
+        }
+        if self.is_blocked_by(Self::PARTICIPANT_ROTATED) {
+            return err!(ReferralError::ParticipantRotated);
+        }
+        if self.is_blocked_by(Self::PARTICIPANT_LEFT) {
+            return err!(ReferralError::ParticipantLeft);
+        }
+        if self.is_blocked_by(Self::PROGRAM_INACTIVE) {
+            return err!(ReferralError::ProgramInactive);
+        }
+        if self.is_blocked_by(Self::NO_REWARDS) {
+            return err!(ReferralError::NoRewardsAvailable);
+        }
+        if self.is_blocked_by(Self::REWARDS_LOCKED) {
+            return err!(ReferralError::RewardsLocked);
+        }
+        if self.is_blocked_by(Self::PROGRAM_FROZEN) {
+            return err!(ReferralError::ProgramFrozen);
+        }
+        if self.is_blocked_by(Self::NOT_ACTIVATED) {
+            return err!(ReferralError::ParticipantNotActivated);
+        }
+        Ok(())
+    }
+}
+
+/// The accounts a claim presents besides the program and participant, for the gates that depend on them.
+///
+/// The default presents none, for callers that only judge the participant's own state: the region gates are skipped,
+/// while a program that only accepts direct claims reports the claim as not shown to be direct.
+#[derive(Clone, Copy, Default)]
+pub struct ClaimAccounts<'a, 'info> {
+    /// The program's eligibility criteria; the region gates are only evaluated when they are supplied
+    pub eligibility_criteria: Option<&'a EligibilityCriteria>,
+    /// The region attestor's attestation of the participant's owner
+    pub region_attestation: Option<&'a AccountInfo<'info>>,
+    /// The instructions sysvar, showing whether the claim was invoked directly
+    pub instructions_sysvar: Option<&'a AccountInfo<'info>>,
+}
+
+/// Evaluates every claim precondition for a participant at `now`, given the accounts the claim presents.
+///
+/// This is the single source of truth for claim gating: the claim handlers enforce its result via
+/// `ClaimEligibility::require_claimable` and `check_claim` returns it unchanged, so the explanation shown
+/// to users can never disagree with what the claim instruction actually does.
+pub fn claim_eligibility(
+    program: &ReferralProgram,
+    participant: &Participant,
+    now: i64,
+    accounts: ClaimAccounts,
+) -> ClaimEligibility {
+    let mut blocked = 0;
+    let mut claimable_at = now;
+
+    // A sysvar that cannot be read cannot show the claim to be direct either
+    if program.direct_claims_only && !is_direct_invocation(accounts.instructions_sysvar).unwrap_or(false) {
+        blocked |= ClaimEligibility::NOT_DIRECT;
+    }
+
+    // Claims only re-check the region when the program asks for it, so a participant embargoed after joining can
+    // otherwise still claim what it earned instead of stranding it in the vault
+    if let Some(criteria) = accounts.eligibility_criteria.filter(|criteria| criteria.region_checked_on_claim) {
+        blocked |= match region_violation(criteria, &participant.owner, accounts.region_attestation) {
+            None => 0,
+            Some(ReferralError::RegionAttestationRequired) => ClaimEligibility::REGION_ATTESTATION_REQUIRED,
+            Some(ReferralError::RegionEmbargoed) => ClaimEligibility::REGION_EMBARGOED,
+            Some(_) => ClaimEligibility::REGION_ATTESTATION_INVALID,
+        };
+    }
+
+    if !program.is_active && program.setup_state & ReferralProgram::SETUP_ACTIVATED == 0 {
+        blocked |= ClaimEligibility::PROGRAM_INACTIVE;
+    }
+
+    if participant.pending_rewards == 0 {
+        blocked |= ClaimEligibility::NO_REWARDS;
+    }
+
+    if participant.rotated_to.is_some() {
+        blocked |= ClaimEligibility::PARTICIPANT_ROTATED;
+    }
+
+    if participant.has_left() {
+        blocked |= ClaimEligibility::PARTICIPANT_LEFT;
+    }
+
+    if program.frozen {
+        blocked |= ClaimEligibility::PROGRAM_FROZEN;
+    }
+
+    if participant.awaits_activation() {
+        blocked |= ClaimEligibility::NOT_ACTIVATED;
+    }
+
+    let unlocks_at = participant.unlocks_at(program.locked_period);
+    if unlocks_at > now {
+        blocked |= ClaimEligibility::REWARDS_LOCKED;
+        claimable_at = claimable_at.max(unlocks_at);
+    }
+
+    ClaimEligibility { blocked, claimable_at }
+}
 
 #[derive(Accounts)]
 pub struct ClaimRewards<'info> {
     #[account(mut)]
     pub referral_program: Account<'info, ReferralProgram>,
+    #[account(
+        seeds = [b"eligibility_criteria", referral_program.key().as_ref()],
+        bump
+    )]
+    pub eligibility_criteria: Account<'info, EligibilityCriteria>,
     #[account(
         mut,
         seeds = [
@@ -15,9 +195,17 @@ pub struct ClaimRewards<'info> {
             referral_program.key().as_ref(),
             user.key().as_ref()
         ],
-        bump
+        bump,
+        constraint = participant.owner == user.key() @ ReferralError::InvalidAuthority,
+        constraint = participant.program == referral_program.key(),
     )]
     pub participant: Account<'info, Participant>,
+    /// The participant's claim splitter; required while it exists, with its destinations in the remaining accounts
+    #[account(
+        seeds = [SPLITTER_SEED, participant.key().as_ref()],
+        bump = claim_splitter.bump,
+    )]
+    pub claim_splitter: Option<Account<'info, ClaimSplitter>>,
     #[account(
         mut,
         seeds = [b"vault", referral_program.key().as_ref()],
@@ -26,65 +214,619 @@ pub struct ClaimRewards<'info> {
     pub vault: SystemAccount<'info>,
     #[account(mut)]
     pub user: Signer<'info>,
+    /// Optional event queue of the program; appended to when supplied
+    #[account(
+        mut,
+        seeds = [EVENT_QUEUE_SEED, referral_program.key().as_ref()],
+        bump = event_queue.bump,
+    )]
+    pub event_queue: Option<Box<Account<'info, EventQueue>>>,
+    /// CHECK: The instructions sysvar, checked by address; required when the program only accepts direct claims
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+    /// CHECK: The region attestor's attestation of the user; its owner and contents are checked in the handler.
+    /// Required when the program checks regions on claims
+    pub region_attestation: Option<UncheckedAccount<'info>>,
     pub system_program: Program<'info, System>,
 }
 
-pub fn process_claim_rewards(ctx: Context<ClaimRewards>) -> Result<()> {
+/// How a payout leaves the program, recorded with it by `record_payout`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PayoutKind {
+    /// A claim from a SOL program's vault, early or not
+    Sol,
+    /// A claim from a SOL program's vault paid into wrapped SOL token accounts
+    WrappedSol,
+    /// A claim from a token program's token vault
+    Token,
+    /// A referee's bonus paid from a SOL program's vault by the join that credited it
+    JoinAndClaim,
+}
+
+impl PayoutKind {
+    /// Returns true if the payout is made in the program's token rather than in SOL
+    pub fn pays_tokens(self) -> bool {
+        self == Self::Token
+    }
+}
+
+/// The running records of a program's payouts that `record_payout` keeps besides its counters.
+pub struct ProgramStats<'a> {
+    /// The program's event queue, appended a claim record for each payout
+    pub event_queue: &'a mut EventQueue,
+    /// When the payout is made
+    pub now: i64,
+}
+
+impl<'a> ProgramStats<'a> {
+    /// Returns the stats of a claim made at `now` that supplied the program's event queue, if it did.
+    pub fn of_queue(event_queue: Option<&'a mut Account<'_, EventQueue>>, now: i64) -> Option<Self> {
+        event_queue.map(|event_queue| Self { event_queue: &mut **event_queue, now })
+    }
+}
+
+/// Records the bookkeeping of `amount` paid out of the program's vault to a participant.
+///
+/// Every payout path goes through here so the program's counters, the participant's totals and the program's stats
+/// stay in step: the amount moves from `pending_rewards` to `total_rewards`, and from `total_available` and
+/// `total_committed` to `total_rewards_distributed`, and the payout is appended to the event queue in `stats`.
+/// A payout in the wrong currency for the program is refused. Nothing is updated unless every counter can move,
+/// and a zero amount is a no-op.
+pub fn record_payout(
+    referral_program: &mut ReferralProgram,
+    participant: &mut Participant,
+    stats: Option<&mut ProgramStats>,
+    amount: u64,
+    kind: PayoutKind,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    require!(kind.pays_tokens() == (referral_program.token_mint != Pubkey::default()), ReferralError::InvalidTokenMint);
+
+    let pending_rewards = participant.pending_rewards.checked_sub(amount).ok_or(ReferralError::NumericOverflow)?;
+    let total_rewards = participant.total_rewards.checked_add(amount).ok_or(ReferralError::NumericOverflow)?;
+    let total_available =
+        referral_program.total_available.checked_sub(amount).ok_or(ReferralError::InsufficientFunds)?;
+    let total_committed = referral_program.total_committed.checked_sub(amount).ok_or(ReferralError::NumericOverflow)?;
+    let total_rewards_distributed =
+        referral_program.total_rewards_distributed.checked_add(amount).ok_or(ReferralError::NumericOverflow)?;
+
+    // Funds only move from available to distributed; their sum is conserved, so funds that balanced against the
+    // deposits still do
+    debug_assert_eq!(
+        u128::from(total_available) + u128::from(total_rewards_distributed),
+        u128::from(referral_program.total_available) + u128::from(referral_program.total_rewards_distributed)
+    );
+    let balanced = funds_are_balanced(referral_program);
+
+    participant.pending_rewards = pending_rewards;
+    participant.total_rewards = total_rewards;
+    referral_program.total_available = total_available;
+    referral_program.total_committed = total_committed;
+    referral_program.total_rewards_distributed = total_rewards_distributed;
+    referral_program.refresh_ui_totals();
+    debug_assert!(!balanced || funds_are_balanced(referral_program), "payout unbalanced the funds");
+
+    if let Some(stats) = stats {
+        stats.event_queue.push(EventRecord::KIND_CLAIM, participant.owner, amount, stats.now);
+    }
+    Ok(())
+}
+
+/// Settles a claim of a participant's pending rewards in a fixed order: the amount is validated against the
+/// program's accounting and the vault's real balance first, all accounting is updated next, and `transfer`
+/// runs last. Nothing leaves the vault unless every check passed, whatever the transfer does.
+///
+/// Returns the amount that was paid out.
+pub fn settle_claim(
+    referral_program: &mut ReferralProgram,
+    participant: &mut Participant,
+    vault_balance: u64,
+    stats: Option<&mut ProgramStats>,
+    kind: PayoutKind,
+    transfer: impl FnOnce(u64) -> Result<()>,
+) -> Result<u64> {
+    // 1. Validate
+    let reward_amount = participant.pending_rewards;
+    require!(reward_amount <= referral_program.total_available, ReferralError::InsufficientVaultBalance);
+    require!(reward_amount <= vault_balance, ReferralError::InsufficientVaultBalance);
+
+    // 2. Update accounting
+    record_payout(referral_program, participant, stats, reward_amount, kind)?;
+
+    // 3. Transfer
+    transfer(reward_amount)?;
+
+    Ok(reward_amount)
+}
+
+/// The core every claim handler shares, whatever its vault holds: checks every claim gate at `now` against the
+/// accounts the claim presents, settles the participant's pending rewards through `settle_claim` and stops new
+/// referrals if the payout left the program short of a reward.
+///
+/// Returns the amount that was paid out.
+#[allow(clippy::too_many_arguments)]
+pub fn claim_pending(
+    referral_program: &mut Account<ReferralProgram>,
+    participant: &mut Participant,
+    vault_balance: u64,
+    now: i64,
+    accounts: ClaimAccounts,
+    stats: Option<&mut ProgramStats>,
+    kind: PayoutKind,
+    transfer: impl FnOnce(u64) -> Result<()>,
+) -> Result<u64> {
+    claim_eligibility(referral_program, participant, now, accounts).require_claimable()?;
+    let paid = settle_claim(referral_program, participant, vault_balance, stats, kind, transfer)?;
+    check_referral_funding(referral_program)?;
+    Ok(paid)
+}
+
+/// Returns the early redemption fee of `fee_bps` basis points on `amount`, rounded down.
+pub fn early_redemption_fee(amount: u64, fee_bps: u64) -> Result<u64> {
+    let fee = u128::from(amount).checked_mul(u128::from(fee_bps)).ok_or(ReferralError::NumericOverflow)? / 10_000;
+    u64::try_from(fee).map_err(|_| error!(ReferralError::NumericOverflow))
+}
+
+/// Forfeits `fee` of a participant's pending rewards on an early redemption. The fee stays in the vault: it is
+/// released from `total_committed` back into the program's uncommitted funds, and counted in the participant's
+/// `early_redemption_fees`.
+pub fn forfeit_early_redemption_fee(
+    referral_program: &mut ReferralProgram,
+    participant: &mut Participant,
+    fee: u64,
+) -> Result<()> {
+    let pending_rewards = participant.pending_rewards.checked_sub(fee).ok_or(ReferralError::NumericOverflow)?;
+    let early_redemption_fees =
+        participant.early_redemption_fees.checked_add(fee).ok_or(ReferralError::NumericOverflow)?;
+    let total_committed = referral_program.total_committed.checked_sub(fee).ok_or(ReferralError::NumericOverflow)?;
+
+    participant.pending_rewards = pending_rewards;
+    participant.early_redemption_fees = early_redemption_fees;
+    referral_program.total_committed = total_committed;
+    referral_program.refresh_ui_totals();
+    Ok(())
+}
+
+/// `claim_pending` for early redemptions: every claim gate but the locked period applies. While the participant's
+/// rewards are locked, the program's `early_redemption_fee` is forfeited from them before the rest is settled;
+/// rewards that have unlocked are claimed in full.
+///
+/// Returns the amount that was paid out and the fee that was forfeited.
+#[allow(clippy::too_many_arguments)]
+pub fn claim_pending_early(
+    referral_program: &mut Account<ReferralProgram>,
+    participant: &mut Participant,
+    vault_balance: u64,
+    now: i64,
+    accounts: ClaimAccounts,
+    stats: Option<&mut ProgramStats>,
+    kind: PayoutKind,
+    transfer: impl FnOnce(u64) -> Result<()>,
+) -> Result<(u64, u64)> {
+    let eligibility = claim_eligibility(referral_program, participant, now, accounts);
+    let locked = eligibility.is_blocked_by(ClaimEligibility::REWARDS_LOCKED);
+    ClaimEligibility { blocked: eligibility.blocked & !ClaimEligibility::REWARDS_LOCKED, ..eligibility }
+        .require_claimable()?;
+
+    let fee = if locked {
+        require!(referral_program.early_redemption_fee > 0, ReferralError::EarlyRedemptionDisabled);
+        early_redemption_fee(participant.pending_rewards, referral_program.early_redemption_fee)?
+    } else {
+        0
+    };
+    forfeit_early_redemption_fee(referral_program, participant, fee)?;
+    let paid = settle_claim(referral_program, participant, vault_balance, stats, kind, transfer)?;
+    check_referral_funding(referral_program)?;
+    Ok((paid, fee))
+}
+
+/// Claims a participant's pending rewards from a SOL program's vault, paying the user or, while the participant
+/// has a claim splitter, each destination wallet passed in the remaining accounts its share.
+pub fn process_claim_rewards<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimRewards<'info>>) -> Result<()> {
+    claim_sol_rewards(ctx, false)
+}
+
+/// Claims a participant's pending rewards from a SOL program's vault like `process_claim_rewards`, before the
+/// locked period has passed if need be, forfeiting the program's early redemption fee.
+pub fn process_early_claim_rewards<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimRewards<'info>>) -> Result<()> {
+    claim_sol_rewards(ctx, true)
+}
+
+/// The SOL claim both `process_claim_rewards` and `process_early_claim_rewards` make, settled through
+/// `claim_pending_early` when `early` is set.
+fn claim_sol_rewards<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimRewards<'info>>, early: bool) -> Result<()> {
+    let split = claim_split(&ctx.accounts.participant, ctx.accounts.claim_splitter.as_deref(), ctx.remaining_accounts)?;
+    let destinations = match split {
+        Some(claim_splitter) => {
+            require_split_wallets(claim_splitter, ctx.remaining_accounts)?;
+            ctx.remaining_accounts.to_vec()
+        }
+        None => vec![ctx.accounts.user.to_account_info()],
+    };
+    let guard = ClaimGuard::new_split(
+        ctx.accounts.participant.to_account_info(),
+        ctx.accounts.vault.to_account_info(),
+        destinations.clone(),
+    )?;
     let referral_program = &mut ctx.accounts.referral_program;
     let participant = &mut ctx.accounts.participant;
-    
-    // Verify program is active
-    require!(referral_program.is_active, ReferralError::ProgramInactive);
-    
-    // Calculate rewards amount
-    let reward_amount = calculate_reward_share(
-        participant.total_referrals,
-        referral_program.total_participants,
-        referral_program.total_available
-    );
+
+    // SOL claims are only available for SOL programs
+    require!(referral_program.token_mint == Pubkey::default(), ReferralError::InvalidTokenMint);
 
     // Transfer from vault using seeds signing
     let binding = referral_program.key();
-    let seeds = &[
-        VAULT_SEED,
-        binding.as_ref(),
-        &[referral_program.vault_bump], // Use the vault_bump from the referral program
-    ];
+    let seeds = &[VAULT_SEED, binding.as_ref(), &[ctx.bumps.vault]];
     let signer = &[&seeds[..]];
-    
-    // Transfer rewards to participant
-    let transfer_ctx = CpiContext::new_with_signer(
-        ctx.accounts.system_program.to_account_info(),
-        Transfer {
-            from: ctx.accounts.vault.to_account_info(),
-            to: ctx.accounts.user.to_account_info(),
-        },
-        signer,
-    );
-    
-    transfer(transfer_ctx, reward_amount)?;
-    
-    // Update participant state
-    participant.total_rewards = participant.total_rewards
-        .checked_add(reward_amount)
-        .ok_or(ReferralError::NumericOverflow)?;
-
-    referral_program.total_available = referral_program.total_available
-        .checked_sub(reward_amount)
-        .ok_or(ReferralError::InsufficientFunds)?;
-    
-    referral_program.total_rewards_distributed = referral_program.total_rewards_distributed
-        .checked_add(reward_amount)
-        .ok_or(ReferralError::NumericOverflow)?;
-    
+
+    // Pay out everything credited to the participant so far
+    let now = Clock::get()?.unix_timestamp;
+    let vault_balance = ctx.accounts.vault.lamports();
+    let claim_accounts = ClaimAccounts {
+        eligibility_criteria: Some(&ctx.accounts.eligibility_criteria),
+        region_attestation: ctx.accounts.region_attestation.as_ref().map(|attestation| attestation.as_ref()),
+        instructions_sysvar: ctx.accounts.instructions_sysvar.as_ref().map(|sysvar| sysvar.as_ref()),
+    };
+    let claim_splitter = ctx.accounts.claim_splitter.as_deref();
+    let transfer = |amount| {
+        let shares = claim_splitter.map_or_else(|| vec![amount], |claim_splitter| claim_splitter.shares(amount));
+        pay_sol_shares(
+            &ctx.accounts.vault.to_account_info(),
+            &destinations,
+            &shares,
+            signer,
+            &ctx.accounts.system_program.to_account_info(),
+        )
+    };
+    let mut stats = ProgramStats::of_queue(ctx.accounts.event_queue.as_deref_mut(), now);
+    let (stats, kind) = (stats.as_mut(), PayoutKind::Sol);
+    let (reward_amount, fee) = if early {
+        claim_pending_early(referral_program, participant, vault_balance, now, claim_accounts, stats, kind, transfer)?
+    } else {
+        (claim_pending(referral_program, participant, vault_balance, now, claim_accounts, stats, kind, transfer)?, 0)
+    };
+    guard.finish(reward_amount)?;
+    debug_assert_funds(&ctx.accounts.referral_program, ctx.accounts.vault.lamports());
+
+    if fee > 0 {
+        emit!(EarlyRedemption {
+            referral_program: ctx.accounts.referral_program.key(),
+            participant: ctx.accounts.participant.key(),
+            paid: reward_amount,
+            fee,
+        });
+    }
+
     Ok(())
 }
 
-fn calculate_reward_share(participant_referrals: u64, total_participants: u64, total_available: u64) -> u64 {
-    // Implement reward distribution formula here
-    // Example: proportional distribution based on referral count
-    if total_participants == 0 {
-        return 0;
+#[derive(Accounts)]
+pub struct ClaimRewardsWrapped<'info> {
+    #[account(mut)]
+    pub referral_program: Account<'info, ReferralProgram>,
+    #[account(
+        seeds = [b"eligibility_criteria", referral_program.key().as_ref()],
+        bump
+    )]
+    pub eligibility_criteria: Account<'info, EligibilityCriteria>,
+    #[account(
+        mut,
+        seeds = [
+            b"participant",
+            referral_program.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump,
+        constraint = participant.owner == user.key() @ ReferralError::InvalidAuthority,
+        constraint = participant.program == referral_program.key(),
+    )]
+    pub participant: Account<'info, Participant>,
+    /// The participant's claim splitter; required while it exists, with its destinations in the remaining accounts
+    #[account(
+        seeds = [SPLITTER_SEED, participant.key().as_ref()],
+        bump = claim_splitter.bump,
+    )]
+    pub claim_splitter: Option<Account<'info, ClaimSplitter>>,
+    #[account(
+        mut,
+        seeds = [b"vault", referral_program.key().as_ref()],
+        bump
+    )]
+    pub vault: SystemAccount<'info>,
+    /// The wrapped SOL mint
+    #[account(address = native_mint::ID @ ReferralError::InvalidTokenMint)]
+    pub native_mint: Account<'info, Mint>,
+    /// The user's wrapped SOL token account, receiving the rewards; created when missing
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = native_mint,
+        associated_token::authority = user,
+    )]
+    pub user_wsol_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    /// Optional event queue of the program; appended to when supplied
+    #[account(
+        mut,
+        seeds = [EVENT_QUEUE_SEED, referral_program.key().as_ref()],
+        bump = event_queue.bump,
+    )]
+    pub event_queue: Option<Box<Account<'info, EventQueue>>>,
+    /// CHECK: The instructions sysvar, checked by address; required when the program only accepts direct claims
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+    /// CHECK: The region attestor's attestation of the user; its owner and contents are checked in the handler.
+    /// Required when the program checks regions on claims
+    pub region_attestation: Option<UncheckedAccount<'info>>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Claims a SOL program's rewards as wrapped SOL, through the same `claim_pending` core as plain SOL claims.
+///
+/// The lamports go from the vault into the user's wrapped SOL token account and `sync_native` credits them as
+/// tokens, so the claim guard watches the token account's lamports and the token balance must rise by exactly the
+/// claimed amount. While the participant has a claim splitter, each destination's wrapped SOL account passed in the
+/// remaining accounts is paid its share instead, and must rise by exactly it.
+pub fn process_claim_rewards_wrapped<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ClaimRewardsWrapped<'info>>,
+) -> Result<()> {
+    let claim_splitter =
+        claim_split(&ctx.accounts.participant, ctx.accounts.claim_splitter.as_deref(), ctx.remaining_accounts)?;
+    let mut destinations = match claim_splitter {
+        Some(claim_splitter) => split_token_accounts(claim_splitter, ctx.remaining_accounts, native_mint::ID)?,
+        None => vec![ctx.accounts.user_wsol_account.clone()],
+    };
+    let guard = ClaimGuard::new_split(
+        ctx.accounts.participant.to_account_info(),
+        ctx.accounts.vault.to_account_info(),
+        destinations.iter().map(|destination| destination.to_account_info()).collect(),
+    )?;
+    let referral_program = &mut ctx.accounts.referral_program;
+    let participant = &mut ctx.accounts.participant;
+
+    // Wrapped claims pay out SOL, so they are only available for SOL programs
+    require!(referral_program.token_mint == Pubkey::default(), ReferralError::InvalidTokenMint);
+
+    let binding = referral_program.key();
+    let seeds = &[VAULT_SEED, binding.as_ref(), &[ctx.bumps.vault]];
+    let signer = &[&seeds[..]];
+
+    let now = Clock::get()?.unix_timestamp;
+    let vault_balance = ctx.accounts.vault.lamports();
+    let claim_accounts = ClaimAccounts {
+        eligibility_criteria: Some(&ctx.accounts.eligibility_criteria),
+        region_attestation: ctx.accounts.region_attestation.as_ref().map(|attestation| attestation.as_ref()),
+        instructions_sysvar: ctx.accounts.instructions_sysvar.as_ref().map(|sysvar| sysvar.as_ref()),
+    };
+    let tokens_before: Vec<u64> = destinations.iter().map(|destination| destination.amount).collect();
+    let destination_infos: Vec<AccountInfo> =
+        destinations.iter().map(|destination| destination.to_account_info()).collect();
+    let split_shares = |amount: u64| claim_splitter.map_or_else(|| vec![amount], |splitter| splitter.shares(amount));
+    let mut stats = ProgramStats::of_queue(ctx.accounts.event_queue.as_deref_mut(), now);
+    let (stats, kind) = (stats.as_mut(), PayoutKind::WrappedSol);
+    let reward_amount =
+        claim_pending(referral_program, participant, vault_balance, now, claim_accounts, stats, kind, |amount| {
+            let shares = split_shares(amount);
+            pay_sol_shares(
+                &ctx.accounts.vault.to_account_info(),
+                &destination_infos,
+                &shares,
+                signer,
+                &ctx.accounts.system_program.to_account_info(),
+            )?;
+            for destination in &destination_infos {
+                token::sync_native(CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    SyncNative { account: destination.clone() },
+                ))?;
+            }
+            Ok(())
+        })?;
+    guard.finish(reward_amount)?;
+
+    if !token_shares_paid(&mut destinations, &tokens_before, &split_shares(reward_amount))? {
+        msg!("Claim guard: wrapped SOL balances did not rise by exactly {}", reward_amount);
+        return err!(ReferralError::ClaimGuardViolation);
     }
-    (participant_referrals * total_available) / total_participants
+    debug_assert_funds(&ctx.accounts.referral_program, ctx.accounts.vault.lamports());
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ClaimTokenRewards<'info> {
+    #[account(mut)]
+    pub referral_program: Account<'info, ReferralProgram>,
+    #[account(
+        seeds = [b"eligibility_criteria", referral_program.key().as_ref()],
+        bump
+    )]
+    pub eligibility_criteria: Account<'info, EligibilityCriteria>,
+    #[account(
+        mut,
+        seeds = [
+            b"participant",
+            referral_program.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump,
+        constraint = participant.owner == user.key() @ ReferralError::InvalidAuthority,
+        constraint = participant.program == referral_program.key(),
+    )]
+    pub participant: Account<'info, Participant>,
+    /// The participant's claim splitter; required while it exists, with its destinations in the remaining accounts
+    #[account(
+        seeds = [SPLITTER_SEED, participant.key().as_ref()],
+        bump = claim_splitter.bump,
+    )]
+    pub claim_splitter: Option<Account<'info, ClaimSplitter>>,
+    /// PDA with seeds: ["token_vault", referral_program.key()]
+    #[account(
+        mut,
+        seeds = [TOKEN_VAULT_SEED, referral_program.key().as_ref()],
+        bump,
+        token::authority = referral_program,
+    )]
+    pub token_vault: Account<'info, TokenAccount>,
+    /// The participant's token account of the program's mint, receiving the rewards
+    #[account(
+        mut,
+        constraint = user_token_account.mint == referral_program.token_mint &&
+                     user_token_account.owner == user.key() @ ReferralError::InvalidTokenAccounts
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    pub user: Signer<'info>,
+    /// Optional event queue of the program; appended to when supplied
+    #[account(
+        mut,
+        seeds = [EVENT_QUEUE_SEED, referral_program.key().as_ref()],
+        bump = event_queue.bump,
+    )]
+    pub event_queue: Option<Box<Account<'info, EventQueue>>>,
+    /// CHECK: The instructions sysvar, checked by address; required when the program only accepts direct claims
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+    /// CHECK: The region attestor's attestation of the user; its owner and contents are checked in the handler.
+    /// Required when the program checks regions on claims
+    pub region_attestation: Option<UncheckedAccount<'info>>,
+    pub token_program: Program<'info, Token>,
+}
+
+/// Claims a participant's pending rewards from a token program's vault, through the same `claim_pending` core
+/// as SOL claims so both pay the same amount for the same state.
+///
+/// The claim guard watches the token balances: the vault must have paid exactly the claimed amount, the
+/// participant's token account received exactly it and the participant PDA's lamports stayed put, as for SOL
+/// claims. While the participant has a claim splitter, each destination's token account passed in the remaining
+/// accounts is paid its share instead, and must receive exactly it.
+pub fn process_claim_token_rewards<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimTokenRewards<'info>>) -> Result<()> {
+    claim_token_rewards(ctx, false)
+}
+
+/// Claims a participant's pending rewards from a token program's vault like `process_claim_token_rewards`, before
+/// the locked period has passed if need be, forfeiting the program's early redemption fee.
+pub fn process_early_claim_token_rewards<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ClaimTokenRewards<'info>>,
+) -> Result<()> {
+    claim_token_rewards(ctx, true)
+}
+
+/// The token claim both `process_claim_token_rewards` and `process_early_claim_token_rewards` make, settled
+/// through `claim_pending_early` when `early` is set.
+fn claim_token_rewards<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimTokenRewards<'info>>, early: bool) -> Result<()> {
+    let claim_splitter =
+        claim_split(&ctx.accounts.participant, ctx.accounts.claim_splitter.as_deref(), ctx.remaining_accounts)?;
+    let token_mint = ctx.accounts.referral_program.token_mint;
+
+    // Token claims are only available for token programs
+    require!(token_mint != Pubkey::default(), ReferralError::InvalidTokenMint);
+    let mut destinations = match claim_splitter {
+        Some(claim_splitter) => split_token_accounts(claim_splitter, ctx.remaining_accounts, token_mint)?,
+        None => vec![ctx.accounts.user_token_account.clone()],
+    };
+    let guard = ClaimGuard::new_token_split(
+        ctx.accounts.participant.to_account_info(),
+        ctx.accounts.token_vault.to_account_info(),
+        destinations.iter().map(|destination| destination.to_account_info()).collect(),
+    )?;
+    let referral_program = &mut ctx.accounts.referral_program;
+    let participant = &mut ctx.accounts.participant;
+
+    // The claim updates the program account while the transfer signs with its seeds, so sign from a copy
+    let signing_program = ReferralProgram::clone(referral_program);
+    let index_bytes = signing_program.program_index.to_le_bytes();
+    let seeds = signing_program.signer_seeds(&index_bytes);
+    let signer = &[&seeds[..]];
+
+    let now = Clock::get()?.unix_timestamp;
+    let vault_before = ctx.accounts.token_vault.amount;
+    let claim_accounts = ClaimAccounts {
+        eligibility_criteria: Some(&ctx.accounts.eligibility_criteria),
+        region_attestation: ctx.accounts.region_attestation.as_ref().map(|attestation| attestation.as_ref()),
+        instructions_sysvar: ctx.accounts.instructions_sysvar.as_ref().map(|sysvar| sysvar.as_ref()),
+    };
+    let destinations_before: Vec<u64> = destinations.iter().map(|destination| destination.amount).collect();
+    let program_info = referral_program.to_account_info();
+    let split_shares = |amount: u64| claim_splitter.map_or_else(|| vec![amount], |splitter| splitter.shares(amount));
+    let mut stats = ProgramStats::of_queue(ctx.accounts.event_queue.as_deref_mut(), now);
+    let (stats, kind) = (stats.as_mut(), PayoutKind::Token);
+    let transfer = |amount| {
+        let shares = split_shares(amount);
+        for (destination, &share) in destinations.iter().zip(&shares).filter(|(_, &share)| share > 0) {
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::Transfer {
+                        from: ctx.accounts.token_vault.to_account_info(),
+                        to: destination.to_account_info(),
+                        authority: program_info.clone(),
+                    },
+                    signer,
+                ),
+                share,
+            )?;
+        }
+        Ok(())
+    };
+    let (reward_amount, fee) = if early {
+        claim_pending_early(referral_program, participant, vault_before, now, claim_accounts, stats, kind, transfer)?
+    } else {
+        (claim_pending(referral_program, participant, vault_before, now, claim_accounts, stats, kind, transfer)?, 0)
+    };
+
+    guard.finish(reward_amount)?;
+
+    if !token_shares_paid(&mut destinations, &destinations_before, &split_shares(reward_amount))? {
+        msg!("Claim guard: token balances did not rise by exactly their shares of {}", reward_amount);
+        return err!(ReferralError::ClaimGuardViolation);
+    }
+    ctx.accounts.token_vault.reload()?;
+    debug_assert_funds(&ctx.accounts.referral_program, ctx.accounts.token_vault.amount);
+
+    if fee > 0 {
+        emit!(EarlyRedemption {
+            referral_program: ctx.accounts.referral_program.key(),
+            participant: ctx.accounts.participant.key(),
+            paid: reward_amount,
+            fee,
+        });
+    }
+    Ok(())
+}
+
+/// Accounts required for the read-only `check_claim` instruction.
+#[derive(Accounts)]
+pub struct CheckClaim<'info> {
+    pub referral_program: Account<'info, ReferralProgram>,
+    #[account(
+        seeds = [b"eligibility_criteria", referral_program.key().as_ref()],
+        bump
+    )]
+    pub eligibility_criteria: Account<'info, EligibilityCriteria>,
+    #[account(
+        constraint = participant.program == referral_program.key()
+    )]
+    pub participant: Account<'info, Participant>,
+    /// CHECK: The region attestor's attestation of the participant's owner, judged as a claim would judge it
+    pub region_attestation: Option<UncheckedAccount<'info>>,
+    /// CHECK: The instructions sysvar, checked by address; the check is judged as direct as a claim made the same
+    /// way would be
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+}
+
+/// Returns the claim eligibility of a participant without mutating any state.
+pub fn check_claim(ctx: Context<CheckClaim>) -> Result<ClaimEligibility> {
+    let now = Clock::get()?.unix_timestamp;
+    let claim_accounts = ClaimAccounts {
+        eligibility_criteria: Some(&ctx.accounts.eligibility_criteria),
+        region_attestation: ctx.accounts.region_attestation.as_ref().map(|attestation| attestation.as_ref()),
+        instructions_sysvar: ctx.accounts.instructions_sysvar.as_ref().map(|sysvar| sysvar.as_ref()),
+    };
+    Ok(claim_eligibility(&ctx.accounts.referral_program, &ctx.accounts.participant, now, claim_accounts))
 }
@@ -0,0 +1,165 @@
+//! Participants seeded by the authority for partner wallets.
+//!
+//! Partnerships are often signed before a program launches, and partners want their referral links live from the
+//! start without signing a join themselves. The authority can create a partner's participant account for it: the
+//! account can be named as a referrer at once and is credited like any other, but only claims once its owner has
+//! activated it with their own key. Accounts a partner never activates and that never earned anything can be
+//! reclaimed by the authority after the program's `seed_reclaim_seconds`.
+use crate::{error::ReferralError, events::*, state::*};
+use anchor_lang::{prelude::*, system_program::System};
+
+/// Accounts required for seeding a participant account for a partner wallet.
+#[derive(Accounts)]
+#[instruction(owner: Pubkey)]
+pub struct SeedParticipant<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    /// The partner's participant account, created here with its rent paid by the authority
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Participant::SIZE,
+        seeds = [
+            b"participant",
+            referral_program.key().as_ref(),
+            owner.as_ref()
+        ],
+        bump
+    )]
+    pub participant: Account<'info, Participant>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Creates the participant account of `owner`, a partner wallet, paying its rent from the authority.
+///
+/// The program need not be active yet, so partners' links can be handed out before launch. The account counts
+/// toward the program's participants, can be named as a referrer at once and accrues rewards as usual, but cannot
+/// claim until `owner` activates it with `activate_seeded`. It joins directly and has accepted no terms.
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `ProgramEnded`, `ProgramClosing` - If the program no longer takes participants
+/// * `ProgramAbandoned` - If the program was declared abandoned
+pub fn seed_participant(ctx: Context<SeedParticipant>, owner: Pubkey) -> Result<()> {
+    let current_time = Clock::get()?.unix_timestamp;
+    let referral_program = &mut ctx.accounts.referral_program;
+    require!(!referral_program.has_ended(current_time), ReferralError::ProgramEnded);
+    require!(!referral_program.is_closing(), ReferralError::ProgramClosing);
+    referral_program.record_authority_action(current_time)?;
+    referral_program.total_participants =
+        referral_program.total_participants.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+
+    let participant = &mut ctx.accounts.participant;
+    participant.owner = owner;
+    participant.program = referral_program.key();
+    participant.join_time = current_time;
+    participant.seeded = true;
+    participant.set_referral_link(&owner, referral_program.link_format);
+
+    emit!(ParticipantSeeded { referral_program: referral_program.key(), participant: participant.key(), owner });
+    Ok(())
+}
+
+/// Accounts required for the owner of a seeded participant activating it.
+#[derive(Accounts)]
+pub struct ActivateSeeded<'info> {
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    /// The signer's seeded participant account
+    #[account(
+        mut,
+        seeds = [
+            b"participant",
+            referral_program.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump
+    )]
+    pub participant: Account<'info, Participant>,
+
+    pub user: Signer<'info>,
+}
+
+/// Activates the signer's seeded participant account, letting it claim what it has been credited.
+///
+/// The referral link is written again in the program's current link format, which may have changed since the
+/// account was seeded, and logged for the frontend to pick up.
+///
+/// # Errors
+/// * `NotAwaitingActivation` - If the account was not seeded or is already activated
+pub fn activate_seeded(ctx: Context<ActivateSeeded>) -> Result<()> {
+    let participant = &mut ctx.accounts.participant;
+    require!(participant.awaits_activation(), ReferralError::NotAwaitingActivation);
+    participant.activated = true;
+    participant.set_referral_link(&ctx.accounts.user.key(), ctx.accounts.referral_program.link_format);
+    log_referral_link(participant);
+    Ok(())
+}
+
+/// Accounts required for the authority reclaiming an unused seeded participant.
+#[derive(Accounts)]
+pub struct ReclaimSeededParticipant<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    /// The seeded participant account, closed to the authority
+    #[account(
+        mut,
+        close = authority,
+        constraint = participant.program == referral_program.key() @ ReferralError::InvalidReferrer
+    )]
+    pub participant: Account<'info, Participant>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+/// Closes a seeded participant its owner never activated, returning its rent to the authority.
+///
+/// The account must be untouched: never referred anyone or earned anything (see `Participant::has_earned`), and
+/// seeded at least the program's `seed_reclaim_seconds` ago. It no longer counts toward the program's
+/// participants.
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `InvalidReferrer` - If the participant belongs to another program
+/// * `NotAwaitingActivation` - If the account was not seeded or its owner activated it
+/// * `SeedNotReclaimable` - If the program has no reclaim period, it has not passed since the account was seeded,
+///   or the account was rotated or has earned anything
+/// * `ProgramAbandoned` - If the program was declared abandoned
+pub fn reclaim_seeded_participant(ctx: Context<ReclaimSeededParticipant>) -> Result<()> {
+    let current_time = Clock::get()?.unix_timestamp;
+    let referral_program = &mut ctx.accounts.referral_program;
+    referral_program.record_authority_action(current_time)?;
+
+    let participant = &ctx.accounts.participant;
+    require!(participant.awaits_activation(), ReferralError::NotAwaitingActivation);
+    let period = referral_program.seed_reclaim_seconds;
+    require!(
+        period > 0
+            && current_time >= participant.join_time.saturating_add(period)
+            && participant.rotated_to.is_none()
+            && !participant.has_left()
+            && !participant.has_earned(),
+        ReferralError::SeedNotReclaimable
+    );
+    referral_program.total_participants = referral_program.total_participants.saturating_sub(1);
+
+    emit!(SeededParticipantReclaimed {
+        referral_program: referral_program.key(),
+        participant: participant.key(),
+        owner: participant.owner,
+    });
+    Ok(())
+}
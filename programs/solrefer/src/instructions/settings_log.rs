@@ -0,0 +1,169 @@
+use crate::{
+    constants::SETTINGS_CHANGE_SEED,
+    events::ProgramField,
+    instructions::{create_aux_account, ProgramSettings, RentPayer},
+    state::*,
+};
+use anchor_lang::{prelude::*, solana_program::hash::hash};
+
+/// Returns the settings a program currently has, as `update_program_settings` would have set them.
+pub fn current_settings(program: &ReferralProgram, criteria: &EligibilityCriteria) -> ProgramSettings {
+    ProgramSettings {
+        fixed_reward_amount: program.fixed_reward_amount,
+        locked_period: program.locked_period,
+        program_end_time: criteria.program_end_time,
+        base_reward: criteria.base_reward,
+        max_reward_cap: criteria.max_reward_cap,
+        max_depth: program.max_depth,
+        milestones: criteria.milestones,
+        invite_only: program.invite_only,
+        revenue_share_percent: criteria.revenue_share_percent,
+        referee_reward_amount: program.referee_reward_amount,
+        referee_rewards_locked: program.referee_rewards_locked,
+        reserve_bps: program.reserve_bps,
+        max_referrals_per_window: criteria.max_referrals_per_window,
+        referral_window_seconds: criteria.referral_window_seconds,
+        rate_limit_strict: criteria.rate_limit_strict,
+        required_collection: criteria.required_collection,
+        collection_gates_credits: criteria.collection_gates_credits,
+        transfers_enabled: criteria.transfers_enabled,
+        dispute_window_seconds: program.dispute_window_seconds,
+        rent_payer_mode: program.rent_payer_mode,
+        min_joiner_balance: criteria.min_joiner_balance,
+        min_account_age_seconds: criteria.min_account_age_seconds,
+        alert_thresholds: program.alert_thresholds,
+        referrer_requirement: criteria.referrer_requirement,
+        referee_requirement: criteria.referee_requirement,
+        direct_claims_only: program.direct_claims_only,
+        trailing_commission_bps: criteria.trailing_commission_bps,
+        link_signer: criteria.link_signer,
+        dormancy_period_seconds: program.dormancy_period_seconds,
+        region_attestor: criteria.region_attestor,
+        embargoed_regions: criteria.embargoed_regions,
+        region_checked_on_claim: criteria.region_checked_on_claim,
+        bridge_source_program: criteria.bridge_source_program,
+        bridge_bps: criteria.bridge_bps,
+        cleanup_bounty_bps: program.cleanup_bounty_bps,
+        event_mode: program.event_mode,
+        attribution_grace_seconds: program.attribution_grace_seconds,
+        tier1_reward: criteria.tier1_reward,
+        tier2_reward: criteria.tier2_reward,
+        auto_downgrade: criteria.auto_downgrade,
+        min_remaining_credits: criteria.min_remaining_credits,
+        seed_reclaim_seconds: program.seed_reclaim_seconds,
+        early_redemption_fee: program.early_redemption_fee,
+    }
+}
+
+/// Returns the first eight bytes of the hash of a serialized value, standing in for a group of parameters in a
+/// `FieldChange`.
+fn fingerprint(value: &impl AnchorSerialize) -> Result<u64> {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hash(&value.try_to_vec()?).to_bytes()[..8]);
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Encodes an end time for a `FieldChange`, an open-ended program as 0.
+pub fn end_time_value(program_end_time: Option<i64>) -> u64 {
+    program_end_time.unwrap_or(0) as u64
+}
+
+/// Returns the settings that differ between `old` and `new`, in `ProgramField` order.
+pub fn settings_changes(old: &ProgramSettings, new: &ProgramSettings) -> Result<Vec<FieldChange>> {
+    let rate_limit = |settings: &ProgramSettings| {
+        fingerprint(&(settings.max_referrals_per_window, settings.referral_window_seconds, settings.rate_limit_strict))
+    };
+    let collection =
+        |settings: &ProgramSettings| fingerprint(&(settings.required_collection, settings.collection_gates_credits));
+    let join_requirements =
+        |settings: &ProgramSettings| fingerprint(&(settings.min_joiner_balance, settings.min_account_age_seconds));
+    let token_requirements =
+        |settings: &ProgramSettings| fingerprint(&(settings.referrer_requirement, settings.referee_requirement));
+    let bridge = |settings: &ProgramSettings| fingerprint(&(settings.bridge_source_program, settings.bridge_bps));
+    let auto_downgrade =
+        |settings: &ProgramSettings| fingerprint(&(settings.auto_downgrade, settings.min_remaining_credits));
+    let region_embargo = |settings: &ProgramSettings| {
+        fingerprint(&(settings.region_attestor, settings.embargoed_regions, settings.region_checked_on_claim))
+    };
+
+    let values = [
+        (ProgramField::FixedRewardAmount, old.fixed_reward_amount, new.fixed_reward_amount),
+        (ProgramField::BaseReward, old.base_reward, new.base_reward),
+        (ProgramField::MaxRewardCap, old.max_reward_cap, new.max_reward_cap),
+        (ProgramField::LockedPeriod, old.locked_period as u64, new.locked_period as u64),
+        (ProgramField::ProgramEndTime, end_time_value(old.program_end_time), end_time_value(new.program_end_time)),
+        (ProgramField::Milestones, fingerprint(&old.milestones)?, fingerprint(&new.milestones)?),
+        (ProgramField::Tier1Reward, old.tier1_reward, new.tier1_reward),
+        (ProgramField::Tier2Reward, old.tier2_reward, new.tier2_reward),
+        (ProgramField::RevenueSharePercent, old.revenue_share_percent, new.revenue_share_percent),
+        (ProgramField::ReserveBps, old.reserve_bps, new.reserve_bps),
+        (ProgramField::ReferralRateLimit, rate_limit(old)?, rate_limit(new)?),
+        (ProgramField::RequiredCollection, collection(old)?, collection(new)?),
+        (ProgramField::DisputeWindow, old.dispute_window_seconds as u64, new.dispute_window_seconds as u64),
+        (ProgramField::RentPayerMode, old.rent_payer_mode.into(), new.rent_payer_mode.into()),
+        (ProgramField::JoinRequirements, join_requirements(old)?, join_requirements(new)?),
+        (ProgramField::AlertThresholds, fingerprint(&old.alert_thresholds)?, fingerprint(&new.alert_thresholds)?),
+        (ProgramField::TokenRequirements, token_requirements(old)?, token_requirements(new)?),
+        (ProgramField::TrailingCommission, old.trailing_commission_bps, new.trailing_commission_bps),
+        (ProgramField::MaxDepth, old.max_depth.into(), new.max_depth.into()),
+        (ProgramField::InviteOnly, old.invite_only.into(), new.invite_only.into()),
+        (ProgramField::RefereeReward, old.referee_reward_amount, new.referee_reward_amount),
+        (ProgramField::RefereeRewardsLocked, old.referee_rewards_locked.into(), new.referee_rewards_locked.into()),
+        (ProgramField::TransfersEnabled, old.transfers_enabled.into(), new.transfers_enabled.into()),
+        (ProgramField::DirectClaimsOnly, old.direct_claims_only.into(), new.direct_claims_only.into()),
+        (ProgramField::LinkSigner, fingerprint(&old.link_signer)?, fingerprint(&new.link_signer)?),
+        (ProgramField::DormancyPeriod, old.dormancy_period_seconds as u64, new.dormancy_period_seconds as u64),
+        (ProgramField::RegionEmbargo, region_embargo(old)?, region_embargo(new)?),
+        (ProgramField::Bridge, bridge(old)?, bridge(new)?),
+        (ProgramField::CleanupBounty, old.cleanup_bounty_bps.into(), new.cleanup_bounty_bps.into()),
+        (ProgramField::EventMode, old.event_mode.into(), new.event_mode.into()),
+        (ProgramField::AttributionGrace, old.attribution_grace_seconds as u64, new.attribution_grace_seconds as u64),
+        (ProgramField::AutoDowngrade, auto_downgrade(old)?, auto_downgrade(new)?),
+        (ProgramField::SeedReclaim, old.seed_reclaim_seconds as u64, new.seed_reclaim_seconds as u64),
+        (ProgramField::EarlyRedemptionFee, old.early_redemption_fee, new.early_redemption_fee),
+    ];
+    Ok(values
+        .into_iter()
+        .filter(|(_, old_value, new_value)| old_value != new_value)
+        .map(|(field, old_value, new_value)| FieldChange { field: field as u8, old_value, new_value })
+        .collect())
+}
+
+/// Creates the program's next `SettingsChangeRecord` holding `changes`, its rent paid by `payer`, and advances the
+/// program's `settings_change_count`; records nothing if `changes` is empty.
+///
+/// `record` must be the PDA of index `settings_change_count`, with its bump.
+pub fn record_settings_change<'info>(
+    referral_program: &mut Account<'info, ReferralProgram>,
+    record: (&AccountInfo<'info>, u8),
+    changes: Vec<FieldChange>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    now: i64,
+) -> Result<()> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let (record_info, bump) = record;
+    let program_key = referral_program.key();
+    let index = referral_program.settings_change_count;
+    create_aux_account(
+        record_info,
+        &[SETTINGS_CHANGE_SEED, program_key.as_ref(), &index.to_le_bytes(), &[bump]],
+        8 + SettingsChangeRecord::size(changes.len()),
+        &RentPayer::Signer(payer),
+        system_program,
+    )?;
+
+    let record = SettingsChangeRecord {
+        referral_program: program_key,
+        index,
+        authority: referral_program.authority,
+        changed_at: now,
+        bump,
+        changes,
+    };
+    record.try_serialize(&mut &mut record_info.try_borrow_mut_data()?[..])?;
+    referral_program.settings_change_count = index + 1;
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+/// Setup progress of a referral program, returned by `get_setup_state`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SetupStatus {
+    /// Bitmask of the completed `ReferralProgram::SETUP_*` steps
+    pub setup_state: u8,
+    /// The first step still to be performed, as its `SETUP_*` bit; 0 once setup is complete
+    pub next_step: u8,
+    /// Whether new referrals are credited; false once a claim or withdrawal left too little to fund a reward
+    pub accepting_referrals: bool,
+    /// Until when the creator locked the program's settings (0 = never locked); clients compare it to the clock
+    pub settings_locked_until: i64,
+}
+
+/// Accounts required for reading a program's setup state.
+#[derive(Accounts)]
+pub struct GetSetupState<'info> {
+    pub referral_program: Account<'info, ReferralProgram>,
+}
+
+/// Returns the setup steps a program has completed and the next one to perform, without mutating any state.
+pub fn get_setup_state(ctx: Context<GetSetupState>) -> Result<SetupStatus> {
+    let referral_program = &ctx.accounts.referral_program;
+    Ok(SetupStatus {
+        setup_state: referral_program.setup_state,
+        next_step: referral_program.next_setup_step(),
+        accepting_referrals: referral_program.accepting_referrals,
+        settings_locked_until: referral_program.settings_locked_until,
+    })
+}
@@ -0,0 +1,209 @@
+use crate::{
+    constants::{RENT_PAYER_SPONSOR, SPONSOR_VAULT_SEED},
+    error::ReferralError,
+    state::*,
+    validation::require_nonzero_amount,
+};
+use anchor_lang::{
+    prelude::*,
+    system_program::{self, Allocate, Assign, CreateAccount, Transfer},
+};
+
+/// Who pays the rent of an auxiliary account, resolved from the program's `rent_payer_mode`.
+pub enum RentPayer<'a, 'info> {
+    /// A wallet that signed the transaction
+    Signer(&'a AccountInfo<'info>),
+    /// The program's sponsor vault, which signs with its seeds
+    SponsorVault { vault: &'a AccountInfo<'info>, referral_program: Pubkey, bump: u8 },
+}
+
+impl<'a, 'info> RentPayer<'a, 'info> {
+    /// Resolves who pays for the auxiliary accounts `user` causes in `referral_program`: the user, or the sponsor
+    /// vault with its bump in `RENT_PAYER_SPONSOR` mode.
+    ///
+    /// # Errors
+    /// * `SponsorVaultUnderfunded` - If the sponsor vault pays but was not supplied
+    pub fn for_program(
+        referral_program: &Account<ReferralProgram>,
+        user: &'a AccountInfo<'info>,
+        sponsor_vault: Option<(&'a AccountInfo<'info>, u8)>,
+    ) -> Result<Self> {
+        if referral_program.rent_payer_mode != RENT_PAYER_SPONSOR {
+            return Ok(RentPayer::Signer(user));
+        }
+        let (vault, bump) = sponsor_vault.ok_or(ReferralError::SponsorVaultUnderfunded)?;
+        Ok(RentPayer::SponsorVault { vault, referral_program: referral_program.key(), bump })
+    }
+}
+
+/// Creates the program-owned account `account` at the PDA derived from `seeds` with `space` bytes, its rent paid
+/// by `rent_payer`.
+///
+/// Every auxiliary account goes through here, so the program's `rent_payer_mode` applies to all of them. An
+/// address that already holds lamports is topped up, allocated and assigned instead, so funding it first cannot
+/// block its creation.
+///
+/// # Errors
+/// * `SponsorVaultUnderfunded` - If the sponsor vault pays and cannot cover the rent while staying rent-exempt
+pub fn create_aux_account<'info>(
+    account: &AccountInfo<'info>,
+    seeds: &[&[u8]],
+    space: usize,
+    rent_payer: &RentPayer<'_, 'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let rent = Rent::get()?;
+    let lamports = rent.minimum_balance(space).saturating_sub(account.lamports());
+    match rent_payer {
+        RentPayer::Signer(payer) => create_signed(account, space, lamports, payer, &[seeds], system_program),
+        RentPayer::SponsorVault { vault, referral_program, bump } => {
+            let left = vault.lamports().checked_sub(lamports);
+            require!(
+                left.is_some_and(|left| left == 0 || left >= rent.minimum_balance(0)),
+                ReferralError::SponsorVaultUnderfunded
+            );
+            let bump = [*bump];
+            let vault_seeds: [&[u8]; 3] = [SPONSOR_VAULT_SEED, referral_program.as_ref(), &bump];
+            create_signed(account, space, lamports, vault, &[seeds, &vault_seeds], system_program)
+        }
+    }
+}
+
+/// Moves `lamports` from `payer` into `account` and makes it a program account of `space` bytes, signing every
+/// step with `signers`.
+fn create_signed<'info>(
+    account: &AccountInfo<'info>,
+    space: usize,
+    lamports: u64,
+    payer: &AccountInfo<'info>,
+    signers: &[&[&[u8]]],
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    if account.lamports() == 0 {
+        return system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                CreateAccount { from: payer.clone(), to: account.clone() },
+                signers,
+            ),
+            lamports,
+            space as u64,
+            &crate::ID,
+        );
+    }
+
+    if lamports > 0 {
+        system_program::transfer(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                Transfer { from: payer.clone(), to: account.clone() },
+                signers,
+            ),
+            lamports,
+        )?;
+    }
+    system_program::allocate(
+        CpiContext::new_with_signer(system_program.clone(), Allocate { account_to_allocate: account.clone() }, signers),
+        space as u64,
+    )?;
+    system_program::assign(
+        CpiContext::new_with_signer(system_program.clone(), Assign { account_to_assign: account.clone() }, signers),
+        &crate::ID,
+    )
+}
+
+/// Accounts required for funding a program's sponsor vault.
+#[derive(Accounts)]
+pub struct FundSponsorVault<'info> {
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    /// The vault that pays sponsored rent
+    /// PDA with seeds: ["sponsor_vault", referral_program.key()]
+    #[account(
+        mut,
+        seeds = [SPONSOR_VAULT_SEED, referral_program.key().as_ref()],
+        bump,
+    )]
+    pub sponsor_vault: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Adds SOL to the program's sponsor vault, which pays the rent of auxiliary accounts in `RENT_PAYER_SPONSOR`
+/// mode. Anyone can fund it; only the authority can withdraw.
+///
+/// # Arguments
+/// * `ctx` - The context for the FundSponsorVault instruction
+/// * `amount` - The lamports to add
+///
+/// # Errors
+/// * `InsufficientDeposit` - If the amount is zero
+pub fn fund_sponsor_vault(ctx: Context<FundSponsorVault>, amount: u64) -> Result<()> {
+    require_nonzero_amount(amount, ReferralError::InsufficientDeposit)?;
+    system_program::transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            Transfer { from: ctx.accounts.funder.to_account_info(), to: ctx.accounts.sponsor_vault.to_account_info() },
+        ),
+        amount,
+    )?;
+
+    msg!("Funded the sponsor vault of referral program {} with {}", ctx.accounts.referral_program.key(), amount);
+    Ok(())
+}
+
+/// Accounts required for withdrawing from a program's sponsor vault.
+#[derive(Accounts)]
+pub struct WithdrawSponsorVault<'info> {
+    #[account(mut, has_one = authority @ ReferralError::InvalidAuthority)]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    /// The vault that pays sponsored rent
+    /// PDA with seeds: ["sponsor_vault", referral_program.key()]
+    #[account(
+        mut,
+        seeds = [SPONSOR_VAULT_SEED, referral_program.key().as_ref()],
+        bump,
+    )]
+    pub sponsor_vault: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Moves SOL from the program's sponsor vault back to the authority.
+///
+/// # Arguments
+/// * `ctx` - The context for the WithdrawSponsorVault instruction
+/// * `amount` - The lamports to withdraw
+///
+/// # Errors
+/// * `InvalidAuthority` - If the signer is not the program authority
+/// * `InsufficientFunds` - If the amount is zero
+/// * `ProgramFrozen` - If the guardian froze the program
+pub fn withdraw_sponsor_vault(ctx: Context<WithdrawSponsorVault>, amount: u64) -> Result<()> {
+    require_nonzero_amount(amount, ReferralError::InsufficientFunds)?;
+    ctx.accounts.referral_program.record_authority_action(Clock::get()?.unix_timestamp)?;
+    require!(!ctx.accounts.referral_program.frozen, ReferralError::ProgramFrozen);
+    let program_key = ctx.accounts.referral_program.key();
+    let seeds: &[&[u8]] = &[SPONSOR_VAULT_SEED, program_key.as_ref(), &[ctx.bumps.sponsor_vault]];
+    system_program::transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.system_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.sponsor_vault.to_account_info(),
+                to: ctx.accounts.authority.to_account_info(),
+            },
+            &[seeds],
+        ),
+        amount,
+    )?;
+
+    msg!("Withdrew {} from the sponsor vault of referral program {}", amount, program_key);
+    Ok(())
+}
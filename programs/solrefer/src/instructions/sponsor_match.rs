@@ -0,0 +1,146 @@
+use crate::{
+    constants::{MATCH_OFFER_SEED, MAX_MATCH_BPS},
+    error::ReferralError,
+    events::{JoinEvents, RewardMatched},
+    state::*,
+    validation::require_nonzero_amount,
+};
+use anchor_lang::{
+    prelude::*,
+    system_program::{self, Transfer},
+};
+
+/// Accounts required for creating or topping up a sponsor's match offer.
+#[derive(Accounts)]
+pub struct CreateMatchOffer<'info> {
+    #[account(mut)]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    /// PDA with seeds: ["match", referral_program.key(), sponsor.key()]
+    #[account(
+        init_if_needed,
+        payer = sponsor,
+        space = 8 + MatchOffer::SIZE,
+        seeds = [MATCH_OFFER_SEED, referral_program.key().as_ref(), sponsor.key().as_ref()],
+        bump
+    )]
+    pub match_offer: Account<'info, MatchOffer>,
+
+    #[account(mut)]
+    pub sponsor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Escrows `budget` lamports in the signer's match offer and sets the match it pays on each referral reward.
+///
+/// Every referral credited while the offer is passed to the join pays the referrer `match_bps` basis points of
+/// its reward from the offer, until the budget runs out. Funding again tops the offer up and replaces the match.
+///
+/// # Arguments
+/// * `ctx` - The context for the CreateMatchOffer instruction
+/// * `match_bps` - The match in basis points of each referral reward, at most `MAX_MATCH_BPS`
+/// * `budget` - Lamports to escrow
+///
+/// # Errors
+/// * `InsufficientDeposit` - If the budget is zero
+/// * `InvalidMatchBps` - If the match is zero or above `MAX_MATCH_BPS`
+/// * `ProgramEnded` - If the program's end time has passed
+/// * `ProgramClosing` - If the program is pending closure
+pub fn create_match_offer(ctx: Context<CreateMatchOffer>, match_bps: u16, budget: u64) -> Result<()> {
+    require_nonzero_amount(budget, ReferralError::InsufficientDeposit)?;
+    require!(match_bps > 0 && match_bps <= MAX_MATCH_BPS, ReferralError::InvalidMatchBps);
+    let referral_program = &ctx.accounts.referral_program;
+    require!(!referral_program.has_ended(Clock::get()?.unix_timestamp), ReferralError::ProgramEnded);
+    require!(!referral_program.is_closing(), ReferralError::ProgramClosing);
+
+    system_program::transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            Transfer { from: ctx.accounts.sponsor.to_account_info(), to: ctx.accounts.match_offer.to_account_info() },
+        ),
+        budget,
+    )?;
+
+    let referral_program = &mut ctx.accounts.referral_program;
+    let match_offer = &mut ctx.accounts.match_offer;
+    if match_offer.program == Pubkey::default() {
+        referral_program.open_match_offers =
+            referral_program.open_match_offers.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+    }
+    match_offer.program = referral_program.key();
+    match_offer.sponsor = ctx.accounts.sponsor.key();
+    match_offer.match_bps = match_bps;
+    match_offer.balance = match_offer.balance.checked_add(budget).ok_or(ReferralError::NumericOverflow)?;
+    match_offer.bump = ctx.bumps.match_offer;
+    Ok(())
+}
+
+/// Accounts required for cancelling a sponsor's match offer.
+#[derive(Accounts)]
+pub struct CancelMatchOffer<'info> {
+    #[account(mut)]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    /// PDA with seeds: ["match", referral_program.key(), sponsor.key()]
+    #[account(
+        mut,
+        close = sponsor,
+        seeds = [MATCH_OFFER_SEED, referral_program.key().as_ref(), sponsor.key().as_ref()],
+        bump = match_offer.bump,
+    )]
+    pub match_offer: Account<'info, MatchOffer>,
+
+    #[account(mut)]
+    pub sponsor: Signer<'info>,
+}
+
+/// Closes the signer's match offer, returning its unspent budget and rent; allowed at any time.
+///
+/// Matches already paid stay with the referrers.
+pub fn cancel_match_offer(ctx: Context<CancelMatchOffer>) -> Result<()> {
+    let referral_program = &mut ctx.accounts.referral_program;
+    referral_program.open_match_offers = referral_program.open_match_offers.saturating_sub(1);
+    let match_offer = &ctx.accounts.match_offer;
+    msg!("Cancelled match offer; {} lamports unspent, {} matched", match_offer.balance, match_offer.total_matched);
+    Ok(())
+}
+
+/// Pays `referrer_wallet` the match of `match_offer` on a referral reward of `reward`, returning the amount paid.
+///
+/// The offer must be a match offer of `program` and the wallet the owner of the `referrer` participant account.
+/// Once the budget is short of a full match, what is left of it is paid, reported through `events`.
+pub fn pay_reward_match(
+    match_offer: &mut Account<MatchOffer>,
+    program: Pubkey,
+    referrer: &Account<Participant>,
+    referrer_wallet: &AccountInfo,
+    reward: u64,
+    events: &mut JoinEvents,
+) -> Result<u64> {
+    require!(
+        match_offer.program == program && referrer_wallet.key() == referrer.owner,
+        ReferralError::InvalidMatchOffer
+    );
+    let amount = match_offer.next_match(reward);
+    if amount == 0 {
+        msg!("Match offer exhausted; no match paid");
+        return Ok(0);
+    }
+
+    match_offer.balance -= amount;
+    match_offer.total_matched = match_offer.total_matched.checked_add(amount).ok_or(ReferralError::NumericOverflow)?;
+    match_offer.referrals_matched =
+        match_offer.referrals_matched.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+    match_offer.sub_lamports(amount)?;
+    referrer_wallet.add_lamports(amount)?;
+
+    events.reward_matched(RewardMatched {
+        referral_program: program,
+        sponsor: match_offer.sponsor,
+        referrer: referrer.key(),
+        amount,
+        remaining: match_offer.balance,
+    });
+    Ok(amount)
+}
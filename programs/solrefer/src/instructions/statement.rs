@@ -0,0 +1,113 @@
+use crate::{
+    instructions::{claim_eligibility, ClaimAccounts, ClaimEligibility},
+    state::*,
+};
+use anchor_lang::prelude::*;
+
+/// A participant's reward accounting broken down by category, returned by `get_reward_statement`.
+///
+/// Every figure is in the program's raw reward units (lamports or base token units). The figures always satisfy
+/// `gross_credited - reductions - claimed = locked + claimable`, where the reductions are `cap_clamped`,
+/// `decay_reduction`, `clawed_back`, `early_redemption_fees`, `protocol_fees` and `transferred_out`.
+/// `boost_received` was paid straight to the wallet at join time and is reported alongside, outside that
+/// identity. Rewards transferred in are part of `gross_credited`.
+///
+/// Fields are only ever appended; a layout change gets a new `RewardStatementV*` type.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RewardStatementV1 {
+    /// Every reward earned, including the part withheld by clamping
+    pub gross_credited: u64,
+    /// Withheld because it exceeded the reward cap or the vault's uncommitted funds
+    pub cap_clamped: u64,
+    /// Removed by reward decay; this program version does not decay rewards, so always 0
+    pub decay_reduction: u64,
+    /// Referee boosts paid from referrers' boost escrows
+    pub boost_received: u64,
+    /// Removed from the pending rewards by clawbacks
+    pub clawed_back: u64,
+    /// Forfeited as fees on early redemptions
+    pub early_redemption_fees: u64,
+    /// Protocol fees charged on rewards; this program version charges none, so always 0
+    pub protocol_fees: u64,
+    /// Paid out by claims so far
+    pub claimed: u64,
+    /// Pending but still within the program's locked period
+    pub locked: u64,
+    /// Pending and past the locked period
+    pub claimable: u64,
+    /// Pending rewards transferred to other participants
+    pub transferred_out: u64,
+}
+
+impl RewardStatementV1 {
+    /// Returns the total of every reduction applied to the gross rewards.
+    pub fn reductions(&self) -> u128 {
+        u128::from(self.cap_clamped)
+            + u128::from(self.decay_reduction)
+            + u128::from(self.clawed_back)
+            + u128::from(self.early_redemption_fees)
+            + u128::from(self.protocol_fees)
+            + u128::from(self.transferred_out)
+    }
+
+    /// Returns true if `gross_credited - reductions - claimed = locked + claimable`.
+    pub fn is_balanced(&self) -> bool {
+        u128::from(self.gross_credited)
+            == self.reductions() + u128::from(self.claimed) + u128::from(self.locked) + u128::from(self.claimable)
+    }
+}
+
+/// Builds the reward statement of a participant at `now`.
+///
+/// The pending rewards are locked while the claim gates report `REWARDS_LOCKED`, so the split always agrees with
+/// `check_claim`; other gates such as an inactive program do not make them locked.
+pub fn reward_statement(program: &ReferralProgram, participant: &Participant, now: i64) -> RewardStatementV1 {
+    let locked = claim_eligibility(program, participant, now, ClaimAccounts::default())
+        .is_blocked_by(ClaimEligibility::REWARDS_LOCKED);
+    let (locked, claimable) = if locked { (participant.pending_rewards, 0) } else { (0, participant.pending_rewards) };
+
+    let statement = RewardStatementV1 {
+        gross_credited: participant.gross_credited,
+        cap_clamped: participant.cap_clamped,
+        decay_reduction: 0,
+        boost_received: participant.boost_received,
+        clawed_back: participant.clawed_back,
+        early_redemption_fees: participant.early_redemption_fees,
+        protocol_fees: 0,
+        claimed: participant.total_rewards,
+        locked,
+        claimable,
+        transferred_out: participant.transferred_out,
+    };
+    debug_assert!(statement.is_balanced(), "unbalanced reward statement: {statement:?}");
+    statement
+}
+
+/// Accounts required for the read-only `get_reward_statement` instruction.
+#[derive(Accounts)]
+pub struct GetRewardStatement<'info> {
+    pub referral_program: Account<'info, ReferralProgram>,
+    #[account(
+        seeds = [b"eligibility_criteria", referral_program.key().as_ref()],
+        bump
+    )]
+    pub eligibility_criteria: Account<'info, EligibilityCriteria>,
+    #[account(
+        constraint = participant.program == referral_program.key()
+    )]
+    pub participant: Account<'info, Participant>,
+}
+
+/// Returns the reward statement of a participant without mutating any state.
+///
+/// A rotated account's history moved to the account it was rotated to, whose statement covers it.
+///
+/// # Errors
+/// * `ParticipantRotated` - If the participant account was rotated to a new owner
+/// * `ParticipantLeft` - If the participant left the program
+pub fn get_reward_statement(ctx: Context<GetRewardStatement>) -> Result<RewardStatementV1> {
+    let participant = &ctx.accounts.participant;
+    participant.require_current()?;
+    let now = Clock::get()?.unix_timestamp;
+    Ok(reward_statement(&ctx.accounts.referral_program, participant, now))
+}
@@ -0,0 +1,58 @@
+use crate::{error::ReferralError, state::*};
+use anchor_lang::prelude::*;
+
+/// Accounts required for replacing a program's terms.
+#[derive(Accounts)]
+pub struct UpdateTerms<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+    pub authority: Signer<'info>,
+}
+
+/// Replaces the hash of the program's terms and bumps its version.
+///
+/// Participants keep the version they accepted until they call `reaccept_terms`; new joins must present
+/// the new hash.
+pub fn update_terms(ctx: Context<UpdateTerms>, terms_hash: [u8; 32]) -> Result<()> {
+    let referral_program = &mut ctx.accounts.referral_program;
+    let now = Clock::get()?.unix_timestamp;
+    referral_program.record_authority_action(now)?;
+    referral_program.require_settings_unlocked(now)?;
+    referral_program.terms_hash = terms_hash;
+    referral_program.terms_version =
+        referral_program.terms_version.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+    msg!("Terms of referral program {} updated to version {}", referral_program.key(), referral_program.terms_version);
+    Ok(())
+}
+
+/// Accounts required for a participant to accept a program's current terms.
+#[derive(Accounts)]
+pub struct ReacceptTerms<'info> {
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        mut,
+        seeds = [b"participant", referral_program.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub participant: Account<'info, Participant>,
+
+    pub user: Signer<'info>,
+}
+
+/// Records that the participant accepted the program's current terms.
+///
+/// # Errors
+/// * `TermsMismatch` - If `accepted_terms_hash` is not the program's current terms hash
+pub fn reaccept_terms(ctx: Context<ReacceptTerms>, accepted_terms_hash: [u8; 32]) -> Result<()> {
+    let referral_program = &ctx.accounts.referral_program;
+    referral_program.require_current_terms(&accepted_terms_hash)?;
+
+    let participant = &mut ctx.accounts.participant;
+    participant.accepted_terms_hash = accepted_terms_hash;
+    participant.accepted_terms_version = referral_program.terms_version;
+    Ok(())
+}
@@ -0,0 +1,268 @@
+//! Instructions for driving the program into states the regular instructions refuse to produce in tests.
+//!
+//! The module is only compiled with the `test-utils` feature, which deployed builds never enable. The
+//! `#[program]` macro would dispatch these instructions whatever their `cfg` attributes, so they are routed
+//! through the program's fallback by `dispatch` instead, with `instruction` and `accounts` standing in for the
+//! client modules the macro generates for the regular instructions.
+use crate::{
+    error::ReferralError,
+    instructions::{ClaimGuard, ClaimRewards},
+    state::*,
+};
+use anchor_lang::{prelude::*, AccountsExit, Bumps, Discriminator};
+use std::collections::BTreeSet;
+
+/// Instruction data of the test-only instructions, under the discriminators `#[program]` would give them.
+pub mod instruction {
+    use super::CorruptedField;
+    use anchor_lang::{prelude::*, Discriminator, InstructionData};
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct CorruptTotalAvailable {
+        pub total_available: u64,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct CorruptProgramField {
+        pub field: CorruptedField,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct BackdateClosure {
+        pub closure_requested_at: i64,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct OverrideProgramTiming {
+        pub locked_period: i64,
+        pub program_end_time: Option<i64>,
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct MisroutedClaim {}
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct SkewReferralCount {
+        pub total_referrals: u64,
+    }
+
+    macro_rules! discriminator {
+        ($($name:ident = $discriminator:expr;)*) => {
+            $(
+                impl Discriminator for $name {
+                    const DISCRIMINATOR: [u8; 8] = $discriminator;
+                }
+
+                impl InstructionData for $name {}
+            )*
+        };
+    }
+
+    // sha256("global:<snake_case_name>")[..8]
+    discriminator! {
+        CorruptTotalAvailable = [165, 192, 35, 46, 79, 26, 222, 146];
+        CorruptProgramField = [86, 36, 167, 232, 96, 212, 107, 182];
+        BackdateClosure = [213, 238, 159, 146, 229, 114, 40, 16];
+        OverrideProgramTiming = [227, 14, 23, 243, 34, 57, 106, 178];
+        MisroutedClaim = [178, 219, 29, 107, 5, 226, 47, 161];
+        SkewReferralCount = [191, 47, 241, 244, 201, 211, 99, 73];
+    }
+}
+
+/// Client account structs of the test-only instructions; `misrouted_claim` takes `crate::accounts::ClaimRewards`.
+pub mod accounts {
+    pub use super::{
+        __client_accounts_backdate_closure::*, __client_accounts_corrupt_total_available::*,
+        __client_accounts_override_program_timing::*, __client_accounts_skew_referral_count::*,
+    };
+}
+
+/// Routes a test-only instruction to its handler.
+///
+/// # Errors
+/// * `InstructionFallbackNotFound` - If the discriminator matches no test-only instruction
+pub fn dispatch<'info>(program_id: &Pubkey, accounts: &'info [AccountInfo<'info>], data: &[u8]) -> Result<()> {
+    let discriminator: [u8; 8] = data
+        .get(..8)
+        .and_then(|discriminator| discriminator.try_into().ok())
+        .ok_or(anchor_lang::error::ErrorCode::InstructionMissing)?;
+    let args = &data[8..];
+    match discriminator {
+        instruction::CorruptTotalAvailable::DISCRIMINATOR => {
+            process(program_id, accounts, args, |ctx, ix: instruction::CorruptTotalAvailable| {
+                corrupt_total_available(ctx, ix.total_available)
+            })
+        }
+        instruction::CorruptProgramField::DISCRIMINATOR => {
+            process(program_id, accounts, args, |ctx, ix: instruction::CorruptProgramField| {
+                corrupt_program_field(ctx, ix.field)
+            })
+        }
+        instruction::BackdateClosure::DISCRIMINATOR => {
+            process(program_id, accounts, args, |ctx, ix: instruction::BackdateClosure| {
+                backdate_closure(ctx, ix.closure_requested_at)
+            })
+        }
+        instruction::OverrideProgramTiming::DISCRIMINATOR => {
+            process(program_id, accounts, args, |ctx, ix: instruction::OverrideProgramTiming| {
+                override_program_timing(ctx, ix.locked_period, ix.program_end_time)
+            })
+        }
+        instruction::MisroutedClaim::DISCRIMINATOR => {
+            process(program_id, accounts, args, |ctx, _: instruction::MisroutedClaim| misrouted_claim(ctx))
+        }
+        instruction::SkewReferralCount::DISCRIMINATOR => {
+            process(program_id, accounts, args, |ctx, ix: instruction::SkewReferralCount| {
+                skew_referral_count(ctx, ix.total_referrals)
+            })
+        }
+        _ => Err(anchor_lang::error::ErrorCode::InstructionFallbackNotFound.into()),
+    }
+}
+
+/// Deserializes the instruction and its accounts, runs the handler and persists the accounts, as the handlers
+/// `#[program]` generates do.
+fn process<'info, T, I>(
+    program_id: &Pubkey,
+    accounts: &'info [AccountInfo<'info>],
+    args: &[u8],
+    handler: impl FnOnce(Context<'_, '_, 'info, 'info, T>, I) -> Result<()>,
+) -> Result<()>
+where
+    T: Accounts<'info, T::Bumps> + Bumps + AccountsExit<'info>,
+    T::Bumps: Default,
+    I: AnchorDeserialize,
+{
+    let ix = I::deserialize(&mut &args[..]).map_err(|_| anchor_lang::error::ErrorCode::InstructionDidNotDeserialize)?;
+    let mut bumps = T::Bumps::default();
+    let mut reallocs = BTreeSet::new();
+    let mut remaining_accounts = accounts;
+    let mut accounts = T::try_accounts(program_id, &mut remaining_accounts, args, &mut bumps, &mut reallocs)?;
+    handler(Context::new(program_id, &mut accounts, remaining_accounts, bumps), ix)?;
+    accounts.exit(program_id)
+}
+
+/// Accounts required for overwriting a program's accounting.
+#[derive(Accounts)]
+pub struct CorruptTotalAvailable<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+    pub authority: Signer<'info>,
+}
+
+/// Overwrites `total_available` without moving any funds.
+pub fn corrupt_total_available(ctx: Context<CorruptTotalAvailable>, total_available: u64) -> Result<()> {
+    ctx.accounts.referral_program.total_available = total_available;
+    Ok(())
+}
+
+/// A program field `corrupt_program_field` overwrites, and the value it writes.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CorruptedField {
+    TotalRewardsDistributed(u64),
+    TotalReferralsCredited(u64),
+    /// The program's cached end time only; the eligibility criteria keep theirs
+    ProgramEndTime(Option<i64>),
+}
+
+/// Overwrites one field of the program account, leaving everything derived from it alone.
+pub fn corrupt_program_field(ctx: Context<CorruptTotalAvailable>, field: CorruptedField) -> Result<()> {
+    let referral_program = &mut ctx.accounts.referral_program;
+    match field {
+        CorruptedField::TotalRewardsDistributed(value) => referral_program.total_rewards_distributed = value,
+        CorruptedField::TotalReferralsCredited(value) => referral_program.total_referrals_credited = value,
+        CorruptedField::ProgramEndTime(value) => referral_program.program_end_time = value,
+    }
+    Ok(())
+}
+
+/// Accounts required for backdating a closure request.
+#[derive(Accounts)]
+pub struct BackdateClosure<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+    pub authority: Signer<'info>,
+}
+
+/// Overwrites `closure_requested_at` so a pending closure can be finalized without waiting out the grace period.
+pub fn backdate_closure(ctx: Context<BackdateClosure>, closure_requested_at: i64) -> Result<()> {
+    let referral_program = &mut ctx.accounts.referral_program;
+    require!(referral_program.is_closing(), ReferralError::NoClosurePending);
+    referral_program.closure_requested_at = closure_requested_at;
+    Ok(())
+}
+
+/// Accounts required for overriding a program's timing.
+#[derive(Accounts)]
+pub struct OverrideProgramTiming<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        mut,
+        seeds = [b"eligibility_criteria", referral_program.key().as_ref()],
+        bump
+    )]
+    pub eligibility_criteria: Account<'info, EligibilityCriteria>,
+
+    pub authority: Signer<'info>,
+}
+
+/// Overwrites the locked period and end time without the bounds `update_program_settings` enforces.
+pub fn override_program_timing(
+    ctx: Context<OverrideProgramTiming>,
+    locked_period: i64,
+    program_end_time: Option<i64>,
+) -> Result<()> {
+    let referral_program = &mut ctx.accounts.referral_program;
+    referral_program.locked_period = locked_period;
+    referral_program.program_end_time = program_end_time;
+    ctx.accounts.eligibility_criteria.program_end_time = program_end_time;
+    Ok(())
+}
+
+/// Pays the participant's pending rewards out of its own participant account instead of the vault, under the
+/// same `ClaimGuard` the real claims use, which must reject the transfer.
+pub fn misrouted_claim(ctx: Context<ClaimRewards>) -> Result<()> {
+    let guard = ClaimGuard::new(
+        ctx.accounts.participant.to_account_info(),
+        ctx.accounts.vault.to_account_info(),
+        ctx.accounts.user.to_account_info(),
+    )?;
+    let amount = ctx.accounts.participant.pending_rewards;
+    ctx.accounts.participant.sub_lamports(amount)?;
+    ctx.accounts.user.add_lamports(amount)?;
+    guard.finish(amount)
+}
+
+/// Accounts required for overwriting a participant's referral count.
+#[derive(Accounts)]
+pub struct SkewReferralCount<'info> {
+    #[account(
+        has_one = authority @ ReferralError::InvalidAuthority,
+    )]
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        mut,
+        constraint = participant.program == referral_program.key() @ ReferralError::InvalidReferrer
+    )]
+    pub participant: Account<'info, Participant>,
+
+    pub authority: Signer<'info>,
+}
+
+/// Overwrites `total_referrals` without touching the referee receipts, as a drifted counter would.
+pub fn skew_referral_count(ctx: Context<SkewReferralCount>, total_referrals: u64) -> Result<()> {
+    ctx.accounts.participant.total_referrals = total_referrals;
+    Ok(())
+}
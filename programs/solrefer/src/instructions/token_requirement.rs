@@ -0,0 +1,23 @@
+//! Token holdings a program can require of each side of a referral.
+//!
+//! The referrer and referee requirements are independent: a program can ask referrers for a stake in its token,
+//! referees for proof of real interest in it, both or neither. Holdings are shown with a token account of the
+//! requirement's mint owned by the wallet, and only its balance at the time of the check counts.
+use crate::state::referral_program::TokenRequirement;
+use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
+
+/// Returns true if there is no `requirement`, or `token_account` is a token account of `holder` holding at least
+/// `min_amount` of the required mint.
+pub fn meets_token_requirement(
+    requirement: Option<&TokenRequirement>,
+    token_account: Option<&Account<TokenAccount>>,
+    holder: &Pubkey,
+) -> bool {
+    let Some(requirement) = requirement else {
+        return true;
+    };
+    token_account.is_some_and(|account| {
+        account.mint == requirement.mint && account.owner == *holder && account.amount >= requirement.min_amount
+    })
+}
@@ -0,0 +1,54 @@
+//! Trailing commissions: a referrer's share of what its referees go on to earn.
+//!
+//! When a participant that joined through a referral is credited a referee bonus, a purchase reward or a milestone
+//! bonus, its referrer is credited `trailing_commission_bps` of it on top, from the same uncommitted funds. A
+//! commission never earns a commission of its own, so it stops one level up the referral tree.
+use crate::{error::ReferralError, state::*};
+use anchor_lang::prelude::*;
+
+/// Returns the trailing commission due on a credit of `amount` to `earner`, clamped to the program's uncommitted
+/// funds; zero when the earner joined without a referral.
+pub fn trailing_commission_due(
+    program: &ReferralProgram,
+    criteria: &EligibilityCriteria,
+    earner: &Participant,
+    amount: u64,
+) -> Result<u64> {
+    if earner.referrer.is_none() {
+        return Ok(0);
+    }
+    Ok(criteria.trailing_commission(amount)?.min(program.headroom()))
+}
+
+/// Credits `commission` to `upline`, the referrer of the earner whose `referrer` field is `earner_referrer`.
+///
+/// Integrators cannot skip a commission by leaving the referrer's account out: a commission that is due fails the
+/// credit without it. A referrer that was rotated to a new wallet or left the program can no longer claim, so it is
+/// credited nothing.
+///
+/// # Errors
+/// * `ReferrerAccountRequired` - If `commission` is non-zero and `upline` is missing or not the earner's referrer
+pub fn pay_trailing_commission(
+    program: &mut ReferralProgram,
+    earner_referrer: Option<Pubkey>,
+    upline: Option<&mut Account<Participant>>,
+    commission: u64,
+) -> Result<()> {
+    if commission == 0 {
+        return Ok(());
+    }
+    let upline =
+        upline.filter(|upline| earner_referrer == Some(upline.key())).ok_or(ReferralError::ReferrerAccountRequired)?;
+    if upline.has_left() {
+        msg!("Referrer {} left the program; no trailing commission credited", upline.key());
+        return Ok(());
+    }
+    if upline.rotated_to.is_some() {
+        msg!("Referrer {} was rotated; no trailing commission credited", upline.key());
+        return Ok(());
+    }
+    upline.credit_reward(commission)?;
+    program.total_committed = program.total_committed.checked_add(commission).ok_or(ReferralError::NumericOverflow)?;
+    msg!("Credited a trailing commission of {} to {}", commission, upline.key());
+    Ok(())
+}
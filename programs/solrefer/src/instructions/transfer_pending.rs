@@ -0,0 +1,87 @@
+use crate::{error::ReferralError, events::PendingRewardsTransferred, state::*, validation::require_nonzero_amount};
+use anchor_lang::prelude::*;
+
+/// Accounts required for transferring pending rewards to another participant.
+#[derive(Accounts)]
+pub struct TransferPending<'info> {
+    pub referral_program: Account<'info, ReferralProgram>,
+
+    #[account(
+        seeds = [b"eligibility_criteria", referral_program.key().as_ref()],
+        bump
+    )]
+    pub eligibility_criteria: Account<'info, EligibilityCriteria>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"participant",
+            referral_program.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump
+    )]
+    pub source: Account<'info, Participant>,
+
+    #[account(
+        mut,
+        constraint = destination.program == referral_program.key() @ ReferralError::InvalidTransferDestination,
+        constraint = destination.key() != source.key() @ ReferralError::InvalidTransferDestination,
+    )]
+    pub destination: Account<'info, Participant>,
+
+    pub user: Signer<'info>,
+}
+
+/// Moves `amount` of the signer's pending rewards to another participant of the same program.
+///
+/// The transferred rewards stay locked until they would have unlocked at the source: the destination's lock is
+/// extended to at least that time. All of a participant's pending rewards share one lock, so the destination's
+/// own rewards wait for it too, and transferred rewards wait for the destination's own lock when that ends
+/// later. The transfer counts toward the destination's earnings under the program's reward cap.
+///
+/// # Arguments
+/// * `ctx` - The context for the TransferPending instruction
+/// * `amount` - Pending rewards to transfer, in raw units
+///
+/// # Errors
+/// * `InvalidTransferAmount` - If the amount is zero or exceeds the signer's pending rewards
+/// * `TransfersDisabled` - If the program does not allow transfers
+/// * `ParticipantRotated` - If the signer's participant account was rotated to a new owner
+/// * `ParticipantLeft` - If the signer's participant left the program
+/// * `InvalidTransferDestination` - If the destination is the source, belongs to another program, was rotated
+///   or left the program
+/// * `RewardCapExceeded` - If the destination's earnings would exceed the program's reward cap
+pub fn transfer_pending(ctx: Context<TransferPending>, amount: u64) -> Result<()> {
+    require_nonzero_amount(amount, ReferralError::InvalidTransferAmount)?;
+    let criteria = &ctx.accounts.eligibility_criteria;
+    require!(criteria.transfers_enabled, ReferralError::TransfersDisabled);
+
+    let source = &mut ctx.accounts.source;
+    let destination = &mut ctx.accounts.destination;
+    source.require_current()?;
+    require!(destination.rotated_to.is_none() && !destination.has_left(), ReferralError::InvalidTransferDestination);
+    require!(amount <= source.pending_rewards, ReferralError::InvalidTransferAmount);
+
+    let earned = destination
+        .total_rewards
+        .checked_add(destination.pending_rewards)
+        .and_then(|earned| earned.checked_add(amount))
+        .ok_or(ReferralError::NumericOverflow)?;
+    require!(criteria.max_reward_cap == 0 || earned <= criteria.max_reward_cap, ReferralError::RewardCapExceeded);
+
+    let unlocks_at = source.unlocks_at(ctx.accounts.referral_program.locked_period);
+    source.pending_rewards -= amount;
+    source.transferred_out = source.transferred_out.checked_add(amount).ok_or(ReferralError::NumericOverflow)?;
+    destination.credit_reward(amount)?;
+    destination.locked_until = destination.locked_until.max(unlocks_at);
+
+    emit!(PendingRewardsTransferred {
+        referral_program: ctx.accounts.referral_program.key(),
+        from: source.key(),
+        to: destination.key(),
+        amount,
+        unlocks_at,
+    });
+    Ok(())
+}
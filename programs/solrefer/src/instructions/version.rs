@@ -0,0 +1,92 @@
+use crate::{constants::SUPPORTED_FEATURES, state::*};
+use anchor_lang::prelude::*;
+
+/// The account layout version of every state account, as defined by each account's `LAYOUT_VERSION`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AccountLayoutVersions {
+    pub referral_program: u8,
+    pub eligibility_criteria: u8,
+    pub participant: u8,
+    pub referee_receipt: u8,
+    pub invite: u8,
+    pub event_queue: u8,
+    pub fee_config: u8,
+    pub authority_meta: u8,
+    pub contest: u8,
+    pub boost_escrow: u8,
+    pub network_config: u8,
+    pub withdrawal_request: u8,
+    pub final_report: u8,
+}
+
+/// What this build of the program is, returned by `get_program_version`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProgramVersion {
+    /// The crate's semver, parsed from its manifest at compile time
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+    pub layouts: AccountLayoutVersions,
+    /// Bitmask of the `FEATURE_*` constants this build supports
+    pub features: u64,
+}
+
+/// The version and capabilities of this build.
+pub const PROGRAM_VERSION: ProgramVersion = ProgramVersion {
+    major: semver_part(env!("CARGO_PKG_VERSION"), 0),
+    minor: semver_part(env!("CARGO_PKG_VERSION"), 1),
+    patch: semver_part(env!("CARGO_PKG_VERSION"), 2),
+    layouts: AccountLayoutVersions {
+        referral_program: ReferralProgram::LAYOUT_VERSION,
+        eligibility_criteria: EligibilityCriteria::LAYOUT_VERSION,
+        participant: Participant::LAYOUT_VERSION,
+        referee_receipt: RefereeReceipt::LAYOUT_VERSION,
+        invite: Invite::LAYOUT_VERSION,
+        event_queue: EventQueue::LAYOUT_VERSION,
+        fee_config: FeeConfig::LAYOUT_VERSION,
+        authority_meta: AuthorityMeta::LAYOUT_VERSION,
+        contest: Contest::LAYOUT_VERSION,
+        boost_escrow: BoostEscrow::LAYOUT_VERSION,
+        network_config: NetworkConfig::LAYOUT_VERSION,
+        withdrawal_request: WithdrawalRequest::LAYOUT_VERSION,
+        final_report: FinalReport::LAYOUT_VERSION,
+    },
+    features: SUPPORTED_FEATURES,
+};
+
+/// Returns the `index`th dot-separated number of a `major.minor.patch[-pre][+build]` version.
+///
+/// Evaluated at compile time, so a malformed version fails the build instead of reaching the chain.
+pub const fn semver_part(version: &str, index: usize) -> u16 {
+    let bytes = version.as_bytes();
+    let mut part = 0;
+    let mut value: u16 = 0;
+    let mut digits = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let byte = bytes[i];
+        if byte == b'.' || byte == b'-' || byte == b'+' {
+            if part == index {
+                break;
+            }
+            assert!(byte == b'.', "version has fewer parts than requested");
+            part += 1;
+        } else if part == index {
+            assert!(byte.is_ascii_digit(), "version part is not a number");
+            value = value * 10 + (byte - b'0') as u16;
+            digits += 1;
+        }
+        i += 1;
+    }
+    assert!(part == index && digits > 0, "version has fewer parts than requested");
+    value
+}
+
+/// Accounts required for the read-only `get_program_version` instruction; none beyond the program itself.
+#[derive(Accounts)]
+pub struct GetProgramVersion {}
+
+/// Returns the program's version, account layout versions and supported features.
+pub fn get_program_version(_ctx: Context<GetProgramVersion>) -> Result<ProgramVersion> {
+    Ok(PROGRAM_VERSION)
+}
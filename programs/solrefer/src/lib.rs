@@ -1,7 +1,11 @@
 pub mod constants;
 pub mod error;
+pub mod events;
 pub mod instructions;
+pub mod linkcodec;
+pub mod space;
 pub mod state;
+pub mod validation;
 
 use anchor_lang::prelude::*;
 use instructions::*;
@@ -17,25 +21,209 @@ pub mod solrefer {
     /// This function sets up a new referral program with the provided configuration options.
     /// The referral program allows users to earn rewards for referring others to the program.
     /// The program can have various tiers and thresholds for earning rewards, as well as
-    /// a fixed reward amount, locked period, early redemption fee, mint fee, and more.
+    /// a fixed reward amount, locked period, early redemption fee, and more.
     ///
     /// # Arguments
     ///
     /// * `ctx` - The context for the create referral program instruction.
+    /// * `program_index` - Seeds the program account after the authority's key, so one authority can run several
+    ///   programs; index 0 adds no seed, keeping the address programs had before they were indexed. The same
+    ///   authority cannot reuse an index while its program exists, nor once it was closed and left its final
+    ///   report.
     /// * `token_mint` - The optional token mint for the referral program rewards.
-    /// * `fixed_reward_amount` - The fixed amount of rewards for each referral.
-    /// * `locked_period` - The period of time the rewards are locked before they can be redeemed.
-    /// * `max_reward_cap` - The maximum total reward amount that can be earned.
-    /// * `revenue_share_percent` - The percentage of revenue shared with referrers.
-    /// * `program_end_time` - The optional end time for the referral program.
+    /// * `fixed_reward_amount` - The fixed amount of rewards for each referral, in `reward_denomination`.
+    /// * `reward_denomination` - 0 for raw units, 1 for US cents converted with the token mint's decimals.
+    /// * `start_inactive` - If true, the program is created inactive and goes live with `activate_program`.
+    /// * `terms_hash` - Hash of the off-chain terms of service participants accept when joining.
+    /// * `guardian` - An optional guardian key, which can freeze the program and cancel withdrawals queued
+    ///   above its threshold; it cannot be changed later.
+    /// * `withdrawal_destinations` - Addresses besides the authority that withdrawals may pay out to; all-default
+    ///   leaves withdrawals unrestricted. Only `set_withdrawal_destinations` can change it later.
+    /// * `program_end_time` - The optional end time for the referral program; `None` makes it open-ended, so
+    ///   it never expires until `extend_program` gives it an end time.
+    /// * `settings_locked_until` - An optional time, at most `MAX_SETTINGS_LOCK` ahead and no later than the end
+    ///   time, before which the authority's settings, criteria, terms, withdrawal and clawback instructions fail
+    ///   with `SettingsTimelocked`; deposits and the guardian's actions stay open. It can never be moved.
+    /// * `settings` - Optional settings the program starts with, such as its locked period, early redemption fee,
+    ///   reward tiers and eligibility criteria, validated as by `update_program_settings`; they must repeat
+    ///   `fixed_reward_amount` and `program_end_time`. Without them the program starts with none of these set.
+    ///
+    /// A rejected parameter is reported in a `ValidationFailure` event naming the `ProgramField` and why.
+    ///
+    /// Once the protocol fee config is initialized, the authority pays its creation fee into the treasury
+    /// unless exempt, and may not hold more live programs than the config allows.
+    ///
+    /// # Errors
+    /// * `FinalReportExists` - If a program closed earlier at the same address left its final report
+    /// * `CreationFeeRequired` - If a fee is owed and the fee config treasury was not provided
+    /// * `TooManyPrograms` - If the authority already holds the maximum number of programs
+    /// * `InvalidGuardian` - If the guardian is the default key or the authority itself
+    /// * `InvalidSettingsLock` - If the settings lock is not in the future, outlasts the program or
+    ///   `MAX_SETTINGS_LOCK`
+    /// * `CreationSettingsMismatch` - If the settings disagree with `fixed_reward_amount` or `program_end_time`
+    /// * Every error of `update_program_settings`' validation, for the settings
     #[allow(clippy::too_many_arguments)]
     pub fn create_referral_program(
         ctx: Context<CreateReferralProgram>,
+        program_index: u64,
         token_mint: Option<Pubkey>,
         fixed_reward_amount: u64,
-        program_end_time: i64,
+        program_end_time: Option<i64>,
+        reward_denomination: u8,
+        start_inactive: bool,
+        terms_hash: [u8; 32],
+        guardian: Option<GuardianConfig>,
+        withdrawal_destinations: [Pubkey; constants::MAX_WITHDRAWAL_DESTINATIONS],
+        settings_locked_until: Option<i64>,
+        settings: Option<ProgramSettings>,
+    ) -> Result<()> {
+        instructions::referral_program::create_referral_program(
+            ctx,
+            program_index,
+            token_mint,
+            fixed_reward_amount,
+            program_end_time,
+            reward_denomination,
+            start_inactive,
+            terms_hash,
+            guardian,
+            withdrawal_destinations,
+            settings_locked_until,
+            settings,
+        )
+    }
+
+    /// Initializes the protocol-wide fee config applied to program creation.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - fee_config: The fee config PDA to create
+    ///   - program: This program
+    ///   - program_data: This program's data account
+    ///   - admin: The program's upgrade authority (signer), who becomes the fee config admin
+    ///   - system_program: The system program
+    /// * `settings` - The treasury, creation fee, per-authority program limit and exempt authorities
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program's upgrade authority
+    pub fn initialize_fee_config(ctx: Context<InitializeFeeConfig>, settings: FeeConfigSettings) -> Result<()> {
+        instructions::fee_config::initialize_fee_config(ctx, settings)
+    }
+
+    /// Replaces the protocol fee config settings.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - fee_config: The fee config PDA
+    ///   - admin: The fee config admin (signer)
+    /// * `settings` - The new settings
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the fee config admin
+    pub fn update_fee_config(ctx: Context<UpdateFeeConfig>, settings: FeeConfigSettings) -> Result<()> {
+        instructions::fee_config::update_fee_config(ctx, settings)
+    }
+
+    /// Initializes the cluster-wide network config overriding the validation limits.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - network_config: The network config PDA to create
+    ///   - program: This program
+    ///   - program_data: This program's data account
+    ///   - admin: The program's upgrade authority (signer), who becomes the network config admin
+    ///   - system_program: The system program
+    /// * `limits` - The locked period, fee, duration and reward limits programs are validated against
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program's upgrade authority
+    /// * `InvalidNetworkConfig` - If a limit lies outside its absolute bound
+    pub fn initialize_network_config(
+        ctx: Context<InitializeNetworkConfig>,
+        limits: state::NetworkLimits,
     ) -> Result<()> {
-        instructions::referral_program::create_referral_program(ctx, token_mint, fixed_reward_amount, program_end_time)
+        instructions::network_config::initialize_network_config(ctx, limits)
+    }
+
+    /// Replaces the network config's limits.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - network_config: The network config PDA
+    ///   - admin: The network config admin (signer)
+    /// * `limits` - The new limits
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the network config admin
+    /// * `InvalidNetworkConfig` - If a limit lies outside its absolute bound
+    pub fn update_network_config(ctx: Context<UpdateNetworkConfig>, limits: state::NetworkLimits) -> Result<()> {
+        instructions::network_config::update_network_config(ctx, limits)
+    }
+
+    /// Activates a referral program that was created inactive, optionally funding it first.
+    ///
+    /// The deposit and the activation happen in one transaction, so participants can never join an
+    /// unfunded program. Activation requires the criteria to be configured with `update_program_settings`
+    /// (programs created active have them from creation) with an end time in the future, if any, and, for
+    /// token programs, the token vault to be initialized.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - eligibility_criteria: The program's eligibility criteria
+    ///   - vault: The SOL vault PDA
+    ///   - token_vault: The token vault PDA (token programs only)
+    ///   - depositor_token_account: The authority's token account (token deposits only)
+    ///   - authority: The program authority (signer)
+    ///   - system_program: The system program
+    ///   - token_program: The token program (token deposits only)
+    /// * `initial_deposit` - Amount to deposit before activating, in lamports or token units; 0 for none
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `CriteriaNotSet` - If the program's criteria were never configured
+    /// * `TokenVaultNotInitialized` - If a token program's vault has not been initialized
+    /// * `ProgramSetupIncomplete` - If the program's end time has passed
+    /// * `InvalidTokenAccounts` - If a token deposit is requested without valid token accounts
+    /// * `ProgramClosing` - If the program is pending closure
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    pub fn activate_program(ctx: Context<ActivateProgram>, initial_deposit: u64) -> Result<()> {
+        instructions::referral_program::activate_program(ctx, initial_deposit)
+    }
+
+    /// Pauses or unpauses a referral program that has gone live.
+    ///
+    /// While paused, joins, late attributions and deposits fail with `ProgramInactive`; participants can still
+    /// claim the rewards they have earned.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - eligibility_criteria: The program's eligibility criteria
+    ///   - authority: The program authority (signer)
+    /// * `active` - False to pause the program, true to unpause it
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `ProgramSetupIncomplete` - If unpausing a program that was never activated; use `activate_program`
+    /// * `ProgramClosing` - If unpausing a program pending closure
+    /// * `TokenVaultClosed` - If unpausing a token program whose vault was closed
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    pub fn set_program_status(ctx: Context<SetProgramStatus>, active: bool) -> Result<()> {
+        instructions::referral_program::set_program_status(ctx, active)
+    }
+
+    /// Reports how far a program's setup has come.
+    ///
+    /// This read-only instruction returns a `SetupStatus` in the transaction return data: the bitmask of
+    /// completed `ReferralProgram::SETUP_*` steps (create, criteria, vault, funding, activation) and the next
+    /// step to perform, or 0 once setup is complete. Clients simulate it to resume an interrupted setup.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    pub fn get_setup_state(ctx: Context<GetSetupState>) -> Result<SetupStatus> {
+        instructions::setup::get_setup_state(ctx)
     }
 
     /// Initializes the token vault for a token-based referral program.
@@ -43,21 +231,23 @@ pub mod solrefer {
     /// This instruction creates and initializes the token vault account that will hold
     /// deposited tokens for the referral program. This must be called after creating
     /// a token-based referral program and before any token deposits can be made.
+    /// The program records the vault as initialized; the vault can only be created once. A program created
+    /// inactive initializes its vault before `activate_program`.
     ///
     /// # Arguments
     /// * `ctx` - The context containing:
-    ///   - referral_program: The program account (must be active)
+    ///   - referral_program: The program account
     ///   - token_vault: The token vault PDA to initialize
     ///   - token_mint: The token mint (must match program config)
     ///   - authority: The program authority (signer)
     ///   - system_program: The system program
     ///   - token_program: The token program
-    ///   - rent: The rent sysvar
     ///
     /// # Errors
-    /// * `ProgramInactive` - If the referral program is not active
     /// * `InvalidAuthority` - If the signer is not the program authority
     /// * `InvalidTokenMint` - If the referral program is not configured for tokens
+    /// * `TokenVaultClosed` - If the program's vault was closed with `close_token_vault`
+    /// * `ProgramAbandoned` - If the program was declared abandoned
     pub fn initialize_token_vault(ctx: Context<InitializeTokenVault>) -> Result<()> {
         instructions::referral_program::initialize_token_vault(ctx)
     }
@@ -66,12 +256,15 @@ pub mod solrefer {
     ///
     /// This instruction allows the program authority to deposit SOL that will be used
     /// to pay out referral rewards. The program must be configured for SOL deposits.
+    /// The program's `reserve_bps` share of the amount is held back in its insurance
+    /// reserve until `release_reserve`.
     ///
     /// # Arguments
     /// * `ctx` - The deposit context containing:
     ///   - referral_program: The program account (must be active)
     ///   - vault: The SOL vault PDA
     ///   - authority: The program authority (signer)
+    ///   - event_queue: The program's event queue (optional; appended to when supplied)
     ///   - system_program: The system program
     /// * `amount` - Amount to deposit in lamports
     ///
@@ -80,6 +273,8 @@ pub mod solrefer {
     /// * `InvalidAuthority` - If the signer is not the program authority
     /// * `InsufficientDeposit` - If the deposit amount is zero
     /// * `SolDepositToTokenProgram` - If attempting SOL deposit to a token program
+    /// * `ProgramClosing` - If the program is pending closure
+    /// * `ProgramAbandoned` - If the program was declared abandoned
     pub fn deposit_sol(ctx: Context<DepositSol>, amount: u64) -> Result<()> {
         instructions::deposit::deposit_sol(ctx, amount)
     }
@@ -88,6 +283,7 @@ pub mod solrefer {
     ///
     /// This instruction allows the program authority to deposit SPL tokens that will be used
     /// to pay out referral rewards. The program must be configured for token deposits.
+    /// As for SOL deposits, the `reserve_bps` share is held back in the insurance reserve.
     ///
     /// # Arguments
     /// * `ctx` - The deposit context containing:
@@ -96,6 +292,7 @@ pub mod solrefer {
     ///   - token_mint: The token mint (must match program config)
     ///   - depositor_token_account: The authority's token account
     ///   - authority: The program authority (signer)
+    ///   - event_queue: The program's event queue (optional; appended to when supplied)
     ///   - token_program: The token program
     /// * `amount` - Amount to deposit in token units
     ///
@@ -107,6 +304,9 @@ pub mod solrefer {
     /// * `InvalidTokenAccounts` - If the token accounts are invalid
     /// * `InsufficientDeposit` - If the deposit amount is zero
     /// * `TokenDepositToSolProgram` - If attempting token deposit to a SOL program
+    /// * `TokenVaultNotInitialized` - If `initialize_token_vault` has not been called yet
+    /// * `ProgramClosing` - If the program is pending closure
+    /// * `ProgramAbandoned` - If the program was declared abandoned
     pub fn deposit_token(ctx: Context<DepositToken>, amount: u64) -> Result<()> {
         instructions::deposit::deposit_token(ctx, amount)
     }
@@ -116,74 +316,1815 @@ pub mod solrefer {
     /// This function allows the program authority to update various settings of the referral program,
     /// such as reward amounts, locked periods, and fees. It validates the new settings to ensure they
     /// meet the program's requirements.
+    /// A rejected setting is reported in a `ValidationFailure` event naming the `ProgramField` and why.
+    /// On a program created inactive this is the setup step that configures its criteria before activation.
+    /// The limits come from the network config once it is initialized, and from the compile-time constants until then.
+    /// Every change is recorded on-chain in a `SettingsChangeRecord` holding the before and after of each setting
+    /// changed, numbered by the program's `settings_change_count`.
     ///
     /// # Arguments
     /// * `ctx` - The context for the UpdateProgramSettings instruction
     /// * `new_settings` - The new settings to apply to the program
-    pub fn update_program_settings(ctx: Context<UpdateProgramSettings>, new_settings: ProgramSettings) -> Result<()> {
-        instructions::referral_program::update_program_settings(ctx, new_settings)
+    /// * `idempotency_key` - Optional key making retries safe: a call repeating one of the program's last 8 keys
+    ///   fails with `DuplicateIdempotencyKey` instead of applying twice
+    ///
+    /// On a program under participant governance an update that cuts rewards or shortens the program is held as
+    /// the program's pending change instead, and only applied by `execute_change` once participants approve it.
+    ///
+    /// # Errors
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    /// * `PendingChangeRequired` - If a governed program's update cuts rewards and the pending change PDA is missing
+    /// * `ChangeAlreadyPending` - If a governed program's update cuts rewards while another change awaits approval
+    /// * `SettingsTimelocked` - If the creator's settings lock has not lapsed yet
+    pub fn update_program_settings(
+        ctx: Context<UpdateProgramSettings>,
+        new_settings: ProgramSettings,
+        idempotency_key: Option<[u8; constants::IDEMPOTENCY_KEY_LEN]>,
+    ) -> Result<()> {
+        instructions::referral_program::update_program_settings(ctx, new_settings, idempotency_key)
+    }
+
+    /// Creates the event queue of a referral program.
+    ///
+    /// The queue is a ring buffer holding the last 32 program events. Once it exists, joins, referrals,
+    /// deposits, claims and settings changes that are passed the queue append a record to it, so clients
+    /// without log subscriptions can poll one account and read the records past their cursor.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - event_queue: The event queue PDA to initialize
+    ///   - authority: The program authority (signer)
+    ///   - system_program: The system program
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    pub fn initialize_event_queue(ctx: Context<InitializeEventQueue>) -> Result<()> {
+        instructions::event_queue::initialize_event_queue(ctx)
     }
 
     /// Allows a user to join a referral program as someone who wants to refer others.
     ///
     /// This instruction creates a new participant account for the user and generates
     /// their unique referral link that they can share with others. The user joins
-    /// directly (not through a referral).
+    /// directly (not through a referral). The hash of the terms the user accepted
+    /// must match the program's current terms and is recorded on their account.
+    /// A program requiring an NFT collection only admits users holding a verified NFT of it.
+    /// A program with a minimum joiner balance or account age only admits users meeting them.
+    /// A program with a referrer token requirement only admits users holding enough of its token.
+    /// A program with a region attestor only admits users attested outside its embargoed regions.
+    /// A program with a bridge source credits the wallet that referred the user into the source program a bridge
+    /// credit, when both bridge accounts are supplied.
     ///
     /// # Arguments
     /// * `ctx` - The context containing:
     ///   - referral_program: The program account (must be active)
+    ///   - eligibility_criteria: The program's eligibility criteria (required collection)
     ///   - participant: The new participant account to create
+    ///   - invite: An unclaimed invite (required if the program is invite-only)
+    ///   - collection_metadata: The NFT's Metaplex metadata (required if the program requires a collection)
+    ///   - collection_nft: The user's token account holding the NFT (required if the program requires a collection)
+    ///   - age_reference: A participant account of the user in any program (required if the program has a
+    ///     minimum account age)
+    ///   - region_attestation: The region attestor's attestation of the user (required if the program has a
+    ///     region attestor)
+    ///   - referrer_token_account: The user's token account of the required mint (required if the program has a
+    ///     referrer token requirement)
+    ///   - bridge_participant: The user's participant account in the bridge source program (optional)
+    ///   - bridge_referrer: The participant account in this program of the wallet that referred the user into the
+    ///     bridge source program (optional; the bridge is skipped unless both are supplied)
     ///   - user: The user joining the program (signer)
+    ///   - event_queue: The program's event queue (optional; appended to when supplied)
     ///   - system_program: The system program
-    ///   - rent: The rent sysvar
+    /// * `accepted_terms_hash` - Hash of the terms of service the user accepted
     ///
     /// # Errors
     /// * `ProgramInactive` - If the referral program is not active
-    pub fn join_referral_program(ctx: Context<JoinReferralProgram>) -> Result<()> {
-        instructions::join_referral_program(ctx)
+    /// * `ProgramEnded` - If the program's end time has passed
+    /// * `ProgramClosing` - If the program is pending closure
+    /// * `TermsMismatch` - If the accepted terms are not the program's current terms
+    /// * `InviteRequired` - If the program is invite-only and no invite was provided
+    /// * `InviteAlreadyClaimed` - If the invite has already been used
+    /// * `CollectionNftRequired` - If the program requires a collection and no NFT was supplied
+    /// * `InvalidCollectionMetadata` - If the metadata account is not the NFT's Metaplex metadata
+    /// * `CollectionNotVerified` - If the NFT is not a verified member of the required collection
+    /// * `CollectionNftNotHeld` - If the token account does not hold the NFT or is not the user's
+    /// * `JoinerBalanceTooLow` - If the user held less than the minimum joiner balance before joining
+    /// * `InvalidAgeReference` - If the age reference is not the user's
+    /// * `JoinerAccountTooNew` - If the program has a minimum account age and the age reference is missing or
+    ///   joined too recently
+    /// * `ReferrerRequirementNotMet` - If the program has a referrer token requirement and the token account is
+    ///   missing, not the user's, of another mint or holds too little
+    /// * `RegionAttestationRequired` - If the program has a region attestor and the user's attestation is missing
+    /// * `InvalidRegionAttestation` - If the attestation is not the attestor's, is not of the user or names no region
+    /// * `RegionEmbargoed` - If the attested region is embargoed by the program
+    /// * `InvalidBridgeAccounts` - If the bridge accounts are not the user's participant in the bridge source and
+    ///   the participant in this program of the wallet that referred the user there
+    pub fn join_referral_program(ctx: Context<JoinReferralProgram>, accepted_terms_hash: [u8; 32]) -> Result<()> {
+        instructions::join_referral_program(ctx, accepted_terms_hash)
     }
 
     /// Join a referral program through someone's referral link.
     ///
     /// This instruction creates a new participant account for the user,
     /// credits the referrer, and generates a new referral link for the user
-    /// to share with others.
+    /// to share with others. A wallet is only ever credited as a referee once
+    /// per program; later joins through any referrer credit nothing and emit
+    /// `AlreadyReferredNoCredit`.
+    /// The new participant's tree depth is the referrer's depth + 1; referrals deeper
+    /// than the program's `max_depth` (when non-zero) join but credit nothing.
+    /// A credited referral that brings the referrer to a milestone threshold also
+    /// credits that milestone's one-time bonus, if the vault has headroom for it.
+    /// A credited referral also credits the referee the program's sign-up bonus
+    /// (`referee_reward_amount`), claimable like any other reward. When the
+    /// program pays a sign-up bonus and the referrer's boost escrow is supplied,
+    /// the referee is also paid the referrer's boost straight from that escrow.
+    /// A referrer over the program's rate limit (`max_referrals_per_window` per
+    /// `referral_window_seconds`) is credited nothing for the referral, or the join is
+    /// rejected when `rate_limit_strict` is set.
+    /// Every join counts toward the referrer's `raw_referrals` and the program's
+    /// `total_referrals_raw`; only credited ones count toward `total_referrals` and
+    /// `total_referrals_credited`, which milestones and contest rankings use.
+    ///
+    /// An optional ASCII `source_tag` records the campaign the referee came from on the
+    /// participant and receipt, and is counted in the program's per-tag join counts.
+    /// As for direct joins, the accepted terms hash must match the program's current
+    /// terms and is recorded on the new participant, and the program's minimum joiner
+    /// balance and account age apply.
+    /// A program's referee token requirement applies to the user, and its referrer
+    /// token requirement to the referrer at every referral it makes.
     ///
     /// # Arguments
     /// * `ctx` - The context containing:
     ///   - referral_program: The program account (must be active)
+    ///   - eligibility_criteria: The program's eligibility criteria (milestones)
     ///   - participant: The new participant account to create
     ///   - referrer: The referrer's participant account
+    ///   - rotated_referrer: The account the referrer was rotated to (required if the referrer was rotated)
+    ///   - split_recipient: The participant receiving the referrer's payout split (required if one is set)
+    ///   - boost_escrow: The referrer's boost escrow (optional; pays the referee's boost when supplied)
+    ///   - match_offer: A sponsor's match offer (optional; pays the referrer's match when supplied)
+    ///   - referrer_wallet: The referrer's wallet (required with a match offer)
+    ///   - referee_receipt: The user's referee receipt PDA (created on first credit)
+    ///   - sponsor_vault: The program's sponsor vault PDA (required if the program sponsors rent)
+    ///   - invite: An unclaimed invite (required if the program is invite-only)
+    ///   - referrer_collection_metadata: The Metaplex metadata of the referrer's collection NFT (required if the
+    ///     program gates credits on its collection)
+    ///   - referrer_collection_nft: The referrer's token account holding the NFT (required if the program gates
+    ///     credits on its collection)
+    ///   - age_reference: A participant account of the user in any program (required if the program has a
+    ///     minimum account age)
+    ///   - region_attestation: The region attestor's attestation of the user (required if the program has a
+    ///     region attestor)
+    ///   - referrer_token_account: The referrer's token account of the required mint (required if the program has
+    ///     a referrer token requirement)
+    ///   - referee_token_account: The user's token account of the required mint (required if the program has a
+    ///     referee token requirement)
+    ///   - referrer_upline: The participant who referred the referrer (required if the program has a trailing
+    ///     commission and the join credits the referee or the referrer a bonus)
     ///   - user: The user joining through the referral (signer)
+    ///   - event_queue: The program's event queue (optional; appended to when supplied)
+    ///   - instructions_sysvar: The instructions sysvar (required if the program has a link signer)
     ///   - system_program: The system program
-    ///   - rent: The rent sysvar
+    /// * `source_tag` - Optional ASCII campaign tag
+    /// * `accepted_terms_hash` - Hash of the terms of service the user accepted
+    /// * `link_proof` - The signed referral link the user joins through (required if the program has a link
+    ///   signer, in which case the instruction right before must be the ed25519 program verifying its signature)
     ///
     /// # Errors
     /// * `ProgramInactive` - If the referral program is not active
-    /// * `InvalidReferrer` - If the referrer is not part of this program
-    pub fn join_through_referral(ctx: Context<JoinThroughReferral>) -> Result<()> {
-        instructions::join_through_referral(ctx)
+    /// * `ProgramEnded` - If the program's end time has passed
+    /// * `ProgramClosing` - If the program is pending closure
+    /// * `TermsMismatch` - If the accepted terms are not the program's current terms
+    /// * `InvalidReferrer` - If the referrer is not part of this program, or the referral link was signed for another
+    ///   referrer
+    /// * `InviteRequired` - If the program is invite-only and no invite was provided
+    /// * `InviteAlreadyClaimed` - If the invite has already been used
+    /// * `ParticipantRotated` - If the referrer was rotated and the account it was rotated to is missing or wrong
+    /// * `ParticipantLeft` - If the referrer left the program and only its tombstone remains
+    /// * `InvalidSplitRecipient` - If the referrer has a payout split and its recipient account is missing or wrong
+    /// * `InvalidSourceTag` - If the source tag is not ASCII
+    /// * `InvalidBoostEscrow` - If the boost escrow does not belong to the credited referrer
+    /// * `InvalidMatchOffer` - If the match offer is of another program, or the referrer's wallet is missing or is
+    ///   not the credited referrer's owner
+    /// * `ReferralRateLimited` - If the referrer is over its rate limit and the program's limit is strict
+    /// * `ProgramUnderfunded` - If the program is not accepting referrals or its uncommitted funds cannot cover the
+    ///   referral reward and the sign-up bonus
+    /// * `CollectionNftRequired`, `InvalidCollectionMetadata`, `CollectionNotVerified`, `CollectionNftNotHeld` - If
+    ///   the program gates credits on its collection and the referrer's NFT is missing or fails the checks
+    /// * `SponsorVaultUnderfunded` - If the program sponsors rent and its sponsor vault is missing or cannot pay
+    ///   for the referee receipt
+    /// * `JoinerBalanceTooLow`, `InvalidAgeReference`, `JoinerAccountTooNew` - As for `join_referral_program`
+    /// * `RefereeRequirementNotMet` - If the program has a referee token requirement the user's token account does
+    ///   not meet
+    /// * `ReferrerRequirementNotMet` - If the program has a referrer token requirement the referrer's token account
+    ///   does not meet
+    /// * `ReferrerAccountRequired` - If a trailing commission is due and the referrer's referrer was not supplied
+    /// * `LinkProofRequired` - If the program has a link signer and the link proof or instructions sysvar is missing
+    /// * `LinkProofExpired` - If the referral link has expired
+    /// * `InvalidLinkProof` - If the preceding instruction does not verify the link signer's signature of the link
+    /// * `RegionAttestationRequired`, `InvalidRegionAttestation`, `RegionEmbargoed` - As for `join_referral_program`
+    pub fn join_through_referral(
+        ctx: Context<JoinThroughReferral>,
+        source_tag: Option<[u8; constants::SOURCE_TAG_LEN]>,
+        accepted_terms_hash: [u8; 32],
+        link_proof: Option<LinkProof>,
+    ) -> Result<()> {
+        instructions::join_through_referral(ctx, source_tag, accepted_terms_hash, link_proof)
     }
 
-    /// Claims earned rewards for a participant in the referral program.
+    /// Joins a referral program through the referrer named by a compact referral payload, e.g. from a QR code.
     ///
-    /// This instruction calculates and transfers the earned rewards from the program vault
-    /// to the participant based on their referral performance. The reward amount is determined
-    /// by the participant's total referrals and program parameters.
+    /// Clients build the payload with `linkcodec::encode_referral`. Its campaign code, if any, becomes the join's
+    /// source tag, and the join then behaves like `join_through_referral` without a link proof, so programs with a
+    /// link signer reject it.
+    ///
+    /// # Arguments
+    /// * `ctx` - The same accounts as `join_through_referral`
+    /// * `payload` - The referral payload
+    /// * `accepted_terms_hash` - Hash of the terms of service the user accepted
+    ///
+    /// # Errors
+    /// * `InvalidReferralPayload` - If the payload is malformed, of an unsupported version or names another program
+    /// * `ReferralPayloadChecksum` - If the payload fails its checksum, e.g. after a bad scan
+    /// * `InvalidReferrer` - If the referrer account is not owned by the payload's referrer
+    /// * Any error of `join_through_referral`
+    pub fn join_with_payload(
+        ctx: Context<JoinThroughReferral>,
+        payload: Vec<u8>,
+        accepted_terms_hash: [u8; 32],
+    ) -> Result<()> {
+        instructions::join_with_payload(ctx, payload, accepted_terms_hash)
+    }
+
+    /// Joins a referral program through a referrer and pays the referee's
+    /// sign-up bonus in the same transaction.
+    ///
+    /// Behaves exactly like `join_through_referral`, including the referrer's
+    /// credit. When the referral credits the referee a bonus and the program
+    /// does not lock referee rewards, the bonus is then paid from the vault
+    /// straight away. Otherwise the call is a plain join and the bonus, if any,
+    /// stays pending. The amount paid (zero if nothing was paid) is returned in
+    /// the transaction return data. A program that only accepts direct claims
+    /// also leaves the bonus pending unless this instruction is invoked directly
+    /// by the transaction.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - join: The accounts of `join_through_referral`
+    ///   - vault: The program's SOL vault PDA
+    ///   - instructions_sysvar: The instructions sysvar (optional; needed to pay the bonus when the program only
+    ///     accepts direct claims)
+    /// * `source_tag` - Optional ASCII campaign tag, as for `join_through_referral`
+    /// * `accepted_terms_hash` - Hash of the terms of service the user accepted, as for `join_through_referral`
+    /// * `link_proof` - The signed referral link the user joins through, as for `join_through_referral`
+    ///
+    /// # Errors
+    /// * Every error of `join_through_referral`
+    /// * `InvalidTokenMint` - If a bonus is due but the program pays in tokens
+    /// * `InsufficientVaultBalance` - If the vault cannot cover the bonus
+    /// * `ClaimGuardViolation` - If the payout moved lamports other than from the vault to the user
+    pub fn join_and_claim_through_referral(
+        ctx: Context<JoinAndClaimThroughReferral>,
+        source_tag: Option<[u8; constants::SOURCE_TAG_LEN]>,
+        accepted_terms_hash: [u8; 32],
+        link_proof: Option<LinkProof>,
+    ) -> Result<u64> {
+        instructions::join_and_claim_through_referral(ctx, source_tag, accepted_terms_hash, link_proof)
+    }
+
+    /// Replaces the hash of the program's off-chain terms of service and bumps its version.
+    ///
+    /// New joins must present the new hash. Existing participants keep the version they
+    /// accepted until they call `reaccept_terms`.
     ///
     /// # Arguments
     /// * `ctx` - The context containing:
     ///   - referral_program: The program account
-    ///   - participant: The participant's account
-    ///   - vault: The program's vault
-    ///   - user: The participant claiming rewards (signer)
+    ///   - authority: The program authority (signer)
+    /// * `terms_hash` - Hash of the new terms
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `NumericOverflow` - If the terms version overflows
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    /// * `SettingsTimelocked` - If the creator's settings lock has not lapsed yet
+    pub fn update_terms(ctx: Context<UpdateTerms>, terms_hash: [u8; 32]) -> Result<()> {
+        instructions::terms::update_terms(ctx, terms_hash)
+    }
+
+    /// Records that a participant accepted the program's current terms of service.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - participant: The signer's participant account
+    ///   - user: The participant's owner (signer)
+    /// * `accepted_terms_hash` - Hash of the terms the participant accepted
+    ///
+    /// # Errors
+    /// * `TermsMismatch` - If the accepted terms are not the program's current terms
+    pub fn reaccept_terms(ctx: Context<ReacceptTerms>, accepted_terms_hash: [u8; 32]) -> Result<()> {
+        instructions::terms::reaccept_terms(ctx, accepted_terms_hash)
+    }
+
+    /// Routes a share of the signer's future referral rewards to another participant.
+    ///
+    /// Agencies use this to split revenue with sub-affiliates: every reward credited to the signer as a
+    /// referrer is divided, with `bps` basis points going to `recipient`'s pending rewards. Passing
+    /// `bps = 0` clears the split; already-credited rewards are never moved.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - participant: The signer's participant account
+    ///   - recipient_participant: The recipient's participant account (not needed when clearing)
+    ///   - user: The participant setting the split (signer)
+    /// * `recipient` - The wallet of the participant receiving the share
+    /// * `bps` - The share in basis points, at most 5_000
+    ///
+    /// # Errors
+    /// * `ParticipantRotated` - If the signer's participant account was rotated to a new owner
+    /// * `ParticipantLeft` - If the signer's participant account left the program
+    /// * `InvalidPayoutSplit` - If `bps` exceeds `MAX_PAYOUT_SPLIT_BPS`
+    /// * `InvalidSplitRecipient` - If the recipient is the signer or not a participant of the program
+    pub fn set_payout_split(ctx: Context<SetPayoutSplit>, recipient: Pubkey, bps: u16) -> Result<()> {
+        instructions::payout_split::set_payout_split(ctx, recipient, bps)
+    }
+
+    /// Sets the landing parameters clients apply to referees joining through the signer's referral.
+    ///
+    /// Growth teams store a discount code or campaign variant here instead of in an off-chain mapping.
+    /// `preview_referral` returns the referrer's current parameters and every join snapshots them on the
+    /// referee receipt, so changing them only affects future joins. Empty parameters clear them.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - participant: The signer's participant account
+    ///   - user: The participant setting its parameters (signer)
+    /// * `params` - Opaque parameters, at most 32 bytes
+    ///
+    /// # Errors
+    /// * `ParticipantRotated` - If the signer's participant account was rotated to a new owner
+    /// * `ParticipantLeft` - If the signer's participant account left the program
+    /// * `InvalidLinkParams` - If the parameters are longer than `LINK_PARAMS_LEN` bytes
+    pub fn set_link_params(ctx: Context<SetLinkParams>, params: Vec<u8>) -> Result<()> {
+        instructions::link_params::set_link_params(ctx, params)
+    }
+
+    /// Mints single-use invites for an invite-only program.
+    ///
+    /// Each invite is a PDA seeded by the program and a running invite index; the PDAs to create are
+    /// passed as remaining accounts in index order, starting at the program's `invite_count`.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - authority: The program authority (signer, pays for the invites)
     ///   - system_program: The system program
+    ///   - remaining accounts: The invite PDAs to create
+    /// * `count` - The number of invites to mint, at most 8
     ///
     /// # Errors
-    /// * `InsufficientFunds` - If the vault has insufficient funds
-    /// * `NumericOverflow` - If calculations result in overflow
-    pub fn claim_rewards(ctx: Context<ClaimRewards>) -> Result<()> {
-        instructions::rewards::process_claim_rewards(ctx)
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `InvalidInviteCount` - If `count` is zero, above the limit, or does not match the remaining accounts
+    /// * `InvalidInviteAccount` - If a remaining account is not the next invite PDA
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    pub fn mint_invites<'info>(ctx: Context<'_, '_, 'info, 'info, MintInvites<'info>>, count: u8) -> Result<()> {
+        instructions::invite::mint_invites(ctx, count)
+    }
+
+    /// Revokes an unclaimed invite, returning its rent to the authority.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - invite: The invite to close
+    ///   - authority: The program authority (signer)
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `InviteAlreadyClaimed` - If the invite has already been used
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    pub fn revoke_invite(ctx: Context<RevokeInvite>) -> Result<()> {
+        instructions::invite::revoke_invite(ctx)
+    }
+
+    /// Reverses a credited referral.
+    ///
+    /// The referrer loses the referral from its count and as much of the credited reward as is still
+    /// pending. Milestone bonuses already paid are kept and cannot be earned a second time. Programs with a
+    /// dispute window hold the removed rewards until the clawback is final or overturned.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - referee_receipt: The receipt of the referral to reverse
+    ///   - referrer: The participant account credited for the referral
+    ///   - authority: The program authority (signer)
+    /// * `idempotency_key` - Optional key making retries safe: a call repeating one of the program's last 8 keys
+    ///   fails with `DuplicateIdempotencyKey` instead of applying twice
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `AlreadyClawedBack` - If the referral was already clawed back, or a clawback of it was overturned
+    /// * `InvalidReferralRecord` - If the referral was never counted, e.g. it was rate-limited
+    /// * `InvalidReferrer` - If the referrer account is not the current account of the referrer credited on the
+    ///   receipt
+    /// * `DuplicateIdempotencyKey` - If the key was used by one of the program's recent authority mutations
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    /// * `SettingsTimelocked` - If the creator's settings lock has not lapsed yet
+    pub fn clawback_referral(
+        ctx: Context<ClawbackReferral>,
+        idempotency_key: Option<[u8; constants::IDEMPOTENCY_KEY_LEN]>,
+    ) -> Result<()> {
+        instructions::clawback::clawback_referral(ctx, idempotency_key)
+    }
+
+    /// Contests an open clawback of one of the signer's referrals within the program's dispute window.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - referee_receipt: The receipt of the clawed-back referral
+    ///   - referrer: The signer's participant account credited for the referral
+    ///   - user: The referrer's owner (signer)
+    /// * `evidence_hash` - Hash of off-chain evidence supporting the referral, if any
+    ///
+    /// # Errors
+    /// * `InvalidReferrer` - If the referrer is not the current account of the referrer credited on the receipt or
+    ///   not owned by the signer
+    /// * `InvalidClawbackState` - If the referral has no open, uncontested clawback
+    /// * `ClawbackDeadlinePassed` - If the dispute window has closed
+    pub fn contest_clawback(ctx: Context<ContestClawback>, evidence_hash: Option<[u8; 32]>) -> Result<()> {
+        instructions::clawback::contest_clawback(ctx, evidence_hash)
+    }
+
+    /// Rules on a contested clawback, releasing the held rewards to the vault or restoring them to the referrer.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - referee_receipt: The receipt of the contested referral
+    ///   - referrer: The participant account credited for the referral
+    ///   - authority: The program authority (signer)
+    /// * `uphold` - Whether the clawback stands
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `InvalidReferrer` - If the referrer account is not the current account of the referrer credited on the
+    ///   receipt
+    /// * `InvalidClawbackState` - If the clawback was not contested or is already resolved
+    /// * `ClawbackDeadlinePassed` - If the resolution timeout has passed
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    pub fn resolve_clawback(ctx: Context<ResolveClawback>, uphold: bool) -> Result<()> {
+        instructions::clawback::resolve_clawback(ctx, uphold)
+    }
+
+    /// Makes an uncontested clawback final once its dispute window has closed; anyone can call it.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - referee_receipt: The receipt of the clawed-back referral
+    ///   - referrer: The participant account credited for the referral
+    ///
+    /// # Errors
+    /// * `InvalidReferrer` - If the referrer account is not the current account of the referrer credited on the
+    ///   receipt
+    /// * `InvalidClawbackState` - If the referral has no open, uncontested clawback
+    /// * `ClawbackDeadlineNotReached` - If the referrer can still contest the clawback
+    pub fn finalize_clawback(ctx: Context<SettleClawback>) -> Result<()> {
+        instructions::clawback::finalize_clawback(ctx)
+    }
+
+    /// Rules a contested clawback for the referrer once the authority failed to resolve it in time; anyone can
+    /// call it.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - referee_receipt: The receipt of the contested referral
+    ///   - referrer: The participant account credited for the referral
+    ///
+    /// # Errors
+    /// * `InvalidReferrer` - If the referrer account is not the current account of the referrer credited on the
+    ///   receipt
+    /// * `InvalidClawbackState` - If the clawback was not contested or is already resolved
+    /// * `ClawbackDeadlineNotReached` - If the authority can still resolve the clawback
+    pub fn default_resolve(ctx: Context<SettleClawback>) -> Result<()> {
+        instructions::clawback::default_resolve(ctx)
+    }
+
+    /// Corrects a participant's referral count from its referee receipts; callable by anyone.
+    ///
+    /// The receipts are passed as remaining accounts. Once the batch holds every receipt counted for the
+    /// participant, its `total_referrals` is set to the number not clawed back; a partial batch is only checked.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - participant: The participant account to recount
+    ///   - remaining accounts: Up to 20 of the participant's referee receipts
+    ///
+    /// # Errors
+    /// * `ParticipantRotated` - If the participant account was rotated to a new owner
+    /// * `ParticipantLeft` - If the participant left the program
+    /// * `InvalidRecountBatch` - If the batch holds more than 20 receipts or repeats one
+    /// * `InvalidReferralRecord` - If a receipt is of another program or was not counted for the participant
+    pub fn recount_referrals<'info>(ctx: Context<'_, '_, 'info, 'info, RecountReferrals<'info>>) -> Result<()> {
+        instructions::recount::recount_referrals(ctx)
+    }
+
+    /// Transfers pending rewards from the signer's participant account to another participant.
+    ///
+    /// Gated by the program's `transfers_enabled` setting. The transferred rewards keep the lock they had at
+    /// the source and count toward the destination's reward cap.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - eligibility_criteria: The program's eligibility criteria
+    ///   - source: The signer's participant account
+    ///   - destination: The participant account receiving the rewards
+    ///   - user: The source's owner (signer)
+    /// * `amount` - Pending rewards to transfer, in raw units
+    ///
+    /// # Errors
+    /// * `InvalidTransferAmount` - If the amount is zero or exceeds the signer's pending rewards
+    /// * `TransfersDisabled` - If the program does not allow transfers
+    /// * `ParticipantRotated` - If the signer's participant account was rotated to a new owner
+    /// * `ParticipantLeft` - If the signer's participant account left the program
+    /// * `InvalidTransferDestination` - If the destination is the source, of another program, was rotated or left
+    /// * `RewardCapExceeded` - If the destination's earnings would exceed the program's reward cap
+    pub fn transfer_pending(ctx: Context<TransferPending>, amount: u64) -> Result<()> {
+        instructions::transfer_pending::transfer_pending(ctx, amount)
+    }
+
+    /// Writes the note the program authority keeps on a participant, e.g. the terms negotiated with a partner.
+    ///
+    /// The note has no effect on rewards and only the authority can change it; an empty note clears it.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - participant: The participant account the note is on
+    ///   - authority: The program authority (signer)
+    /// * `note` - The new note, at most 64 bytes of UTF-8
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `InvalidReferrer` - If the participant belongs to another program
+    /// * `InvalidParticipantNote` - If the note is longer than 64 bytes
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    pub fn set_participant_note(ctx: Context<SetParticipantNote>, note: String) -> Result<()> {
+        instructions::participant_note::set_participant_note(ctx, note)
+    }
+
+    /// Starts handing the signer's participant account over to a new wallet.
+    ///
+    /// Wallet rotation is two-step so a typo cannot strand an account: the current owner nominates
+    /// `new_owner` here and the new owner accepts with `complete_owner_rotation`.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - participant: The signer's participant account
+    ///   - user: The current owner (signer)
+    /// * `new_owner` - The wallet that will own the new participant account
+    ///
+    /// # Errors
+    /// * `InvalidRotation` - If `new_owner` is the current owner or the account was already rotated to or from another
+    /// * `ParticipantLeft` - If the participant left the program
+    pub fn initiate_owner_rotation(ctx: Context<InitiateOwnerRotation>, new_owner: Pubkey) -> Result<()> {
+        instructions::owner_rotation::initiate_owner_rotation(ctx, new_owner)
+    }
+
+    /// Completes a wallet rotation by moving the participant to the new owner's PDA.
+    ///
+    /// All referral history and pending rewards move to a new participant account seeded by the new
+    /// owner. The old account keeps a `rotated_to` tombstone: it can no longer claim, and referees that
+    /// still reference it credit the new account, which counts them as its active referees.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - old_participant: The participant account being handed over
+    ///   - new_participant: The new owner's participant account to create
+    ///   - new_owner: The nominated wallet (signer)
+    ///   - system_program: The system program
+    ///
+    /// # Errors
+    /// * `InvalidRotation` - If the signer was not nominated by the old account's owner or it was already rotated
+    pub fn complete_owner_rotation(ctx: Context<CompleteOwnerRotation>) -> Result<()> {
+        instructions::owner_rotation::complete_owner_rotation(ctx)
+    }
+
+    /// Configures the program's referral contest: ranked prizes paid from a separate escrow after the program
+    /// ends.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - contest: The contest PDA (created on first use)
+    ///   - authority: The program authority (signer)
+    ///   - system_program: The system program
+    /// * `prizes` - Prize for each rank, best first; unused ranks are zero and must come last
+    /// * `dispute_window` - Seconds between finalization and prizes becoming claimable (at most 30 days)
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `ContestAlreadySettled` - If the contest was already finalized
+    /// * `InvalidContestConfig` - If the prizes or dispute window are invalid
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    pub fn configure_contest(
+        ctx: Context<ConfigureContest>,
+        prizes: [u64; constants::MAX_CONTEST_PRIZES],
+        dispute_window: i64,
+    ) -> Result<()> {
+        instructions::contest::configure_contest(ctx, prizes, dispute_window)
+    }
+
+    /// Deposits SOL into the contest prize escrow. The program cannot be closed until the deposit has been paid out
+    /// as prizes or withdrawn with `withdraw_contest_leftover`.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - contest: The contest PDA
+    ///   - escrow: The prize escrow PDA
+    ///   - authority: The program authority (signer)
+    ///   - system_program: The system program
+    /// * `amount` - The amount to deposit in lamports
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `InsufficientDeposit` - If the amount is zero
+    /// * `ContestAlreadySettled` - If the contest was already finalized
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    pub fn fund_contest(ctx: Context<FundContest>, amount: u64) -> Result<()> {
+        instructions::contest::fund_contest(ctx, amount)
+    }
+
+    /// Finalizes the contest ranking at or after the program end time.
+    ///
+    /// The winning participant accounts are passed as remaining accounts, best rank first, and must have
+    /// non-increasing referral counts. Finalizing opens the dispute window; prizes become claimable once it
+    /// closes. A contest is finalized exactly once.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - eligibility_criteria: The program's eligibility criteria
+    ///   - contest: The contest PDA
+    ///   - authority: The program authority (signer)
+    ///   - remaining accounts: The winning participant accounts in rank order
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `ContestNotEnded` - If the program end time has not passed
+    /// * `ContestAlreadySettled` - If the contest was already finalized
+    /// * `InvalidContestRanking` - If the winners are missing, too many, duplicated, foreign or out of order
+    /// * `InsufficientFunds` - If the escrow does not cover the awarded prizes
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    pub fn finalize_contest<'info>(ctx: Context<'_, '_, 'info, 'info, FinalizeContest<'info>>) -> Result<()> {
+        instructions::contest::finalize_contest(ctx)
+    }
+
+    /// Claims a contest prize once the dispute window has closed.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - contest: The contest PDA
+    ///   - escrow: The prize escrow PDA
+    ///   - winner: The winning wallet (signer)
+    ///   - system_program: The system program
+    /// * `rank` - The rank won, 0 being the best
+    ///
+    /// # Errors
+    /// * `ContestNotFinalized` - If the contest has not been finalized
+    /// * `ContestDisputeWindowOpen` - If the dispute window has not closed
+    /// * `InvalidContestWinner` - If the signer did not win `rank`
+    /// * `PrizeAlreadyClaimed` - If the prize was already paid
+    pub fn claim_contest_prize(ctx: Context<ClaimContestPrize>, rank: u8) -> Result<()> {
+        instructions::contest::claim_contest_prize(ctx, rank)
+    }
+
+    /// Returns what the escrow of a finalized contest holds beyond its unclaimed prizes to the authority.
+    ///
+    /// The program cannot be closed while its contest escrow holds funds, so this returns the funding of ranks
+    /// left without a winner and any excess deposit; the prizes themselves leave the escrow as winners claim them.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - contest: The contest PDA
+    ///   - escrow: The prize escrow PDA
+    ///   - authority: The program authority (signer, receives the leftover)
+    ///   - system_program: The system program
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `ContestNotFinalized` - If the contest has not been finalized
+    /// * `InsufficientFunds` - If the escrow holds nothing beyond the unclaimed prizes
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    /// * `ProgramFrozen` - If the guardian froze the program
+    pub fn withdraw_contest_leftover(ctx: Context<WithdrawContestLeftover>) -> Result<()> {
+        instructions::contest::withdraw_contest_leftover(ctx)
+    }
+
+    /// Records a purchase made by a referred participant and credits the revenue share to their referrer.
+    ///
+    /// The share is `revenue_share_percent` basis points of the purchase, clamped to the referrer's remaining
+    /// `max_reward_cap` and to the vault's uncommitted funds. The full purchase amount is added to the
+    /// referrer's and the program's `total_attributed_volume` whatever reward was credited.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - eligibility_criteria: The program's eligibility criteria
+    ///   - buyer: The buyer's participant account
+    ///   - referrer: The participant account that referred the buyer
+    ///   - referrer_upline: The participant that referred the referrer (required if the program has a trailing
+    ///     commission and the purchase credits the referrer a reward)
+    ///   - authority: The program authority (signer)
+    /// * `amount` - The purchase amount
+    /// * `idempotency_key` - Optional key making retries safe: a call repeating one of the program's last 8 keys
+    ///   fails with `DuplicateIdempotencyKey` instead of applying twice
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `InvalidReferrer` - If the buyer was not referred by `referrer` in this program
+    /// * `InvalidPurchaseAmount` - If the amount is zero
+    /// * `ProgramEnded` - If the program's end time has passed
+    /// * `ReferrerAccountRequired` - If a trailing commission is due and the referrer's referrer was not supplied
+    /// * `DuplicateIdempotencyKey` - If the key was used by one of the program's recent authority mutations
+    /// * `NumericOverflow` - If calculations result in overflow
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    pub fn record_purchase(
+        ctx: Context<RecordPurchase>,
+        amount: u64,
+        idempotency_key: Option<[u8; constants::IDEMPOTENCY_KEY_LEN]>,
+    ) -> Result<()> {
+        instructions::purchase::record_purchase(ctx, amount, idempotency_key)
+    }
+
+    /// Records a batch of purchases in one transaction, depositing their total into the vault.
+    ///
+    /// Merchants with high sales volume use this instead of one `record_purchase` per sale. Each entry names
+    /// a buyer by its index in the remaining accounts, and each buyer's referrer must also be passed there,
+    /// writable. A referrer backing several buyers is passed once and written once. Purchases are credited in
+    /// order exactly as by `record_purchase`. The first invalid entry fails the whole batch and its index is
+    /// logged.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - eligibility_criteria: The program's eligibility criteria
+    ///   - vault: The program's SOL vault PDA
+    ///   - authority: The program authority (signer, funds the deposit)
+    ///   - system_program: The system program
+    ///   - remaining accounts: The buyers' participant accounts and their referrers' participant accounts, and
+    ///     the referrers' own referrers when a trailing commission is due
+    /// * `purchases` - Up to `MAX_PURCHASE_BATCH` purchases
+    /// * `deposit` - Lamports to deposit, which must equal the sum of the purchase amounts
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `InvalidPurchaseBatch` - If the batch is empty, too large or references a missing account
+    /// * `PurchaseDepositMismatch` - If `deposit` is not the sum of the purchase amounts
+    /// * `SolDepositToTokenProgram` - If the program pays in tokens
+    /// * `ProgramEnded` - If the program's end time has passed
+    /// * `ProgramClosing` - If the program is pending closure
+    /// * `InvalidReferrer` - If a buyer was not referred within this program or its referrer is not writable
+    /// * `InvalidPurchaseAmount` - If a purchase amount is zero
+    /// * `ReferrerAccountRequired` - If a trailing commission is due and the referrer's referrer was not passed
+    /// * `NumericOverflow` - If calculations result in overflow
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    pub fn record_purchases_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RecordPurchasesBatch<'info>>,
+        purchases: Vec<PurchaseEntry>,
+        deposit: u64,
+    ) -> Result<()> {
+        instructions::purchase::record_purchases_batch(ctx, purchases, deposit)
+    }
+
+    /// Closes the token vault of a wound-down token program, sweeping any remaining tokens to the authority.
+    ///
+    /// The program must be inactive or past its end time with no credited rewards outstanding. The vault's
+    /// rent is returned to the authority. The program keeps its token mint, so its totals and final report stay in
+    /// token units and SOL deposits and claims still reject it, and the vault cannot be reopened.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - eligibility_criteria: The program's eligibility criteria
+    ///   - token_vault: The token vault PDA
+    ///   - destination_token_account: Authority token account for remaining tokens (required if the vault is
+    ///     not empty)
+    ///   - authority: The program authority (signer, receives the rent)
+    ///   - token_program: The token program
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `InvalidTokenMint` - If the program is not a token program
+    /// * `ProgramStillActive` - If the program is active and has not ended
+    /// * `OutstandingRewards` - If credited rewards have not been claimed or clawed back
+    /// * `ProgramFrozen` - If the guardian froze the program
+    /// * `TokenVaultNotEmpty` - If the vault holds tokens and no destination account was provided
+    /// * `InvalidTokenAccounts` - If the destination is not an authority token account of the program's mint
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    pub fn close_token_vault(ctx: Context<CloseTokenVault>) -> Result<()> {
+        instructions::close_token_vault::close_token_vault(ctx)
+    }
+
+    /// Releases the program's insurance reserve once the program has ended.
+    ///
+    /// Deposits ring-fence `reserve_bps` of each amount in `reserved_balance`, which no claim can reach. After
+    /// the program's end time the authority either moves it back into `total_available`, where it can pay out
+    /// outstanding rewards, or takes it straight out of the vault. The amount released is returned in the
+    /// transaction return data.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - vault: The program's SOL vault PDA
+    ///   - token_vault: The token vault PDA (required to pay a token program's reserve out)
+    ///   - destination_token_account: Token account receiving a token program's reserve
+    ///   - destination: Optional allowed wallet receiving a SOL program's reserve instead of the authority
+    ///   - authority: The program authority (signer)
+    ///   - system_program: The system program
+    ///   - token_program: The token program (required to pay a token program's reserve out)
+    /// * `to_authority` - Pay the reserve to the authority instead of returning it to the available funds
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `ReserveLocked` - If the program's end time has not passed
+    /// * `ProgramFrozen` - If the reserve is paid to the authority of a program its guardian froze
+    /// * `DestinationNotAllowed` - If the destination is neither the authority nor on the program's allow-list
+    /// * `InvalidTokenAccounts` - If a token program's reserve is paid out without valid token accounts
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    pub fn release_reserve(ctx: Context<ReleaseReserve>, to_authority: bool) -> Result<u64> {
+        instructions::reserve::release_reserve(ctx, to_authority)
+    }
+
+    /// Withdraws uncommitted funds from the vault to the authority at once.
+    ///
+    /// Credited rewards and the insurance reserve cannot be withdrawn. A program with a guardian only pays out
+    /// amounts up to its withdrawal delay threshold this way; larger ones go through `queue_withdrawal`.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - vault: The program's SOL vault PDA
+    ///   - token_vault: The token vault PDA (required for token programs)
+    ///   - destination_token_account: Token account receiving a token program's funds
+    ///   - destination: Optional allowed wallet receiving a SOL program's funds instead of the authority
+    ///   - authority: The program authority (signer)
+    ///   - system_program: The system program
+    ///   - token_program: The token program (required for token programs)
+    /// * `amount` - The amount to withdraw
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `DestinationNotAllowed` - If the destination is neither the authority nor on the program's allow-list
+    /// * `WithdrawalDelayRequired` - If the program has a guardian and the amount is above its delay threshold
+    /// * `ProgramFrozen` - If the guardian froze the program
+    /// * `InvalidWithdrawalAmount` - If the amount is zero
+    /// * `InsufficientFunds` - If the amount is more than the uncommitted funds
+    /// * `InvalidTokenAccounts` - If a token program's funds are withdrawn without the token accounts
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    /// * `SettingsTimelocked` - If the creator's settings lock has not lapsed yet
+    pub fn withdraw_funds(ctx: Context<WithdrawFunds>, amount: u64) -> Result<()> {
+        instructions::guardian::withdraw_funds(ctx, amount)
+    }
+
+    /// Queues a withdrawal that can execute once `WITHDRAWAL_DELAY` has elapsed; the guardian can cancel it
+    /// meanwhile. One withdrawal can be queued per program at a time.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - withdrawal_request: The withdrawal request PDA to create
+    ///   - destination: Optional allowed wallet the withdrawal will pay out to instead of the authority
+    ///   - authority: The program authority (signer)
+    ///   - system_program: The system program
+    /// * `amount` - The amount to withdraw
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `DestinationNotAllowed` - If the destination is neither the authority nor on the program's allow-list
+    /// * `ProgramFrozen` - If the guardian froze the program
+    /// * `InvalidWithdrawalAmount` - If the amount is zero
+    /// * `InsufficientFunds` - If the amount is more than the uncommitted funds
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    /// * `SettingsTimelocked` - If the creator's settings lock has not lapsed yet
+    pub fn queue_withdrawal(ctx: Context<QueueWithdrawal>, amount: u64) -> Result<()> {
+        instructions::guardian::queue_withdrawal(ctx, amount)
+    }
+
+    /// Pays out the queued withdrawal once its delay has elapsed and closes the request.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - withdraw: The accounts of `withdraw_funds`
+    ///   - withdrawal_request: The queued withdrawal request PDA
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `WithdrawalNotReady` - If the delay has not elapsed
+    /// * `DestinationNotAllowed` - If the destination is not the one queued, or is no longer allowed
+    /// * `ProgramFrozen` - If the guardian froze the program
+    /// * `InsufficientFunds` - If the amount is now more than the uncommitted funds
+    /// * `InvalidTokenAccounts` - If a token program's funds are withdrawn without the token accounts
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    /// * `SettingsTimelocked` - If the creator's settings lock has not lapsed yet
+    pub fn execute_withdrawal(ctx: Context<ExecuteWithdrawal>) -> Result<()> {
+        instructions::guardian::execute_withdrawal(ctx)
+    }
+
+    /// Cancels the program's queued withdrawal; only the guardian can, and the rent goes to the authority.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - withdrawal_request: The queued withdrawal request PDA
+    ///   - authority: The program authority, receiving the request's rent
+    ///   - guardian: The program's guardian (signer)
+    ///
+    /// # Errors
+    /// * `InvalidGuardian` - If the signer is not the program's guardian
+    /// * `InvalidAuthority` - If the rent recipient is not the program authority
+    pub fn guardian_cancel_withdrawal(ctx: Context<GuardianCancelWithdrawal>) -> Result<()> {
+        instructions::guardian::guardian_cancel_withdrawal(ctx)
+    }
+
+    /// Freezes the program, stopping claims and withdrawals, in case the authority key is compromised.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - guardian: The program's guardian (signer)
+    ///
+    /// # Errors
+    /// * `InvalidGuardian` - If the signer is not the program's guardian
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    pub fn guardian_freeze(ctx: Context<GuardianFreeze>) -> Result<()> {
+        instructions::guardian::guardian_freeze(ctx)
+    }
+
+    /// Lifts a guardian freeze; needs both the authority and the guardian to sign.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - authority: The program authority (signer)
+    ///   - guardian: The program's guardian (signer)
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the authority signer is not the program authority
+    /// * `InvalidGuardian` - If the guardian signer is not the program's guardian
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    pub fn unfreeze_program(ctx: Context<UnfreezeProgram>) -> Result<()> {
+        instructions::guardian::unfreeze_program(ctx)
+    }
+
+    /// Replaces the addresses besides the authority that withdrawals may pay out to; needs both the authority
+    /// and the guardian to sign, so a program without a guardian keeps the list it was created with.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - authority: The program authority (signer)
+    ///   - guardian: The program's guardian (signer)
+    /// * `destinations` - The new allow-list; all-default leaves withdrawals unrestricted
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the authority signer is not the program authority
+    /// * `InvalidGuardian` - If the guardian signer is not the program's guardian
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    /// * `SettingsTimelocked` - If the creator's settings lock has not lapsed yet
+    pub fn set_withdrawal_destinations(
+        ctx: Context<SetWithdrawalDestinations>,
+        destinations: [Pubkey; constants::MAX_WITHDRAWAL_DESTINATIONS],
+    ) -> Result<()> {
+        instructions::guardian::set_withdrawal_destinations(ctx, destinations)
+    }
+
+    /// Moves a program's end time, or makes it open-ended.
+    ///
+    /// Converts an open-ended program to a dated one and back, or moves a dated program's end. A new end time
+    /// must fall after the program's locked period and within the network's maximum program duration; `None`
+    /// removes the end, so joins and purchases never expire while the reserve and any contest stay locked.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - eligibility_criteria: The program's eligibility criteria
+    ///   - network_config: The network config PDA, which may not be initialized yet
+    ///   - authority: The program authority (signer), paying the settings change record's rent
+    ///   - settings_change: The program's next settings change record PDA, created if the end time moves
+    /// * `program_end_time` - The new end time, or `None` for an open-ended program
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `ProgramClosing` - If the program is pending closure
+    /// * `InvalidProgramEndTime` - If the end time does not fall after the locked period
+    /// * `ProgramDurationTooLong` - If the end time is beyond the maximum program duration from now
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    /// * `SettingsTimelocked` - If the creator's settings lock has not lapsed yet
+    pub fn extend_program(ctx: Context<ExtendProgram>, program_end_time: Option<i64>) -> Result<()> {
+        instructions::extend_program::extend_program(ctx, program_end_time)
+    }
+
+    /// Stakes the signer's own SOL to boost the sign-up bonus of everyone joining through their link.
+    ///
+    /// The lamports go into the referrer's boost escrow PDA, kept apart from the program vault. While the
+    /// program pays sign-up bonuses, each referee the referrer is credited for is paid `boost_per_referee`
+    /// from the escrow on top of the program's bonus, until the escrow holds less than a full boost. Funding
+    /// again tops the escrow up and replaces the boost per referee.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - participant: The signer's participant account
+    ///   - boost_escrow: The signer's boost escrow PDA (created on first funding)
+    ///   - user: The referrer funding the boost (signer)
+    ///   - system_program: The system program
+    /// * `amount` - Lamports to deposit
+    /// * `boost_per_referee` - Lamports paid to each boosted referee
+    ///
+    /// # Errors
+    /// * `InsufficientDeposit` - If the amount is zero
+    /// * `InvalidBoostAmount` - If the boost per referee is zero
+    /// * `ParticipantRotated` - If the signer's participant account was rotated to a new owner
+    /// * `ParticipantLeft` - If the signer's participant account left the program
+    /// * `ProgramEnded` - If the program's end time has passed
+    /// * `ProgramClosing` - If the program is pending closure
+    pub fn fund_referee_boost(ctx: Context<FundRefereeBoost>, amount: u64, boost_per_referee: u64) -> Result<()> {
+        instructions::boost::fund_referee_boost(ctx, amount, boost_per_referee)
+    }
+
+    /// Withdraws unused lamports from the signer's boost escrow; allowed at any time.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - participant: The signer's participant account
+    ///   - boost_escrow: The signer's boost escrow PDA
+    ///   - user: The referrer withdrawing (signer)
+    /// * `amount` - Lamports to withdraw
+    ///
+    /// # Errors
+    /// * `InvalidBoostAmount` - If the amount is zero
+    /// * `InsufficientBoostBalance` - If the escrow holds less than `amount`
+    pub fn withdraw_referee_boost(ctx: Context<WithdrawRefereeBoost>, amount: u64) -> Result<()> {
+        instructions::boost::withdraw_referee_boost(ctx, amount)
+    }
+
+    /// Escrows the signer's SOL to match the referral rewards of a program it does not run.
+    ///
+    /// Anyone can sponsor a program. Each referral credited while the offer and the referrer's wallet are passed
+    /// to `join_through_referral` pays the referrer `match_bps` basis points of its reward from the offer, straight
+    /// to its wallet, until the budget runs out; the program's own accounting never sees the match. A sponsor has
+    /// one offer per program: funding again tops it up and replaces the match.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - match_offer: The signer's match offer PDA (created on first funding)
+    ///   - sponsor: The wallet funding the match (signer)
+    ///   - system_program: The system program
+    /// * `match_bps` - The match in basis points of each referral reward, at most 10_000
+    /// * `budget` - Lamports to escrow
+    ///
+    /// # Errors
+    /// * `InsufficientDeposit` - If the budget is zero
+    /// * `InvalidMatchBps` - If the match is zero or above `MAX_MATCH_BPS`
+    /// * `ProgramEnded` - If the program's end time has passed
+    /// * `ProgramClosing` - If the program is pending closure
+    pub fn create_match_offer(ctx: Context<CreateMatchOffer>, match_bps: u16, budget: u64) -> Result<()> {
+        instructions::sponsor_match::create_match_offer(ctx, match_bps, budget)
+    }
+
+    /// Cancels the signer's match offer, returning its unspent budget and rent; allowed at any time.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - match_offer: The signer's match offer PDA
+    ///   - sponsor: The wallet that funded the offer (signer)
+    pub fn cancel_match_offer(ctx: Context<CancelMatchOffer>) -> Result<()> {
+        instructions::sponsor_match::cancel_match_offer(ctx)
+    }
+
+    /// Closes spent auxiliary accounts of a program for a share of their rent; anyone can call it.
+    ///
+    /// Claimed invites and exhausted match offers can be closed, up to 10 per call, and while the program is
+    /// closing its other match offers and its boost escrows too. The caller receives the program's
+    /// `cleanup_bounty_bps` of each account's rent (10% by default) and the rent payer recorded on the account the
+    /// rest, along with any funds it still escrows. One account that is not closable fails the whole batch.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - caller: The wallet cleaning up and receiving the bounty (signer)
+    ///   - remaining accounts: Each account to close followed by the wallet that paid its rent
+    ///
+    /// # Errors
+    /// * `InvalidCleanupBatch` - If the batch is empty, too large, repeats an account or names the wrong rent payer
+    /// * `AccountNotClosable` - If an account is not a closable auxiliary account of the program
+    pub fn cleanup<'info>(ctx: Context<'_, '_, 'info, 'info, Cleanup<'info>>) -> Result<()> {
+        instructions::cleanup::cleanup(ctx)
+    }
+
+    /// Puts a program under participant governance; one-way.
+    ///
+    /// From then on a settings update that lowers a reward, the revenue share or the trailing commission, adds or
+    /// lowers the reward cap, or brings the end time forward is held for participants to vote on for 7 days.
+    /// Each participant votes with its credited referrals, and the change passes once approvals exceed
+    /// `approval_threshold_percent` of the program's credited referrals. Other updates still apply at once.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - authority: The program authority (signer)
+    /// * `approval_threshold_percent` - The share of credited referrals approvals must exceed, from 1 to 99
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `GovernanceAlreadyEnabled` - If the program is already governed
+    /// * `InvalidApprovalThreshold` - If the threshold is not between 1 and 99 percent
+    pub fn enable_governance(ctx: Context<EnableGovernance>, approval_threshold_percent: u8) -> Result<()> {
+        instructions::governance::enable_governance(ctx, approval_threshold_percent)
+    }
+
+    /// Votes for or against a governed program's pending settings change, weighed by the signer's credited
+    /// referrals; once per participant and change.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - pending_change: The program's pending change PDA
+    ///   - participant: The signer's participant account
+    ///   - change_vote: The vote PDA, created by the vote
+    ///   - user: The voting participant (signer)
+    ///   - system_program: The system program
+    /// * `approve` - Whether to approve the change
+    ///
+    /// # Errors
+    /// * `VotingClosed` - If the change is past its deadline, approved or rejected
+    /// * `ParticipantRotated` - If the participant account was rotated to a new owner
+    /// * `ParticipantLeft` - If the participant left the program
+    /// * `NoVotingWeight` - If the participant has no credited referrals
+    pub fn vote_change(ctx: Context<VoteChange>, approve: bool) -> Result<()> {
+        instructions::governance::vote_change(ctx, approve)
+    }
+
+    /// Applies an approved pending settings change and closes it; anyone can call it.
+    ///
+    /// The settings are validated again and recorded in the program's next `SettingsChangeRecord`, whose rent the
+    /// caller pays. The pending change's rent goes back to the authority that proposed it.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - eligibility_criteria: The program's eligibility criteria
+    ///   - network_config: The network config PDA
+    ///   - pending_change: The program's pending change PDA
+    ///   - proposer: The authority that proposed the change
+    ///   - settings_change: The PDA recording the change
+    ///   - caller: The wallet executing the change (signer)
+    ///   - event_queue: The program's event queue (optional; appended to when supplied)
+    ///   - system_program: The system program
+    ///
+    /// # Errors
+    /// * `ChangeNotApproved` - If participants have not approved the change
+    /// * Any error of `update_program_settings` validation - If the settings are no longer valid
+    pub fn execute_change(ctx: Context<ExecuteChange>) -> Result<()> {
+        instructions::governance::execute_change(ctx)
+    }
+
+    /// Discards a pending settings change that was rejected or missed its deadline; anyone can call it.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - pending_change: The program's pending change PDA
+    ///   - proposer: The authority that proposed the change, receiving its rent
+    ///   - caller: Anyone (signer)
+    ///
+    /// # Errors
+    /// * `ChangeNotVoid` - If the change is approved or can still be approved
+    pub fn void_change(ctx: Context<VoidChange>) -> Result<()> {
+        instructions::governance::void_change(ctx)
+    }
+
+    /// Splits the signer's future claims across up to 5 destinations in fixed proportions.
+    ///
+    /// The shares are in basis points and must sum to exactly 10_000. While the splitter exists, every claim
+    /// variant pays each destination its share, rounded down, with the rounding remainder going to the first
+    /// destination; the destinations (or their token accounts) follow the claim's accounts in the order configured.
+    /// Configuring again replaces the entries.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - participant: The signer's participant account
+    ///   - claim_splitter: The splitter PDA (created on first configuration)
+    ///   - user: The participant (signer)
+    ///   - system_program: The system program
+    /// * `entries` - The destinations and their shares in basis points
+    ///
+    /// # Errors
+    /// * `ParticipantRotated` - If the participant account was rotated to a new owner
+    /// * `ParticipantLeft` - If the participant left the program
+    /// * `InvalidClaimSplitter` - If the entries are empty, more than 5, repeat a destination, hold a zero share or
+    ///   do not sum to 10_000 basis points
+    pub fn configure_splitter(ctx: Context<ConfigureSplitter>, entries: Vec<state::SplitterEntry>) -> Result<()> {
+        instructions::claim_splitter::configure_splitter(ctx, entries)
+    }
+
+    /// Removes the signer's claim splitter and returns its rent; claims pay the participant alone again.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - participant: The signer's participant account
+    ///   - claim_splitter: The splitter PDA
+    ///   - user: The participant (signer)
+    pub fn remove_splitter(ctx: Context<RemoveSplitter>) -> Result<()> {
+        instructions::claim_splitter::remove_splitter(ctx)
+    }
+
+    /// Takes the signer out of the program, closing its participant account and returning its rent.
+    ///
+    /// The participant stops counting toward its referrer's `active_referee_count`. A participant whose own count
+    /// is non-zero cannot close its account, since its referees still reference it for trailing commissions;
+    /// with `force` the account is instead kept as a tombstone (`left` set) that is credited nothing and accepts
+    /// no referrals, and leaving again closes it once the count reaches zero. A rotated referrer is followed to the
+    /// account it was rotated to, which carries the count.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - participant: The signer's participant account
+    ///   - referrer: The participant's referrer (required if it joined through a referral)
+    ///   - rotated_referrer: The account the referrer was rotated to (required if the referrer was rotated)
+    ///   - referee_receipt: The referee receipt of the wallet that joined, the one the account was rotated from if
+    ///     it was rotated in (required if it joined through a referral)
+    ///   - user: The participant (signer)
+    /// * `force` - Whether to leave a tombstone if referees still reference the account
+    ///
+    /// # Errors
+    /// * `ParticipantRotated` - If the participant account was rotated to a new owner
+    /// * `ActiveRefereesRemain` - If referees still reference the participant and `force` is not set
+    /// * `PendingRewardsRemain` - If the participant has pending rewards
+    /// * `InvalidReferrer` - If the referrer or referee receipt is missing for a participant that was referred, or
+    ///   the receipt is not that of the wallet that joined
+    /// * `ParticipantRotated` - If the referrer was rotated and the account it was rotated to is missing
+    pub fn leave_program(ctx: Context<LeaveProgram>, force: bool) -> Result<()> {
+        instructions::leave_program::leave_program(ctx, force)
+    }
+
+    /// Names the signer's referrer after it joined directly, crediting the referral as `join_through_referral`
+    /// would have.
+    ///
+    /// Allowed within the program's `attribution_grace_seconds` of joining, for a participant without a referrer
+    /// that has earned nothing yet and was never credited as a referee in the program. The referee receipt is
+    /// created, its rent paid as the program's `rent_payer_mode` says.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - eligibility_criteria: The program's eligibility criteria
+    ///   - participant: The signer's participant account
+    ///   - referrer: The participant named as referrer
+    ///   - split_recipient: The referrer's payout split recipient (required if it has one)
+    ///   - referee_receipt: The signer's referee receipt PDA, created here
+    ///   - sponsor_vault: The program's sponsor vault (required in `RENT_PAYER_SPONSOR` mode)
+    ///   - referrer_collection_metadata, referrer_collection_nft: As for `join_through_referral`
+    ///   - referrer_token_account, referee_token_account: As for `join_through_referral`
+    ///   - referrer_upline: The referrer's referrer (required if a milestone commission is due)
+    ///   - user: The participant (signer)
+    ///   - event_queue: Optional event queue of the program
+    ///   - system_program: The system program
+    ///
+    /// # Errors
+    /// * `AttributionNotAllowed` - If the participant has a referrer, has earned anything, or was already credited
+    ///   as a referee in the program
+    /// * `AttributionWindowClosed` - If the program has no grace period or it has passed since the participant
+    ///   joined
+    /// * `InvalidReferrer` - If the referrer belongs to another program or is the participant itself
+    /// * `LinkProofRequired` - If the program has a link signer
+    /// * Any referrer or referee check of `join_through_referral`
+    pub fn set_referrer_late(ctx: Context<SetReferrerLate>) -> Result<()> {
+        instructions::late_attribution::set_referrer_late(ctx)
+    }
+
+    /// Creates the participant account of a partner wallet ahead of launch, its rent paid by the authority.
+    ///
+    /// The seeded account can be named as a referrer at once and accrues rewards as usual, but cannot claim until
+    /// its owner activates it with `activate_seeded`. The program need not be active yet.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - participant: The owner's participant PDA, created here
+    ///   - authority: The program authority (signer)
+    ///   - system_program: The system program
+    /// * `owner` - The partner wallet the account is seeded for
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `ProgramEnded`, `ProgramClosing` - If the program no longer takes participants
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    pub fn seed_participant(ctx: Context<SeedParticipant>, owner: Pubkey) -> Result<()> {
+        instructions::seeding::seed_participant(ctx, owner)
+    }
+
+    /// Activates the signer's seeded participant account so it can claim, writing its referral link again in the
+    /// program's current link format.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - participant: The signer's seeded participant account
+    ///   - user: The owner of the seeded account (signer)
+    ///
+    /// # Errors
+    /// * `NotAwaitingActivation` - If the account was not seeded or is already activated
+    pub fn activate_seeded(ctx: Context<ActivateSeeded>) -> Result<()> {
+        instructions::seeding::activate_seeded(ctx)
+    }
+
+    /// Closes a seeded participant its owner never activated and that never earned anything, returning its rent to
+    /// the authority once the program's `seed_reclaim_seconds` have passed since it was seeded.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - participant: The seeded participant account to close
+    ///   - authority: The program authority (signer), receiving the rent
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `InvalidReferrer` - If the participant belongs to another program
+    /// * `NotAwaitingActivation` - If the account was not seeded or its owner activated it
+    /// * `SeedNotReclaimable` - If the reclaim period has not passed or the account has been used
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    pub fn reclaim_seeded_participant(ctx: Context<ReclaimSeededParticipant>) -> Result<()> {
+        instructions::seeding::reclaim_seeded_participant(ctx)
+    }
+
+    /// Adds lamports to the program's sponsor vault, which pays the rent of referee receipts when the program's
+    /// `rent_payer_mode` is `RENT_PAYER_SPONSOR`. Anyone can fund it.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - sponsor_vault: The program's sponsor vault PDA
+    ///   - funder: The wallet funding the vault (signer)
+    ///   - system_program: The system program
+    /// * `amount` - Lamports to deposit
+    ///
+    /// # Errors
+    /// * `InsufficientDeposit` - If the amount is zero
+    pub fn fund_sponsor_vault(ctx: Context<FundSponsorVault>, amount: u64) -> Result<()> {
+        instructions::sponsor::fund_sponsor_vault(ctx, amount)
+    }
+
+    /// Withdraws lamports from the program's sponsor vault to the authority.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - sponsor_vault: The program's sponsor vault PDA
+    ///   - authority: The program authority (signer)
+    ///   - system_program: The system program
+    /// * `amount` - Lamports to withdraw
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `InsufficientFunds` - If the amount is zero
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    /// * `ProgramFrozen` - If the guardian froze the program
+    pub fn withdraw_sponsor_vault(ctx: Context<WithdrawSponsorVault>, amount: u64) -> Result<()> {
+        instructions::sponsor::withdraw_sponsor_vault(ctx, amount)
+    }
+
+    /// Closes a referral program in two phases, protecting live campaigns from accidental closure.
+    ///
+    /// The first call records the request and starts a 72 hour grace period during which joins and deposits
+    /// are rejected but participants can still claim, anyone can close the program's boost escrows and match
+    /// offers with `cleanup`, and `cancel_closure` restores normal operation. A call
+    /// after the grace period, once the program is paused or has ended, sweeps the SOL vault and the sponsor vault
+    /// to the authority, writes a permanent `FinalReport` of the program's figures and closes the program and
+    /// criteria accounts, returning their rent.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - eligibility_criteria: The program's eligibility criteria (closed in the final phase)
+    ///   - vault: The program's SOL vault PDA (swept in the final phase)
+    ///   - sponsor_vault: The program's sponsor vault PDA (swept in the final phase)
+    ///   - authority_meta: The authority's metadata PDA
+    ///   - final_report: The program's final report PDA (created in the final phase)
+    ///   - authority: The program authority (signer, receives the vaults' balances and rent, pays the report's rent)
+    ///   - system_program: The system program
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `ClosureGracePeriodActive` - If closure was requested less than `CLOSURE_GRACE_PERIOD` seconds ago
+    /// * `ProgramStillActive` - If the grace period has elapsed but the program is active and has not ended
+    /// * `OutstandingRewards` - If credited rewards have not been claimed or clawed back
+    /// * `ProgramFrozen` - If the guardian froze the program
+    /// * `TokenVaultStillOpen` - If the token vault has not been closed with `close_token_vault`
+    /// * `ContestPrizesOutstanding` - If contest prizes have not been claimed, or the contest escrow's other funds
+    ///   not withdrawn with `withdraw_contest_leftover`
+    /// * `WithdrawalPending` - If a queued withdrawal has not been executed or cancelled
+    /// * `EscrowsOutstanding` - If boost escrows or match offers of the program have not been closed with `cleanup`
+    /// * `FinalReportExists` - If a program closed earlier at the same address already left a report
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    /// * `SettingsTimelocked` - If the creator's settings lock has not lapsed yet
+    pub fn close_referral_program(ctx: Context<CloseReferralProgram>) -> Result<()> {
+        instructions::close_program::close_referral_program(ctx)
+    }
+
+    /// Cancels a pending closure, reopening the program to joins and deposits.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - authority: The program authority (signer)
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the signer is not the program authority
+    /// * `NoClosurePending` - If no closure has been requested
+    /// * `ProgramAbandoned` - If the program was declared abandoned
+    pub fn cancel_closure(ctx: Context<CancelClosure>) -> Result<()> {
+        instructions::close_program::cancel_closure(ctx)
+    }
+
+    /// Performs whatever time-based maintenance is due on a program; callable by anyone, meant for keeper bots.
+    ///
+    /// Fires the runway alerts due, stops referrals on a program that can no longer fund a referral reward or
+    /// whose end time has passed, resumes them on a live program funded again, and finalizes a closure whose
+    /// grace period has elapsed exactly as `close_referral_program` would. Actions that are not due are
+    /// skipped, so repeated cranks are harmless. Every call emits one `MaintenancePerformed` event, and the
+    /// bitmask of the `MAINTENANCE_*` actions performed is returned in the transaction return data.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - eligibility_criteria: The program's eligibility criteria (closed when a closure is finalized)
+    ///   - vault: The program's SOL vault PDA (swept when a closure is finalized)
+    ///   - authority: The program authority (receives the vault balance and rent when a closure is finalized)
+    ///   - authority_meta: The authority's metadata PDA
+    ///   - final_report: The program's final report PDA (created when a closure is finalized)
+    ///   - cranker: The caller (signer, pays the final report's rent)
+    ///   - system_program: The system program
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the authority account is not the program's authority
+    pub fn crank_maintenance(ctx: Context<CrankMaintenance>) -> Result<u8> {
+        instructions::maintenance::crank_maintenance(ctx)
+    }
+
+    /// Declares a program abandoned once its authority has not acted on it for its dormancy period; callable by
+    /// anyone.
+    ///
+    /// Every instruction the authority signs records the time on the program. Once `dormancy_period_seconds`
+    /// (`DEFAULT_DORMANCY_PERIOD` when 0) has passed since, the program can be declared abandoned: the authority
+    /// can no longer act on it, so its settings stay as they are for good, and a guardian freeze is lifted. Joins
+    /// and claims carry on until the funds or the program run out. Emits `ProgramAbandoned`.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - caller: Anyone (signer)
+    ///
+    /// # Errors
+    /// * `ProgramAbandoned` - If the program was already declared abandoned
+    /// * `ProgramNotDormant` - If the authority acted within the program's dormancy period
+    pub fn declare_abandoned(ctx: Context<DeclareAbandoned>) -> Result<()> {
+        instructions::abandonment::declare_abandoned(ctx)
+    }
+
+    /// Claims earned rewards for a participant in the referral program.
+    ///
+    /// This instruction calculates and transfers the earned rewards from the program vault
+    /// to the participant based on their referral performance. The reward amount is determined
+    /// by the participant's total referrals and program parameters.
+    ///
+    /// The claim is validated in full and the program's accounting updated before any lamports leave the
+    /// vault, so a failing check can never follow a transfer.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - eligibility_criteria: The program's eligibility criteria
+    ///   - participant: The participant's account
+    ///   - claim_splitter: The participant's claim splitter (optional; required while it exists)
+    ///   - vault: The program's vault
+    ///   - user: The participant claiming rewards (signer)
+    ///   - event_queue: The program's event queue (optional; appended to when supplied)
+    ///   - instructions_sysvar: The instructions sysvar (optional; required when the program only accepts direct
+    ///     claims)
+    ///   - region_attestation: The region attestor's attestation of the user (optional; required when the program
+    ///     checks regions on claims)
+    ///   - system_program: The system program
+    ///   - remaining accounts: The splitter's destination wallets in order, when the participant has a splitter
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the participant account is not the signer's account in the program
+    /// * `ClaimSplitterMismatch` - If the splitter or its destinations were not passed as configured
+    /// * `CpiClaimNotAllowed` - If the program only accepts direct claims and the claim was made through another
+    ///   program or without the instructions sysvar
+    /// * `ProgramInactive` - If the program has never gone live
+    /// * `NoRewardsAvailable` - If the participant has nothing to claim
+    /// * `RewardsLocked` - If the program's locked period has not elapsed since the participant joined
+    /// * `ParticipantRotated` - If the participant account was rotated to a new owner
+    /// * `ParticipantLeft` - If the participant left the program
+    /// * `ProgramFrozen` - If the program's guardian froze it
+    /// * `InsufficientVaultBalance` - If the rewards exceed the program's available funds or the vault's balance
+    /// * `NumericOverflow` - If calculations result in overflow
+    /// * `ClaimGuardViolation` - If the payout moved lamports other than from the vault to the user
+    /// * `RegionAttestationRequired`, `InvalidRegionAttestation`, `RegionEmbargoed` - If the program checks regions
+    ///   on claims and the user's attestation is missing, invalid or names an embargoed region
+    pub fn claim_rewards<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimRewards<'info>>) -> Result<()> {
+        instructions::rewards::process_claim_rewards(ctx)
+    }
+
+    /// Claims a participant's pending rewards before the program's locked period has passed, forfeiting the
+    /// program's early redemption fee.
+    ///
+    /// While the rewards are locked, `early_redemption_fee` basis points of them are forfeited and the rest is
+    /// paid as `claim_rewards` would. The fee stays in the vault, returned to the program's uncommitted funds, and
+    /// is reported in the participant's reward statement. Once the rewards have unlocked they are claimed in full.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context for the ClaimRewards instruction
+    ///
+    /// # Errors
+    /// * `EarlyRedemptionDisabled` - If the rewards are locked and the program charges no early redemption fee
+    /// * Every error of `claim_rewards` but `RewardsLocked`
+    pub fn early_claim_rewards<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimRewards<'info>>) -> Result<()> {
+        instructions::rewards::process_early_claim_rewards(ctx)
+    }
+
+    /// Claims a SOL program's rewards as wrapped SOL into the user's associated token account of the native mint.
+    ///
+    /// Follows the same rules and bookkeeping as `claim_rewards`; the lamports leave the vault for the token
+    /// account, which is created when missing, and `sync_native` turns them into wrapped SOL tokens.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - eligibility_criteria: The program's eligibility criteria
+    ///   - participant: The participant's account
+    ///   - claim_splitter: The participant's claim splitter (optional; required while it exists)
+    ///   - vault: The program's vault
+    ///   - native_mint: The wrapped SOL mint
+    ///   - user_wsol_account: The user's associated wrapped SOL token account (created if needed)
+    ///   - user: The participant claiming rewards (signer)
+    ///   - event_queue: The program's event queue (optional; appended to when supplied)
+    ///   - instructions_sysvar: The instructions sysvar (optional; required when the program only accepts direct
+    ///     claims)
+    ///   - region_attestation: The region attestor's attestation of the user (optional; required when the program
+    ///     checks regions on claims)
+    ///   - token_program: The token program
+    ///   - associated_token_program: The associated token account program
+    ///   - system_program: The system program
+    ///   - remaining accounts: A wrapped SOL token account owned by each of the splitter's destinations, in order,
+    ///     when the participant has a splitter; the user's account is then created but not paid
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the participant account is not the signer's account in the program
+    /// * `ClaimSplitterMismatch` - If the splitter or its destinations' token accounts were not passed as configured
+    /// * `InvalidTokenMint` - If the program is a token program or the mint is not the native mint
+    /// * `ConstraintTokenMint`, `ConstraintTokenOwner` - If the token account is not the user's wrapped SOL account
+    /// * `CpiClaimNotAllowed` - If the program only accepts direct claims and the claim was made through another
+    ///   program or without the instructions sysvar
+    /// * `ProgramInactive` - If the program has never gone live
+    /// * `NoRewardsAvailable` - If the participant has nothing to claim
+    /// * `RewardsLocked` - If the program's locked period has not elapsed since the participant joined
+    /// * `ParticipantRotated` - If the participant account was rotated to a new owner
+    /// * `ParticipantLeft` - If the participant left the program
+    /// * `ProgramFrozen` - If the program's guardian froze it
+    /// * `InsufficientVaultBalance` - If the rewards exceed the program's available funds or the vault's balance
+    /// * `ClaimGuardViolation` - If the payout moved lamports other than from the vault to the token account, or the
+    ///   wrapped SOL balance did not rise by exactly the claimed amount
+    /// * `RegionAttestationRequired`, `InvalidRegionAttestation`, `RegionEmbargoed` - If the program checks regions
+    ///   on claims and the user's attestation is missing, invalid or names an embargoed region
+    pub fn claim_rewards_wrapped<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimRewardsWrapped<'info>>) -> Result<()> {
+        instructions::rewards::process_claim_rewards_wrapped(ctx)
+    }
+
+    /// Claims a participant's pending rewards from a token program's vault into their token account.
+    ///
+    /// Token claims go through the same gates and settlement as `claim_rewards`, so a SOL and a token program
+    /// in the same state pay out the same amount in raw units.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - eligibility_criteria: The program's eligibility criteria
+    ///   - participant: The participant's account
+    ///   - claim_splitter: The participant's claim splitter (optional; required while it exists)
+    ///   - token_vault: The program's token vault PDA
+    ///   - user_token_account: The participant's token account of the program's mint
+    ///   - user: The participant claiming rewards (signer)
+    ///   - event_queue: The program's event queue (optional; appended to when supplied)
+    ///   - instructions_sysvar: The instructions sysvar (optional; required when the program only accepts direct
+    ///     claims)
+    ///   - region_attestation: The region attestor's attestation of the user (optional; required when the program
+    ///     checks regions on claims)
+    ///   - token_program: The token program
+    ///   - remaining accounts: A token account of the program's mint owned by each of the splitter's destinations,
+    ///     in order, when the participant has a splitter
+    ///
+    /// # Errors
+    /// * `InvalidAuthority` - If the participant account is not the signer's account in the program
+    /// * `ClaimSplitterMismatch` - If the splitter or its destinations' token accounts were not passed as configured
+    /// * `CpiClaimNotAllowed` - If the program only accepts direct claims and the claim was made through another
+    ///   program or without the instructions sysvar
+    /// * `InvalidTokenMint` - If the program is a SOL program
+    /// * `InvalidTokenAccounts` - If the destination is not a token account of the program's mint owned by the user
+    /// * `ProgramInactive` - If the program has never gone live
+    /// * `NoRewardsAvailable` - If the participant has nothing to claim
+    /// * `RewardsLocked` - If the program's locked period has not elapsed since the participant joined
+    /// * `ParticipantRotated` - If the participant account was rotated to a new owner
+    /// * `ParticipantLeft` - If the participant left the program
+    /// * `ProgramFrozen` - If the program's guardian froze it
+    /// * `InsufficientVaultBalance` - If the rewards exceed the program's available funds or the vault's balance
+    /// * `ClaimGuardViolation` - If the token vault and destination did not move by exactly the claimed amount, or
+    ///   the participant's lamports changed
+    /// * `RegionAttestationRequired`, `InvalidRegionAttestation`, `RegionEmbargoed` - If the program checks regions
+    ///   on claims and the user's attestation is missing, invalid or names an embargoed region
+    pub fn claim_token_rewards<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimTokenRewards<'info>>) -> Result<()> {
+        instructions::rewards::process_claim_token_rewards(ctx)
+    }
+
+    /// Claims a participant's pending rewards from a token program's vault before the program's locked period has
+    /// passed, forfeiting the program's early redemption fee.
+    ///
+    /// The token counterpart of `early_claim_rewards`: while the rewards are locked, `early_redemption_fee` basis
+    /// points of them are forfeited and the rest is paid as `claim_token_rewards` would. The fee stays in the token
+    /// vault, returned to the program's uncommitted funds. Once the rewards have unlocked they are claimed in full.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context for the ClaimTokenRewards instruction
+    ///
+    /// # Errors
+    /// * `EarlyRedemptionDisabled` - If the rewards are locked and the program charges no early redemption fee
+    /// * Every error of `claim_token_rewards` but `RewardsLocked`
+    pub fn early_claim_token_rewards<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimTokenRewards<'info>>,
+    ) -> Result<()> {
+        instructions::rewards::process_early_claim_token_rewards(ctx)
+    }
+
+    /// Reports whether a participant can claim rewards right now, and why not.
+    ///
+    /// This read-only instruction evaluates the same gates as `claim_rewards` and returns a
+    /// `ClaimEligibility` in the transaction return data: a bitmask of the blocking gates plus the
+    /// earliest time the time-based gates lift. Clients simulate it to explain blocked claims.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - eligibility_criteria: The program's eligibility criteria
+    ///   - participant: The participant's account (must belong to the program)
+    ///   - region_attestation: The region attestor's attestation of the participant's owner (optional; the region
+    ///     gates report it missing when the program checks regions on claims)
+    ///   - instructions_sysvar: The instructions sysvar (optional; a program that only accepts direct claims reports
+    ///     the claim as not direct without it, or when `check_claim` itself is invoked through a CPI)
+    pub fn check_claim(ctx: Context<CheckClaim>) -> Result<ClaimEligibility> {
+        instructions::rewards::check_claim(ctx)
+    }
+
+    /// Explains a participant's balance category by category.
+    ///
+    /// This read-only instruction returns a `RewardStatementV1` in the transaction return data: the gross
+    /// rewards credited, what cap clamping and clawbacks removed, what was claimed, and how the rest splits
+    /// into locked and claimable, plus referee boosts paid straight to the wallet. Clients simulate it to
+    /// answer balance disputes.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - eligibility_criteria: The program's eligibility criteria
+    ///   - participant: The participant's account (must belong to the program)
+    ///
+    /// # Errors
+    /// * `ParticipantRotated` - If the participant account was rotated; its statement is the new account's
+    /// * `ParticipantLeft` - If the participant left the program
+    pub fn get_reward_statement(ctx: Context<GetRewardStatement>) -> Result<RewardStatementV1> {
+        instructions::statement::get_reward_statement(ctx)
+    }
+
+    /// Exports a participant's history for wallets in a single call.
+    ///
+    /// This read-only instruction returns a `ParticipantHistoryV1` in the transaction return data: the
+    /// participant's headline figures plus one entry per referee receipt passed in the remaining accounts, for
+    /// the referrals it made and its own join, sorted by when they were credited. Only the receipts passed in
+    /// appear, and those that do not fit in the return data are left out with `truncated` set.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - participant: The participant's account (must belong to the program)
+    ///   - remaining accounts: Up to 20 of the participant's referee receipts
+    ///
+    /// # Errors
+    /// * `ParticipantRotated` - If the participant account was rotated; its history is the new account's
+    /// * `ParticipantLeft` - If the participant left the program
+    /// * `InvalidHistoryBatch` - If the batch holds more than 20 receipts or repeats one
+    /// * `InvalidReferralRecord` - If a receipt is of another program or does not name the participant
+    pub fn get_participant_history<'info>(
+        ctx: Context<'_, '_, 'info, 'info, GetParticipantHistory<'info>>,
+    ) -> Result<state::ParticipantHistoryV1> {
+        instructions::history::get_participant_history(ctx)
+    }
+
+    /// Previews what a new referee joining through a referrer now would credit, via return data.
+    ///
+    /// Runs the same credit calculation as `join_through_referral` against the current state without
+    /// mutating anything; call it through a simulated transaction. It also reports whether
+    /// the referrer or the referee fails the program's token requirements and returns the referrer's
+    /// landing parameters for the client to apply at sign-up.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - eligibility_criteria: The program's eligibility criteria
+    ///   - referrer: The referrer's participant account
+    ///   - boost_escrow: The referrer's boost escrow (optional)
+    ///   - referrer_token_account: The referrer's token account of the mint required of referrers (optional)
+    ///   - referee_token_account: The prospective referee's token account of the mint required of referees
+    ///     (optional)
+    ///
+    /// # Errors
+    /// * `ProgramInactive` - If the program is not active
+    /// * `ProgramEnded` - If the program's end time has passed
+    /// * `ProgramClosing` - If the program is pending closure
+    /// * `ParticipantRotated` - If the referrer was rotated to a new owner
+    /// * `ParticipantLeft` - If the referrer left the program
+    /// * `InvalidBoostEscrow` - If the boost escrow does not belong to the referrer
+    pub fn preview_referral(ctx: Context<PreviewReferral>) -> Result<ReferralPreview> {
+        instructions::preview::preview_referral(ctx)
+    }
+
+    /// Reports which version of the program is deployed and what it supports.
+    ///
+    /// This read-only instruction takes no accounts and returns a `ProgramVersion` in the transaction
+    /// return data: the crate's semver, the layout version of every state account and the bitmask of
+    /// `FEATURE_*` constants this build supports. Clients simulate it once per deployment and cache it.
+    pub fn get_program_version(ctx: Context<GetProgramVersion>) -> Result<ProgramVersion> {
+        instructions::version::get_program_version(ctx)
+    }
+
+    /// Audits a program's accounting invariants; callable by anyone, meant for keeper bots.
+    ///
+    /// This read-only instruction checks the program against its vault: the vault holds the available funds and
+    /// reserve within `AUDIT_DUST_TOLERANCE`, those funds and the distributed rewards add up to no more than
+    /// was deposited, the referral counters agree and the cached end time matches the eligibility criteria. The
+    /// bitmask of the `AUDIT_*` invariants violated is returned in the transaction return data, zero for a sound
+    /// program, and an `AuditFailed` event is emitted when it is not zero. Nothing is written.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context containing:
+    ///   - referral_program: The program account
+    ///   - eligibility_criteria: The program's eligibility criteria
+    ///   - vault: The program's SOL vault PDA
+    ///   - token_vault: The program's token vault PDA (required for a token program whose token vault was
+    ///     initialized)
+    ///
+    /// # Errors
+    /// * `InvalidTokenAccounts` - If a token program's initialized token vault was not passed
+    pub fn audit_program(ctx: Context<AuditProgram>) -> Result<u8> {
+        instructions::audit::audit_program(ctx)
+    }
+
+    /// Runs the test-only instructions of `instructions::test_utils`.
+    ///
+    /// `#[program]` dispatches every handler it is given, whatever its `cfg` attributes, so the test-only
+    /// instructions are kept out of it and reach the program through this fallback instead. Builds without the
+    /// `test-utils` feature reject them like any other unknown instruction.
+    ///
+    /// # Errors
+    /// * `InstructionFallbackNotFound` - If the instruction is unknown, or test-only in a build without `test-utils`
+    pub fn fallback<'info>(program_id: &Pubkey, accounts: &'info [AccountInfo<'info>], data: &[u8]) -> Result<()> {
+        #[cfg(feature = "test-utils")]
+        return instructions::test_utils::dispatch(program_id, accounts, data);
+        #[cfg(not(feature = "test-utils"))]
+        {
+            let _ = (program_id, accounts, data);
+            Err(anchor_lang::error::ErrorCode::InstructionFallbackNotFound.into())
+        }
     }
 }
@@ -0,0 +1,104 @@
+//! Compact binary payload of a referral link, for QR codes.
+//!
+//! A full referral URL spends most of a QR code's capacity on the domain and base58 keys, and ties printed codes to
+//! the domain. The payload carries the same referral in at most `MAX_LINK_PAYLOAD_LEN` bytes:
+//!
+//! | bytes   | field                                                        |
+//! |---------|--------------------------------------------------------------|
+//! | 1       | version, `LINK_PAYLOAD_VERSION`                              |
+//! | 32      | referral program                                             |
+//! | 32      | referrer's owner                                             |
+//! | 1       | code length, 0 for none                                      |
+//! | 0..=12  | code, printable ASCII                                        |
+//! | 1       | CRC-8 of every byte before it, catching scanning errors      |
+//!
+//! Clients build payloads with [`encode_referral`] and `join_with_payload` decodes them on chain with
+//! [`decode_referral`]. The codec only uses `core` and `alloc`, so the same module serves both.
+
+use crate::{
+    constants::{LINK_PAYLOAD_VERSION, MAX_LINK_CODE_LEN, SOURCE_TAG_LEN},
+    error::ReferralError,
+};
+use anchor_lang::prelude::*;
+
+/// The length of a payload without a code.
+const BASE_PAYLOAD_LEN: usize = 1 + 32 + 32 + 1 + 1;
+
+/// The longest payload, carrying a code of `MAX_LINK_CODE_LEN` bytes.
+pub const MAX_LINK_PAYLOAD_LEN: usize = BASE_PAYLOAD_LEN + MAX_LINK_CODE_LEN;
+
+/// The components of a referral payload.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodedReferral {
+    /// The referral program joined
+    pub program: Pubkey,
+    /// The owner of the referrer's participant account
+    pub referrer_owner: Pubkey,
+    /// The campaign code the link carries, if any
+    pub code: Option<String>,
+}
+
+impl DecodedReferral {
+    /// Returns the code as a zero-padded source tag, the attribution a join through the payload records.
+    pub fn source_tag(&self) -> Option<[u8; SOURCE_TAG_LEN]> {
+        self.code.as_ref().map(|code| {
+            let mut tag = [0u8; SOURCE_TAG_LEN];
+            tag[..code.len()].copy_from_slice(code.as_bytes());
+            tag
+        })
+    }
+}
+
+/// Encodes a referral to `referrer_owner` in `program` as a payload, with an optional campaign code.
+///
+/// # Panics
+/// If the code is longer than `MAX_LINK_CODE_LEN` bytes or not printable ASCII; such a payload would never decode.
+/// An empty code encodes as none.
+pub fn encode_referral(program: &Pubkey, referrer_owner: &Pubkey, code: Option<&str>) -> Vec<u8> {
+    let code = code.map(str::as_bytes).unwrap_or_default();
+    assert!(code.len() <= MAX_LINK_CODE_LEN && is_valid_code(code), "invalid referral link code");
+    let mut payload = Vec::with_capacity(BASE_PAYLOAD_LEN + code.len());
+    payload.push(LINK_PAYLOAD_VERSION);
+    payload.extend_from_slice(program.as_ref());
+    payload.extend_from_slice(referrer_owner.as_ref());
+    payload.push(code.len() as u8);
+    payload.extend_from_slice(code);
+    payload.push(crc8(&payload));
+    payload
+}
+
+/// Decodes a payload built by `encode_referral`.
+///
+/// # Errors
+/// * `ReferralPayloadChecksum` - If the checksum byte does not match the rest of the payload
+/// * `InvalidReferralPayload` - If the payload is of another version, is truncated or overlong, or its code is
+///   not printable ASCII
+pub fn decode_referral(payload: &[u8]) -> Result<DecodedReferral> {
+    let (&checksum, body) = payload.split_last().ok_or(ReferralError::InvalidReferralPayload)?;
+    require!(body.len() >= BASE_PAYLOAD_LEN - 1, ReferralError::InvalidReferralPayload);
+    require!(crc8(body) == checksum, ReferralError::ReferralPayloadChecksum);
+    require!(body[0] == LINK_PAYLOAD_VERSION, ReferralError::InvalidReferralPayload);
+
+    let program = Pubkey::try_from(&body[1..33]).map_err(|_| ReferralError::InvalidReferralPayload)?;
+    let referrer_owner = Pubkey::try_from(&body[33..65]).map_err(|_| ReferralError::InvalidReferralPayload)?;
+    let code = &body[66..];
+    require!(
+        usize::from(body[65]) == code.len() && code.len() <= MAX_LINK_CODE_LEN && is_valid_code(code),
+        ReferralError::InvalidReferralPayload
+    );
+    let code = (!code.is_empty()).then(|| code.iter().map(|&byte| char::from(byte)).collect());
+    Ok(DecodedReferral { program, referrer_owner, code })
+}
+
+/// Returns true if `code` is printable ASCII without spaces; the empty code stands for none
+fn is_valid_code(code: &[u8]) -> bool {
+    code.iter().all(u8::is_ascii_graphic)
+}
+
+/// Returns the CRC-8 (polynomial 0x07) of `bytes`, the checksum ending every payload; it detects every single-byte
+/// error.
+pub fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |crc, &byte| {
+        (0..8).fold(crc ^ byte, |crc, _| if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 })
+    })
+}
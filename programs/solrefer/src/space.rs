@@ -0,0 +1,98 @@
+//! Account space.
+//!
+//! Every fixed-size account lists its fields with the most bytes each serializes to in a `LAYOUT`, and derives its
+//! `SIZE` from it with [`layout_size`], so the size cannot drift from the breakdown it is reviewed by. Each account
+//! must also fit a space budget: a field addition that takes an account past its budget fails the build, rather
+//! than quietly raising the rent every program, participant or referee pays for it. [`space_report`] renders every
+//! layout against its budget.
+//!
+//! Accounts sized by their contents, `SettingsChangeRecord` and `PendingChange`, work out their size as they are
+//! created and have no layout.
+
+use crate::state::*;
+
+/// A field of an account layout: its name and the most bytes it serializes to.
+pub type LayoutField = (&'static str, usize);
+
+/// Returns the total size of `layout` in bytes.
+pub const fn layout_size(layout: &[LayoutField]) -> usize {
+    let mut size = 0;
+    let mut index = 0;
+    while index < layout.len() {
+        size += layout[index].1;
+        index += 1;
+    }
+    size
+}
+
+/// Returns the serialized size of an `Option` of a value of `size` bytes: a tag byte, then the value.
+pub const fn option(size: usize) -> usize {
+    1 + size
+}
+
+/// A fixed-size account: its name, layout and space budget.
+#[derive(Clone, Copy, Debug)]
+pub struct AccountSpace {
+    pub name: &'static str,
+    pub layout: &'static [LayoutField],
+    /// The space the account may take including its discriminator, in bytes
+    pub budget: usize,
+}
+
+impl AccountSpace {
+    /// The space the account is created with, including its 8-byte discriminator.
+    pub const fn space(&self) -> usize {
+        8 + layout_size(self.layout)
+    }
+}
+
+/// Sets the space budget of each account, asserting at compile time that the account fits it, and lists the
+/// accounts in `ACCOUNTS`.
+macro_rules! space_budgets {
+    ($($account:ident: $budget:expr,)*) => {
+        $(
+            const _: () = assert!(
+                8 + $account::SIZE <= $budget,
+                concat!(stringify!($account), " outgrew its space budget; slim it down or raise the budget in space.rs")
+            );
+        )*
+
+        /// Every fixed-size account with its space budget.
+        pub const ACCOUNTS: &[AccountSpace] = &[
+            $(AccountSpace { name: stringify!($account), layout: $account::LAYOUT, budget: $budget },)*
+        ];
+    };
+}
+
+// Budgets in bytes of account space, discriminator included, leaving each account headroom for a few fields
+space_budgets! {
+    ReferralProgram: 1_024,
+    EligibilityCriteria: 512,
+    Participant: 768,
+    RefereeReceipt: 256,
+    Invite: 128,
+    NetworkConfig: 128,
+    FeeConfig: 256,
+    AuthorityMeta: 64,
+    WithdrawalRequest: 128,
+    MatchOffer: 128,
+    BoostEscrow: 160,
+    Contest: 320,
+    ClaimSplitter: 256,
+    ChangeVote: 128,
+    FinalReport: 256,
+    EventQueue: 2_048,
+}
+
+/// Returns a table of every account's layout, space and budget, for reviewing what a change costs.
+pub fn space_report() -> String {
+    let mut report = String::new();
+    for account in ACCOUNTS {
+        report += &format!("{}: {} of {} bytes\n", account.name, account.space(), account.budget);
+        report += &format!("  {:<40}{:>6}\n", "discriminator", 8);
+        for (name, size) in account.layout {
+            report += &format!("  {:<40}{:>6}\n", name, size);
+        }
+    }
+    report
+}
@@ -0,0 +1,58 @@
+use crate::space::{layout_size, LayoutField};
+use anchor_lang::prelude::*;
+
+/// SOL a referrer has staked to top up the sign-up bonus of everyone joining through their link.
+///
+/// The escrow holds its boost funds as lamports on top of its rent-exempt minimum and is accounted for
+/// separately from the program vault.
+///
+/// PDA with seeds: ["boost", referral_program.key(), participant.key()]
+#[account]
+#[derive(Default)]
+pub struct BoostEscrow {
+    /// The referral program the boost applies to
+    pub program: Pubkey,
+    /// The referrer participant account funding the boost
+    pub participant: Pubkey,
+    /// The wallet that funds and withdraws the boost
+    pub owner: Pubkey,
+    /// Lamports paid to each boosted referee
+    pub boost_per_referee: u64,
+    /// Lamports left for boosts and withdrawals
+    pub balance: u64,
+    /// Lamports paid out to referees so far
+    pub total_boosted: u64,
+    /// Number of referees boosted so far
+    pub referees_boosted: u64,
+    /// Bump seed for the escrow PDA
+    pub bump: u8,
+}
+
+impl BoostEscrow {
+    /// Version of the `BoostEscrow` account layout, bumped whenever its fields change.
+    pub const LAYOUT_VERSION: u8 = 1;
+
+    /// Each field of the `BoostEscrow` account and the most bytes it serializes to, discriminator excluded.
+    pub const LAYOUT: &'static [LayoutField] = &[
+        ("program", 32),
+        ("participant", 32),
+        ("owner", 32),
+        ("boost_per_referee", 8),
+        ("balance", 8),
+        ("total_boosted", 8),
+        ("referees_boosted", 8),
+        ("bump", 1),
+    ];
+
+    /// The size of the `BoostEscrow` account in bytes, excluding the discriminator.
+    pub const SIZE: usize = layout_size(Self::LAYOUT);
+
+    /// The boost the next referee receives: a full `boost_per_referee`, or nothing once the balance is short of it.
+    pub fn next_boost(&self) -> u64 {
+        if self.boost_per_referee > 0 && self.balance >= self.boost_per_referee {
+            self.boost_per_referee
+        } else {
+            0
+        }
+    }
+}
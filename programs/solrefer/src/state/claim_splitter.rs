@@ -0,0 +1,62 @@
+use crate::{
+    constants::MAX_SPLITTER_ENTRIES,
+    space::{layout_size, LayoutField},
+};
+use anchor_lang::prelude::*;
+
+/// One destination of a claim splitter and its share of every claim.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SplitterEntry {
+    /// The wallet paid the share; token claims pay a token account it owns
+    pub destination: Pubkey,
+    /// The share of each claim, in basis points
+    pub bps: u16,
+}
+
+/// Splits a participant's claims across up to `MAX_SPLITTER_ENTRIES` destinations in fixed proportions, e.g. among
+/// the contributors of a team referring as one participant.
+///
+/// While it exists every claim of the participant pays each destination its share instead of paying the owner.
+///
+/// PDA with seeds: ["splitter", participant.key()]
+#[account]
+#[derive(Default)]
+pub struct ClaimSplitter {
+    /// The participant account whose claims are split
+    pub participant: Pubkey,
+    /// Number of entries in use, from the start of `entries`
+    pub entry_count: u8,
+    /// The destinations and their shares, summing to 10_000 basis points; unused entries are zeroed
+    pub entries: [SplitterEntry; MAX_SPLITTER_ENTRIES],
+    /// Bump seed for the splitter PDA
+    pub bump: u8,
+}
+
+impl ClaimSplitter {
+    /// Version of the `ClaimSplitter` account layout, bumped whenever its fields change.
+    pub const LAYOUT_VERSION: u8 = 1;
+
+    /// Each field of the `ClaimSplitter` account and the most bytes it serializes to, discriminator excluded.
+    pub const LAYOUT: &'static [LayoutField] =
+        &[("participant", 32), ("entry_count", 1), ("entries", (32 + 2) * MAX_SPLITTER_ENTRIES), ("bump", 1)];
+
+    /// The size of the `ClaimSplitter` account in bytes, excluding the discriminator.
+    pub const SIZE: usize = layout_size(Self::LAYOUT);
+
+    /// The entries in use
+    pub fn entries(&self) -> &[SplitterEntry] {
+        &self.entries[..usize::from(self.entry_count)]
+    }
+
+    /// Divides a claim of `amount` into the share of each entry in use, rounding every share down and paying the
+    /// remainder to the first entry.
+    pub fn shares(&self, amount: u64) -> Vec<u64> {
+        let mut shares: Vec<u64> =
+            self.entries().iter().map(|entry| (u128::from(amount) * u128::from(entry.bps) / 10_000) as u64).collect();
+        let remainder = amount - shares.iter().sum::<u64>();
+        if let Some(first) = shares.first_mut() {
+            *first += remainder;
+        }
+        shares
+    }
+}
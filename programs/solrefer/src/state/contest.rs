@@ -0,0 +1,93 @@
+use crate::{
+    constants::MAX_CONTEST_PRIZES,
+    error::ReferralError,
+    space::{layout_size, LayoutField},
+};
+use anchor_lang::prelude::*;
+
+/// A rank-ordered referral contest settled once the referral program ends.
+///
+/// PDA with seeds: ["contest", referral_program.key()]
+#[account]
+#[derive(Default)]
+pub struct Contest {
+    /// The referral program running the contest
+    pub program: Pubkey,
+    /// Prize for each rank, best first; zeroed entries are unused
+    pub prizes: [u64; MAX_CONTEST_PRIZES],
+    /// Winning wallet for each rank, set when the contest is finalized
+    pub winners: [Pubkey; MAX_CONTEST_PRIZES],
+    /// Lamports deposited into the prize escrow
+    pub funded_amount: u64,
+    /// Seconds between finalization and prizes becoming claimable
+    pub dispute_window: i64,
+    /// When the contest was finalized
+    pub finalized_at: i64,
+    /// Whether the ranking has been finalized
+    pub settled: bool,
+    /// Bit `i` is set once the prize of rank `i` has been claimed
+    pub claimed_bitmap: u8,
+    /// Bump seed for the contest PDA
+    pub bump: u8,
+}
+
+impl Contest {
+    /// Version of the `Contest` account layout, bumped whenever its fields change.
+    pub const LAYOUT_VERSION: u8 = 1;
+
+    /// Each field of the `Contest` account and the most bytes it serializes to, discriminator excluded.
+    pub const LAYOUT: &'static [LayoutField] = &[
+        ("program", 32),
+        ("prizes", 8 * MAX_CONTEST_PRIZES),
+        ("winners", 32 * MAX_CONTEST_PRIZES),
+        ("funded_amount", 8),
+        ("dispute_window", 8),
+        ("finalized_at", 8),
+        ("settled", 1),
+        ("claimed_bitmap", 1),
+        ("bump", 1),
+    ];
+
+    /// The size of the `Contest` account in bytes, excluding the discriminator.
+    pub const SIZE: usize = layout_size(Self::LAYOUT);
+
+    /// Number of ranks with a prize
+    pub fn prize_count(&self) -> usize {
+        self.prizes.iter().take_while(|prize| **prize > 0).count()
+    }
+
+    /// Sum of the prizes of the first `ranks` ranks
+    pub fn total_prizes(&self, ranks: usize) -> Result<u64> {
+        self.prizes[..ranks]
+            .iter()
+            .try_fold(0u64, |total, prize| total.checked_add(*prize))
+            .ok_or(error!(ReferralError::NumericOverflow))
+    }
+
+    /// Sum of the prizes awarded to a winner that have not been claimed yet
+    pub fn unclaimed_prizes(&self) -> Result<u64> {
+        (0..MAX_CONTEST_PRIZES)
+            .filter(|rank| self.winners[*rank] != Pubkey::default() && self.claimed_bitmap & (1 << rank) == 0)
+            .try_fold(0u64, |total, rank| total.checked_add(self.prizes[rank]))
+            .ok_or(error!(ReferralError::NumericOverflow))
+    }
+
+    /// Earliest time the prizes of a finalized contest can be claimed
+    pub fn claimable_at(&self) -> i64 {
+        self.finalized_at.saturating_add(self.dispute_window)
+    }
+}
+
+/// Checks contest prizes: at least one, with every non-zero prize before the first zero.
+pub fn validate_contest_prizes(prizes: &[u64; MAX_CONTEST_PRIZES]) -> Result<()> {
+    let configured = prizes.iter().take_while(|prize| **prize > 0).count();
+    require!(configured > 0, ReferralError::InvalidContestConfig);
+    require!(prizes[configured..].iter().all(|prize| *prize == 0), ReferralError::InvalidContestConfig);
+    Ok(())
+}
+
+/// Checks that referral counts, listed in claimed rank order, never increase from one rank to the next.
+pub fn validate_contest_ranking(referral_counts: &[u64]) -> Result<()> {
+    require!(referral_counts.windows(2).all(|pair| pair[0] >= pair[1]), ReferralError::InvalidContestRanking);
+    Ok(())
+}
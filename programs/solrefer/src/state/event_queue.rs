@@ -0,0 +1,90 @@
+use crate::space::{layout_size, LayoutField};
+use anchor_lang::prelude::*;
+
+/// The number of records retained by an `EventQueue`.
+pub const EVENT_QUEUE_CAPACITY: usize = 32;
+
+/// A compact record of a state-changing action on a referral program.
+///
+/// Serialized layout (49 bytes, little-endian):
+/// - `kind`: u8 at offset 0 (one of the `EventRecord::KIND_*` constants)
+/// - `actor`: Pubkey at offset 1 (the wallet that performed the action)
+/// - `amount`: u64 at offset 33 (lamports or token units moved or credited, 0 if none)
+/// - `ts`: i64 at offset 41 (unix timestamp of the action)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EventRecord {
+    pub kind: u8,
+    pub actor: Pubkey,
+    pub amount: u64,
+    pub ts: i64,
+}
+
+impl EventRecord {
+    /// A user joined the program directly
+    pub const KIND_JOIN: u8 = 1;
+    /// A user joined through a referral; `amount` is the reward credited to the referrer
+    pub const KIND_REFERRAL: u8 = 2;
+    /// The authority deposited funds into the vault
+    pub const KIND_DEPOSIT: u8 = 3;
+    /// A participant claimed rewards
+    pub const KIND_CLAIM: u8 = 4;
+    /// The authority changed the program settings
+    pub const KIND_SETTINGS_CHANGE: u8 = 5;
+
+    /// The serialized size of a record in bytes.
+    pub const SIZE: usize = 1 + 32 + 8 + 8;
+}
+
+/// A ring buffer of the most recent events of a referral program, for clients that poll accounts
+/// instead of subscribing to logs.
+///
+/// The record for event number `n` (counting from 0) is stored at `records[n % EVENT_QUEUE_CAPACITY]`
+/// and `head` is the number of events ever appended. A client that has seen events up to `cursor`
+/// reads the new ones with `decode_events`; if more than `EVENT_QUEUE_CAPACITY` events happened since,
+/// the oldest of them have been overwritten.
+#[account]
+pub struct EventQueue {
+    /// The referral program this queue belongs to
+    pub program: Pubkey,
+    /// The total number of events ever appended
+    pub head: u64,
+    /// The most recent events
+    pub records: [EventRecord; EVENT_QUEUE_CAPACITY],
+    /// Bump seed for the queue PDA
+    pub bump: u8,
+}
+
+impl EventQueue {
+    /// Version of the `EventQueue` account layout, bumped whenever its fields change.
+    pub const LAYOUT_VERSION: u8 = 1;
+
+    /// Each field of the `EventQueue` account and the most bytes it serializes to, discriminator excluded.
+    pub const LAYOUT: &'static [LayoutField] =
+        &[("program", 32), ("head", 8), ("records", EventRecord::SIZE * EVENT_QUEUE_CAPACITY), ("bump", 1)];
+
+    /// The size of the `EventQueue` account in bytes, excluding the discriminator.
+    pub const SIZE: usize = layout_size(Self::LAYOUT);
+
+    /// Appends a record, overwriting the oldest one once the buffer is full.
+    pub fn push(&mut self, kind: u8, actor: Pubkey, amount: u64, ts: i64) {
+        let index = (self.head % EVENT_QUEUE_CAPACITY as u64) as usize;
+        self.records[index] = EventRecord { kind, actor, amount, ts };
+        self.head = self.head.wrapping_add(1);
+    }
+
+    /// Returns the retained events numbered `cursor` and later, oldest first.
+    pub fn decode_events(&self, cursor: u64) -> Vec<EventRecord> {
+        let oldest = self.head.saturating_sub(EVENT_QUEUE_CAPACITY as u64);
+        (cursor.max(oldest)..self.head)
+            .map(|n| self.records[(n % EVENT_QUEUE_CAPACITY as u64) as usize])
+            .collect()
+    }
+}
+
+/// Decodes the events numbered `cursor` and later from raw `EventQueue` account data.
+///
+/// Returns the queue's `head`, which the client stores as its next cursor, along with the events.
+pub fn decode_events(account_data: &[u8], cursor: u64) -> Result<(u64, Vec<EventRecord>)> {
+    let queue = EventQueue::try_deserialize(&mut &account_data[..])?;
+    Ok((queue.head, queue.decode_events(cursor)))
+}
@@ -0,0 +1,95 @@
+use crate::{
+    constants::MAX_FEE_EXEMPT_AUTHORITIES,
+    error::ReferralError,
+    space::{layout_size, LayoutField},
+};
+use anchor_lang::prelude::*;
+
+/// Protocol-wide settings applied when referral programs are created, administered by the program's
+/// upgrade authority. Until it is initialized, program creation is free and unlimited.
+///
+/// PDA with seeds: ["fee_config"]
+#[account]
+#[derive(Default)]
+pub struct FeeConfig {
+    /// The protocol admin allowed to update the config
+    pub admin: Pubkey,
+    /// The account receiving creation fees
+    pub treasury: Pubkey,
+    /// Lamports charged to the authority for every program created (0 disables the fee)
+    pub creation_fee_lamports: u64,
+    /// The number of live programs an authority may hold (0 disables the limit)
+    pub max_programs_per_authority: u8,
+    /// Authorities that create programs without paying the fee (unused slots are the default pubkey)
+    pub exempt_authorities: [Pubkey; MAX_FEE_EXEMPT_AUTHORITIES],
+    /// Bump seed for the fee config PDA
+    pub bump: u8,
+}
+
+impl FeeConfig {
+    /// Version of the `FeeConfig` account layout, bumped whenever its fields change.
+    pub const LAYOUT_VERSION: u8 = 1;
+
+    /// Each field of the `FeeConfig` account and the most bytes it serializes to, discriminator excluded.
+    pub const LAYOUT: &'static [LayoutField] = &[
+        ("admin", 32),
+        ("treasury", 32),
+        ("creation_fee_lamports", 8),
+        ("max_programs_per_authority", 1),
+        ("exempt_authorities", 32 * MAX_FEE_EXEMPT_AUTHORITIES),
+        ("bump", 1),
+    ];
+
+    /// The size of the `FeeConfig` account in bytes, excluding the discriminator.
+    pub const SIZE: usize = layout_size(Self::LAYOUT);
+
+    /// Returns the creation fee owed by `authority`.
+    pub fn creation_fee_for(&self, authority: &Pubkey) -> u64 {
+        if *authority != Pubkey::default() && self.exempt_authorities.contains(authority) {
+            0
+        } else {
+            self.creation_fee_lamports
+        }
+    }
+}
+
+/// Per-authority bookkeeping of program creations.
+///
+/// PDA with seeds: ["authority_meta", authority.key()]
+#[account]
+#[derive(Default)]
+pub struct AuthorityMeta {
+    /// The authority this account tracks
+    pub authority: Pubkey,
+    /// Programs created by the authority that have not been closed
+    pub active_programs: u8,
+    /// Programs ever created by the authority
+    pub total_created: u64,
+    /// Bump seed for the authority meta PDA
+    pub bump: u8,
+}
+
+impl AuthorityMeta {
+    /// Version of the `AuthorityMeta` account layout, bumped whenever its fields change.
+    pub const LAYOUT_VERSION: u8 = 1;
+
+    /// Each field of the `AuthorityMeta` account and the most bytes it serializes to, discriminator excluded.
+    pub const LAYOUT: &'static [LayoutField] =
+        &[("authority", 32), ("active_programs", 1), ("total_created", 8), ("bump", 1)];
+
+    /// The size of the `AuthorityMeta` account in bytes, excluding the discriminator.
+    pub const SIZE: usize = layout_size(Self::LAYOUT);
+
+    /// Records a new program, failing if the authority already holds `max_programs` live programs.
+    pub fn register_program(&mut self, max_programs: u8) -> Result<()> {
+        require!(max_programs == 0 || self.active_programs < max_programs, ReferralError::TooManyPrograms);
+        self.active_programs = self.active_programs.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+        self.total_created = self.total_created.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+        Ok(())
+    }
+
+    /// Records that one of the authority's programs was fully closed.
+    pub fn release_program(&mut self) {
+        self.active_programs = self.active_programs.saturating_sub(1);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::{
+    constants::FINAL_REPORT_SEED,
+    space::{layout_size, option, LayoutField},
+};
+use anchor_lang::prelude::*;
+
+/// The final figures of a closed referral program, written by `close_referral_program` before it closes the
+/// program and criteria accounts.
+///
+/// No instruction mutates or closes a report once written, so it outlives the program as a permanent record, and
+/// no program can be created at the closed program's address again.
+///
+/// PDA with seeds: ["final_report", referral_program.key()]
+#[account]
+#[derive(Default)]
+pub struct FinalReport {
+    /// The closed referral program, whose account no longer exists
+    pub referral_program: Pubkey,
+    /// The authority that closed it
+    pub authority: Pubkey,
+    /// The program's token mint (default for SOL programs)
+    pub token_mint: Pubkey,
+    /// Every deposit made to the program, in raw units, reserved shares included
+    pub total_deposited: u64,
+    /// Rewards paid out by claims, in raw units
+    pub total_distributed: u64,
+    /// Protocol fees the program's authority paid for it, in lamports
+    pub total_fees: u64,
+    /// Participants that joined the program
+    pub total_participants: u64,
+    /// Referrals credited, less clawbacks
+    pub total_referrals_credited: u64,
+    /// Every join through a referral, credited or not
+    pub total_referrals_raw: u64,
+    /// When the program started
+    pub program_start_time: i64,
+    /// When the program was set to end (None = open-ended)
+    pub program_end_time: Option<i64>,
+    /// When the closure was finalized
+    pub closed_at: i64,
+    /// Bump seed for the report PDA
+    pub bump: u8,
+}
+
+impl FinalReport {
+    /// Version of the `FinalReport` account layout, bumped whenever its fields change.
+    pub const LAYOUT_VERSION: u8 = 1;
+
+    /// Each field of the `FinalReport` account and the most bytes it serializes to, discriminator excluded.
+    pub const LAYOUT: &'static [LayoutField] = &[
+        ("referral_program", 32),
+        ("authority", 32),
+        ("token_mint", 32),
+        ("total_deposited", 8),
+        ("total_distributed", 8),
+        ("total_fees", 8),
+        ("total_participants", 8),
+        ("total_referrals_credited", 8),
+        ("total_referrals_raw", 8),
+        ("program_start_time", 8),
+        ("program_end_time", option(8)),
+        ("closed_at", 8),
+        ("bump", 1),
+    ];
+
+    /// The size of the `FinalReport` account in bytes, excluding the discriminator.
+    pub const SIZE: usize = layout_size(Self::LAYOUT);
+
+    /// Returns the address of the report a closed `referral_program` leaves behind.
+    pub fn address(referral_program: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[FINAL_REPORT_SEED, referral_program.as_ref()], &crate::ID).0
+    }
+}
+
+/// Deserializes a final report from its account data, for off-chain use.
+///
+/// # Errors
+/// * `AccountDiscriminatorMismatch` - If the data is not a final report
+pub fn get_final_report(data: &[u8]) -> Result<FinalReport> {
+    FinalReport::try_deserialize(&mut &data[..])
+}
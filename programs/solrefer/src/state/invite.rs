@@ -0,0 +1,49 @@
+use crate::{
+    error::ReferralError,
+    space::{layout_size, LayoutField},
+};
+use anchor_lang::prelude::*;
+
+/// A single-use invite to an invite-only referral program, minted by the program authority.
+///
+/// PDA with seeds: ["invite", referral_program.key(), index.to_le_bytes()]
+#[account]
+#[derive(Default)]
+pub struct Invite {
+    /// The referral program this invite admits to
+    pub program: Pubkey,
+    /// Position of the invite in the program's invite counter
+    pub index: u64,
+    /// Whether the invite has been used to join
+    pub claimed: bool,
+    /// The wallet that joined with this invite (default until claimed)
+    pub claimer: Pubkey,
+    /// Bump seed for the invite PDA
+    pub bump: u8,
+    /// The wallet that paid the invite's rent and gets it back when the invite is closed
+    pub rent_payer: Pubkey,
+}
+
+impl Invite {
+    /// Version of the `Invite` account layout, bumped whenever its fields change.
+    pub const LAYOUT_VERSION: u8 = 2;
+
+    /// Each field of the `Invite` account and the most bytes it serializes to, discriminator excluded.
+    pub const LAYOUT: &'static [LayoutField] =
+        &[("program", 32), ("index", 8), ("claimed", 1), ("claimer", 32), ("bump", 1), ("rent_payer", 32)];
+
+    /// The size of the `Invite` account in bytes, excluding the discriminator.
+    pub const SIZE: usize = layout_size(Self::LAYOUT);
+}
+
+/// Consumes the invite passed to a join when the program is invite-only; open programs ignore it.
+pub fn redeem_invite(invite_only: bool, invite: Option<&mut Invite>, claimer: Pubkey) -> Result<()> {
+    if !invite_only {
+        return Ok(());
+    }
+    let invite = invite.ok_or(ReferralError::InviteRequired)?;
+    require!(!invite.claimed, ReferralError::InviteAlreadyClaimed);
+    invite.claimed = true;
+    invite.claimer = claimer;
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use crate::space::{layout_size, LayoutField};
+use anchor_lang::prelude::*;
+
+/// SOL a sponsor has put up to match the referral rewards of a program it does not run.
+///
+/// The offer holds its budget as lamports on top of its rent-exempt minimum. Matches are paid straight to the
+/// referrer's wallet, so neither the budget nor the matches ever enter the program's own accounting.
+///
+/// PDA with seeds: ["match", referral_program.key(), sponsor.key()]
+#[account]
+#[derive(Default)]
+pub struct MatchOffer {
+    /// The referral program whose rewards are matched
+    pub program: Pubkey,
+    /// The wallet that funds the offer and can cancel it
+    pub sponsor: Pubkey,
+    /// The match paid on each credited referral reward, in basis points of the reward
+    pub match_bps: u16,
+    /// Lamports left for matches
+    pub balance: u64,
+    /// Lamports paid out to referrers so far
+    pub total_matched: u64,
+    /// Number of referral rewards matched so far
+    pub referrals_matched: u64,
+    /// Bump seed for the offer PDA
+    pub bump: u8,
+}
+
+impl MatchOffer {
+    /// Version of the `MatchOffer` account layout, bumped whenever its fields change.
+    pub const LAYOUT_VERSION: u8 = 1;
+
+    /// Each field of the `MatchOffer` account and the most bytes it serializes to, discriminator excluded.
+    pub const LAYOUT: &'static [LayoutField] = &[
+        ("program", 32),
+        ("sponsor", 32),
+        ("match_bps", 2),
+        ("balance", 8),
+        ("total_matched", 8),
+        ("referrals_matched", 8),
+        ("bump", 1),
+    ];
+
+    /// The size of the `MatchOffer` account in bytes, excluding the discriminator.
+    pub const SIZE: usize = layout_size(Self::LAYOUT);
+
+    /// The match on a referral reward of `reward`, rounded down and capped at what is left of the budget.
+    pub fn next_match(&self, reward: u64) -> u64 {
+        let full = (u128::from(reward) * u128::from(self.match_bps) / 10_000) as u64;
+        full.min(self.balance)
+    }
+}
@@ -2,3 +2,31 @@ pub mod referral_program;
 pub use referral_program::*;
 pub mod participant;
 pub use participant::*;
+pub mod referee_receipt;
+pub use referee_receipt::*;
+pub mod event_queue;
+pub use event_queue::*;
+pub mod invite;
+pub use invite::*;
+pub mod fee_config;
+pub use fee_config::*;
+pub mod contest;
+pub use contest::*;
+pub mod boost;
+pub use boost::*;
+pub mod network_config;
+pub use network_config::*;
+pub mod withdrawal;
+pub use withdrawal::*;
+pub mod final_report;
+pub use final_report::*;
+pub mod participant_history;
+pub use participant_history::*;
+pub mod settings_change;
+pub use settings_change::*;
+pub mod match_offer;
+pub use match_offer::*;
+pub mod pending_change;
+pub use pending_change::*;
+pub mod claim_splitter;
+pub use claim_splitter::*;
@@ -0,0 +1,101 @@
+use crate::{
+    constants::*,
+    error::ReferralError,
+    space::{layout_size, LayoutField},
+};
+use anchor_lang::prelude::*;
+
+/// Validation limits applied to every referral program on this cluster.
+///
+/// The defaults are the compile-time constants, which stay the absolute bounds: a cluster can tighten the limits
+/// or loosen them within those bounds, never past them. The minimum locked period is the one exception, allowed
+/// down to `MIN_LOCKED_PERIOD_FLOOR` so test clusters can unlock rewards within minutes.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NetworkLimits {
+    /// The shortest locked period a program may set, in seconds
+    pub min_locked_period: i64,
+    /// The longest locked period a program may set, in seconds
+    pub max_locked_period: i64,
+    /// The largest revenue share a program may set, in basis points
+    pub max_fee_percentage: u64,
+    /// The furthest a program's end time may lie from the time it is set, in seconds
+    pub max_program_duration: i64,
+    /// The smallest fixed or base reward a program may set
+    pub min_reward_amount: u64,
+}
+
+impl Default for NetworkLimits {
+    fn default() -> Self {
+        Self {
+            min_locked_period: MIN_LOCKED_PERIOD,
+            max_locked_period: MAX_LOCKED_PERIOD,
+            max_fee_percentage: MAX_FEE_PERCENTAGE,
+            max_program_duration: MAX_PROGRAM_DURATION,
+            min_reward_amount: MIN_REWARD_AMOUNT,
+        }
+    }
+}
+
+impl NetworkLimits {
+    /// Checks the limits against the absolute bounds.
+    ///
+    /// # Errors
+    /// * `InvalidNetworkConfig` - If a limit lies outside its absolute bound or the locked period range is empty
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.min_locked_period >= MIN_LOCKED_PERIOD_FLOOR
+                && self.min_locked_period <= self.max_locked_period
+                && self.max_locked_period <= MAX_LOCKED_PERIOD
+                && self.max_fee_percentage <= MAX_FEE_PERCENTAGE
+                && self.max_program_duration > 0
+                && self.max_program_duration <= MAX_PROGRAM_DURATION
+                && self.min_reward_amount >= MIN_REWARD_AMOUNT,
+            ReferralError::InvalidNetworkConfig
+        );
+        Ok(())
+    }
+
+    /// Reads the limits from the network config PDA, or returns the defaults while it is uninitialized.
+    ///
+    /// The caller must have checked the account's address against the PDA seeds.
+    pub fn load(network_config: &AccountInfo, program_id: &Pubkey) -> Result<Self> {
+        if network_config.owner != program_id || network_config.data_is_empty() {
+            return Ok(Self::default());
+        }
+        let config = NetworkConfig::try_deserialize(&mut &network_config.try_borrow_data()?[..])?;
+        Ok(config.limits)
+    }
+}
+
+/// Cluster-wide overrides of the program's validation limits, administered by the program's upgrade authority.
+/// Until it is initialized, the compile-time constants apply.
+///
+/// PDA with seeds: ["network_config"]
+#[account]
+pub struct NetworkConfig {
+    /// The admin allowed to update the config
+    pub admin: Pubkey,
+    /// The limits every program is validated against
+    pub limits: NetworkLimits,
+    /// Bump seed for the network config PDA
+    pub bump: u8,
+}
+
+impl NetworkConfig {
+    /// Version of the `NetworkConfig` account layout, bumped whenever its fields change.
+    pub const LAYOUT_VERSION: u8 = 1;
+
+    /// Each field of the `NetworkConfig` account and the most bytes it serializes to, discriminator excluded.
+    pub const LAYOUT: &'static [LayoutField] = &[
+        ("admin", 32),
+        ("limits.min_locked_period", 8),
+        ("limits.max_locked_period", 8),
+        ("limits.max_fee_percentage", 8),
+        ("limits.max_program_duration", 8),
+        ("limits.min_reward_amount", 8),
+        ("bump", 1),
+    ];
+
+    /// The size of the `NetworkConfig` account in bytes, excluding the discriminator.
+    pub const SIZE: usize = layout_size(Self::LAYOUT);
+}
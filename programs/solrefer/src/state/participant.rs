@@ -1,12 +1,30 @@
-use anchor_lang::prelude::*;
+use crate::{
+    constants::{LINK_FORMAT_LEGACY, LINK_PARAMS_LEN, LINK_SLUG_LEN, PARTICIPANT_NOTE_LEN, SOURCE_TAG_LEN},
+    error::ReferralError,
+    space::{layout_size, option, LayoutField},
+    state::{EligibilityCriteria, ReferralProgram},
+};
+use anchor_lang::{prelude::*, solana_program::log::sol_log};
 
 /// Represents a participant in the referral program.
 ///
 /// This struct stores information about a participant including their:
-/// - Referral link for sharing with others
+/// - Referral link for sharing with others, stored in full or as a slug depending on the program
 /// - Total number of successful referrals
 /// - Total rewards earned
+/// - Rewards credited but not yet claimed
 /// - Optional referrer if they joined through someone's link
+/// - Depth in the referral tree
+/// - Optional payout split routing part of their referral rewards to another participant
+/// - Wallet rotation state linking it to the account it was rotated to or from
+/// - Running totals of every adjustment to its rewards, reported by `get_reward_statement`
+/// - A lock inherited from pending rewards transferred to it
+/// - A note only the program authority can write, e.g. the terms negotiated with a partner
+/// - Landing parameters the participant sets for the referees joining through its link
+/// - Sponsor matches paid straight to its wallet on top of its referral rewards
+/// - Whether its claims are split across the destinations of a claim splitter
+/// - How many referees that joined through it are still in the program
+/// - Whether the authority seeded it for a partner wallet, and whether that wallet has activated it
 #[account]
 pub struct Participant {
     /// The owner of this participant account
@@ -15,14 +33,80 @@ pub struct Participant {
     pub program: Pubkey,
     /// When this participant joined the program
     pub join_time: i64,
-    /// Number of successful referrals made
+    /// Number of referrals that credited this participant, less those clawed back
     pub total_referrals: u64,
     /// Total rewards earned from referrals
     pub total_rewards: u64,
     /// Who referred this participant (if any)
     pub referrer: Option<Pubkey>,
-    /// Unique referral link for this participant
+    /// Unique referral link for this participant; only written for `LINK_FORMAT_LEGACY` programs
     pub referral_link: [u8; 100],
+    /// Rewards credited from referrals that have not been claimed yet
+    pub pending_rewards: u64,
+    /// Share of each credited referral reward routed to another participant (if any)
+    pub payout_split: Option<PayoutSplit>,
+    /// Depth in the referral tree: 0 for direct joins, the referrer's depth + 1 otherwise
+    pub referral_depth: u16,
+    /// New owner this account is being handed over to, set by `initiate_owner_rotation`
+    pub pending_owner: Option<Pubkey>,
+    /// Participant account this one was rotated to; a tombstone that blocks claims from this account
+    pub rotated_to: Option<Pubkey>,
+    /// Participant account this one was rotated from; such accounts cannot be rotated again
+    pub rotated_from: Option<Pubkey>,
+    /// Bit `i` is set once milestone `i` of the program has paid its bonus to this participant
+    pub milestones_claimed_bitmap: u8,
+    /// Full amount of every purchase attributed to this participant, whatever reward it earned
+    pub total_attributed_volume: u64,
+    /// Campaign tag the participant joined with; zeros for untagged or direct joins
+    pub source_tag: [u8; SOURCE_TAG_LEN],
+    /// Hash of the program terms this participant last accepted
+    pub accepted_terms_hash: [u8; 32],
+    /// Version of the program terms this participant last accepted
+    pub accepted_terms_version: u16,
+    /// Every reward credited to this participant, including the part withheld by clamping
+    pub gross_credited: u64,
+    /// Part of `gross_credited` withheld because it exceeded the reward cap or the vault's uncommitted funds
+    pub cap_clamped: u64,
+    /// Pending rewards removed by `clawback_referral`
+    pub clawed_back: u64,
+    /// Referee boosts paid straight to this participant's wallet; never part of `pending_rewards`
+    pub boost_received: u64,
+    /// Start of the current referral rate-limit window
+    pub window_start: i64,
+    /// Referrals credited since `window_start`
+    pub referrals_in_window: u32,
+    /// Referee receipts counted toward `total_referrals`, clawed back or not; never decremented
+    pub record_count: u64,
+    /// Pending rewards are locked until at least this time; set by rewards transferred in with `transfer_pending`
+    pub locked_until: i64,
+    /// Pending rewards transferred to other participants with `transfer_pending`
+    pub transferred_out: u64,
+    /// Note written by the program authority with `set_participant_note`; zero-padded UTF-8, no effect on rewards
+    pub authority_note: [u8; PARTICIPANT_NOTE_LEN],
+    /// Slug of the referral link, the owner's base58 address; only written for `LINK_FORMAT_SLUG` programs
+    pub link_slug: [u8; LINK_SLUG_LEN],
+    /// How the referral link is stored, copied from the program at join (one of the `LINK_FORMAT_*` constants)
+    pub link_format: u8,
+    /// Every join through this participant's referral, credited or not
+    pub raw_referrals: u64,
+    /// Opaque landing parameters set with `set_link_params`, e.g. a discount code; zeros when none are set
+    pub link_params: [u8; LINK_PARAMS_LEN],
+    /// Sponsor matches paid straight to this participant's wallet; never part of `pending_rewards`
+    pub match_received: u64,
+    /// Whether the participant's claim splitter exists; claims must then pay its destinations
+    pub has_claim_splitter: bool,
+    /// Referees that joined through this participant and are still in the program, less those clawed back
+    pub active_referee_count: u32,
+    /// Whether the program authority created this account for its owner with `seed_participant`
+    pub seeded: bool,
+    /// Whether the owner of a seeded account has activated it with `activate_seeded`; seeded accounts cannot
+    /// claim until then
+    pub activated: bool,
+    /// Pending rewards forfeited as fees on early redemptions, made with `early_claim_rewards` or
+    /// `early_claim_token_rewards`
+    pub early_redemption_fees: u64,
+    /// Whether the participant left the program with `leave_program` while referees still referenced it
+    pub left: bool,
 }
 
 impl Default for Participant {
@@ -35,6 +119,314 @@ impl Default for Participant {
             total_rewards: 0,
             referrer: None,
             referral_link: [0u8; 100],
+            pending_rewards: 0,
+            payout_split: None,
+            referral_depth: 0,
+            pending_owner: None,
+            rotated_to: None,
+            rotated_from: None,
+            milestones_claimed_bitmap: 0,
+            total_attributed_volume: 0,
+            source_tag: [0u8; SOURCE_TAG_LEN],
+            accepted_terms_hash: [0u8; 32],
+            accepted_terms_version: 0,
+            gross_credited: 0,
+            cap_clamped: 0,
+            clawed_back: 0,
+            boost_received: 0,
+            window_start: 0,
+            referrals_in_window: 0,
+            record_count: 0,
+            locked_until: 0,
+            transferred_out: 0,
+            authority_note: [0u8; PARTICIPANT_NOTE_LEN],
+            link_slug: [0u8; LINK_SLUG_LEN],
+            link_format: LINK_FORMAT_LEGACY,
+            raw_referrals: 0,
+            link_params: [0u8; LINK_PARAMS_LEN],
+            match_received: 0,
+            has_claim_splitter: false,
+            active_referee_count: 0,
+            seeded: false,
+            activated: false,
+            early_redemption_fees: 0,
+            left: false,
         }
     }
 }
+
+/// Routes a share of a referrer's credited rewards to another participant of the same program.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PayoutSplit {
+    /// The wallet of the participant receiving the share
+    pub recipient: Pubkey,
+    /// The share routed to the recipient, in basis points
+    pub bps: u16,
+}
+
+impl PayoutSplit {
+    /// The serialized size of a payout split in bytes.
+    pub const SIZE: usize = 32 + 2;
+
+    /// Divides a reward into the referrer's remainder and the recipient's share, rounding the share down.
+    pub fn split(&self, amount: u64) -> (u64, u64) {
+        let share = (u128::from(amount) * u128::from(self.bps) / 10_000) as u64;
+        (amount - share, share)
+    }
+}
+
+/// The prefix of every referral link; the owner's base58 address follows it.
+pub const REFERRAL_LINK_PREFIX: &[u8] = b"https://solrefer.io/ref/";
+
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// 58^5, the largest power of 58 whose limbs can absorb a 32-bit word without overflowing a u64.
+const BASE58_LIMB: u64 = 58 * 58 * 58 * 58 * 58;
+
+impl Participant {
+    /// Version of the `Participant` account layout, bumped whenever its fields change.
+    pub const LAYOUT_VERSION: u8 = 13;
+
+    /// Each field of the `Participant` account and the most bytes it serializes to, discriminator excluded.
+    pub const LAYOUT: &'static [LayoutField] = &[
+        ("owner", 32),
+        ("program", 32),
+        ("join_time", 8),
+        ("total_referrals", 8),
+        ("total_rewards", 8),
+        ("referrer", option(32)),
+        ("referral_link", 100),
+        ("pending_rewards", 8),
+        ("payout_split", option(PayoutSplit::SIZE)),
+        ("referral_depth", 2),
+        ("pending_owner", option(32)),
+        ("rotated_to", option(32)),
+        ("rotated_from", option(32)),
+        ("milestones_claimed_bitmap", 1),
+        ("total_attributed_volume", 8),
+        ("source_tag", SOURCE_TAG_LEN),
+        ("accepted_terms_hash", 32),
+        ("accepted_terms_version", 2),
+        ("gross_credited", 8),
+        ("cap_clamped", 8),
+        ("clawed_back", 8),
+        ("boost_received", 8),
+        ("window_start", 8),
+        ("referrals_in_window", 4),
+        ("record_count", 8),
+        ("locked_until", 8),
+        ("transferred_out", 8),
+        ("authority_note", PARTICIPANT_NOTE_LEN),
+        ("link_slug", LINK_SLUG_LEN),
+        ("link_format", 1),
+        ("raw_referrals", 8),
+        ("link_params", LINK_PARAMS_LEN),
+        ("match_received", 8),
+        ("has_claim_splitter", 1),
+        ("active_referee_count", 4),
+        ("seeded", 1),
+        ("activated", 1),
+        ("early_redemption_fees", 8),
+        ("left", 1),
+    ];
+
+    /// The size of the `Participant` account in bytes, excluding the discriminator.
+    pub const SIZE: usize = layout_size(Self::LAYOUT);
+
+    /// Returns the address of `owner`'s participant account in `referral_program`.
+    pub fn address(referral_program: &Pubkey, owner: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"participant", referral_program.as_ref(), owner.as_ref()], &crate::ID).0
+    }
+
+    /// Returns true if the participant left the program with `leave_program` while it still had active referees,
+    /// leaving this account behind as a tombstone that is credited nothing.
+    pub fn has_left(&self) -> bool {
+        self.left
+    }
+
+    /// Fails unless this is the live account of a participant still in the program: neither rotated to a new owner
+    /// nor left.
+    pub fn require_current(&self) -> Result<()> {
+        require!(self.rotated_to.is_none(), ReferralError::ParticipantRotated);
+        require!(!self.has_left(), ReferralError::ParticipantLeft);
+        Ok(())
+    }
+
+    /// Returns true if the participant has taken part in anything that earns rewards: a join through its link, a
+    /// reward credited or transferred to it, a purchase attributed to it, or a sponsor match or referee boost.
+    pub fn has_earned(&self) -> bool {
+        self.raw_referrals > 0
+            || self.record_count > 0
+            || self.gross_credited > 0
+            || self.pending_rewards > 0
+            || self.total_rewards > 0
+            || self.total_attributed_volume > 0
+            || self.match_received > 0
+            || self.boost_received > 0
+            || self.transferred_out > 0
+    }
+
+    /// Returns true if the authority seeded this account and its owner has not activated it yet.
+    pub fn awaits_activation(&self) -> bool {
+        self.seeded && !self.activated
+    }
+
+    /// Returns when this participant's pending rewards unlock under a program with `locked_period`.
+    ///
+    /// All pending rewards share one lock: the locked period from joining, extended by any lock inherited from
+    /// rewards transferred in.
+    pub fn unlocks_at(&self, locked_period: i64) -> i64 {
+        self.join_time.saturating_add(locked_period).max(self.locked_until)
+    }
+
+    /// Credits `amount` to the pending rewards, counting it towards `gross_credited`.
+    pub fn credit_reward(&mut self, amount: u64) -> Result<()> {
+        let pending_rewards = self.pending_rewards.checked_add(amount).ok_or(ReferralError::NumericOverflow)?;
+        let gross_credited = self.gross_credited.checked_add(amount).ok_or(ReferralError::NumericOverflow)?;
+        self.pending_rewards = pending_rewards;
+        self.gross_credited = gross_credited;
+        Ok(())
+    }
+
+    /// Counts a referral against this referrer's rate-limit window at `now`, returning false if the window is full.
+    ///
+    /// A window that has elapsed starts over at `now`. Only credited referrals count, and nothing is tracked
+    /// while `criteria` has no rate limit; `total_referrals` is left to the caller.
+    pub fn record_window_referral(&mut self, criteria: &EligibilityCriteria, now: i64) -> bool {
+        if !criteria.is_rate_limited() {
+            return true;
+        }
+        if self.window_is_full(criteria, now) {
+            return false;
+        }
+        if now.saturating_sub(self.window_start) >= criteria.referral_window_seconds {
+            self.window_start = now;
+            self.referrals_in_window = 0;
+        }
+        self.referrals_in_window += 1;
+        true
+    }
+
+    /// Returns true if a referral at `now` would be over this referrer's rate limit; always false while
+    /// `criteria` has no rate limit.
+    pub fn window_is_full(&self, criteria: &EligibilityCriteria, now: i64) -> bool {
+        let window_elapsed = now.saturating_sub(self.window_start) >= criteria.referral_window_seconds;
+        criteria.is_rate_limited() && !window_elapsed && self.referrals_in_window >= criteria.max_referrals_per_window
+    }
+
+    /// Records `amount` of a reward that was earned but withheld by clamping, so it shows in `gross_credited`
+    /// and `cap_clamped` without ever reaching the pending rewards.
+    pub fn record_clamped(&mut self, amount: u64) -> Result<()> {
+        let gross_credited = self.gross_credited.checked_add(amount).ok_or(ReferralError::NumericOverflow)?;
+        let cap_clamped = self.cap_clamped.checked_add(amount).ok_or(ReferralError::NumericOverflow)?;
+        self.gross_credited = gross_credited;
+        self.cap_clamped = cap_clamped;
+        Ok(())
+    }
+
+    /// Builds the referral link of `owner` in a fixed buffer without allocating, returning it with its length.
+    pub fn referral_link_for(owner: &Pubkey) -> ([u8; 100], usize) {
+        let mut link = [0u8; 100];
+        link[..REFERRAL_LINK_PREFIX.len()].copy_from_slice(REFERRAL_LINK_PREFIX);
+        let len = REFERRAL_LINK_PREFIX.len() + write_base58(&owner.to_bytes(), &mut link[REFERRAL_LINK_PREFIX.len()..]);
+        (link, len)
+    }
+
+    /// Builds the slug of `owner`'s referral link, its base58 address zero-padded to `LINK_SLUG_LEN`.
+    pub fn link_slug_for(owner: &Pubkey) -> [u8; LINK_SLUG_LEN] {
+        let mut slug = [0u8; LINK_SLUG_LEN];
+        write_base58(&owner.to_bytes(), &mut slug);
+        slug
+    }
+
+    /// Stores `owner`'s referral link in `link_format`: the full link for legacy programs, the slug otherwise.
+    pub fn set_referral_link(&mut self, owner: &Pubkey, link_format: u8) {
+        self.link_format = link_format;
+        if link_format == LINK_FORMAT_LEGACY {
+            self.referral_link = Self::referral_link_for(owner).0;
+        } else {
+            self.link_slug = Self::link_slug_for(owner);
+        }
+    }
+
+    /// Returns the stored referral link without its zero padding: the full link or the slug, per `link_format`.
+    pub fn stored_link(&self) -> &[u8] {
+        let stored: &[u8] = if self.link_format == LINK_FORMAT_LEGACY { &self.referral_link } else { &self.link_slug };
+        let len = stored.iter().position(|byte| *byte == 0).unwrap_or(stored.len());
+        &stored[..len]
+    }
+}
+
+/// Returns the referral URL of `participant` in `program`, for off-chain use.
+///
+/// Slugs are joined to the program's base URL, so every link can be derived again and follows a change of domain;
+/// participants of legacy programs get back the full link stored when they joined.
+pub fn referral_url(program: &ReferralProgram, participant: &Participant) -> String {
+    let link = participant.stored_link();
+    let url = match participant.link_format {
+        LINK_FORMAT_LEGACY => link.to_vec(),
+        _ => [program.base_url(), link].concat(),
+    };
+    String::from_utf8_lossy(&url).into_owned()
+}
+
+/// Logs the participant's referral link for the frontend to pick up, without going through `format!`.
+///
+/// Legacy links are logged as `referral_link:<link>`; slugs as `referral_slug:<slug>`, which the frontend joins to
+/// the program's base URL.
+pub fn log_referral_link(participant: &Participant) {
+    let tag: &[u8] = match participant.link_format {
+        LINK_FORMAT_LEGACY => b"referral_link:",
+        _ => b"referral_slug:",
+    };
+    let link = participant.stored_link();
+    let mut line = [0u8; 128];
+    line[..tag.len()].copy_from_slice(tag);
+    line[tag.len()..tag.len() + link.len()].copy_from_slice(link);
+    if let Ok(line) = core::str::from_utf8(&line[..tag.len() + link.len()]) {
+        sol_log(line);
+    }
+}
+
+/// Writes the base58 encoding of a 32-byte key into `out`, returning the number of bytes written (at most 44).
+fn write_base58(bytes: &[u8; 32], out: &mut [u8]) -> usize {
+    // Little-endian limbs in base 58^5; 9 limbs hold any 256-bit value
+    let mut limbs = [0u64; 9];
+    let mut limb_count = 0;
+    for word in bytes.chunks_exact(4) {
+        let mut carry = u64::from(u32::from_be_bytes([word[0], word[1], word[2], word[3]]));
+        for limb in limbs[..limb_count].iter_mut() {
+            carry += *limb << 32;
+            *limb = carry % BASE58_LIMB;
+            carry /= BASE58_LIMB;
+        }
+        while carry > 0 {
+            limbs[limb_count] = carry % BASE58_LIMB;
+            limb_count += 1;
+            carry /= BASE58_LIMB;
+        }
+    }
+
+    // Expand the limbs into base58 digits, least significant first, and drop the zero padding at the top
+    let mut digits = [0u8; 45];
+    let mut digit_count = 0;
+    for limb in &limbs[..limb_count] {
+        let mut limb = *limb;
+        for _ in 0..5 {
+            digits[digit_count] = (limb % 58) as u8;
+            limb /= 58;
+            digit_count += 1;
+        }
+    }
+    while digit_count > 0 && digits[digit_count - 1] == 0 {
+        digit_count -= 1;
+    }
+
+    // Each leading zero byte is encoded as a '1'
+    let zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+    out[..zeros].fill(b'1');
+    for (i, digit) in digits[..digit_count].iter().rev().enumerate() {
+        out[zeros + i] = BASE58_ALPHABET[*digit as usize];
+    }
+    zeros + digit_count
+}
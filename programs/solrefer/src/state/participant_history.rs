@@ -0,0 +1,108 @@
+use crate::state::{Participant, RefereeReceipt};
+use anchor_lang::{prelude::*, solana_program::program::MAX_RETURN_DATA};
+
+/// One referee receipt in a participant's history, as packed by `get_participant_history`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HistoryEntry {
+    /// What the receipt records for the participant, one of the `ParticipantHistoryV1::KIND_*` constants
+    pub kind: u8,
+    /// The referee's wallet for a referral the participant made; the referrer's participant account for its join
+    pub counterparty: Pubkey,
+    /// The reward credited to the referrer for the referral
+    pub amount: u64,
+    /// When the referral was credited
+    pub timestamp: i64,
+    /// Where a clawback of the referral stands, one of the `RefereeReceipt::CLAWBACK_*` constants
+    pub status: u8,
+}
+
+impl HistoryEntry {
+    /// The size of a serialized `HistoryEntry` in bytes.
+    pub const SIZE: usize = 1 + // kind
+        32 + // counterparty
+        8 + // amount
+        8 + // timestamp
+        1; // status
+
+    /// Builds the entry of a receipt of the given kind.
+    pub fn from_receipt(receipt: &RefereeReceipt, kind: u8) -> Self {
+        let counterparty = if kind == ParticipantHistoryV1::KIND_REFERRED { receipt.referee } else { receipt.referrer };
+        Self {
+            kind,
+            counterparty,
+            amount: receipt.credited_amount,
+            timestamp: receipt.credited_at,
+            status: receipt.clawback_status,
+        }
+    }
+}
+
+/// A participant's headline figures and the referee receipts its client supplied, returned by
+/// `get_participant_history`.
+///
+/// Only the receipts passed to the instruction appear, oldest first; the instruction packs and checks them but
+/// does not discover any. Entries past what fits in the transaction return data are dropped and `truncated` is
+/// set, so a client pages through a long history by passing the receipts after the last entry it got.
+///
+/// Fields are only ever appended; a layout change gets a new `ParticipantHistoryV*` type.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParticipantHistoryV1 {
+    /// The participant's wallet
+    pub owner: Pubkey,
+    /// The participant account of its referrer, if it joined through one
+    pub referrer: Option<Pubkey>,
+    /// When it joined
+    pub join_time: i64,
+    /// Its referrals, less clawbacks
+    pub total_referrals: u64,
+    /// Rewards credited but not yet claimed
+    pub pending_rewards: u64,
+    /// Rewards paid out by claims so far
+    pub total_rewards: u64,
+    /// Removed from its pending rewards by clawbacks
+    pub clawed_back: u64,
+    /// The supplied receipts, sorted by when they were credited
+    pub entries: Vec<HistoryEntry>,
+    /// Some supplied receipts did not fit and were left out
+    pub truncated: bool,
+}
+
+impl ParticipantHistoryV1 {
+    /// A referral the participant made; the receipt names it as the referrer
+    pub const KIND_REFERRED: u8 = 0;
+    /// The participant's own join through a referral; the receipt names it as the referee
+    pub const KIND_JOINED: u8 = 1;
+
+    /// The size of a serialized `ParticipantHistoryV1` without entries, in bytes, when it has a referrer.
+    pub const HEADER_SIZE: usize = 32 + // owner
+        1 + 32 + // referrer
+        8 + // join_time
+        8 + // total_referrals
+        8 + // pending_rewards
+        8 + // total_rewards
+        8 + // clawed_back
+        4 + // entries length
+        1; // truncated
+
+    /// The most entries that fit in the transaction return data.
+    pub const MAX_ENTRIES: usize = (MAX_RETURN_DATA - Self::HEADER_SIZE) / HistoryEntry::SIZE;
+
+    /// Builds the history of a participant from the entries of its receipts, sorting them and dropping those past
+    /// `MAX_ENTRIES`.
+    pub fn new(participant: &Participant, mut entries: Vec<HistoryEntry>) -> Self {
+        entries.sort_by_key(|entry| entry.timestamp);
+        let truncated = entries.len() > Self::MAX_ENTRIES;
+        entries.truncate(Self::MAX_ENTRIES);
+        Self {
+            owner: participant.owner,
+            referrer: participant.referrer,
+            join_time: participant.join_time,
+            total_referrals: participant.total_referrals,
+            pending_rewards: participant.pending_rewards,
+            total_rewards: participant.total_rewards,
+            clawed_back: participant.clawed_back,
+            entries,
+            truncated,
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{
+    instructions::ProgramSettings,
+    space::{layout_size, LayoutField},
+};
+use anchor_lang::prelude::*;
+
+/// A settings update of a governed program that cuts rewards or shortens the program, held until participants
+/// approve it.
+///
+/// A program has at most one pending change. It is closed when it is executed or voided, returning its rent to the
+/// authority that proposed it.
+///
+/// PDA with seeds: ["pending_change", referral_program.key()]
+#[account]
+#[derive(Default)]
+pub struct PendingChange {
+    /// The referral program whose settings would change
+    pub referral_program: Pubkey,
+    /// The position of the change among the program's proposals; votes are keyed by it
+    pub index: u64,
+    /// The authority that proposed the change and paid the rent
+    pub proposer: Pubkey,
+    /// When the change was proposed
+    pub proposed_at: i64,
+    /// Votes are accepted until this time; an unapproved change can be voided after it
+    pub voting_ends_at: i64,
+    /// The program's `total_referrals_credited` when the change was proposed, the total weight of the vote
+    pub referrals_at_proposal: u64,
+    /// The program's approval threshold when the change was proposed, in percent of `referrals_at_proposal`
+    pub approval_threshold_percent: u8,
+    /// Weight of the votes for the change
+    pub approvals: u64,
+    /// Weight of the votes against the change
+    pub rejections: u64,
+    /// Bump seed for the pending change PDA
+    pub bump: u8,
+    /// The settings the change applies once executed
+    pub settings: ProgramSettings,
+}
+
+impl PendingChange {
+    /// Version of the `PendingChange` account layout, bumped whenever its fields change.
+    pub const LAYOUT_VERSION: u8 = 1;
+
+    /// The size of the `PendingChange` account in bytes, excluding the discriminator, for settings that serialize
+    /// to `settings_len` bytes.
+    pub const fn size(settings_len: usize) -> usize {
+        32 + // referral_program
+        8 + // index
+        32 + // proposer
+        8 + // proposed_at
+        8 + // voting_ends_at
+        8 + // referrals_at_proposal
+        1 + // approval_threshold_percent
+        8 + // approvals
+        8 + // rejections
+        1 + // bump
+        settings_len // settings
+    }
+
+    /// Returns true once the approvals exceed the threshold
+    pub fn is_approved(&self) -> bool {
+        u128::from(self.approvals) * 100
+            > u128::from(self.approval_threshold_percent) * u128::from(self.referrals_at_proposal)
+    }
+
+    /// Returns true once the rejections leave too little weight for the approvals to exceed the threshold
+    pub fn is_rejected(&self) -> bool {
+        self.rejections > 0
+            && u128::from(self.rejections) * 100
+                >= u128::from(100 - self.approval_threshold_percent) * u128::from(self.referrals_at_proposal)
+    }
+
+    /// Returns true while participants can still vote at `now`
+    pub fn is_open(&self, now: i64) -> bool {
+        now < self.voting_ends_at && !self.is_approved() && !self.is_rejected()
+    }
+
+    /// Returns true once the change can no longer pass at `now`: rejected, or unapproved past its deadline
+    pub fn is_void(&self, now: i64) -> bool {
+        !self.is_approved() && (self.is_rejected() || now >= self.voting_ends_at)
+    }
+}
+
+/// A participant's vote on one of a program's pending changes, which keeps it from voting twice.
+///
+/// PDA with seeds: ["change_vote", referral_program.key(), index.to_le_bytes(), participant.key()]
+#[account]
+#[derive(Default)]
+pub struct ChangeVote {
+    /// The referral program the change belongs to
+    pub referral_program: Pubkey,
+    /// The `index` of the pending change voted on
+    pub change_index: u64,
+    /// The participant account that voted
+    pub participant: Pubkey,
+    /// Whether the vote approved the change
+    pub approve: bool,
+    /// The participant's credited referrals when it voted
+    pub weight: u64,
+    /// Bump seed for the vote PDA
+    pub bump: u8,
+}
+
+impl ChangeVote {
+    /// Version of the `ChangeVote` account layout, bumped whenever its fields change.
+    pub const LAYOUT_VERSION: u8 = 1;
+
+    /// Each field of the `ChangeVote` account and the most bytes it serializes to, discriminator excluded.
+    pub const LAYOUT: &'static [LayoutField] = &[
+        ("referral_program", 32),
+        ("change_index", 8),
+        ("participant", 32),
+        ("approve", 1),
+        ("weight", 8),
+        ("bump", 1),
+    ];
+
+    /// The size of the `ChangeVote` account in bytes, excluding the discriminator.
+    pub const SIZE: usize = layout_size(Self::LAYOUT);
+}
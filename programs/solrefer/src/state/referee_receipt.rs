@@ -0,0 +1,95 @@
+use crate::{
+    constants::{CLAWBACK_RESOLUTION_TIMEOUT, LINK_PARAMS_LEN, SOURCE_TAG_LEN},
+    space::{layout_size, LayoutField},
+};
+use anchor_lang::prelude::*;
+
+/// Records that a wallet has been credited as a referee in a referral program.
+///
+/// A receipt is created the first time a wallet joins a program through a referral and is never closed,
+/// so a wallet can contribute at most one credited referral per program no matter how many times its
+/// participant account is recreated or which referrer's link it uses.
+#[account]
+#[derive(Default)]
+pub struct RefereeReceipt {
+    /// The referral program this receipt belongs to
+    pub program: Pubkey,
+    /// The wallet that was credited as a referee
+    pub referee: Pubkey,
+    /// The participant account of the referrer that was originally credited
+    pub referrer: Pubkey,
+    /// When the referral was credited
+    pub credited_at: i64,
+    /// Bump seed for the receipt PDA
+    pub bump: u8,
+    /// The reward credited to the referrer for this referral, excluding any payout-split share
+    pub credited_amount: u64,
+    /// Whether the authority has clawed this referral back; cleared again if the clawback is overturned
+    pub clawed_back: bool,
+    /// Campaign tag the referee joined with; zeros when untagged
+    pub source_tag: [u8; SOURCE_TAG_LEN],
+    /// Whether the referral counted toward the referrer's `total_referrals` and `record_count`
+    pub counted: bool,
+    /// Where a clawback of this referral stands, one of the `CLAWBACK_*` constants
+    pub clawback_status: u8,
+    /// The pending rewards the clawback removed from the referrer, held until it is resolved
+    pub disputed_amount: u64,
+    /// When the clawback was opened
+    pub dispute_opened_at: i64,
+    /// The referrer can contest the clawback until this time
+    pub contest_deadline: i64,
+    /// When the referrer contested the clawback; 0 if it did not
+    pub contested_at: i64,
+    /// Hash of the referrer's off-chain evidence; zeros when none was attached
+    pub evidence_hash: [u8; 32],
+    /// The referrer's landing parameters when the referee joined; later changes to them leave this untouched
+    pub referrer_link_params: [u8; LINK_PARAMS_LEN],
+    /// Whether the participant account that joined through this referral is still in the program; it counts
+    /// toward the referrer's `active_referee_count` unless clawed back
+    pub referee_active: bool,
+}
+
+impl RefereeReceipt {
+    /// Version of the `RefereeReceipt` account layout, bumped whenever its fields change.
+    pub const LAYOUT_VERSION: u8 = 5;
+
+    /// No clawback was opened
+    pub const CLAWBACK_NONE: u8 = 0;
+    /// A clawback is open and the referrer can still contest it
+    pub const CLAWBACK_OPEN: u8 = 1;
+    /// The referrer contested the clawback and awaits the authority's ruling
+    pub const CLAWBACK_CONTESTED: u8 = 2;
+    /// The clawback is final and the amount went back to the vault
+    pub const CLAWBACK_UPHELD: u8 = 3;
+    /// The clawback was overturned and the amount restored to the referrer
+    pub const CLAWBACK_OVERTURNED: u8 = 4;
+
+    /// Each field of the `RefereeReceipt` account and the most bytes it serializes to, discriminator excluded.
+    pub const LAYOUT: &'static [LayoutField] = &[
+        ("program", 32),
+        ("referee", 32),
+        ("referrer", 32),
+        ("credited_at", 8),
+        ("bump", 1),
+        ("credited_amount", 8),
+        ("clawed_back", 1),
+        ("source_tag", SOURCE_TAG_LEN),
+        ("counted", 1),
+        ("clawback_status", 1),
+        ("disputed_amount", 8),
+        ("dispute_opened_at", 8),
+        ("contest_deadline", 8),
+        ("contested_at", 8),
+        ("evidence_hash", 32),
+        ("referrer_link_params", LINK_PARAMS_LEN),
+        ("referee_active", 1),
+    ];
+
+    /// The size of the `RefereeReceipt` account in bytes, excluding the discriminator.
+    pub const SIZE: usize = layout_size(Self::LAYOUT);
+
+    /// Returns when the authority's time to resolve a contested clawback runs out
+    pub fn resolution_deadline(&self) -> i64 {
+        self.contested_at.saturating_add(CLAWBACK_RESOLUTION_TIMEOUT)
+    }
+}
@@ -1,6 +1,13 @@
+use crate::{
+    constants::*,
+    error::ReferralError,
+    space::{layout_size, option, LayoutField},
+    state::participant::REFERRAL_LINK_PREFIX,
+};
 use anchor_lang::prelude::*;
 
 #[account]
+#[derive(Default)]
 /// Represents the state of a referral program.
 ///
 /// This struct contains the core configuration and state of a referral program,
@@ -11,7 +18,7 @@ pub struct ReferralProgram {
     pub token_mint: Pubkey,             // 32 (Optional, if None/zero pubkey then use SOL)
     pub fixed_reward_amount: u64,       // 8
     pub locked_period: i64,             // 8
-    pub total_referrals: u64,           // 8
+    pub total_referrals_credited: u64,  // 8 (credited referrals less clawbacks; see total_referrals_raw)
     pub total_rewards_distributed: u64, // 8
     pub total_available: u64,           // 8
     pub is_active: bool,                // 1
@@ -19,6 +26,141 @@ pub struct ReferralProgram {
     pub total_participants: u64,        // 8
     /// Bump seed for the vault PDA
     pub vault_bump: u8, // Add this field
+    /// How `fixed_reward_amount` is denominated (one of the `REWARD_DENOMINATION_*` constants)
+    pub reward_denomination: u8, // 1
+    /// Decimals of `token_mint`, captured at creation (0 for SOL programs)
+    pub token_decimals: u8, // 1
+    /// Rewards credited to participants that have not been claimed yet
+    pub total_committed: u64, // 8
+    /// Deepest referral depth that still earns the referrer a reward (0 = unlimited)
+    pub max_depth: u16, // 2
+    /// Deepest referral depth of any participant so far
+    pub max_observed_depth: u16, // 2
+    /// Whether joining requires an unclaimed `Invite`
+    pub invite_only: bool, // 1
+    /// Number of invites ever minted; the next invite's index
+    pub invite_count: u64, // 8
+    /// Full amount of every purchase attributed to a participant of this program
+    pub total_attributed_volume: u64, // 8
+    /// Joins through a referral counted per source tag, one slot per distinct tag
+    pub source_tag_counts: [SourceTagCount; MAX_SOURCE_TAG_SLOTS], // 24 * 8
+    /// Tagged joins whose tag found no free slot
+    pub other_tag_joins: u64, // 8
+    /// Joins through a referral that carried no source tag (the "direct" bucket)
+    pub untagged_joins: u64, // 8
+    /// Sign-up bonus credited to a referee whose referral was credited, in raw units (0 = none)
+    pub referee_reward_amount: u64, // 8
+    /// Whether the referee bonus waits out the locked period instead of being paid by `join_and_claim_through_referral`
+    pub referee_rewards_locked: bool, // 1
+    /// Cached copy of the eligibility criteria's `program_end_time`, so hot paths can check expiry without it
+    /// (`None` = open-ended)
+    pub program_end_time: Option<i64>, // 8 + 1
+    /// Whether `initialize_token_vault` has created the token vault
+    pub token_vault_initialized: bool, // 1
+    /// When the authority requested the program's closure (0 = none); joins and deposits stop meanwhile
+    pub closure_requested_at: i64, // 8
+    /// Hash of the off-chain terms of service participants accept when joining
+    pub terms_hash: [u8; 32], // 32
+    /// Incremented each time `update_terms` replaces `terms_hash`
+    pub terms_version: u16, // 2
+    /// Share of each deposit ring-fenced in `reserved_balance`, in basis points
+    pub reserve_bps: u64, // 8
+    /// Deposited funds held back from claims until `release_reserve`; not part of `total_available`
+    pub reserved_balance: u64, // 8
+    /// Bitmask of the `SETUP_*` steps completed so far
+    pub setup_state: u8, // 1
+    /// Whether the uncommitted funds covered a referral reward when last checked; referrals credit nothing while
+    /// false. Cleared by claims and withdrawals, restored by deposits; `crank_maintenance` also keeps it in step and
+    /// clears it once the program has ended.
+    pub accepting_referrals: bool, // 1
+    /// How long a referrer can contest a clawback of its referral; clawbacks are final at once when 0
+    pub dispute_window_seconds: i64, // 8
+    /// Key that can freeze the program and cancel queued withdrawals, set at creation (default = none)
+    pub guardian: Pubkey, // 32
+    /// Withdrawals above this amount wait out `WITHDRAWAL_DELAY` when the program has a guardian
+    pub withdrawal_delay_threshold: u64, // 8
+    /// Set by the guardian; claims and withdrawals stop until the authority and guardian unfreeze it
+    pub frozen: bool, // 1
+    /// How participants store their referral link (one of the `LINK_FORMAT_*` constants); programs created before
+    /// slugs keep `LINK_FORMAT_LEGACY`
+    pub link_format: u8, // 1
+    /// Who pays the rent of auxiliary accounts such as referee receipts (one of the `RENT_PAYER_*` constants)
+    pub rent_payer_mode: u8, // 1
+    /// Every join through a referral, credited or not; `total_referrals_credited` only counts credited ones
+    pub total_referrals_raw: u64, // 8
+    /// The highest `total_available` any deposit has brought the program to
+    pub peak_total_available: u64, // 8
+    /// Percentages of `peak_total_available` below which a `RunwayAlert` fires; 0 disables a slot
+    pub alert_thresholds: [u8; RUNWAY_ALERT_SLOTS], // 3
+    /// Bit `i` is set once the alert of `alert_thresholds[i]` has fired, until a deposit lifts the funds above it or
+    /// the threshold is changed
+    pub alerts_fired: u8, // 1
+    /// Whether reward claims must be top-level instructions of their transaction rather than CPIs
+    pub direct_claims_only: bool, // 1
+    /// Every deposit ever credited, reserved shares included; unlike `total_available`, never reduced
+    pub total_deposited: u64, // 8
+    /// Protocol fees the authority paid for this program, which is its creation fee
+    pub total_fees_paid: u64, // 8
+    /// `total_available` for display, in hundredths of a whole token (of a SOL for SOL programs); see `to_ui`.
+    /// Never used in the program's own accounting
+    pub total_available_ui: u64, // 8
+    /// `total_rewards_distributed` for display, in hundredths of a whole token (of a SOL for SOL programs)
+    pub total_rewards_distributed_ui: u64, // 8
+    /// Addresses besides the authority that withdrawals may pay out to, set at creation and only changed with the
+    /// guardian's signature; all-default leaves withdrawals unrestricted
+    pub allowed_withdrawal_destinations: [Pubkey; MAX_WITHDRAWAL_DESTINATIONS], // 128
+    /// The idempotency keys of the latest authority mutations that carried one, oldest overwritten first
+    pub recent_idempotency_keys: [[u8; IDEMPOTENCY_KEY_LEN]; IDEMPOTENCY_LOG_LEN], // 128
+    /// The slot of `recent_idempotency_keys` the next key is written to
+    pub next_idempotency_slot: u8, // 1
+    /// Settings changes recorded so far, and the index of the next `SettingsChangeRecord`
+    pub settings_change_count: u64, // 8
+    /// How long the authority may go without acting before anyone can declare the program abandoned; 0 stands
+    /// for `DEFAULT_DORMANCY_PERIOD`
+    pub dormancy_period_seconds: i64, // 8
+    /// The last time an instruction signed by the authority changed the program
+    pub last_authority_action: i64, // 8
+    /// When the program was declared abandoned (0 = never); the authority can no longer act on it after that
+    pub abandoned_at: i64, // 8
+    /// The share of the reclaimed rent `cleanup` pays its caller, in basis points; 0 stands for
+    /// `DEFAULT_CLEANUP_BOUNTY_BPS`
+    pub cleanup_bounty_bps: u16, // 2
+    /// Whether settings updates that cut rewards or shorten the program need participant approval; once set it
+    /// cannot be cleared
+    pub governance_mode: bool, // 1
+    /// The share of `total_referrals_credited` approvals must exceed for a pending change to pass, in percent
+    pub approval_threshold_percent: u8, // 1
+    /// Settings changes proposed for participant approval so far, and the index of the next one
+    pub change_proposal_count: u64, // 8
+    /// Until when the authority cannot change the program's settings, criteria or funds, set at creation and
+    /// immutable (0 = never locked)
+    pub settings_locked_until: i64, // 8
+    /// How joins report their events, one of the `EVENT_MODE_*` constants
+    pub event_mode: u8, // 1
+    /// How long after joining directly a participant can still name its referrer with `set_referrer_late`, in
+    /// seconds (0 = never)
+    pub attribution_grace_seconds: i64, // 8
+    /// How long a seeded participant must sit untouched before the authority can reclaim it, in seconds (0 = never)
+    pub seed_reclaim_seconds: i64, // 8
+    /// Fee forfeited by claims made with `early_claim_rewards` or `early_claim_token_rewards` before the locked
+    /// period has passed, in basis points (0 = no early redemption)
+    pub early_redemption_fee: u64, // 8
+    /// Tells apart the programs of one authority; part of the program account's seeds after the authority, except
+    /// for index 0 (see `index_seed`)
+    pub program_index: u64, // 8
+    /// Set by `close_token_vault`; the program keeps its mint, but its token vault cannot be opened again
+    pub token_vault_closed: bool, // 1
+    /// Lamports funded into the contest escrow that have not been paid out as prizes or withdrawn yet; the program
+    /// cannot be closed while any remain
+    pub contest_escrowed: u64, // 8
+    /// Set while a queued withdrawal waits to be executed or cancelled; the program cannot be closed meanwhile
+    pub withdrawal_pending: bool, // 1
+    /// Referrers' boost escrows of the program that have not been closed; the program cannot be closed while any
+    /// remain
+    pub open_boost_escrows: u64, // 8
+    /// Sponsors' match offers on the program that have not been closed; the program cannot be closed while any
+    /// remain
+    pub open_match_offers: u64, // 8
 }
 
 /// The size of the `ReferralProgram` account in bytes.
@@ -27,20 +169,420 @@ pub struct ReferralProgram {
 /// the discriminator, all the fields, and any padding required by the Solana
 /// runtime.
 impl ReferralProgram {
-    pub const SIZE: usize = 8 + // discriminator
-        32 + // authority
-        32 + // token_mint
-        8 + // fixed_reward_amount
-        8 + // locked_period
-        8 + // early_redemption_fee
-        8 + // min_stake_amount
-        8 + // total_referrals
-        8 + // total_rewards_distributed
-        8 + // total_available
-        1 + // is_active
-        1 + // bump
-        8 + // total_participants
-        1; // vault_bump
+    /// Version of the `ReferralProgram` account layout, bumped whenever its fields change.
+    pub const LAYOUT_VERSION: u8 = 26;
+
+    /// Each field of the `ReferralProgram` account and the most bytes it serializes to, discriminator excluded.
+    pub const LAYOUT: &'static [LayoutField] = &[
+        ("authority", 32),
+        ("token_mint", 32),
+        ("fixed_reward_amount", 8),
+        ("locked_period", 8),
+        ("total_referrals_credited", 8),
+        ("total_rewards_distributed", 8),
+        ("total_available", 8),
+        ("is_active", 1),
+        ("bump", 1),
+        ("total_participants", 8),
+        ("vault_bump", 1),
+        ("reward_denomination", 1),
+        ("token_decimals", 1),
+        ("total_committed", 8),
+        ("max_depth", 2),
+        ("max_observed_depth", 2),
+        ("invite_only", 1),
+        ("invite_count", 8),
+        ("total_attributed_volume", 8),
+        ("source_tag_counts", SourceTagCount::SIZE * MAX_SOURCE_TAG_SLOTS),
+        ("other_tag_joins", 8),
+        ("untagged_joins", 8),
+        ("referee_reward_amount", 8),
+        ("referee_rewards_locked", 1),
+        ("program_end_time", option(8)),
+        ("token_vault_initialized", 1),
+        ("closure_requested_at", 8),
+        ("terms_hash", 32),
+        ("terms_version", 2),
+        ("reserve_bps", 8),
+        ("reserved_balance", 8),
+        ("setup_state", 1),
+        ("accepting_referrals", 1),
+        ("dispute_window_seconds", 8),
+        ("guardian", 32),
+        ("withdrawal_delay_threshold", 8),
+        ("frozen", 1),
+        ("link_format", 1),
+        ("rent_payer_mode", 1),
+        ("total_referrals_raw", 8),
+        ("peak_total_available", 8),
+        ("alert_thresholds", RUNWAY_ALERT_SLOTS),
+        ("alerts_fired", 1),
+        ("direct_claims_only", 1),
+        ("total_deposited", 8),
+        ("total_fees_paid", 8),
+        ("total_available_ui", 8),
+        ("total_rewards_distributed_ui", 8),
+        ("allowed_withdrawal_destinations", 32 * MAX_WITHDRAWAL_DESTINATIONS),
+        ("recent_idempotency_keys", IDEMPOTENCY_KEY_LEN * IDEMPOTENCY_LOG_LEN),
+        ("next_idempotency_slot", 1),
+        ("settings_change_count", 8),
+        ("dormancy_period_seconds", 8),
+        ("last_authority_action", 8),
+        ("abandoned_at", 8),
+        ("cleanup_bounty_bps", 2),
+        ("governance_mode", 1),
+        ("approval_threshold_percent", 1),
+        ("change_proposal_count", 8),
+        ("settings_locked_until", 8),
+        ("event_mode", 1),
+        ("attribution_grace_seconds", 8),
+        ("seed_reclaim_seconds", 8),
+        ("early_redemption_fee", 8),
+        ("program_index", 8),
+        ("token_vault_closed", 1),
+        ("contest_escrowed", 8),
+        ("withdrawal_pending", 1),
+        ("open_boost_escrows", 8),
+        ("open_match_offers", 8),
+        // Counted since before the layout: a second discriminator and the removed `min_stake_amount`
+        ("reserved", 8 + 8),
+    ];
+
+    pub const SIZE: usize = layout_size(Self::LAYOUT);
+
+    /// `setup_state` bit set by `create_referral_program`
+    pub const SETUP_CREATED: u8 = 1 << 0;
+    /// `setup_state` bit set once the criteria are configured: at creation for programs created active, by
+    /// `update_program_settings` otherwise
+    pub const SETUP_CRITERIA_SET: u8 = 1 << 1;
+    /// `setup_state` bit set by `initialize_token_vault`; SOL programs get it at creation, their vault needs no
+    /// initialization
+    pub const SETUP_VAULT_INITIALIZED: u8 = 1 << 2;
+    /// `setup_state` bit set by the first non-zero deposit
+    pub const SETUP_FUNDED: u8 = 1 << 3;
+    /// `setup_state` bit set once the program is active: at creation or by `activate_program`
+    pub const SETUP_ACTIVATED: u8 = 1 << 4;
+    /// Every setup step in the order they are performed
+    pub const SETUP_STEPS: [u8; 5] = [
+        Self::SETUP_CREATED,
+        Self::SETUP_CRITERIA_SET,
+        Self::SETUP_VAULT_INITIALIZED,
+        Self::SETUP_FUNDED,
+        Self::SETUP_ACTIVATED,
+    ];
+
+    /// Returns the first setup step not completed yet, or 0 once setup is complete.
+    pub fn next_setup_step(&self) -> u8 {
+        Self::SETUP_STEPS.into_iter().find(|step| self.setup_state & step == 0).unwrap_or(0)
+    }
+
+    /// Checks that a setup step has been completed, returning `error` (which names the step) otherwise.
+    pub fn require_setup_step(&self, step: u8, error: ReferralError) -> Result<()> {
+        if self.setup_state & step == 0 {
+            return Err(error.into());
+        }
+        Ok(())
+    }
+
+    /// Returns the reward credited per referral in raw units (lamports or base token units),
+    /// converting from the program's reward denomination.
+    ///
+    /// The reward is the flat `fixed_reward_amount`, the same for every referrer: no referral is rewarded by tier.
+    /// The tier rewards only serve as the lower steps `EligibilityCriteria::downgraded_reward` falls back to.
+    pub fn referral_reward_amount(&self) -> Result<u64> {
+        match self.reward_denomination {
+            REWARD_DENOMINATION_USD_CENTS => usd_cents_to_raw(self.fixed_reward_amount, self.token_decimals),
+            _ => Ok(self.fixed_reward_amount),
+        }
+    }
+
+    /// Adds a deposit of `amount` to the program, ring-fencing `reserve_bps` of it in `reserved_balance` and
+    /// making the rest available to claims.
+    ///
+    /// Returns the reserved part of the deposit. A non-zero deposit also completes the `SETUP_FUNDED` step.
+    pub fn credit_deposit(&mut self, amount: u64) -> Result<u64> {
+        let reserved = u64::try_from(u128::from(amount) * u128::from(self.reserve_bps) / 10_000)
+            .map_err(|_| ReferralError::NumericOverflow)?;
+        let available = amount.checked_sub(reserved).ok_or(ReferralError::NumericOverflow)?;
+        let total_available = self.total_available.checked_add(available).ok_or(ReferralError::NumericOverflow)?;
+        let reserved_balance = self.reserved_balance.checked_add(reserved).ok_or(ReferralError::NumericOverflow)?;
+        let total_deposited = self.total_deposited.checked_add(amount).ok_or(ReferralError::NumericOverflow)?;
+        self.total_available = total_available;
+        self.reserved_balance = reserved_balance;
+        self.total_deposited = total_deposited;
+        if amount > 0 {
+            self.setup_state |= Self::SETUP_FUNDED;
+        }
+        self.peak_total_available = self.peak_total_available.max(total_available);
+        self.rearm_runway_alerts();
+        self.refresh_ui_totals();
+        Ok(reserved)
+    }
+
+    /// Returns the decimals of the program's reward unit: its mint's, or SOL's for a SOL program
+    pub fn ui_decimals(&self) -> u8 {
+        if self.token_mint == Pubkey::default() {
+            SOL_DECIMALS
+        } else {
+            self.token_decimals
+        }
+    }
+
+    /// Recomputes `total_available_ui` and `total_rewards_distributed_ui` from the raw totals, after every change
+    /// to them.
+    pub fn refresh_ui_totals(&mut self) {
+        self.total_available_ui = to_ui(self.total_available, self.ui_decimals());
+        self.total_rewards_distributed_ui = to_ui(self.total_rewards_distributed, self.ui_decimals());
+    }
+
+    /// Returns true if `total_available` is below `threshold_pct` percent of `peak_total_available`.
+    pub fn is_below_runway(&self, threshold_pct: u8) -> bool {
+        u128::from(self.total_available) * 100 < u128::from(self.peak_total_available) * u128::from(threshold_pct)
+    }
+
+    /// Marks the runway alerts whose threshold `total_available` has fallen below since they were last armed.
+    ///
+    /// Returns a bitmask of them; each alert is returned once until `rearm_runway_alerts` arms it again.
+    pub fn take_runway_alerts(&mut self) -> u8 {
+        let mut due = 0;
+        for (index, &threshold) in self.alert_thresholds.iter().enumerate() {
+            if threshold > 0 && self.alerts_fired & (1 << index) == 0 && self.is_below_runway(threshold) {
+                due |= 1 << index;
+            }
+        }
+        self.alerts_fired |= due;
+        due
+    }
+
+    /// Replaces the runway alert thresholds, arming again only the alerts whose slot changed; an alert that already
+    /// fired for an unchanged threshold stays fired until a deposit lifts the funds back above it.
+    pub fn set_alert_thresholds(&mut self, thresholds: [u8; RUNWAY_ALERT_SLOTS]) {
+        for (index, (&old, &new)) in self.alert_thresholds.iter().zip(&thresholds).enumerate() {
+            if old != new {
+                self.alerts_fired &= !(1 << index);
+            }
+        }
+        self.alert_thresholds = thresholds;
+    }
+
+    /// Arms again every runway alert whose threshold `total_available` is back at or above.
+    pub fn rearm_runway_alerts(&mut self) {
+        for (index, &threshold) in self.alert_thresholds.iter().enumerate() {
+            if !self.is_below_runway(threshold) {
+                self.alerts_fired &= !(1 << index);
+            }
+        }
+    }
+
+    /// Returns how many more referral rewards the uncommitted funds cover.
+    pub fn estimated_referrals_remaining(&self) -> Result<u64> {
+        Ok(self.headroom().checked_div(self.referral_reward_amount()?).unwrap_or(0))
+    }
+
+    /// Returns the uncommitted funds, `total_available - total_committed`
+    pub fn headroom(&self) -> u64 {
+        self.total_available.saturating_sub(self.total_committed)
+    }
+
+    /// Returns true if the uncommitted funds cover one referral reward
+    pub fn can_fund_referral(&self) -> Result<bool> {
+        Ok(self.headroom() >= self.referral_reward_amount()?)
+    }
+
+    /// Returns the seeds the program account signs with as the authority of its token vault.
+    ///
+    /// Every CPI signed by the program account derives its seeds here, so a change to the account's
+    /// derivation only has to be made in one place. `index_bytes` holds `program_index.to_le_bytes()`, which the
+    /// seeds borrow.
+    pub fn signer_seeds<'a>(&'a self, index_bytes: &'a [u8; 8]) -> [&'a [u8]; 4] {
+        [
+            REFERRAL_PROGRAM_SEED,
+            self.authority.as_ref(),
+            Self::index_seed(index_bytes),
+            std::slice::from_ref(&self.bump),
+        ]
+    }
+
+    /// Returns the seed a program's index adds to its address, given `program_index.to_le_bytes()`.
+    ///
+    /// Index 0 adds nothing, so an authority's first program keeps the `[REFERRAL_PROGRAM_SEED, authority]`
+    /// address programs were created at before they had an index; every other index adds its bytes.
+    pub fn index_seed(index_bytes: &[u8; 8]) -> &[u8] {
+        if *index_bytes == [0; 8] {
+            &[]
+        } else {
+            index_bytes
+        }
+    }
+
+    /// Returns the base URL this program's referral slugs are joined to.
+    ///
+    /// Every program shares `REFERRAL_LINK_PREFIX` for now. Slugs never include it, so a new domain only has to
+    /// change here.
+    pub fn base_url(&self) -> &'static [u8] {
+        REFERRAL_LINK_PREFIX
+    }
+
+    /// Returns true if the program was created with a guardian
+    pub fn has_guardian(&self) -> bool {
+        self.guardian != Pubkey::default()
+    }
+
+    /// Returns true if the authority's withdrawals may pay out to `destination`: the authority itself always can,
+    /// and any address can while the allow-list is empty
+    pub fn is_withdrawal_destination_allowed(&self, destination: Pubkey) -> bool {
+        let destinations = &self.allowed_withdrawal_destinations;
+        if destinations.iter().all(|address| *address == Pubkey::default()) {
+            return true;
+        }
+        destination == self.authority || (destination != Pubkey::default() && destinations.contains(&destination))
+    }
+
+    /// Records the idempotency key of an authority mutation, failing if it is one of the last
+    /// `IDEMPOTENCY_LOG_LEN` recorded so a retried transaction that already landed is not applied twice.
+    ///
+    /// A mutation without a key is never checked. Keys expire only by being overwritten by newer ones.
+    pub fn record_idempotency_key(&mut self, key: Option<[u8; IDEMPOTENCY_KEY_LEN]>) -> Result<()> {
+        let Some(key) = key else {
+            return Ok(());
+        };
+        require!(key != [0; IDEMPOTENCY_KEY_LEN], ReferralError::InvalidIdempotencyKey);
+        require!(!self.recent_idempotency_keys.contains(&key), ReferralError::DuplicateIdempotencyKey);
+        let slot = usize::from(self.next_idempotency_slot) % IDEMPOTENCY_LOG_LEN;
+        self.recent_idempotency_keys[slot] = key;
+        self.next_idempotency_slot = ((slot + 1) % IDEMPOTENCY_LOG_LEN) as u8;
+        Ok(())
+    }
+
+    /// Records that the authority acted on the program at `now`, failing once the program was declared abandoned.
+    ///
+    /// Every instruction the authority signs calls this, so `last_authority_action` shows whether the authority
+    /// is still around.
+    pub fn record_authority_action(&mut self, now: i64) -> Result<()> {
+        require!(!self.is_abandoned(), ReferralError::ProgramAbandoned);
+        self.last_authority_action = now;
+        Ok(())
+    }
+
+    /// Checks that the creator's settings lock has lapsed by `now`.
+    ///
+    /// Every authority instruction that changes the program's settings, criteria or funds calls this; deposits,
+    /// the guardian's actions and unfreezing stay open during the lock.
+    pub fn require_settings_unlocked(&self, now: i64) -> Result<()> {
+        require!(now >= self.settings_locked_until, ReferralError::SettingsTimelocked);
+        Ok(())
+    }
+
+    /// Returns true once the program was declared abandoned
+    pub fn is_abandoned(&self) -> bool {
+        self.abandoned_at != 0
+    }
+
+    /// Returns when the program can be declared abandoned if its authority does not act before then
+    pub fn dormant_at(&self) -> i64 {
+        let period = match self.dormancy_period_seconds {
+            0 => DEFAULT_DORMANCY_PERIOD,
+            period => period,
+        };
+        self.last_authority_action.saturating_add(period)
+    }
+
+    /// Returns the share of reclaimed rent `cleanup` pays its caller, in basis points
+    pub fn cleanup_bounty_bps(&self) -> u16 {
+        match self.cleanup_bounty_bps {
+            0 => DEFAULT_CLEANUP_BOUNTY_BPS,
+            bps => bps,
+        }
+    }
+
+    /// Returns true once the cached program end time has passed; an open-ended program never ends
+    pub fn has_ended(&self, now: i64) -> bool {
+        self.program_end_time.is_some_and(|end| now >= end)
+    }
+
+    /// Returns true while a requested closure has not been cancelled or finalized
+    pub fn is_closing(&self) -> bool {
+        self.closure_requested_at != 0
+    }
+
+    /// Returns when a requested closure can be finalized, if one is pending
+    pub fn closure_effective_at(&self) -> Option<i64> {
+        self.is_closing().then(|| self.closure_requested_at.saturating_add(CLOSURE_GRACE_PERIOD))
+    }
+
+    /// Checks that a participant accepted the program's current terms
+    pub fn require_current_terms(&self, accepted_terms_hash: &[u8; 32]) -> Result<()> {
+        require!(*accepted_terms_hash == self.terms_hash, ReferralError::TermsMismatch);
+        Ok(())
+    }
+
+    /// Counts a join through a referral under its source tag.
+    ///
+    /// An all-zero tag counts as untagged. A new tag claims the first empty slot; once every slot holds
+    /// another tag it is counted in `other_tag_joins` instead.
+    pub fn record_source_tag(&mut self, tag: &[u8; SOURCE_TAG_LEN]) {
+        if *tag == [0u8; SOURCE_TAG_LEN] {
+            self.untagged_joins = self.untagged_joins.saturating_add(1);
+            return;
+        }
+        let slot = match self.source_tag_counts.iter().position(|slot| slot.tag == *tag) {
+            Some(index) => Some(index),
+            None => self.source_tag_counts.iter().position(|slot| !slot.is_set()),
+        };
+        match slot {
+            Some(index) => {
+                let slot = &mut self.source_tag_counts[index];
+                slot.tag = *tag;
+                slot.count = slot.count.saturating_add(1);
+            }
+            None => self.other_tag_joins = self.other_tag_joins.saturating_add(1),
+        }
+    }
+}
+
+/// The number of joins through a referral that carried `tag`.
+///
+/// Entries with an all-zero tag are unused.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SourceTagCount {
+    pub tag: [u8; SOURCE_TAG_LEN],
+    pub count: u64,
+}
+
+impl SourceTagCount {
+    /// The serialized size of a source tag count in bytes.
+    pub const SIZE: usize = SOURCE_TAG_LEN + 8;
+
+    /// Returns true if this entry holds a tag
+    pub fn is_set(&self) -> bool {
+        self.tag != [0u8; SOURCE_TAG_LEN]
+    }
+}
+
+/// Validates that a source tag is ASCII. Tags shorter than `SOURCE_TAG_LEN` are zero-padded.
+pub fn validate_source_tag(tag: &[u8; SOURCE_TAG_LEN]) -> Result<()> {
+    require!(tag.is_ascii(), ReferralError::InvalidSourceTag);
+    Ok(())
+}
+
+/// Converts an amount in US cents to raw units of a USD stable mint with the given decimals.
+///
+/// One cent is `10^(decimals - 2)` raw units, so mints with fewer than 2 decimals cannot represent cents.
+pub fn usd_cents_to_raw(cents: u64, decimals: u8) -> Result<u64> {
+    require!(decimals >= 2, ReferralError::InsufficientMintDecimals);
+    let scale = 10u64.checked_pow(u32::from(decimals - 2)).ok_or(ReferralError::NumericOverflow)?;
+    cents.checked_mul(scale).ok_or(ReferralError::NumericOverflow.into())
+}
+
+/// Converts a raw amount of a unit with `decimals` decimals to hundredths of a whole unit, rounding down and
+/// saturating at `u64::MAX`.
+///
+/// The result is only ever displayed, so it cannot fail: a mint with more decimals than `u128` can scale by
+/// converts everything to zero.
+pub fn to_ui(amount: u64, decimals: u8) -> u64 {
+    let hundredths = u128::from(amount) * 100;
+    let ui = 10u128.checked_pow(u32::from(decimals)).map_or(0, |scale| hundredths / scale);
+    u64::try_from(ui).unwrap_or(u64::MAX)
 }
 
 /// Represents the eligibility criteria for a referral program.
@@ -61,30 +603,245 @@ pub struct EligibilityCriteria {
     pub max_reward_cap: u64,        // 8
     pub revenue_share_percent: u64, // 8
 
-    // Optional Token Requirement
-    pub required_token: Option<Pubkey>, // 32 + 1
-    pub min_token_amount: u64,          // 8
+    // Token holdings required of each side of a referral (`None` = no requirement)
+    pub referrer_requirement: Option<TokenRequirement>, // 1 + 40
+    pub referee_requirement: Option<TokenRequirement>,  // 1 + 40
 
     // Time Parameters
-    pub program_start_time: i64, // 8
-    pub program_end_time: i64,   // 8 + 1
+    pub program_start_time: i64,       // 8
+    pub program_end_time: Option<i64>, // 8 + 1 (None = open-ended)
 
     // Status
-    pub is_active: bool,   // 1
+    /// Deprecated activity flag, kept for layout compatibility; never read. `ReferralProgram.is_active` alone
+    /// says whether a program is active
+    pub reserved_is_active: bool, // 1
     pub last_updated: i64, // 8
     pub bump: u8,          // 1
+
+    // One-time bonuses for crossing referral counts
+    pub milestones: [Milestone; MAX_MILESTONES], // 16 * 4
+
+    // Per-referrer cap on credited referrals within a rolling window (0 in either field disables it)
+    pub max_referrals_per_window: u32, // 4
+    pub referral_window_seconds: i64,  // 8
+    /// Whether a join over the cap is rejected with `ReferralRateLimited` instead of joining uncredited
+    pub rate_limit_strict: bool, // 1
+
+    // NFT collection gate
+    /// Verified collection whose NFT a wallet must hold to join as a referrer (`None` = no gate)
+    pub required_collection: Option<Pubkey>, // 32 + 1
+    /// Whether referrers must also hold the collection's NFT each time a referral credits them
+    pub collection_gates_credits: bool, // 1
+
+    /// Whether participants may transfer pending rewards to each other with `transfer_pending`
+    pub transfers_enabled: bool, // 1
+
+    // Anti-sybil join requirements (0 disables each)
+    /// Lamports a wallet must hold before paying for its participant account to join
+    pub min_joiner_balance: u64, // 8
+    /// How long ago a wallet must have joined some program to join this one
+    pub min_account_age_seconds: i64, // 8
+
+    /// Share of each referee-side credit also credited to the referee's referrer, in basis points (0 = none)
+    pub trailing_commission_bps: u64, // 8
+
+    /// Key whose signed referral link a wallet must present to join through a referrer (`None` = no links needed)
+    pub link_signer: Option<Pubkey>, // 32 + 1
+
+    // Region embargo
+    /// Program whose per-wallet region attestations a wallet must present to join (`None` = no embargo)
+    pub region_attestor: Option<Pubkey>, // 32 + 1
+    /// ISO 3166-1 numeric codes of the regions whose wallets cannot join; zeroed entries are ignored
+    pub embargoed_regions: [u16; MAX_EMBARGOED_REGIONS], // 2 * 8
+    /// Whether claims re-check the claiming wallet's region instead of only joins
+    pub region_checked_on_claim: bool, // 1
+
+    // Referral bridge
+    /// Program whose referrers are credited a bridge credit when their referees join this one (`None` = no bridge)
+    pub bridge_source_program: Option<Pubkey>, // 32 + 1
+    /// Bridge credit in basis points of `base_reward`
+    pub bridge_bps: u64, // 8
+
+    // Budget-aware rewards
+    /// Whether the flat referral reward steps down through the tier and base rewards when the uncommitted funds
+    /// cannot sustain it, see `downgraded_reward`
+    pub auto_downgrade: bool, // 1
+    /// How many more rewards the uncommitted funds must cover for a reward to be sustainable under `auto_downgrade`
+    pub min_remaining_credits: u32, // 4
 }
 
 /// Defines the total size of the `EligibilityCriteria` account, including the
 /// discriminator, all the fields, and any padding required by the Solana runtime.
 impl EligibilityCriteria {
-    pub const SIZE: usize = 8 + // discriminator
-        8 * 7 + // reward structure (u64s)
-        (32 + 1) + // required_token (Option<Pubkey>)
-        8 + // min_token_amount
-        8 + // program_start_time
-        (8 + 1) + // program_end_time (Option<i64>)
-        1 + // is_active
-        8 + // last_updated
-        1; // bump
+    /// Version of the `EligibilityCriteria` account layout, bumped whenever its fields change.
+    pub const LAYOUT_VERSION: u8 = 11;
+
+    /// Each field of the `EligibilityCriteria` account and the most bytes it serializes to, discriminator excluded.
+    pub const LAYOUT: &'static [LayoutField] = &[
+        ("base_reward", 8),
+        ("tier1_threshold", 8),
+        ("tier1_reward", 8),
+        ("tier2_threshold", 8),
+        ("tier2_reward", 8),
+        ("max_reward_cap", 8),
+        ("revenue_share_percent", 8),
+        ("referrer_requirement", option(TokenRequirement::SIZE)),
+        ("referee_requirement", option(TokenRequirement::SIZE)),
+        ("program_start_time", 8),
+        ("program_end_time", option(8)),
+        ("reserved_is_active", 1),
+        ("last_updated", 8),
+        ("bump", 1),
+        ("milestones", Milestone::SIZE * MAX_MILESTONES),
+        ("max_referrals_per_window", 4),
+        ("referral_window_seconds", 8),
+        ("rate_limit_strict", 1),
+        ("required_collection", option(32)),
+        ("collection_gates_credits", 1),
+        ("transfers_enabled", 1),
+        ("min_joiner_balance", 8),
+        ("min_account_age_seconds", 8),
+        ("trailing_commission_bps", 8),
+        ("link_signer", option(32)),
+        ("region_attestor", option(32)),
+        ("embargoed_regions", 2 * MAX_EMBARGOED_REGIONS),
+        ("region_checked_on_claim", 1),
+        ("bridge_source_program", option(32)),
+        ("bridge_bps", 8),
+        ("auto_downgrade", 1),
+        ("min_remaining_credits", 4),
+        // A second discriminator counted since before the layout, kept so the account's size does not change
+        ("reserved", 8),
+    ];
+
+    pub const SIZE: usize = layout_size(Self::LAYOUT);
+
+    /// Returns the reward to credit for a referral whose full reward is `requested`, the program's flat
+    /// `referral_reward_amount`, with `headroom` uncommitted funds left in the program.
+    ///
+    /// Without `auto_downgrade` the full reward is credited whatever the headroom. With it, a reward is sustainable
+    /// when the headroom covers it `min_remaining_credits` times, and the reward steps down from `requested`
+    /// through the tier 2, tier 1 and base rewards below it to the first sustainable one. The lowest step is
+    /// credited as long as the headroom covers it once; only then is the reward zero.
+    pub fn downgraded_reward(&self, requested: u64, headroom: u64) -> u64 {
+        if !self.auto_downgrade {
+            return requested;
+        }
+        let sustainable =
+            |reward: u64| u128::from(reward) * u128::from(self.min_remaining_credits) <= u128::from(headroom);
+        let mut reward = requested;
+        for lower in [self.tier2_reward, self.tier1_reward, self.base_reward] {
+            if sustainable(reward) {
+                return reward;
+            }
+            if lower > 0 && lower < reward {
+                reward = lower;
+            }
+        }
+        if reward <= headroom {
+            reward
+        } else {
+            0
+        }
+    }
+
+    /// Returns true if referrers are limited to `max_referrals_per_window` credited referrals per window.
+    pub fn is_rate_limited(&self) -> bool {
+        self.max_referrals_per_window > 0 && self.referral_window_seconds > 0
+    }
+
+    /// Returns the revenue share of a purchase of `amount` before any cap is applied.
+    pub fn revenue_share(&self, amount: u64) -> Result<u64> {
+        let share = u128::from(amount)
+            .checked_mul(u128::from(self.revenue_share_percent))
+            .map(|product| product / 10_000)
+            .and_then(|share| u64::try_from(share).ok())
+            .ok_or(ReferralError::NumericOverflow)?;
+        Ok(share)
+    }
+
+    /// Returns the trailing commission a participant's referrer earns on a credit of `amount` to the participant,
+    /// before it is clamped to the program's uncommitted funds.
+    pub fn trailing_commission(&self, amount: u64) -> Result<u64> {
+        let commission = u128::from(amount)
+            .checked_mul(u128::from(self.trailing_commission_bps))
+            .map(|product| product / 10_000)
+            .and_then(|commission| u64::try_from(commission).ok())
+            .ok_or(ReferralError::NumericOverflow)?;
+        Ok(commission)
+    }
+
+    /// Returns the bridge credit a referrer in the bridge source program earns when its referee joins this program,
+    /// before it is clamped to the program's uncommitted funds.
+    pub fn bridge_credit(&self) -> Result<u64> {
+        let credit = u128::from(self.base_reward)
+            .checked_mul(u128::from(self.bridge_bps))
+            .map(|product| product / 10_000)
+            .and_then(|credit| u64::try_from(credit).ok())
+            .ok_or(ReferralError::NumericOverflow)?;
+        Ok(credit)
+    }
+
+    /// Returns the revenue share of a purchase of `amount` credited to a referrer that has already earned
+    /// `earned`, clamped so the referrer's earnings never exceed `max_reward_cap` (0 = uncapped).
+    pub fn purchase_reward(&self, amount: u64, earned: u64) -> Result<u64> {
+        let share = self.revenue_share(amount)?;
+        if self.max_reward_cap == 0 {
+            return Ok(share);
+        }
+        Ok(share.min(self.max_reward_cap.saturating_sub(earned)))
+    }
+}
+
+/// A one-time bonus credited to a referrer whose `total_referrals` reaches `threshold`.
+///
+/// Entries with a zero threshold are unused.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Milestone {
+    pub threshold: u64,
+    pub bonus: u64,
+}
+
+impl Milestone {
+    /// The serialized size of a milestone in bytes.
+    pub const SIZE: usize = 8 + 8;
+
+    /// Returns true if this entry is configured
+    pub fn is_set(&self) -> bool {
+        self.threshold != 0
+    }
+}
+
+/// A token a wallet must hold at least `min_amount` of, in raw units of `mint`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TokenRequirement {
+    pub mint: Pubkey,
+    pub min_amount: u64,
+}
+
+impl TokenRequirement {
+    /// The serialized size of a token requirement in bytes.
+    pub const SIZE: usize = 32 + 8;
+}
+
+/// Validates that the configured milestones have strictly ascending thresholds, ignoring unused entries.
+pub fn validate_milestones(milestones: &[Milestone; MAX_MILESTONES]) -> Result<()> {
+    let mut previous = 0;
+    for milestone in milestones.iter().filter(|milestone| milestone.is_set()) {
+        require!(milestone.threshold > previous, ReferralError::InvalidMilestones);
+        previous = milestone.threshold;
+    }
+    Ok(())
+}
+
+/// Returns the indices of the milestones reached at `total_referrals` that are not yet in `claimed_bitmap`.
+pub fn newly_reached_milestones(
+    milestones: &[Milestone; MAX_MILESTONES],
+    total_referrals: u64,
+    claimed_bitmap: u8,
+) -> impl Iterator<Item = usize> + '_ {
+    milestones.iter().enumerate().filter_map(move |(index, milestone)| {
+        let reached = milestone.is_set() && total_referrals >= milestone.threshold;
+        (reached && claimed_bitmap & (1 << index) == 0).then_some(index)
+    })
 }
@@ -0,0 +1,73 @@
+use crate::constants::SETTINGS_CHANGE_SEED;
+use anchor_lang::prelude::*;
+
+/// One setting changed by a `SettingsChangeRecord`.
+///
+/// Values are stored as `u64`: signed values by their two's-complement bits, flags as 0 or 1, an open-ended
+/// `program_end_time` as 0, and fields that do not fit in a `u64` (such as the milestones, a token requirement or
+/// the link signer) as a fingerprint of their serialized value, which tells that they changed but not to what.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FieldChange {
+    /// The changed setting, a `ProgramField` discriminant
+    pub field: u8,
+    /// Its value before the change
+    pub old_value: u64,
+    /// Its value after the change
+    pub new_value: u64,
+}
+
+impl FieldChange {
+    /// The size of a serialized `FieldChange` in bytes.
+    pub const SIZE: usize = 1 + // field
+        8 + // old_value
+        8; // new_value
+}
+
+/// The before and after of one change to a program's settings, kept on-chain so participants can audit every
+/// change without relying on logs.
+///
+/// Records are numbered from 0 by the program's `settings_change_count` and never mutated or closed. A call that
+/// changes nothing writes no record.
+///
+/// PDA with seeds: ["settings_change", referral_program.key(), index.to_le_bytes()]
+#[account]
+#[derive(Default)]
+pub struct SettingsChangeRecord {
+    /// The referral program whose settings changed
+    pub referral_program: Pubkey,
+    /// The position of the change among the program's changes
+    pub index: u64,
+    /// The program's authority when the change was made; it paid the record's rent unless the change was a
+    /// governed change executed by someone else
+    pub authority: Pubkey,
+    /// When the change was made
+    pub changed_at: i64,
+    /// Bump seed for the record PDA
+    pub bump: u8,
+    /// The settings that changed, in `ProgramField` order
+    pub changes: Vec<FieldChange>,
+}
+
+impl SettingsChangeRecord {
+    /// Version of the `SettingsChangeRecord` account layout, bumped whenever its fields change.
+    pub const LAYOUT_VERSION: u8 = 1;
+
+    /// The size of a `SettingsChangeRecord` holding `changes` changes in bytes, excluding the discriminator.
+    pub fn size(changes: usize) -> usize {
+        32 + // referral_program
+            8 + // index
+            32 + // authority
+            8 + // changed_at
+            1 + // bump
+            4 + changes * FieldChange::SIZE // changes
+    }
+
+    /// Returns the address of the record of change `index` of `referral_program`.
+    pub fn address(referral_program: &Pubkey, index: u64) -> Pubkey {
+        Pubkey::find_program_address(
+            &[SETTINGS_CHANGE_SEED, referral_program.as_ref(), &index.to_le_bytes()],
+            &crate::ID,
+        )
+        .0
+    }
+}
@@ -0,0 +1,41 @@
+use crate::space::{layout_size, LayoutField};
+use anchor_lang::prelude::*;
+
+/// A withdrawal above the program's delay threshold, waiting out `WITHDRAWAL_DELAY` before the authority can
+/// execute it. The program's guardian can cancel it meanwhile.
+///
+/// PDA with seeds: ["withdrawal", referral_program.key()]
+#[account]
+#[derive(Default)]
+pub struct WithdrawalRequest {
+    /// The referral program the funds are withdrawn from
+    pub referral_program: Pubkey,
+    /// The amount to withdraw, in lamports or token units
+    pub amount: u64,
+    /// The wallet the withdrawal pays out to, or whose token account it pays; fixed when it is queued
+    pub destination: Pubkey,
+    /// When the withdrawal was queued
+    pub requested_at: i64,
+    /// The earliest time `execute_withdrawal` can run
+    pub executable_at: i64,
+    /// Bump seed for the withdrawal request PDA
+    pub bump: u8,
+}
+
+impl WithdrawalRequest {
+    /// Version of the `WithdrawalRequest` account layout, bumped whenever its fields change.
+    pub const LAYOUT_VERSION: u8 = 2;
+
+    /// Each field of the `WithdrawalRequest` account and the most bytes it serializes to, discriminator excluded.
+    pub const LAYOUT: &'static [LayoutField] = &[
+        ("referral_program", 32),
+        ("amount", 8),
+        ("destination", 32),
+        ("requested_at", 8),
+        ("executable_at", 8),
+        ("bump", 1),
+    ];
+
+    /// The size of the `WithdrawalRequest` account in bytes, excluding the discriminator.
+    pub const SIZE: usize = layout_size(Self::LAYOUT);
+}
@@ -0,0 +1,22 @@
+//! Argument checks shared by every instruction.
+//!
+//! Zero amounts: an instruction whose amount argument moves funds or credits a counter rejects zero with the
+//! error it already uses for a bad amount (`InsufficientDeposit` for deposits, contest and boost funding,
+//! `InvalidPurchaseAmount` for each purchase, batched or not, `InvalidBoostAmount` for boost withdrawals,
+//! `InvalidTransferAmount` for pending-reward transfers, `InvalidWithdrawalAmount` for authority withdrawals). A zero transfer would only cost the caller fees
+//! and, for purchases, bump counters without any volume behind them. New fund-moving instructions call
+//! [`require_nonzero_amount`] before any other check.
+//!
+//! Exceptions are amounts where zero is a documented "none": `activate_program`'s `initial_deposit` skips the
+//! deposit when zero. Read-only and validation instructions accept any amount.
+
+use crate::error::ReferralError;
+use anchor_lang::prelude::*;
+
+/// Returns `error` if a fund-moving `amount` is zero.
+pub fn require_nonzero_amount(amount: u64, error: ReferralError) -> Result<()> {
+    if amount == 0 {
+        return Err(error.into());
+    }
+    Ok(())
+}
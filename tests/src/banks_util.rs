@@ -0,0 +1,1037 @@
+use anchor_client::{
+    anchor_lang::{
+        __private::base64::{engine::general_purpose::STANDARD, Engine},
+        system_program, AccountDeserialize, AccountSerialize, AnchorDeserialize, Discriminator, InstructionData,
+        ToAccountMetas,
+    },
+    solana_sdk::{
+        account::Account,
+        account_info::AccountInfo,
+        clock::Clock,
+        entrypoint::ProgramResult,
+        instruction::{AccountMeta, Instruction, InstructionError},
+        native_token::LAMPORTS_PER_SOL,
+        program_stubs::{self, SyscallStubs},
+        pubkey::Pubkey,
+        signature::Keypair,
+        signer::Signer,
+        system_instruction,
+        transaction::{Transaction, TransactionError},
+    },
+};
+use anchor_spl::token::spl_token;
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solrefer::{
+    accounts,
+    constants::{
+        FEE_CONFIG_SEED, METADATA_SEED, NETWORK_CONFIG_SEED, REWARD_DENOMINATION_RAW, TOKEN_METADATA_PROGRAM_ID,
+    },
+    error::ReferralError,
+    instruction,
+    instructions::{
+        current_settings, FeeConfigSettings, ProgramSettings, RewardStatementV1, SetupStatus, METADATA_V1_KEY,
+    },
+    state::{EligibilityCriteria, FeeConfig, NetworkConfig, NetworkLimits, ReferralProgram},
+};
+use std::{cell::Cell, sync::Once};
+
+use crate::test_util::{
+    get_authority_meta_pda, get_eligibility_criteria_pda, get_fee_config_pda, get_final_report_pda,
+    get_network_config_pda, get_participant_pda, get_referee_receipt_pda, get_referral_program_pda,
+    get_settings_change_pda, get_sponsor_vault_pda,
+};
+
+/// Runs the program's entrypoint in-process.
+///
+/// Anchor's entrypoint ties the account slice to the accounts' own lifetime, which the native processor
+/// signature cannot express, so the slice is copied and leaked for the duration of the test.
+fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    PROGRAM_DEPTH.with(|depth| depth.set(depth.get() + 1));
+    let result = solrefer::entry(program_id, accounts, data);
+    PROGRAM_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    result
+}
+
+thread_local! {
+    /// How many invocations of the program are running on this thread, counting CPIs back into it
+    static PROGRAM_DEPTH: Cell<u32> = const { Cell::new(0) };
+}
+
+/// Starts an in-process bank running the program and returns its context with a funded owner, alice and bob
+pub async fn setup() -> (ProgramTestContext, Keypair, Keypair, Keypair) {
+    start(program_test()).await
+}
+
+/// A bank running the program, to which suites can add programs of their own before starting it
+pub fn program_test() -> ProgramTest {
+    ProgramTest::new("solrefer", solrefer::ID, processor!(process_instruction))
+}
+
+/// Starts `program_test` and returns its context with a funded owner, alice and bob
+pub async fn start(program_test: ProgramTest) -> (ProgramTestContext, Keypair, Keypair, Keypair) {
+    let mut context = program_test.start_with_context().await;
+    static LOG_EVENTS: Once = Once::new();
+    LOG_EVENTS.call_once(|| {
+        let inner = program_stubs::set_syscall_stubs(Box::new(DefaultStubs));
+        program_stubs::set_syscall_stubs(Box::new(EventLogStubs { inner }));
+    });
+
+    let owner = create_funded_user(&mut context).await;
+    let alice = create_funded_user(&mut context).await;
+    let bob = create_funded_user(&mut context).await;
+    (context, owner, alice, bob)
+}
+
+/// The default syscall stubs, held briefly while `EventLogStubs` takes over from `solana-program-test`'s
+struct DefaultStubs;
+
+impl SyscallStubs for DefaultStubs {}
+
+/// The syscall stubs `solana-program-test` installs for in-process programs, except that event data is written to
+/// the transaction's log.
+///
+/// `solana-program-test` leaves `sol_log_data` printing to stdout, so in-process programs never show their events
+/// in the log messages `process_with_events` reads. These stubs log them as `Program data: ` messages instead.
+/// Outside of the program, where there is no transaction to log to, unit tests of code that logs print to stdout
+/// as they would without a bank.
+struct EventLogStubs {
+    inner: Box<dyn SyscallStubs>,
+}
+
+impl SyscallStubs for EventLogStubs {
+    fn sol_log(&self, message: &str) {
+        if PROGRAM_DEPTH.with(Cell::get) == 0 {
+            DefaultStubs.sol_log(message)
+        } else {
+            self.inner.sol_log(message)
+        }
+    }
+    fn sol_log_compute_units(&self) {
+        self.inner.sol_log_compute_units()
+    }
+    fn sol_remaining_compute_units(&self) -> u64 {
+        self.inner.sol_remaining_compute_units()
+    }
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        self.inner.sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner.sol_get_clock_sysvar(var_addr)
+    }
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner.sol_get_epoch_schedule_sysvar(var_addr)
+    }
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner.sol_get_fees_sysvar(var_addr)
+    }
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner.sol_get_rent_sysvar(var_addr)
+    }
+    fn sol_get_epoch_rewards_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner.sol_get_epoch_rewards_sysvar(var_addr)
+    }
+    fn sol_get_last_restart_slot(&self, var_addr: *mut u8) -> u64 {
+        self.inner.sol_get_last_restart_slot(var_addr)
+    }
+    unsafe fn sol_memcpy(&self, dst: *mut u8, src: *const u8, n: usize) {
+        self.inner.sol_memcpy(dst, src, n)
+    }
+    unsafe fn sol_memmove(&self, dst: *mut u8, src: *const u8, n: usize) {
+        self.inner.sol_memmove(dst, src, n)
+    }
+    unsafe fn sol_memcmp(&self, s1: *const u8, s2: *const u8, n: usize, result: *mut i32) {
+        self.inner.sol_memcmp(s1, s2, n, result)
+    }
+    unsafe fn sol_memset(&self, s: *mut u8, c: u8, n: usize) {
+        self.inner.sol_memset(s, c, n)
+    }
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        self.inner.sol_get_return_data()
+    }
+    fn sol_set_return_data(&self, data: &[u8]) {
+        self.inner.sol_set_return_data(data)
+    }
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        let fields: Vec<String> = fields.iter().map(|field| STANDARD.encode(field)).collect();
+        self.sol_log(&format!("Program data: {}", fields.join(" ")))
+    }
+    fn sol_get_processed_sibling_instruction(&self, index: usize) -> Option<Instruction> {
+        self.inner.sol_get_processed_sibling_instruction(index)
+    }
+    fn sol_get_stack_height(&self) -> u64 {
+        self.inner.sol_get_stack_height()
+    }
+}
+
+/// Creates a wallet funded from the bank's payer
+pub async fn create_funded_user(context: &mut ProgramTestContext) -> Keypair {
+    let user = Keypair::new();
+    let ix = system_instruction::transfer(&context.payer.pubkey(), &user.pubkey(), LAMPORTS_PER_SOL * 2);
+    process(context, &[ix], &[]).await.expect("Failed to fund user");
+    user
+}
+
+/// Signs the instructions with the bank's payer and `signers` and processes them in one transaction.
+///
+/// Every transaction waits for a fresh blockhash, so retrying an identical transaction is never deduplicated.
+pub async fn process(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), BanksClientError> {
+    let blockhash = context.get_new_latest_blockhash().await?;
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let tx = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        all_signers.as_slice(),
+        blockhash,
+    );
+    context.banks_client.process_transaction(tx).await
+}
+
+/// Processes a transaction like `process` and returns the events of type `T` it emitted
+pub async fn process_with_events<T: AnchorDeserialize + Discriminator>(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Vec<T> {
+    let blockhash = context.get_new_latest_blockhash().await.expect("Failed to fetch blockhash");
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let tx = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        all_signers.as_slice(),
+        blockhash,
+    );
+    let processed =
+        context.banks_client.process_transaction_with_metadata(tx).await.expect("Failed to process transaction");
+    processed.result.expect("Transaction failed");
+    decode_events(&processed.metadata.expect("Transaction reported no metadata").log_messages)
+}
+
+/// Simulates the instructions signed by the bank's payer and `signers` and returns the events of type `T` they
+/// emitted, whether or not the transaction would succeed
+pub async fn simulate_events<T: AnchorDeserialize + Discriminator>(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Vec<T> {
+    let blockhash = context.get_new_latest_blockhash().await.expect("Failed to fetch blockhash");
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let tx = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        all_signers.as_slice(),
+        blockhash,
+    );
+    let simulation = context.banks_client.simulate_transaction(tx).await.expect("Failed to simulate transaction");
+    decode_events(&simulation.simulation_details.expect("Simulation reported no details").logs)
+}
+
+/// Decodes the events of type `T` from a transaction's log messages
+fn decode_events<T: AnchorDeserialize + Discriminator>(logs: &[String]) -> Vec<T> {
+    logs.iter()
+        .filter_map(|log| log.strip_prefix("Program log: Program data: "))
+        .filter_map(|data| STANDARD.decode(data).ok())
+        .filter(|bytes| bytes.starts_with(&T::DISCRIMINATOR))
+        .map(|bytes| T::deserialize(&mut &bytes[8..]).expect("Failed to deserialize event"))
+        .collect()
+}
+
+/// Builds an instruction of this program from its accounts and arguments
+pub fn program_instruction(accounts: impl ToAccountMetas, args: impl InstructionData) -> Instruction {
+    Instruction { program_id: solrefer::ID, accounts: accounts.to_account_metas(None), data: args.data() }
+}
+
+/// Fetches and deserializes a program account
+pub async fn get_account<T: AccountDeserialize>(context: &mut ProgramTestContext, address: Pubkey) -> T {
+    let account = context
+        .banks_client
+        .get_account(address)
+        .await
+        .expect("Failed to fetch account")
+        .expect("Account does not exist");
+    T::try_deserialize(&mut account.data.as_slice()).expect("Failed to deserialize account")
+}
+
+/// Returns the lamport balance of an address
+pub async fn get_balance(context: &mut ProgramTestContext, address: Pubkey) -> u64 {
+    context.banks_client.get_balance(address).await.expect("Failed to fetch balance")
+}
+
+/// Returns the bank's current unix timestamp
+pub async fn get_clock_time(context: &mut ProgramTestContext) -> i64 {
+    let clock: Clock = context.banks_client.get_sysvar().await.expect("Failed to fetch clock sysvar");
+    clock.unix_timestamp
+}
+
+/// Moves the bank's clock `seconds` forward on the next slot
+pub async fn advance_clock(context: &mut ProgramTestContext, seconds: i64) {
+    let mut clock: Clock = context.banks_client.get_sysvar().await.expect("Failed to fetch clock sysvar");
+    context.warp_to_slot(clock.slot + 1).expect("Failed to warp to the next slot");
+    clock.slot += 1;
+    clock.unix_timestamp += seconds;
+    context.set_sysvar(&clock);
+}
+
+/// Writes the network config PDA with `admin` and `limits` straight into the bank and returns its address.
+///
+/// The in-process program has no upgrade authority to sign `initialize_network_config`, so the account is
+/// installed as that instruction would have created it.
+pub fn set_network_config(context: &mut ProgramTestContext, admin: Pubkey, limits: NetworkLimits) -> Pubkey {
+    let (network_config, bump) = Pubkey::find_program_address(&[NETWORK_CONFIG_SEED], &solrefer::ID);
+    let mut data = Vec::with_capacity(8 + NetworkConfig::SIZE);
+    NetworkConfig { admin, limits, bump }.try_serialize(&mut data).expect("Failed to serialize network config");
+    let account = Account { lamports: LAMPORTS_PER_SOL, data, owner: solrefer::ID, executable: false, rent_epoch: 0 };
+    context.set_account(&network_config, &account.into());
+    network_config
+}
+
+/// Installs the fee config PDA with `admin` and `settings`, returning its address.
+///
+/// Like `initialize_network_config`, `initialize_fee_config` needs the upgrade authority the in-process program
+/// lacks, so the account is installed as that instruction would have created it.
+pub fn set_fee_config(context: &mut ProgramTestContext, admin: Pubkey, settings: FeeConfigSettings) -> Pubkey {
+    let (fee_config, bump) = Pubkey::find_program_address(&[FEE_CONFIG_SEED], &solrefer::ID);
+    let config = FeeConfig {
+        admin,
+        treasury: settings.treasury,
+        creation_fee_lamports: settings.creation_fee_lamports,
+        max_programs_per_authority: settings.max_programs_per_authority,
+        exempt_authorities: settings.exempt_authorities,
+        bump,
+    };
+    let mut data = Vec::with_capacity(8 + FeeConfig::SIZE);
+    config.try_serialize(&mut data).expect("Failed to serialize fee config");
+    let account = Account { lamports: LAMPORTS_PER_SOL, data, owner: solrefer::ID, executable: false, rent_epoch: 0 };
+    context.set_account(&fee_config, &account.into());
+    fee_config
+}
+
+/// Encodes a Metaplex `MetadataV1` account of `mint` with empty strings, no creators and the given collection
+pub fn nft_metadata_data(mint: Pubkey, collection: Option<(Pubkey, bool)>) -> Vec<u8> {
+    let mut data = vec![METADATA_V1_KEY];
+    data.extend_from_slice(Pubkey::new_unique().as_ref()); // update_authority
+    data.extend_from_slice(mint.as_ref());
+    data.extend_from_slice(&[0u8; 12]); // name, symbol and uri
+    data.extend_from_slice(&500u16.to_le_bytes()); // seller_fee_basis_points
+    data.extend_from_slice(&[0, 1, 1, 0, 0]); // no creators, primary_sale_happened, is_mutable, no nonce or standard
+    match collection {
+        Some((key, verified)) => {
+            data.extend_from_slice(&[1, u8::from(verified)]);
+            data.extend_from_slice(key.as_ref());
+        }
+        None => data.push(0),
+    }
+    data
+}
+
+/// Writes the Metaplex metadata PDA of `mint` naming `collection` straight into the bank and returns its address
+pub fn set_nft_metadata(context: &mut ProgramTestContext, mint: Pubkey, collection: Option<(Pubkey, bool)>) -> Pubkey {
+    let (metadata, _) = Pubkey::find_program_address(
+        &[METADATA_SEED, TOKEN_METADATA_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &TOKEN_METADATA_PROGRAM_ID,
+    );
+    let data = nft_metadata_data(mint, collection);
+    let account = Account {
+        lamports: LAMPORTS_PER_SOL,
+        data,
+        owner: TOKEN_METADATA_PROGRAM_ID,
+        executable: false,
+        rent_epoch: 0,
+    };
+    context.set_account(&metadata, &account.into());
+    metadata
+}
+
+/// Asserts that a transaction failed with the given program error
+pub fn assert_referral_error(result: Result<(), BanksClientError>, error: ReferralError) {
+    assert_referral_error_at(result, 0, error);
+}
+
+/// Asserts that a transaction failed with the given program error in its instruction at `index`
+pub fn assert_referral_error_at(result: Result<(), BanksClientError>, index: u8, error: ReferralError) {
+    let err = result.expect_err("Transaction unexpectedly succeeded").unwrap();
+    assert_eq!(err, TransactionError::InstructionError(index, InstructionError::Custom(u32::from(error))));
+}
+
+/// Derives the PDA of an authority's first referral program and the program's SOL and token vault PDAs
+pub fn referral_program_pdas(authority: Pubkey) -> (Pubkey, Pubkey, Pubkey) {
+    indexed_referral_program_pdas(authority, 0)
+}
+
+/// Derives the PDA of an authority's referral program at `program_index` and the program's vault PDAs
+pub fn indexed_referral_program_pdas(authority: Pubkey, program_index: u64) -> (Pubkey, Pubkey, Pubkey) {
+    let referral_program = get_referral_program_pda(authority, program_index, solrefer::ID);
+    let (vault, _) = Pubkey::find_program_address(&[b"vault", referral_program.as_ref()], &solrefer::ID);
+    let (token_vault, _) = Pubkey::find_program_address(&[b"token_vault", referral_program.as_ref()], &solrefer::ID);
+    (referral_program, vault, token_vault)
+}
+
+/// Builds a `create_referral_program` instruction paying in `token_mint`, or SOL when `None`
+pub fn create_referral_program_ix(
+    owner: &Keypair,
+    token_mint: Option<Pubkey>,
+    fixed_reward_amount: u64,
+    program_end_time: Option<i64>,
+    start_inactive: bool,
+) -> Instruction {
+    create_indexed_referral_program_ix(owner, 0, token_mint, fixed_reward_amount, program_end_time, start_inactive)
+}
+
+/// Returns the accounts of `owner` creating its program at `program_index`, paying in `token_mint` or SOL when
+/// `None`, with no creation fee treasury
+pub fn create_referral_program_accounts(
+    owner: &Keypair,
+    program_index: u64,
+    token_mint: Option<Pubkey>,
+) -> accounts::CreateReferralProgram {
+    let (referral_program, _, _) = indexed_referral_program_pdas(owner.pubkey(), program_index);
+    accounts::CreateReferralProgram {
+        referral_program,
+        eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+        final_report: get_final_report_pda(referral_program, solrefer::ID),
+        authority: owner.pubkey(),
+        token_mint_info: token_mint,
+        fee_config: get_fee_config_pda(solrefer::ID),
+        network_config: get_network_config_pda(solrefer::ID),
+        authority_meta: get_authority_meta_pda(owner.pubkey(), solrefer::ID),
+        treasury: None,
+        token_program: token_mint.map(|_| spl_token::id()),
+        system_program: system_program::ID,
+    }
+}
+
+/// Builds a `create_referral_program` instruction for `owner`'s program at `program_index`
+pub fn create_indexed_referral_program_ix(
+    owner: &Keypair,
+    program_index: u64,
+    token_mint: Option<Pubkey>,
+    fixed_reward_amount: u64,
+    program_end_time: Option<i64>,
+    start_inactive: bool,
+) -> Instruction {
+    program_instruction(
+        create_referral_program_accounts(owner, program_index, token_mint),
+        instruction::CreateReferralProgram {
+            program_index,
+            token_mint,
+            fixed_reward_amount,
+            program_end_time,
+            reward_denomination: REWARD_DENOMINATION_RAW,
+            start_inactive,
+            terms_hash: [0u8; 32],
+            guardian: None,
+            withdrawal_destinations: Default::default(),
+            settings_locked_until: None,
+            settings: None,
+        },
+    )
+}
+
+/// Builds an `initialize_token_vault` instruction for `owner`'s token program
+pub fn initialize_token_vault_ix(owner: &Keypair, mint: Pubkey) -> Instruction {
+    let (referral_program, _, token_vault) = referral_program_pdas(owner.pubkey());
+    program_instruction(
+        accounts::InitializeTokenVault {
+            referral_program,
+            token_vault,
+            token_mint: mint,
+            authority: owner.pubkey(),
+            system_program: system_program::ID,
+            token_program: spl_token::id(),
+        },
+        instruction::InitializeTokenVault,
+    )
+}
+
+/// Creates a SOL referral program and returns its PDA and vault
+pub async fn create_sol_referral_program(
+    context: &mut ProgramTestContext,
+    owner: &Keypair,
+    fixed_reward_amount: u64,
+    program_end_time: Option<i64>,
+) -> (Pubkey, Pubkey) {
+    let (referral_program, vault, _) = referral_program_pdas(owner.pubkey());
+    let ix = create_referral_program_ix(owner, None, fixed_reward_amount, program_end_time, false);
+    process(context, &[ix], &[owner]).await.expect("Failed to create SOL referral program");
+    (referral_program, vault)
+}
+
+/// Creates a 9-decimal token mint with `owner` as its mint authority
+pub async fn create_mint(context: &mut ProgramTestContext, owner: &Keypair) -> Pubkey {
+    create_mint_with_decimals(context, owner, 9).await
+}
+
+/// Creates a token mint with `decimals` decimals and `owner` as its mint authority
+pub async fn create_mint_with_decimals(context: &mut ProgramTestContext, owner: &Keypair, decimals: u8) -> Pubkey {
+    let mint = Keypair::new();
+    let rent = context.banks_client.get_rent().await.expect("Failed to fetch rent").minimum_balance(82);
+    let ixs = [
+        system_instruction::create_account(&context.payer.pubkey(), &mint.pubkey(), rent, 82, &spl_token::id()),
+        spl_token::instruction::initialize_mint(&spl_token::id(), &mint.pubkey(), &owner.pubkey(), None, decimals)
+            .unwrap(),
+    ];
+    process(context, &ixs, &[&mint]).await.expect("Failed to create mint");
+    mint.pubkey()
+}
+
+/// Creates a token account of `mint` owned by `owner` and mints `amount` into it with `owner` as mint authority
+pub async fn create_funded_token_account(
+    context: &mut ProgramTestContext,
+    owner: &Keypair,
+    mint: Pubkey,
+    amount: u64,
+) -> Pubkey {
+    let account = Keypair::new();
+    let rent = context.banks_client.get_rent().await.expect("Failed to fetch rent").minimum_balance(165);
+    let ixs = [
+        system_instruction::create_account(&context.payer.pubkey(), &account.pubkey(), rent, 165, &spl_token::id()),
+        spl_token::instruction::initialize_account(&spl_token::id(), &account.pubkey(), &mint, &owner.pubkey())
+            .unwrap(),
+        spl_token::instruction::mint_to(&spl_token::id(), &mint, &account.pubkey(), &owner.pubkey(), &[], amount)
+            .unwrap(),
+    ];
+    process(context, &ixs, &[&account, owner]).await.expect("Failed to create token account");
+    account.pubkey()
+}
+
+/// Creates an empty token account of `mint` owned by `owner`
+pub async fn create_token_account(context: &mut ProgramTestContext, owner: Pubkey, mint: Pubkey) -> Pubkey {
+    let account = Keypair::new();
+    let rent = context.banks_client.get_rent().await.expect("Failed to fetch rent").minimum_balance(165);
+    let ixs = [
+        system_instruction::create_account(&context.payer.pubkey(), &account.pubkey(), rent, 165, &spl_token::id()),
+        spl_token::instruction::initialize_account(&spl_token::id(), &account.pubkey(), &mint, &owner).unwrap(),
+    ];
+    process(context, &ixs, &[&account]).await.expect("Failed to create token account");
+    account.pubkey()
+}
+
+/// Creates a token referral program paying in `mint`, initializes its token vault and returns both PDAs
+pub async fn create_token_referral_program(
+    context: &mut ProgramTestContext,
+    owner: &Keypair,
+    mint: Pubkey,
+    fixed_reward_amount: u64,
+    program_end_time: Option<i64>,
+) -> (Pubkey, Pubkey) {
+    let (referral_program, _, token_vault) = referral_program_pdas(owner.pubkey());
+    let create_ix = create_referral_program_ix(owner, Some(mint), fixed_reward_amount, program_end_time, false);
+    let vault_ix = initialize_token_vault_ix(owner, mint);
+    process(context, &[create_ix, vault_ix], &[owner]).await.expect("Failed to create token referral program");
+    (referral_program, token_vault)
+}
+
+/// Builds a `deposit_sol` instruction into `referral_program`'s vault
+pub fn deposit_sol_ix(authority: &Keypair, referral_program: Pubkey, vault: Pubkey, amount: u64) -> Instruction {
+    program_instruction(
+        accounts::DepositSol {
+            referral_program,
+            vault,
+            authority: authority.pubkey(),
+            event_queue: None,
+            system_program: system_program::ID,
+        },
+        instruction::DepositSol { amount },
+    )
+}
+
+/// Deposits SOL into a referral program's vault
+pub async fn deposit_sol(
+    context: &mut ProgramTestContext,
+    authority: &Keypair,
+    referral_program: Pubkey,
+    vault: Pubkey,
+    amount: u64,
+) {
+    let ix = deposit_sol_ix(authority, referral_program, vault, amount);
+    process(context, &[ix], &[authority]).await.expect("Failed to deposit SOL");
+}
+
+/// Builds a `close_referral_program` instruction, which requests the closure or finalizes an elapsed one
+pub fn close_referral_program_ix(owner: &Keypair, referral_program: Pubkey, vault: Pubkey) -> Instruction {
+    program_instruction(
+        accounts::CloseReferralProgram {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            vault,
+            sponsor_vault: get_sponsor_vault_pda(referral_program, solrefer::ID),
+            authority_meta: get_authority_meta_pda(owner.pubkey(), solrefer::ID),
+            final_report: get_final_report_pda(referral_program, solrefer::ID),
+            authority: owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::CloseReferralProgram {},
+    )
+}
+
+/// Builds `authority` setting whether `referral_program` is active
+pub fn set_program_status_ix(authority: &Keypair, referral_program: Pubkey, active: bool) -> Instruction {
+    program_instruction(
+        accounts::SetProgramStatus {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            authority: authority.pubkey(),
+        },
+        instruction::SetProgramStatus { active },
+    )
+}
+
+/// Builds a `deposit_token` instruction moving `amount` of `mint` from `depositor_token_account` to the token vault
+pub fn deposit_token_ix(
+    authority: &Keypair,
+    referral_program: Pubkey,
+    token_vault: Pubkey,
+    mint: Pubkey,
+    depositor_token_account: Pubkey,
+    amount: u64,
+) -> Instruction {
+    program_instruction(
+        accounts::DepositToken {
+            referral_program,
+            token_vault,
+            token_mint: mint,
+            depositor_token_account,
+            authority: authority.pubkey(),
+            event_queue: None,
+            token_program: spl_token::id(),
+        },
+        instruction::DepositToken { amount },
+    )
+}
+
+/// Builds a `close_token_vault` instruction sweeping any remaining tokens to `destination_token_account`
+pub fn close_token_vault_ix(
+    authority: &Keypair,
+    referral_program: Pubkey,
+    token_vault: Pubkey,
+    destination_token_account: Option<Pubkey>,
+) -> Instruction {
+    program_instruction(
+        accounts::CloseTokenVault {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            token_vault,
+            destination_token_account,
+            authority: authority.pubkey(),
+            token_program: spl_token::id(),
+        },
+        instruction::CloseTokenVault {},
+    )
+}
+
+/// Derives the PDA the next settings change of a referral program is recorded at
+pub async fn next_settings_change_pda(context: &mut ProgramTestContext, referral_program: Pubkey) -> Pubkey {
+    let program: ReferralProgram = get_account(context, referral_program).await;
+    get_settings_change_pda(referral_program, program.settings_change_count, solrefer::ID)
+}
+
+/// Builds an `update_program_settings` instruction recording its change at the program's next record
+pub async fn update_program_settings_ix(
+    context: &mut ProgramTestContext,
+    authority: &Keypair,
+    referral_program: Pubkey,
+    new_settings: ProgramSettings,
+) -> Instruction {
+    program_instruction(
+        accounts::UpdateProgramSettings {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            network_config: get_network_config_pda(solrefer::ID),
+            authority: authority.pubkey(),
+            settings_change: next_settings_change_pda(context, referral_program).await,
+            pending_change: None,
+            event_queue: None,
+            system_program: system_program::ID,
+        },
+        instruction::UpdateProgramSettings { new_settings, idempotency_key: None },
+    )
+}
+
+/// Reads the settings a referral program currently runs with, to be updated field by field
+pub async fn read_program_settings(context: &mut ProgramTestContext, referral_program: Pubkey) -> ProgramSettings {
+    let program: ReferralProgram = get_account(context, referral_program).await;
+    let criteria: EligibilityCriteria =
+        get_account(context, get_eligibility_criteria_pda(referral_program, solrefer::ID)).await;
+    current_settings(&program, &criteria)
+}
+
+/// Updates the settings of a referral program
+pub async fn update_program_settings(
+    context: &mut ProgramTestContext,
+    authority: &Keypair,
+    referral_program: Pubkey,
+    new_settings: ProgramSettings,
+) {
+    let ix = update_program_settings_ix(context, authority, referral_program, new_settings).await;
+    process(context, &[ix], &[authority]).await.expect("Failed to update program settings");
+}
+
+/// Returns the accounts of a direct join of `user` to a referral program, with every optional account left out
+pub fn join_referral_program_accounts(user: &Keypair, referral_program: Pubkey) -> accounts::JoinReferralProgram {
+    accounts::JoinReferralProgram {
+        referral_program,
+        eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+        participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+        invite: None,
+        collection_metadata: None,
+        collection_nft: None,
+        age_reference: None,
+        region_attestation: None,
+        referrer_token_account: None,
+        bridge_participant: None,
+        bridge_referrer: None,
+        user: user.pubkey(),
+        event_queue: None,
+        system_program: system_program::ID,
+    }
+}
+
+/// Builds `owner` minting one invite to `referral_program` at each of `invites`, in index order
+pub fn mint_invites_ix(owner: &Keypair, referral_program: Pubkey, invites: &[Pubkey]) -> Instruction {
+    let mut ix = program_instruction(
+        accounts::MintInvites { referral_program, authority: owner.pubkey(), system_program: system_program::ID },
+        instruction::MintInvites { count: invites.len() as u8 },
+    );
+    ix.accounts.extend(invites.iter().map(|invite| AccountMeta::new(*invite, false)));
+    ix
+}
+
+/// Builds a direct join of `user` to a referral program with the default terms
+pub fn join_referral_program_ix(user: &Keypair, referral_program: Pubkey) -> Instruction {
+    program_instruction(
+        join_referral_program_accounts(user, referral_program),
+        instruction::JoinReferralProgram { accepted_terms_hash: [0u8; 32] },
+    )
+}
+
+/// Joins a referral program directly, returning the result and the participant PDA
+pub async fn try_join_referral_program(
+    context: &mut ProgramTestContext,
+    user: &Keypair,
+    referral_program: Pubkey,
+) -> (Result<(), BanksClientError>, Pubkey) {
+    let participant = get_participant_pda(referral_program, user.pubkey(), solrefer::ID);
+    let ix = join_referral_program_ix(user, referral_program);
+    (process(context, &[ix], &[user]).await, participant)
+}
+
+/// Joins a referral program directly and returns the new participant PDA
+pub async fn join_referral_program(
+    context: &mut ProgramTestContext,
+    user: &Keypair,
+    referral_program: Pubkey,
+) -> Pubkey {
+    let (result, participant) = try_join_referral_program(context, user, referral_program).await;
+    result.expect("Failed to join referral program");
+    participant
+}
+
+/// Joins a referral program through a referrer's participant account and returns the new participant PDA
+pub async fn join_through_referral(
+    context: &mut ProgramTestContext,
+    user: &Keypair,
+    referral_program: Pubkey,
+    referrer: Pubkey,
+) -> Pubkey {
+    join_through_referral_with_boost(context, user, referral_program, referrer, None).await
+}
+
+/// Joins through a referrer, passing the referrer's boost escrow when given, and returns the new participant PDA
+pub async fn join_through_referral_with_boost(
+    context: &mut ProgramTestContext,
+    user: &Keypair,
+    referral_program: Pubkey,
+    referrer: Pubkey,
+    boost_escrow: Option<Pubkey>,
+) -> Pubkey {
+    let (result, participant) =
+        try_join_through_referral_with_boost(context, user, referral_program, referrer, boost_escrow).await;
+    result.expect("Failed to join through referral");
+    participant
+}
+
+/// Joins a referral program through a referrer's participant account, returning the result and the participant PDA
+pub async fn try_join_through_referral(
+    context: &mut ProgramTestContext,
+    user: &Keypair,
+    referral_program: Pubkey,
+    referrer: Pubkey,
+) -> (Result<(), BanksClientError>, Pubkey) {
+    try_join_through_referral_with_boost(context, user, referral_program, referrer, None).await
+}
+
+/// Joins through a referrer with an optional boost escrow, returning the result and the participant PDA
+async fn try_join_through_referral_with_boost(
+    context: &mut ProgramTestContext,
+    user: &Keypair,
+    referral_program: Pubkey,
+    referrer: Pubkey,
+    boost_escrow: Option<Pubkey>,
+) -> (Result<(), BanksClientError>, Pubkey) {
+    let participant = get_participant_pda(referral_program, user.pubkey(), solrefer::ID);
+    let ix = join_through_referral_ix(user, referral_program, referrer, boost_escrow);
+    (process(context, &[ix], &[user]).await, participant)
+}
+
+/// Returns the accounts of `user` joining a referral program through a referrer's participant account, with every
+/// optional account left out
+pub fn join_through_referral_accounts(
+    user: &Keypair,
+    referral_program: Pubkey,
+    referrer: Pubkey,
+) -> accounts::JoinThroughReferral {
+    accounts::JoinThroughReferral {
+        referral_program,
+        eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+        participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+        referrer,
+        rotated_referrer: None,
+        split_recipient: None,
+        boost_escrow: None,
+        match_offer: None,
+        referrer_wallet: None,
+        referee_receipt: get_referee_receipt_pda(referral_program, user.pubkey(), solrefer::ID),
+        sponsor_vault: None,
+        invite: None,
+        referrer_collection_metadata: None,
+        referrer_collection_nft: None,
+        age_reference: None,
+        region_attestation: None,
+        referrer_token_account: None,
+        referee_token_account: None,
+        referrer_upline: None,
+        user: user.pubkey(),
+        event_queue: None,
+        instructions_sysvar: None,
+        system_program: system_program::ID,
+    }
+}
+
+/// Builds `user` joining a referral program through a referrer's participant account, passing the referrer's
+/// boost escrow when given
+pub fn join_through_referral_ix(
+    user: &Keypair,
+    referral_program: Pubkey,
+    referrer: Pubkey,
+    boost_escrow: Option<Pubkey>,
+) -> Instruction {
+    program_instruction(
+        accounts::JoinThroughReferral {
+            boost_escrow,
+            ..join_through_referral_accounts(user, referral_program, referrer)
+        },
+        instruction::JoinThroughReferral { source_tag: None, accepted_terms_hash: [0u8; 32], link_proof: None },
+    )
+}
+
+/// Rotates `owner`'s participant account to `new_owner` and returns the new participant PDA
+pub async fn rotate_owner(
+    context: &mut ProgramTestContext,
+    referral_program: Pubkey,
+    owner: &Keypair,
+    new_owner: &Keypair,
+) -> Pubkey {
+    let old_participant = get_participant_pda(referral_program, owner.pubkey(), solrefer::ID);
+    let new_participant = get_participant_pda(referral_program, new_owner.pubkey(), solrefer::ID);
+    let initiate_ix = program_instruction(
+        accounts::InitiateOwnerRotation { referral_program, participant: old_participant, user: owner.pubkey() },
+        instruction::InitiateOwnerRotation { new_owner: new_owner.pubkey() },
+    );
+    process(context, &[initiate_ix], &[owner]).await.unwrap();
+    let complete_ix = program_instruction(
+        accounts::CompleteOwnerRotation {
+            referral_program,
+            old_participant,
+            new_participant,
+            new_owner: new_owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::CompleteOwnerRotation {},
+    );
+    process(context, &[complete_ix], &[new_owner]).await.unwrap();
+    new_participant
+}
+
+/// Returns the accounts of `user` claiming a participant's pending rewards from a SOL referral program, with every
+/// optional account left out
+pub fn claim_rewards_accounts(
+    user: &Keypair,
+    referral_program: Pubkey,
+    participant: Pubkey,
+    vault: Pubkey,
+) -> accounts::ClaimRewards {
+    accounts::ClaimRewards {
+        referral_program,
+        eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+        participant,
+        claim_splitter: None,
+        vault,
+        user: user.pubkey(),
+        event_queue: None,
+        instructions_sysvar: None,
+        region_attestation: None,
+        system_program: system_program::ID,
+    }
+}
+
+/// Claims a participant's pending rewards from a SOL referral program
+pub async fn claim_rewards(
+    context: &mut ProgramTestContext,
+    user: &Keypair,
+    referral_program: Pubkey,
+    participant: Pubkey,
+    vault: Pubkey,
+) -> Result<(), BanksClientError> {
+    let ix = program_instruction(
+        claim_rewards_accounts(user, referral_program, participant, vault),
+        instruction::ClaimRewards {},
+    );
+    process(context, &[ix], &[user]).await
+}
+
+/// Simulates a read-only instruction and deserializes the value it returned
+pub async fn simulate_return<T: AnchorDeserialize>(context: &mut ProgramTestContext, ix: Instruction) -> T {
+    let blockhash = context.get_new_latest_blockhash().await.expect("Failed to fetch blockhash");
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&context.payer.pubkey()), &[&context.payer], blockhash);
+    let simulation = context.banks_client.simulate_transaction(tx).await.expect("Failed to simulate transaction");
+    let return_data =
+        simulation.simulation_details.and_then(|details| details.return_data).expect("Simulation returned no data");
+    T::try_from_slice(&return_data.data).expect("Failed to deserialize return data")
+}
+
+/// Simulates an instruction signed by `signer` and returns the compute units it consumed
+pub async fn simulate_units_consumed(context: &mut ProgramTestContext, ix: Instruction, signer: &Keypair) -> u64 {
+    let blockhash = context.get_new_latest_blockhash().await.expect("Failed to fetch blockhash");
+    let tx =
+        Transaction::new_signed_with_payer(&[ix], Some(&context.payer.pubkey()), &[&context.payer, signer], blockhash);
+    let simulation = context.banks_client.simulate_transaction(tx).await.expect("Failed to simulate transaction");
+    simulation.simulation_details.expect("Simulation reported no details").units_consumed
+}
+
+/// Reads a program's setup state by simulating `get_setup_state`
+pub async fn get_setup_state(context: &mut ProgramTestContext, referral_program: Pubkey) -> SetupStatus {
+    let ix = program_instruction(accounts::GetSetupState { referral_program }, instruction::GetSetupState);
+    simulate_return(context, ix).await
+}
+
+/// Reads a participant's reward statement by simulating `get_reward_statement`
+pub async fn get_reward_statement(
+    context: &mut ProgramTestContext,
+    referral_program: Pubkey,
+    participant: Pubkey,
+) -> RewardStatementV1 {
+    let ix = program_instruction(
+        accounts::GetRewardStatement {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant,
+        },
+        instruction::GetRewardStatement,
+    );
+    simulate_return(context, ix).await
+}
+
+/// The program `resume_setup` brings to a fully set-up, active state
+pub struct SetupPlan {
+    /// The mint and the authority's funded token account, for a token program
+    pub token: Option<(Pubkey, Pubkey)>,
+    /// The criteria configured by the `update_program_settings` step
+    pub settings: ProgramSettings,
+    /// The amount deposited by the funding step
+    pub deposit: u64,
+}
+
+/// Brings `owner`'s referral program through every setup step it has not completed yet, in order.
+///
+/// The program's setup state decides what is submitted, so a setup interrupted at any step resumes without
+/// repeating a completed one, and a complete setup submits nothing. A missing program is created inactive.
+/// Returns the program PDA and the names of the instructions submitted.
+pub async fn resume_setup(
+    context: &mut ProgramTestContext,
+    owner: &Keypair,
+    plan: &SetupPlan,
+) -> (Pubkey, Vec<&'static str>) {
+    let (referral_program, vault, token_vault) = referral_program_pdas(owner.pubkey());
+    let mint = plan.token.map(|(mint, _)| mint);
+    let mut submitted = Vec::new();
+
+    let exists = context.banks_client.get_account(referral_program).await.expect("Failed to fetch account").is_some();
+    if !exists {
+        let fixed_reward_amount = plan.settings.fixed_reward_amount;
+        let ix = create_referral_program_ix(owner, mint, fixed_reward_amount, plan.settings.program_end_time, true);
+        process(context, &[ix], &[owner]).await.expect("Failed to create referral program");
+        submitted.push("create_referral_program");
+    }
+
+    loop {
+        let status = get_setup_state(context, referral_program).await;
+        let activated = status.setup_state & ReferralProgram::SETUP_ACTIVATED != 0;
+        let (name, ix) = match status.next_step {
+            0 => return (referral_program, submitted),
+            ReferralProgram::SETUP_CRITERIA_SET => {
+                let ix = update_program_settings_ix(context, owner, referral_program, plan.settings.clone()).await;
+                ("update_program_settings", ix)
+            }
+            ReferralProgram::SETUP_VAULT_INITIALIZED => {
+                ("initialize_token_vault", initialize_token_vault_ix(owner, mint.expect("SOL programs need no vault")))
+            }
+            // An inactive program is funded by its activation; deposits need an active program
+            ReferralProgram::SETUP_FUNDED if !activated => {
+                ("activate_program", activate_program_ix(owner, plan.token, plan.deposit))
+            }
+            ReferralProgram::SETUP_FUNDED => match plan.token {
+                Some((mint, depositor_token_account)) => (
+                    "deposit_token",
+                    program_instruction(
+                        accounts::DepositToken {
+                            referral_program,
+                            token_vault,
+                            token_mint: mint,
+                            depositor_token_account,
+                            authority: owner.pubkey(),
+                            event_queue: None,
+                            token_program: spl_token::id(),
+                        },
+                        instruction::DepositToken { amount: plan.deposit },
+                    ),
+                ),
+                None => ("deposit_sol", deposit_sol_ix(owner, referral_program, vault, plan.deposit)),
+            },
+            ReferralProgram::SETUP_ACTIVATED => ("activate_program", activate_program_ix(owner, plan.token, 0)),
+            step => panic!("Unexpected setup step {step}"),
+        };
+        process(context, &[ix], &[owner]).await.unwrap_or_else(|err| panic!("Setup step {name} failed: {err}"));
+        submitted.push(name);
+    }
+}
+
+/// Builds an `activate_program` instruction depositing `initial_deposit`, from the token account in `token` for
+/// a token program
+pub fn activate_program_ix(owner: &Keypair, token: Option<(Pubkey, Pubkey)>, initial_deposit: u64) -> Instruction {
+    let (referral_program, vault, token_vault) = referral_program_pdas(owner.pubkey());
+    program_instruction(
+        accounts::ActivateProgram {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            vault,
+            token_vault: token.map(|_| token_vault),
+            depositor_token_account: token.map(|(_, depositor_token_account)| depositor_token_account),
+            authority: owner.pubkey(),
+            system_program: system_program::ID,
+            token_program: token.map(|_| spl_token::id()),
+        },
+        instruction::ActivateProgram { initial_deposit },
+    )
+}
@@ -1,13 +1,163 @@
-#[cfg(test)]
+#[cfg(all(test, feature = "validator"))]
 mod test_referral_program;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "validator"))]
 mod test_token_referral_program;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "validator"))]
 mod test_join_referral_program;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "validator"))]
 mod test_reward;
+#[cfg(test)]
+mod test_properties;
+
+// In-process suites on solana-program-test; the others need a local validator and run with `--features validator`
+#[cfg(test)]
+mod banks_util;
+#[cfg(test)]
+mod test_banks_abandonment;
+#[cfg(test)]
+mod test_banks_audit;
+#[cfg(test)]
+mod test_banks_boost;
+#[cfg(test)]
+mod test_banks_bridge;
+#[cfg(test)]
+mod test_banks_claim;
+#[cfg(test)]
+mod test_banks_claim_differential;
+#[cfg(test)]
+mod test_banks_clawback_dispute;
+#[cfg(test)]
+mod test_banks_collection_gate;
+#[cfg(test)]
+mod test_banks_direct_claims;
+#[cfg(test)]
+mod test_banks_final_report;
+#[cfg(test)]
+mod test_banks_funding;
+#[cfg(test)]
+mod test_banks_guardian;
+#[cfg(test)]
+mod test_banks_history;
+#[cfg(test)]
+mod test_banks_idempotency;
+#[cfg(test)]
+mod test_banks_join;
+#[cfg(test)]
+mod test_banks_join_requirements;
+#[cfg(test)]
+mod test_banks_link_params;
+#[cfg(test)]
+mod test_banks_sponsor_match;
+#[cfg(test)]
+mod test_banks_cleanup;
+#[cfg(test)]
+mod test_banks_wrapped_claim;
+#[cfg(test)]
+mod test_banks_governance;
+#[cfg(test)]
+mod test_banks_claim_splitter;
+#[cfg(test)]
+mod test_banks_settings_timelock;
+#[cfg(test)]
+mod test_banks_link_payload;
+#[cfg(test)]
+mod test_banks_leave;
+#[cfg(test)]
+mod test_banks_join_digest;
+#[cfg(test)]
+mod test_space;
+#[cfg(test)]
+mod test_banks_late_attribution;
+#[cfg(test)]
+mod test_banks_auto_downgrade;
+#[cfg(test)]
+mod test_banks_seeding;
+#[cfg(test)]
+mod test_banks_early_redemption;
+#[cfg(test)]
+mod test_banks_program_status;
+#[cfg(test)]
+mod test_banks_program_index;
+#[cfg(test)]
+mod test_banks_token_vault;
+#[cfg(test)]
+mod test_banks_usd_rewards;
+#[cfg(test)]
+mod test_banks_link_proof;
+#[cfg(test)]
+mod test_banks_maintenance;
+#[cfg(test)]
+mod test_banks_network_config;
+#[cfg(test)]
+mod test_banks_note;
+#[cfg(test)]
+mod test_banks_preview;
+#[cfg(test)]
+mod test_banks_rate_limit;
+#[cfg(test)]
+mod test_banks_referral_counts;
+#[cfg(test)]
+mod test_banks_region_embargo;
+#[cfg(test)]
+mod test_banks_runway_alerts;
+#[cfg(test)]
+mod test_banks_settings_log;
+#[cfg(test)]
+mod test_banks_setup;
+#[cfg(test)]
+mod test_banks_sponsored_rent;
+#[cfg(test)]
+mod test_banks_statement;
+#[cfg(test)]
+mod test_banks_token_requirements;
+#[cfg(test)]
+mod test_banks_trailing_commission;
+#[cfg(test)]
+mod test_banks_transfer;
+#[cfg(test)]
+mod test_banks_ui_totals;
+#[cfg(test)]
+mod test_banks_version;
+#[cfg(test)]
+mod test_banks_zero_amounts;
+#[cfg(test)]
+mod test_banks_claim_eligibility;
+#[cfg(test)]
+mod test_banks_event_queue;
+#[cfg(test)]
+mod test_banks_payout_split;
+#[cfg(test)]
+mod test_banks_owner_rotation;
+#[cfg(test)]
+mod test_banks_milestones;
+#[cfg(test)]
+mod test_banks_invites;
+#[cfg(test)]
+mod test_banks_fee_config;
+#[cfg(test)]
+mod test_banks_contest;
+#[cfg(test)]
+mod test_banks_validation;
+#[cfg(test)]
+mod test_banks_purchase;
+#[cfg(test)]
+mod test_banks_source_tags;
+#[cfg(test)]
+mod test_banks_join_and_claim;
+#[cfg(test)]
+mod test_banks_close_program;
+#[cfg(test)]
+mod test_banks_terms;
+#[cfg(test)]
+mod test_banks_reserve;
+#[cfg(test)]
+mod test_banks_open_ended;
+#[cfg(test)]
+mod test_banks_recount;
 
 pub mod test_util;
+#[cfg(test)]
+mod test_banks_payout_kinds;
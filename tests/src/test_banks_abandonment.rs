@@ -0,0 +1,187 @@
+//! Abandonment of programs whose authority stopped acting.
+//!
+//! The authority sets up a guarded program and goes quiet after Alice earns a referral reward. Once the dormancy
+//! period has passed anyone declares the program abandoned; Alice can still claim while the authority is locked out.
+
+use anchor_client::{
+    anchor_lang::InstructionData,
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer},
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::{DEFAULT_DORMANCY_PERIOD, MIN_DORMANCY_PERIOD, MIN_LOCKED_PERIOD, REWARD_DENOMINATION_RAW},
+    error::ReferralError,
+    instruction,
+    instructions::{GuardianConfig, ProgramSettings},
+    state::ReferralProgram,
+};
+
+use crate::banks_util::{
+    advance_clock, assert_referral_error, claim_rewards, create_funded_user, create_referral_program_ix, deposit_sol,
+    deposit_sol_ix, get_account, get_balance, get_clock_time, join_referral_program, join_through_referral, process,
+    program_instruction, referral_program_pdas, setup, update_program_settings, update_program_settings_ix,
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_DAY: i64 = 86400;
+const ONE_YEAR: i64 = 365 * ONE_DAY;
+
+fn settings(end_time: i64) -> ProgramSettings {
+    ProgramSettings {
+        fixed_reward_amount: REWARD,
+        locked_period: MIN_LOCKED_PERIOD,
+        program_end_time: Some(end_time),
+        base_reward: REWARD,
+        max_reward_cap: 100 * REWARD,
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: MIN_DORMANCY_PERIOD,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
+    }
+}
+
+fn declare_abandoned_ix(caller: &Keypair, referral_program: Pubkey) -> Instruction {
+    program_instruction(
+        accounts::DeclareAbandoned { referral_program, caller: caller.pubkey() },
+        instruction::DeclareAbandoned {},
+    )
+}
+
+fn guardian_freeze_ix(guardian: &Keypair, referral_program: Pubkey) -> Instruction {
+    program_instruction(
+        accounts::GuardianFreeze { referral_program, guardian: guardian.pubkey() },
+        instruction::GuardianFreeze {},
+    )
+}
+
+/// Creates a SOL program guarded by `guardian`, with the minimum dormancy period, and funds it with ten rewards
+async fn create_program(context: &mut ProgramTestContext, owner: &Keypair, guardian: &Keypair) -> (Pubkey, Pubkey) {
+    let end_time = get_clock_time(context).await + ONE_YEAR;
+    let mut create_ix = create_referral_program_ix(owner, None, REWARD, Some(end_time), false);
+    create_ix.data = instruction::CreateReferralProgram {
+        program_index: 0,
+        token_mint: None,
+        fixed_reward_amount: REWARD,
+        program_end_time: Some(end_time),
+        reward_denomination: REWARD_DENOMINATION_RAW,
+        start_inactive: false,
+        terms_hash: [0u8; 32],
+        guardian: Some(GuardianConfig { guardian: guardian.pubkey(), withdrawal_delay_threshold: 0 }),
+        withdrawal_destinations: Default::default(),
+        settings_locked_until: None,
+        settings: None,
+    }
+    .data();
+    process(context, &[create_ix], &[owner]).await.unwrap();
+
+    let (referral_program, vault, _) = referral_program_pdas(owner.pubkey());
+    update_program_settings(context, owner, referral_program, settings(end_time)).await;
+    deposit_sol(context, owner, referral_program, vault, 10 * REWARD).await;
+    (referral_program, vault)
+}
+
+#[test]
+fn test_authority_actions_postpone_dormancy() {
+    let mut program = ReferralProgram::default();
+    program.record_authority_action(1_000).unwrap();
+    assert_eq!(program.dormant_at(), 1_000 + DEFAULT_DORMANCY_PERIOD);
+
+    program.dormancy_period_seconds = MIN_DORMANCY_PERIOD;
+    program.record_authority_action(2_000).unwrap();
+    assert_eq!((program.last_authority_action, program.dormant_at()), (2_000, 2_000 + MIN_DORMANCY_PERIOD));
+    assert!(!program.is_abandoned());
+
+    // Once abandoned the authority can no longer act, and its last action stays on record
+    program.abandoned_at = program.dormant_at();
+    assert!(program.is_abandoned());
+    let error = program.record_authority_action(program.abandoned_at + 1).unwrap_err();
+    assert_eq!(error, ReferralError::ProgramAbandoned.into());
+    assert_eq!(program.last_authority_action, 2_000);
+}
+
+#[tokio::test]
+async fn test_abandoned_program_keeps_paying_claims() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let guardian = Keypair::new();
+    let keeper = create_funded_user(&mut context).await;
+    let (referral_program, vault) = create_program(&mut context, &owner, &guardian).await;
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    join_through_referral(&mut context, &bob, referral_program, alice_participant).await;
+
+    // Nobody can declare it abandoned while the authority keeps acting
+    let result = process(&mut context, &[declare_abandoned_ix(&keeper, referral_program)], &[&keeper]).await;
+    assert_referral_error(result, ReferralError::ProgramNotDormant);
+    advance_clock(&mut context, MIN_DORMANCY_PERIOD - ONE_DAY).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, REWARD).await;
+    let last_authority_action = get_clock_time(&mut context).await;
+    advance_clock(&mut context, ONE_DAY).await;
+    let result = process(&mut context, &[declare_abandoned_ix(&keeper, referral_program)], &[&keeper]).await;
+    assert_referral_error(result, ReferralError::ProgramNotDormant);
+
+    // The guardian freezes the program, then the authority goes quiet for the whole dormancy period
+    process(&mut context, &[guardian_freeze_ix(&guardian, referral_program)], &[&guardian]).await.unwrap();
+    advance_clock(&mut context, MIN_DORMANCY_PERIOD).await;
+    process(&mut context, &[declare_abandoned_ix(&keeper, referral_program)], &[&keeper]).await.unwrap();
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.abandoned_at, get_clock_time(&mut context).await);
+    assert_eq!(program.last_authority_action, last_authority_action);
+    assert!(!program.frozen);
+
+    // Alice's reward is still paid, and new referrals still credit her
+    let before = get_balance(&mut context, alice.pubkey()).await;
+    claim_rewards(&mut context, &alice, referral_program, alice_participant, vault).await.unwrap();
+    assert_eq!(get_balance(&mut context, alice.pubkey()).await, before + REWARD);
+    let carol = create_funded_user(&mut context).await;
+    join_through_referral(&mut context, &carol, referral_program, alice_participant).await;
+    let before = get_balance(&mut context, alice.pubkey()).await;
+    claim_rewards(&mut context, &alice, referral_program, alice_participant, vault).await.unwrap();
+    assert_eq!(get_balance(&mut context, alice.pubkey()).await, before + REWARD);
+
+    // The authority is locked out, the settings stay as they are, and nobody can freeze or re-declare it
+    let ix = deposit_sol_ix(&owner, referral_program, vault, REWARD);
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::ProgramAbandoned);
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let ix = update_program_settings_ix(&mut context, &owner, referral_program, settings(end_time)).await;
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::ProgramAbandoned);
+    let ix = guardian_freeze_ix(&guardian, referral_program);
+    assert_referral_error(process(&mut context, &[ix], &[&guardian]).await, ReferralError::ProgramAbandoned);
+    let result = process(&mut context, &[declare_abandoned_ix(&keeper, referral_program)], &[&keeper]).await;
+    assert_referral_error(result, ReferralError::ProgramAbandoned);
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.last_authority_action, last_authority_action);
+}
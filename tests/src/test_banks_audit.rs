@@ -0,0 +1,162 @@
+//! Read-only audits of a program's accounting invariants.
+//!
+//! Alice refers Bob and claims the reward; the audit passes throughout, then flags the vault once lamports leave it
+//! behind the program's back. Corrupting one recorded total at a time flags exactly the invariant it breaks.
+
+use anchor_client::solana_sdk::{instruction::Instruction, pubkey::Pubkey, signer::Signer};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::{AUDIT_END_TIME_STALE, AUDIT_FUNDS_UNBALANCED, AUDIT_REFERRAL_COUNTS, AUDIT_VAULT_INSOLVENT},
+    events::AuditFailed,
+    instruction,
+    instructions::{
+        audit,
+        test_utils::{self, CorruptedField},
+    },
+    state::{EligibilityCriteria, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        claim_rewards, create_funded_user, create_sol_referral_program, deposit_sol, get_account, get_clock_time,
+        join_referral_program, join_through_referral, process, program_instruction, setup, simulate_events,
+        simulate_return,
+    },
+    test_util::get_eligibility_criteria_pda,
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+fn audit_program_ix(referral_program: Pubkey, vault: Pubkey) -> Instruction {
+    program_instruction(
+        accounts::AuditProgram {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            vault,
+            token_vault: None,
+        },
+        instruction::AuditProgram {},
+    )
+}
+
+/// Audits a SOL program by simulating `audit_program`
+async fn audit_program(context: &mut ProgramTestContext, referral_program: Pubkey, vault: Pubkey) -> u8 {
+    simulate_return(context, audit_program_ix(referral_program, vault)).await
+}
+
+#[test]
+fn test_audit_flags_each_invariant() {
+    let mut program = ReferralProgram {
+        total_deposited: 10 * REWARD,
+        total_available: 6 * REWARD,
+        reserved_balance: REWARD,
+        total_rewards_distributed: 3 * REWARD,
+        total_referrals_raw: 3,
+        total_referrals_credited: 3,
+        untagged_joins: 3,
+        ..Default::default()
+    };
+    let criteria = EligibilityCriteria::default();
+    assert_eq!(audit(&program, &criteria, 7 * REWARD), 0);
+
+    // Dust short of the recorded funds is tolerated
+    assert_eq!(audit(&program, &criteria, 7 * REWARD - 10), 0);
+    assert_eq!(audit(&program, &criteria, 7 * REWARD - 11), AUDIT_VAULT_INSOLVENT);
+
+    program.total_rewards_distributed += 1;
+    assert_eq!(audit(&program, &criteria, 7 * REWARD), AUDIT_FUNDS_UNBALANCED);
+    program.total_rewards_distributed -= 1;
+
+    program.total_referrals_credited += 1;
+    assert_eq!(audit(&program, &criteria, 7 * REWARD), AUDIT_REFERRAL_COUNTS);
+    program.total_referrals_credited -= 1;
+    program.other_tag_joins += 1;
+    assert_eq!(audit(&program, &criteria, 7 * REWARD), AUDIT_REFERRAL_COUNTS);
+    program.other_tag_joins -= 1;
+
+    program.program_end_time = Some(ONE_YEAR);
+    assert_eq!(audit(&program, &criteria, 7 * REWARD), AUDIT_END_TIME_STALE);
+}
+
+#[tokio::test]
+async fn test_audit_passes_until_the_vault_is_drained() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    assert_eq!(audit_program(&mut context, referral_program, vault).await, 0);
+
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    join_through_referral(&mut context, &bob, referral_program, alice_participant).await;
+    claim_rewards(&mut context, &alice, referral_program, alice_participant, vault).await.unwrap();
+    assert_eq!(audit_program(&mut context, referral_program, vault).await, 0);
+
+    // Lamports leaving the vault outside the program's accounting leave it insolvent, and nothing else
+    let mut account = context.banks_client.get_account(vault).await.unwrap().unwrap();
+    account.lamports -= REWARD;
+    context.set_account(&vault, &account.into());
+    assert_eq!(audit_program(&mut context, referral_program, vault).await, AUDIT_VAULT_INSOLVENT);
+}
+
+#[tokio::test]
+async fn test_audit_flags_exactly_the_corrupted_invariant() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let carol = create_funded_user(&mut context).await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    join_through_referral(&mut context, &bob, referral_program, alice_participant).await;
+    join_through_referral(&mut context, &carol, referral_program, alice_participant).await;
+
+    let corrupt_ix = |field: CorruptedField| {
+        program_instruction(
+            test_utils::accounts::CorruptTotalAvailable { referral_program, authority: owner.pubkey() },
+            test_utils::instruction::CorruptProgramField { field },
+        )
+    };
+    assert_eq!(audit_program(&mut context, referral_program, vault).await, 0);
+    let events: Vec<AuditFailed> =
+        simulate_events(&mut context, &[audit_program_ix(referral_program, vault)], &[]).await;
+    assert!(events.is_empty());
+    let healthy: ReferralProgram = get_account(&mut context, referral_program).await;
+
+    // Rewards distributed out of thin air unbalance the funds against the deposits
+    let ix = corrupt_ix(CorruptedField::TotalRewardsDistributed(REWARD));
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    assert_eq!(audit_program(&mut context, referral_program, vault).await, AUDIT_FUNDS_UNBALANCED);
+    let events: Vec<AuditFailed> =
+        simulate_events(&mut context, &[audit_program_ix(referral_program, vault)], &[]).await;
+    assert_eq!(events.len(), 1);
+    assert_eq!((events[0].referral_program, events[0].violations), (referral_program, AUDIT_FUNDS_UNBALANCED));
+    let ix = corrupt_ix(CorruptedField::TotalRewardsDistributed(healthy.total_rewards_distributed));
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    assert_eq!(audit_program(&mut context, referral_program, vault).await, 0);
+
+    let ix = corrupt_ix(CorruptedField::TotalReferralsCredited(healthy.total_referrals_raw + 1));
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    assert_eq!(audit_program(&mut context, referral_program, vault).await, AUDIT_REFERRAL_COUNTS);
+    let ix = corrupt_ix(CorruptedField::TotalReferralsCredited(healthy.total_referrals_credited));
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+
+    let ix = corrupt_ix(CorruptedField::ProgramEndTime(None));
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    assert_eq!(audit_program(&mut context, referral_program, vault).await, AUDIT_END_TIME_STALE);
+    let ix = corrupt_ix(CorruptedField::ProgramEndTime(healthy.program_end_time));
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    assert_eq!(audit_program(&mut context, referral_program, vault).await, 0);
+
+    // The vault only ever holds the deposits not yet paid out, so recording more funds than it holds also
+    // records more than was deposited
+    let ix = program_instruction(
+        test_utils::accounts::CorruptTotalAvailable { referral_program, authority: owner.pubkey() },
+        test_utils::instruction::CorruptTotalAvailable { total_available: 11 * REWARD },
+    );
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    assert_eq!(
+        audit_program(&mut context, referral_program, vault).await,
+        AUDIT_VAULT_INSOLVENT | AUDIT_FUNDS_UNBALANCED
+    );
+}
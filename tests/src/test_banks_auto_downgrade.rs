@@ -0,0 +1,162 @@
+//! The flat referral reward stepping down through the tier and base rewards as the vault depletes.
+//!
+//! `downgraded_reward` is checked at every headroom around each step of the ladder. Then Alice refers six wallets
+//! into a program whose flat reward is the tier 2 reward and whose deposit runs out along the way: her credits step
+//! down to tier 1, then the base reward, and are zero only once even the base reward no longer fits.
+
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    constants::MIN_LOCKED_PERIOD,
+    error::ReferralError,
+    events::RewardDowngraded,
+    instructions::{current_settings, ProgramSettings},
+    state::{EligibilityCriteria, RefereeReceipt, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol, get_account,
+        get_clock_time, join_referral_program, join_through_referral_ix, process, process_with_events, setup,
+        update_program_settings, update_program_settings_ix,
+    },
+    test_util::{get_eligibility_criteria_pda, get_referee_receipt_pda},
+};
+
+const BASE: u64 = 100_000;
+const TIER1: u64 = 2 * BASE;
+const TIER2: u64 = 4 * BASE;
+const ONE_YEAR: i64 = 365 * 86400;
+
+/// Criteria with the reward ladder `TIER2`, `TIER1`, `BASE` and auto downgrade sustaining `min_remaining_credits`
+fn ladder(min_remaining_credits: u32) -> EligibilityCriteria {
+    EligibilityCriteria {
+        base_reward: BASE,
+        tier1_reward: TIER1,
+        tier2_reward: TIER2,
+        auto_downgrade: true,
+        min_remaining_credits,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_downgraded_reward_steps_down_at_each_boundary() {
+    let criteria = ladder(5);
+    let expected = |headroom: u64| match headroom {
+        headroom if headroom >= 5 * TIER2 => TIER2,
+        headroom if headroom >= 5 * TIER1 => TIER1,
+        headroom if headroom >= BASE => BASE,
+        _ => 0,
+    };
+    for headroom in 0..=5 * TIER2 + 1 {
+        assert_eq!(criteria.downgraded_reward(TIER2, headroom), expected(headroom), "headroom {headroom}");
+    }
+}
+
+#[test]
+fn test_downgraded_reward_only_steps_below_the_request() {
+    // A request between the tiers steps down to the tier below it
+    let criteria = ladder(2);
+    let between = TIER1 + BASE / 2;
+    assert_eq!(criteria.downgraded_reward(between, 2 * between), between);
+    assert_eq!(criteria.downgraded_reward(between, 2 * between - 1), TIER1);
+
+    // A request at or below the base reward is the only step, credited while it fits once
+    for requested in [BASE, BASE / 2] {
+        assert_eq!(criteria.downgraded_reward(requested, 2 * requested), requested);
+        assert_eq!(criteria.downgraded_reward(requested, requested), requested);
+        assert_eq!(criteria.downgraded_reward(requested, requested - 1), 0);
+    }
+
+    // Unset tiers are no steps
+    let base_only = EligibilityCriteria { tier1_reward: 0, tier2_reward: 0, ..ladder(2) };
+    assert_eq!(base_only.downgraded_reward(TIER2, 2 * TIER2 - 1), BASE);
+    assert_eq!(base_only.downgraded_reward(TIER2, BASE - 1), 0);
+}
+
+#[test]
+fn test_downgraded_reward_without_auto_downgrade() {
+    let criteria = EligibilityCriteria { auto_downgrade: false, ..ladder(5) };
+    for headroom in [0, BASE - 1, BASE, 5 * TIER2 - 1, u64::MAX] {
+        assert_eq!(criteria.downgraded_reward(TIER2, headroom), TIER2);
+    }
+}
+
+#[test]
+fn test_downgraded_reward_does_not_overflow() {
+    let criteria = EligibilityCriteria { tier2_reward: u64::MAX, ..ladder(u32::MAX) };
+    assert_eq!(criteria.downgraded_reward(u64::MAX, u64::MAX), TIER1);
+    assert_eq!(criteria.downgraded_reward(u64::MAX, BASE), BASE);
+}
+
+/// Returns the settings of `referral_program` paying the tier 2 reward, auto downgraded to sustain
+/// `min_remaining_credits`
+async fn downgrade_settings(
+    context: &mut ProgramTestContext,
+    referral_program: Pubkey,
+    min_remaining_credits: u32,
+) -> ProgramSettings {
+    let program: ReferralProgram = get_account(context, referral_program).await;
+    let criteria: EligibilityCriteria =
+        get_account(context, get_eligibility_criteria_pda(referral_program, solrefer::ID)).await;
+    ProgramSettings {
+        locked_period: MIN_LOCKED_PERIOD,
+        fixed_reward_amount: TIER2,
+        base_reward: BASE,
+        tier1_reward: TIER1,
+        tier2_reward: TIER2,
+        max_reward_cap: 100 * TIER2,
+        auto_downgrade: true,
+        min_remaining_credits,
+        ..current_settings(&program, &criteria)
+    }
+}
+
+#[tokio::test]
+async fn test_rewards_step_down_as_the_vault_depletes() {
+    let (mut context, owner, alice, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, TIER2, Some(end_time)).await;
+    let settings = downgrade_settings(&mut context, referral_program, 2).await;
+    update_program_settings(&mut context, &owner, referral_program, settings).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * BASE).await;
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+
+    // Each reward must be covered twice over by what is left: 1.0M, 600k, 400k, 200k, 100k, then nothing
+    let mut credited = Vec::new();
+    let mut downgrades = Vec::new();
+    for _ in 0..6 {
+        let referee: Keypair = create_funded_user(&mut context).await;
+        let ix = join_through_referral_ix(&referee, referral_program, alice_participant, None);
+        let events: Vec<RewardDowngraded> = process_with_events(&mut context, &[ix], &[&referee]).await;
+        downgrades.extend(events.into_iter().map(|event| (event.requested, event.paid)));
+        let receipt: RefereeReceipt =
+            get_account(&mut context, get_referee_receipt_pda(referral_program, referee.pubkey(), solrefer::ID)).await;
+        credited.push(receipt.credited_amount);
+    }
+    assert_eq!(credited, [TIER2, TIER1, TIER1, BASE, BASE, 0]);
+    assert_eq!(downgrades, [(TIER2, TIER1), (TIER2, TIER1), (TIER2, BASE), (TIER2, BASE), (TIER2, 0)]);
+
+    // Every credited reward is committed and the vault is fully spoken for
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.total_committed, 10 * BASE);
+    assert_eq!(program.total_referrals_credited, 6);
+}
+
+#[tokio::test]
+async fn test_auto_downgrade_needs_remaining_credits() {
+    let (mut context, owner, ..) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, _) = create_sol_referral_program(&mut context, &owner, TIER2, Some(end_time)).await;
+
+    let settings = downgrade_settings(&mut context, referral_program, 0).await;
+    let ix = update_program_settings_ix(&mut context, &owner, referral_program, settings).await;
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::InvalidAutoDowngrade);
+
+    // Tiers sit above the base reward and below each other
+    let settings =
+        ProgramSettings { tier2_reward: BASE, ..downgrade_settings(&mut context, referral_program, 2).await };
+    let ix = update_program_settings_ix(&mut context, &owner, referral_program, settings).await;
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::InvalidTierReward);
+}
@@ -0,0 +1,184 @@
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer},
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::MIN_LOCKED_PERIOD,
+    error::ReferralError,
+    instruction,
+    instructions::ProgramSettings,
+    state::{BoostEscrow, Participant},
+};
+
+use crate::{
+    banks_util::{
+        assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol, get_account, get_balance,
+        get_clock_time, join_referral_program, join_through_referral_with_boost, process, program_instruction, setup,
+        update_program_settings,
+    },
+    test_util::{get_boost_escrow_pda, get_participant_pda, get_referee_receipt_pda},
+};
+
+const REFERRAL_REWARD: u64 = 100_000_000;
+const REFEREE_REWARD: u64 = 20_000_000;
+const BOOST: u64 = 50_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+#[test]
+fn test_next_boost_is_all_or_nothing() {
+    let mut escrow = BoostEscrow { boost_per_referee: BOOST, balance: BOOST + BOOST / 2, ..Default::default() };
+    assert_eq!(escrow.next_boost(), BOOST);
+    escrow.balance = BOOST - 1;
+    assert_eq!(escrow.next_boost(), 0);
+    escrow.boost_per_referee = 0;
+    escrow.balance = BOOST;
+    assert_eq!(escrow.next_boost(), 0);
+}
+
+#[tokio::test]
+async fn test_referrer_funded_boost() {
+    let (mut context, owner, referrer, referee) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) =
+        create_sol_referral_program(&mut context, &owner, REFERRAL_REWARD, Some(end_time)).await;
+    update_program_settings(
+        &mut context,
+        &owner,
+        referral_program,
+        ProgramSettings {
+            fixed_reward_amount: REFERRAL_REWARD,
+            locked_period: MIN_LOCKED_PERIOD,
+            program_end_time: Some(end_time),
+            base_reward: REFERRAL_REWARD,
+            max_reward_cap: 10 * REFERRAL_REWARD,
+            max_depth: 0,
+            milestones: Default::default(),
+            invite_only: false,
+            revenue_share_percent: 0,
+            referee_reward_amount: REFEREE_REWARD,
+            referee_rewards_locked: false,
+            reserve_bps: 0,
+            max_referrals_per_window: 0,
+            referral_window_seconds: 0,
+            rate_limit_strict: false,
+            required_collection: None,
+            collection_gates_credits: false,
+            transfers_enabled: false,
+            dispute_window_seconds: 0,
+            rent_payer_mode: 0,
+            min_joiner_balance: 0,
+            min_account_age_seconds: 0,
+            alert_thresholds: [0; 3],
+            referrer_requirement: None,
+            referee_requirement: None,
+            direct_claims_only: false,
+            trailing_commission_bps: 0,
+            link_signer: None,
+            dormancy_period_seconds: 0,
+            region_attestor: None,
+            embargoed_regions: [0; 8],
+            region_checked_on_claim: false,
+            bridge_source_program: None,
+            bridge_bps: 0,
+            cleanup_bounty_bps: 0,
+            event_mode: 0,
+            attribution_grace_seconds: 0,
+            tier1_reward: 0,
+            tier2_reward: 0,
+            auto_downgrade: false,
+            min_remaining_credits: 0,
+            seed_reclaim_seconds: 0,
+            early_redemption_fee: 0,
+        },
+    )
+    .await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REFERRAL_REWARD).await;
+
+    let referrer_participant = join_referral_program(&mut context, &referrer, referral_program).await;
+    let boost_escrow = get_boost_escrow_pda(referral_program, referrer_participant, solrefer::ID);
+    let fund_ix = |amount: u64, boost_per_referee: u64| {
+        program_instruction(
+            accounts::FundRefereeBoost {
+                referral_program,
+                participant: referrer_participant,
+                boost_escrow,
+                user: referrer.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::FundRefereeBoost { amount, boost_per_referee },
+        )
+    };
+    let withdraw_ix = |amount: u64| {
+        program_instruction(
+            accounts::WithdrawRefereeBoost {
+                referral_program,
+                participant: referrer_participant,
+                boost_escrow,
+                user: referrer.pubkey(),
+            },
+            instruction::WithdrawRefereeBoost { amount },
+        )
+    };
+
+    let result = process(&mut context, &[fund_ix(BOOST, 0)], &[&referrer]).await;
+    assert_referral_error(result, ReferralError::InvalidBoostAmount);
+    process(&mut context, &[fund_ix(BOOST + BOOST / 2, BOOST)], &[&referrer]).await.unwrap();
+    let escrow: BoostEscrow = get_account(&mut context, boost_escrow).await;
+    assert_eq!(escrow.balance, BOOST + BOOST / 2);
+    assert_eq!(escrow.boost_per_referee, BOOST);
+    let escrow_lamports = get_balance(&mut context, boost_escrow).await;
+    let vault_lamports = get_balance(&mut context, vault).await;
+
+    // The referee is paid the boost at join time and credited the program's sign-up bonus as usual
+    let boosted = join_and_measure(&mut context, &referee, referral_program, referrer_participant, boost_escrow).await;
+    assert_eq!(boosted, BOOST as i64);
+    let participant: Participant =
+        get_account(&mut context, get_participant_pda(referral_program, referee.pubkey(), solrefer::ID)).await;
+    assert_eq!(participant.pending_rewards, REFEREE_REWARD);
+    let escrow: BoostEscrow = get_account(&mut context, boost_escrow).await;
+    assert_eq!(escrow.balance, BOOST / 2);
+    assert_eq!(escrow.total_boosted, BOOST);
+    assert_eq!(escrow.referees_boosted, 1);
+    assert_eq!(get_balance(&mut context, boost_escrow).await, escrow_lamports - BOOST);
+    assert_eq!(get_balance(&mut context, vault).await, vault_lamports);
+
+    // The remainder is short of a full boost and stays withdrawable
+    let result = process(&mut context, &[withdraw_ix(BOOST)], &[&referrer]).await;
+    assert_referral_error(result, ReferralError::InsufficientBoostBalance);
+    let referrer_lamports = get_balance(&mut context, referrer.pubkey()).await;
+    process(&mut context, &[withdraw_ix(BOOST / 2)], &[&referrer]).await.unwrap();
+    assert_eq!(get_balance(&mut context, referrer.pubkey()).await, referrer_lamports + BOOST / 2);
+    let escrow: BoostEscrow = get_account(&mut context, boost_escrow).await;
+    assert_eq!(escrow.balance, 0);
+
+    // Once the escrow is exhausted referees only get the program's bonus
+    let late_referee = create_funded_user(&mut context).await;
+    let boosted =
+        join_and_measure(&mut context, &late_referee, referral_program, referrer_participant, boost_escrow).await;
+    assert_eq!(boosted, 0);
+    let participant: Participant =
+        get_account(&mut context, get_participant_pda(referral_program, late_referee.pubkey(), solrefer::ID)).await;
+    assert_eq!(participant.pending_rewards, REFEREE_REWARD);
+    let escrow: BoostEscrow = get_account(&mut context, boost_escrow).await;
+    assert_eq!(escrow.referees_boosted, 1);
+}
+
+/// Joins `user` through the referrer with its boost escrow and returns the lamports the join paid the user,
+/// net of the rent the user put into its new participant and receipt accounts
+async fn join_and_measure(
+    context: &mut ProgramTestContext,
+    user: &Keypair,
+    referral_program: Pubkey,
+    referrer_participant: Pubkey,
+    boost_escrow: Pubkey,
+) -> i64 {
+    let before = get_balance(context, user.pubkey()).await;
+    let participant =
+        join_through_referral_with_boost(context, user, referral_program, referrer_participant, Some(boost_escrow))
+            .await;
+    let rent = get_balance(context, participant).await
+        + get_balance(context, get_referee_receipt_pda(referral_program, user.pubkey(), solrefer::ID)).await;
+    get_balance(context, user.pubkey()).await as i64 - before as i64 + rent as i64
+}
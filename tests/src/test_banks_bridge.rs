@@ -0,0 +1,187 @@
+//! Referral bridges between programs.
+//!
+//! Alice refers Bob into the "wallet" program; when Bob later joins the "exchange" program directly, the exchange
+//! credits Alice a bridge credit from its own vault.
+
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer},
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::{MAX_BRIDGE_BPS, MIN_LOCKED_PERIOD},
+    error::ReferralError,
+    instruction,
+    instructions::ProgramSettings,
+    state::{Participant, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol, get_account,
+        get_clock_time, join_referral_program, join_through_referral, process, program_instruction, setup,
+        update_program_settings, update_program_settings_ix,
+    },
+    test_util::{get_eligibility_criteria_pda, get_participant_pda},
+};
+
+const REWARD: u64 = 1_000_000;
+const BRIDGE_BPS: u64 = 2_500;
+const ONE_YEAR: i64 = 365 * 86400;
+
+fn settings(end_time: i64, bridge_source_program: Option<Pubkey>, bridge_bps: u64) -> ProgramSettings {
+    ProgramSettings {
+        fixed_reward_amount: REWARD,
+        locked_period: MIN_LOCKED_PERIOD,
+        program_end_time: Some(end_time),
+        base_reward: REWARD,
+        max_reward_cap: 100 * REWARD,
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program,
+        bridge_bps,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
+    }
+}
+
+fn join_ix(
+    user: &Keypair,
+    referral_program: Pubkey,
+    bridge_participant: Option<Pubkey>,
+    bridge_referrer: Option<Pubkey>,
+) -> Instruction {
+    program_instruction(
+        accounts::JoinReferralProgram {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+            invite: None,
+            collection_metadata: None,
+            collection_nft: None,
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account: None,
+            bridge_participant,
+            bridge_referrer,
+            user: user.pubkey(),
+            event_queue: None,
+            system_program: system_program::ID,
+        },
+        instruction::JoinReferralProgram { accepted_terms_hash: [0u8; 32] },
+    )
+}
+
+/// Creates the wallet program and the exchange program bridging from it, both funded, and returns them
+async fn create_programs(context: &mut ProgramTestContext, wallet_owner: &Keypair) -> (Pubkey, Pubkey) {
+    let end_time = get_clock_time(context).await + ONE_YEAR;
+    let (wallet, wallet_vault) = create_sol_referral_program(context, wallet_owner, REWARD, Some(end_time)).await;
+    update_program_settings(context, wallet_owner, wallet, settings(end_time, None, 0)).await;
+    deposit_sol(context, wallet_owner, wallet, wallet_vault, 10 * REWARD).await;
+
+    let exchange_owner = create_funded_user(context).await;
+    let (exchange, exchange_vault) =
+        create_sol_referral_program(context, &exchange_owner, REWARD, Some(end_time)).await;
+    update_program_settings(context, &exchange_owner, exchange, settings(end_time, Some(wallet), BRIDGE_BPS)).await;
+    deposit_sol(context, &exchange_owner, exchange, exchange_vault, 10 * REWARD).await;
+
+    // A bridge credit is capped at the base reward and needs a source to bridge from
+    let ix = update_program_settings_ix(
+        context,
+        &exchange_owner,
+        exchange,
+        settings(end_time, Some(wallet), MAX_BRIDGE_BPS + 1),
+    )
+    .await;
+    assert_referral_error(process(context, &[ix], &[&exchange_owner]).await, ReferralError::InvalidBridge);
+    let ix = update_program_settings_ix(context, &exchange_owner, exchange, settings(end_time, None, BRIDGE_BPS)).await;
+    assert_referral_error(process(context, &[ix], &[&exchange_owner]).await, ReferralError::InvalidBridge);
+    (wallet, exchange)
+}
+
+#[tokio::test]
+async fn test_direct_join_credits_bridged_referrer() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let (wallet, exchange) = create_programs(&mut context, &owner).await;
+    let alice_in_wallet = join_referral_program(&mut context, &alice, wallet).await;
+    let alice_in_exchange = join_referral_program(&mut context, &alice, exchange).await;
+    let bob_in_wallet = join_through_referral(&mut context, &bob, wallet, alice_in_wallet).await;
+    let carol = create_funded_user(&mut context).await;
+    let carol_in_exchange = join_referral_program(&mut context, &carol, exchange).await;
+    let committed_before = get_account::<ReferralProgram>(&mut context, exchange).await.total_committed;
+
+    // The bridge only credits the wallet that referred Bob, through Bob's own account in the source program
+    let ix = join_ix(&bob, exchange, Some(bob_in_wallet), Some(carol_in_exchange));
+    assert_referral_error(process(&mut context, &[ix], &[&bob]).await, ReferralError::InvalidBridgeAccounts);
+    let ix = join_ix(&bob, exchange, Some(alice_in_wallet), Some(alice_in_exchange));
+    assert_referral_error(process(&mut context, &[ix], &[&bob]).await, ReferralError::InvalidBridgeAccounts);
+
+    let ix = join_ix(&bob, exchange, Some(bob_in_wallet), Some(alice_in_exchange));
+    process(&mut context, &[ix], &[&bob]).await.unwrap();
+    let credit = REWARD * BRIDGE_BPS / 10_000;
+    let alice_account: Participant = get_account(&mut context, alice_in_exchange).await;
+    assert_eq!((alice_account.pending_rewards, alice_account.total_referrals), (credit, 0));
+    let program: ReferralProgram = get_account(&mut context, exchange).await;
+    assert_eq!(program.total_committed, committed_before + credit);
+
+    // Nothing moved in the source program
+    let alice_account: Participant = get_account(&mut context, alice_in_wallet).await;
+    assert_eq!(alice_account.pending_rewards, REWARD);
+}
+
+#[tokio::test]
+async fn test_bridge_is_skipped_without_its_accounts() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let (wallet, exchange) = create_programs(&mut context, &owner).await;
+    let alice_in_wallet = join_referral_program(&mut context, &alice, wallet).await;
+    let alice_in_exchange = join_referral_program(&mut context, &alice, exchange).await;
+    join_through_referral(&mut context, &bob, wallet, alice_in_wallet).await;
+
+    // Bob joins the exchange without the bridge accounts
+    process(&mut context, &[join_ix(&bob, exchange, None, None)], &[&bob]).await.unwrap();
+    let alice_account: Participant = get_account(&mut context, alice_in_exchange).await;
+    assert_eq!(alice_account.pending_rewards, 0);
+
+    // Dave's referrer in the wallet program never joined the exchange, so there is no one to credit there
+    let erin = create_funded_user(&mut context).await;
+    let erin_in_wallet = join_referral_program(&mut context, &erin, wallet).await;
+    let dave = create_funded_user(&mut context).await;
+    let dave_in_wallet = join_through_referral(&mut context, &dave, wallet, erin_in_wallet).await;
+    let committed_before = get_account::<ReferralProgram>(&mut context, exchange).await.total_committed;
+    process(&mut context, &[join_ix(&dave, exchange, Some(dave_in_wallet), None)], &[&dave]).await.unwrap();
+    let program: ReferralProgram = get_account(&mut context, exchange).await;
+    assert_eq!(program.total_committed, committed_before);
+}
@@ -0,0 +1,375 @@
+use anchor_client::{
+    anchor_lang::{error::ErrorCode, prelude::AccountInfo, system_program},
+    solana_sdk::{
+        instruction::InstructionError, pubkey::Pubkey, signature::Keypair, signer::Signer,
+        transaction::TransactionError,
+    },
+};
+use anchor_spl::token::spl_token;
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::MIN_LOCKED_PERIOD,
+    error::ReferralError,
+    instruction,
+    instructions::{ClaimBalances, ClaimGuard, ProgramSettings},
+    state::{Participant, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, claim_rewards, create_funded_user, create_sol_referral_program,
+        deposit_sol, get_account, get_balance, get_clock_time, join_referral_program, join_through_referral, process,
+        program_instruction, setup, update_program_settings,
+    },
+    test_util::{get_eligibility_criteria_pda, get_participant_pda, get_referee_receipt_pda},
+};
+
+const REFERRAL_REWARD: u64 = 1_000_000_000;
+const REFEREE_REWARD: u64 = REFERRAL_REWARD / 10;
+const ONE_YEAR: i64 = 365 * 86400;
+
+#[tokio::test]
+async fn test_reward_claim() {
+    let (mut context, owner, referrer, referee) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) =
+        create_sol_referral_program(&mut context, &owner, REFERRAL_REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, REFERRAL_REWARD).await;
+
+    let referrer_participant = join_referral_program(&mut context, &referrer, referral_program).await;
+    join_through_referral(&mut context, &referee, referral_program, referrer_participant).await;
+
+    // The bank's payer covers the fees, so balances move by exactly the reward
+    let vault_balance_before = get_balance(&mut context, vault).await;
+    let referrer_balance_before = get_balance(&mut context, referrer.pubkey()).await;
+    let participant_balance_before = get_balance(&mut context, referrer_participant).await;
+    claim_rewards(&mut context, &referrer, referral_program, referrer_participant, vault).await.unwrap();
+    assert_eq!(get_balance(&mut context, vault).await, vault_balance_before - REFERRAL_REWARD);
+    assert_eq!(get_balance(&mut context, referrer.pubkey()).await, referrer_balance_before + REFERRAL_REWARD);
+    assert_eq!(get_balance(&mut context, referrer_participant).await, participant_balance_before);
+
+    let participant: Participant = get_account(&mut context, referrer_participant).await;
+    assert_eq!(participant.total_rewards, REFERRAL_REWARD);
+    assert_eq!(participant.pending_rewards, 0);
+    let program_state: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program_state.total_rewards_distributed, REFERRAL_REWARD);
+    assert_eq!(program_state.total_available, 0);
+
+    // Nothing is left to claim, and a second claim moves nothing
+    let result = claim_rewards(&mut context, &referrer, referral_program, referrer_participant, vault).await;
+    assert_referral_error(result, ReferralError::NoRewardsAvailable);
+    assert_eq!(get_balance(&mut context, vault).await, vault_balance_before - REFERRAL_REWARD);
+}
+
+#[tokio::test]
+async fn test_claim_is_bound_to_its_owner_and_program() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (program_a, vault_a) = create_sol_referral_program(&mut context, &owner, REFERRAL_REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, program_a, vault_a, REFERRAL_REWARD).await;
+    let other_owner = create_funded_user(&mut context).await;
+    let (program_b, vault_b) =
+        create_sol_referral_program(&mut context, &other_owner, REFERRAL_REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &other_owner, program_b, vault_b, REFERRAL_REWARD).await;
+    let alice_participant = join_referral_program(&mut context, &alice, program_a).await;
+    join_through_referral(&mut context, &bob, program_a, alice_participant).await;
+
+    // The participant PDA is derived from the program and the signer, and the vault from the program; the seeds
+    // reject each mix before the participant's owner and program constraints are reached
+    let vault_a_before = get_balance(&mut context, vault_a).await;
+    let vault_b_before = get_balance(&mut context, vault_b).await;
+    let seeds_mismatch = InstructionError::Custom(ErrorCode::ConstraintSeeds.into());
+    for (user, program, vault) in
+        [(&bob, program_a, vault_a), (&alice, program_b, vault_b), (&alice, program_a, vault_b)]
+    {
+        let err = claim_rewards(&mut context, user, program, alice_participant, vault).await.unwrap_err().unwrap();
+        assert_eq!(err, TransactionError::InstructionError(0, seeds_mismatch.clone()));
+    }
+    assert_eq!(get_balance(&mut context, vault_a).await, vault_a_before);
+    assert_eq!(get_balance(&mut context, vault_b).await, vault_b_before);
+
+    claim_rewards(&mut context, &alice, program_a, alice_participant, vault_a).await.unwrap();
+    assert_eq!(get_balance(&mut context, vault_a).await, vault_a_before - REFERRAL_REWARD);
+}
+
+#[test]
+fn test_claim_guard_checks() {
+    let before = ClaimBalances { participant: 2_000, vault: 10_000, destination: 500 };
+    let paid = ClaimBalances { participant: 2_000, vault: 9_000, destination: 1_500 };
+    assert!(before.check_payout(&paid, 1_000).is_ok());
+    assert!(before.check_payout(&before, 0).is_ok());
+
+    // Paid out of the participant account instead of the vault
+    let drained = ClaimBalances { participant: 1_000, vault: 10_000, destination: 1_500 };
+    assert!(before.check_payout(&drained, 1_000).is_err());
+    // More left the vault than the destination received, or the other way round
+    assert!(before.check_payout(&ClaimBalances { vault: 8_000, ..paid }, 1_000).is_err());
+    assert!(before.check_payout(&ClaimBalances { destination: 2_500, ..paid }, 1_000).is_err());
+    // A payout larger than the vault can never balance
+    assert!(before.check_payout(&paid, 20_000).is_err());
+}
+
+/// Runs a token claim guard over a participant PDA holding 2_000 lamports, a token vault holding 10_000 tokens and
+/// a destination holding 500, with `pay` moving balances between creating the guard and finishing it with 1_000
+fn guarded_token_claim(pay: impl FnOnce(&AccountInfo, &AccountInfo, &AccountInfo)) -> bool {
+    let token_account = |amount: u64| {
+        let mut data = vec![0u8; 165];
+        data[64..72].copy_from_slice(&amount.to_le_bytes());
+        data
+    };
+    let keys = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+    let (mut participant_lamports, mut vault_lamports, mut destination_lamports) = (2_000, 100, 100);
+    let (mut participant_data, mut vault_data, mut destination_data) =
+        (vec![], token_account(10_000), token_account(500));
+    let participant = AccountInfo::new(
+        &keys[0],
+        false,
+        true,
+        &mut participant_lamports,
+        &mut participant_data,
+        &solrefer::ID,
+        false,
+        0,
+    );
+    let vault = AccountInfo::new(&keys[1], false, true, &mut vault_lamports, &mut vault_data, &spl_token::ID, false, 0);
+    let destination = AccountInfo::new(
+        &keys[2],
+        false,
+        true,
+        &mut destination_lamports,
+        &mut destination_data,
+        &spl_token::ID,
+        false,
+        0,
+    );
+    let guard = ClaimGuard::new_token_split(participant.clone(), vault.clone(), vec![destination.clone()]).unwrap();
+    pay(&participant, &vault, &destination);
+    guard.finish(1_000).is_ok()
+}
+
+#[test]
+fn test_token_claim_guard_checks() {
+    let set_tokens = |account: &AccountInfo, amount: u64| {
+        account.try_borrow_mut_data().unwrap()[64..72].copy_from_slice(&amount.to_le_bytes());
+    };
+    let pay_tokens = |vault: &AccountInfo, destination: &AccountInfo| {
+        set_tokens(vault, 9_000);
+        set_tokens(destination, 1_500);
+    };
+    assert!(guarded_token_claim(|_, vault, destination| pay_tokens(vault, destination)));
+
+    // The participant PDA's lamports are watched for token claims too
+    assert!(!guarded_token_claim(|participant, vault, destination| {
+        pay_tokens(vault, destination);
+        **participant.try_borrow_mut_lamports().unwrap() -= 1_000;
+    }));
+    // Lamports leaving the vault do not pay a token claim
+    assert!(!guarded_token_claim(|_, vault, destination| {
+        **vault.try_borrow_mut_lamports().unwrap() -= 100;
+        **destination.try_borrow_mut_lamports().unwrap() += 100;
+    }));
+    // More tokens left the vault than the destination received
+    assert!(!guarded_token_claim(|_, vault, destination| {
+        set_tokens(vault, 8_000);
+        set_tokens(destination, 1_500);
+    }));
+}
+
+#[tokio::test]
+async fn test_join_and_claim_pays_only_from_vault() {
+    let (mut context, owner, referrer, referee) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) =
+        create_sol_referral_program(&mut context, &owner, REFERRAL_REWARD, Some(end_time)).await;
+    update_program_settings(
+        &mut context,
+        &owner,
+        referral_program,
+        ProgramSettings {
+            fixed_reward_amount: REFERRAL_REWARD,
+            locked_period: MIN_LOCKED_PERIOD,
+            program_end_time: Some(end_time),
+            base_reward: REFERRAL_REWARD,
+            max_reward_cap: 10 * REFERRAL_REWARD,
+            max_depth: 0,
+            milestones: Default::default(),
+            invite_only: false,
+            revenue_share_percent: 0,
+            referee_reward_amount: REFEREE_REWARD,
+            referee_rewards_locked: false,
+            reserve_bps: 0,
+            max_referrals_per_window: 0,
+            referral_window_seconds: 0,
+            rate_limit_strict: false,
+            required_collection: None,
+            collection_gates_credits: false,
+            transfers_enabled: false,
+            dispute_window_seconds: 0,
+            rent_payer_mode: 0,
+            min_joiner_balance: 0,
+            min_account_age_seconds: 0,
+            alert_thresholds: [0; 3],
+            referrer_requirement: None,
+            referee_requirement: None,
+            direct_claims_only: false,
+            trailing_commission_bps: 0,
+            link_signer: None,
+            dormancy_period_seconds: 0,
+            region_attestor: None,
+            embargoed_regions: [0; 8],
+            region_checked_on_claim: false,
+            bridge_source_program: None,
+            bridge_bps: 0,
+            cleanup_bounty_bps: 0,
+            event_mode: 0,
+            attribution_grace_seconds: 0,
+            tier1_reward: 0,
+            tier2_reward: 0,
+            auto_downgrade: false,
+            min_remaining_credits: 0,
+            seed_reclaim_seconds: 0,
+            early_redemption_fee: 0,
+        },
+    )
+    .await;
+    // Exactly what the referral commits, which the owner's two funded SOL cover along with the program's rent
+    deposit_sol(&mut context, &owner, referral_program, vault, REFERRAL_REWARD + REFEREE_REWARD).await;
+
+    // The in-process bank cannot follow lamports a program moves itself before a CPI, so the referrer has no boost
+    // escrow here; the join still moves lamports into the new accounts it pays rent for
+    let referrer_participant = join_referral_program(&mut context, &referrer, referral_program).await;
+
+    // The guard only covers the claim step, so the join's own transfers do not trip it
+    let vault_balance_before = get_balance(&mut context, vault).await;
+    let referee_balance_before = get_balance(&mut context, referee.pubkey()).await;
+    let referee_participant =
+        join_and_claim(&mut context, &referee, referral_program, vault, referrer_participant).await;
+    let rent = get_balance(&mut context, referee_participant).await
+        + get_balance(&mut context, get_referee_receipt_pda(referral_program, referee.pubkey(), solrefer::ID)).await;
+    assert_eq!(get_balance(&mut context, vault).await, vault_balance_before - REFEREE_REWARD);
+    assert_eq!(get_balance(&mut context, referee.pubkey()).await, referee_balance_before + REFEREE_REWARD - rent);
+    let participant: Participant = get_account(&mut context, referee_participant).await;
+    assert_eq!((participant.pending_rewards, participant.total_rewards), (0, REFEREE_REWARD));
+}
+
+/// Joins `user` through the referrer, claims the sign-up bonus in the same instruction and returns the new
+/// participant PDA
+async fn join_and_claim(
+    context: &mut ProgramTestContext,
+    user: &Keypair,
+    referral_program: Pubkey,
+    vault: Pubkey,
+    referrer: Pubkey,
+) -> Pubkey {
+    let participant = get_participant_pda(referral_program, user.pubkey(), solrefer::ID);
+    let ix = program_instruction(
+        accounts::JoinAndClaimThroughReferral {
+            join: accounts::JoinThroughReferral {
+                referral_program,
+                eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+                participant,
+                referrer,
+                rotated_referrer: None,
+                split_recipient: None,
+                boost_escrow: None,
+                match_offer: None,
+                referrer_wallet: None,
+                referee_receipt: get_referee_receipt_pda(referral_program, user.pubkey(), solrefer::ID),
+                sponsor_vault: None,
+                invite: None,
+                referrer_collection_metadata: None,
+                referrer_collection_nft: None,
+                age_reference: None,
+                region_attestation: None,
+                referrer_token_account: None,
+                referee_token_account: None,
+                referrer_upline: None,
+                user: user.pubkey(),
+                event_queue: None,
+                instructions_sysvar: None,
+                system_program: system_program::ID,
+            },
+            vault,
+            instructions_sysvar: None,
+        },
+        instruction::JoinAndClaimThroughReferral { source_tag: None, accepted_terms_hash: [0u8; 32], link_proof: None },
+    );
+    process(context, &[ix], &[user]).await.expect("Failed to join and claim");
+    participant
+}
+
+#[tokio::test]
+async fn test_claim_waits_out_locked_period() {
+    let (mut context, owner, referrer, referee) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) =
+        create_sol_referral_program(&mut context, &owner, REFERRAL_REWARD, Some(end_time)).await;
+    update_program_settings(
+        &mut context,
+        &owner,
+        referral_program,
+        ProgramSettings {
+            fixed_reward_amount: REFERRAL_REWARD,
+            locked_period: MIN_LOCKED_PERIOD,
+            program_end_time: Some(end_time),
+            base_reward: REFERRAL_REWARD,
+            max_reward_cap: 10 * REFERRAL_REWARD,
+            max_depth: 0,
+            milestones: Default::default(),
+            invite_only: false,
+            revenue_share_percent: 0,
+            referee_reward_amount: 0,
+            referee_rewards_locked: false,
+            reserve_bps: 0,
+            max_referrals_per_window: 0,
+            referral_window_seconds: 0,
+            rate_limit_strict: false,
+            required_collection: None,
+            collection_gates_credits: false,
+            transfers_enabled: false,
+            dispute_window_seconds: 0,
+            rent_payer_mode: 0,
+            min_joiner_balance: 0,
+            min_account_age_seconds: 0,
+            alert_thresholds: [0; 3],
+            referrer_requirement: None,
+            referee_requirement: None,
+            direct_claims_only: false,
+            trailing_commission_bps: 0,
+            link_signer: None,
+            dormancy_period_seconds: 0,
+            region_attestor: None,
+            embargoed_regions: [0; 8],
+            region_checked_on_claim: false,
+            bridge_source_program: None,
+            bridge_bps: 0,
+            cleanup_bounty_bps: 0,
+            event_mode: 0,
+            attribution_grace_seconds: 0,
+            tier1_reward: 0,
+            tier2_reward: 0,
+            auto_downgrade: false,
+            min_remaining_credits: 0,
+            seed_reclaim_seconds: 0,
+            early_redemption_fee: 0,
+        },
+    )
+    .await;
+    deposit_sol(&mut context, &owner, referral_program, vault, REFERRAL_REWARD).await;
+
+    let referrer_participant = join_referral_program(&mut context, &referrer, referral_program).await;
+    join_through_referral(&mut context, &referee, referral_program, referrer_participant).await;
+
+    // Rewards stay locked until exactly `locked_period` after the referrer joined
+    let result = claim_rewards(&mut context, &referrer, referral_program, referrer_participant, vault).await;
+    assert_referral_error(result, ReferralError::RewardsLocked);
+    advance_clock(&mut context, MIN_LOCKED_PERIOD - 1).await;
+    let result = claim_rewards(&mut context, &referrer, referral_program, referrer_participant, vault).await;
+    assert_referral_error(result, ReferralError::RewardsLocked);
+
+    advance_clock(&mut context, 1).await;
+    claim_rewards(&mut context, &referrer, referral_program, referrer_participant, vault).await.unwrap();
+    let participant: Participant = get_account(&mut context, referrer_participant).await;
+    assert_eq!(participant.total_rewards, REFERRAL_REWARD);
+}
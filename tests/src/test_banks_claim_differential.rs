@@ -0,0 +1,308 @@
+//! Differential tests of the SOL and token claim paths.
+//!
+//! Every scenario is scripted against a SOL program and a token program paying in a 9-decimal mint, so raw
+//! units are comparable, and the two runs must end every claim with the same result, payout and counters.
+
+use anchor_client::solana_sdk::{
+    instruction::InstructionError, pubkey::Pubkey, signature::Keypair, signer::Signer, transaction::TransactionError,
+};
+use anchor_spl::token::{spl_token, TokenAccount};
+use solana_program_test::{BanksClientError, ProgramTestContext};
+use solrefer::{
+    accounts,
+    constants::{MAX_MILESTONES, MIN_LOCKED_PERIOD},
+    error::ReferralError,
+    instruction,
+    instructions::ProgramSettings,
+    state::{Milestone, Participant, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, claim_rewards, create_funded_token_account, create_funded_user, create_mint,
+        create_sol_referral_program, create_token_account, create_token_referral_program, deposit_sol, get_account,
+        get_balance, get_clock_time, join_referral_program, process, program_instruction, setup,
+        try_join_through_referral, update_program_settings,
+    },
+    test_util::get_eligibility_criteria_pda,
+};
+
+const REWARD: u64 = 10_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+/// Scenarios whose SOL and token outcomes are known to differ, each with the reason next to it. Claims carry no
+/// fees and always pay the full pending amount in this program version, so nothing diverges today.
+const WHITELISTED_DIVERGENCES: &[&str] = &[];
+
+/// A scripted run: the program's settings and funding, then rounds of referrals each followed by a wait and a
+/// claim by the referrer.
+#[derive(Clone, Copy)]
+struct Scenario {
+    name: &'static str,
+    reward: u64,
+    deposit: u64,
+    max_reward_cap: u64,
+    reserve_bps: u64,
+    referee_reward: u64,
+    milestone: Milestone,
+    /// Referrals credited before each claim
+    rounds: &'static [u64],
+    /// Seconds waited before each claim
+    wait: i64,
+}
+
+const BASE: Scenario = Scenario {
+    name: "",
+    reward: REWARD,
+    deposit: 10 * REWARD,
+    max_reward_cap: 100 * REWARD,
+    reserve_bps: 0,
+    referee_reward: 0,
+    milestone: Milestone { threshold: 0, bonus: 0 },
+    rounds: &[1],
+    wait: MIN_LOCKED_PERIOD,
+};
+
+const SCENARIOS: [Scenario; 14] = [
+    Scenario { name: "single referral", ..BASE },
+    Scenario { name: "still locked", wait: 0, ..BASE },
+    Scenario { name: "nothing pending", rounds: &[0], ..BASE },
+    Scenario { name: "several referrals", rounds: &[3], ..BASE },
+    Scenario { name: "capped", max_reward_cap: 2 * REWARD + REWARD / 2, rounds: &[3], ..BASE },
+    Scenario { name: "reserve", reserve_bps: 1_000, rounds: &[2], ..BASE },
+    Scenario { name: "reserve leaves too little", reserve_bps: 2_000, deposit: REWARD, ..BASE },
+    Scenario { name: "exactly funded", deposit: REWARD, ..BASE },
+    Scenario { name: "runs out of funds", deposit: REWARD, rounds: &[2], ..BASE },
+    Scenario { name: "referee bonus", referee_reward: REWARD / 2, rounds: &[2], ..BASE },
+    Scenario { name: "milestone", milestone: Milestone { threshold: 2, bonus: REWARD / 3 }, rounds: &[2], ..BASE },
+    Scenario { name: "two claims", rounds: &[1, 2], ..BASE },
+    Scenario { name: "nothing left to claim", rounds: &[1, 0], ..BASE },
+    Scenario { name: "odd amounts", reward: 3_333_333, reserve_bps: 333, rounds: &[2, 1], ..BASE },
+];
+
+/// Where a claim moves an instance's rewards from and to
+enum Payout {
+    Sol { vault: Pubkey },
+    Token { token_vault: Pubkey, destination: Pubkey },
+}
+
+/// One program of a scenario and its referrer
+struct Instance {
+    referral_program: Pubkey,
+    referrer: Keypair,
+    referrer_participant: Pubkey,
+    payout: Payout,
+}
+
+/// The result of one claim and the state it left behind
+#[derive(Debug, PartialEq, Eq)]
+struct ClaimOutcome {
+    error: Option<u32>,
+    paid: u64,
+    pending_rewards: u64,
+    total_rewards: u64,
+    total_available: u64,
+    total_committed: u64,
+    total_rewards_distributed: u64,
+    reserved_balance: u64,
+    accepting_referrals: bool,
+}
+
+#[tokio::test]
+async fn test_sol_and_token_claims_agree() {
+    let (mut context, _, _, _) = setup().await;
+    for scenario in SCENARIOS {
+        let sol = run(&mut context, scenario, false).await;
+        let token = run(&mut context, scenario, true).await;
+        if WHITELISTED_DIVERGENCES.contains(&scenario.name) {
+            continue;
+        }
+        assert_eq!(sol, token, "SOL and token claims diverged in scenario {:?}", scenario.name);
+    }
+}
+
+#[tokio::test]
+async fn test_scenarios_cover_every_outcome() {
+    // The table is only meaningful if it reaches the failure paths as well as the payouts
+    let (mut context, _, _, _) = setup().await;
+    let mut errors = Vec::new();
+    for scenario in SCENARIOS {
+        for outcome in run(&mut context, scenario, false).await {
+            errors.push(outcome.error);
+            assert_eq!(outcome.error.is_none(), outcome.paid > 0, "scenario {:?}", scenario.name);
+        }
+    }
+    for error in [None, Some(ReferralError::RewardsLocked), Some(ReferralError::NoRewardsAvailable)] {
+        assert!(errors.contains(&error.map(u32::from)), "no scenario ends with {error:?}");
+    }
+}
+
+/// Runs `scenario` against a new SOL or token program and returns the outcome of each claim
+async fn run(context: &mut ProgramTestContext, scenario: Scenario, token: bool) -> Vec<ClaimOutcome> {
+    let instance = start(context, scenario, token).await;
+    let mut outcomes = Vec::new();
+    for &referrals in scenario.rounds {
+        for _ in 0..referrals {
+            // Referrals the program can no longer fund are rejected and credit nothing
+            let referee = create_funded_user(context).await;
+            let (result, _) =
+                try_join_through_referral(context, &referee, instance.referral_program, instance.referrer_participant)
+                    .await;
+            let error = error_code(result);
+            assert!(error.is_none() || error == Some(ReferralError::ProgramUnderfunded.into()), "{error:?}");
+        }
+        if scenario.wait > 0 {
+            advance_clock(context, scenario.wait).await;
+        }
+        outcomes.push(claim(context, &instance).await);
+    }
+    outcomes
+}
+
+/// Creates and funds a program configured as `scenario` asks, and joins its referrer
+async fn start(context: &mut ProgramTestContext, scenario: Scenario, token: bool) -> Instance {
+    let owner = create_funded_user(context).await;
+    let end_time = get_clock_time(context).await + ONE_YEAR;
+    let mut milestones = [Milestone::default(); MAX_MILESTONES];
+    milestones[0] = scenario.milestone;
+    let settings = ProgramSettings {
+        fixed_reward_amount: scenario.reward,
+        locked_period: MIN_LOCKED_PERIOD,
+        program_end_time: Some(end_time),
+        base_reward: scenario.reward,
+        max_reward_cap: scenario.max_reward_cap,
+        max_depth: 0,
+        milestones,
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: scenario.referee_reward,
+        referee_rewards_locked: false,
+        reserve_bps: scenario.reserve_bps,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
+    };
+
+    let referrer = create_funded_user(context).await;
+    let (referral_program, payout) = if token {
+        let mint = create_mint(context, &owner).await;
+        let (referral_program, token_vault) =
+            create_token_referral_program(context, &owner, mint, scenario.reward, Some(end_time)).await;
+        update_program_settings(context, &owner, referral_program, settings).await;
+        let depositor_token_account = create_funded_token_account(context, &owner, mint, scenario.deposit).await;
+        let deposit_ix = program_instruction(
+            accounts::DepositToken {
+                referral_program,
+                token_vault,
+                token_mint: mint,
+                depositor_token_account,
+                authority: owner.pubkey(),
+                event_queue: None,
+                token_program: spl_token::id(),
+            },
+            instruction::DepositToken { amount: scenario.deposit },
+        );
+        process(context, &[deposit_ix], &[&owner]).await.unwrap();
+        let destination = create_token_account(context, referrer.pubkey(), mint).await;
+        (referral_program, Payout::Token { token_vault, destination })
+    } else {
+        let (referral_program, vault) =
+            create_sol_referral_program(context, &owner, scenario.reward, Some(end_time)).await;
+        update_program_settings(context, &owner, referral_program, settings).await;
+        deposit_sol(context, &owner, referral_program, vault, scenario.deposit).await;
+        (referral_program, Payout::Sol { vault })
+    };
+
+    let referrer_participant = join_referral_program(context, &referrer, referral_program).await;
+    Instance { referral_program, referrer, referrer_participant, payout }
+}
+
+/// Claims the referrer's rewards through the instance's claim path and records the outcome
+async fn claim(context: &mut ProgramTestContext, instance: &Instance) -> ClaimOutcome {
+    let before = received(context, instance).await;
+    let result = match instance.payout {
+        Payout::Sol { vault } => {
+            claim_rewards(context, &instance.referrer, instance.referral_program, instance.referrer_participant, vault)
+                .await
+        }
+        Payout::Token { token_vault, destination } => {
+            let ix = program_instruction(
+                accounts::ClaimTokenRewards {
+                    referral_program: instance.referral_program,
+                    eligibility_criteria: get_eligibility_criteria_pda(instance.referral_program, solrefer::ID),
+                    participant: instance.referrer_participant,
+                    claim_splitter: None,
+                    token_vault,
+                    user_token_account: destination,
+                    user: instance.referrer.pubkey(),
+                    event_queue: None,
+                    instructions_sysvar: None,
+                    region_attestation: None,
+                    token_program: spl_token::id(),
+                },
+                instruction::ClaimTokenRewards {},
+            );
+            process(context, &[ix], &[&instance.referrer]).await
+        }
+    };
+    let paid = received(context, instance).await - before;
+
+    let participant: Participant = get_account(context, instance.referrer_participant).await;
+    let program: ReferralProgram = get_account(context, instance.referral_program).await;
+    ClaimOutcome {
+        error: error_code(result),
+        paid,
+        pending_rewards: participant.pending_rewards,
+        total_rewards: participant.total_rewards,
+        total_available: program.total_available,
+        total_committed: program.total_committed,
+        total_rewards_distributed: program.total_rewards_distributed,
+        reserved_balance: program.reserved_balance,
+        accepting_referrals: program.accepting_referrals,
+    }
+}
+
+/// What the referrer's claim destination holds, in lamports or token units
+async fn received(context: &mut ProgramTestContext, instance: &Instance) -> u64 {
+    match instance.payout {
+        Payout::Sol { .. } => get_balance(context, instance.referrer.pubkey()).await,
+        Payout::Token { destination, .. } => get_account::<TokenAccount>(context, destination).await.amount,
+    }
+}
+
+/// The program error a claim or join failed with, if any
+fn error_code(result: Result<(), BanksClientError>) -> Option<u32> {
+    let err = result.err()?.unwrap();
+    match err {
+        TransactionError::InstructionError(0, InstructionError::Custom(code)) => Some(code),
+        other => panic!("Transaction failed outside the program: {other:?}"),
+    }
+}
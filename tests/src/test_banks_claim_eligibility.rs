@@ -0,0 +1,221 @@
+//! What keeps a participant from claiming, reported as a bitmask.
+//!
+//! Each gate of `claim_eligibility` is checked on its own and in combination, and `check_claim` returns the
+//! same report for Alice while the reward she earned for referring Bob is still locked.
+
+use anchor_client::solana_sdk::{pubkey::Pubkey, signer::Signer};
+use solrefer::{
+    accounts,
+    error::ReferralError,
+    instruction,
+    instructions::{claim_eligibility, ClaimAccounts, ClaimEligibility, ProgramSettings},
+    state::{EligibilityCriteria, Participant, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        create_sol_referral_program, deposit_sol, get_account, get_clock_time, join_referral_program,
+        join_through_referral, program_instruction, read_program_settings, setup, simulate_return,
+        update_program_settings,
+    },
+    test_util::get_eligibility_criteria_pda,
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+const NOW: i64 = 1_700_000_000;
+const LOCKED_PERIOD: i64 = 86400;
+
+/// Returns a program and participant that pass every claim gate at `NOW`
+fn claimable_state() -> (ReferralProgram, Participant) {
+    let program = ReferralProgram { is_active: true, locked_period: LOCKED_PERIOD, ..Default::default() };
+    let participant = Participant { join_time: NOW - LOCKED_PERIOD, pending_rewards: 1, ..Default::default() };
+    (program, participant)
+}
+
+#[test]
+fn test_claim_eligibility_all_gates_pass() {
+    let (program, participant) = claimable_state();
+
+    let eligibility = claim_eligibility(&program, &participant, NOW, ClaimAccounts::default());
+    assert!(eligibility.is_claimable());
+    assert_eq!(eligibility.claimable_at, NOW);
+    assert!(eligibility.require_claimable().is_ok());
+}
+
+#[test]
+fn test_claim_eligibility_program_inactive() {
+    let (mut program, participant) = claimable_state();
+
+    program.is_active = false;
+    let eligibility = claim_eligibility(&program, &participant, NOW, ClaimAccounts::default());
+    assert_eq!(eligibility.blocked, ClaimEligibility::PROGRAM_INACTIVE);
+    assert_eq!(eligibility.claimable_at, NOW);
+    assert_eq!(eligibility.require_claimable().unwrap_err(), ReferralError::ProgramInactive.into());
+}
+
+#[test]
+fn test_claim_eligibility_no_rewards() {
+    let (program, mut participant) = claimable_state();
+    participant.pending_rewards = 0;
+
+    let eligibility = claim_eligibility(&program, &participant, NOW, ClaimAccounts::default());
+    assert_eq!(eligibility.blocked, ClaimEligibility::NO_REWARDS);
+    assert_eq!(eligibility.claimable_at, NOW);
+    assert_eq!(eligibility.require_claimable().unwrap_err(), ReferralError::NoRewardsAvailable.into());
+}
+
+#[test]
+fn test_claim_eligibility_rewards_locked() {
+    let (program, mut participant) = claimable_state();
+    participant.join_time = NOW - 10;
+
+    let eligibility = claim_eligibility(&program, &participant, NOW, ClaimAccounts::default());
+    assert_eq!(eligibility.blocked, ClaimEligibility::REWARDS_LOCKED);
+    assert_eq!(eligibility.claimable_at, NOW - 10 + LOCKED_PERIOD);
+    assert_eq!(eligibility.require_claimable().unwrap_err(), ReferralError::RewardsLocked.into());
+
+    // The lock lifts exactly at join_time + locked_period
+    let eligibility = claim_eligibility(&program, &participant, NOW - 10 + LOCKED_PERIOD, ClaimAccounts::default());
+    assert!(eligibility.is_claimable());
+}
+
+#[test]
+fn test_claim_eligibility_lock_saturates() {
+    let (mut program, mut participant) = claimable_state();
+    program.locked_period = i64::MAX;
+    participant.join_time = NOW;
+
+    let eligibility = claim_eligibility(&program, &participant, NOW, ClaimAccounts::default());
+    assert_eq!(eligibility.blocked, ClaimEligibility::REWARDS_LOCKED);
+    assert_eq!(eligibility.claimable_at, i64::MAX);
+}
+
+#[test]
+fn test_claim_eligibility_combined_gates() {
+    let (mut program, mut participant) = claimable_state();
+    program.is_active = false;
+    participant.pending_rewards = 0;
+    participant.join_time = NOW;
+
+    let eligibility = claim_eligibility(&program, &participant, NOW, ClaimAccounts::default());
+    assert_eq!(
+        eligibility.blocked,
+        ClaimEligibility::PROGRAM_INACTIVE | ClaimEligibility::NO_REWARDS | ClaimEligibility::REWARDS_LOCKED
+    );
+    assert_eq!(eligibility.claimable_at, NOW + LOCKED_PERIOD);
+    // The lowest set flag determines the error
+    assert_eq!(eligibility.require_claimable().unwrap_err(), ReferralError::ProgramInactive.into());
+
+    program.is_active = true;
+    let eligibility = claim_eligibility(&program, &participant, NOW, ClaimAccounts::default());
+    assert_eq!(eligibility.blocked, ClaimEligibility::NO_REWARDS | ClaimEligibility::REWARDS_LOCKED);
+    assert_eq!(eligibility.require_claimable().unwrap_err(), ReferralError::NoRewardsAvailable.into());
+}
+
+#[test]
+fn test_claim_eligibility_rotated_participant() {
+    let (program, mut participant) = claimable_state();
+    participant.rotated_to = Some(Pubkey::new_unique());
+    participant.pending_rewards = 0;
+
+    let eligibility = claim_eligibility(&program, &participant, NOW, ClaimAccounts::default());
+    assert_eq!(eligibility.blocked, ClaimEligibility::NO_REWARDS | ClaimEligibility::PARTICIPANT_ROTATED);
+    // Rotation takes precedence over lower flags since it never lifts
+    assert_eq!(eligibility.require_claimable().unwrap_err(), ReferralError::ParticipantRotated.into());
+}
+
+#[test]
+fn test_claim_eligibility_left_participant() {
+    let (program, mut participant) = claimable_state();
+    participant.left = true;
+    participant.pending_rewards = 0;
+
+    let eligibility = claim_eligibility(&program, &participant, NOW, ClaimAccounts::default());
+    assert_eq!(eligibility.blocked, ClaimEligibility::NO_REWARDS | ClaimEligibility::PARTICIPANT_LEFT);
+    assert_eq!(eligibility.require_claimable().unwrap_err(), ReferralError::ParticipantLeft.into());
+}
+
+#[test]
+fn test_claim_eligibility_seeded_participant() {
+    let (program, mut participant) = claimable_state();
+    participant.seeded = true;
+
+    let eligibility = claim_eligibility(&program, &participant, NOW, ClaimAccounts::default());
+    assert_eq!(eligibility.blocked, ClaimEligibility::NOT_ACTIVATED);
+    assert_eq!(eligibility.require_claimable().unwrap_err(), ReferralError::ParticipantNotActivated.into());
+
+    participant.activated = true;
+    assert!(claim_eligibility(&program, &participant, NOW, ClaimAccounts::default()).is_claimable());
+}
+
+#[test]
+fn test_claim_eligibility_region_checked_on_claim() {
+    let (program, participant) = claimable_state();
+    let criteria = EligibilityCriteria {
+        region_attestor: Some(Pubkey::new_unique()),
+        region_checked_on_claim: true,
+        ..Default::default()
+    };
+    let accounts = ClaimAccounts { eligibility_criteria: Some(&criteria), ..Default::default() };
+
+    let eligibility = claim_eligibility(&program, &participant, NOW, accounts);
+    assert_eq!(eligibility.blocked, ClaimEligibility::REGION_ATTESTATION_REQUIRED);
+    assert_eq!(eligibility.require_claimable().unwrap_err(), ReferralError::RegionAttestationRequired.into());
+
+    // Region gates report ahead of the participant's own
+    let rotated = Participant { rotated_to: Some(Pubkey::new_unique()), ..participant.clone() };
+    let eligibility = claim_eligibility(&program, &rotated, NOW, accounts);
+    assert_eq!(eligibility.require_claimable().unwrap_err(), ReferralError::RegionAttestationRequired.into());
+
+    // Without claim-time checks the region is left to the join
+    let criteria = EligibilityCriteria { region_checked_on_claim: false, ..criteria };
+    let accounts = ClaimAccounts { eligibility_criteria: Some(&criteria), ..Default::default() };
+    assert!(claim_eligibility(&program, &participant, NOW, accounts).is_claimable());
+}
+
+#[test]
+fn test_claim_eligibility_direct_claims_only() {
+    let (program, participant) = claimable_state();
+    let program = ReferralProgram { direct_claims_only: true, ..program };
+
+    // Without the instructions sysvar the claim cannot be shown to be direct
+    let eligibility = claim_eligibility(&program, &participant, NOW, ClaimAccounts::default());
+    assert_eq!(eligibility.blocked, ClaimEligibility::NOT_DIRECT);
+    assert_eq!(eligibility.require_claimable().unwrap_err(), ReferralError::CpiClaimNotAllowed.into());
+}
+
+#[tokio::test]
+async fn test_check_claim_return_data_locked() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let settings = ProgramSettings {
+        locked_period: LOCKED_PERIOD,
+        base_reward: REWARD,
+        max_reward_cap: 100 * REWARD,
+        ..read_program_settings(&mut context, referral_program).await
+    };
+    update_program_settings(&mut context, &owner, referral_program, settings).await;
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    join_through_referral(&mut context, &bob, referral_program, alice_participant).await;
+
+    let ix = program_instruction(
+        accounts::CheckClaim {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant: alice_participant,
+            region_attestation: None,
+            instructions_sysvar: None,
+        },
+        instruction::CheckClaim {},
+    );
+    let eligibility: ClaimEligibility = simulate_return(&mut context, ix).await;
+
+    let participant: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!(eligibility.blocked, ClaimEligibility::REWARDS_LOCKED);
+    assert_eq!(eligibility.claimable_at, participant.join_time + LOCKED_PERIOD);
+    assert_eq!(participant.owner, alice.pubkey());
+}
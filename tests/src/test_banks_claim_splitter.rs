@@ -0,0 +1,227 @@
+//! Claims split across several destinations by a claim splitter.
+//!
+//! Alice refers Bob and splits her claims 60/30/10 among three contributors, who each receive their share of her
+//! reward, the rounding remainder going to the first. A claim that does not pass the splitter's destinations as
+//! configured is rejected, and removing the splitter pays Alice directly again. Token claims split the same way
+//! across the destinations' token accounts.
+
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+        signature::Keypair,
+        signer::Signer,
+    },
+};
+use anchor_spl::token::{spl_token, TokenAccount};
+use solrefer::{
+    accounts,
+    constants::MIN_LOCKED_PERIOD,
+    error::ReferralError,
+    instruction,
+    state::{ClaimSplitter, Participant, SplitterEntry},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, claim_rewards, create_funded_token_account, create_funded_user,
+        create_mint, create_sol_referral_program, create_token_account, create_token_referral_program, deposit_sol,
+        get_account, get_balance, get_clock_time, join_referral_program, join_through_referral, process,
+        program_instruction, setup,
+    },
+    test_util::{get_eligibility_criteria_pda, get_participant_pda},
+};
+
+/// A reward that does not divide evenly by the shares
+const REWARD: u64 = 1_000_003;
+const ONE_YEAR: i64 = 365 * 86400;
+
+fn get_splitter_pda(participant: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"splitter", participant.as_ref()], &solrefer::ID).0
+}
+
+fn entries(shares: &[(Pubkey, u16)]) -> Vec<SplitterEntry> {
+    shares.iter().map(|&(destination, bps)| SplitterEntry { destination, bps }).collect()
+}
+
+#[test]
+fn test_splitter_shares_give_remainder_to_first_entry() {
+    let mut splitter = ClaimSplitter { entry_count: 3, ..Default::default() };
+    for (entry, bps) in splitter.entries.iter_mut().zip([6_000, 3_000, 1_000]) {
+        entry.bps = bps;
+    }
+    assert_eq!(splitter.shares(REWARD), vec![600_003, 300_000, 100_000]);
+    assert_eq!(splitter.shares(0), vec![0, 0, 0]);
+    assert_eq!(splitter.shares(9), vec![7, 2, 0]);
+}
+
+#[tokio::test]
+async fn test_sol_claim_is_split_across_destinations() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    join_through_referral(&mut context, &bob, referral_program, alice_participant).await;
+    advance_clock(&mut context, MIN_LOCKED_PERIOD).await;
+
+    // The shares must name distinct destinations and sum to exactly 10_000 basis points
+    let mut contributors = Vec::new();
+    for _ in 0..3 {
+        contributors.push(create_funded_user(&mut context).await.pubkey());
+    }
+    let invalid = [
+        entries(&[(contributors[0], 6_000), (contributors[1], 3_000), (contributors[2], 999)]),
+        entries(&[(contributors[0], 6_000), (contributors[0], 4_000)]),
+        entries(&[(contributors[0], 10_000), (contributors[1], 0)]),
+        entries(&[(contributors[0], 2_000); 6]),
+        Vec::new(),
+    ];
+    for entries in invalid {
+        let result = process(&mut context, &[configure_ix(&alice, referral_program, entries)], &[&alice]).await;
+        assert_referral_error(result, ReferralError::InvalidClaimSplitter);
+    }
+    let split = entries(&[(contributors[0], 6_000), (contributors[1], 3_000), (contributors[2], 1_000)]);
+    process(&mut context, &[configure_ix(&alice, referral_program, split)], &[&alice]).await.unwrap();
+
+    // A claim must pass the splitter and its destinations in the configured order
+    let result = claim_rewards(&mut context, &alice, referral_program, alice_participant, vault).await;
+    assert_referral_error(result, ReferralError::ClaimSplitterMismatch);
+    let reordered = [contributors[1], contributors[0], contributors[2]];
+    let result = process(&mut context, &[claim_ix(&alice, referral_program, vault, &reordered)], &[&alice]).await;
+    assert_referral_error(result, ReferralError::ClaimSplitterMismatch);
+    let result =
+        process(&mut context, &[claim_ix(&alice, referral_program, vault, &contributors[..2])], &[&alice]).await;
+    assert_referral_error(result, ReferralError::ClaimSplitterMismatch);
+
+    // Each contributor receives its share and the first also the rounding remainder
+    let mut before = Vec::new();
+    for contributor in &contributors {
+        before.push(get_balance(&mut context, *contributor).await);
+    }
+    let alice_before = get_balance(&mut context, alice.pubkey()).await;
+    process(&mut context, &[claim_ix(&alice, referral_program, vault, &contributors)], &[&alice]).await.unwrap();
+    for ((contributor, before), share) in contributors.iter().zip(before).zip([600_003, 300_000, 100_000]) {
+        assert_eq!(get_balance(&mut context, *contributor).await, before + share);
+    }
+    assert_eq!(get_balance(&mut context, alice.pubkey()).await, alice_before);
+    let participant: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!((participant.pending_rewards, participant.total_rewards), (0, REWARD));
+
+    // Without the splitter Alice is paid directly again
+    let remove_ix = program_instruction(
+        accounts::RemoveSplitter {
+            referral_program,
+            participant: alice_participant,
+            claim_splitter: get_splitter_pda(alice_participant),
+            user: alice.pubkey(),
+        },
+        instruction::RemoveSplitter {},
+    );
+    process(&mut context, &[remove_ix], &[&alice]).await.unwrap();
+    let carol = create_funded_user(&mut context).await;
+    join_through_referral(&mut context, &carol, referral_program, alice_participant).await;
+    let alice_before = get_balance(&mut context, alice.pubkey()).await;
+    claim_rewards(&mut context, &alice, referral_program, alice_participant, vault).await.unwrap();
+    assert_eq!(get_balance(&mut context, alice.pubkey()).await, alice_before + REWARD);
+}
+
+#[tokio::test]
+async fn test_token_claim_is_split_across_token_accounts() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let mint = create_mint(&mut context, &owner).await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, token_vault) =
+        create_token_referral_program(&mut context, &owner, mint, REWARD, Some(end_time)).await;
+    let depositor_token_account = create_funded_token_account(&mut context, &owner, mint, 10 * REWARD).await;
+    let deposit_ix = program_instruction(
+        accounts::DepositToken {
+            referral_program,
+            token_vault,
+            token_mint: mint,
+            depositor_token_account,
+            authority: owner.pubkey(),
+            event_queue: None,
+            token_program: spl_token::id(),
+        },
+        instruction::DepositToken { amount: 10 * REWARD },
+    );
+    process(&mut context, &[deposit_ix], &[&owner]).await.unwrap();
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    join_through_referral(&mut context, &bob, referral_program, alice_participant).await;
+    advance_clock(&mut context, MIN_LOCKED_PERIOD).await;
+
+    let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let split = entries(&[(first, 5_000), (second, 5_000)]);
+    process(&mut context, &[configure_ix(&alice, referral_program, split)], &[&alice]).await.unwrap();
+    let first_account = create_token_account(&mut context, first, mint).await;
+    let second_account = create_token_account(&mut context, second, mint).await;
+    let alice_account = create_token_account(&mut context, alice.pubkey(), mint).await;
+
+    // Token accounts must belong to the destinations in order
+    let claim = |destinations: &[Pubkey]| {
+        let mut ix = program_instruction(
+            accounts::ClaimTokenRewards {
+                referral_program,
+                eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+                participant: alice_participant,
+                claim_splitter: Some(get_splitter_pda(alice_participant)),
+                token_vault,
+                user_token_account: alice_account,
+                user: alice.pubkey(),
+                event_queue: None,
+                instructions_sysvar: None,
+                region_attestation: None,
+                token_program: spl_token::id(),
+            },
+            instruction::ClaimTokenRewards {},
+        );
+        ix.accounts.extend(destinations.iter().map(|destination| AccountMeta::new(*destination, false)));
+        ix
+    };
+    let result = process(&mut context, &[claim(&[second_account, first_account])], &[&alice]).await;
+    assert_referral_error(result, ReferralError::ClaimSplitterMismatch);
+    process(&mut context, &[claim(&[first_account, second_account])], &[&alice]).await.unwrap();
+
+    let first_tokens: TokenAccount = get_account(&mut context, first_account).await;
+    let second_tokens: TokenAccount = get_account(&mut context, second_account).await;
+    let alice_tokens: TokenAccount = get_account(&mut context, alice_account).await;
+    assert_eq!((first_tokens.amount, second_tokens.amount, alice_tokens.amount), (500_002, 500_001, 0));
+}
+
+fn configure_ix(user: &Keypair, referral_program: Pubkey, entries: Vec<SplitterEntry>) -> Instruction {
+    let participant = get_participant_pda(referral_program, user.pubkey(), solrefer::ID);
+    program_instruction(
+        accounts::ConfigureSplitter {
+            referral_program,
+            participant,
+            claim_splitter: get_splitter_pda(participant),
+            user: user.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::ConfigureSplitter { entries },
+    )
+}
+
+/// Builds a SOL claim of `user` passing its splitter and `destinations` in the remaining accounts
+fn claim_ix(user: &Keypair, referral_program: Pubkey, vault: Pubkey, destinations: &[Pubkey]) -> Instruction {
+    let participant = get_participant_pda(referral_program, user.pubkey(), solrefer::ID);
+    let mut ix = program_instruction(
+        accounts::ClaimRewards {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant,
+            claim_splitter: Some(get_splitter_pda(participant)),
+            vault,
+            user: user.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
+            region_attestation: None,
+            system_program: system_program::ID,
+        },
+        instruction::ClaimRewards {},
+    );
+    ix.accounts.extend(destinations.iter().map(|destination| AccountMeta::new(*destination, false)));
+    ix
+}
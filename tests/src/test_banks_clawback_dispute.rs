@@ -0,0 +1,285 @@
+use anchor_client::solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::{CLAWBACK_RESOLUTION_TIMEOUT, MIN_LOCKED_PERIOD},
+    error::ReferralError,
+    instruction,
+    instructions::ProgramSettings,
+    state::{Participant, RefereeReceipt, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol,
+        get_account, get_clock_time, join_referral_program, join_through_referral, process, program_instruction,
+        rotate_owner, setup, update_program_settings,
+    },
+    test_util::get_referee_receipt_pda,
+};
+
+const REFERRAL_REWARD: u64 = 1_000_000;
+const DISPUTE_WINDOW: i64 = 86400;
+const ONE_YEAR: i64 = 365 * 86400;
+const EVIDENCE: [u8; 32] = [7; 32];
+
+#[test]
+fn test_resolution_deadline_follows_contest() {
+    let receipt = RefereeReceipt { contested_at: 1_000, ..Default::default() };
+    assert_eq!(receipt.resolution_deadline(), 1_000 + CLAWBACK_RESOLUTION_TIMEOUT);
+}
+
+struct Dispute {
+    referral_program: Pubkey,
+    referrer_participant: Pubkey,
+    receipt: Pubkey,
+}
+
+/// Creates a funded program with a dispute window, credits one referral and claws it back
+async fn open_dispute(context: &mut ProgramTestContext, owner: &Keypair, referrer: &Keypair) -> Dispute {
+    let end_time = get_clock_time(context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(context, owner, REFERRAL_REWARD, Some(end_time)).await;
+    update_program_settings(
+        context,
+        owner,
+        referral_program,
+        ProgramSettings {
+            fixed_reward_amount: REFERRAL_REWARD,
+            locked_period: MIN_LOCKED_PERIOD,
+            program_end_time: Some(end_time),
+            base_reward: REFERRAL_REWARD,
+            max_reward_cap: 10 * REFERRAL_REWARD,
+            max_depth: 0,
+            milestones: Default::default(),
+            invite_only: false,
+            revenue_share_percent: 0,
+            referee_reward_amount: 0,
+            referee_rewards_locked: false,
+            reserve_bps: 0,
+            max_referrals_per_window: 0,
+            referral_window_seconds: 0,
+            rate_limit_strict: false,
+            required_collection: None,
+            collection_gates_credits: false,
+            transfers_enabled: false,
+            dispute_window_seconds: DISPUTE_WINDOW,
+            rent_payer_mode: 0,
+            min_joiner_balance: 0,
+            min_account_age_seconds: 0,
+            alert_thresholds: [0; 3],
+            referrer_requirement: None,
+            referee_requirement: None,
+            direct_claims_only: false,
+            trailing_commission_bps: 0,
+            link_signer: None,
+            dormancy_period_seconds: 0,
+            region_attestor: None,
+            embargoed_regions: [0; 8],
+            region_checked_on_claim: false,
+            bridge_source_program: None,
+            bridge_bps: 0,
+            cleanup_bounty_bps: 0,
+            event_mode: 0,
+            attribution_grace_seconds: 0,
+            tier1_reward: 0,
+            tier2_reward: 0,
+            auto_downgrade: false,
+            min_remaining_credits: 0,
+            seed_reclaim_seconds: 0,
+            early_redemption_fee: 0,
+        },
+    )
+    .await;
+    deposit_sol(context, owner, referral_program, vault, 10 * REFERRAL_REWARD).await;
+
+    let referrer_participant = join_referral_program(context, referrer, referral_program).await;
+    let referee = create_funded_user(context).await;
+    join_through_referral(context, &referee, referral_program, referrer_participant).await;
+    let receipt = get_referee_receipt_pda(referral_program, referee.pubkey(), solrefer::ID);
+
+    let clawback_ix = program_instruction(
+        accounts::ClawbackReferral {
+            referral_program,
+            referee_receipt: receipt,
+            referrer: referrer_participant,
+            authority: owner.pubkey(),
+        },
+        instruction::ClawbackReferral { idempotency_key: None },
+    );
+    process(context, &[clawback_ix], &[owner]).await.unwrap();
+
+    // The rewards are taken from the referrer but stay committed while the clawback can be contested
+    let participant: Participant = get_account(context, referrer_participant).await;
+    assert_eq!((participant.pending_rewards, participant.total_referrals), (0, 0));
+    assert_eq!(participant.clawed_back, REFERRAL_REWARD);
+    let program: ReferralProgram = get_account(context, referral_program).await;
+    assert_eq!(program.total_committed, REFERRAL_REWARD);
+    let receipt_account: RefereeReceipt = get_account(context, receipt).await;
+    assert_eq!(receipt_account.clawback_status, RefereeReceipt::CLAWBACK_OPEN);
+    assert_eq!(receipt_account.disputed_amount, REFERRAL_REWARD);
+
+    Dispute { referral_program, referrer_participant, receipt }
+}
+
+impl Dispute {
+    fn contest_ix(&self, user: &Keypair) -> Instruction {
+        program_instruction(
+            accounts::ContestClawback {
+                referral_program: self.referral_program,
+                referee_receipt: self.receipt,
+                referrer: self.referrer_participant,
+                user: user.pubkey(),
+            },
+            instruction::ContestClawback { evidence_hash: Some(EVIDENCE) },
+        )
+    }
+
+    fn resolve_ix(&self, authority: &Keypair, uphold: bool) -> Instruction {
+        program_instruction(
+            accounts::ResolveClawback {
+                referral_program: self.referral_program,
+                referee_receipt: self.receipt,
+                referrer: self.referrer_participant,
+                authority: authority.pubkey(),
+            },
+            instruction::ResolveClawback { uphold },
+        )
+    }
+
+    fn finalize_ix(&self) -> Instruction {
+        program_instruction(self.settle_accounts(), instruction::FinalizeClawback)
+    }
+
+    fn default_resolve_ix(&self) -> Instruction {
+        program_instruction(self.settle_accounts(), instruction::DefaultResolve)
+    }
+
+    fn settle_accounts(&self) -> accounts::SettleClawback {
+        accounts::SettleClawback {
+            referral_program: self.referral_program,
+            referee_receipt: self.receipt,
+            referrer: self.referrer_participant,
+        }
+    }
+
+    async fn assert_upheld(&self, context: &mut ProgramTestContext) {
+        let program: ReferralProgram = get_account(context, self.referral_program).await;
+        assert_eq!(program.total_committed, 0);
+        let participant: Participant = get_account(context, self.referrer_participant).await;
+        assert_eq!((participant.pending_rewards, participant.clawed_back), (0, REFERRAL_REWARD));
+        let receipt: RefereeReceipt = get_account(context, self.receipt).await;
+        assert_eq!(receipt.clawback_status, RefereeReceipt::CLAWBACK_UPHELD);
+    }
+
+    async fn assert_overturned(&self, context: &mut ProgramTestContext) {
+        let program: ReferralProgram = get_account(context, self.referral_program).await;
+        assert_eq!(program.total_committed, REFERRAL_REWARD);
+        let participant: Participant = get_account(context, self.referrer_participant).await;
+        assert_eq!((participant.pending_rewards, participant.clawed_back), (REFERRAL_REWARD, 0));
+        assert_eq!(participant.total_referrals, 1);
+        let receipt: RefereeReceipt = get_account(context, self.receipt).await;
+        assert_eq!(receipt.clawback_status, RefereeReceipt::CLAWBACK_OVERTURNED);
+        assert!(!receipt.clawed_back);
+    }
+}
+
+#[tokio::test]
+async fn test_uncontested_clawback_finalizes_after_window() {
+    let (mut context, owner, referrer, _) = setup().await;
+    let dispute = open_dispute(&mut context, &owner, &referrer).await;
+
+    let result = process(&mut context, &[dispute.finalize_ix()], &[]).await;
+    assert_referral_error(result, ReferralError::ClawbackDeadlineNotReached);
+
+    // Once the window closes anyone can finalize it, and the referrer can no longer contest
+    advance_clock(&mut context, DISPUTE_WINDOW).await;
+    let result = process(&mut context, &[dispute.contest_ix(&referrer)], &[&referrer]).await;
+    assert_referral_error(result, ReferralError::ClawbackDeadlinePassed);
+    process(&mut context, &[dispute.finalize_ix()], &[]).await.unwrap();
+    dispute.assert_upheld(&mut context).await;
+
+    let result = process(&mut context, &[dispute.finalize_ix()], &[]).await;
+    assert_referral_error(result, ReferralError::InvalidClawbackState);
+}
+
+#[tokio::test]
+async fn test_contested_clawback_upheld_by_authority() {
+    let (mut context, owner, referrer, stranger) = setup().await;
+    let dispute = open_dispute(&mut context, &owner, &referrer).await;
+
+    // Only the referrer can contest
+    let result = process(&mut context, &[dispute.contest_ix(&stranger)], &[&stranger]).await;
+    assert_referral_error(result, ReferralError::InvalidReferrer);
+    process(&mut context, &[dispute.contest_ix(&referrer)], &[&referrer]).await.unwrap();
+    let receipt: RefereeReceipt = get_account(&mut context, dispute.receipt).await;
+    assert_eq!(receipt.clawback_status, RefereeReceipt::CLAWBACK_CONTESTED);
+    assert_eq!(receipt.evidence_hash, EVIDENCE);
+
+    // A contested clawback is not finalized when the window closes; it waits for the authority
+    advance_clock(&mut context, DISPUTE_WINDOW).await;
+    let result = process(&mut context, &[dispute.finalize_ix()], &[]).await;
+    assert_referral_error(result, ReferralError::InvalidClawbackState);
+    let result = process(&mut context, &[dispute.resolve_ix(&referrer, false)], &[&referrer]).await;
+    assert_referral_error(result, ReferralError::InvalidAuthority);
+
+    process(&mut context, &[dispute.resolve_ix(&owner, true)], &[&owner]).await.unwrap();
+    dispute.assert_upheld(&mut context).await;
+}
+
+#[tokio::test]
+async fn test_contested_clawback_overturned_by_authority() {
+    let (mut context, owner, referrer, _) = setup().await;
+    let dispute = open_dispute(&mut context, &owner, &referrer).await;
+
+    process(&mut context, &[dispute.contest_ix(&referrer)], &[&referrer]).await.unwrap();
+    process(&mut context, &[dispute.resolve_ix(&owner, false)], &[&owner]).await.unwrap();
+    dispute.assert_overturned(&mut context).await;
+
+    // An overturned referral cannot be clawed back again
+    let clawback_ix = program_instruction(
+        accounts::ClawbackReferral {
+            referral_program: dispute.referral_program,
+            referee_receipt: dispute.receipt,
+            referrer: dispute.referrer_participant,
+            authority: owner.pubkey(),
+        },
+        instruction::ClawbackReferral { idempotency_key: None },
+    );
+    let result = process(&mut context, &[clawback_ix], &[&owner]).await;
+    assert_referral_error(result, ReferralError::AlreadyClawedBack);
+}
+
+#[tokio::test]
+async fn test_unresolved_contest_defaults_to_referrer() {
+    let (mut context, owner, referrer, _) = setup().await;
+    let dispute = open_dispute(&mut context, &owner, &referrer).await;
+    process(&mut context, &[dispute.contest_ix(&referrer)], &[&referrer]).await.unwrap();
+
+    let result = process(&mut context, &[dispute.default_resolve_ix()], &[]).await;
+    assert_referral_error(result, ReferralError::ClawbackDeadlineNotReached);
+
+    // After the timeout the authority can no longer rule and anyone can settle it for the referrer
+    advance_clock(&mut context, CLAWBACK_RESOLUTION_TIMEOUT).await;
+    let result = process(&mut context, &[dispute.resolve_ix(&owner, true)], &[&owner]).await;
+    assert_referral_error(result, ReferralError::ClawbackDeadlinePassed);
+    process(&mut context, &[dispute.default_resolve_ix()], &[]).await.unwrap();
+    dispute.assert_overturned(&mut context).await;
+}
+
+#[tokio::test]
+async fn test_clawback_overturned_after_rotation() {
+    let (mut context, owner, referrer, _) = setup().await;
+    let dispute = open_dispute(&mut context, &owner, &referrer).await;
+    let new_wallet = create_funded_user(&mut context).await;
+    let new_participant = rotate_owner(&mut context, dispute.referral_program, &referrer, &new_wallet).await;
+
+    // The rotated-away account can no longer take part in the dispute; the account it was rotated to can
+    let result = process(&mut context, &[dispute.contest_ix(&referrer)], &[&referrer]).await;
+    assert_referral_error(result, ReferralError::InvalidReferrer);
+    let dispute = Dispute { referrer_participant: new_participant, ..dispute };
+    process(&mut context, &[dispute.contest_ix(&new_wallet)], &[&new_wallet]).await.unwrap();
+
+    // Overturning it restores the referral and rewards onto the live account
+    process(&mut context, &[dispute.resolve_ix(&owner, false)], &[&owner]).await.unwrap();
+    dispute.assert_overturned(&mut context).await;
+}
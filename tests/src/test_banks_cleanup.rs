@@ -0,0 +1,231 @@
+//! Permissionless cleanup of spent auxiliary accounts.
+//!
+//! An invite-only program admits Alice and Bob with two of its three invites, and a sponsor's match offer runs dry
+//! matching Bob's referral. A stranger closes the claimed invites and the empty offer for a share of their rent,
+//! the rest going back to the authority and the sponsor; the unclaimed invite cannot be cleaned up.
+
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+        signature::Keypair,
+        signer::Signer,
+    },
+};
+use solrefer::{
+    accounts,
+    constants::{DEFAULT_CLEANUP_BOUNTY_BPS, MIN_LOCKED_PERIOD},
+    error::ReferralError,
+    events::AccountsCleanedUp,
+    instruction,
+    instructions::{cleanup_bounty, ProgramSettings},
+    state::MatchOffer,
+};
+
+use crate::{
+    banks_util::{
+        assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol, get_account, get_balance,
+        get_clock_time, mint_invites_ix, process, process_with_events, program_instruction, setup,
+        update_program_settings, update_program_settings_ix,
+    },
+    test_util::{
+        get_eligibility_criteria_pda, get_invite_pda, get_match_offer_pda, get_participant_pda, get_referee_receipt_pda,
+    },
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+#[test]
+fn test_cleanup_bounty_rounds_down() {
+    assert_eq!(cleanup_bounty(1_000_000, DEFAULT_CLEANUP_BOUNTY_BPS), 100_000);
+    assert_eq!(cleanup_bounty(9, DEFAULT_CLEANUP_BOUNTY_BPS), 0);
+    assert_eq!(cleanup_bounty(u64::MAX, 10_000), u64::MAX);
+}
+
+fn settings(end_time: i64, cleanup_bounty_bps: u16) -> ProgramSettings {
+    ProgramSettings {
+        fixed_reward_amount: REWARD,
+        locked_period: MIN_LOCKED_PERIOD,
+        program_end_time: Some(end_time),
+        base_reward: REWARD,
+        max_reward_cap: 100 * REWARD,
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: true,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
+    }
+}
+
+#[tokio::test]
+async fn test_cleanup_closes_spent_accounts_for_a_bounty() {
+    let (mut context, owner, alice, sponsor) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+
+    // The bounty cannot exceed the reclaimed rent
+    let ix = update_program_settings_ix(&mut context, &owner, referral_program, settings(end_time, 10_001)).await;
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::InvalidCleanupBounty);
+    update_program_settings(&mut context, &owner, referral_program, settings(end_time, 0)).await;
+
+    // Alice and Bob use two of three invites, and Bob's referral uses up the sponsor's whole budget
+    let invites: Vec<Pubkey> = (0..3).map(|index| get_invite_pda(referral_program, index, solrefer::ID)).collect();
+    process(&mut context, &[mint_invites_ix(&owner, referral_program, &invites)], &[&owner]).await.unwrap();
+    process(&mut context, &[join_ix(&alice, referral_program, invites[0])], &[&alice]).await.unwrap();
+    let create_offer_ix = program_instruction(
+        accounts::CreateMatchOffer {
+            referral_program,
+            match_offer: get_match_offer_pda(referral_program, sponsor.pubkey(), solrefer::ID),
+            sponsor: sponsor.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::CreateMatchOffer { match_bps: 10_000, budget: REWARD },
+    );
+    process(&mut context, &[create_offer_ix], &[&sponsor]).await.unwrap();
+    let match_offer = get_match_offer_pda(referral_program, sponsor.pubkey(), solrefer::ID);
+    let bob = create_funded_user(&mut context).await;
+    let ix = join_through_referral_ix(&bob, referral_program, &alice, invites[1], match_offer);
+    process(&mut context, &[ix], &[&bob]).await.unwrap();
+    let offer: MatchOffer = get_account(&mut context, match_offer).await;
+    assert_eq!(offer.balance, 0);
+
+    // An unclaimed invite fails the whole batch, and rent only goes back to whoever paid it
+    let caller = create_funded_user(&mut context).await;
+    let mixed = [(invites[0], owner.pubkey()), (invites[2], owner.pubkey())];
+    let result = process(&mut context, &[cleanup_ix(&caller, referral_program, &mixed)], &[&caller]).await;
+    assert_referral_error(result, ReferralError::AccountNotClosable);
+    assert!(context.banks_client.get_account(invites[0]).await.unwrap().is_some());
+    let misdirected = [(match_offer, owner.pubkey())];
+    let result = process(&mut context, &[cleanup_ix(&caller, referral_program, &misdirected)], &[&caller]).await;
+    assert_referral_error(result, ReferralError::InvalidCleanupBatch);
+
+    // The caller earns the default bounty on each account and the rent payers get the rest back
+    let invite_lamports = get_balance(&mut context, invites[0]).await;
+    let offer_lamports = get_balance(&mut context, match_offer).await;
+    let caller_before = get_balance(&mut context, caller.pubkey()).await;
+    let owner_before = get_balance(&mut context, owner.pubkey()).await;
+    let sponsor_before = get_balance(&mut context, sponsor.pubkey()).await;
+    let batch = [(invites[0], owner.pubkey()), (invites[1], owner.pubkey()), (match_offer, sponsor.pubkey())];
+    let cleaned: Vec<AccountsCleanedUp> =
+        process_with_events(&mut context, &[cleanup_ix(&caller, referral_program, &batch)], &[&caller]).await;
+
+    let invite_bounty = invite_lamports / 10;
+    let offer_bounty = offer_lamports / 10;
+    let bounty = 2 * invite_bounty + offer_bounty;
+    assert_eq!(get_balance(&mut context, caller.pubkey()).await, caller_before + bounty);
+    assert_eq!(get_balance(&mut context, owner.pubkey()).await, owner_before + 2 * (invite_lamports - invite_bounty));
+    assert_eq!(get_balance(&mut context, sponsor.pubkey()).await, sponsor_before + offer_lamports - offer_bounty);
+    assert_eq!(cleaned.len(), 1);
+    assert_eq!((cleaned[0].accounts_closed, cleaned[0].bounty), (3, bounty));
+    assert_eq!(cleaned[0].refunded, 2 * invite_lamports + offer_lamports - bounty);
+    for (account, _) in batch {
+        assert!(context.banks_client.get_account(account).await.unwrap().is_none());
+    }
+    assert!(context.banks_client.get_account(invites[2]).await.unwrap().is_some());
+}
+
+fn cleanup_ix(caller: &Keypair, referral_program: Pubkey, batch: &[(Pubkey, Pubkey)]) -> Instruction {
+    let mut ix =
+        program_instruction(accounts::Cleanup { referral_program, caller: caller.pubkey() }, instruction::Cleanup {});
+    for (account, rent_payer) in batch {
+        ix.accounts.push(AccountMeta::new(*account, false));
+        ix.accounts.push(AccountMeta::new(*rent_payer, false));
+    }
+    ix
+}
+
+fn join_ix(user: &Keypair, referral_program: Pubkey, invite: Pubkey) -> Instruction {
+    program_instruction(
+        accounts::JoinReferralProgram {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+            invite: Some(invite),
+            collection_metadata: None,
+            collection_nft: None,
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account: None,
+            bridge_participant: None,
+            bridge_referrer: None,
+            user: user.pubkey(),
+            event_queue: None,
+            system_program: system_program::ID,
+        },
+        instruction::JoinReferralProgram { accepted_terms_hash: [0u8; 32] },
+    )
+}
+
+/// Joins `user` through `referrer` with an invite, passing the match offer that pays the referrer
+fn join_through_referral_ix(
+    user: &Keypair,
+    referral_program: Pubkey,
+    referrer: &Keypair,
+    invite: Pubkey,
+    match_offer: Pubkey,
+) -> Instruction {
+    program_instruction(
+        accounts::JoinThroughReferral {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+            referrer: get_participant_pda(referral_program, referrer.pubkey(), solrefer::ID),
+            rotated_referrer: None,
+            split_recipient: None,
+            boost_escrow: None,
+            match_offer: Some(match_offer),
+            referrer_wallet: Some(referrer.pubkey()),
+            referee_receipt: get_referee_receipt_pda(referral_program, user.pubkey(), solrefer::ID),
+            sponsor_vault: None,
+            invite: Some(invite),
+            referrer_collection_metadata: None,
+            referrer_collection_nft: None,
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account: None,
+            referee_token_account: None,
+            referrer_upline: None,
+            user: user.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
+            system_program: system_program::ID,
+        },
+        instruction::JoinThroughReferral { source_tag: None, accepted_terms_hash: [0u8; 32], link_proof: None },
+    )
+}
@@ -0,0 +1,263 @@
+//! Two-phase closure of a referral program.
+//!
+//! The first `close_referral_program` only records the request and stops joins and deposits; the authority may
+//! cancel it during the grace period. Once the grace period has elapsed and the program is paused, a second call
+//! sweeps the vault and closes the program's accounts. It waits for a queued withdrawal to be settled, and for the
+//! program's boost escrows and match offers to be closed with `cleanup`, which refunds their owners.
+
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{instruction::AccountMeta, signer::Signer},
+};
+use solrefer::{
+    accounts,
+    constants::{CLOSURE_GRACE_PERIOD, WITHDRAWAL_DELAY},
+    error::ReferralError,
+    instruction,
+    instructions::{cleanup_bounty, closure_phase, ClosurePhase},
+    state::ReferralProgram,
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, close_referral_program_ix, create_funded_user,
+        create_sol_referral_program, deposit_sol, deposit_sol_ix, get_account, get_balance, join_referral_program,
+        process, program_instruction, set_program_status_ix, setup, try_join_referral_program,
+    },
+    test_util::{
+        get_boost_escrow_pda, get_eligibility_criteria_pda, get_match_offer_pda, get_sponsor_vault_pda,
+        get_withdrawal_request_pda,
+    },
+};
+
+const REWARD: u64 = 1_000_000;
+const DEPOSIT: u64 = 5 * REWARD;
+const SPONSOR_FUNDS: u64 = 2 * REWARD;
+
+#[test]
+fn test_closure_phase() {
+    let mut program = ReferralProgram::default();
+    assert!(!program.is_closing());
+    assert_eq!(program.closure_effective_at(), None);
+    assert_eq!(closure_phase(&program, 1_000).unwrap(), ClosurePhase::Request);
+
+    program.closure_requested_at = 1_000;
+    let effective_at = 1_000 + CLOSURE_GRACE_PERIOD;
+    assert!(program.is_closing());
+    assert_eq!(program.closure_effective_at(), Some(effective_at));
+    assert_eq!(closure_phase(&program, effective_at - 1).unwrap_err(), ReferralError::ClosureGracePeriodActive.into());
+    assert_eq!(closure_phase(&program, effective_at).unwrap(), ClosurePhase::Finalize);
+
+    // Finalizing must wait for the program to be paused or to end
+    program.is_active = true;
+    assert_eq!(closure_phase(&program, effective_at).unwrap_err(), ReferralError::ProgramStillActive.into());
+    program.program_end_time = Some(effective_at);
+    assert_eq!(closure_phase(&program, effective_at).unwrap(), ClosurePhase::Finalize);
+    program.is_active = false;
+
+    // Finalizing must not strand credited rewards or a token vault
+    program.total_committed = 1;
+    assert_eq!(closure_phase(&program, effective_at).unwrap_err(), ReferralError::OutstandingRewards.into());
+    program.total_committed = 0;
+    program.token_vault_initialized = true;
+    assert_eq!(closure_phase(&program, effective_at).unwrap_err(), ReferralError::TokenVaultStillOpen.into());
+    program.token_vault_initialized = false;
+    program.contest_escrowed = 1;
+    assert_eq!(closure_phase(&program, effective_at).unwrap_err(), ReferralError::ContestPrizesOutstanding.into());
+    program.contest_escrowed = 0;
+
+    // Nor a queued withdrawal, or the boost escrows and match offers still open on the program
+    program.withdrawal_pending = true;
+    assert_eq!(closure_phase(&program, effective_at).unwrap_err(), ReferralError::WithdrawalPending.into());
+    program.withdrawal_pending = false;
+    for (open_boost_escrows, open_match_offers) in [(1, 0), (0, 1)] {
+        let program = ReferralProgram { open_boost_escrows, open_match_offers, ..program.clone() };
+        assert_eq!(closure_phase(&program, effective_at).unwrap_err(), ReferralError::EscrowsOutstanding.into());
+    }
+    assert_eq!(closure_phase(&program, effective_at).unwrap(), ClosurePhase::Finalize);
+}
+
+#[tokio::test]
+async fn test_two_phase_close() {
+    let (mut context, owner, alice, _) = setup().await;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, None).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, DEPOSIT).await;
+    let sponsor_vault = get_sponsor_vault_pda(referral_program, solrefer::ID);
+    let fund_ix = program_instruction(
+        accounts::FundSponsorVault {
+            referral_program,
+            sponsor_vault,
+            funder: owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::FundSponsorVault { amount: SPONSOR_FUNDS },
+    );
+    process(&mut context, &[fund_ix], &[&owner]).await.unwrap();
+    let close_ix = close_referral_program_ix(&owner, referral_program, vault);
+    let cancel_ix = program_instruction(
+        accounts::CancelClosure { referral_program, authority: owner.pubkey() },
+        instruction::CancelClosure {},
+    );
+
+    // The first call only records the request; nothing is closed
+    process(&mut context, std::slice::from_ref(&close_ix), &[&owner]).await.unwrap();
+    let program_account: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert!(program_account.is_closing());
+    assert_eq!(get_balance(&mut context, vault).await, DEPOSIT);
+
+    // Joins and deposits stop during the grace period
+    let (result, _) = try_join_referral_program(&mut context, &alice, referral_program).await;
+    assert_referral_error(result, ReferralError::ProgramClosing);
+    let deposit_ix = deposit_sol_ix(&owner, referral_program, vault, REWARD);
+    assert_referral_error(process(&mut context, &[deposit_ix], &[&owner]).await, ReferralError::ProgramClosing);
+
+    // Closing again before the grace period has elapsed is rejected
+    let result = process(&mut context, std::slice::from_ref(&close_ix), &[&owner]).await;
+    assert_referral_error(result, ReferralError::ClosureGracePeriodActive);
+
+    // Cancelling reopens the program
+    process(&mut context, std::slice::from_ref(&cancel_ix), &[&owner]).await.unwrap();
+    let program_account: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert!(!program_account.is_closing());
+    join_referral_program(&mut context, &alice, referral_program).await;
+    assert_referral_error(process(&mut context, &[cancel_ix], &[&owner]).await, ReferralError::NoClosurePending);
+
+    // Request again and wait out the grace period
+    process(&mut context, std::slice::from_ref(&close_ix), &[&owner]).await.unwrap();
+    advance_clock(&mut context, CLOSURE_GRACE_PERIOD).await;
+
+    // Finalizing waits for the program to be paused
+    let result = process(&mut context, std::slice::from_ref(&close_ix), &[&owner]).await;
+    assert_referral_error(result, ReferralError::ProgramStillActive);
+    process(&mut context, &[set_program_status_ix(&owner, referral_program, false)], &[&owner]).await.unwrap();
+
+    let owner_balance_before = get_balance(&mut context, owner.pubkey()).await;
+    process(&mut context, &[close_ix], &[&owner]).await.unwrap();
+
+    // Both vaults are swept to the authority along with the closed accounts' rent, as neither can be withdrawn
+    // from once the program is gone
+    assert_eq!(get_balance(&mut context, vault).await, 0);
+    assert_eq!(get_balance(&mut context, sponsor_vault).await, 0);
+    assert!(get_balance(&mut context, owner.pubkey()).await > owner_balance_before + DEPOSIT + SPONSOR_FUNDS);
+    for address in [referral_program, get_eligibility_criteria_pda(referral_program, solrefer::ID)] {
+        assert!(context.banks_client.get_account(address).await.unwrap().is_none());
+    }
+}
+
+#[tokio::test]
+async fn test_close_waits_for_withdrawal_and_escrows() {
+    const BOOST: u64 = 3 * REWARD;
+    const BUDGET: u64 = 4 * REWARD;
+    let (mut context, owner, alice, sponsor) = setup().await;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, None).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, DEPOSIT).await;
+
+    // Alice stakes a boost, a sponsor offers a match and the authority queues a withdrawal
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    let boost_escrow = get_boost_escrow_pda(referral_program, alice_participant, solrefer::ID);
+    let fund_boost_ix = program_instruction(
+        accounts::FundRefereeBoost {
+            referral_program,
+            participant: alice_participant,
+            boost_escrow,
+            user: alice.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::FundRefereeBoost { amount: BOOST, boost_per_referee: REWARD },
+    );
+    process(&mut context, &[fund_boost_ix], &[&alice]).await.unwrap();
+    let match_offer = get_match_offer_pda(referral_program, sponsor.pubkey(), solrefer::ID);
+    let create_offer_ix = program_instruction(
+        accounts::CreateMatchOffer {
+            referral_program,
+            match_offer,
+            sponsor: sponsor.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::CreateMatchOffer { match_bps: 5_000, budget: BUDGET },
+    );
+    process(&mut context, &[create_offer_ix], &[&sponsor]).await.unwrap();
+    let queue_ix = program_instruction(
+        accounts::QueueWithdrawal {
+            referral_program,
+            withdrawal_request: get_withdrawal_request_pda(referral_program, solrefer::ID),
+            destination: None,
+            authority: owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::QueueWithdrawal { amount: REWARD },
+    );
+    process(&mut context, &[queue_ix], &[&owner]).await.unwrap();
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert!(program.withdrawal_pending);
+    assert_eq!((program.open_boost_escrows, program.open_match_offers), (1, 1));
+
+    // A funded boost escrow or match offer cannot be cleaned up while the program runs
+    let caller = create_funded_user(&mut context).await;
+    let cleanup_ix = |batch: &[(_, _)]| {
+        let mut ix = program_instruction(
+            accounts::Cleanup { referral_program, caller: caller.pubkey() },
+            instruction::Cleanup {},
+        );
+        for &(account, rent_payer) in batch {
+            ix.accounts.push(AccountMeta::new(account, false));
+            ix.accounts.push(AccountMeta::new(rent_payer, false));
+        }
+        ix
+    };
+    let batch = [(boost_escrow, alice.pubkey()), (match_offer, sponsor.pubkey())];
+    for entry in batch {
+        let result = process(&mut context, &[cleanup_ix(&[entry])], &[&caller]).await;
+        assert_referral_error(result, ReferralError::AccountNotClosable);
+    }
+
+    let close_ix = close_referral_program_ix(&owner, referral_program, vault);
+    process(&mut context, &[set_program_status_ix(&owner, referral_program, false)], &[&owner]).await.unwrap();
+    process(&mut context, std::slice::from_ref(&close_ix), &[&owner]).await.unwrap();
+    advance_clock(&mut context, CLOSURE_GRACE_PERIOD.max(WITHDRAWAL_DELAY)).await;
+
+    // The queued withdrawal has to be settled first
+    let result = process(&mut context, std::slice::from_ref(&close_ix), &[&owner]).await;
+    assert_referral_error(result, ReferralError::WithdrawalPending);
+    let execute_ix = program_instruction(
+        accounts::ExecuteWithdrawal {
+            withdraw: accounts::WithdrawFunds {
+                referral_program,
+                vault,
+                token_vault: None,
+                destination_token_account: None,
+                destination: None,
+                authority: owner.pubkey(),
+                system_program: system_program::ID,
+                token_program: None,
+            },
+            withdrawal_request: get_withdrawal_request_pda(referral_program, solrefer::ID),
+        },
+        instruction::ExecuteWithdrawal,
+    );
+    process(&mut context, &[execute_ix], &[&owner]).await.unwrap();
+
+    // Then the escrows, which anyone can now close, refunding their funds in full and all but the bounty on rent
+    let result = process(&mut context, std::slice::from_ref(&close_ix), &[&owner]).await;
+    assert_referral_error(result, ReferralError::EscrowsOutstanding);
+    let bounty_bps = get_account::<ReferralProgram>(&mut context, referral_program).await.cleanup_bounty_bps();
+    let (boost_lamports, offer_lamports) =
+        (get_balance(&mut context, boost_escrow).await, get_balance(&mut context, match_offer).await);
+    let (alice_before, sponsor_before) =
+        (get_balance(&mut context, alice.pubkey()).await, get_balance(&mut context, sponsor.pubkey()).await);
+    process(&mut context, &[cleanup_ix(&batch)], &[&caller]).await.unwrap();
+    assert_eq!(
+        get_balance(&mut context, alice.pubkey()).await,
+        alice_before + boost_lamports - cleanup_bounty(boost_lamports - BOOST, bounty_bps)
+    );
+    assert_eq!(
+        get_balance(&mut context, sponsor.pubkey()).await,
+        sponsor_before + offer_lamports - cleanup_bounty(offer_lamports - BUDGET, bounty_bps)
+    );
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert!(!program.withdrawal_pending);
+    assert_eq!((program.open_boost_escrows, program.open_match_offers), (0, 0));
+
+    process(&mut context, &[close_ix], &[&owner]).await.unwrap();
+    assert!(context.banks_client.get_account(referral_program).await.unwrap().is_none());
+}
@@ -0,0 +1,222 @@
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer},
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::MIN_LOCKED_PERIOD,
+    error::ReferralError,
+    instruction,
+    instructions::{parse_nft_metadata, NftMetadata, ProgramSettings},
+    state::{EligibilityCriteria, Participant},
+};
+
+use crate::{
+    banks_util::{
+        assert_referral_error, create_funded_token_account, create_mint, create_sol_referral_program, deposit_sol,
+        get_account, get_clock_time, nft_metadata_data, process, program_instruction, set_nft_metadata, setup,
+        update_program_settings, update_program_settings_ix,
+    },
+    test_util::{get_eligibility_criteria_pda, get_participant_pda, get_referee_receipt_pda},
+};
+
+const REFERRAL_REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+fn settings(
+    program_end_time: i64,
+    required_collection: Option<Pubkey>,
+    collection_gates_credits: bool,
+) -> ProgramSettings {
+    ProgramSettings {
+        fixed_reward_amount: REFERRAL_REWARD,
+        locked_period: MIN_LOCKED_PERIOD,
+        program_end_time: Some(program_end_time),
+        base_reward: REFERRAL_REWARD,
+        max_reward_cap: 10 * REFERRAL_REWARD,
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection,
+        collection_gates_credits,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
+    }
+}
+
+#[test]
+fn test_parse_nft_metadata() {
+    let mint = Pubkey::new_unique();
+    let collection = Pubkey::new_unique();
+
+    let data = nft_metadata_data(mint, Some((collection, true)));
+    assert_eq!(parse_nft_metadata(&data), Some(NftMetadata { mint, collection: Some((collection, true)) }));
+    let data = nft_metadata_data(mint, None);
+    assert_eq!(parse_nft_metadata(&data), Some(NftMetadata { mint, collection: None }));
+
+    // Creators sit between the fixed fields and the collection and are skipped over
+    let mut data = nft_metadata_data(mint, Some((collection, false)));
+    let creators_offset = 1 + 32 + 32 + 12 + 2;
+    let mut creators = vec![1, 2, 0, 0, 0];
+    creators.extend_from_slice(&[7u8; 2 * 34]);
+    data.splice(creators_offset..=creators_offset, creators);
+    assert_eq!(parse_nft_metadata(&data), Some(NftMetadata { mint, collection: Some((collection, false)) }));
+
+    // Other account kinds and truncated data are rejected
+    let mut data = nft_metadata_data(mint, Some((collection, true)));
+    data[0] = 6;
+    assert_eq!(parse_nft_metadata(&data), None);
+    let data = nft_metadata_data(mint, Some((collection, true)));
+    assert_eq!(parse_nft_metadata(&data[..data.len() - 1]), None);
+}
+
+#[tokio::test]
+async fn test_collection_gated_program() {
+    let (mut context, owner, referrer, referee) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) =
+        create_sol_referral_program(&mut context, &owner, REFERRAL_REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REFERRAL_REWARD).await;
+    let collection = Pubkey::new_unique();
+
+    // Gating credits needs a collection to gate on
+    let ix = update_program_settings_ix(&mut context, &owner, referral_program, settings(end_time, None, true)).await;
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::InvalidCollectionGate);
+    update_program_settings(&mut context, &owner, referral_program, settings(end_time, Some(collection), true)).await;
+    let criteria: EligibilityCriteria =
+        get_account(&mut context, get_eligibility_criteria_pda(referral_program, solrefer::ID)).await;
+    assert_eq!(criteria.required_collection, Some(collection));
+    assert!(criteria.collection_gates_credits);
+
+    // The referrer holds a verified NFT of the collection; the referee holds one whose membership is unverified
+    let nft = mint_nft(&mut context, &referrer, Some((collection, true))).await;
+    let unverified_nft = mint_nft(&mut context, &referee, Some((collection, false))).await;
+
+    let result = process(&mut context, &[join_ix(&referrer, referral_program, None)], &[&referrer]).await;
+    assert_referral_error(result, ReferralError::CollectionNftRequired);
+    let result = process(&mut context, &[join_ix(&referee, referral_program, Some(unverified_nft))], &[&referee]).await;
+    assert_referral_error(result, ReferralError::CollectionNotVerified);
+    // Someone else's NFT does not qualify
+    let result = process(&mut context, &[join_ix(&referee, referral_program, Some(nft))], &[&referee]).await;
+    assert_referral_error(result, ReferralError::CollectionNftNotHeld);
+    // Nor does a metadata account of another mint
+    let result =
+        process(&mut context, &[join_ix(&referrer, referral_program, Some((unverified_nft.0, nft.1)))], &[&referrer])
+            .await;
+    assert_referral_error(result, ReferralError::InvalidCollectionMetadata);
+
+    process(&mut context, &[join_ix(&referrer, referral_program, Some(nft))], &[&referrer]).await.unwrap();
+    let referrer_participant = get_participant_pda(referral_program, referrer.pubkey(), solrefer::ID);
+
+    // Referees do not need an NFT, but the referrer must still hold its own for the referral to be credited
+    let ix = join_through_referral_ix(&referee, referral_program, referrer_participant, None);
+    let result = process(&mut context, &[ix], &[&referee]).await;
+    assert_referral_error(result, ReferralError::CollectionNftRequired);
+    let ix = join_through_referral_ix(&referee, referral_program, referrer_participant, Some(nft));
+    process(&mut context, &[ix], &[&referee]).await.unwrap();
+    let participant: Participant = get_account(&mut context, referrer_participant).await;
+    assert_eq!(participant.total_referrals, 1);
+    assert_eq!(participant.pending_rewards, REFERRAL_REWARD);
+}
+
+/// Mints a single NFT to `holder` with a metadata account naming `collection`, returning the metadata and the
+/// holder's token account
+async fn mint_nft(
+    context: &mut ProgramTestContext,
+    holder: &Keypair,
+    collection: Option<(Pubkey, bool)>,
+) -> (Pubkey, Pubkey) {
+    let mint = create_mint(context, holder).await;
+    let token_account = create_funded_token_account(context, holder, mint, 1).await;
+    (set_nft_metadata(context, mint, collection), token_account)
+}
+
+fn join_ix(user: &Keypair, referral_program: Pubkey, nft: Option<(Pubkey, Pubkey)>) -> Instruction {
+    program_instruction(
+        accounts::JoinReferralProgram {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+            invite: None,
+            collection_metadata: nft.map(|(metadata, _)| metadata),
+            collection_nft: nft.map(|(_, token_account)| token_account),
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account: None,
+            bridge_participant: None,
+            bridge_referrer: None,
+            user: user.pubkey(),
+            event_queue: None,
+            system_program: system_program::ID,
+        },
+        instruction::JoinReferralProgram { accepted_terms_hash: [0u8; 32] },
+    )
+}
+
+fn join_through_referral_ix(
+    user: &Keypair,
+    referral_program: Pubkey,
+    referrer: Pubkey,
+    referrer_nft: Option<(Pubkey, Pubkey)>,
+) -> Instruction {
+    program_instruction(
+        accounts::JoinThroughReferral {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+            referrer,
+            rotated_referrer: None,
+            split_recipient: None,
+            boost_escrow: None,
+            match_offer: None,
+            referrer_wallet: None,
+            referee_receipt: get_referee_receipt_pda(referral_program, user.pubkey(), solrefer::ID),
+            sponsor_vault: None,
+            invite: None,
+            referrer_collection_metadata: referrer_nft.map(|(metadata, _)| metadata),
+            referrer_collection_nft: referrer_nft.map(|(_, token_account)| token_account),
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account: None,
+            referee_token_account: None,
+            referrer_upline: None,
+            user: user.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
+            system_program: system_program::ID,
+        },
+        instruction::JoinThroughReferral { source_tag: None, accepted_terms_hash: [0u8; 32], link_proof: None },
+    )
+}
@@ -0,0 +1,202 @@
+//! Ranked referral contests settled after the program ends.
+//!
+//! Alice, Bob and Carol refer three, two and one wallets. Once the program has ended the authority settles the
+//! contest in that order, and after the dispute window each winner claims the prize of their own rank. The authority
+//! takes back what the escrow holds beyond the prizes, but not while the program is frozen.
+
+use anchor_client::{
+    anchor_lang::{system_program, AccountSerialize},
+    solana_sdk::{
+        instruction::AccountMeta, instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer,
+    },
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    error::ReferralError,
+    instruction,
+    state::{validate_contest_prizes, validate_contest_ranking, Contest, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol,
+        get_account, get_balance, get_clock_time, join_referral_program, join_through_referral, process,
+        program_instruction, setup,
+    },
+    test_util::get_eligibility_criteria_pda,
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_DAY: i64 = 86400;
+const PRIZES: [u64; 5] = [10_000_000, 5_000_000, 2_000_000, 0, 0];
+const LEFTOVER: u64 = 3_000_000;
+
+#[test]
+fn test_validate_contest_prizes() {
+    assert!(validate_contest_prizes(&PRIZES).is_ok());
+    assert_eq!(validate_contest_prizes(&[0; 5]).unwrap_err(), ReferralError::InvalidContestConfig.into());
+    assert_eq!(
+        validate_contest_prizes(&[10_000_000, 0, 2_000_000, 0, 0]).unwrap_err(),
+        ReferralError::InvalidContestConfig.into()
+    );
+}
+
+#[test]
+fn test_validate_contest_ranking() {
+    assert!(validate_contest_ranking(&[3, 2, 1]).is_ok());
+    assert!(validate_contest_ranking(&[3, 3, 1]).is_ok());
+    assert_eq!(validate_contest_ranking(&[2, 3, 1]).unwrap_err(), ReferralError::InvalidContestRanking.into());
+    assert_eq!(validate_contest_ranking(&[3, 1, 2]).unwrap_err(), ReferralError::InvalidContestRanking.into());
+}
+
+/// Rewrites the referral program's guardian freeze, as `guardian_freeze` and `unfreeze_program` would set it
+async fn set_frozen(context: &mut ProgramTestContext, referral_program: Pubkey, frozen: bool) {
+    let mut program: ReferralProgram = get_account(context, referral_program).await;
+    program.frozen = frozen;
+    let mut data = Vec::new();
+    program.try_serialize(&mut data).unwrap();
+    let mut account = context.banks_client.get_account(referral_program).await.unwrap().unwrap();
+    account.data[..data.len()].copy_from_slice(&data);
+    context.set_account(&referral_program, &account.into());
+}
+
+#[tokio::test]
+async fn test_contest_finalize_and_claim() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let carol = create_funded_user(&mut context).await;
+    let end_time = get_clock_time(&mut context).await + ONE_DAY;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+
+    // Alice refers three wallets, bob two and carol one
+    let mut participants = Vec::new();
+    for (user, referrals) in [(&alice, 3), (&bob, 2), (&carol, 1)] {
+        let participant = join_referral_program(&mut context, user, referral_program).await;
+        for _ in 0..referrals {
+            let referee = create_funded_user(&mut context).await;
+            join_through_referral(&mut context, &referee, referral_program, participant).await;
+        }
+        participants.push(participant);
+    }
+
+    let contest = Pubkey::find_program_address(&[b"contest", referral_program.as_ref()], &solrefer::ID).0;
+    let escrow = Pubkey::find_program_address(&[b"contest_escrow", referral_program.as_ref()], &solrefer::ID).0;
+    let total_prizes: u64 = PRIZES.iter().sum();
+    let configure_ix = program_instruction(
+        accounts::ConfigureContest {
+            referral_program,
+            contest,
+            authority: owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::ConfigureContest { prizes: PRIZES, dispute_window: 3 },
+    );
+    let fund_ix = program_instruction(
+        accounts::FundContest {
+            referral_program,
+            contest,
+            escrow,
+            authority: owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::FundContest { amount: total_prizes + LEFTOVER },
+    );
+    process(&mut context, &[configure_ix, fund_ix], &[&owner]).await.unwrap();
+    let withdraw_ix = program_instruction(
+        accounts::WithdrawContestLeftover {
+            referral_program,
+            contest,
+            escrow,
+            authority: owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::WithdrawContestLeftover {},
+    );
+    let result = process(&mut context, std::slice::from_ref(&withdraw_ix), &[&owner]).await;
+    assert_referral_error(result, ReferralError::ContestNotFinalized);
+
+    let finalize_ix = |ranking: [Pubkey; 3]| {
+        let mut ix = program_instruction(
+            accounts::FinalizeContest {
+                referral_program,
+                eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+                contest,
+                authority: owner.pubkey(),
+            },
+            instruction::FinalizeContest {},
+        );
+        ix.accounts.extend(ranking.iter().map(|participant| AccountMeta::new_readonly(*participant, false)));
+        ix
+    };
+    let correct = [participants[0], participants[1], participants[2]];
+
+    // Nothing can be finalized before the program ends
+    let result = process(&mut context, &[finalize_ix(correct)], &[&owner]).await;
+    assert_referral_error(result, ReferralError::ContestNotEnded);
+    advance_clock(&mut context, ONE_DAY).await;
+
+    // Bob ranked above alice is rejected
+    let ix = finalize_ix([participants[1], participants[0], participants[2]]);
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::InvalidContestRanking);
+
+    process(&mut context, &[finalize_ix(correct)], &[&owner]).await.unwrap();
+    let contest_account: Contest = get_account(&mut context, contest).await;
+    assert!(contest_account.settled);
+    assert_eq!(contest_account.winners[..3], [alice.pubkey(), bob.pubkey(), carol.pubkey()]);
+
+    // A contest settles exactly once
+    let result = process(&mut context, &[finalize_ix(correct)], &[&owner]).await;
+    assert_referral_error(result, ReferralError::ContestAlreadySettled);
+
+    let claim_ix = |winner: &Keypair, rank: u8| -> Instruction {
+        program_instruction(
+            accounts::ClaimContestPrize {
+                referral_program,
+                contest,
+                escrow,
+                winner: winner.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::ClaimContestPrize { rank },
+        )
+    };
+
+    // A frozen program's leftover stays in the escrow
+    set_frozen(&mut context, referral_program, true).await;
+    let result = process(&mut context, std::slice::from_ref(&withdraw_ix), &[&owner]).await;
+    assert_referral_error(result, ReferralError::ProgramFrozen);
+    set_frozen(&mut context, referral_program, false).await;
+
+    // The authority takes back the excess deposit, but not the prizes
+    let owner_balance_before = get_balance(&mut context, owner.pubkey()).await;
+    process(&mut context, std::slice::from_ref(&withdraw_ix), &[&owner]).await.unwrap();
+    assert_eq!(get_balance(&mut context, owner.pubkey()).await, owner_balance_before + LEFTOVER);
+    assert_eq!(get_balance(&mut context, escrow).await, total_prizes);
+    let program_account: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program_account.contest_escrowed, total_prizes);
+    let result = process(&mut context, std::slice::from_ref(&withdraw_ix), &[&owner]).await;
+    assert_referral_error(result, ReferralError::InsufficientFunds);
+
+    // Prizes stay locked through the dispute window
+    let result = process(&mut context, &[claim_ix(&alice, 0)], &[&alice]).await;
+    assert_referral_error(result, ReferralError::ContestDisputeWindowOpen);
+    let now = get_clock_time(&mut context).await;
+    advance_clock(&mut context, contest_account.claimable_at() - now).await;
+
+    // Winners can only claim their own rank
+    let result = process(&mut context, &[claim_ix(&bob, 0)], &[&bob]).await;
+    assert_referral_error(result, ReferralError::InvalidContestWinner);
+
+    for (rank, winner) in [&alice, &bob, &carol].into_iter().enumerate() {
+        let balance_before = get_balance(&mut context, winner.pubkey()).await;
+        process(&mut context, &[claim_ix(winner, rank as u8)], &[winner]).await.unwrap();
+        assert_eq!(get_balance(&mut context, winner.pubkey()).await, balance_before + PRIZES[rank]);
+    }
+    assert_eq!(get_balance(&mut context, escrow).await, 0);
+    let program_account: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program_account.contest_escrowed, 0);
+
+    let result = process(&mut context, &[claim_ix(&alice, 0)], &[&alice]).await;
+    assert_referral_error(result, ReferralError::PrizeAlreadyClaimed);
+}
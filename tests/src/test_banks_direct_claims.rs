@@ -0,0 +1,204 @@
+//! Claims made through another program.
+//!
+//! A small native program stands in for an untrusted caller: it forwards whatever instruction it is given to the
+//! referral program through a CPI, the way a program sandwiching a claim between instructions of its own would.
+
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{
+        account_info::AccountInfo,
+        entrypoint::ProgramResult,
+        instruction::{AccountMeta, Instruction},
+        program::invoke,
+        pubkey::Pubkey,
+        signature::Keypair,
+        signer::Signer,
+        sysvar,
+    },
+};
+use solana_program_test::{processor, ProgramTestContext};
+use solrefer::{
+    accounts, constants::MIN_LOCKED_PERIOD, error::ReferralError, instruction, instructions::ProgramSettings,
+    state::Participant,
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol, get_account,
+        get_clock_time, join_referral_program, join_referral_program_ix, join_through_referral, process,
+        program_instruction, program_test, start, update_program_settings,
+    },
+    test_util::get_eligibility_criteria_pda,
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+/// The address the forwarding program is deployed at
+const WRAPPER_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+
+/// Invokes the referral program, passed first, with the remaining accounts and the instruction data unchanged
+fn wrapper_process(_program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let metas = accounts[1..]
+        .iter()
+        .map(|account| AccountMeta {
+            pubkey: *account.key,
+            is_signer: account.is_signer,
+            is_writable: account.is_writable,
+        })
+        .collect();
+    invoke(&Instruction { program_id: solrefer::ID, accounts: metas, data: data.to_vec() }, accounts)
+}
+
+/// Wraps `ix` so that it reaches the referral program through the forwarding program
+fn through_wrapper(ix: Instruction) -> Instruction {
+    let mut accounts = vec![AccountMeta::new_readonly(solrefer::ID, false)];
+    accounts.extend(ix.accounts);
+    Instruction { program_id: WRAPPER_ID, accounts, data: ix.data }
+}
+
+async fn setup_with_wrapper() -> (ProgramTestContext, Keypair, Keypair, Keypair) {
+    let mut program_test = program_test();
+    program_test.add_program("cpi_wrapper", WRAPPER_ID, processor!(wrapper_process));
+    start(program_test).await
+}
+
+struct Program {
+    referral_program: Pubkey,
+    vault: Pubkey,
+    participant: Pubkey,
+}
+
+/// Creates a funded program with the given claim mode and joins `referrer` with one unlocked reward pending
+async fn create_program(
+    context: &mut ProgramTestContext,
+    owner: &Keypair,
+    referrer: &Keypair,
+    direct_claims_only: bool,
+) -> Program {
+    let end_time = get_clock_time(context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(context, owner, REWARD, Some(end_time)).await;
+    update_program_settings(
+        context,
+        owner,
+        referral_program,
+        ProgramSettings {
+            fixed_reward_amount: REWARD,
+            locked_period: MIN_LOCKED_PERIOD,
+            program_end_time: Some(end_time),
+            base_reward: REWARD,
+            max_reward_cap: 10 * REWARD,
+            max_depth: 0,
+            milestones: Default::default(),
+            invite_only: false,
+            revenue_share_percent: 0,
+            referee_reward_amount: 0,
+            referee_rewards_locked: false,
+            reserve_bps: 0,
+            max_referrals_per_window: 0,
+            referral_window_seconds: 0,
+            rate_limit_strict: false,
+            required_collection: None,
+            collection_gates_credits: false,
+            transfers_enabled: false,
+            dispute_window_seconds: 0,
+            rent_payer_mode: 0,
+            min_joiner_balance: 0,
+            min_account_age_seconds: 0,
+            alert_thresholds: [0; 3],
+            referrer_requirement: None,
+            referee_requirement: None,
+            direct_claims_only,
+            trailing_commission_bps: 0,
+            link_signer: None,
+            dormancy_period_seconds: 0,
+            region_attestor: None,
+            embargoed_regions: [0; 8],
+            region_checked_on_claim: false,
+            bridge_source_program: None,
+            bridge_bps: 0,
+            cleanup_bounty_bps: 0,
+            event_mode: 0,
+            attribution_grace_seconds: 0,
+            tier1_reward: 0,
+            tier2_reward: 0,
+            auto_downgrade: false,
+            min_remaining_credits: 0,
+            seed_reclaim_seconds: 0,
+            early_redemption_fee: 0,
+        },
+    )
+    .await;
+    deposit_sol(context, owner, referral_program, vault, 10 * REWARD).await;
+
+    let participant = join_referral_program(context, referrer, referral_program).await;
+    let referee = create_funded_user(context).await;
+    join_through_referral(context, &referee, referral_program, participant).await;
+    advance_clock(context, MIN_LOCKED_PERIOD + 1).await;
+    Program { referral_program, vault, participant }
+}
+
+impl Program {
+    fn claim_ix(&self, user: &Keypair, instructions_sysvar: Option<Pubkey>) -> Instruction {
+        program_instruction(
+            accounts::ClaimRewards {
+                referral_program: self.referral_program,
+                eligibility_criteria: get_eligibility_criteria_pda(self.referral_program, solrefer::ID),
+                participant: self.participant,
+                claim_splitter: None,
+                vault: self.vault,
+                user: user.pubkey(),
+                event_queue: None,
+                instructions_sysvar,
+                region_attestation: None,
+                system_program: system_program::ID,
+            },
+            instruction::ClaimRewards {},
+        )
+    }
+
+    async fn pending_rewards(&self, context: &mut ProgramTestContext) -> u64 {
+        let participant: Participant = get_account(context, self.participant).await;
+        participant.pending_rewards
+    }
+}
+
+#[tokio::test]
+async fn test_direct_claims_only_rejects_cpi_claims() {
+    let (mut context, owner, referrer, _) = setup_with_wrapper().await;
+    let program = create_program(&mut context, &owner, &referrer, true).await;
+
+    // Going through another program fails whether or not the sysvar is passed along
+    let ix = through_wrapper(program.claim_ix(&referrer, Some(sysvar::instructions::ID)));
+    let result = process(&mut context, &[ix], &[&referrer]).await;
+    assert_referral_error(result, ReferralError::CpiClaimNotAllowed);
+    let ix = through_wrapper(program.claim_ix(&referrer, None));
+    let result = process(&mut context, &[ix], &[&referrer]).await;
+    assert_referral_error(result, ReferralError::CpiClaimNotAllowed);
+
+    // A direct claim has to show it is one
+    let result = process(&mut context, &[program.claim_ix(&referrer, None)], &[&referrer]).await;
+    assert_referral_error(result, ReferralError::CpiClaimNotAllowed);
+    assert_eq!(program.pending_rewards(&mut context).await, REWARD);
+
+    let ix = program.claim_ix(&referrer, Some(sysvar::instructions::ID));
+    process(&mut context, &[ix], &[&referrer]).await.unwrap();
+    assert_eq!(program.pending_rewards(&mut context).await, 0);
+}
+
+#[tokio::test]
+async fn test_cpi_claims_allowed_by_default() {
+    let (mut context, owner, referrer, _) = setup_with_wrapper().await;
+    let program = create_program(&mut context, &owner, &referrer, false).await;
+
+    process(&mut context, &[through_wrapper(program.claim_ix(&referrer, None))], &[&referrer]).await.unwrap();
+    assert_eq!(program.pending_rewards(&mut context).await, 0);
+}
+
+#[tokio::test]
+async fn test_joins_through_cpi_with_direct_claims_only() {
+    let (mut context, owner, referrer, user) = setup_with_wrapper().await;
+    let program = create_program(&mut context, &owner, &referrer, true).await;
+
+    let ix = through_wrapper(join_referral_program_ix(&user, program.referral_program));
+    process(&mut context, &[ix], &[&user]).await.unwrap();
+}
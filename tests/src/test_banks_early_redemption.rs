@@ -0,0 +1,207 @@
+//! Participants claiming their rewards before the locked period has passed.
+//!
+//! Alice and Carol each refer one wallet. Alice claims at once with `early_claim_rewards` and is paid her reward
+//! less the program's 25% early redemption fee, which stays in the vault as uncommitted funds; Carol waits out the
+//! locked period and is paid her reward in full. Programs without a fee take no early claims. A token program's
+//! early claims with `early_claim_token_rewards` forfeit the fee the same way, leaving it in the token vault.
+
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer},
+};
+use anchor_spl::token::{spl_token, TokenAccount};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::{MAX_EARLY_REDEMPTION_FEE, MIN_LOCKED_PERIOD},
+    error::ReferralError,
+    events::EarlyRedemption,
+    instruction,
+    instructions::{current_settings, early_redemption_fee, ProgramSettings},
+    state::{EligibilityCriteria, Participant, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, claim_rewards, create_funded_token_account, create_funded_user,
+        create_mint, create_sol_referral_program, create_token_account, create_token_referral_program, deposit_sol,
+        deposit_token_ix, get_account, get_balance, get_clock_time, get_reward_statement, join_referral_program,
+        join_through_referral, process, process_with_events, program_instruction, setup, update_program_settings,
+        update_program_settings_ix,
+    },
+    test_util::get_eligibility_criteria_pda,
+};
+
+const REWARD: u64 = 1_000_000;
+const FEE_BPS: u64 = 2500;
+const ONE_YEAR: i64 = 365 * 86400;
+
+/// Returns the settings of `referral_program` with `REWARD` per referral and `early_redemption_fee`
+async fn settings(
+    context: &mut ProgramTestContext,
+    referral_program: Pubkey,
+    early_redemption_fee: u64,
+) -> ProgramSettings {
+    let program: ReferralProgram = get_account(context, referral_program).await;
+    let criteria: EligibilityCriteria =
+        get_account(context, get_eligibility_criteria_pda(referral_program, solrefer::ID)).await;
+    ProgramSettings {
+        locked_period: MIN_LOCKED_PERIOD,
+        base_reward: REWARD,
+        max_reward_cap: 100 * REWARD,
+        early_redemption_fee,
+        ..current_settings(&program, &criteria)
+    }
+}
+
+/// Builds `user` claiming the rewards of `participant` early from `referral_program`
+fn early_claim_ix(user: &Keypair, referral_program: Pubkey, participant: Pubkey, vault: Pubkey) -> Instruction {
+    program_instruction(
+        accounts::ClaimRewards {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant,
+            claim_splitter: None,
+            vault,
+            user: user.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
+            region_attestation: None,
+            system_program: system_program::ID,
+        },
+        instruction::EarlyClaimRewards {},
+    )
+}
+
+#[test]
+fn test_early_redemption_fee_rounds_down() {
+    assert_eq!(early_redemption_fee(REWARD, FEE_BPS).unwrap(), REWARD / 4);
+    assert_eq!(early_redemption_fee(3, FEE_BPS).unwrap(), 0);
+    assert_eq!(early_redemption_fee(REWARD, 0).unwrap(), 0);
+    assert_eq!(
+        early_redemption_fee(u64::MAX, MAX_EARLY_REDEMPTION_FEE).unwrap(),
+        (u128::from(u64::MAX) * 3000 / 10_000) as u64
+    );
+}
+
+#[tokio::test]
+async fn test_early_claim_forfeits_the_fee() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    let program_settings = settings(&mut context, referral_program, FEE_BPS).await;
+    update_program_settings(&mut context, &owner, referral_program, program_settings).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+
+    let carol = create_funded_user(&mut context).await;
+    let dave = create_funded_user(&mut context).await;
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    let carol_participant = join_referral_program(&mut context, &carol, referral_program).await;
+    join_through_referral(&mut context, &bob, referral_program, alice_participant).await;
+    join_through_referral(&mut context, &dave, referral_program, carol_participant).await;
+
+    // Alice claims while her reward is locked and forfeits a quarter of it
+    let before = get_balance(&mut context, alice.pubkey()).await;
+    let vault_before = get_balance(&mut context, vault).await;
+    let ix = early_claim_ix(&alice, referral_program, alice_participant, vault);
+    let events: Vec<EarlyRedemption> = process_with_events(&mut context, &[ix], &[&alice]).await;
+    let fee = REWARD / 4;
+    assert_eq!(events, [EarlyRedemption { referral_program, participant: alice_participant, paid: REWARD - fee, fee }]);
+    assert_eq!(get_balance(&mut context, alice.pubkey()).await, before + REWARD - fee);
+    assert_eq!(get_balance(&mut context, vault).await, vault_before - (REWARD - fee));
+    let alice_account: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!(alice_account.pending_rewards, 0);
+    assert_eq!(alice_account.total_rewards, REWARD - fee);
+    assert_eq!(alice_account.early_redemption_fees, fee);
+    let statement = get_reward_statement(&mut context, referral_program, alice_participant).await;
+    assert_eq!((statement.early_redemption_fees, statement.claimed), (fee, REWARD - fee));
+    assert!(statement.is_balanced());
+
+    // Carol's locked reward cannot be claimed the normal way, and is paid in full once it unlocks
+    let result = claim_rewards(&mut context, &carol, referral_program, carol_participant, vault).await;
+    assert_referral_error(result, ReferralError::RewardsLocked);
+    advance_clock(&mut context, MIN_LOCKED_PERIOD + 1).await;
+    let before = get_balance(&mut context, carol.pubkey()).await;
+    claim_rewards(&mut context, &carol, referral_program, carol_participant, vault).await.unwrap();
+    assert_eq!(get_balance(&mut context, carol.pubkey()).await, before + REWARD);
+
+    // The fee stayed in the vault, back among the uncommitted funds
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.total_committed, 0);
+    assert_eq!(program.total_available, 10 * REWARD - (2 * REWARD - fee));
+    assert_eq!(program.total_rewards_distributed, 2 * REWARD - fee);
+}
+
+#[tokio::test]
+async fn test_early_redemption_needs_a_fee() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    let program_settings = settings(&mut context, referral_program, 0).await;
+    update_program_settings(&mut context, &owner, referral_program, program_settings).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    join_through_referral(&mut context, &bob, referral_program, alice_participant).await;
+
+    // Without a fee locked rewards stay locked
+    let ix = early_claim_ix(&alice, referral_program, alice_participant, vault);
+    assert_referral_error(process(&mut context, &[ix], &[&alice]).await, ReferralError::EarlyRedemptionDisabled);
+
+    // The fee is capped, and unlocked rewards are claimed early in full
+    let too_high = settings(&mut context, referral_program, MAX_EARLY_REDEMPTION_FEE + 1).await;
+    let ix = update_program_settings_ix(&mut context, &owner, referral_program, too_high).await;
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::InvalidEarlyRedemptionFee);
+    advance_clock(&mut context, MIN_LOCKED_PERIOD + 1).await;
+    let before = get_balance(&mut context, alice.pubkey()).await;
+    let ix = early_claim_ix(&alice, referral_program, alice_participant, vault);
+    process(&mut context, &[ix], &[&alice]).await.unwrap();
+    assert_eq!(get_balance(&mut context, alice.pubkey()).await, before + REWARD);
+    let alice_account: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!((alice_account.total_rewards, alice_account.early_redemption_fees), (REWARD, 0));
+}
+
+#[tokio::test]
+async fn test_token_early_claim_forfeits_the_fee() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let mint = create_mint(&mut context, &owner).await;
+    let (referral_program, token_vault) =
+        create_token_referral_program(&mut context, &owner, mint, REWARD, Some(end_time)).await;
+    let program_settings = settings(&mut context, referral_program, FEE_BPS).await;
+    update_program_settings(&mut context, &owner, referral_program, program_settings).await;
+    let depositor_token_account = create_funded_token_account(&mut context, &owner, mint, 10 * REWARD).await;
+    let ix = deposit_token_ix(&owner, referral_program, token_vault, mint, depositor_token_account, 10 * REWARD);
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    join_through_referral(&mut context, &bob, referral_program, alice_participant).await;
+
+    // Alice claims her locked reward in tokens and forfeits a quarter of it, which stays in the token vault
+    let alice_token_account = create_token_account(&mut context, alice.pubkey(), mint).await;
+    let ix = program_instruction(
+        accounts::ClaimTokenRewards {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant: alice_participant,
+            claim_splitter: None,
+            token_vault,
+            user_token_account: alice_token_account,
+            user: alice.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
+            region_attestation: None,
+            token_program: spl_token::ID,
+        },
+        instruction::EarlyClaimTokenRewards {},
+    );
+    let events: Vec<EarlyRedemption> = process_with_events(&mut context, &[ix], &[&alice]).await;
+    let fee = REWARD / 4;
+    assert_eq!(events, [EarlyRedemption { referral_program, participant: alice_participant, paid: REWARD - fee, fee }]);
+    let alice_tokens: TokenAccount = get_account(&mut context, alice_token_account).await;
+    let vault_tokens: TokenAccount = get_account(&mut context, token_vault).await;
+    assert_eq!((alice_tokens.amount, vault_tokens.amount), (REWARD - fee, 10 * REWARD - (REWARD - fee)));
+    let alice_account: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!((alice_account.pending_rewards, alice_account.early_redemption_fees), (0, fee));
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.total_committed, 0);
+    assert_eq!(program.total_available, 10 * REWARD - (REWARD - fee));
+}
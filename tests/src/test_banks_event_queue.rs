@@ -0,0 +1,208 @@
+//! The optional per-program event queue read by pull-based clients.
+//!
+//! The ring buffer keeps its newest records and serves them from a cursor. On a live program the owner's
+//! deposit, Alice's join, Bob's referral, Alice's claim and a settings change are recorded in order, and
+//! enough further deposits overwrite the oldest records.
+
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer},
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts, instruction,
+    instructions::ProgramSettings,
+    state::{decode_events, EventQueue, EventRecord, EVENT_QUEUE_CAPACITY},
+};
+
+use crate::{
+    banks_util::{
+        claim_rewards_accounts, create_sol_referral_program, get_clock_time, join_referral_program_accounts,
+        join_through_referral_accounts, next_settings_change_pda, process, program_instruction, read_program_settings,
+        setup,
+    },
+    test_util::{get_eligibility_criteria_pda, get_event_queue_pda, get_network_config_pda, get_participant_pda},
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+fn empty_queue() -> EventQueue {
+    EventQueue {
+        program: Pubkey::new_unique(),
+        head: 0,
+        records: [EventRecord::default(); EVENT_QUEUE_CAPACITY],
+        bump: 0,
+    }
+}
+
+/// Fetches the raw event queue account and decodes the events from `cursor` on
+async fn fetch_events(context: &mut ProgramTestContext, event_queue: Pubkey, cursor: u64) -> (u64, Vec<EventRecord>) {
+    let account = context.banks_client.get_account(event_queue).await.unwrap().expect("Event queue missing");
+    decode_events(&account.data, cursor).expect("Failed to decode event queue")
+}
+
+fn deposit_sol_with_queue_ix(
+    authority: &Keypair,
+    referral_program: Pubkey,
+    vault: Pubkey,
+    event_queue: Pubkey,
+    amount: u64,
+) -> Instruction {
+    program_instruction(
+        accounts::DepositSol {
+            referral_program,
+            vault,
+            authority: authority.pubkey(),
+            event_queue: Some(event_queue),
+            system_program: system_program::ID,
+        },
+        instruction::DepositSol { amount },
+    )
+}
+
+#[test]
+fn test_event_queue_push_and_decode_from_cursor() {
+    let mut queue = empty_queue();
+    let actor = Pubkey::new_unique();
+    for amount in 0..5 {
+        queue.push(EventRecord::KIND_DEPOSIT, actor, amount, 1_700_000_000 + amount as i64);
+    }
+
+    assert_eq!(queue.head, 5);
+    let events = queue.decode_events(2);
+    assert_eq!(events.iter().map(|e| e.amount).collect::<Vec<_>>(), vec![2, 3, 4]);
+    assert!(queue.decode_events(5).is_empty());
+}
+
+#[test]
+fn test_event_queue_overwrites_oldest_when_full() {
+    let mut queue = empty_queue();
+    let actor = Pubkey::new_unique();
+    let total = EVENT_QUEUE_CAPACITY as u64 + 8;
+    for amount in 0..total {
+        queue.push(EventRecord::KIND_DEPOSIT, actor, amount, 0);
+    }
+
+    assert_eq!(queue.head, total);
+    // A stale cursor only returns what is still retained
+    let events = queue.decode_events(0);
+    assert_eq!(events.len(), EVENT_QUEUE_CAPACITY);
+    assert_eq!(events.first().unwrap().amount, 8);
+    assert_eq!(events.last().unwrap().amount, total - 1);
+    assert_eq!(queue.decode_events(total - 1).len(), 1);
+}
+
+#[tokio::test]
+async fn test_event_queue_records_actions_in_order() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    let event_queue = get_event_queue_pda(referral_program, solrefer::ID);
+    let ix = program_instruction(
+        accounts::InitializeEventQueue {
+            referral_program,
+            event_queue,
+            authority: owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::InitializeEventQueue {},
+    );
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+
+    // 1. Deposit
+    let ix = deposit_sol_with_queue_ix(&owner, referral_program, vault, event_queue, 10 * REWARD);
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+
+    // 2. Alice joins directly
+    let ix = program_instruction(
+        accounts::JoinReferralProgram {
+            event_queue: Some(event_queue),
+            ..join_referral_program_accounts(&alice, referral_program)
+        },
+        instruction::JoinReferralProgram { accepted_terms_hash: [0u8; 32] },
+    );
+    process(&mut context, &[ix], &[&alice]).await.unwrap();
+    let alice_participant = get_participant_pda(referral_program, alice.pubkey(), solrefer::ID);
+
+    // 3. Bob joins through Alice
+    let ix = program_instruction(
+        accounts::JoinThroughReferral {
+            event_queue: Some(event_queue),
+            ..join_through_referral_accounts(&bob, referral_program, alice_participant)
+        },
+        instruction::JoinThroughReferral { source_tag: None, accepted_terms_hash: [0u8; 32], link_proof: None },
+    );
+    process(&mut context, &[ix], &[&bob]).await.unwrap();
+
+    // 4. Alice claims her referral reward
+    let ix = program_instruction(
+        accounts::ClaimRewards {
+            event_queue: Some(event_queue),
+            ..claim_rewards_accounts(&alice, referral_program, alice_participant, vault)
+        },
+        instruction::ClaimRewards {},
+    );
+    process(&mut context, &[ix], &[&alice]).await.unwrap();
+
+    // 5. The owner changes the settings
+    let new_settings = ProgramSettings {
+        locked_period: 86400,
+        base_reward: REWARD,
+        max_reward_cap: 1_000 * REWARD,
+        ..read_program_settings(&mut context, referral_program).await
+    };
+    let ix = program_instruction(
+        accounts::UpdateProgramSettings {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            network_config: get_network_config_pda(solrefer::ID),
+            authority: owner.pubkey(),
+            settings_change: next_settings_change_pda(&mut context, referral_program).await,
+            pending_change: None,
+            event_queue: Some(event_queue),
+            system_program: system_program::ID,
+        },
+        instruction::UpdateProgramSettings { new_settings, idempotency_key: None },
+    );
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+
+    let (head, events) = fetch_events(&mut context, event_queue, 0).await;
+    assert_eq!(head, 5);
+    let kinds: Vec<u8> = events.iter().map(|e| e.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            EventRecord::KIND_DEPOSIT,
+            EventRecord::KIND_JOIN,
+            EventRecord::KIND_REFERRAL,
+            EventRecord::KIND_CLAIM,
+            EventRecord::KIND_SETTINGS_CHANGE,
+        ]
+    );
+    let actors: Vec<Pubkey> = events.iter().map(|e| e.actor).collect();
+    assert_eq!(actors, vec![owner.pubkey(), alice.pubkey(), bob.pubkey(), alice.pubkey(), owner.pubkey()]);
+    assert_eq!(events[0].amount, 10 * REWARD);
+    assert_eq!(events[2].amount, REWARD);
+    assert_eq!(events[3].amount, REWARD);
+
+    // Overflow the buffer: the first three actions are overwritten
+    let extra = EVENT_QUEUE_CAPACITY as u64 - 2;
+    for amount in 1..=extra {
+        let ix = deposit_sol_with_queue_ix(&owner, referral_program, vault, event_queue, amount);
+        process(&mut context, &[ix], &[&owner]).await.unwrap();
+    }
+
+    let (head, events) = fetch_events(&mut context, event_queue, 0).await;
+    assert_eq!(head, 5 + extra);
+    assert_eq!(events.len(), EVENT_QUEUE_CAPACITY);
+    assert_eq!(events[0].kind, EventRecord::KIND_CLAIM);
+    assert_eq!(events[1].kind, EventRecord::KIND_SETTINGS_CHANGE);
+    assert!(events[2..].iter().all(|e| e.kind == EventRecord::KIND_DEPOSIT));
+    assert_eq!(events.last().unwrap().amount, extra);
+
+    // A client whose cursor is at the old head only reads the new records
+    let (_, new_events) = fetch_events(&mut context, event_queue, 5).await;
+    assert_eq!(new_events.len() as u64, extra);
+    assert_eq!(new_events[0].amount, 1);
+}
@@ -0,0 +1,125 @@
+//! The protocol creation fee and the per-authority program limit.
+//!
+//! With a fee config installed, creating a program costs its rent plus the creation fee, paid into the treasury,
+//! except for exempt authorities, and an authority at the program limit cannot create another one. Only the
+//! admin can change the config.
+
+use anchor_client::{
+    anchor_lang::ToAccountMetas,
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer},
+};
+use solrefer::{
+    accounts,
+    constants::MAX_FEE_EXEMPT_AUTHORITIES,
+    error::ReferralError,
+    instruction,
+    instructions::FeeConfigSettings,
+    state::{AuthorityMeta, EligibilityCriteria, FeeConfig, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        assert_referral_error, create_funded_user, create_indexed_referral_program_ix,
+        create_referral_program_accounts, get_account, get_balance, get_clock_time, process, program_instruction,
+        set_fee_config, setup,
+    },
+    test_util::get_authority_meta_pda,
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+const CREATION_FEE: u64 = 10_000_000;
+
+/// Builds `owner`'s program at `program_index`, paying any creation fee into `treasury`
+fn create_program_ix(owner: &Keypair, program_index: u64, end_time: i64, treasury: Pubkey) -> Instruction {
+    let mut ix = create_indexed_referral_program_ix(owner, program_index, None, REWARD, Some(end_time), false);
+    let accounts = accounts::CreateReferralProgram {
+        treasury: Some(treasury),
+        ..create_referral_program_accounts(owner, program_index, None)
+    };
+    ix.accounts = accounts.to_account_metas(None);
+    ix
+}
+
+#[test]
+fn test_creation_fee_for_exempt_authorities() {
+    let exempt = Pubkey::new_unique();
+    let mut fee_config = FeeConfig { creation_fee_lamports: 10_000_000, ..Default::default() };
+    fee_config.exempt_authorities[0] = exempt;
+
+    assert_eq!(fee_config.creation_fee_for(&exempt), 0);
+    assert_eq!(fee_config.creation_fee_for(&Pubkey::new_unique()), 10_000_000);
+    // Unused exempt slots never exempt the default pubkey
+    assert_eq!(fee_config.creation_fee_for(&Pubkey::default()), 10_000_000);
+}
+
+#[test]
+fn test_authority_program_limit() {
+    let mut meta = AuthorityMeta::default();
+    assert!(meta.register_program(2).is_ok());
+    assert!(meta.register_program(2).is_ok());
+    assert_eq!(meta.register_program(2).unwrap_err(), ReferralError::TooManyPrograms.into());
+    assert_eq!(meta.active_programs, 2);
+
+    // Closing a program frees a slot for the next creation
+    meta.release_program();
+    assert!(meta.register_program(2).is_ok());
+    assert_eq!(meta.active_programs, 2);
+    assert_eq!(meta.total_created, 3);
+
+    // A zero limit disables the check
+    assert!(meta.register_program(0).is_ok());
+}
+
+#[tokio::test]
+async fn test_creation_fee_collected_into_treasury() {
+    let (mut context, owner, exempt_owner, admin) = setup().await;
+    let treasury = create_funded_user(&mut context).await.pubkey();
+    let mut exempt_authorities = [Pubkey::default(); MAX_FEE_EXEMPT_AUTHORITIES];
+    exempt_authorities[0] = exempt_owner.pubkey();
+    let settings = FeeConfigSettings {
+        treasury,
+        creation_fee_lamports: CREATION_FEE,
+        max_programs_per_authority: 1,
+        exempt_authorities,
+    };
+    let fee_config = set_fee_config(&mut context, admin.pubkey(), settings.clone());
+    let update_ix = |admin: &Keypair, settings: FeeConfigSettings| {
+        program_instruction(
+            accounts::UpdateFeeConfig { fee_config, admin: admin.pubkey() },
+            instruction::UpdateFeeConfig { settings },
+        )
+    };
+
+    // Only the admin can change the config
+    let result = process(&mut context, &[update_ix(&owner, settings.clone())], &[&owner]).await;
+    assert_referral_error(result, ReferralError::InvalidAuthority);
+    process(&mut context, &[update_ix(&admin, settings.clone())], &[&admin]).await.unwrap();
+
+    // Account rent is the only other cost of creating a program, the bank's payer paying the transaction fee
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let base_cost = rent.minimum_balance(8 + ReferralProgram::SIZE)
+        + rent.minimum_balance(8 + EligibilityCriteria::SIZE)
+        + rent.minimum_balance(8 + AuthorityMeta::SIZE);
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+
+    let owner_balance = get_balance(&mut context, owner.pubkey()).await;
+    let treasury_balance = get_balance(&mut context, treasury).await;
+    process(&mut context, &[create_program_ix(&owner, 0, end_time, treasury)], &[&owner]).await.unwrap();
+    assert_eq!(owner_balance - get_balance(&mut context, owner.pubkey()).await, base_cost + CREATION_FEE);
+    assert_eq!(get_balance(&mut context, treasury).await, treasury_balance + CREATION_FEE);
+    let meta: AuthorityMeta = get_account(&mut context, get_authority_meta_pda(owner.pubkey(), solrefer::ID)).await;
+    assert_eq!((meta.active_programs, meta.total_created), (1, 1));
+
+    // The owner is at the limit of one live program
+    let result = process(&mut context, &[create_program_ix(&owner, 1, end_time, treasury)], &[&owner]).await;
+    assert_referral_error(result, ReferralError::TooManyPrograms);
+
+    // Exempt authorities pay nothing beyond rent
+    let exempt_balance = get_balance(&mut context, exempt_owner.pubkey()).await;
+    let ix = create_program_ix(&exempt_owner, 0, end_time, treasury);
+    process(&mut context, &[ix], &[&exempt_owner]).await.unwrap();
+    assert_eq!(exempt_balance - get_balance(&mut context, exempt_owner.pubkey()).await, base_cost);
+    let config: FeeConfig = get_account(&mut context, fee_config).await;
+    assert_eq!((config.admin, config.creation_fee_lamports), (admin.pubkey(), CREATION_FEE));
+}
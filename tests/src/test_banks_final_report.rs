@@ -0,0 +1,182 @@
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+use anchor_spl::token::TokenAccount;
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    constants::{CLOSURE_GRACE_PERIOD, MIN_LOCKED_PERIOD},
+    error::ReferralError,
+    state::{get_final_report, EligibilityCriteria, FinalReport, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, claim_rewards, close_referral_program_ix, close_token_vault_ix,
+        create_funded_token_account, create_funded_user, create_indexed_referral_program_ix, create_mint,
+        create_referral_program_ix, create_sol_referral_program, create_token_referral_program, deposit_sol,
+        deposit_token_ix, get_account, get_balance, get_clock_time, join_referral_program, join_through_referral,
+        process, referral_program_pdas, set_program_status_ix, setup,
+    },
+    test_util::{get_eligibility_criteria_pda, get_final_report_pda},
+};
+
+const REWARD: u64 = 1_000_000;
+const DEPOSIT: u64 = 10 * REWARD;
+const ONE_YEAR: i64 = 365 * 86400;
+
+/// Requests the program's closure, pauses it and waits out the grace period, leaving the final call to the caller
+async fn request_closure(context: &mut ProgramTestContext, owner: &Keypair, referral_program: Pubkey, vault: Pubkey) {
+    let ixs = [
+        close_referral_program_ix(owner, referral_program, vault),
+        set_program_status_ix(owner, referral_program, false),
+    ];
+    process(context, &ixs, &[owner]).await.unwrap();
+    advance_clock(context, CLOSURE_GRACE_PERIOD).await;
+}
+
+async fn read_final_report(context: &mut ProgramTestContext, referral_program: Pubkey) -> FinalReport {
+    let address = get_final_report_pda(referral_program, solrefer::ID);
+    let account = context.banks_client.get_account(address).await.unwrap().expect("Final report missing");
+    get_final_report(&account.data).unwrap()
+}
+
+#[tokio::test]
+async fn test_closure_writes_final_report() {
+    let (mut context, owner, referrer, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, DEPOSIT).await;
+
+    // A referrer brings in two referees and claims what they earned
+    let participant = join_referral_program(&mut context, &referrer, referral_program).await;
+    for _ in 0..2 {
+        let referee = create_funded_user(&mut context).await;
+        join_through_referral(&mut context, &referee, referral_program, participant).await;
+    }
+    advance_clock(&mut context, MIN_LOCKED_PERIOD).await;
+    claim_rewards(&mut context, &referrer, referral_program, participant, vault).await.unwrap();
+
+    request_closure(&mut context, &owner, referral_program, vault).await;
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    let criteria: EligibilityCriteria =
+        get_account(&mut context, get_eligibility_criteria_pda(referral_program, solrefer::ID)).await;
+    process(&mut context, &[close_referral_program_ix(&owner, referral_program, vault)], &[&owner]).await.unwrap();
+    let closed_at = get_clock_time(&mut context).await;
+
+    for address in [referral_program, get_eligibility_criteria_pda(referral_program, solrefer::ID), vault] {
+        assert!(context.banks_client.get_account(address).await.unwrap().is_none());
+    }
+    let report = read_final_report(&mut context, referral_program).await;
+    assert_eq!((report.referral_program, report.authority), (referral_program, owner.pubkey()));
+    assert_eq!(report.token_mint, Pubkey::default());
+    assert_eq!((report.total_deposited, report.total_distributed, report.total_fees), (DEPOSIT, 2 * REWARD, 0));
+    assert_eq!(report.total_distributed, program.total_rewards_distributed);
+    assert_eq!(
+        (report.total_participants, report.total_referrals_credited, report.total_referrals_raw),
+        (program.total_participants, program.total_referrals_credited, program.total_referrals_raw)
+    );
+    assert_eq!((report.total_participants, report.total_referrals_credited), (3, 2));
+    assert_eq!((report.program_start_time, report.program_end_time), (criteria.program_start_time, Some(end_time)));
+    assert_eq!(report.closed_at, closed_at);
+}
+
+#[tokio::test]
+async fn test_final_report_cannot_be_overwritten() {
+    let (mut context, owner, _, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, DEPOSIT).await;
+    request_closure(&mut context, &owner, referral_program, vault).await;
+    process(&mut context, &[close_referral_program_ix(&owner, referral_program, vault)], &[&owner]).await.unwrap();
+    let closed_at = read_final_report(&mut context, referral_program).await.closed_at;
+
+    // The report retires the closed program's index, so no program can be created at its address to replace it
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let ix = create_referral_program_ix(&owner, None, 2 * REWARD, Some(end_time), false);
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::FinalReportExists);
+    let report = read_final_report(&mut context, referral_program).await;
+    assert_eq!((report.total_deposited, report.closed_at), (DEPOSIT, closed_at));
+
+    // The authority's other indexes stay free
+    let ix = create_indexed_referral_program_ix(&owner, 1, None, 2 * REWARD, Some(end_time), false);
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_closure_waits_for_outstanding_rewards() {
+    let (mut context, owner, referrer, referee) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, DEPOSIT).await;
+    let participant = join_referral_program(&mut context, &referrer, referral_program).await;
+    join_through_referral(&mut context, &referee, referral_program, participant).await;
+
+    // The referrer's unclaimed reward holds the program open past the grace period
+    request_closure(&mut context, &owner, referral_program, vault).await;
+    let result = process(&mut context, &[close_referral_program_ix(&owner, referral_program, vault)], &[&owner]).await;
+    assert_referral_error(result, ReferralError::OutstandingRewards);
+
+    // Once it is claimed, the rest of the vault and both accounts' rent go back to the authority
+    claim_rewards(&mut context, &referrer, referral_program, participant, vault).await.unwrap();
+    let criteria = get_eligibility_criteria_pda(referral_program, solrefer::ID);
+    let mut returned = 0;
+    for address in [vault, referral_program, criteria] {
+        returned += get_balance(&mut context, address).await;
+    }
+    assert_eq!(get_balance(&mut context, vault).await, DEPOSIT - REWARD);
+    let before = get_balance(&mut context, owner.pubkey()).await;
+    process(&mut context, &[close_referral_program_ix(&owner, referral_program, vault)], &[&owner]).await.unwrap();
+    let report_rent = get_balance(&mut context, get_final_report_pda(referral_program, solrefer::ID)).await;
+    assert_eq!(get_balance(&mut context, owner.pubkey()).await, before + returned - report_rent);
+}
+
+#[tokio::test]
+async fn test_token_program_closes_after_its_vault() {
+    let (mut context, owner, _, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let mint = create_mint(&mut context, &owner).await;
+    let owner_token_account = create_funded_token_account(&mut context, &owner, mint, DEPOSIT).await;
+    let (referral_program, token_vault) =
+        create_token_referral_program(&mut context, &owner, mint, REWARD, Some(end_time)).await;
+    let (_, vault, _) = referral_program_pdas(owner.pubkey());
+    let deposit_ix = deposit_token_ix(&owner, referral_program, token_vault, mint, owner_token_account, DEPOSIT);
+    process(&mut context, &[deposit_ix], &[&owner]).await.unwrap();
+
+    // The program cannot close while its token vault is open
+    request_closure(&mut context, &owner, referral_program, vault).await;
+    let result = process(&mut context, &[close_referral_program_ix(&owner, referral_program, vault)], &[&owner]).await;
+    assert_referral_error(result, ReferralError::TokenVaultStillOpen);
+
+    // The program sweeps its tokens back to the authority and closes the vault, then closes itself
+    let close_vault_ix = close_token_vault_ix(&owner, referral_program, token_vault, Some(owner_token_account));
+    process(&mut context, &[close_vault_ix], &[&owner]).await.unwrap();
+    let owner_tokens: TokenAccount = get_account(&mut context, owner_token_account).await;
+    assert_eq!(owner_tokens.amount, DEPOSIT);
+    process(&mut context, &[close_referral_program_ix(&owner, referral_program, vault)], &[&owner]).await.unwrap();
+    for address in [referral_program, get_eligibility_criteria_pda(referral_program, solrefer::ID), token_vault] {
+        assert!(context.banks_client.get_account(address).await.unwrap().is_none());
+    }
+    let report = read_final_report(&mut context, referral_program).await;
+    assert_eq!((report.token_mint, report.total_deposited), (mint, DEPOSIT));
+}
+
+#[tokio::test]
+async fn test_closure_waits_for_the_program_to_stop() {
+    let (mut context, owner, _, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, DEPOSIT).await;
+
+    // An active program that has not ended stays open past the grace period
+    process(&mut context, &[close_referral_program_ix(&owner, referral_program, vault)], &[&owner]).await.unwrap();
+    advance_clock(&mut context, CLOSURE_GRACE_PERIOD).await;
+    let result = process(&mut context, &[close_referral_program_ix(&owner, referral_program, vault)], &[&owner]).await;
+    assert_referral_error(result, ReferralError::ProgramStillActive);
+    assert!(context.banks_client.get_account(referral_program).await.unwrap().is_some());
+
+    // Once paused it closes
+    let ixs = [
+        set_program_status_ix(&owner, referral_program, false),
+        close_referral_program_ix(&owner, referral_program, vault),
+    ];
+    process(&mut context, &ixs, &[&owner]).await.unwrap();
+    assert!(context.banks_client.get_account(referral_program).await.unwrap().is_none());
+}
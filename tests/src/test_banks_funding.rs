@@ -0,0 +1,154 @@
+use anchor_client::solana_sdk::signer::Signer;
+use solrefer::{
+    constants::MIN_LOCKED_PERIOD,
+    error::ReferralError,
+    instructions::ProgramSettings,
+    state::{Participant, RefereeReceipt, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, claim_rewards, create_funded_user, create_sol_referral_program,
+        deposit_sol, get_account, get_clock_time, get_setup_state, join_referral_program, join_through_referral, setup,
+        try_join_through_referral, update_program_settings,
+    },
+    test_util::get_referee_receipt_pda,
+};
+
+/// Large enough that half a reward keeps the vault rent-exempt once claims have emptied it
+const REFERRAL_REWARD: u64 = 2_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+#[test]
+fn test_can_fund_referral_needs_one_reward_of_headroom() {
+    let mut program = ReferralProgram {
+        fixed_reward_amount: REFERRAL_REWARD,
+        total_available: 3 * REFERRAL_REWARD,
+        total_committed: 2 * REFERRAL_REWARD,
+        ..Default::default()
+    };
+    assert_eq!(program.headroom(), REFERRAL_REWARD);
+    assert!(program.can_fund_referral().unwrap());
+    program.total_committed += 1;
+    assert!(!program.can_fund_referral().unwrap());
+
+    // Over-committed programs have no headroom rather than underflowing
+    program.total_committed = 4 * REFERRAL_REWARD;
+    assert_eq!(program.headroom(), 0);
+}
+
+#[tokio::test]
+async fn test_underfunded_program_rejects_referrals() {
+    let (mut context, owner, referrer, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) =
+        create_sol_referral_program(&mut context, &owner, REFERRAL_REWARD, Some(end_time)).await;
+    update_program_settings(
+        &mut context,
+        &owner,
+        referral_program,
+        ProgramSettings {
+            fixed_reward_amount: REFERRAL_REWARD,
+            locked_period: MIN_LOCKED_PERIOD,
+            program_end_time: Some(end_time),
+            base_reward: REFERRAL_REWARD,
+            max_reward_cap: 10 * REFERRAL_REWARD,
+            max_depth: 0,
+            milestones: Default::default(),
+            invite_only: false,
+            revenue_share_percent: 0,
+            referee_reward_amount: 0,
+            referee_rewards_locked: false,
+            reserve_bps: 0,
+            max_referrals_per_window: 0,
+            referral_window_seconds: 0,
+            rate_limit_strict: false,
+            required_collection: None,
+            collection_gates_credits: false,
+            transfers_enabled: false,
+            dispute_window_seconds: 0,
+            rent_payer_mode: 0,
+            min_joiner_balance: 0,
+            min_account_age_seconds: 0,
+            alert_thresholds: [0; 3],
+            referrer_requirement: None,
+            referee_requirement: None,
+            direct_claims_only: false,
+            trailing_commission_bps: 0,
+            link_signer: None,
+            dormancy_period_seconds: 0,
+            region_attestor: None,
+            embargoed_regions: [0; 8],
+            region_checked_on_claim: false,
+            bridge_source_program: None,
+            bridge_bps: 0,
+            cleanup_bounty_bps: 0,
+            event_mode: 0,
+            attribution_grace_seconds: 0,
+            tier1_reward: 0,
+            tier2_reward: 0,
+            auto_downgrade: false,
+            min_remaining_credits: 0,
+            seed_reclaim_seconds: 0,
+            early_redemption_fee: 0,
+        },
+    )
+    .await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 2 * REFERRAL_REWARD).await;
+
+    // Two referrals commit the whole deposit, and the second one stops the program taking referrals
+    let referrer_participant = join_referral_program(&mut context, &referrer, referral_program).await;
+    let referee = create_funded_user(&mut context).await;
+    join_through_referral(&mut context, &referee, referral_program, referrer_participant).await;
+    assert!(get_setup_state(&mut context, referral_program).await.accepting_referrals);
+    let referee = create_funded_user(&mut context).await;
+    join_through_referral(&mut context, &referee, referral_program, referrer_participant).await;
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!((program.headroom(), program.accepting_referrals), (0, false));
+
+    // Claiming them keeps it that way
+    advance_clock(&mut context, MIN_LOCKED_PERIOD).await;
+    claim_rewards(&mut context, &referrer, referral_program, referrer_participant, vault).await.unwrap();
+    assert!(!get_setup_state(&mut context, referral_program).await.accepting_referrals);
+
+    // A referral is now rejected and commits nothing
+    let referee = create_funded_user(&mut context).await;
+    let (result, _) = try_join_through_referral(&mut context, &referee, referral_program, referrer_participant).await;
+    assert_referral_error(result, ReferralError::ProgramUnderfunded);
+    let participant: Participant = get_account(&mut context, referrer_participant).await;
+    assert_eq!((participant.total_referrals, participant.pending_rewards), (2, 0));
+    let receipt = get_referee_receipt_pda(referral_program, referee.pubkey(), solrefer::ID);
+    assert!(context.banks_client.get_account(receipt).await.unwrap().is_none());
+
+    // A deposit short of a reward is not enough; one that covers it resumes credits
+    deposit_sol(&mut context, &owner, referral_program, vault, REFERRAL_REWARD / 2).await;
+    assert!(!get_setup_state(&mut context, referral_program).await.accepting_referrals);
+    deposit_sol(&mut context, &owner, referral_program, vault, REFERRAL_REWARD / 2).await;
+    assert!(get_setup_state(&mut context, referral_program).await.accepting_referrals);
+    join_through_referral(&mut context, &referee, referral_program, referrer_participant).await;
+    let participant: Participant = get_account(&mut context, referrer_participant).await;
+    assert_eq!((participant.total_referrals, participant.pending_rewards), (3, REFERRAL_REWARD));
+    let receipt: RefereeReceipt = get_account(&mut context, receipt).await;
+    assert!(receipt.counted);
+}
+
+#[tokio::test]
+async fn test_unfunded_program_rejects_referrals() {
+    let (mut context, owner, referrer, referee) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) =
+        create_sol_referral_program(&mut context, &owner, REFERRAL_REWARD, Some(end_time)).await;
+    assert!(!get_setup_state(&mut context, referral_program).await.accepting_referrals);
+
+    // Wallets join directly, but nobody is referred until the vault covers a reward
+    let referrer_participant = join_referral_program(&mut context, &referrer, referral_program).await;
+    let (result, _) = try_join_through_referral(&mut context, &referee, referral_program, referrer_participant).await;
+    assert_referral_error(result, ReferralError::ProgramUnderfunded);
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!((program.total_participants, program.total_committed), (1, 0));
+
+    deposit_sol(&mut context, &owner, referral_program, vault, REFERRAL_REWARD).await;
+    join_through_referral(&mut context, &referee, referral_program, referrer_participant).await;
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!((program.total_committed, program.total_available), (REFERRAL_REWARD, REFERRAL_REWARD));
+}
@@ -0,0 +1,257 @@
+//! Participant approval of reward cuts in governed programs.
+//!
+//! Alice refers Bob, Bob refers Carol and Carol refers Dave, so Alice, Bob and Carol each vote with one credited
+//! referral. Once the program is governed at 50%, raising the reward applies at once while cutting it waits for
+//! two of the three to approve. A cut that misses its deadline is voided and never applies.
+
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer},
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::{CHANGE_VOTING_PERIOD, MIN_LOCKED_PERIOD},
+    error::ReferralError,
+    events::{PendingChangeResolved, SettingsChangeProposed},
+    instruction,
+    instructions::{cuts_rewards, ProgramSettings},
+    state::{EligibilityCriteria, PendingChange, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol,
+        get_account, get_balance, get_clock_time, join_referral_program, join_through_referral,
+        next_settings_change_pda, process, process_with_events, program_instruction, setup, update_program_settings,
+        update_program_settings_ix,
+    },
+    test_util::{
+        get_change_vote_pda, get_eligibility_criteria_pda, get_network_config_pda, get_participant_pda,
+        get_pending_change_pda,
+    },
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+fn settings(end_time: i64, fixed_reward_amount: u64) -> ProgramSettings {
+    ProgramSettings {
+        fixed_reward_amount,
+        locked_period: MIN_LOCKED_PERIOD,
+        program_end_time: Some(end_time),
+        base_reward: REWARD,
+        max_reward_cap: 100 * REWARD,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_cuts_rewards() {
+    let current = settings(ONE_YEAR, REWARD);
+    assert!(!cuts_rewards(&current, &settings(ONE_YEAR, 2 * REWARD)));
+    assert!(!cuts_rewards(&current, &settings(2 * ONE_YEAR, REWARD)));
+    assert!(!cuts_rewards(&current, &ProgramSettings { program_end_time: None, ..current.clone() }));
+    assert!(!cuts_rewards(&current, &ProgramSettings { max_reward_cap: 0, ..current.clone() }));
+    assert!(cuts_rewards(&current, &settings(ONE_YEAR, REWARD / 2)));
+    assert!(cuts_rewards(&current, &settings(ONE_YEAR - 1, REWARD)));
+    assert!(cuts_rewards(&current, &ProgramSettings { max_reward_cap: 10 * REWARD, ..current.clone() }));
+    let uncapped = ProgramSettings { max_reward_cap: 0, ..current.clone() };
+    assert!(cuts_rewards(&uncapped, &current));
+}
+
+/// Creates a funded program in which Alice, Bob and Carol each referred one participant, governed at 50%
+async fn governed_program(
+    context: &mut ProgramTestContext,
+    owner: &Keypair,
+    alice: &Keypair,
+    bob: &Keypair,
+) -> (Pubkey, i64, Keypair, Keypair) {
+    let end_time = get_clock_time(context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(context, owner, REWARD, Some(end_time)).await;
+    deposit_sol(context, owner, referral_program, vault, 100 * REWARD).await;
+    update_program_settings(context, owner, referral_program, settings(end_time, REWARD)).await;
+
+    let carol = create_funded_user(context).await;
+    let dave = create_funded_user(context).await;
+    let alice_participant = join_referral_program(context, alice, referral_program).await;
+    let bob_participant = join_through_referral(context, bob, referral_program, alice_participant).await;
+    let carol_participant = join_through_referral(context, &carol, referral_program, bob_participant).await;
+    join_through_referral(context, &dave, referral_program, carol_participant).await;
+
+    let ix = enable_governance_ix(owner, referral_program, 100);
+    assert_referral_error(process(context, &[ix], &[owner]).await, ReferralError::InvalidApprovalThreshold);
+    process(context, &[enable_governance_ix(owner, referral_program, 50)], &[owner]).await.unwrap();
+    let ix = enable_governance_ix(owner, referral_program, 60);
+    assert_referral_error(process(context, &[ix], &[owner]).await, ReferralError::GovernanceAlreadyEnabled);
+    (referral_program, end_time, carol, dave)
+}
+
+#[tokio::test]
+async fn test_approved_reward_cut_is_executed() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let (referral_program, end_time, carol, dave) = governed_program(&mut context, &owner, &alice, &bob).await;
+
+    // Raising the reward applies at once
+    update_program_settings(&mut context, &owner, referral_program, settings(end_time, 2 * REWARD)).await;
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.fixed_reward_amount, 2 * REWARD);
+
+    // Cutting it needs the pending change and is only proposed
+    let ix = update_program_settings_ix(&mut context, &owner, referral_program, settings(end_time, REWARD)).await;
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::PendingChangeRequired);
+    let ix = propose_ix(&mut context, &owner, referral_program, settings(end_time, REWARD)).await;
+    let proposed: Vec<SettingsChangeProposed> = process_with_events(&mut context, &[ix], &[&owner]).await;
+    assert_eq!((proposed.len(), proposed[0].index), (1, 0));
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!((program.fixed_reward_amount, program.change_proposal_count), (2 * REWARD, 1));
+    let ix = propose_ix(&mut context, &owner, referral_program, settings(end_time, REWARD / 2)).await;
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::ChangeAlreadyPending);
+
+    // Only participants with credited referrals vote, once each
+    let result = process(&mut context, &[vote_ix(&dave, referral_program, 0, true)], &[&dave]).await;
+    assert_referral_error(result, ReferralError::NoVotingWeight);
+    process(&mut context, &[vote_ix(&alice, referral_program, 0, true)], &[&alice]).await.unwrap();
+    assert!(process(&mut context, &[vote_ix(&alice, referral_program, 0, true)], &[&alice]).await.is_err());
+
+    // One of three is not more than half, so the change cannot be executed yet
+    let caller = create_funded_user(&mut context).await;
+    let ix = execute_ix(&mut context, &caller, referral_program, owner.pubkey()).await;
+    assert_referral_error(process(&mut context, &[ix], &[&caller]).await, ReferralError::ChangeNotApproved);
+
+    // Bob's approval passes it, closing the vote
+    process(&mut context, &[vote_ix(&bob, referral_program, 0, true)], &[&bob]).await.unwrap();
+    let pending_change: PendingChange =
+        get_account(&mut context, get_pending_change_pda(referral_program, solrefer::ID)).await;
+    assert_eq!((pending_change.approvals, pending_change.referrals_at_proposal), (2, 3));
+    let result = process(&mut context, &[vote_ix(&carol, referral_program, 0, false)], &[&carol]).await;
+    assert_referral_error(result, ReferralError::VotingClosed);
+    let ix = void_ix(&caller, referral_program, owner.pubkey());
+    assert_referral_error(process(&mut context, &[ix], &[&caller]).await, ReferralError::ChangeNotVoid);
+
+    // Anyone executes it; the cut applies and the proposer gets the rent back
+    let pending_change_pda = get_pending_change_pda(referral_program, solrefer::ID);
+    let pending_rent = get_balance(&mut context, pending_change_pda).await;
+    let owner_before = get_balance(&mut context, owner.pubkey()).await;
+    let ix = execute_ix(&mut context, &caller, referral_program, owner.pubkey()).await;
+    let resolved: Vec<PendingChangeResolved> = process_with_events(&mut context, &[ix], &[&caller]).await;
+    assert_eq!(resolved.len(), 1);
+    assert!(resolved[0].executed);
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!((program.fixed_reward_amount, program.settings_change_count), (REWARD, 3));
+    assert_eq!(get_balance(&mut context, owner.pubkey()).await, owner_before + pending_rent);
+    assert!(context.banks_client.get_account(pending_change_pda).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_unapproved_reward_cut_is_voided_after_deadline() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let (referral_program, end_time, _carol, _dave) = governed_program(&mut context, &owner, &alice, &bob).await;
+
+    // Shortening the program is a cut too
+    let ix = propose_ix(&mut context, &owner, referral_program, settings(end_time - 86400, REWARD)).await;
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    process(&mut context, &[vote_ix(&alice, referral_program, 0, true)], &[&alice]).await.unwrap();
+
+    let caller = create_funded_user(&mut context).await;
+    let ix = void_ix(&caller, referral_program, owner.pubkey());
+    assert_referral_error(process(&mut context, &[ix], &[&caller]).await, ReferralError::ChangeNotVoid);
+
+    // Past the deadline nobody can vote or execute, and the change is voided
+    advance_clock(&mut context, CHANGE_VOTING_PERIOD).await;
+    let result = process(&mut context, &[vote_ix(&bob, referral_program, 0, true)], &[&bob]).await;
+    assert_referral_error(result, ReferralError::VotingClosed);
+    let ix = execute_ix(&mut context, &caller, referral_program, owner.pubkey()).await;
+    assert_referral_error(process(&mut context, &[ix], &[&caller]).await, ReferralError::ChangeNotApproved);
+    let ix = void_ix(&caller, referral_program, owner.pubkey());
+    let resolved: Vec<PendingChangeResolved> = process_with_events(&mut context, &[ix], &[&caller]).await;
+    assert!(!resolved[0].executed);
+
+    let criteria: EligibilityCriteria =
+        get_account(&mut context, get_eligibility_criteria_pda(referral_program, solrefer::ID)).await;
+    assert_eq!(criteria.program_end_time, Some(end_time));
+    let pending_change_pda = get_pending_change_pda(referral_program, solrefer::ID);
+    assert!(context.banks_client.get_account(pending_change_pda).await.unwrap().is_none());
+
+    // The next proposal takes the next index
+    let ix = propose_ix(&mut context, &owner, referral_program, settings(end_time, REWARD / 2)).await;
+    let proposed: Vec<SettingsChangeProposed> = process_with_events(&mut context, &[ix], &[&owner]).await;
+    assert_eq!(proposed[0].index, 1);
+}
+
+fn enable_governance_ix(owner: &Keypair, referral_program: Pubkey, approval_threshold_percent: u8) -> Instruction {
+    program_instruction(
+        accounts::EnableGovernance { referral_program, authority: owner.pubkey() },
+        instruction::EnableGovernance { approval_threshold_percent },
+    )
+}
+
+async fn propose_ix(
+    context: &mut ProgramTestContext,
+    owner: &Keypair,
+    referral_program: Pubkey,
+    new_settings: ProgramSettings,
+) -> Instruction {
+    program_instruction(
+        accounts::UpdateProgramSettings {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            network_config: get_network_config_pda(solrefer::ID),
+            authority: owner.pubkey(),
+            settings_change: next_settings_change_pda(context, referral_program).await,
+            pending_change: Some(get_pending_change_pda(referral_program, solrefer::ID)),
+            event_queue: None,
+            system_program: system_program::ID,
+        },
+        instruction::UpdateProgramSettings { new_settings, idempotency_key: None },
+    )
+}
+
+fn vote_ix(user: &Keypair, referral_program: Pubkey, index: u64, approve: bool) -> Instruction {
+    let participant = get_participant_pda(referral_program, user.pubkey(), solrefer::ID);
+    program_instruction(
+        accounts::VoteChange {
+            referral_program,
+            pending_change: get_pending_change_pda(referral_program, solrefer::ID),
+            participant,
+            change_vote: get_change_vote_pda(referral_program, index, participant, solrefer::ID),
+            user: user.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::VoteChange { approve },
+    )
+}
+
+async fn execute_ix(
+    context: &mut ProgramTestContext,
+    caller: &Keypair,
+    referral_program: Pubkey,
+    proposer: Pubkey,
+) -> Instruction {
+    program_instruction(
+        accounts::ExecuteChange {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            network_config: get_network_config_pda(solrefer::ID),
+            pending_change: get_pending_change_pda(referral_program, solrefer::ID),
+            proposer,
+            settings_change: next_settings_change_pda(context, referral_program).await,
+            caller: caller.pubkey(),
+            event_queue: None,
+            system_program: system_program::ID,
+        },
+        instruction::ExecuteChange {},
+    )
+}
+
+fn void_ix(caller: &Keypair, referral_program: Pubkey, proposer: Pubkey) -> Instruction {
+    program_instruction(
+        accounts::VoidChange {
+            referral_program,
+            pending_change: get_pending_change_pda(referral_program, solrefer::ID),
+            proposer,
+            caller: caller.pubkey(),
+        },
+        instruction::VoidChange {},
+    )
+}
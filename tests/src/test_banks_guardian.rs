@@ -0,0 +1,470 @@
+use anchor_client::{
+    anchor_lang::{system_program, InstructionData},
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer},
+};
+use anchor_spl::token::{spl_token, TokenAccount};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::{MAX_WITHDRAWAL_DESTINATIONS, MIN_LOCKED_PERIOD, REWARD_DENOMINATION_RAW, WITHDRAWAL_DELAY},
+    error::ReferralError,
+    instruction,
+    instructions::{requires_withdrawal_delay, validate_guardian, GuardianConfig},
+    state::ReferralProgram,
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, claim_rewards, create_funded_token_account, create_funded_user,
+        create_mint, create_referral_program_ix, create_token_referral_program, deposit_sol, get_account, get_balance,
+        get_clock_time, join_referral_program, join_through_referral, process, program_instruction,
+        referral_program_pdas, setup,
+    },
+    test_util::{get_sponsor_vault_pda, get_withdrawal_request_pda},
+};
+
+const REFERRAL_REWARD: u64 = 1_000_000;
+const THRESHOLD: u64 = 2 * REFERRAL_REWARD;
+const ONE_YEAR: i64 = 365 * 86400;
+
+#[test]
+fn test_withdrawal_delay_applies_above_threshold_with_guardian() {
+    let guardian = Pubkey::new_unique();
+    let authority = Pubkey::new_unique();
+    assert!(validate_guardian(&GuardianConfig { guardian, withdrawal_delay_threshold: 0 }, authority).is_ok());
+    for guardian in [Pubkey::default(), authority] {
+        let config = GuardianConfig { guardian, withdrawal_delay_threshold: 0 };
+        assert_eq!(validate_guardian(&config, authority).unwrap_err(), ReferralError::InvalidGuardian.into());
+    }
+
+    let program = ReferralProgram { guardian, withdrawal_delay_threshold: THRESHOLD, ..Default::default() };
+    assert!(!requires_withdrawal_delay(&program, THRESHOLD));
+    assert!(requires_withdrawal_delay(&program, THRESHOLD + 1));
+
+    // Without a guardian nobody could cancel a queued withdrawal, so none is delayed
+    let program = ReferralProgram { guardian: Pubkey::default(), ..program };
+    assert!(!requires_withdrawal_delay(&program, u64::MAX));
+}
+
+#[test]
+fn test_withdrawal_destination_allow_list() {
+    let authority = Pubkey::new_unique();
+    let listed = Pubkey::new_unique();
+    let program = ReferralProgram { authority, ..Default::default() };
+    assert!(program.is_withdrawal_destination_allowed(Pubkey::new_unique()));
+
+    let mut allowed_withdrawal_destinations = [Pubkey::default(); MAX_WITHDRAWAL_DESTINATIONS];
+    allowed_withdrawal_destinations[2] = listed;
+    let program = ReferralProgram { allowed_withdrawal_destinations, ..program };
+    assert!(program.is_withdrawal_destination_allowed(authority));
+    assert!(program.is_withdrawal_destination_allowed(listed));
+    assert!(!program.is_withdrawal_destination_allowed(Pubkey::new_unique()));
+    assert!(!program.is_withdrawal_destination_allowed(Pubkey::default()));
+}
+
+/// Creates a SOL program guarded by `guardian` and funds it with ten rewards
+async fn create_guarded_program(
+    context: &mut ProgramTestContext,
+    owner: &Keypair,
+    guardian: &Keypair,
+) -> (Pubkey, Pubkey) {
+    let config = GuardianConfig { guardian: guardian.pubkey(), withdrawal_delay_threshold: THRESHOLD };
+    create_program(context, owner, Some(config), Default::default()).await
+}
+
+/// Creates a SOL program with the given guardian and withdrawal allow-list and funds it with ten rewards
+async fn create_program(
+    context: &mut ProgramTestContext,
+    owner: &Keypair,
+    guardian: Option<GuardianConfig>,
+    withdrawal_destinations: [Pubkey; MAX_WITHDRAWAL_DESTINATIONS],
+) -> (Pubkey, Pubkey) {
+    let end_time = get_clock_time(context).await + ONE_YEAR;
+    let mut create_ix = create_referral_program_ix(owner, None, REFERRAL_REWARD, Some(end_time), false);
+    create_ix.data = instruction::CreateReferralProgram {
+        program_index: 0,
+        token_mint: None,
+        fixed_reward_amount: REFERRAL_REWARD,
+        program_end_time: Some(end_time),
+        reward_denomination: REWARD_DENOMINATION_RAW,
+        start_inactive: false,
+        terms_hash: [0u8; 32],
+        guardian,
+        withdrawal_destinations,
+        settings_locked_until: None,
+        settings: None,
+    }
+    .data();
+    process(context, &[create_ix], &[owner]).await.unwrap();
+
+    let (referral_program, vault, _) = referral_program_pdas(owner.pubkey());
+    deposit_sol(context, owner, referral_program, vault, 10 * REFERRAL_REWARD).await;
+    (referral_program, vault)
+}
+
+fn withdraw_accounts(authority: &Keypair, referral_program: Pubkey, vault: Pubkey) -> accounts::WithdrawFunds {
+    accounts::WithdrawFunds {
+        referral_program,
+        vault,
+        token_vault: None,
+        destination_token_account: None,
+        destination: None,
+        authority: authority.pubkey(),
+        system_program: system_program::ID,
+        token_program: None,
+    }
+}
+
+fn withdraw_funds_ix(authority: &Keypair, referral_program: Pubkey, vault: Pubkey, amount: u64) -> Instruction {
+    program_instruction(withdraw_accounts(authority, referral_program, vault), instruction::WithdrawFunds { amount })
+}
+
+/// Builds a withdrawal of `amount` from a SOL program paid to `destination` instead of the authority
+fn withdraw_to_ix(
+    authority: &Keypair,
+    referral_program: Pubkey,
+    vault: Pubkey,
+    destination: Pubkey,
+    amount: u64,
+) -> Instruction {
+    let accounts = accounts::WithdrawFunds {
+        destination: Some(destination),
+        ..withdraw_accounts(authority, referral_program, vault)
+    };
+    program_instruction(accounts, instruction::WithdrawFunds { amount })
+}
+
+fn queue_withdrawal_ix(
+    authority: &Keypair,
+    referral_program: Pubkey,
+    destination: Option<Pubkey>,
+    amount: u64,
+) -> Instruction {
+    program_instruction(
+        accounts::QueueWithdrawal {
+            referral_program,
+            withdrawal_request: get_withdrawal_request_pda(referral_program, solrefer::ID),
+            destination,
+            authority: authority.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::QueueWithdrawal { amount },
+    )
+}
+
+fn execute_withdrawal_ix(
+    authority: &Keypair,
+    referral_program: Pubkey,
+    vault: Pubkey,
+    destination: Option<Pubkey>,
+) -> Instruction {
+    program_instruction(
+        accounts::ExecuteWithdrawal {
+            withdraw: accounts::WithdrawFunds { destination, ..withdraw_accounts(authority, referral_program, vault) },
+            withdrawal_request: get_withdrawal_request_pda(referral_program, solrefer::ID),
+        },
+        instruction::ExecuteWithdrawal,
+    )
+}
+
+fn cancel_withdrawal_ix(guardian: &Keypair, referral_program: Pubkey, authority: Pubkey) -> Instruction {
+    program_instruction(
+        accounts::GuardianCancelWithdrawal {
+            referral_program,
+            withdrawal_request: get_withdrawal_request_pda(referral_program, solrefer::ID),
+            authority,
+            guardian: guardian.pubkey(),
+        },
+        instruction::GuardianCancelWithdrawal,
+    )
+}
+
+#[tokio::test]
+async fn test_guardian_cancels_large_withdrawal() {
+    let (mut context, owner, guardian, _) = setup().await;
+    let (referral_program, vault) = create_guarded_program(&mut context, &owner, &guardian).await;
+    let withdrawal_request = get_withdrawal_request_pda(referral_program, solrefer::ID);
+
+    // A withdrawal within the threshold is paid at once
+    let vault_before = get_balance(&mut context, vault).await;
+    process(&mut context, &[withdraw_funds_ix(&owner, referral_program, vault, THRESHOLD)], &[&owner]).await.unwrap();
+    assert_eq!(get_balance(&mut context, vault).await, vault_before - THRESHOLD);
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.total_available, 10 * REFERRAL_REWARD - THRESHOLD);
+
+    // A larger one has to be queued, and cannot execute before the delay; nothing cannot be queued
+    let large = 5 * REFERRAL_REWARD;
+    let result = process(&mut context, &[queue_withdrawal_ix(&owner, referral_program, None, 0)], &[&owner]).await;
+    assert_referral_error(result, ReferralError::InvalidWithdrawalAmount);
+    let result = process(&mut context, &[withdraw_funds_ix(&owner, referral_program, vault, large)], &[&owner]).await;
+    assert_referral_error(result, ReferralError::WithdrawalDelayRequired);
+    process(&mut context, &[queue_withdrawal_ix(&owner, referral_program, None, large)], &[&owner]).await.unwrap();
+    let result =
+        process(&mut context, &[execute_withdrawal_ix(&owner, referral_program, vault, None)], &[&owner]).await;
+    assert_referral_error(result, ReferralError::WithdrawalNotReady);
+
+    // Only the guardian can cancel it, and cancelling leaves the funds in the vault
+    let stranger = create_funded_user(&mut context).await;
+    let result =
+        process(&mut context, &[cancel_withdrawal_ix(&stranger, referral_program, owner.pubkey())], &[&stranger]).await;
+    assert_referral_error(result, ReferralError::InvalidGuardian);
+    process(&mut context, &[cancel_withdrawal_ix(&guardian, referral_program, owner.pubkey())], &[&guardian])
+        .await
+        .unwrap();
+    assert!(context.banks_client.get_account(withdrawal_request).await.unwrap().is_none());
+    advance_clock(&mut context, WITHDRAWAL_DELAY).await;
+    let result =
+        process(&mut context, &[execute_withdrawal_ix(&owner, referral_program, vault, None)], &[&owner]).await;
+    assert!(result.is_err());
+    assert_eq!(get_balance(&mut context, vault).await, vault_before - THRESHOLD);
+
+    // An uncancelled request executes once the delay has passed
+    process(&mut context, &[queue_withdrawal_ix(&owner, referral_program, None, large)], &[&owner]).await.unwrap();
+    advance_clock(&mut context, WITHDRAWAL_DELAY).await;
+    process(&mut context, &[execute_withdrawal_ix(&owner, referral_program, vault, None)], &[&owner]).await.unwrap();
+    assert_eq!(get_balance(&mut context, vault).await, vault_before - THRESHOLD - large);
+    assert!(context.banks_client.get_account(withdrawal_request).await.unwrap().is_none());
+
+    // The guardian cannot move funds itself
+    let result =
+        process(&mut context, &[withdraw_funds_ix(&guardian, referral_program, vault, REFERRAL_REWARD)], &[&guardian])
+            .await;
+    assert_referral_error(result, ReferralError::InvalidAuthority);
+}
+
+#[tokio::test]
+async fn test_guardian_freeze_blocks_claims_and_withdrawals() {
+    let (mut context, owner, guardian, referrer) = setup().await;
+    let (referral_program, vault) = create_guarded_program(&mut context, &owner, &guardian).await;
+    let referrer_participant = join_referral_program(&mut context, &referrer, referral_program).await;
+    let referee = create_funded_user(&mut context).await;
+    join_through_referral(&mut context, &referee, referral_program, referrer_participant).await;
+    advance_clock(&mut context, MIN_LOCKED_PERIOD).await;
+    let sponsor_vault = get_sponsor_vault_pda(referral_program, solrefer::ID);
+    let fund_ix = program_instruction(
+        accounts::FundSponsorVault {
+            referral_program,
+            sponsor_vault,
+            funder: owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::FundSponsorVault { amount: REFERRAL_REWARD },
+    );
+    process(&mut context, &[fund_ix], &[&owner]).await.unwrap();
+
+    let freeze_ix = |signer: &Keypair| {
+        program_instruction(
+            accounts::GuardianFreeze { referral_program, guardian: signer.pubkey() },
+            instruction::GuardianFreeze,
+        )
+    };
+    let result = process(&mut context, &[freeze_ix(&owner)], &[&owner]).await;
+    assert_referral_error(result, ReferralError::InvalidGuardian);
+    process(&mut context, &[freeze_ix(&guardian)], &[&guardian]).await.unwrap();
+
+    let result = claim_rewards(&mut context, &referrer, referral_program, referrer_participant, vault).await;
+    assert_referral_error(result, ReferralError::ProgramFrozen);
+    let small_withdrawal = withdraw_funds_ix(&owner, referral_program, vault, REFERRAL_REWARD);
+    let result = process(&mut context, std::slice::from_ref(&small_withdrawal), &[&owner]).await;
+    assert_referral_error(result, ReferralError::ProgramFrozen);
+    let result =
+        process(&mut context, &[queue_withdrawal_ix(&owner, referral_program, None, THRESHOLD + 1)], &[&owner]).await;
+    assert_referral_error(result, ReferralError::ProgramFrozen);
+    let sponsor_withdrawal = program_instruction(
+        accounts::WithdrawSponsorVault {
+            referral_program,
+            sponsor_vault,
+            authority: owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::WithdrawSponsorVault { amount: REFERRAL_REWARD },
+    );
+    let result = process(&mut context, std::slice::from_ref(&sponsor_withdrawal), &[&owner]).await;
+    assert_referral_error(result, ReferralError::ProgramFrozen);
+
+    // Unfreezing takes both keys
+    let unfreeze_ix = program_instruction(
+        accounts::UnfreezeProgram { referral_program, authority: owner.pubkey(), guardian: guardian.pubkey() },
+        instruction::UnfreezeProgram,
+    );
+    let mut authority_only = unfreeze_ix.clone();
+    authority_only.accounts[2].is_signer = false;
+    assert!(process(&mut context, &[authority_only], &[&owner]).await.is_err());
+    process(&mut context, &[unfreeze_ix], &[&owner, &guardian]).await.unwrap();
+
+    claim_rewards(&mut context, &referrer, referral_program, referrer_participant, vault).await.unwrap();
+    process(&mut context, &[small_withdrawal], &[&owner]).await.unwrap();
+    process(&mut context, &[sponsor_withdrawal], &[&owner]).await.unwrap();
+    assert_eq!(get_balance(&mut context, sponsor_vault).await, 0);
+}
+
+/// Builds the dual-signed instruction replacing the program's withdrawal allow-list
+fn set_destinations_ix(
+    owner: &Keypair,
+    guardian: &Keypair,
+    referral_program: Pubkey,
+    destinations: [Pubkey; MAX_WITHDRAWAL_DESTINATIONS],
+) -> Instruction {
+    program_instruction(
+        accounts::SetWithdrawalDestinations {
+            referral_program,
+            authority: owner.pubkey(),
+            guardian: guardian.pubkey(),
+        },
+        instruction::SetWithdrawalDestinations { destinations },
+    )
+}
+
+#[tokio::test]
+async fn test_allow_list_restricts_withdrawal_destinations() {
+    let (mut context, owner, listed, stranger) = setup().await;
+    let mut destinations = [Pubkey::default(); MAX_WITHDRAWAL_DESTINATIONS];
+    destinations[0] = listed.pubkey();
+    let (referral_program, vault) = create_program(&mut context, &owner, None, destinations).await;
+
+    let ix = withdraw_to_ix(&owner, referral_program, vault, stranger.pubkey(), REFERRAL_REWARD);
+    let result = process(&mut context, &[ix], &[&owner]).await;
+    assert_referral_error(result, ReferralError::DestinationNotAllowed);
+
+    let listed_before = get_balance(&mut context, listed.pubkey()).await;
+    let ix = withdraw_to_ix(&owner, referral_program, vault, listed.pubkey(), REFERRAL_REWARD);
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    assert_eq!(get_balance(&mut context, listed.pubkey()).await, listed_before + REFERRAL_REWARD);
+
+    // The authority itself is always allowed
+    let vault_before = get_balance(&mut context, vault).await;
+    let ix = withdraw_funds_ix(&owner, referral_program, vault, REFERRAL_REWARD);
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    assert_eq!(get_balance(&mut context, vault).await, vault_before - REFERRAL_REWARD);
+
+    // Without a guardian to co-sign, the list is fixed
+    let ix = set_destinations_ix(&owner, &stranger, referral_program, [stranger.pubkey(); MAX_WITHDRAWAL_DESTINATIONS]);
+    let result = process(&mut context, &[ix], &[&owner, &stranger]).await;
+    assert_referral_error(result, ReferralError::InvalidGuardian);
+}
+
+#[tokio::test]
+async fn test_unrestricted_program_withdraws_anywhere() {
+    let (mut context, owner, _, stranger) = setup().await;
+    let (referral_program, vault) = create_program(&mut context, &owner, None, Default::default()).await;
+
+    let stranger_before = get_balance(&mut context, stranger.pubkey()).await;
+    let ix = withdraw_to_ix(&owner, referral_program, vault, stranger.pubkey(), REFERRAL_REWARD);
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    assert_eq!(get_balance(&mut context, stranger.pubkey()).await, stranger_before + REFERRAL_REWARD);
+
+    let vault_before = get_balance(&mut context, vault).await;
+    let ix = withdraw_funds_ix(&owner, referral_program, vault, REFERRAL_REWARD);
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    assert_eq!(get_balance(&mut context, vault).await, vault_before - REFERRAL_REWARD);
+}
+
+#[tokio::test]
+async fn test_withdrawal_leaves_credited_rewards_in_the_vault() {
+    let (mut context, owner, referrer, referee) = setup().await;
+    let (referral_program, vault) = create_program(&mut context, &owner, None, Default::default()).await;
+    let referrer_participant = join_referral_program(&mut context, &referrer, referral_program).await;
+    join_through_referral(&mut context, &referee, referral_program, referrer_participant).await;
+
+    // The referrer's credited reward is not the authority's to take back, and nothing is not an amount
+    let ix = withdraw_funds_ix(&owner, referral_program, vault, 10 * REFERRAL_REWARD);
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::InsufficientFunds);
+    let ix = withdraw_funds_ix(&owner, referral_program, vault, 0);
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::InvalidWithdrawalAmount);
+
+    let vault_before = get_balance(&mut context, vault).await;
+    let ix = withdraw_funds_ix(&owner, referral_program, vault, 9 * REFERRAL_REWARD);
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    assert_eq!(get_balance(&mut context, vault).await, vault_before - 9 * REFERRAL_REWARD);
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!((program.total_available, program.total_committed), (REFERRAL_REWARD, REFERRAL_REWARD));
+}
+
+#[tokio::test]
+async fn test_token_program_withdraws_from_its_token_vault() {
+    let (mut context, owner, referrer, referee) = setup().await;
+    let mint = create_mint(&mut context, &owner).await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, token_vault) =
+        create_token_referral_program(&mut context, &owner, mint, REFERRAL_REWARD, Some(end_time)).await;
+    let depositor_token_account = create_funded_token_account(&mut context, &owner, mint, 10 * REFERRAL_REWARD).await;
+    let deposit_ix = program_instruction(
+        accounts::DepositToken {
+            referral_program,
+            token_vault,
+            token_mint: mint,
+            depositor_token_account,
+            authority: owner.pubkey(),
+            event_queue: None,
+            token_program: spl_token::id(),
+        },
+        instruction::DepositToken { amount: 10 * REFERRAL_REWARD },
+    );
+    process(&mut context, &[deposit_ix], &[&owner]).await.unwrap();
+    let referrer_participant = join_referral_program(&mut context, &referrer, referral_program).await;
+    join_through_referral(&mut context, &referee, referral_program, referrer_participant).await;
+
+    // Token funds are paid from the token vault, signed for by the program, to a token account of the mint
+    let (_, vault, _) = referral_program_pdas(owner.pubkey());
+    let withdraw_tokens_ix = |amount| {
+        let accounts = accounts::WithdrawFunds {
+            token_vault: Some(token_vault),
+            destination_token_account: Some(depositor_token_account),
+            token_program: Some(spl_token::id()),
+            ..withdraw_accounts(&owner, referral_program, vault)
+        };
+        program_instruction(accounts, instruction::WithdrawFunds { amount })
+    };
+    let ix = withdraw_funds_ix(&owner, referral_program, vault, REFERRAL_REWARD);
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::InvalidTokenAccounts);
+    let ix = withdraw_tokens_ix(10 * REFERRAL_REWARD);
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::InsufficientFunds);
+
+    process(&mut context, &[withdraw_tokens_ix(9 * REFERRAL_REWARD)], &[&owner]).await.unwrap();
+    let destination: TokenAccount = get_account(&mut context, depositor_token_account).await;
+    let vault_tokens: TokenAccount = get_account(&mut context, token_vault).await;
+    assert_eq!((destination.amount, vault_tokens.amount), (9 * REFERRAL_REWARD, REFERRAL_REWARD));
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!((program.total_available, program.total_committed), (REFERRAL_REWARD, REFERRAL_REWARD));
+}
+
+#[tokio::test]
+async fn test_queued_withdrawal_keeps_its_destination() {
+    let (mut context, owner, guardian, listed) = setup().await;
+    let config = GuardianConfig { guardian: guardian.pubkey(), withdrawal_delay_threshold: THRESHOLD };
+    let mut destinations = [Pubkey::default(); MAX_WITHDRAWAL_DESTINATIONS];
+    destinations[0] = listed.pubkey();
+    let (referral_program, vault) = create_program(&mut context, &owner, Some(config), destinations).await;
+    let large = 5 * REFERRAL_REWARD;
+
+    let stranger = create_funded_user(&mut context).await;
+    let ix = queue_withdrawal_ix(&owner, referral_program, Some(stranger.pubkey()), large);
+    let result = process(&mut context, &[ix], &[&owner]).await;
+    assert_referral_error(result, ReferralError::DestinationNotAllowed);
+    let ix = queue_withdrawal_ix(&owner, referral_program, Some(listed.pubkey()), large);
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    advance_clock(&mut context, WITHDRAWAL_DELAY).await;
+
+    // The authority is an allowed destination, but not the one this withdrawal was queued for
+    let result =
+        process(&mut context, &[execute_withdrawal_ix(&owner, referral_program, vault, None)], &[&owner]).await;
+    assert_referral_error(result, ReferralError::DestinationNotAllowed);
+
+    let listed_before = get_balance(&mut context, listed.pubkey()).await;
+    let ix = execute_withdrawal_ix(&owner, referral_program, vault, Some(listed.pubkey()));
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    assert_eq!(get_balance(&mut context, listed.pubkey()).await, listed_before + large);
+
+    // With the guardian's signature the list can change, and the stranger can then be paid
+    let mut destinations = [Pubkey::default(); MAX_WITHDRAWAL_DESTINATIONS];
+    destinations[0] = stranger.pubkey();
+    let ix = set_destinations_ix(&owner, &guardian, referral_program, destinations);
+    process(&mut context, &[ix], &[&owner, &guardian]).await.unwrap();
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.allowed_withdrawal_destinations, destinations);
+    let ix = withdraw_to_ix(&owner, referral_program, vault, listed.pubkey(), REFERRAL_REWARD);
+    let result = process(&mut context, &[ix], &[&owner]).await;
+    assert_referral_error(result, ReferralError::DestinationNotAllowed);
+    let ix = withdraw_to_ix(&owner, referral_program, vault, stranger.pubkey(), REFERRAL_REWARD);
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+}
@@ -0,0 +1,116 @@
+//! Participant history exports.
+//!
+//! Alice refers Bob, who refers Carol, Dave and Erin an hour apart. Bob's history packs his own join and his three
+//! referrals from the referee receipts his client passes in.
+
+use anchor_client::{
+    anchor_lang::{solana_program::program::MAX_RETURN_DATA, AnchorSerialize},
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+        signer::Signer,
+    },
+};
+use solrefer::{
+    accounts,
+    error::ReferralError,
+    instruction,
+    state::{HistoryEntry, Participant, ParticipantHistoryV1, RefereeReceipt},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol,
+        get_account, get_clock_time, join_referral_program, join_through_referral, process, program_instruction, setup,
+        simulate_return,
+    },
+    test_util::get_referee_receipt_pda,
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_HOUR: i64 = 3600;
+const ONE_YEAR: i64 = 365 * 86400;
+
+fn history_ix(referral_program: Pubkey, participant: Pubkey, receipts: &[Pubkey]) -> Instruction {
+    let mut ix = program_instruction(
+        accounts::GetParticipantHistory { referral_program, participant },
+        instruction::GetParticipantHistory {},
+    );
+    ix.accounts.extend(receipts.iter().map(|receipt| AccountMeta::new_readonly(*receipt, false)));
+    ix
+}
+
+#[test]
+fn test_history_sorts_and_truncates_under_return_data_limit() {
+    let participant = Participant { referrer: Some(Pubkey::new_unique()), total_referrals: 30, ..Default::default() };
+    let entries = (0..30)
+        .rev()
+        .map(|timestamp| HistoryEntry { timestamp, amount: REWARD, ..Default::default() })
+        .collect::<Vec<_>>();
+
+    let history = ParticipantHistoryV1::new(&participant, entries.clone());
+    assert!(history.truncated);
+    assert_eq!(history.entries.len(), ParticipantHistoryV1::MAX_ENTRIES);
+    assert!(history.entries.iter().enumerate().all(|(index, entry)| entry.timestamp == index as i64));
+    let packed = history.try_to_vec().unwrap();
+    assert_eq!(
+        packed.len(),
+        ParticipantHistoryV1::HEADER_SIZE + ParticipantHistoryV1::MAX_ENTRIES * HistoryEntry::SIZE
+    );
+    assert!(packed.len() <= MAX_RETURN_DATA);
+    assert!(packed.len() + HistoryEntry::SIZE > MAX_RETURN_DATA);
+
+    let history = ParticipantHistoryV1::new(&participant, entries[..3].to_vec());
+    assert!(!history.truncated);
+    assert_eq!(history.entries.iter().map(|entry| entry.timestamp).collect::<Vec<_>>(), [27, 28, 29]);
+}
+
+#[tokio::test]
+async fn test_participant_history_packs_supplied_receipts() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    let bob_participant = join_through_referral(&mut context, &bob, referral_program, alice_participant).await;
+    let mut referees = Vec::new();
+    for _ in 0..3 {
+        advance_clock(&mut context, ONE_HOUR).await;
+        let referee = create_funded_user(&mut context).await;
+        join_through_referral(&mut context, &referee, referral_program, bob_participant).await;
+        referees.push(referee.pubkey());
+    }
+    let receipt = |wallet: Pubkey| get_referee_receipt_pda(referral_program, wallet, solrefer::ID);
+    let bob_receipt = receipt(bob.pubkey());
+    let referee_receipts: Vec<Pubkey> = referees.iter().map(|referee| receipt(*referee)).collect();
+
+    // Passed newest first, the receipts come back oldest first, Bob's own join leading
+    let receipts = [referee_receipts[2], referee_receipts[0], bob_receipt, referee_receipts[1]];
+    let history: ParticipantHistoryV1 =
+        simulate_return(&mut context, history_ix(referral_program, bob_participant, &receipts)).await;
+    assert_eq!((history.owner, history.referrer), (bob.pubkey(), Some(alice_participant)));
+    assert_eq!((history.total_referrals, history.pending_rewards), (3, 3 * REWARD));
+    assert!(!history.truncated);
+
+    let kinds: Vec<u8> = history.entries.iter().map(|entry| entry.kind).collect();
+    let (joined, referred) = (ParticipantHistoryV1::KIND_JOINED, ParticipantHistoryV1::KIND_REFERRED);
+    assert_eq!(kinds, [joined, referred, referred, referred]);
+    let counterparties: Vec<Pubkey> = history.entries.iter().map(|entry| entry.counterparty).collect();
+    assert_eq!(counterparties, [alice_participant, referees[0], referees[1], referees[2]]);
+    assert!(history.entries.windows(2).all(|pair| pair[0].timestamp < pair[1].timestamp));
+    let first: RefereeReceipt = get_account(&mut context, referee_receipts[0]).await;
+    assert_eq!((history.entries[1].amount, history.entries[1].timestamp), (first.credited_amount, first.credited_at));
+    assert_eq!(history.entries[1].status, RefereeReceipt::CLAWBACK_NONE);
+
+    // Only what was passed in appears
+    let history: ParticipantHistoryV1 =
+        simulate_return(&mut context, history_ix(referral_program, bob_participant, &[])).await;
+    assert!(history.entries.is_empty());
+
+    // Alice's history cannot include Bob's referrals, nor can Bob's repeat a receipt
+    let ix = history_ix(referral_program, alice_participant, &[bob_receipt, referee_receipts[0]]);
+    assert_referral_error(process(&mut context, &[ix], &[]).await, ReferralError::InvalidReferralRecord);
+    let ix = history_ix(referral_program, bob_participant, &[bob_receipt, bob_receipt]);
+    assert_referral_error(process(&mut context, &[ix], &[]).await, ReferralError::InvalidHistoryBatch);
+}
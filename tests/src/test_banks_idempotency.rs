@@ -0,0 +1,183 @@
+//! Idempotency keys on authority mutations.
+//!
+//! The authority records purchases with keys the way retrying automation would, resending a call whose first
+//! attempt already landed.
+
+use anchor_client::{
+    anchor_lang::InstructionData,
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer},
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::{IDEMPOTENCY_KEY_LEN, IDEMPOTENCY_LOG_LEN, MIN_LOCKED_PERIOD},
+    error::ReferralError,
+    instruction,
+    instructions::ProgramSettings,
+    state::{Participant, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol, get_account,
+        get_clock_time, join_referral_program, join_through_referral, process, program_instruction, setup,
+        update_program_settings, update_program_settings_ix,
+    },
+    test_util::get_eligibility_criteria_pda,
+};
+
+const REWARD: u64 = 1_000_000;
+const PURCHASE: u64 = 10 * REWARD;
+/// The revenue share, 10%
+const REVENUE_SHARE_BPS: u64 = 1_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+fn settings(end_time: i64) -> ProgramSettings {
+    ProgramSettings {
+        fixed_reward_amount: REWARD,
+        locked_period: MIN_LOCKED_PERIOD,
+        program_end_time: Some(end_time),
+        base_reward: REWARD,
+        max_reward_cap: 100 * REWARD,
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: REVENUE_SHARE_BPS,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
+    }
+}
+
+fn key(byte: u8) -> Option<[u8; IDEMPOTENCY_KEY_LEN]> {
+    Some([byte; IDEMPOTENCY_KEY_LEN])
+}
+
+struct Program {
+    referral_program: Pubkey,
+    referrer: Pubkey,
+    buyer: Pubkey,
+}
+
+impl Program {
+    fn purchase_ix(&self, owner: &Keypair, idempotency_key: Option<[u8; IDEMPOTENCY_KEY_LEN]>) -> Instruction {
+        program_instruction(
+            accounts::RecordPurchase {
+                referral_program: self.referral_program,
+                eligibility_criteria: get_eligibility_criteria_pda(self.referral_program, solrefer::ID),
+                buyer: self.buyer,
+                referrer: self.referrer,
+                referrer_upline: None,
+                authority: owner.pubkey(),
+            },
+            instruction::RecordPurchase { amount: PURCHASE, idempotency_key },
+        )
+    }
+
+    async fn pending_rewards(&self, context: &mut ProgramTestContext) -> u64 {
+        let participant: Participant = get_account(context, self.referrer).await;
+        participant.pending_rewards
+    }
+}
+
+/// Creates a funded program with a revenue share and joins a referrer with one referred buyer
+async fn create_program(context: &mut ProgramTestContext, owner: &Keypair, referrer: &Keypair) -> Program {
+    let end_time = get_clock_time(context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(context, owner, REWARD, Some(end_time)).await;
+    update_program_settings(context, owner, referral_program, settings(end_time)).await;
+    deposit_sol(context, owner, referral_program, vault, 100 * REWARD).await;
+
+    let referrer_participant = join_referral_program(context, referrer, referral_program).await;
+    let buyer = create_funded_user(context).await;
+    let buyer = join_through_referral(context, &buyer, referral_program, referrer_participant).await;
+    Program { referral_program, referrer: referrer_participant, buyer }
+}
+
+#[test]
+fn test_idempotency_log_evicts_oldest_key() {
+    let mut program = ReferralProgram::default();
+    program.record_idempotency_key(None).unwrap();
+    program.record_idempotency_key(None).unwrap();
+    assert_eq!(program.record_idempotency_key(key(0)).unwrap_err(), ReferralError::InvalidIdempotencyKey.into());
+
+    for byte in 1..=IDEMPOTENCY_LOG_LEN as u8 {
+        program.record_idempotency_key(key(byte)).unwrap();
+    }
+    for byte in 1..=IDEMPOTENCY_LOG_LEN as u8 {
+        let error = program.record_idempotency_key(key(byte)).unwrap_err();
+        assert_eq!(error, ReferralError::DuplicateIdempotencyKey.into());
+    }
+
+    // A ninth key overwrites the first, which can then be used again and in turn overwrites the second
+    program.record_idempotency_key(key(IDEMPOTENCY_LOG_LEN as u8 + 1)).unwrap();
+    program.record_idempotency_key(key(1)).unwrap();
+    let error = program.record_idempotency_key(key(3)).unwrap_err();
+    assert_eq!(error, ReferralError::DuplicateIdempotencyKey.into());
+    program.record_idempotency_key(key(2)).unwrap();
+}
+
+#[tokio::test]
+async fn test_retried_purchase_is_not_credited_twice() {
+    let (mut context, owner, referrer, _) = setup().await;
+    let program = create_program(&mut context, &owner, &referrer).await;
+    let share = PURCHASE * REVENUE_SHARE_BPS / 10_000;
+    let before = program.pending_rewards(&mut context).await;
+
+    process(&mut context, &[program.purchase_ix(&owner, key(1))], &[&owner]).await.unwrap();
+    assert_eq!(program.pending_rewards(&mut context).await, before + share);
+
+    // The retry of a call that landed fails and credits nothing
+    let result = process(&mut context, &[program.purchase_ix(&owner, key(1))], &[&owner]).await;
+    assert_referral_error(result, ReferralError::DuplicateIdempotencyKey);
+    assert_eq!(program.pending_rewards(&mut context).await, before + share);
+
+    // Another key, and no key at all, apply as usual
+    process(&mut context, &[program.purchase_ix(&owner, key(2))], &[&owner]).await.unwrap();
+    process(&mut context, &[program.purchase_ix(&owner, None)], &[&owner]).await.unwrap();
+    assert_eq!(program.pending_rewards(&mut context).await, before + 3 * share);
+
+    // Keys are shared across the authority's mutations, so a settings update cannot reuse one either
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let mut ix = update_program_settings_ix(&mut context, &owner, program.referral_program, settings(end_time)).await;
+    ix.data = instruction::UpdateProgramSettings { new_settings: settings(end_time), idempotency_key: key(2) }.data();
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::DuplicateIdempotencyKey);
+
+    // Once eight newer keys were recorded the first one is forgotten and can be used again
+    for byte in 3..=IDEMPOTENCY_LOG_LEN as u8 + 1 {
+        process(&mut context, &[program.purchase_ix(&owner, key(byte))], &[&owner]).await.unwrap();
+    }
+    let before = program.pending_rewards(&mut context).await;
+    process(&mut context, &[program.purchase_ix(&owner, key(1))], &[&owner]).await.unwrap();
+    assert_eq!(program.pending_rewards(&mut context).await, before + share);
+}
@@ -0,0 +1,110 @@
+//! Invite-only programs joined with authority-minted invites.
+//!
+//! Alice needs an invite to join and uses it up; Bob cannot reuse it. The authority revokes an unclaimed invite
+//! for its rent, cannot revoke a claimed one, and a revoked invite admits nobody.
+
+use anchor_client::solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer};
+use solrefer::{
+    accounts,
+    error::ReferralError,
+    instruction,
+    instructions::ProgramSettings,
+    state::{redeem_invite, Invite, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        assert_referral_error, create_funded_user, create_sol_referral_program, get_account, get_balance,
+        get_clock_time, join_referral_program_accounts, mint_invites_ix, process, program_instruction,
+        read_program_settings, setup, update_program_settings,
+    },
+    test_util::get_invite_pda,
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+fn join_ix(user: &Keypair, referral_program: Pubkey, invite: Option<Pubkey>) -> Instruction {
+    program_instruction(
+        accounts::JoinReferralProgram { invite, ..join_referral_program_accounts(user, referral_program) },
+        instruction::JoinReferralProgram { accepted_terms_hash: [0u8; 32] },
+    )
+}
+
+fn revoke_invite_ix(owner: &Keypair, referral_program: Pubkey, invite: Pubkey) -> Instruction {
+    program_instruction(
+        accounts::RevokeInvite { referral_program, invite, authority: owner.pubkey() },
+        instruction::RevokeInvite {},
+    )
+}
+
+#[test]
+fn test_redeem_invite() {
+    let claimer = Pubkey::new_unique();
+
+    // Open programs ignore the invite entirely
+    assert!(redeem_invite(false, None, claimer).is_ok());
+
+    assert_eq!(redeem_invite(true, None, claimer).unwrap_err(), ReferralError::InviteRequired.into());
+
+    let mut invite = Invite::default();
+    assert!(redeem_invite(true, Some(&mut invite), claimer).is_ok());
+    assert!(invite.claimed);
+    assert_eq!(invite.claimer, claimer);
+
+    assert_eq!(
+        redeem_invite(true, Some(&mut invite), Pubkey::new_unique()).unwrap_err(),
+        ReferralError::InviteAlreadyClaimed.into()
+    );
+}
+
+#[tokio::test]
+async fn test_invite_only_join() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let carol = create_funded_user(&mut context).await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, _) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    let settings = ProgramSettings {
+        locked_period: 86400,
+        base_reward: REWARD,
+        max_reward_cap: 1_000 * REWARD,
+        invite_only: true,
+        ..read_program_settings(&mut context, referral_program).await
+    };
+    update_program_settings(&mut context, &owner, referral_program, settings).await;
+
+    let invites =
+        [get_invite_pda(referral_program, 0, solrefer::ID), get_invite_pda(referral_program, 1, solrefer::ID)];
+    process(&mut context, &[mint_invites_ix(&owner, referral_program, &invites)], &[&owner]).await.unwrap();
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.invite_count, 2);
+
+    // Joining without an invite is rejected
+    let result = process(&mut context, &[join_ix(&alice, referral_program, None)], &[&alice]).await;
+    assert_referral_error(result, ReferralError::InviteRequired);
+
+    // A fresh invite admits the user and records them as its claimer
+    process(&mut context, &[join_ix(&alice, referral_program, Some(invites[0]))], &[&alice]).await.unwrap();
+    let invite: Invite = get_account(&mut context, invites[0]).await;
+    assert!(invite.claimed);
+    assert_eq!(invite.claimer, alice.pubkey());
+
+    // The same invite cannot be used twice
+    let result = process(&mut context, &[join_ix(&bob, referral_program, Some(invites[0]))], &[&bob]).await;
+    assert_referral_error(result, ReferralError::InviteAlreadyClaimed);
+
+    // Revoking an unclaimed invite closes it and refunds its rent to the authority
+    let invite_lamports = get_balance(&mut context, invites[1]).await;
+    let owner_balance = get_balance(&mut context, owner.pubkey()).await;
+    process(&mut context, &[revoke_invite_ix(&owner, referral_program, invites[1])], &[&owner]).await.unwrap();
+    assert!(context.banks_client.get_account(invites[1]).await.unwrap().is_none());
+    assert_eq!(get_balance(&mut context, owner.pubkey()).await, owner_balance + invite_lamports);
+
+    // A claimed invite cannot be revoked
+    let result = process(&mut context, &[revoke_invite_ix(&owner, referral_program, invites[0])], &[&owner]).await;
+    assert_referral_error(result, ReferralError::InviteAlreadyClaimed);
+
+    // Revoked invites no longer admit anyone
+    let result = process(&mut context, &[join_ix(&carol, referral_program, Some(invites[1]))], &[&carol]).await;
+    assert!(result.is_err());
+}
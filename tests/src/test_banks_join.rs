@@ -0,0 +1,145 @@
+use anchor_client::{
+    anchor_lang::AccountSerialize,
+    solana_sdk::{pubkey::Pubkey, signer::Signer},
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    constants::{LINK_FORMAT_LEGACY, LINK_FORMAT_SLUG},
+    error::ReferralError,
+    state::{referral_url, Participant, RefereeReceipt, ReferralProgram},
+};
+use std::str;
+
+use crate::banks_util::{
+    advance_clock, assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol, get_account,
+    get_clock_time, join_referral_program, join_referral_program_ix, join_through_referral, setup,
+    simulate_units_consumed, try_join_referral_program,
+};
+use crate::test_util::get_referee_receipt_pda;
+
+const ONE_YEAR: i64 = 365 * 86400;
+
+#[tokio::test]
+async fn test_join_referral_program_success() {
+    let (mut context, owner, alice, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, _) = create_sol_referral_program(&mut context, &owner, 1_000_000, Some(end_time)).await;
+
+    let participant = join_referral_program(&mut context, &alice, referral_program).await;
+
+    let participant_account: Participant = get_account(&mut context, participant).await;
+    assert_eq!(participant_account.owner, alice.pubkey());
+    assert_eq!(participant_account.program, referral_program);
+    assert_eq!(participant_account.total_referrals, 0);
+    assert_eq!(participant_account.total_rewards, 0);
+    assert_eq!(participant_account.referrer, None);
+
+    // Only the slug is stored; the URL is composed from the program's base URL
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(participant_account.link_format, LINK_FORMAT_SLUG);
+    assert_eq!(str::from_utf8(participant_account.stored_link()).unwrap(), alice.pubkey().to_string());
+    assert!(participant_account.referral_link.iter().all(|byte| *byte == 0));
+    assert_eq!(referral_url(&program, &participant_account), format!("https://solrefer.io/ref/{}", alice.pubkey()));
+}
+
+#[test]
+fn test_referral_url_for_both_link_formats() {
+    let owner = Pubkey::new_unique();
+    let program = ReferralProgram { link_format: LINK_FORMAT_SLUG, ..Default::default() };
+    let expected = format!("https://solrefer.io/ref/{}", owner);
+
+    let mut participant = Participant::default();
+    participant.set_referral_link(&owner, LINK_FORMAT_SLUG);
+    assert_eq!(referral_url(&program, &participant), expected);
+
+    // Accounts from before slugs keep their full link, which is returned as stored
+    let mut legacy = Participant::default();
+    legacy.set_referral_link(&owner, LINK_FORMAT_LEGACY);
+    assert!(legacy.link_slug.iter().all(|byte| *byte == 0));
+    assert_eq!(referral_url(&program, &legacy), expected);
+    legacy.referral_link = Participant::referral_link_for(&Pubkey::default()).0;
+    assert_eq!(referral_url(&program, &legacy), format!("https://solrefer.io/ref/{}", Pubkey::default()));
+}
+
+/// Rewrites a referral program as if it had been created before slugs, with `LINK_FORMAT_LEGACY`
+async fn make_legacy(context: &mut ProgramTestContext, referral_program: Pubkey) {
+    let mut program: ReferralProgram = get_account(context, referral_program).await;
+    program.link_format = LINK_FORMAT_LEGACY;
+    let mut data = Vec::new();
+    program.try_serialize(&mut data).unwrap();
+    let mut account = context.banks_client.get_account(referral_program).await.unwrap().unwrap();
+    account.data[..data.len()].copy_from_slice(&data);
+    context.set_account(&referral_program, &account.into());
+}
+
+#[tokio::test]
+async fn test_legacy_programs_keep_full_links() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, _) = create_sol_referral_program(&mut context, &owner, 1_000_000, Some(end_time)).await;
+    let legacy_owner = create_funded_user(&mut context).await;
+    let (legacy_program, _) = create_sol_referral_program(&mut context, &legacy_owner, 1_000_000, Some(end_time)).await;
+    make_legacy(&mut context, legacy_program).await;
+
+    // Storing the slug alone costs less than building the full link. Only a bank running the compiled program
+    // meters the difference; the in-process build reports the same units for both.
+    let slug_units =
+        simulate_units_consumed(&mut context, join_referral_program_ix(&alice, referral_program), &alice).await;
+    let legacy_units =
+        simulate_units_consumed(&mut context, join_referral_program_ix(&alice, legacy_program), &alice).await;
+    assert!(slug_units <= legacy_units, "slug join used {slug_units} CU, legacy join {legacy_units} CU");
+
+    let participant = join_referral_program(&mut context, &bob, legacy_program).await;
+    let participant_account: Participant = get_account(&mut context, participant).await;
+    assert_eq!(participant_account.link_format, LINK_FORMAT_LEGACY);
+    assert!(participant_account.link_slug.iter().all(|byte| *byte == 0));
+    let program: ReferralProgram = get_account(&mut context, legacy_program).await;
+    assert_eq!(referral_url(&program, &participant_account), format!("https://solrefer.io/ref/{}", bob.pubkey()));
+}
+
+#[tokio::test]
+async fn test_join_through_referral_success() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, 1_000_000, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 1_000_000).await;
+
+    let referrer = join_referral_program(&mut context, &alice, referral_program).await;
+    let participant = join_through_referral(&mut context, &bob, referral_program, referrer).await;
+
+    let participant_account: Participant = get_account(&mut context, participant).await;
+    assert_eq!(participant_account.owner, bob.pubkey());
+    assert_eq!(participant_account.referrer, Some(referrer));
+
+    let referrer_account: Participant = get_account(&mut context, referrer).await;
+    assert_eq!(referrer_account.total_referrals, 1);
+    assert_eq!(referrer_account.pending_rewards, 1_000_000);
+
+    // The receipt is stamped with the bank's clock
+    let receipt: RefereeReceipt =
+        get_account(&mut context, get_referee_receipt_pda(referral_program, bob.pubkey(), solrefer::ID)).await;
+    assert_eq!(receipt.referrer, referrer);
+    assert_eq!(receipt.credited_at, get_clock_time(&mut context).await);
+}
+
+#[tokio::test]
+async fn test_join_rejected_at_program_end() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + 3600;
+    let (referral_program, _) = create_sol_referral_program(&mut context, &owner, 1_000_000, Some(end_time)).await;
+
+    // One second before the end the program still accepts joins
+    advance_clock(&mut context, 3599).await;
+    join_referral_program(&mut context, &alice, referral_program).await;
+
+    // From the end time on it rejects them
+    advance_clock(&mut context, 1).await;
+    let (result, _) = try_join_referral_program(&mut context, &bob, referral_program).await;
+    assert_referral_error(result, ReferralError::ProgramEnded);
+
+    // An open-ended program never stops accepting them
+    let open_owner = create_funded_user(&mut context).await;
+    let (open_program, _) = create_sol_referral_program(&mut context, &open_owner, 1_000_000, None).await;
+    advance_clock(&mut context, 10 * ONE_YEAR).await;
+    join_referral_program(&mut context, &bob, open_program).await;
+}
@@ -0,0 +1,95 @@
+//! Joining through a referral and claiming the referee bonus in one transaction.
+//!
+//! Bob joins through Alice with `join_and_claim_through_referral`; an unlocked referee bonus is paid out of the
+//! vault right away, while a locked one is only credited and waits out the locked period.
+
+use anchor_client::solana_sdk::{pubkey::Pubkey, signer::Signer};
+use solrefer::{
+    accounts, instruction,
+    instructions::ProgramSettings,
+    state::{Participant, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        create_sol_referral_program, deposit_sol, get_account, get_balance, join_referral_program,
+        join_through_referral_accounts, process, program_instruction, read_program_settings, setup,
+        update_program_settings,
+    },
+    test_util::get_participant_pda,
+};
+
+const REFERRAL_REWARD: u64 = 1_000_000;
+const REFEREE_REWARD: u64 = 400_000;
+const DEPOSIT: u64 = 10_000_000;
+
+/// Runs `join_and_claim_through_referral` for a fresh referee of a program with the given referee lock,
+/// returning the referrer and referee participant accounts and the vault's balance change
+async fn join_and_claim(referee_rewards_locked: bool) -> (Participant, Participant, u64) {
+    let (mut context, owner, referrer, referee) = setup().await;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REFERRAL_REWARD, None).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, DEPOSIT).await;
+    let settings = ProgramSettings {
+        locked_period: 86400,
+        base_reward: REFERRAL_REWARD,
+        max_reward_cap: DEPOSIT,
+        referee_reward_amount: REFEREE_REWARD,
+        referee_rewards_locked,
+        ..read_program_settings(&mut context, referral_program).await
+    };
+    update_program_settings(&mut context, &owner, referral_program, settings).await;
+    let referrer_participant = join_referral_program(&mut context, &referrer, referral_program).await;
+
+    let vault_balance_before = get_balance(&mut context, vault).await;
+    let ix = program_instruction(
+        accounts::JoinAndClaimThroughReferral {
+            join: join_through_referral_accounts(&referee, referral_program, referrer_participant),
+            vault,
+            instructions_sysvar: None,
+        },
+        instruction::JoinAndClaimThroughReferral { source_tag: None, accepted_terms_hash: [0u8; 32], link_proof: None },
+    );
+    process(&mut context, &[ix], &[&referee]).await.unwrap();
+    let vault_paid = vault_balance_before - get_balance(&mut context, vault).await;
+
+    let program_account: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(
+        program_account.total_available + program_account.total_rewards_distributed,
+        DEPOSIT,
+        "payouts must come out of the program's available funds"
+    );
+
+    let referee_participant: Pubkey = get_participant_pda(referral_program, referee.pubkey(), solrefer::ID);
+    (
+        get_account(&mut context, referrer_participant).await,
+        get_account(&mut context, referee_participant).await,
+        vault_paid,
+    )
+}
+
+#[tokio::test]
+async fn test_join_and_claim_pays_unlocked_referee_reward() {
+    let (referrer, referee, vault_paid) = join_and_claim(false).await;
+
+    // The bonus is paid in the join transaction despite the program's locked period
+    assert_eq!(vault_paid, REFEREE_REWARD);
+    assert_eq!(referee.total_rewards, REFEREE_REWARD);
+    assert_eq!(referee.pending_rewards, 0);
+
+    assert_eq!(referrer.total_referrals, 1);
+    assert_eq!(referrer.pending_rewards, REFERRAL_REWARD);
+}
+
+#[tokio::test]
+async fn test_join_and_claim_with_locked_referee_reward_only_joins() {
+    let (referrer, referee, vault_paid) = join_and_claim(true).await;
+
+    // Nothing is paid; the bonus waits out the locked period like any other reward
+    assert_eq!(vault_paid, 0);
+    assert_eq!(referee.total_rewards, 0);
+    assert_eq!(referee.pending_rewards, REFEREE_REWARD);
+
+    // The referrer is credited exactly as when the bonus is paid
+    assert_eq!(referrer.total_referrals, 1);
+    assert_eq!(referrer.pending_rewards, REFERRAL_REWARD);
+}
@@ -0,0 +1,214 @@
+//! Join events packed into a digest.
+//!
+//! The same worst-case join, Alice's fourth referral crossing all four milestones with a sponsor's match and her
+//! boost paid, runs in a program in each event mode with the same wallets. The digest expands into exactly the
+//! events the verbose join logs, in a fraction of the log space.
+
+use anchor_client::{
+    anchor_lang::{system_program, AnchorDeserialize, Discriminator},
+    solana_sdk::{
+        instruction::Instruction, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signature::Keypair, signer::Signer,
+        system_instruction, transaction::Transaction,
+    },
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::{EVENT_MODE_DIGEST, EVENT_MODE_VERBOSE, MIN_LOCKED_PERIOD},
+    error::ReferralError,
+    events::{parse_digest, JoinDigest, JoinEvent},
+    instruction,
+    instructions::{current_settings, ProgramSettings},
+    state::{EligibilityCriteria, Milestone, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        assert_referral_error, create_sol_referral_program, deposit_sol, get_account, get_clock_time,
+        join_referral_program, join_through_referral, process, program_instruction, program_test, setup, start,
+        update_program_settings, update_program_settings_ix,
+    },
+    test_util::{
+        get_boost_escrow_pda, get_eligibility_criteria_pda, get_match_offer_pda, get_participant_pda,
+        get_referee_receipt_pda,
+    },
+};
+
+const REWARD: u64 = 1_000_000;
+const REFEREE_REWARD: u64 = REWARD / 5;
+const BOOST: u64 = REWARD / 2;
+const ONE_YEAR: i64 = 365 * 86400;
+/// The longest log line a digest of the worst-case join may take
+const MAX_DIGEST_LOG_LEN: usize = 512;
+
+/// The wallets of the scenario, funded afresh in each bank so that both runs produce the same addresses
+struct Cast {
+    owner: Keypair,
+    alice: Keypair,
+    sponsor: Keypair,
+    referees: [Keypair; 4],
+}
+
+/// Returns the settings of `referral_program` with rewards for referrer and referee, `milestones` and `event_mode`
+async fn settings(
+    context: &mut ProgramTestContext,
+    referral_program: Pubkey,
+    milestones: [Milestone; 4],
+    event_mode: u8,
+) -> ProgramSettings {
+    let program: ReferralProgram = get_account(context, referral_program).await;
+    let criteria: EligibilityCriteria =
+        get_account(context, get_eligibility_criteria_pda(referral_program, solrefer::ID)).await;
+    ProgramSettings {
+        locked_period: MIN_LOCKED_PERIOD,
+        base_reward: REWARD,
+        max_reward_cap: 100 * REWARD,
+        referee_reward_amount: REFEREE_REWARD,
+        milestones,
+        event_mode,
+        ..current_settings(&program, &criteria)
+    }
+}
+
+/// Runs the worst-case join in a fresh bank whose program is in `event_mode`, returning the join's log messages
+async fn worst_case_join_logs(cast: &Cast, event_mode: u8) -> Vec<String> {
+    let (mut context, ..) = start(program_test()).await;
+    let wallets = [&cast.owner, &cast.alice, &cast.sponsor].into_iter().chain(&cast.referees);
+    let funding: Vec<Instruction> = wallets
+        .map(|wallet| system_instruction::transfer(&context.payer.pubkey(), &wallet.pubkey(), LAMPORTS_PER_SOL * 2))
+        .collect();
+    process(&mut context, &funding, &[]).await.unwrap();
+
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) =
+        create_sol_referral_program(&mut context, &cast.owner, REWARD, Some(end_time)).await;
+    let program_settings = settings(&mut context, referral_program, Default::default(), event_mode).await;
+    update_program_settings(&mut context, &cast.owner, referral_program, program_settings).await;
+    deposit_sol(&mut context, &cast.owner, referral_program, vault, 100 * REWARD).await;
+    let alice_participant = join_referral_program(&mut context, &cast.alice, referral_program).await;
+    for referee in &cast.referees[..3] {
+        join_through_referral(&mut context, referee, referral_program, alice_participant).await;
+    }
+
+    // Alice's fourth referral crosses every milestone at once
+    let milestones = [1, 2, 3, 4].map(|threshold| Milestone { threshold, bonus: threshold * REWARD / 10 });
+    let program_settings = settings(&mut context, referral_program, milestones, event_mode).await;
+    update_program_settings(&mut context, &cast.owner, referral_program, program_settings).await;
+    let match_offer = get_match_offer_pda(referral_program, cast.sponsor.pubkey(), solrefer::ID);
+    let match_ix = program_instruction(
+        accounts::CreateMatchOffer {
+            referral_program,
+            match_offer,
+            sponsor: cast.sponsor.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::CreateMatchOffer { match_bps: 5_000, budget: 10 * REWARD },
+    );
+    process(&mut context, &[match_ix], &[&cast.sponsor]).await.unwrap();
+    let boost_escrow = get_boost_escrow_pda(referral_program, alice_participant, solrefer::ID);
+    let boost_ix = program_instruction(
+        accounts::FundRefereeBoost {
+            referral_program,
+            participant: alice_participant,
+            boost_escrow,
+            user: cast.alice.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::FundRefereeBoost { amount: 2 * BOOST, boost_per_referee: BOOST },
+    );
+    process(&mut context, &[boost_ix], &[&cast.alice]).await.unwrap();
+
+    let referee = &cast.referees[3];
+    let join_ix = program_instruction(
+        accounts::JoinThroughReferral {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant: get_participant_pda(referral_program, referee.pubkey(), solrefer::ID),
+            referrer: alice_participant,
+            rotated_referrer: None,
+            split_recipient: None,
+            boost_escrow: Some(boost_escrow),
+            match_offer: Some(match_offer),
+            referrer_wallet: Some(cast.alice.pubkey()),
+            referee_receipt: get_referee_receipt_pda(referral_program, referee.pubkey(), solrefer::ID),
+            sponsor_vault: None,
+            invite: None,
+            referrer_collection_metadata: None,
+            referrer_collection_nft: None,
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account: None,
+            referee_token_account: None,
+            referrer_upline: None,
+            user: referee.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
+            system_program: system_program::ID,
+        },
+        instruction::JoinThroughReferral { source_tag: None, accepted_terms_hash: [0u8; 32], link_proof: None },
+    );
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = Transaction::new_signed_with_payer(
+        &[join_ix],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, referee],
+        blockhash,
+    );
+    let processed = context.banks_client.process_transaction_with_metadata(tx).await.unwrap();
+    processed.result.expect("Join failed");
+    processed.metadata.expect("Transaction reported no metadata").log_messages
+}
+
+/// Returns the event log lines among `logs`
+fn event_lines(logs: &[String]) -> Vec<&str> {
+    logs.iter().filter(|log| log.starts_with("Program log: Program data: ")).map(String::as_str).collect()
+}
+
+/// Decodes an event log line into the event's data: its discriminator followed by its fields
+fn event_data(line: &str) -> Vec<u8> {
+    use anchor_client::anchor_lang::__private::base64::{engine::general_purpose::STANDARD, Engine};
+    STANDARD.decode(line.trim_start_matches("Program log: Program data: ")).unwrap()
+}
+
+#[tokio::test]
+async fn test_digest_expands_to_verbose_events() {
+    let cast = Cast {
+        owner: Keypair::new(),
+        alice: Keypair::new(),
+        sponsor: Keypair::new(),
+        referees: [Keypair::new(), Keypair::new(), Keypair::new(), Keypair::new()],
+    };
+    let verbose_logs = worst_case_join_logs(&cast, EVENT_MODE_VERBOSE).await;
+    let digest_logs = worst_case_join_logs(&cast, EVENT_MODE_DIGEST).await;
+
+    // The verbose join logs a match, four milestones and a boost; the digest join logs the digest alone
+    let verbose_lines = event_lines(&verbose_logs);
+    assert_eq!(verbose_lines.len(), 6);
+    let digest_lines = event_lines(&digest_logs);
+    assert_eq!(digest_lines.len(), 1);
+    let data = event_data(digest_lines[0]);
+    assert!(data.starts_with(&JoinDigest::DISCRIMINATOR));
+    let digest = JoinDigest::deserialize(&mut &data[8..]).unwrap();
+    let kinds = JoinDigest::REWARD_MATCHED | JoinDigest::MILESTONES_REACHED | JoinDigest::REFEREE_BOOSTED;
+    assert_eq!((digest.kinds, digest.milestones_reached), (kinds, 0b1111));
+
+    // Expanded, the digest is byte for byte what the verbose join logs
+    let expanded: Vec<Vec<u8>> = parse_digest(&digest).iter().map(JoinEvent::data).collect();
+    let verbose: Vec<Vec<u8>> = verbose_lines.into_iter().map(event_data).collect();
+    assert_eq!(expanded, verbose);
+
+    // At a fraction of the log space
+    let log_len = |lines: &[&str]| lines.iter().map(|line| line.len()).sum::<usize>();
+    assert!(digest_lines[0].len() <= MAX_DIGEST_LOG_LEN);
+    assert!(2 * log_len(&digest_lines) < log_len(&event_lines(&verbose_logs)));
+}
+
+#[tokio::test]
+async fn test_event_mode_must_be_supported() {
+    let (mut context, owner, ..) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, _) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    let unsupported = settings(&mut context, referral_program, Default::default(), EVENT_MODE_DIGEST + 1).await;
+    let ix = update_program_settings_ix(&mut context, &owner, referral_program, unsupported).await;
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::InvalidEventMode);
+}
@@ -0,0 +1,221 @@
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{
+        instruction::Instruction, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signature::Keypair, signer::Signer,
+    },
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::MIN_LOCKED_PERIOD,
+    error::ReferralError,
+    instruction,
+    instructions::{check_join_requirements, ProgramSettings},
+    state::{EligibilityCriteria, Participant},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol,
+        get_clock_time, join_referral_program, process, program_instruction, setup, update_program_settings,
+    },
+    test_util::{get_eligibility_criteria_pda, get_participant_pda, get_referee_receipt_pda},
+};
+
+const REFERRAL_REWARD: u64 = 1_000_000;
+const ONE_DAY: i64 = 86400;
+const ONE_YEAR: i64 = 365 * ONE_DAY;
+/// What `create_funded_user` funds a wallet with
+const USER_BALANCE: u64 = 2 * LAMPORTS_PER_SOL;
+
+#[test]
+fn test_join_requirements() {
+    let user = Pubkey::new_unique();
+    let criteria =
+        EligibilityCriteria { min_joiner_balance: 100, min_account_age_seconds: ONE_DAY, ..Default::default() };
+    let reference = Participant { owner: user, join_time: 1_000, ..Default::default() };
+    let now = 1_000 + ONE_DAY;
+
+    assert!(check_join_requirements(&criteria, &user, 100, Some(&reference), now).is_ok());
+    let result = check_join_requirements(&criteria, &user, 99, Some(&reference), now);
+    assert_eq!(result.unwrap_err(), ReferralError::JoinerBalanceTooLow.into());
+    let result = check_join_requirements(&criteria, &user, 100, Some(&reference), now - 1);
+    assert_eq!(result.unwrap_err(), ReferralError::JoinerAccountTooNew.into());
+    let result = check_join_requirements(&criteria, &user, 100, None, now);
+    assert_eq!(result.unwrap_err(), ReferralError::JoinerAccountTooNew.into());
+    let someone_else = Participant { owner: Pubkey::new_unique(), ..reference };
+    let result = check_join_requirements(&criteria, &user, 100, Some(&someone_else), now);
+    assert_eq!(result.unwrap_err(), ReferralError::InvalidAgeReference.into());
+
+    // Zero disables both requirements
+    let open = EligibilityCriteria::default();
+    assert!(check_join_requirements(&open, &user, 0, None, now).is_ok());
+}
+
+/// Creates a funded program with the given join requirements and joins a referrer
+async fn create_program(
+    context: &mut ProgramTestContext,
+    min_joiner_balance: u64,
+    min_account_age_seconds: i64,
+) -> (Pubkey, Pubkey) {
+    let owner = create_funded_user(context).await;
+    let end_time = get_clock_time(context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(context, &owner, REFERRAL_REWARD, Some(end_time)).await;
+
+    // The referrer joins before the requirements apply, so none of them stands in its way
+    let referrer = create_funded_user(context).await;
+    let referrer_participant = join_referral_program(context, &referrer, referral_program).await;
+    update_program_settings(
+        context,
+        &owner,
+        referral_program,
+        ProgramSettings {
+            fixed_reward_amount: REFERRAL_REWARD,
+            locked_period: MIN_LOCKED_PERIOD,
+            program_end_time: Some(end_time),
+            base_reward: REFERRAL_REWARD,
+            max_reward_cap: 10 * REFERRAL_REWARD,
+            max_depth: 0,
+            milestones: Default::default(),
+            invite_only: false,
+            revenue_share_percent: 0,
+            referee_reward_amount: 0,
+            referee_rewards_locked: false,
+            reserve_bps: 0,
+            max_referrals_per_window: 0,
+            referral_window_seconds: 0,
+            rate_limit_strict: false,
+            required_collection: None,
+            collection_gates_credits: false,
+            transfers_enabled: false,
+            dispute_window_seconds: 0,
+            rent_payer_mode: 0,
+            min_joiner_balance,
+            min_account_age_seconds,
+            alert_thresholds: [0; 3],
+            referrer_requirement: None,
+            referee_requirement: None,
+            direct_claims_only: false,
+            trailing_commission_bps: 0,
+            link_signer: None,
+            dormancy_period_seconds: 0,
+            region_attestor: None,
+            embargoed_regions: [0; 8],
+            region_checked_on_claim: false,
+            bridge_source_program: None,
+            bridge_bps: 0,
+            cleanup_bounty_bps: 0,
+            event_mode: 0,
+            attribution_grace_seconds: 0,
+            tier1_reward: 0,
+            tier2_reward: 0,
+            auto_downgrade: false,
+            min_remaining_credits: 0,
+            seed_reclaim_seconds: 0,
+            early_redemption_fee: 0,
+        },
+    )
+    .await;
+    deposit_sol(context, &owner, referral_program, vault, 10 * REFERRAL_REWARD).await;
+    (referral_program, referrer_participant)
+}
+
+fn join_ix(user: &Keypair, referral_program: Pubkey, age_reference: Option<Pubkey>) -> Instruction {
+    program_instruction(
+        accounts::JoinReferralProgram {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+            invite: None,
+            collection_metadata: None,
+            collection_nft: None,
+            age_reference,
+            region_attestation: None,
+            referrer_token_account: None,
+            bridge_participant: None,
+            bridge_referrer: None,
+            user: user.pubkey(),
+            event_queue: None,
+            system_program: system_program::ID,
+        },
+        instruction::JoinReferralProgram { accepted_terms_hash: [0u8; 32] },
+    )
+}
+
+fn join_through_referral_ix(
+    user: &Keypair,
+    referral_program: Pubkey,
+    referrer: Pubkey,
+    age_reference: Option<Pubkey>,
+) -> Instruction {
+    program_instruction(
+        accounts::JoinThroughReferral {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+            referrer,
+            rotated_referrer: None,
+            split_recipient: None,
+            boost_escrow: None,
+            match_offer: None,
+            referrer_wallet: None,
+            referee_receipt: get_referee_receipt_pda(referral_program, user.pubkey(), solrefer::ID),
+            sponsor_vault: None,
+            invite: None,
+            referrer_collection_metadata: None,
+            referrer_collection_nft: None,
+            age_reference,
+            region_attestation: None,
+            referrer_token_account: None,
+            referee_token_account: None,
+            referrer_upline: None,
+            user: user.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
+            system_program: system_program::ID,
+        },
+        instruction::JoinThroughReferral { source_tag: None, accepted_terms_hash: [0u8; 32], link_proof: None },
+    )
+}
+
+#[tokio::test]
+async fn test_min_joiner_balance() {
+    let (mut context, _, alice, bob) = setup().await;
+
+    // The balance before the join counts, although the join itself pays for the new accounts
+    let (referral_program, referrer) = create_program(&mut context, USER_BALANCE, 0).await;
+    process(&mut context, &[join_ix(&alice, referral_program, None)], &[&alice]).await.unwrap();
+    process(&mut context, &[join_through_referral_ix(&bob, referral_program, referrer, None)], &[&bob]).await.unwrap();
+
+    let (referral_program, referrer) = create_program(&mut context, USER_BALANCE + 1, 0).await;
+    let result = process(&mut context, &[join_ix(&alice, referral_program, None)], &[&alice]).await;
+    assert_referral_error(result, ReferralError::JoinerBalanceTooLow);
+    let user = create_funded_user(&mut context).await;
+    let result =
+        process(&mut context, &[join_through_referral_ix(&user, referral_program, referrer, None)], &[&user]).await;
+    assert_referral_error(result, ReferralError::JoinerBalanceTooLow);
+}
+
+#[tokio::test]
+async fn test_min_account_age() {
+    let (mut context, _, alice, bob) = setup().await;
+    let (older_program, _) = create_program(&mut context, 0, 0).await;
+    let alice_reference = join_referral_program(&mut context, &alice, older_program).await;
+    let bob_reference = join_referral_program(&mut context, &bob, older_program).await;
+    let (referral_program, referrer) = create_program(&mut context, 0, ONE_DAY).await;
+
+    // Without a reference, or with one too recent, the wallet cannot show its age
+    let result = process(&mut context, &[join_ix(&alice, referral_program, None)], &[&alice]).await;
+    assert_referral_error(result, ReferralError::JoinerAccountTooNew);
+    let result = process(&mut context, &[join_ix(&alice, referral_program, Some(alice_reference))], &[&alice]).await;
+    assert_referral_error(result, ReferralError::JoinerAccountTooNew);
+
+    // Another wallet's participant account does not date this one
+    advance_clock(&mut context, ONE_DAY).await;
+    let result = process(&mut context, &[join_ix(&alice, referral_program, Some(bob_reference))], &[&alice]).await;
+    assert_referral_error(result, ReferralError::InvalidAgeReference);
+
+    process(&mut context, &[join_ix(&alice, referral_program, Some(alice_reference))], &[&alice]).await.unwrap();
+    let ix = join_through_referral_ix(&bob, referral_program, referrer, Some(bob_reference));
+    process(&mut context, &[ix], &[&bob]).await.unwrap();
+}
@@ -0,0 +1,139 @@
+//! Participants naming their referrer after joining directly.
+//!
+//! Bob follows Alice's link but joins directly, and names Alice within the program's grace window: she is credited
+//! the referral once, and Bob cannot name a referrer again. Carol waits out the window, and Alice, who has earned
+//! from Bob's referral, cannot name a referrer at all.
+
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer},
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::{MAX_ATTRIBUTION_GRACE, MIN_LOCKED_PERIOD},
+    error::ReferralError,
+    instruction,
+    instructions::{current_settings, ProgramSettings},
+    state::{EligibilityCriteria, Participant, RefereeReceipt, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol,
+        get_account, get_clock_time, join_referral_program, process, program_instruction, setup,
+        update_program_settings, update_program_settings_ix,
+    },
+    test_util::{get_eligibility_criteria_pda, get_participant_pda, get_referee_receipt_pda},
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_DAY: i64 = 86400;
+const ONE_YEAR: i64 = 365 * ONE_DAY;
+
+/// Returns the settings of `referral_program` with `REWARD` per referral and `attribution_grace_seconds`
+async fn settings(
+    context: &mut ProgramTestContext,
+    referral_program: Pubkey,
+    attribution_grace_seconds: i64,
+) -> ProgramSettings {
+    let program: ReferralProgram = get_account(context, referral_program).await;
+    let criteria: EligibilityCriteria =
+        get_account(context, get_eligibility_criteria_pda(referral_program, solrefer::ID)).await;
+    ProgramSettings {
+        locked_period: MIN_LOCKED_PERIOD,
+        base_reward: REWARD,
+        max_reward_cap: 100 * REWARD,
+        attribution_grace_seconds,
+        ..current_settings(&program, &criteria)
+    }
+}
+
+/// Builds `user` naming `referrer` as its referrer in `referral_program`
+fn set_referrer_late_ix(user: &Keypair, referral_program: Pubkey, referrer: Pubkey) -> Instruction {
+    program_instruction(
+        accounts::SetReferrerLate {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+            referrer,
+            split_recipient: None,
+            referee_receipt: get_referee_receipt_pda(referral_program, user.pubkey(), solrefer::ID),
+            sponsor_vault: None,
+            referrer_collection_metadata: None,
+            referrer_collection_nft: None,
+            referrer_token_account: None,
+            referee_token_account: None,
+            referrer_upline: None,
+            user: user.pubkey(),
+            event_queue: None,
+            system_program: system_program::ID,
+        },
+        instruction::SetReferrerLate {},
+    )
+}
+
+#[tokio::test]
+async fn test_referrer_named_within_grace_window() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    let program_settings = settings(&mut context, referral_program, ONE_DAY).await;
+    update_program_settings(&mut context, &owner, referral_program, program_settings).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    let bob_participant = join_referral_program(&mut context, &bob, referral_program).await;
+
+    // Within the window Bob names Alice, who is credited as if he had joined through her link
+    advance_clock(&mut context, ONE_DAY / 2).await;
+    let ix = set_referrer_late_ix(&bob, referral_program, alice_participant);
+    process(&mut context, &[ix], &[&bob]).await.unwrap();
+    let bob_account: Participant = get_account(&mut context, bob_participant).await;
+    assert_eq!((bob_account.referrer, bob_account.referral_depth), (Some(alice_participant), 1));
+    let receipt: RefereeReceipt =
+        get_account(&mut context, get_referee_receipt_pda(referral_program, bob.pubkey(), solrefer::ID)).await;
+    assert_eq!((receipt.referrer, receipt.credited_amount), (alice_participant, REWARD));
+    assert!(receipt.counted && receipt.referee_active);
+
+    // A second attempt credits Alice nothing more
+    let ix = set_referrer_late_ix(&bob, referral_program, alice_participant);
+    assert_referral_error(process(&mut context, &[ix], &[&bob]).await, ReferralError::AttributionNotAllowed);
+    let alice_account: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!(alice_account.total_referrals, 1);
+    assert_eq!(alice_account.raw_referrals, 1);
+    assert_eq!(alice_account.active_referee_count, 1);
+    assert_eq!(alice_account.pending_rewards, REWARD);
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!((program.total_referrals_credited, program.total_referrals_raw), (1, 1));
+    assert_eq!(program.total_committed, REWARD);
+
+    // Alice has earned from Bob's referral, so she can no longer name a referrer of her own
+    let carol = create_funded_user(&mut context).await;
+    let carol_participant = join_referral_program(&mut context, &carol, referral_program).await;
+    let ix = set_referrer_late_ix(&alice, referral_program, carol_participant);
+    assert_referral_error(process(&mut context, &[ix], &[&alice]).await, ReferralError::AttributionNotAllowed);
+
+    // Once the window has passed Carol cannot name Alice either
+    advance_clock(&mut context, ONE_DAY + 1).await;
+    let ix = set_referrer_late_ix(&carol, referral_program, alice_participant);
+    assert_referral_error(process(&mut context, &[ix], &[&carol]).await, ReferralError::AttributionWindowClosed);
+    let alice_account: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!(alice_account.total_referrals, 1);
+}
+
+#[tokio::test]
+async fn test_attribution_grace_is_bounded() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, _) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+
+    // Programs without a grace period take no late attributions
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    join_referral_program(&mut context, &bob, referral_program).await;
+    let ix = set_referrer_late_ix(&bob, referral_program, alice_participant);
+    assert_referral_error(process(&mut context, &[ix], &[&bob]).await, ReferralError::AttributionWindowClosed);
+
+    let too_long = settings(&mut context, referral_program, MAX_ATTRIBUTION_GRACE + 1).await;
+    let ix = update_program_settings_ix(&mut context, &owner, referral_program, too_long).await;
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::InvalidAttributionGrace);
+}
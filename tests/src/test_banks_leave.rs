@@ -0,0 +1,276 @@
+//! Participants leaving a program while referees reference them.
+//!
+//! Alice refers Bob and Carol and cannot leave while either is still in the program; a clawback releases Carol,
+//! who can then leave without releasing her a second time, and once Bob has left too Alice can close her account.
+//! Leaving with `force` instead keeps Alice's account as a tombstone that earns no trailing commission and takes
+//! no referrals, and which closes once her last referee has gone. A referee whose referrer was rotated to a new
+//! wallet releases the account the referrer was rotated to, and a referee rotated to a new wallet leaves with the
+//! receipt of its old one, releasing its referrer only if its referral was not clawed back.
+
+use anchor_client::solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::MIN_LOCKED_PERIOD,
+    error::ReferralError,
+    instruction,
+    instructions::{current_settings, ProgramSettings},
+    state::{EligibilityCriteria, Participant, RefereeReceipt, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, claim_rewards, create_funded_user, create_sol_referral_program,
+        deposit_sol, get_account, get_clock_time, join_referral_program, join_through_referral, process,
+        program_instruction, rotate_owner, setup, try_join_through_referral, update_program_settings,
+    },
+    test_util::{get_eligibility_criteria_pda, get_participant_pda, get_referee_receipt_pda},
+};
+
+const REWARD: u64 = 1_000_000;
+const PURCHASE: u64 = 10 * REWARD;
+/// 10%, for both the revenue share and the trailing commission
+const BPS: u64 = 1_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+/// Builds `user` leaving `referral_program`, passing its referrer and referee receipt when it has a referrer
+fn leave_ix(user: &Keypair, referral_program: Pubkey, referrer: Option<Pubkey>, force: bool) -> Instruction {
+    program_instruction(
+        accounts::LeaveProgram {
+            referral_program,
+            participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+            referrer,
+            rotated_referrer: None,
+            referee_receipt: referrer.map(|_| get_referee_receipt_pda(referral_program, user.pubkey(), solrefer::ID)),
+            user: user.pubkey(),
+        },
+        instruction::LeaveProgram { force },
+    )
+}
+
+async fn active_referees(context: &mut ProgramTestContext, participant: Pubkey) -> u32 {
+    let participant: Participant = get_account(context, participant).await;
+    participant.active_referee_count
+}
+
+async fn account_exists(context: &mut ProgramTestContext, address: Pubkey) -> bool {
+    context.banks_client.get_account(address).await.unwrap().is_some()
+}
+
+#[tokio::test]
+async fn test_referrer_leaves_after_its_referees() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    let bob_participant = join_through_referral(&mut context, &bob, referral_program, alice_participant).await;
+    let carol = create_funded_user(&mut context).await;
+    join_through_referral(&mut context, &carol, referral_program, alice_participant).await;
+    assert_eq!(active_referees(&mut context, alice_participant).await, 2);
+
+    // Alice is held in the program by her referees
+    let result = process(&mut context, &[leave_ix(&alice, referral_program, None, false)], &[&alice]).await;
+    assert_referral_error(result, ReferralError::ActiveRefereesRemain);
+
+    // Clawing back Carol's referral releases her; her leaving later does not release her again
+    let clawback_ix = program_instruction(
+        accounts::ClawbackReferral {
+            referral_program,
+            referee_receipt: get_referee_receipt_pda(referral_program, carol.pubkey(), solrefer::ID),
+            referrer: alice_participant,
+            authority: owner.pubkey(),
+        },
+        instruction::ClawbackReferral { idempotency_key: None },
+    );
+    process(&mut context, &[clawback_ix], &[&owner]).await.unwrap();
+    assert_eq!(active_referees(&mut context, alice_participant).await, 1);
+    let ix = leave_ix(&carol, referral_program, Some(alice_participant), false);
+    process(&mut context, &[ix], &[&carol]).await.unwrap();
+    assert_eq!(active_referees(&mut context, alice_participant).await, 1);
+    let receipt: RefereeReceipt =
+        get_account(&mut context, get_referee_receipt_pda(referral_program, carol.pubkey(), solrefer::ID)).await;
+    assert!(!receipt.referee_active);
+
+    // A referee must pass its referrer to leave
+    let result = process(&mut context, &[leave_ix(&bob, referral_program, None, false)], &[&bob]).await;
+    assert_referral_error(result, ReferralError::InvalidReferrer);
+    let ix = leave_ix(&bob, referral_program, Some(alice_participant), false);
+    process(&mut context, &[ix], &[&bob]).await.unwrap();
+    assert!(!account_exists(&mut context, bob_participant).await);
+    assert_eq!(active_referees(&mut context, alice_participant).await, 0);
+
+    // With her referees gone Alice only has to claim first
+    let result = process(&mut context, &[leave_ix(&alice, referral_program, None, false)], &[&alice]).await;
+    assert_referral_error(result, ReferralError::PendingRewardsRemain);
+    advance_clock(&mut context, MIN_LOCKED_PERIOD).await;
+    claim_rewards(&mut context, &alice, referral_program, alice_participant, vault).await.unwrap();
+    process(&mut context, &[leave_ix(&alice, referral_program, None, false)], &[&alice]).await.unwrap();
+    assert!(!account_exists(&mut context, alice_participant).await);
+}
+
+#[tokio::test]
+async fn test_forced_leave_leaves_a_tombstone() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    let criteria: EligibilityCriteria =
+        get_account(&mut context, get_eligibility_criteria_pda(referral_program, solrefer::ID)).await;
+    let settings = ProgramSettings {
+        locked_period: MIN_LOCKED_PERIOD,
+        base_reward: REWARD,
+        max_reward_cap: 100 * REWARD,
+        revenue_share_percent: BPS,
+        trailing_commission_bps: BPS,
+        ..current_settings(&program, &criteria)
+    };
+    update_program_settings(&mut context, &owner, referral_program, settings).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    let bob_participant = join_through_referral(&mut context, &bob, referral_program, alice_participant).await;
+    advance_clock(&mut context, MIN_LOCKED_PERIOD).await;
+    claim_rewards(&mut context, &alice, referral_program, alice_participant, vault).await.unwrap();
+
+    // Forcing the leave keeps Alice's account for Bob to reference, as a tombstone
+    process(&mut context, &[leave_ix(&alice, referral_program, None, true)], &[&alice]).await.unwrap();
+    let tombstone: Participant = get_account(&mut context, alice_participant).await;
+    assert!(tombstone.has_left());
+    assert_eq!(tombstone.rotated_to, None);
+    assert_eq!(tombstone.active_referee_count, 1);
+
+    // The tombstone takes no referrals and earns no commission on what Bob earns
+    let dave = create_funded_user(&mut context).await;
+    let (result, _) = try_join_through_referral(&mut context, &dave, referral_program, alice_participant).await;
+    assert_referral_error(result, ReferralError::ParticipantLeft);
+    let carol = create_funded_user(&mut context).await;
+    join_through_referral(&mut context, &carol, referral_program, bob_participant).await;
+    let purchase_ix = program_instruction(
+        accounts::RecordPurchase {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            buyer: get_participant_pda(referral_program, carol.pubkey(), solrefer::ID),
+            referrer: bob_participant,
+            referrer_upline: Some(alice_participant),
+            authority: owner.pubkey(),
+        },
+        instruction::RecordPurchase { amount: PURCHASE, idempotency_key: None },
+    );
+    process(&mut context, &[purchase_ix], &[&owner]).await.unwrap();
+    let bob_account: Participant = get_account(&mut context, bob_participant).await;
+    assert_eq!(bob_account.pending_rewards, 2 * REWARD);
+    let tombstone: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!(tombstone.pending_rewards, 0);
+
+    // The tombstone cannot be left again while Bob remains, and closes once he has gone
+    let result = process(&mut context, &[leave_ix(&alice, referral_program, None, true)], &[&alice]).await;
+    assert_referral_error(result, ReferralError::ActiveRefereesRemain);
+    let ix = leave_ix(&carol, referral_program, Some(bob_participant), false);
+    process(&mut context, &[ix], &[&carol]).await.unwrap();
+    advance_clock(&mut context, MIN_LOCKED_PERIOD).await;
+    claim_rewards(&mut context, &bob, referral_program, bob_participant, vault).await.unwrap();
+    let ix = leave_ix(&bob, referral_program, Some(alice_participant), false);
+    process(&mut context, &[ix], &[&bob]).await.unwrap();
+    assert_eq!(active_referees(&mut context, alice_participant).await, 0);
+    process(&mut context, &[leave_ix(&alice, referral_program, None, false)], &[&alice]).await.unwrap();
+    assert!(!account_exists(&mut context, alice_participant).await);
+}
+
+#[tokio::test]
+async fn test_leave_releases_the_rotated_referrer() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    join_through_referral(&mut context, &bob, referral_program, alice_participant).await;
+
+    // Alice's active referees move with her account to her new wallet
+    let alice_new_wallet = create_funded_user(&mut context).await;
+    let rotated_participant = rotate_owner(&mut context, referral_program, &alice, &alice_new_wallet).await;
+    assert_eq!(active_referees(&mut context, alice_participant).await, 0);
+    assert_eq!(active_referees(&mut context, rotated_participant).await, 1);
+
+    // Bob still references Alice's old account, and leaving has to release the account it was rotated to
+    let result =
+        process(&mut context, &[leave_ix(&bob, referral_program, Some(alice_participant), false)], &[&bob]).await;
+    assert_referral_error(result, ReferralError::ParticipantRotated);
+    let ix = program_instruction(
+        accounts::LeaveProgram {
+            referral_program,
+            participant: get_participant_pda(referral_program, bob.pubkey(), solrefer::ID),
+            referrer: Some(alice_participant),
+            rotated_referrer: Some(rotated_participant),
+            referee_receipt: Some(get_referee_receipt_pda(referral_program, bob.pubkey(), solrefer::ID)),
+            user: bob.pubkey(),
+        },
+        instruction::LeaveProgram { force: false },
+    );
+    process(&mut context, &[ix], &[&bob]).await.unwrap();
+    assert_eq!(active_referees(&mut context, rotated_participant).await, 0);
+    assert_eq!(active_referees(&mut context, alice_participant).await, 0);
+}
+
+#[tokio::test]
+async fn test_rotated_referee_leaves_with_its_original_receipt() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    join_through_referral(&mut context, &bob, referral_program, alice_participant).await;
+    let carol = create_funded_user(&mut context).await;
+    join_through_referral(&mut context, &carol, referral_program, alice_participant).await;
+    let bob_receipt = get_referee_receipt_pda(referral_program, bob.pubkey(), solrefer::ID);
+    let carol_receipt = get_referee_receipt_pda(referral_program, carol.pubkey(), solrefer::ID);
+
+    // Clawing back Bob's referral releases him before he moves to a new wallet
+    let clawback_ix = program_instruction(
+        accounts::ClawbackReferral {
+            referral_program,
+            referee_receipt: bob_receipt,
+            referrer: alice_participant,
+            authority: owner.pubkey(),
+        },
+        instruction::ClawbackReferral { idempotency_key: None },
+    );
+    process(&mut context, &[clawback_ix], &[&owner]).await.unwrap();
+    assert_eq!(active_referees(&mut context, alice_participant).await, 1);
+    let bob_new_wallet = create_funded_user(&mut context).await;
+    let bob_rotated = rotate_owner(&mut context, referral_program, &bob, &bob_new_wallet).await;
+    let carol_new_wallet = create_funded_user(&mut context).await;
+    let carol_rotated = rotate_owner(&mut context, referral_program, &carol, &carol_new_wallet).await;
+
+    let rotated_leave_ix = |user: &Keypair, participant: Pubkey, referee_receipt: Pubkey| {
+        program_instruction(
+            accounts::LeaveProgram {
+                referral_program,
+                participant,
+                referrer: Some(alice_participant),
+                rotated_referrer: None,
+                referee_receipt: Some(referee_receipt),
+                user: user.pubkey(),
+            },
+            instruction::LeaveProgram { force: false },
+        )
+    };
+
+    // A rotated account leaves with the receipt of the wallet it was rotated from, and no other
+    let ix = rotated_leave_ix(&bob_new_wallet, bob_rotated, carol_receipt);
+    let result = process(&mut context, &[ix], &[&bob_new_wallet]).await;
+    assert_referral_error(result, ReferralError::InvalidReferrer);
+
+    // Bob's referral was clawed back, so his leaving does not release Alice a second time
+    let ix = rotated_leave_ix(&bob_new_wallet, bob_rotated, bob_receipt);
+    process(&mut context, &[ix], &[&bob_new_wallet]).await.unwrap();
+    assert_eq!(active_referees(&mut context, alice_participant).await, 1);
+    let receipt: RefereeReceipt = get_account(&mut context, bob_receipt).await;
+    assert!(!receipt.referee_active);
+
+    // Carol still counts toward Alice, and her leaving releases her
+    let ix = rotated_leave_ix(&carol_new_wallet, carol_rotated, carol_receipt);
+    process(&mut context, &[ix], &[&carol_new_wallet]).await.unwrap();
+    assert_eq!(active_referees(&mut context, alice_participant).await, 0);
+    let receipt: RefereeReceipt = get_account(&mut context, carol_receipt).await;
+    assert!(!receipt.referee_active);
+}
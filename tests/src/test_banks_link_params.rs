@@ -0,0 +1,107 @@
+use anchor_client::solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::LINK_PARAMS_LEN,
+    error::ReferralError,
+    instruction,
+    instructions::{encode_link_params, ReferralPreview},
+    state::{Participant, RefereeReceipt},
+};
+
+use crate::{
+    banks_util::{
+        assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol, get_account,
+        get_clock_time, join_referral_program, join_through_referral, process, program_instruction, setup,
+        simulate_return,
+    },
+    test_util::{get_eligibility_criteria_pda, get_referee_receipt_pda},
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+const SPRING_PARAMS: &[u8] = b"code=SPRING25;variant=b";
+const SUMMER_PARAMS: &[u8] = b"code=SUMMER10;variant=c";
+
+#[test]
+fn test_encode_link_params() {
+    let encoded = encode_link_params(SPRING_PARAMS).unwrap();
+    assert_eq!(&encoded[..SPRING_PARAMS.len()], SPRING_PARAMS);
+    assert!(encoded[SPRING_PARAMS.len()..].iter().all(|byte| *byte == 0));
+    assert_eq!(encode_link_params(&[]).unwrap(), [0u8; LINK_PARAMS_LEN]);
+    assert!(encode_link_params(&[7; LINK_PARAMS_LEN]).is_ok());
+    assert_eq!(encode_link_params(&[7; LINK_PARAMS_LEN + 1]).unwrap_err(), ReferralError::InvalidLinkParams.into());
+}
+
+#[tokio::test]
+async fn test_link_params_resolve_and_are_snapshotted_at_join() {
+    let (mut context, owner, referrer, first_referee) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let referrer_participant = join_referral_program(&mut context, &referrer, referral_program).await;
+
+    // Only the participant's own account takes its parameters, and they cannot overflow the field
+    let set_params_ix = |user: &Keypair, params: &[u8]| set_link_params_ix(user, referral_program, params.to_vec());
+    process(&mut context, &[set_params_ix(&referrer, SPRING_PARAMS)], &[&referrer]).await.unwrap();
+    let participant: Participant = get_account(&mut context, referrer_participant).await;
+    assert_eq!(participant.link_params, encode_link_params(SPRING_PARAMS).unwrap());
+    let result = process(&mut context, &[set_params_ix(&referrer, &[7; LINK_PARAMS_LEN + 1])], &[&referrer]).await;
+    assert_referral_error(result, ReferralError::InvalidLinkParams);
+
+    // Resolving the referral hands the client the parameters, and the join records them
+    let preview = preview_referral(&mut context, referral_program, referrer_participant).await;
+    assert_eq!(preview.link_params, encode_link_params(SPRING_PARAMS).unwrap());
+    join_through_referral(&mut context, &first_referee, referral_program, referrer_participant).await;
+    let first_receipt = get_referee_receipt_pda(referral_program, first_referee.pubkey(), solrefer::ID);
+    let receipt: RefereeReceipt = get_account(&mut context, first_receipt).await;
+    assert_eq!(receipt.referrer_link_params, encode_link_params(SPRING_PARAMS).unwrap());
+
+    // New parameters only reach joins made after the change
+    process(&mut context, &[set_params_ix(&referrer, SUMMER_PARAMS)], &[&referrer]).await.unwrap();
+    let preview = preview_referral(&mut context, referral_program, referrer_participant).await;
+    assert_eq!(preview.link_params, encode_link_params(SUMMER_PARAMS).unwrap());
+    let second_referee = create_funded_user(&mut context).await;
+    join_through_referral(&mut context, &second_referee, referral_program, referrer_participant).await;
+    let receipt: RefereeReceipt = get_account(&mut context, first_receipt).await;
+    assert_eq!(receipt.referrer_link_params, encode_link_params(SPRING_PARAMS).unwrap());
+    let second_receipt = get_referee_receipt_pda(referral_program, second_referee.pubkey(), solrefer::ID);
+    let receipt: RefereeReceipt = get_account(&mut context, second_receipt).await;
+    assert_eq!(receipt.referrer_link_params, encode_link_params(SUMMER_PARAMS).unwrap());
+
+    // Empty parameters clear them for the next referee
+    process(&mut context, &[set_params_ix(&referrer, &[])], &[&referrer]).await.unwrap();
+    let preview = preview_referral(&mut context, referral_program, referrer_participant).await;
+    assert_eq!(preview.link_params, [0u8; LINK_PARAMS_LEN]);
+}
+
+fn set_link_params_ix(user: &Keypair, referral_program: Pubkey, params: Vec<u8>) -> Instruction {
+    program_instruction(
+        accounts::SetLinkParams {
+            referral_program,
+            participant: Participant::address(&referral_program, &user.pubkey()),
+            user: user.pubkey(),
+        },
+        instruction::SetLinkParams { params },
+    )
+}
+
+/// Resolves a referral through `referrer` the way a joining client does, with `preview_referral`
+async fn preview_referral(
+    context: &mut ProgramTestContext,
+    referral_program: Pubkey,
+    referrer: Pubkey,
+) -> ReferralPreview {
+    let ix = program_instruction(
+        accounts::PreviewReferral {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            referrer,
+            boost_escrow: None,
+            referrer_token_account: None,
+            referee_token_account: None,
+        },
+        instruction::PreviewReferral,
+    );
+    simulate_return(context, ix).await
+}
@@ -0,0 +1,163 @@
+//! Compact referral payloads for QR codes.
+//!
+//! Payloads round-trip through the codec with and without a campaign code and reject scanning errors. Bob joins
+//! through a payload naming Alice, recording its code as his source tag; payloads naming another program or
+//! referrer, or damaged in transit, are rejected.
+
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer},
+};
+use solrefer::{
+    accounts,
+    constants::{LINK_PAYLOAD_VERSION, MAX_LINK_CODE_LEN},
+    error::ReferralError,
+    instruction,
+    linkcodec::{crc8, decode_referral, encode_referral, DecodedReferral, MAX_LINK_PAYLOAD_LEN},
+    state::{Participant, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol, get_account,
+        get_clock_time, join_referral_program, process, program_instruction, setup,
+    },
+    test_util::{get_eligibility_criteria_pda, get_participant_pda, get_referee_receipt_pda},
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+#[test]
+fn test_payload_round_trips() {
+    let (program, referrer_owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+    for code in [None, Some("Q"), Some("SPRING-24"), Some("ABCDEFGHIJKL")] {
+        let payload = encode_referral(&program, &referrer_owner, code);
+        assert!(payload.len() <= MAX_LINK_PAYLOAD_LEN && MAX_LINK_PAYLOAD_LEN <= 80);
+        assert_eq!(payload[0], LINK_PAYLOAD_VERSION);
+        let decoded = decode_referral(&payload).unwrap();
+        assert_eq!(decoded, DecodedReferral { program, referrer_owner, code: code.map(String::from) });
+    }
+
+    // An empty code is no code at all
+    let payload = encode_referral(&program, &referrer_owner, Some(""));
+    assert_eq!(payload, encode_referral(&program, &referrer_owner, None));
+
+    // The code becomes the zero-padded source tag
+    let decoded = decode_referral(&encode_referral(&program, &referrer_owner, Some("QR"))).unwrap();
+    let mut tag = [0u8; 16];
+    tag[..2].copy_from_slice(b"QR");
+    assert_eq!(decoded.source_tag(), Some(tag));
+}
+
+#[test]
+fn test_payload_rejects_scanning_errors() {
+    let payload = encode_referral(&Pubkey::new_unique(), &Pubkey::new_unique(), Some("SPRING"));
+
+    // Every single-byte error fails the checksum
+    for index in 0..payload.len() {
+        let mut damaged = payload.clone();
+        damaged[index] ^= 0x10;
+        assert_eq!(decode_referral(&damaged).unwrap_err(), ReferralError::ReferralPayloadChecksum.into());
+    }
+
+    // Payloads with a valid checksum must still be well formed
+    let with_checksum = |mut body: Vec<u8>| {
+        body.push(crc8(&body));
+        body
+    };
+    let body = &payload[..payload.len() - 1];
+    let mut future_version = body.to_vec();
+    future_version[0] = LINK_PAYLOAD_VERSION + 1;
+    let mut wrong_length = body.to_vec();
+    wrong_length[65] += 1;
+    let mut unprintable = body.to_vec();
+    unprintable[66] = b' ';
+    let truncated = body[..60].to_vec();
+    for malformed in [future_version, wrong_length, unprintable, truncated].map(with_checksum) {
+        assert_eq!(decode_referral(&malformed).unwrap_err(), ReferralError::InvalidReferralPayload.into());
+    }
+    assert_eq!(decode_referral(&[]).unwrap_err(), ReferralError::InvalidReferralPayload.into());
+}
+
+#[test]
+#[should_panic(expected = "invalid referral link code")]
+fn test_encoding_rejects_overlong_code() {
+    let code = "C".repeat(MAX_LINK_CODE_LEN + 1);
+    encode_referral(&Pubkey::new_unique(), &Pubkey::new_unique(), Some(&code));
+}
+
+/// Builds a join of `user` through `referrer` presenting `payload`
+fn join_with_payload_ix(user: &Keypair, referral_program: Pubkey, referrer: Pubkey, payload: Vec<u8>) -> Instruction {
+    program_instruction(
+        accounts::JoinThroughReferral {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+            referrer,
+            rotated_referrer: None,
+            split_recipient: None,
+            boost_escrow: None,
+            match_offer: None,
+            referrer_wallet: None,
+            referee_receipt: get_referee_receipt_pda(referral_program, user.pubkey(), solrefer::ID),
+            sponsor_vault: None,
+            invite: None,
+            referrer_collection_metadata: None,
+            referrer_collection_nft: None,
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account: None,
+            referee_token_account: None,
+            referrer_upline: None,
+            user: user.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
+            system_program: system_program::ID,
+        },
+        instruction::JoinWithPayload { payload, accepted_terms_hash: [0u8; 32] },
+    )
+}
+
+#[tokio::test]
+async fn test_join_with_payload_credits_named_referrer() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    let carol = create_funded_user(&mut context).await;
+
+    // The payload must name this program and the owner of the referrer account passed
+    let elsewhere = encode_referral(&Pubkey::new_unique(), &alice.pubkey(), None);
+    let result =
+        process(&mut context, &[join_with_payload_ix(&bob, referral_program, alice_participant, elsewhere)], &[&bob])
+            .await;
+    assert_referral_error(result, ReferralError::InvalidReferralPayload);
+    let for_carol = encode_referral(&referral_program, &carol.pubkey(), None);
+    let result =
+        process(&mut context, &[join_with_payload_ix(&bob, referral_program, alice_participant, for_carol)], &[&bob])
+            .await;
+    assert_referral_error(result, ReferralError::InvalidReferrer);
+    let mut damaged = encode_referral(&referral_program, &alice.pubkey(), Some("SPRING"));
+    damaged[40] ^= 0xff;
+    let result =
+        process(&mut context, &[join_with_payload_ix(&bob, referral_program, alice_participant, damaged)], &[&bob])
+            .await;
+    assert_referral_error(result, ReferralError::ReferralPayloadChecksum);
+
+    // A payload from the off-chain encoder joins Bob through Alice under its campaign code
+    let payload = encode_referral(&referral_program, &alice.pubkey(), Some("SPRING"));
+    let ix = join_with_payload_ix(&bob, referral_program, alice_participant, payload);
+    process(&mut context, &[ix], &[&bob]).await.unwrap();
+
+    let bob_participant: Participant =
+        get_account(&mut context, get_participant_pda(referral_program, bob.pubkey(), solrefer::ID)).await;
+    assert_eq!(bob_participant.referrer, Some(alice_participant));
+    assert_eq!(&bob_participant.source_tag[..6], b"SPRING");
+    let alice_account: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!((alice_account.total_referrals, alice_account.pending_rewards), (1, REWARD));
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.source_tag_counts[0].tag, bob_participant.source_tag);
+    assert_eq!(program.source_tag_counts[0].count, 1);
+}
@@ -0,0 +1,224 @@
+//! Signed referral links.
+//!
+//! The program's backend key signs a link vouching for Alice as a referrer; Bob joins through it with the ed25519
+//! program verifying the signature right before the join.
+
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{
+        ed25519_program, instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer, sysvar,
+    },
+};
+use solrefer::{
+    accounts,
+    constants::MIN_LOCKED_PERIOD,
+    error::ReferralError,
+    instruction,
+    instructions::{link_message, parse_ed25519_instruction, LinkProof, ProgramSettings},
+    state::Participant,
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, assert_referral_error_at, create_funded_user,
+        create_sol_referral_program, deposit_sol, get_account, get_clock_time, join_referral_program, process,
+        program_instruction, setup, update_program_settings,
+    },
+    test_util::{get_eligibility_criteria_pda, get_participant_pda, get_referee_receipt_pda},
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_HOUR: i64 = 3600;
+const ONE_YEAR: i64 = 365 * 86400;
+
+fn settings(end_time: i64, link_signer: Option<Pubkey>) -> ProgramSettings {
+    ProgramSettings {
+        fixed_reward_amount: REWARD,
+        locked_period: MIN_LOCKED_PERIOD,
+        program_end_time: Some(end_time),
+        base_reward: REWARD,
+        max_reward_cap: 100 * REWARD,
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
+    }
+}
+
+/// Builds an ed25519 program instruction verifying `signer`'s signature of `message`, all held in the instruction
+fn ed25519_ix(signer: &Keypair, message: &[u8]) -> Instruction {
+    const PUBLIC_KEY_OFFSET: u16 = 16;
+    const SIGNATURE_OFFSET: u16 = PUBLIC_KEY_OFFSET + 32;
+    const MESSAGE_OFFSET: u16 = SIGNATURE_OFFSET + 64;
+    let offsets =
+        [SIGNATURE_OFFSET, u16::MAX, PUBLIC_KEY_OFFSET, u16::MAX, MESSAGE_OFFSET, message.len() as u16, u16::MAX];
+    let mut data = vec![1, 0];
+    data.extend(offsets.iter().flat_map(|offset| offset.to_le_bytes()));
+    data.extend_from_slice(signer.pubkey().as_ref());
+    data.extend_from_slice(signer.sign_message(message).as_ref());
+    data.extend_from_slice(message);
+    Instruction { program_id: ed25519_program::ID, accounts: vec![], data }
+}
+
+/// Signs a link vouching for `referrer_owner` in `referral_program` until `expiry`
+fn sign_link(signer: &Keypair, referral_program: Pubkey, referrer_owner: Pubkey, expiry: i64) -> LinkProof {
+    let message = link_message(&referral_program, &referrer_owner, expiry);
+    let signature: [u8; 64] = signer.sign_message(&message).as_ref().try_into().unwrap();
+    LinkProof { referrer_owner, expiry, signature }
+}
+
+fn join_ix(user: &Keypair, referral_program: Pubkey, referrer: Pubkey, link_proof: Option<LinkProof>) -> Instruction {
+    program_instruction(
+        accounts::JoinThroughReferral {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+            referrer,
+            rotated_referrer: None,
+            split_recipient: None,
+            boost_escrow: None,
+            match_offer: None,
+            referrer_wallet: None,
+            referee_receipt: get_referee_receipt_pda(referral_program, user.pubkey(), solrefer::ID),
+            sponsor_vault: None,
+            invite: None,
+            referrer_collection_metadata: None,
+            referrer_collection_nft: None,
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account: None,
+            referee_token_account: None,
+            referrer_upline: None,
+            user: user.pubkey(),
+            event_queue: None,
+            instructions_sysvar: Some(sysvar::instructions::ID),
+            system_program: system_program::ID,
+        },
+        instruction::JoinThroughReferral { source_tag: None, accepted_terms_hash: [0u8; 32], link_proof },
+    )
+}
+
+#[test]
+fn test_parse_ed25519_instruction_reads_inline_signature() {
+    let signer = Keypair::new();
+    let message = link_message(&Pubkey::new_unique(), &Pubkey::new_unique(), 1_000);
+    let ix = ed25519_ix(&signer, &message);
+
+    let (public_key, signature, signed) = parse_ed25519_instruction(&ix.data).unwrap();
+    assert_eq!(public_key, signer.pubkey().as_ref());
+    assert_eq!(signature, signer.sign_message(&message).as_ref());
+    assert_eq!(signed, message.as_slice());
+
+    // Data held in another instruction, several signatures and truncated data are not read
+    let mut elsewhere = ix.data.clone();
+    elsewhere[14..16].copy_from_slice(&0u16.to_le_bytes());
+    assert!(parse_ed25519_instruction(&elsewhere).is_none());
+    let mut several = ix.data.clone();
+    several[0] = 2;
+    assert!(parse_ed25519_instruction(&several).is_none());
+    assert!(parse_ed25519_instruction(&ix.data[..ix.data.len() - 1]).is_none());
+}
+
+#[tokio::test]
+async fn test_join_requires_signed_link() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let link_signer = Keypair::new();
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    update_program_settings(&mut context, &owner, referral_program, settings(end_time, Some(link_signer.pubkey())))
+        .await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    let carol = create_funded_user(&mut context).await;
+    let carol_participant = join_referral_program(&mut context, &carol, referral_program).await;
+
+    let expiry = get_clock_time(&mut context).await + ONE_HOUR;
+    let proof = sign_link(&link_signer, referral_program, alice.pubkey(), expiry);
+    let verify_ix = ed25519_ix(&link_signer, &link_message(&referral_program, &alice.pubkey(), expiry));
+
+    // Without a link, or without the verifying instruction, the join is rejected
+    let ix = join_ix(&bob, referral_program, alice_participant, None);
+    assert_referral_error(process(&mut context, &[ix], &[&bob]).await, ReferralError::LinkProofRequired);
+    let ix = join_ix(&bob, referral_program, alice_participant, Some(proof));
+    assert_referral_error(process(&mut context, &[ix], &[&bob]).await, ReferralError::InvalidLinkProof);
+
+    // A link signed by another key does not count
+    let forged_ix = ed25519_ix(&Keypair::new(), &link_message(&referral_program, &alice.pubkey(), expiry));
+    let ix = join_ix(&bob, referral_program, alice_participant, Some(proof));
+    let result = process(&mut context, &[forged_ix, ix], &[&bob]).await;
+    assert_referral_error_at(result, 1, ReferralError::InvalidLinkProof);
+
+    // Redirecting Alice's link to Carol fails, whether or not the proof is edited to name her
+    let ix = join_ix(&bob, referral_program, carol_participant, Some(proof));
+    let result = process(&mut context, &[verify_ix.clone(), ix], &[&bob]).await;
+    assert_referral_error_at(result, 1, ReferralError::InvalidReferrer);
+    let tampered = LinkProof { referrer_owner: carol.pubkey(), ..proof };
+    let ix = join_ix(&bob, referral_program, carol_participant, Some(tampered));
+    let result = process(&mut context, &[verify_ix.clone(), ix], &[&bob]).await;
+    assert_referral_error_at(result, 1, ReferralError::InvalidLinkProof);
+
+    // The signed link credits Alice
+    let ix = join_ix(&bob, referral_program, alice_participant, Some(proof));
+    process(&mut context, &[verify_ix, ix], &[&bob]).await.unwrap();
+    let alice_account: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!((alice_account.total_referrals, alice_account.pending_rewards), (1, REWARD));
+
+    // Once expired the link no longer admits anyone
+    advance_clock(&mut context, 2 * ONE_HOUR).await;
+    let dave = create_funded_user(&mut context).await;
+    let verify_ix = ed25519_ix(&link_signer, &link_message(&referral_program, &alice.pubkey(), expiry));
+    let ix = join_ix(&dave, referral_program, alice_participant, Some(proof));
+    let result = process(&mut context, &[verify_ix, ix], &[&dave]).await;
+    assert_referral_error_at(result, 1, ReferralError::LinkProofExpired);
+}
+
+#[tokio::test]
+async fn test_join_without_link_signer_needs_no_link() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    update_program_settings(&mut context, &owner, referral_program, settings(end_time, None)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+
+    // Any proof passed along is ignored
+    let unsigned = LinkProof { referrer_owner: Pubkey::new_unique(), expiry: 0, signature: [0; 64] };
+    let ix = join_ix(&bob, referral_program, alice_participant, Some(unsigned));
+    process(&mut context, &[ix], &[&bob]).await.unwrap();
+    let alice_account: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!(alice_account.total_referrals, 1);
+}
@@ -0,0 +1,208 @@
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer},
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::{
+        CLOSURE_GRACE_PERIOD, MAINTENANCE_CLOSURE_FINALIZED, MAINTENANCE_EXPIRED, MAINTENANCE_REFUNDED,
+        MAINTENANCE_RUNWAY_ALERTS, MAINTENANCE_UNDERFUNDED, MIN_LOCKED_PERIOD,
+    },
+    events::MaintenancePerformed,
+    instruction,
+    instructions::ProgramSettings,
+    state::{get_final_report, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, claim_rewards, create_funded_user, create_sol_referral_program, deposit_sol, get_account,
+        get_balance, get_clock_time, join_referral_program, join_through_referral, process, process_with_events,
+        program_instruction, set_program_status_ix, setup, update_program_settings,
+    },
+    test_util::{get_authority_meta_pda, get_eligibility_criteria_pda, get_final_report_pda, get_sponsor_vault_pda},
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+fn settings(end_time: i64, fixed_reward_amount: u64, alert_thresholds: [u8; 3]) -> ProgramSettings {
+    ProgramSettings {
+        fixed_reward_amount,
+        locked_period: MIN_LOCKED_PERIOD,
+        program_end_time: Some(end_time),
+        base_reward: REWARD,
+        max_reward_cap: 100 * REWARD,
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds,
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
+    }
+}
+
+fn crank_ix(cranker: &Keypair, owner: Pubkey, referral_program: Pubkey, vault: Pubkey) -> Instruction {
+    program_instruction(
+        accounts::CrankMaintenance {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            vault,
+            sponsor_vault: get_sponsor_vault_pda(referral_program, solrefer::ID),
+            authority: owner,
+            authority_meta: get_authority_meta_pda(owner, solrefer::ID),
+            final_report: get_final_report_pda(referral_program, solrefer::ID),
+            cranker: cranker.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::CrankMaintenance {},
+    )
+}
+
+/// Cranks the program and returns the actions it reported, checking the cranker was paid nothing
+async fn crank(context: &mut ProgramTestContext, cranker: &Keypair, owner: Pubkey, program: (Pubkey, Pubkey)) -> u8 {
+    let balance = get_balance(context, cranker.pubkey()).await;
+    let (referral_program, vault) = program;
+    let ix = crank_ix(cranker, owner, referral_program, vault);
+    let events: Vec<MaintenancePerformed> = process_with_events(context, &[ix], &[cranker]).await;
+    assert_eq!(events.len(), 1, "expected exactly one maintenance event");
+    assert_eq!((events[0].referral_program, events[0].cranker), (referral_program, cranker.pubkey()));
+    assert!(get_balance(context, cranker.pubkey()).await <= balance, "the cranker was tipped");
+    events[0].actions_bitmask
+}
+
+/// Creates a program funded with ten rewards whose referrer claims six of them, leaving four available
+async fn create_drained_program(context: &mut ProgramTestContext, owner: &Keypair, end_time: i64) -> (Pubkey, Pubkey) {
+    let (referral_program, vault) = create_sol_referral_program(context, owner, REWARD, Some(end_time)).await;
+    update_program_settings(context, owner, referral_program, settings(end_time, REWARD, [0; 3])).await;
+    deposit_sol(context, owner, referral_program, vault, 10 * REWARD).await;
+
+    let referrer = create_funded_user(context).await;
+    let participant = join_referral_program(context, &referrer, referral_program).await;
+    for _ in 0..6 {
+        let referee = create_funded_user(context).await;
+        join_through_referral(context, &referee, referral_program, participant).await;
+    }
+    advance_clock(context, MIN_LOCKED_PERIOD).await;
+    claim_rewards(context, &referrer, referral_program, participant, vault).await.unwrap();
+    (referral_program, vault)
+}
+
+#[tokio::test]
+async fn test_crank_performs_due_actions_once() {
+    let (mut context, owner, cranker, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let program = create_drained_program(&mut context, &owner, end_time).await;
+    let (referral_program, _) = program;
+
+    // A reward raised past the four left, a 50% alert the drain already crossed, and the end time passing
+    update_program_settings(&mut context, &owner, referral_program, settings(end_time, 5 * REWARD, [50, 25, 0])).await;
+    advance_clock(&mut context, ONE_YEAR).await;
+    let referral_program_account: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert!(referral_program_account.accepting_referrals);
+
+    let actions = crank(&mut context, &cranker, owner.pubkey(), program).await;
+    assert_eq!(actions, MAINTENANCE_UNDERFUNDED | MAINTENANCE_EXPIRED | MAINTENANCE_RUNWAY_ALERTS);
+    let referral_program_account: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert!(!referral_program_account.accepting_referrals);
+    assert_eq!(referral_program_account.alerts_fired, 0b001);
+
+    // Nothing is left to do, and cranking again changes nothing
+    assert_eq!(crank(&mut context, &cranker, owner.pubkey(), program).await, 0);
+    let after: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!((after.accepting_referrals, after.alerts_fired), (false, 0b001));
+}
+
+#[tokio::test]
+async fn test_crank_keeps_accepting_referrals_in_step() {
+    let (mut context, owner, cranker, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let program = create_drained_program(&mut context, &owner, end_time).await;
+    let (referral_program, _) = program;
+
+    update_program_settings(&mut context, &owner, referral_program, settings(end_time, 5 * REWARD, [0; 3])).await;
+    assert_eq!(crank(&mut context, &cranker, owner.pubkey(), program).await, MAINTENANCE_UNDERFUNDED);
+
+    // Lowering the reward back makes the funds left cover it again
+    update_program_settings(&mut context, &owner, referral_program, settings(end_time, REWARD, [0; 3])).await;
+    assert_eq!(crank(&mut context, &cranker, owner.pubkey(), program).await, MAINTENANCE_REFUNDED);
+    let referral_program_account: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert!(referral_program_account.accepting_referrals);
+    assert_eq!(crank(&mut context, &cranker, owner.pubkey(), program).await, 0);
+}
+
+#[tokio::test]
+async fn test_crank_finalizes_elapsed_closure() {
+    let (mut context, owner, cranker, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let close_ix = program_instruction(
+        accounts::CloseReferralProgram {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            vault,
+            sponsor_vault: get_sponsor_vault_pda(referral_program, solrefer::ID),
+            authority_meta: get_authority_meta_pda(owner.pubkey(), solrefer::ID),
+            final_report: get_final_report_pda(referral_program, solrefer::ID),
+            authority: owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::CloseReferralProgram {},
+    );
+    let pause_ix = set_program_status_ix(&owner, referral_program, false);
+    process(&mut context, &[close_ix, pause_ix], &[&owner]).await.unwrap();
+
+    // The closure stays pending through its grace period
+    assert_eq!(crank(&mut context, &cranker, owner.pubkey(), (referral_program, vault)).await, 0);
+    assert!(context.banks_client.get_account(referral_program).await.unwrap().is_some());
+
+    advance_clock(&mut context, CLOSURE_GRACE_PERIOD).await;
+    let owner_balance = get_balance(&mut context, owner.pubkey()).await;
+    let actions = crank(&mut context, &cranker, owner.pubkey(), (referral_program, vault)).await;
+    assert_eq!(actions, MAINTENANCE_CLOSURE_FINALIZED);
+    for address in [referral_program, get_eligibility_criteria_pda(referral_program, solrefer::ID), vault] {
+        assert!(context.banks_client.get_account(address).await.unwrap().is_none());
+    }
+    assert!(get_balance(&mut context, owner.pubkey()).await >= owner_balance + 10 * REWARD);
+    let report = context
+        .banks_client
+        .get_account(get_final_report_pda(referral_program, solrefer::ID))
+        .await
+        .unwrap()
+        .expect("Final report missing");
+    assert_eq!(get_final_report(&report.data).unwrap().total_deposited, 10 * REWARD);
+}
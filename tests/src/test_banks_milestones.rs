@@ -0,0 +1,118 @@
+//! One-time milestone bonuses for referral counts.
+//!
+//! Alice earns a bonus at her second and third referrals. Clawing back a referral drops her below a threshold,
+//! and reaching it again pays the referral but not the bonus a second time.
+
+use anchor_client::solana_sdk::signer::Signer;
+use solrefer::{
+    accounts,
+    constants::MAX_MILESTONES,
+    error::ReferralError,
+    instruction,
+    instructions::ProgramSettings,
+    state::{newly_reached_milestones, validate_milestones, Milestone, Participant, RefereeReceipt},
+};
+
+use crate::{
+    banks_util::{
+        create_funded_user, create_sol_referral_program, deposit_sol, get_account, get_clock_time,
+        join_referral_program, join_through_referral, process, program_instruction, read_program_settings, setup,
+        update_program_settings,
+    },
+    test_util::get_referee_receipt_pda,
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+const MILESTONES: [Milestone; MAX_MILESTONES] = [
+    Milestone { threshold: 2, bonus: 500_000 },
+    Milestone { threshold: 3, bonus: 2_000_000 },
+    Milestone { threshold: 0, bonus: 0 },
+    Milestone { threshold: 0, bonus: 0 },
+];
+
+#[test]
+fn test_validate_milestones() {
+    assert!(validate_milestones(&MILESTONES).is_ok());
+    assert!(validate_milestones(&[Milestone::default(); MAX_MILESTONES]).is_ok());
+
+    // Unused entries may sit between configured ones
+    let mut sparse = MILESTONES;
+    sparse.swap(1, 2);
+    assert!(validate_milestones(&sparse).is_ok());
+
+    let mut descending = MILESTONES;
+    descending[1].threshold = 1;
+    assert_eq!(validate_milestones(&descending).unwrap_err(), ReferralError::InvalidMilestones.into());
+
+    let mut duplicate = MILESTONES;
+    duplicate[1].threshold = 2;
+    assert_eq!(validate_milestones(&duplicate).unwrap_err(), ReferralError::InvalidMilestones.into());
+}
+
+#[test]
+fn test_newly_reached_milestones() {
+    assert_eq!(newly_reached_milestones(&MILESTONES, 1, 0).count(), 0);
+    assert_eq!(newly_reached_milestones(&MILESTONES, 2, 0).collect::<Vec<_>>(), vec![0]);
+    assert_eq!(newly_reached_milestones(&MILESTONES, 5, 0).collect::<Vec<_>>(), vec![0, 1]);
+    assert_eq!(newly_reached_milestones(&MILESTONES, 5, 0b01).collect::<Vec<_>>(), vec![1]);
+    assert_eq!(newly_reached_milestones(&MILESTONES, 5, 0b11).count(), 0);
+}
+
+#[tokio::test]
+async fn test_milestone_bonuses_pay_once() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let carol = create_funded_user(&mut context).await;
+    let dave = create_funded_user(&mut context).await;
+    let eve = create_funded_user(&mut context).await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 20 * REWARD).await;
+    let settings = ProgramSettings {
+        locked_period: 86400,
+        base_reward: REWARD,
+        max_reward_cap: 1_000 * REWARD,
+        milestones: MILESTONES,
+        ..read_program_settings(&mut context, referral_program).await
+    };
+    update_program_settings(&mut context, &owner, referral_program, settings).await;
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+
+    // The second referral crosses the first milestone
+    join_through_referral(&mut context, &bob, referral_program, alice_participant).await;
+    join_through_referral(&mut context, &carol, referral_program, alice_participant).await;
+    let alice_account: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!(alice_account.pending_rewards, 2_000_000 + 500_000);
+    assert_eq!(alice_account.milestones_claimed_bitmap, 0b01);
+
+    // The third crosses the second
+    join_through_referral(&mut context, &dave, referral_program, alice_participant).await;
+    let alice_account: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!(alice_account.pending_rewards, 3_000_000 + 2_500_000);
+    assert_eq!(alice_account.milestones_claimed_bitmap, 0b11);
+
+    // Clawing back dave's referral drops alice below the second threshold
+    let dave_receipt = get_referee_receipt_pda(referral_program, dave.pubkey(), solrefer::ID);
+    let ix = program_instruction(
+        accounts::ClawbackReferral {
+            referral_program,
+            referee_receipt: dave_receipt,
+            referrer: alice_participant,
+            authority: owner.pubkey(),
+        },
+        instruction::ClawbackReferral { idempotency_key: None },
+    );
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    let receipt: RefereeReceipt = get_account(&mut context, dave_receipt).await;
+    assert!(receipt.clawed_back);
+    let alice_account: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!(alice_account.total_referrals, 2);
+    assert_eq!(alice_account.pending_rewards, 2_000_000 + 2_500_000);
+
+    // Crossing it again pays the referral but not the milestone a second time
+    join_through_referral(&mut context, &eve, referral_program, alice_participant).await;
+    let alice_account: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!(alice_account.total_referrals, 3);
+    assert_eq!(alice_account.pending_rewards, 3_000_000 + 2_500_000);
+    assert_eq!(alice_account.milestones_claimed_bitmap, 0b11);
+}
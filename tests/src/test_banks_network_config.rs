@@ -0,0 +1,145 @@
+use anchor_client::solana_sdk::signer::Signer;
+use solrefer::{
+    accounts,
+    constants::{
+        MAX_FEE_PERCENTAGE, MAX_LOCKED_PERIOD, MAX_PROGRAM_DURATION, MIN_LOCKED_PERIOD, MIN_LOCKED_PERIOD_FLOOR,
+    },
+    error::ReferralError,
+    instruction,
+    instructions::ProgramSettings,
+    state::{NetworkConfig, NetworkLimits, ReferralProgram},
+};
+
+use crate::banks_util::{
+    assert_referral_error, create_sol_referral_program, get_account, get_clock_time, process, program_instruction,
+    set_network_config, setup, update_program_settings_ix,
+};
+
+const REFERRAL_REWARD: u64 = 1_000_000;
+const TWO_MINUTES: i64 = 120;
+const ONE_YEAR: i64 = 365 * 86400;
+
+fn settings(locked_period: i64, program_end_time: i64) -> ProgramSettings {
+    ProgramSettings {
+        fixed_reward_amount: REFERRAL_REWARD,
+        locked_period,
+        program_end_time: Some(program_end_time),
+        base_reward: REFERRAL_REWARD,
+        max_reward_cap: 10 * REFERRAL_REWARD,
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
+    }
+}
+
+#[test]
+fn test_network_limits_stay_within_absolute_bounds() {
+    let defaults = NetworkLimits::default();
+    assert_eq!(defaults.min_locked_period, MIN_LOCKED_PERIOD);
+    assert!(defaults.validate().is_ok());
+    assert!(NetworkLimits { min_locked_period: MIN_LOCKED_PERIOD_FLOOR, ..defaults }.validate().is_ok());
+
+    let out_of_bounds = [
+        NetworkLimits { min_locked_period: MIN_LOCKED_PERIOD_FLOOR - 1, ..defaults },
+        NetworkLimits { max_locked_period: MAX_LOCKED_PERIOD + 1, ..defaults },
+        NetworkLimits { min_locked_period: 2 * TWO_MINUTES, max_locked_period: TWO_MINUTES, ..defaults },
+        NetworkLimits { max_fee_percentage: MAX_FEE_PERCENTAGE + 1, ..defaults },
+        NetworkLimits { max_program_duration: MAX_PROGRAM_DURATION + 1, ..defaults },
+        NetworkLimits { max_program_duration: 0, ..defaults },
+        NetworkLimits { min_reward_amount: 0, ..defaults },
+    ];
+    for limits in out_of_bounds {
+        assert_eq!(limits.validate().unwrap_err(), ReferralError::InvalidNetworkConfig.into(), "{limits:?}");
+    }
+}
+
+#[tokio::test]
+async fn test_network_config_overrides_locked_period() {
+    let (mut context, owner, admin, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, _) =
+        create_sol_referral_program(&mut context, &owner, REFERRAL_REWARD, Some(end_time)).await;
+
+    // Without a network config the compile-time minimum applies
+    let update_ix =
+        update_program_settings_ix(&mut context, &owner, referral_program, settings(TWO_MINUTES, end_time)).await;
+    let result = process(&mut context, std::slice::from_ref(&update_ix), &[&owner]).await;
+    assert_referral_error(result, ReferralError::InvalidLockedPeriod);
+
+    // A cluster configured for QA accepts a two minute lock
+    let qa_limits = NetworkLimits { min_locked_period: MIN_LOCKED_PERIOD_FLOOR, ..Default::default() };
+    let network_config = set_network_config(&mut context, admin.pubkey(), qa_limits);
+    process(&mut context, &[update_ix], &[&owner]).await.unwrap();
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.locked_period, TWO_MINUTES);
+
+    // The config itself can never go past the absolute bounds
+    let update_config_ix = |limits: NetworkLimits| {
+        program_instruction(
+            accounts::UpdateNetworkConfig { network_config, admin: admin.pubkey() },
+            instruction::UpdateNetworkConfig { limits },
+        )
+    };
+    let too_short = NetworkLimits { min_locked_period: MIN_LOCKED_PERIOD_FLOOR - 1, ..qa_limits };
+    let result = process(&mut context, &[update_config_ix(too_short)], &[&admin]).await;
+    assert_referral_error(result, ReferralError::InvalidNetworkConfig);
+    let too_long = NetworkLimits { max_program_duration: MAX_PROGRAM_DURATION + 1, ..qa_limits };
+    let result = process(&mut context, &[update_config_ix(too_long)], &[&admin]).await;
+    assert_referral_error(result, ReferralError::InvalidNetworkConfig);
+
+    // Only the admin can change it
+    let result = process(
+        &mut context,
+        &[program_instruction(
+            accounts::UpdateNetworkConfig { network_config, admin: owner.pubkey() },
+            instruction::UpdateNetworkConfig { limits: NetworkLimits::default() },
+        )],
+        &[&owner],
+    )
+    .await;
+    assert_referral_error(result, ReferralError::InvalidAuthority);
+
+    // Restoring the defaults brings the compile-time minimum back
+    process(&mut context, &[update_config_ix(NetworkLimits::default())], &[&admin]).await.unwrap();
+    let config: NetworkConfig = get_account(&mut context, network_config).await;
+    assert_eq!(config.limits, NetworkLimits::default());
+    let update_ix =
+        update_program_settings_ix(&mut context, &owner, referral_program, settings(TWO_MINUTES, end_time)).await;
+    let result = process(&mut context, &[update_ix], &[&owner]).await;
+    assert_referral_error(result, ReferralError::InvalidLockedPeriod);
+}
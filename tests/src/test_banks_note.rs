@@ -0,0 +1,133 @@
+use anchor_client::solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer};
+use solrefer::{
+    accounts,
+    constants::{MIN_LOCKED_PERIOD, PARTICIPANT_NOTE_LEN},
+    error::ReferralError,
+    instruction,
+    instructions::{encode_participant_note, ProgramSettings},
+    state::Participant,
+};
+
+use crate::banks_util::{
+    advance_clock, assert_referral_error, claim_rewards, create_funded_user, create_sol_referral_program, deposit_sol,
+    get_account, get_clock_time, join_referral_program, join_through_referral, process, program_instruction, setup,
+    update_program_settings,
+};
+
+const REFERRAL_REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+const NOTE: &str = "agreed to 15% custom split, contract #442";
+
+fn settings(program_end_time: i64, max_reward_cap: u64) -> ProgramSettings {
+    ProgramSettings {
+        fixed_reward_amount: REFERRAL_REWARD,
+        locked_period: MIN_LOCKED_PERIOD,
+        program_end_time: Some(program_end_time),
+        base_reward: REFERRAL_REWARD,
+        max_reward_cap,
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
+    }
+}
+
+#[test]
+fn test_encode_participant_note() {
+    let encoded = encode_participant_note(NOTE).unwrap();
+    assert_eq!(&encoded[..NOTE.len()], NOTE.as_bytes());
+    assert!(encoded[NOTE.len()..].iter().all(|byte| *byte == 0));
+    assert_eq!(encode_participant_note("").unwrap(), [0u8; PARTICIPANT_NOTE_LEN]);
+
+    // The limit is in bytes, so multi-byte characters count for each of their bytes
+    assert!(encode_participant_note(&"a".repeat(PARTICIPANT_NOTE_LEN)).is_ok());
+    let too_long = "é".repeat(PARTICIPANT_NOTE_LEN / 2) + "a";
+    assert_eq!(encode_participant_note(&too_long).unwrap_err(), ReferralError::InvalidParticipantNote.into());
+}
+
+#[tokio::test]
+async fn test_participant_note_is_authority_only_bookkeeping() {
+    let (mut context, owner, partner, referee) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) =
+        create_sol_referral_program(&mut context, &owner, REFERRAL_REWARD, Some(end_time)).await;
+    update_program_settings(&mut context, &owner, referral_program, settings(end_time, 10 * REFERRAL_REWARD)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REFERRAL_REWARD).await;
+    let partner_participant = join_referral_program(&mut context, &partner, referral_program).await;
+    join_through_referral(&mut context, &referee, referral_program, partner_participant).await;
+
+    let note_ix = |authority: &Keypair, note: &str| {
+        set_participant_note_ix(authority, referral_program, partner_participant, note.to_string())
+    };
+    process(&mut context, &[note_ix(&owner, NOTE)], &[&owner]).await.unwrap();
+    let participant: Participant = get_account(&mut context, partner_participant).await;
+    assert_eq!(participant.authority_note, encode_participant_note(NOTE).unwrap());
+
+    // Neither the participant nor anyone else but the authority can write it, and it cannot overflow
+    let result = process(&mut context, &[note_ix(&partner, "agreed to 50%")], &[&partner]).await;
+    assert_referral_error(result, ReferralError::InvalidAuthority);
+    let stranger = create_funded_user(&mut context).await;
+    let result = process(&mut context, &[note_ix(&stranger, "agreed to 50%")], &[&stranger]).await;
+    assert_referral_error(result, ReferralError::InvalidAuthority);
+    let result = process(&mut context, &[note_ix(&owner, &"a".repeat(PARTICIPANT_NOTE_LEN + 1))], &[&owner]).await;
+    assert_referral_error(result, ReferralError::InvalidParticipantNote);
+
+    // Claims and settings updates leave the note as it was
+    advance_clock(&mut context, MIN_LOCKED_PERIOD + 1).await;
+    claim_rewards(&mut context, &partner, referral_program, partner_participant, vault).await.unwrap();
+    update_program_settings(&mut context, &owner, referral_program, settings(end_time, 20 * REFERRAL_REWARD)).await;
+    let participant: Participant = get_account(&mut context, partner_participant).await;
+    assert_eq!((participant.pending_rewards, participant.total_rewards), (0, REFERRAL_REWARD));
+    assert_eq!(participant.authority_note, encode_participant_note(NOTE).unwrap());
+
+    // An empty note clears it
+    process(&mut context, &[note_ix(&owner, "")], &[&owner]).await.unwrap();
+    let participant: Participant = get_account(&mut context, partner_participant).await;
+    assert_eq!(participant.authority_note, [0u8; PARTICIPANT_NOTE_LEN]);
+}
+
+fn set_participant_note_ix(
+    authority: &Keypair,
+    referral_program: Pubkey,
+    participant: Pubkey,
+    note: String,
+) -> Instruction {
+    program_instruction(
+        accounts::SetParticipantNote { referral_program, participant, authority: authority.pubkey() },
+        instruction::SetParticipantNote { note },
+    )
+}
@@ -0,0 +1,103 @@
+//! Programs created without an end time.
+//!
+//! An open-ended program takes joins and pays claims indefinitely. The authority can later give it an end, which
+//! is then enforced, or remove the end again to reopen it.
+
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer},
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::MIN_LOCKED_PERIOD,
+    error::ReferralError,
+    instruction,
+    state::{EligibilityCriteria, Participant, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, claim_rewards, create_funded_user, create_sol_referral_program,
+        deposit_sol, get_account, get_clock_time, join_referral_program, join_through_referral,
+        next_settings_change_pda, process, program_instruction, setup, try_join_referral_program,
+    },
+    test_util::{get_eligibility_criteria_pda, get_network_config_pda},
+};
+
+const REFERRAL_REWARD: u64 = 1_000_000;
+
+/// Builds `authority`'s `extend_program` moving the program's end to `program_end_time`
+async fn extend_ix(
+    context: &mut ProgramTestContext,
+    authority: &Keypair,
+    referral_program: Pubkey,
+    program_end_time: Option<i64>,
+) -> Instruction {
+    program_instruction(
+        accounts::ExtendProgram {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            network_config: get_network_config_pda(solrefer::ID),
+            authority: authority.pubkey(),
+            settings_change: next_settings_change_pda(context, referral_program).await,
+            system_program: system_program::ID,
+        },
+        instruction::ExtendProgram { program_end_time },
+    )
+}
+
+#[test]
+fn test_open_ended_program_never_ends() {
+    // Expiry checks short-circuit for an open-ended program
+    let program = ReferralProgram { program_end_time: None, ..Default::default() };
+    assert!(!program.has_ended(i64::MAX));
+}
+
+#[tokio::test]
+async fn test_open_ended_program_lifecycle() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REFERRAL_REWARD, None).await;
+    let eligibility_criteria = get_eligibility_criteria_pda(referral_program, solrefer::ID);
+    let program_account: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program_account.program_end_time, None);
+    let criteria: EligibilityCriteria = get_account(&mut context, eligibility_criteria).await;
+    assert_eq!(criteria.program_end_time, None);
+
+    // Joins and claims work without an end time
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REFERRAL_REWARD).await;
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    join_through_referral(&mut context, &bob, referral_program, alice_participant).await;
+    let referee = create_funded_user(&mut context).await;
+    join_through_referral(&mut context, &referee, referral_program, alice_participant).await;
+    advance_clock(&mut context, MIN_LOCKED_PERIOD).await;
+    claim_rewards(&mut context, &alice, referral_program, alice_participant, vault).await.unwrap();
+    let participant: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!(participant.total_rewards, 2 * REFERRAL_REWARD);
+
+    // Converting to a dated program enforces the new end
+    let now = get_clock_time(&mut context).await;
+    let ix = extend_ix(&mut context, &owner, referral_program, Some(now - 1)).await;
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::InvalidProgramEndTime);
+    let end_time = now + 2 * MIN_LOCKED_PERIOD;
+    let ix = extend_ix(&mut context, &owner, referral_program, Some(end_time)).await;
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    let program_account: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program_account.program_end_time, Some(end_time));
+    let criteria: EligibilityCriteria = get_account(&mut context, eligibility_criteria).await;
+    assert_eq!(criteria.program_end_time, Some(end_time));
+
+    advance_clock(&mut context, 2 * MIN_LOCKED_PERIOD).await;
+    let carol = create_funded_user(&mut context).await;
+    let (result, _) = try_join_referral_program(&mut context, &carol, referral_program).await;
+    assert_referral_error(result, ReferralError::ProgramEnded);
+
+    // Only the authority can move the end, and removing it reopens the program
+    let ix = extend_ix(&mut context, &alice, referral_program, None).await;
+    assert_referral_error(process(&mut context, &[ix], &[&alice]).await, ReferralError::InvalidAuthority);
+    let ix = extend_ix(&mut context, &owner, referral_program, None).await;
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    join_referral_program(&mut context, &carol, referral_program).await;
+    let program_account: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program_account.program_end_time, None);
+}
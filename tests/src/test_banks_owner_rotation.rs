@@ -0,0 +1,115 @@
+//! Participants rotating to a new wallet while keeping their referral history.
+//!
+//! Alice moves her participant account, with Bob's referral on it, to a new wallet. Only the new account can
+//! claim, and Carol joining through Alice's old link credits the new account.
+
+use anchor_client::{anchor_lang::system_program, solana_sdk::signer::Signer};
+use solrefer::{accounts, error::ReferralError, instruction, state::Participant};
+
+use crate::{
+    banks_util::{
+        assert_referral_error, claim_rewards, create_funded_user, create_sol_referral_program, deposit_sol,
+        get_account, get_clock_time, join_referral_program, join_through_referral, join_through_referral_accounts,
+        process, program_instruction, rotate_owner, setup,
+    },
+    test_util::{get_participant_pda, get_referee_receipt_pda},
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+#[tokio::test]
+async fn test_owner_rotation_moves_participant() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let alice_new_wallet = create_funded_user(&mut context).await;
+    let carol = create_funded_user(&mut context).await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+
+    let old_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    join_through_referral(&mut context, &bob, referral_program, old_participant).await;
+
+    // Rotate alice's participant account to her new wallet
+    let new_participant = get_participant_pda(referral_program, alice_new_wallet.pubkey(), solrefer::ID);
+    let initiate_ix = program_instruction(
+        accounts::InitiateOwnerRotation { referral_program, participant: old_participant, user: alice.pubkey() },
+        instruction::InitiateOwnerRotation { new_owner: alice_new_wallet.pubkey() },
+    );
+    process(&mut context, &[initiate_ix], &[&alice]).await.unwrap();
+    let complete_ix = program_instruction(
+        accounts::CompleteOwnerRotation {
+            referral_program,
+            old_participant,
+            new_participant,
+            new_owner: alice_new_wallet.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::CompleteOwnerRotation {},
+    );
+    process(&mut context, &[complete_ix], &[&alice_new_wallet]).await.unwrap();
+
+    let old_account: Participant = get_account(&mut context, old_participant).await;
+    let new_account: Participant = get_account(&mut context, new_participant).await;
+    assert_eq!((old_account.rotated_to, old_account.pending_rewards), (Some(new_participant), 0));
+    assert_eq!(new_account.owner, alice_new_wallet.pubkey());
+    assert_eq!(new_account.rotated_from, Some(old_participant));
+    assert_eq!((new_account.total_referrals, new_account.pending_rewards), (1, REWARD));
+
+    // The new account claims; the old one is a tombstone
+    claim_rewards(&mut context, &alice_new_wallet, referral_program, new_participant, vault).await.unwrap();
+    let result = claim_rewards(&mut context, &alice, referral_program, old_participant, vault).await;
+    assert_referral_error(result, ReferralError::ParticipantRotated);
+
+    // A referee joining through the old link credits the new account
+    let ix = program_instruction(
+        accounts::JoinThroughReferral {
+            rotated_referrer: Some(new_participant),
+            ..join_through_referral_accounts(&carol, referral_program, old_participant)
+        },
+        instruction::JoinThroughReferral { source_tag: None, accepted_terms_hash: [0u8; 32], link_proof: None },
+    );
+    process(&mut context, &[ix], &[&carol]).await.unwrap();
+
+    let old_account: Participant = get_account(&mut context, old_participant).await;
+    let new_account: Participant = get_account(&mut context, new_participant).await;
+    assert_eq!(old_account.total_referrals, 1);
+    assert_eq!(new_account.total_referrals, 2);
+    assert_eq!((new_account.pending_rewards, new_account.total_rewards), (REWARD, REWARD));
+    let carol_account: Participant =
+        get_account(&mut context, get_participant_pda(referral_program, carol.pubkey(), solrefer::ID)).await;
+    assert_eq!(carol_account.referrer, Some(new_participant));
+}
+
+#[tokio::test]
+async fn test_clawback_follows_rotation() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let alice_new_wallet = create_funded_user(&mut context).await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+
+    let old_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    join_through_referral(&mut context, &bob, referral_program, old_participant).await;
+    let new_participant = rotate_owner(&mut context, referral_program, &alice, &alice_new_wallet).await;
+
+    // Bob's referral was credited to the old account, but only the account it was rotated to can lose it
+    let clawback_ix = |referrer| {
+        program_instruction(
+            accounts::ClawbackReferral {
+                referral_program,
+                referee_receipt: get_referee_receipt_pda(referral_program, bob.pubkey(), solrefer::ID),
+                referrer,
+                authority: owner.pubkey(),
+            },
+            instruction::ClawbackReferral { idempotency_key: None },
+        )
+    };
+    let result = process(&mut context, &[clawback_ix(old_participant)], &[&owner]).await;
+    assert_referral_error(result, ReferralError::InvalidReferrer);
+    process(&mut context, &[clawback_ix(new_participant)], &[&owner]).await.unwrap();
+
+    let new_account: Participant = get_account(&mut context, new_participant).await;
+    assert_eq!((new_account.total_referrals, new_account.pending_rewards), (0, 0));
+    assert_eq!(new_account.clawed_back, REWARD);
+}
@@ -0,0 +1,195 @@
+//! One payout of every kind, each booked through `record_payout`.
+//!
+//! On a SOL program Alice, Bob and Carol each refer a referee: Dave joins through Alice and is paid his bonus by
+//! the join, Bob claims early, and once the lock has passed Alice claims in SOL and Carol as wrapped SOL. A token
+//! program pays Alice's claim from its token vault. Every payout is appended to the event queue, and both programs
+//! end with their funds conserved against what was deposited.
+
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{pubkey::Pubkey, signer::Signer},
+};
+use anchor_spl::{
+    associated_token::{self, get_associated_token_address},
+    token::{spl_token, spl_token::native_mint},
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::MIN_LOCKED_PERIOD,
+    instruction,
+    instructions::{early_redemption_fee, ProgramSettings},
+    state::{decode_events, EventRecord, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, claim_rewards_accounts, create_funded_token_account, create_funded_user, create_mint,
+        create_sol_referral_program, create_token_account, create_token_referral_program, deposit_sol,
+        deposit_token_ix, get_account, get_balance, get_clock_time, join_referral_program, join_through_referral,
+        join_through_referral_accounts, process, program_instruction, read_program_settings, setup,
+        update_program_settings,
+    },
+    test_util::{get_eligibility_criteria_pda, get_event_queue_pda, get_participant_pda},
+};
+
+const REWARD: u64 = 1_000_000;
+const REFEREE_REWARD: u64 = REWARD / 2;
+const FEE_BPS: u64 = 2_500;
+const DEPOSIT: u64 = 10 * REWARD;
+const ONE_YEAR: i64 = 365 * 86400;
+
+/// Asserts that `referral_program` holds exactly what was deposited, less what it paid out
+async fn assert_funds_conserved(context: &mut ProgramTestContext, referral_program: Pubkey, paid: u64) {
+    let program: ReferralProgram = get_account(context, referral_program).await;
+    assert_eq!(program.total_rewards_distributed, paid);
+    assert_eq!(program.total_available + program.reserved_balance + program.total_rewards_distributed, DEPOSIT);
+    assert_eq!(program.total_deposited, DEPOSIT);
+}
+
+#[tokio::test]
+async fn test_every_payout_kind_conserves_funds() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let (carol, dave) = (create_funded_user(&mut context).await, create_funded_user(&mut context).await);
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, DEPOSIT).await;
+    let settings = ProgramSettings {
+        locked_period: MIN_LOCKED_PERIOD,
+        base_reward: REWARD,
+        max_reward_cap: 100 * REWARD,
+        referee_reward_amount: REFEREE_REWARD,
+        referee_rewards_locked: false,
+        early_redemption_fee: FEE_BPS,
+        ..read_program_settings(&mut context, referral_program).await
+    };
+    update_program_settings(&mut context, &owner, referral_program, settings).await;
+    let event_queue = get_event_queue_pda(referral_program, solrefer::ID);
+    let ix = program_instruction(
+        accounts::InitializeEventQueue {
+            referral_program,
+            event_queue,
+            authority: owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::InitializeEventQueue {},
+    );
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+
+    for user in [&alice, &bob, &carol] {
+        join_referral_program(&mut context, user, referral_program).await;
+    }
+    let [alice_participant, bob_participant, carol_participant] =
+        [&alice, &bob, &carol].map(|user| get_participant_pda(referral_program, user.pubkey(), solrefer::ID));
+    for referrer in [bob_participant, carol_participant] {
+        let referee = create_funded_user(&mut context).await;
+        join_through_referral(&mut context, &referee, referral_program, referrer).await;
+    }
+
+    // Dave's bonus is paid by his join
+    let ix = program_instruction(
+        accounts::JoinAndClaimThroughReferral {
+            join: accounts::JoinThroughReferral {
+                event_queue: Some(event_queue),
+                ..join_through_referral_accounts(&dave, referral_program, alice_participant)
+            },
+            vault,
+            instructions_sysvar: None,
+        },
+        instruction::JoinAndClaimThroughReferral { source_tag: None, accepted_terms_hash: [0u8; 32], link_proof: None },
+    );
+    process(&mut context, &[ix], &[&dave]).await.unwrap();
+
+    // Bob claims before the lock has passed and forfeits the fee
+    let ix = program_instruction(
+        accounts::ClaimRewards {
+            event_queue: Some(event_queue),
+            ..claim_rewards_accounts(&bob, referral_program, bob_participant, vault)
+        },
+        instruction::EarlyClaimRewards {},
+    );
+    process(&mut context, &[ix], &[&bob]).await.unwrap();
+    let fee = early_redemption_fee(REWARD, FEE_BPS).unwrap();
+
+    advance_clock(&mut context, MIN_LOCKED_PERIOD).await;
+    let ix = program_instruction(
+        accounts::ClaimRewards {
+            event_queue: Some(event_queue),
+            ..claim_rewards_accounts(&alice, referral_program, alice_participant, vault)
+        },
+        instruction::ClaimRewards {},
+    );
+    process(&mut context, &[ix], &[&alice]).await.unwrap();
+    let ix = program_instruction(
+        accounts::ClaimRewardsWrapped {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant: carol_participant,
+            claim_splitter: None,
+            vault,
+            native_mint: native_mint::ID,
+            user_wsol_account: get_associated_token_address(&carol.pubkey(), &native_mint::ID),
+            user: carol.pubkey(),
+            event_queue: Some(event_queue),
+            instructions_sysvar: None,
+            region_attestation: None,
+            token_program: spl_token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        },
+        instruction::ClaimRewardsWrapped {},
+    );
+    process(&mut context, &[ix], &[&carol]).await.unwrap();
+
+    let account = context.banks_client.get_account(event_queue).await.unwrap().expect("Event queue missing");
+    let (_, events) = decode_events(&account.data, 0).unwrap();
+    let claims: Vec<(Pubkey, u64)> = events
+        .iter()
+        .filter(|event| event.kind == EventRecord::KIND_CLAIM)
+        .map(|event| (event.actor, event.amount))
+        .collect();
+    assert_eq!(
+        claims,
+        vec![
+            (dave.pubkey(), REFEREE_REWARD),
+            (bob.pubkey(), REWARD - fee),
+            (alice.pubkey(), REWARD),
+            (carol.pubkey(), REWARD),
+        ]
+    );
+    let paid = REFEREE_REWARD + REWARD - fee + 2 * REWARD;
+    assert_funds_conserved(&mut context, referral_program, paid).await;
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(get_balance(&mut context, vault).await, program.total_available + program.reserved_balance);
+
+    // A token program's claim is booked the same way
+    let token_owner = create_funded_user(&mut context).await;
+    let mint = create_mint(&mut context, &token_owner).await;
+    let (token_program, token_vault) =
+        create_token_referral_program(&mut context, &token_owner, mint, REWARD, Some(end_time + ONE_YEAR)).await;
+    let depositor_token_account = create_funded_token_account(&mut context, &token_owner, mint, DEPOSIT).await;
+    let ix = deposit_token_ix(&token_owner, token_program, token_vault, mint, depositor_token_account, DEPOSIT);
+    process(&mut context, &[ix], &[&token_owner]).await.unwrap();
+    let alice_token_participant = join_referral_program(&mut context, &alice, token_program).await;
+    let referee = create_funded_user(&mut context).await;
+    join_through_referral(&mut context, &referee, token_program, alice_token_participant).await;
+    advance_clock(&mut context, MIN_LOCKED_PERIOD).await;
+    let ix = program_instruction(
+        accounts::ClaimTokenRewards {
+            referral_program: token_program,
+            eligibility_criteria: get_eligibility_criteria_pda(token_program, solrefer::ID),
+            participant: alice_token_participant,
+            claim_splitter: None,
+            token_vault,
+            user_token_account: create_token_account(&mut context, alice.pubkey(), mint).await,
+            user: alice.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
+            region_attestation: None,
+            token_program: spl_token::id(),
+        },
+        instruction::ClaimTokenRewards {},
+    );
+    process(&mut context, &[ix], &[&alice]).await.unwrap();
+    assert_funds_conserved(&mut context, token_program, REWARD).await;
+}
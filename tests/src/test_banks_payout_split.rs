@@ -0,0 +1,101 @@
+//! Participant payout splits for sub-affiliates.
+//!
+//! An agency routes 20% of each referral reward to its sub-affiliate until it clears the split; clearing only
+//! affects later credits, and a share above half the reward is rejected.
+
+use anchor_client::solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer};
+use solrefer::{
+    accounts,
+    error::ReferralError,
+    instruction,
+    state::{Participant, PayoutSplit, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol, get_account,
+        get_clock_time, join_referral_program, join_through_referral, join_through_referral_accounts, process,
+        program_instruction, setup,
+    },
+    test_util::get_participant_pda,
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+fn set_payout_split_ix(user: &Keypair, referral_program: Pubkey, recipient: Pubkey, bps: u16) -> Instruction {
+    program_instruction(
+        accounts::SetPayoutSplit {
+            referral_program,
+            participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+            recipient_participant: Some(get_participant_pda(referral_program, recipient, solrefer::ID)),
+            user: user.pubkey(),
+        },
+        instruction::SetPayoutSplit { recipient, bps },
+    )
+}
+
+#[test]
+fn test_payout_split_rounds_share_down() {
+    let split = PayoutSplit { recipient: Pubkey::new_unique(), bps: 2_000 };
+    assert_eq!(split.split(1_000_000), (800_000, 200_000));
+    assert_eq!(split.split(9), (8, 1));
+    assert_eq!(split.split(0), (0, 0));
+
+    let max = PayoutSplit { recipient: Pubkey::new_unique(), bps: 5_000 };
+    assert_eq!(max.split(u64::MAX), (u64::MAX - u64::MAX / 2, u64::MAX / 2));
+}
+
+#[tokio::test]
+async fn test_payout_split_routes_share_until_cleared() {
+    let (mut context, owner, agency, sub_affiliate) = setup().await;
+    let first_referee = create_funded_user(&mut context).await;
+    let second_referee = create_funded_user(&mut context).await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let agency_participant = join_referral_program(&mut context, &agency, referral_program).await;
+    let sub_participant = join_referral_program(&mut context, &sub_affiliate, referral_program).await;
+
+    // A 20% split to the sub-affiliate
+    let ix = set_payout_split_ix(&agency, referral_program, sub_affiliate.pubkey(), 2_000);
+    process(&mut context, &[ix], &[&agency]).await.unwrap();
+    let ix = program_instruction(
+        accounts::JoinThroughReferral {
+            split_recipient: Some(sub_participant),
+            ..join_through_referral_accounts(&first_referee, referral_program, agency_participant)
+        },
+        instruction::JoinThroughReferral { source_tag: None, accepted_terms_hash: [0u8; 32], link_proof: None },
+    );
+    process(&mut context, &[ix], &[&first_referee]).await.unwrap();
+
+    let agency_account: Participant = get_account(&mut context, agency_participant).await;
+    let sub_account: Participant = get_account(&mut context, sub_participant).await;
+    assert_eq!((agency_account.pending_rewards, sub_account.pending_rewards), (800_000, 200_000));
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.total_committed, REWARD);
+
+    // Clearing only affects future credits
+    let ix = set_payout_split_ix(&agency, referral_program, sub_affiliate.pubkey(), 0);
+    process(&mut context, &[ix], &[&agency]).await.unwrap();
+    join_through_referral(&mut context, &second_referee, referral_program, agency_participant).await;
+
+    let agency_account: Participant = get_account(&mut context, agency_participant).await;
+    let sub_account: Participant = get_account(&mut context, sub_participant).await;
+    assert_eq!(agency_account.payout_split, None);
+    assert_eq!((agency_account.pending_rewards, sub_account.pending_rewards), (1_800_000, 200_000));
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.total_committed, 2 * REWARD);
+}
+
+#[tokio::test]
+async fn test_payout_split_above_limit_fails() {
+    let (mut context, owner, agency, sub_affiliate) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, _) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    join_referral_program(&mut context, &agency, referral_program).await;
+    join_referral_program(&mut context, &sub_affiliate, referral_program).await;
+
+    let ix = set_payout_split_ix(&agency, referral_program, sub_affiliate.pubkey(), 5_001);
+    assert_referral_error(process(&mut context, &[ix], &[&agency]).await, ReferralError::InvalidPayoutSplit);
+}
@@ -0,0 +1,232 @@
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{pubkey::Pubkey, signer::Signer},
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::{LINK_PARAMS_LEN, MAX_MILESTONES, MIN_LOCKED_PERIOD},
+    instruction,
+    instructions::{referral_credit, referral_preview, ProgramSettings, ReferralPreview},
+    state::{EligibilityCriteria, Milestone, Participant, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, create_funded_user, create_sol_referral_program, deposit_sol, get_account, get_clock_time,
+        join_referral_program, join_through_referral_with_boost, process, program_instruction, setup, simulate_return,
+        update_program_settings,
+    },
+    test_util::{get_boost_escrow_pda, get_eligibility_criteria_pda},
+};
+
+const REFERRAL_REWARD: u64 = 100_000_000;
+const REFEREE_REWARD: u64 = 20_000_000;
+const MILESTONE_BONUS: u64 = 30_000_000;
+const BOOST: u64 = 50_000_000;
+const WINDOW: i64 = 3600;
+const ONE_YEAR: i64 = 365 * 86400;
+
+const MILESTONES: [Milestone; MAX_MILESTONES] = [
+    Milestone { threshold: 2, bonus: MILESTONE_BONUS },
+    Milestone { threshold: 0, bonus: 0 },
+    Milestone { threshold: 0, bonus: 0 },
+    Milestone { threshold: 0, bonus: 0 },
+];
+
+#[test]
+fn test_preview_of_unfunded_milestone_and_full_window() {
+    let program = ReferralProgram {
+        fixed_reward_amount: REFERRAL_REWARD,
+        referee_reward_amount: REFEREE_REWARD,
+        total_available: REFERRAL_REWARD + REFEREE_REWARD,
+        accepting_referrals: true,
+        ..Default::default()
+    };
+    let criteria = EligibilityCriteria {
+        milestones: MILESTONES,
+        max_referrals_per_window: 1,
+        referral_window_seconds: WINDOW,
+        ..Default::default()
+    };
+    let referrer = Participant { total_referrals: 1, window_start: 1_000, ..Default::default() };
+
+    // The milestone bonus does not fit in the headroom left after the reward, so it is not part of the credit
+    let credit = referral_credit(&program, &criteria, &referrer, 1_000).unwrap();
+    assert_eq!(credit.milestones_paid, 0);
+    let preview = referral_preview(&program, &credit, BOOST).unwrap();
+    assert_eq!(
+        preview,
+        ReferralPreview {
+            referrer_credit: REFERRAL_REWARD,
+            referee_credit: REFEREE_REWARD + BOOST,
+            would_be_rate_limited: false,
+            budget_sufficient: true,
+            program_underfunded: false,
+            referrer_requirement_failed: false,
+            referee_requirement_failed: false,
+            link_params: [0; LINK_PARAMS_LEN],
+        }
+    );
+
+    // A full window credits nothing until it elapses
+    let referrer = Participant { referrals_in_window: 1, ..referrer };
+    let credit = referral_credit(&program, &criteria, &referrer, 1_000 + WINDOW - 1).unwrap();
+    let preview = referral_preview(&program, &credit, 0).unwrap();
+    assert_eq!(preview, ReferralPreview { would_be_rate_limited: true, budget_sufficient: true, ..Default::default() });
+    let credit = referral_credit(&program, &criteria, &referrer, 1_000 + WINDOW).unwrap();
+    assert!(credit.is_credited());
+
+    // Once the reward itself exceeds the uncommitted funds the budget is reported short
+    let program = ReferralProgram { total_committed: REFEREE_REWARD + 1, ..program };
+    let preview = referral_preview(&program, &credit, 0).unwrap();
+    assert!(!preview.budget_sufficient);
+}
+
+#[tokio::test]
+async fn test_preview_matches_join() {
+    let (mut context, owner, referrer, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) =
+        create_sol_referral_program(&mut context, &owner, REFERRAL_REWARD, Some(end_time)).await;
+    update_program_settings(
+        &mut context,
+        &owner,
+        referral_program,
+        ProgramSettings {
+            fixed_reward_amount: REFERRAL_REWARD,
+            locked_period: MIN_LOCKED_PERIOD,
+            program_end_time: Some(end_time),
+            base_reward: REFERRAL_REWARD,
+            max_reward_cap: 10 * REFERRAL_REWARD,
+            max_depth: 0,
+            milestones: MILESTONES,
+            invite_only: false,
+            revenue_share_percent: 0,
+            referee_reward_amount: REFEREE_REWARD,
+            referee_rewards_locked: false,
+            reserve_bps: 0,
+            max_referrals_per_window: 1,
+            referral_window_seconds: WINDOW,
+            rate_limit_strict: false,
+            required_collection: None,
+            collection_gates_credits: false,
+            transfers_enabled: false,
+            dispute_window_seconds: 0,
+            rent_payer_mode: 0,
+            min_joiner_balance: 0,
+            min_account_age_seconds: 0,
+            alert_thresholds: [0; 3],
+            referrer_requirement: None,
+            referee_requirement: None,
+            direct_claims_only: false,
+            trailing_commission_bps: 0,
+            link_signer: None,
+            dormancy_period_seconds: 0,
+            region_attestor: None,
+            embargoed_regions: [0; 8],
+            region_checked_on_claim: false,
+            bridge_source_program: None,
+            bridge_bps: 0,
+            cleanup_bounty_bps: 0,
+            event_mode: 0,
+            attribution_grace_seconds: 0,
+            tier1_reward: 0,
+            tier2_reward: 0,
+            auto_downgrade: false,
+            min_remaining_credits: 0,
+            seed_reclaim_seconds: 0,
+            early_redemption_fee: 0,
+        },
+    )
+    .await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REFERRAL_REWARD).await;
+
+    let referrer_participant = join_referral_program(&mut context, &referrer, referral_program).await;
+    let boost_escrow = get_boost_escrow_pda(referral_program, referrer_participant, solrefer::ID);
+    let fund_ix = program_instruction(
+        accounts::FundRefereeBoost {
+            referral_program,
+            participant: referrer_participant,
+            boost_escrow,
+            user: referrer.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::FundRefereeBoost { amount: 3 * BOOST, boost_per_referee: BOOST },
+    );
+    process(&mut context, &[fund_ix], &[&referrer]).await.unwrap();
+
+    // The first referral earns the reward, the sign-up bonus and the boost
+    let preview = preview_referral(&mut context, referral_program, referrer_participant, Some(boost_escrow)).await;
+    assert_eq!(
+        preview,
+        ReferralPreview {
+            referrer_credit: REFERRAL_REWARD,
+            referee_credit: REFEREE_REWARD + BOOST,
+            would_be_rate_limited: false,
+            budget_sufficient: true,
+            program_underfunded: false,
+            referrer_requirement_failed: false,
+            referee_requirement_failed: false,
+            link_params: [0; LINK_PARAMS_LEN],
+        }
+    );
+    assert_join_matches(&mut context, referral_program, referrer_participant, boost_escrow, preview).await;
+
+    // The referrer's window is now full, so a second referral would credit nothing
+    let preview = preview_referral(&mut context, referral_program, referrer_participant, Some(boost_escrow)).await;
+    assert_eq!(preview, ReferralPreview { would_be_rate_limited: true, budget_sufficient: true, ..Default::default() });
+    assert_join_matches(&mut context, referral_program, referrer_participant, boost_escrow, preview).await;
+
+    // Once the window elapses the next credited referral also reaches the milestone
+    advance_clock(&mut context, WINDOW).await;
+    let preview = preview_referral(&mut context, referral_program, referrer_participant, Some(boost_escrow)).await;
+    assert_eq!(preview.referrer_credit, REFERRAL_REWARD + MILESTONE_BONUS);
+    assert_eq!(preview.referee_credit, REFEREE_REWARD + BOOST);
+    assert!(!preview.would_be_rate_limited);
+
+    // Without the escrow the boost is left out
+    let unboosted = preview_referral(&mut context, referral_program, referrer_participant, None).await;
+    assert_eq!(unboosted, ReferralPreview { referee_credit: REFEREE_REWARD, ..preview });
+    assert_join_matches(&mut context, referral_program, referrer_participant, boost_escrow, preview).await;
+}
+
+/// Previews a referral through `referrer`, passing its boost escrow when given
+async fn preview_referral(
+    context: &mut ProgramTestContext,
+    referral_program: Pubkey,
+    referrer: Pubkey,
+    boost_escrow: Option<Pubkey>,
+) -> ReferralPreview {
+    let ix = program_instruction(
+        accounts::PreviewReferral {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            referrer,
+            boost_escrow,
+            referrer_token_account: None,
+            referee_token_account: None,
+        },
+        instruction::PreviewReferral,
+    );
+    simulate_return(context, ix).await
+}
+
+/// Joins a new referee through `referrer` with its boost escrow and checks that both sides were credited as
+/// `preview` said
+async fn assert_join_matches(
+    context: &mut ProgramTestContext,
+    referral_program: Pubkey,
+    referrer: Pubkey,
+    boost_escrow: Pubkey,
+    preview: ReferralPreview,
+) {
+    let before: Participant = get_account(context, referrer).await;
+    let referee = create_funded_user(context).await;
+    let participant =
+        join_through_referral_with_boost(context, &referee, referral_program, referrer, Some(boost_escrow)).await;
+    let after: Participant = get_account(context, referrer).await;
+    assert_eq!(after.pending_rewards - before.pending_rewards, preview.referrer_credit);
+    let participant: Participant = get_account(context, participant).await;
+    assert_eq!(participant.pending_rewards + participant.boost_received, preview.referee_credit);
+}
@@ -0,0 +1,61 @@
+//! One authority running several referral programs, told apart by their `program_index`.
+//!
+//! The owner creates a program at index 0 and another at index 1 with twice the reward. Alice joins both and
+//! refers Bob to the second one only, so each program keeps its own participants, funds and rewards.
+
+use anchor_client::solana_sdk::{pubkey::Pubkey, signer::Signer};
+use solrefer::{
+    constants::REFERRAL_PROGRAM_SEED,
+    state::{AuthorityMeta, Participant, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        create_indexed_referral_program_ix, create_sol_referral_program, deposit_sol, get_account, get_clock_time,
+        indexed_referral_program_pdas, join_referral_program, join_through_referral, process, setup,
+    },
+    test_util::get_authority_meta_pda,
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+#[tokio::test]
+async fn test_one_authority_runs_two_programs() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (first_program, first_vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    let ix = create_indexed_referral_program_ix(&owner, 1, None, 2 * REWARD, Some(end_time), false);
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    let (second_program, second_vault, _) = indexed_referral_program_pdas(owner.pubkey(), 1);
+    assert_ne!(first_program, second_program);
+
+    // The first program keeps the address programs had before they were indexed
+    let (legacy_address, _) =
+        Pubkey::find_program_address(&[REFERRAL_PROGRAM_SEED, owner.pubkey().as_ref()], &solrefer::ID);
+    assert_eq!(first_program, legacy_address);
+    deposit_sol(&mut context, &owner, first_program, first_vault, 10 * REWARD).await;
+    deposit_sol(&mut context, &owner, second_program, second_vault, 20 * REWARD).await;
+
+    // Alice joins each program on its own, and only her second participant earns from Bob
+    let first_participant = join_referral_program(&mut context, &alice, first_program).await;
+    let second_participant = join_referral_program(&mut context, &alice, second_program).await;
+    assert_ne!(first_participant, second_participant);
+    join_through_referral(&mut context, &bob, second_program, second_participant).await;
+
+    let first: Participant = get_account(&mut context, first_participant).await;
+    let second: Participant = get_account(&mut context, second_participant).await;
+    assert_eq!((first.total_referrals, first.pending_rewards), (0, 0));
+    assert_eq!((second.total_referrals, second.pending_rewards), (1, 2 * REWARD));
+
+    let first: ReferralProgram = get_account(&mut context, first_program).await;
+    let second: ReferralProgram = get_account(&mut context, second_program).await;
+    assert_eq!((first.program_index, first.total_participants, first.total_available), (0, 1, 10 * REWARD));
+    assert_eq!((second.program_index, second.total_participants, second.total_available), (1, 2, 20 * REWARD));
+
+    // Both count against the authority's programs, and an index in use cannot be taken again
+    let meta: AuthorityMeta = get_account(&mut context, get_authority_meta_pda(owner.pubkey(), solrefer::ID)).await;
+    assert_eq!((meta.active_programs, meta.total_created), (2, 2));
+    let ix = create_indexed_referral_program_ix(&owner, 1, None, REWARD, Some(end_time), false);
+    assert!(process(&mut context, &[ix], &[&owner]).await.is_err());
+}
@@ -0,0 +1,101 @@
+//! The authority pausing and unpausing a live program.
+//!
+//! While Alice's program is paused nobody can join, directly or through her link, and the authority cannot
+//! deposit, but Alice still claims the reward she earned before the pause. Once unpaused, joins work again.
+
+use anchor_client::solana_sdk::{pubkey::Pubkey, signer::Signer};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    constants::MIN_LOCKED_PERIOD,
+    error::ReferralError,
+    events::ProgramStatusChanged,
+    instructions::{current_settings, ProgramSettings},
+    state::{EligibilityCriteria, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, claim_rewards, create_funded_user, create_referral_program_ix,
+        create_sol_referral_program, deposit_sol, deposit_sol_ix, get_account, get_balance, get_clock_time,
+        join_referral_program, join_through_referral, process, process_with_events, referral_program_pdas,
+        set_program_status_ix, setup, try_join_referral_program, try_join_through_referral, update_program_settings,
+    },
+    test_util::get_eligibility_criteria_pda,
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+/// Returns the settings of `referral_program` with `REWARD` per referral
+async fn settings(context: &mut ProgramTestContext, referral_program: Pubkey) -> ProgramSettings {
+    let program: ReferralProgram = get_account(context, referral_program).await;
+    let criteria: EligibilityCriteria =
+        get_account(context, get_eligibility_criteria_pda(referral_program, solrefer::ID)).await;
+    ProgramSettings {
+        locked_period: MIN_LOCKED_PERIOD,
+        base_reward: REWARD,
+        max_reward_cap: 100 * REWARD,
+        ..current_settings(&program, &criteria)
+    }
+}
+
+#[tokio::test]
+async fn test_paused_program_still_pays_earned_rewards() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    let program_settings = settings(&mut context, referral_program).await;
+    update_program_settings(&mut context, &owner, referral_program, program_settings).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    join_through_referral(&mut context, &bob, referral_program, alice_participant).await;
+
+    // Only the authority can pause the program
+    let ix = set_program_status_ix(&alice, referral_program, false);
+    assert_referral_error(process(&mut context, &[ix], &[&alice]).await, ReferralError::InvalidAuthority);
+    let ix = set_program_status_ix(&owner, referral_program, false);
+    let events: Vec<ProgramStatusChanged> = process_with_events(&mut context, &[ix], &[&owner]).await;
+    assert_eq!(events.len(), 1);
+    assert!(!events[0].active);
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert!(!program.is_active);
+
+    // Nobody joins and nothing is deposited while it is paused
+    let carol = create_funded_user(&mut context).await;
+    let dave = create_funded_user(&mut context).await;
+    let (result, _) = try_join_referral_program(&mut context, &carol, referral_program).await;
+    assert_referral_error(result, ReferralError::ProgramInactive);
+    let (result, _) = try_join_through_referral(&mut context, &dave, referral_program, alice_participant).await;
+    assert_referral_error(result, ReferralError::ProgramInactive);
+    let ix = deposit_sol_ix(&owner, referral_program, vault, REWARD);
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::ProgramInactive);
+
+    // Alice still claims what she earned before the pause
+    advance_clock(&mut context, MIN_LOCKED_PERIOD + 1).await;
+    let before = get_balance(&mut context, alice.pubkey()).await;
+    claim_rewards(&mut context, &alice, referral_program, alice_participant, vault).await.unwrap();
+    assert_eq!(get_balance(&mut context, alice.pubkey()).await, before + REWARD);
+
+    // Once unpaused, joins work again
+    let ix = set_program_status_ix(&owner, referral_program, true);
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    join_referral_program(&mut context, &carol, referral_program).await;
+    join_through_referral(&mut context, &dave, referral_program, alice_participant).await;
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.total_participants, 4);
+}
+
+#[tokio::test]
+async fn test_program_never_activated_cannot_be_unpaused() {
+    let (mut context, owner, ..) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let create_ix = create_referral_program_ix(&owner, None, REWARD, Some(end_time), true);
+    process(&mut context, &[create_ix], &[&owner]).await.unwrap();
+    let (referral_program, _, _) = referral_program_pdas(owner.pubkey());
+
+    // Going live the first time is left to activate_program and its setup checks
+    let ix = set_program_status_ix(&owner, referral_program, true);
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::ProgramSetupIncomplete);
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert!(!program.is_active);
+}
@@ -0,0 +1,169 @@
+//! Revenue share on purchases made by referred participants.
+//!
+//! Bob, referred by Alice, buys through the program; Alice earns a share of each purchase until her reward cap
+//! is reached, while the attributed volume keeps growing. Batches credit several referrers in one transaction
+//! and must deposit exactly what their purchases add up to.
+
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+        signer::Signer,
+    },
+};
+use solrefer::{
+    accounts,
+    constants::{MAX_PURCHASE_BATCH, MIN_LOCKED_PERIOD},
+    error::ReferralError,
+    instruction,
+    instructions::{ProgramSettings, PurchaseEntry},
+    state::{EligibilityCriteria, Participant, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol, get_account, get_balance,
+        join_referral_program, join_through_referral, process, program_instruction, read_program_settings, setup,
+        update_program_settings,
+    },
+    test_util::get_eligibility_criteria_pda,
+};
+
+const REWARD: u64 = 1_000_000;
+
+#[test]
+fn test_purchase_reward_clamps_to_cap() {
+    let criteria =
+        EligibilityCriteria { revenue_share_percent: 1_000, max_reward_cap: 1_500_000, ..Default::default() };
+
+    assert_eq!(criteria.purchase_reward(3_000_000, 0).unwrap(), 300_000);
+    assert_eq!(criteria.purchase_reward(3_000_000, 1_300_000).unwrap(), 200_000);
+    assert_eq!(criteria.purchase_reward(3_000_000, 1_500_000).unwrap(), 0);
+
+    // A zero cap leaves the share unclamped
+    let uncapped = EligibilityCriteria { max_reward_cap: 0, ..criteria };
+    assert_eq!(uncapped.purchase_reward(3_000_000, 10_000_000).unwrap(), 300_000);
+}
+
+#[tokio::test]
+async fn test_attributed_volume_grows_past_reward_cap() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, None).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let settings = ProgramSettings {
+        locked_period: MIN_LOCKED_PERIOD,
+        base_reward: REWARD,
+        max_reward_cap: 1_500_000,
+        revenue_share_percent: 1_000,
+        ..read_program_settings(&mut context, referral_program).await
+    };
+    update_program_settings(&mut context, &owner, referral_program, settings).await;
+
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    let bob_participant = join_through_referral(&mut context, &bob, referral_program, alice_participant).await;
+    let record_purchase_ix = |buyer: Pubkey, referrer: Pubkey| {
+        program_instruction(
+            accounts::RecordPurchase {
+                referral_program,
+                eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+                buyer,
+                referrer,
+                referrer_upline: None,
+                authority: owner.pubkey(),
+            },
+            instruction::RecordPurchase { amount: 3_000_000, idempotency_key: None },
+        )
+    };
+
+    // Alice has earned 1_000_000 for referring bob; 10% of each 3_000_000 purchase fills the rest of her cap
+    let mut expected_pending = REWARD;
+    for expected_reward in [300_000, 200_000, 0] {
+        let ix = record_purchase_ix(bob_participant, alice_participant);
+        process(&mut context, &[ix], &[&owner]).await.unwrap();
+        expected_pending += expected_reward;
+        let alice_account: Participant = get_account(&mut context, alice_participant).await;
+        assert_eq!(alice_account.pending_rewards, expected_pending);
+    }
+
+    let alice_account: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!(alice_account.pending_rewards, 1_500_000);
+    assert_eq!(alice_account.total_attributed_volume, 9_000_000);
+    let program_account: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program_account.total_attributed_volume, 9_000_000);
+
+    // Only the buyer's own referrer can be credited
+    let ix = record_purchase_ix(alice_participant, bob_participant);
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::InvalidReferrer);
+}
+
+#[tokio::test]
+async fn test_record_purchases_batch() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, None).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let settings = ProgramSettings {
+        locked_period: MIN_LOCKED_PERIOD,
+        base_reward: REWARD,
+        max_reward_cap: 100 * REWARD,
+        revenue_share_percent: 1_000,
+        ..read_program_settings(&mut context, referral_program).await
+    };
+    update_program_settings(&mut context, &owner, referral_program, settings).await;
+
+    // Two buyers behind each of two referrers; each referral credits its referrer 1_000_000
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    let bob_participant = join_referral_program(&mut context, &bob, referral_program).await;
+    let mut buyers = Vec::new();
+    for referrer in [alice_participant, alice_participant, bob_participant, bob_participant] {
+        let buyer = create_funded_user(&mut context).await;
+        buyers.push(join_through_referral(&mut context, &buyer, referral_program, referrer).await);
+    }
+
+    let record_batch_ix = |purchases: Vec<PurchaseEntry>, deposit: u64| -> Instruction {
+        let mut ix = program_instruction(
+            accounts::RecordPurchasesBatch {
+                referral_program,
+                eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+                vault,
+                authority: owner.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::RecordPurchasesBatch { purchases, deposit },
+        );
+        ix.accounts.extend(buyers.iter().map(|buyer| AccountMeta::new_readonly(*buyer, false)));
+        ix.accounts.extend([AccountMeta::new(alice_participant, false), AccountMeta::new(bob_participant, false)]);
+        ix
+    };
+    let purchases: Vec<PurchaseEntry> = [1_000_000, 2_000_000, 3_000_000, 4_000_000]
+        .into_iter()
+        .enumerate()
+        .map(|(buyer_index, amount)| PurchaseEntry { amount, buyer_index: buyer_index as u8 })
+        .collect();
+
+    // The deposit must match the purchases it accompanies
+    let ix = record_batch_ix(purchases.clone(), 9_999_999);
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::PurchaseDepositMismatch);
+
+    // Batches are capped per transaction
+    let oversized = vec![PurchaseEntry { amount: 1_000_000, buyer_index: 0 }; MAX_PURCHASE_BATCH + 1];
+    let ix = record_batch_ix(oversized, 1_000_000 * (MAX_PURCHASE_BATCH as u64 + 1));
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::InvalidPurchaseBatch);
+
+    let vault_before = get_balance(&mut context, vault).await;
+    process(&mut context, &[record_batch_ix(purchases, 10_000_000)], &[&owner]).await.unwrap();
+    assert_eq!(get_balance(&mut context, vault).await, vault_before + 10_000_000);
+
+    // 10% of each referrer's buyers' purchases, on top of the referral rewards
+    let alice_account: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!(alice_account.pending_rewards, 2_000_000 + 300_000);
+    assert_eq!(alice_account.total_attributed_volume, 3_000_000);
+    let bob_account: Participant = get_account(&mut context, bob_participant).await;
+    assert_eq!(bob_account.pending_rewards, 2_000_000 + 700_000);
+    assert_eq!(bob_account.total_attributed_volume, 7_000_000);
+
+    let program_account: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program_account.total_available, 20_000_000);
+    assert_eq!(program_account.total_committed, 4_000_000 + 1_000_000);
+    assert_eq!(program_account.total_attributed_volume, 10_000_000);
+}
@@ -0,0 +1,152 @@
+use anchor_client::solana_sdk::signer::Signer;
+use solrefer::{
+    accounts,
+    constants::MIN_LOCKED_PERIOD,
+    error::ReferralError,
+    instruction,
+    instructions::ProgramSettings,
+    state::{EligibilityCriteria, Participant},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol,
+        get_account, get_clock_time, join_referral_program, join_through_referral, process, program_instruction, setup,
+        try_join_through_referral, update_program_settings,
+    },
+    test_util::get_referee_receipt_pda,
+};
+
+const REFERRAL_REWARD: u64 = 1_000_000;
+const WINDOW: i64 = 3_600;
+const ONE_YEAR: i64 = 365 * 86400;
+
+#[test]
+fn test_record_window_referral() {
+    const NOW: i64 = 1_000_000;
+    let criteria =
+        EligibilityCriteria { max_referrals_per_window: 2, referral_window_seconds: WINDOW, ..Default::default() };
+    let mut referrer = Participant::default();
+
+    // The first referral opens a window; the third within it is refused
+    assert!(referrer.record_window_referral(&criteria, NOW));
+    assert_eq!(referrer.window_start, NOW);
+    assert!(referrer.record_window_referral(&criteria, NOW + WINDOW - 1));
+    assert!(!referrer.record_window_referral(&criteria, NOW + WINDOW - 1));
+    assert_eq!(referrer.referrals_in_window, 2);
+
+    // Once the window has elapsed the count starts over
+    assert!(referrer.record_window_referral(&criteria, NOW + WINDOW));
+    assert_eq!((referrer.window_start, referrer.referrals_in_window), (NOW + WINDOW, 1));
+
+    // Without a limit nothing is tracked
+    let mut referrer = Participant::default();
+    for _ in 0..3 {
+        assert!(referrer.record_window_referral(&EligibilityCriteria::default(), NOW));
+    }
+    assert_eq!(referrer.referrals_in_window, 0);
+}
+
+#[tokio::test]
+async fn test_referral_rate_limit() {
+    for strict in [false, true] {
+        let (mut context, owner, referrer, _) = setup().await;
+        let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+        let (referral_program, vault) =
+            create_sol_referral_program(&mut context, &owner, REFERRAL_REWARD, Some(end_time)).await;
+        update_program_settings(
+            &mut context,
+            &owner,
+            referral_program,
+            ProgramSettings {
+                fixed_reward_amount: REFERRAL_REWARD,
+                locked_period: MIN_LOCKED_PERIOD,
+                program_end_time: Some(end_time),
+                base_reward: REFERRAL_REWARD,
+                max_reward_cap: 10 * REFERRAL_REWARD,
+                max_depth: 0,
+                milestones: Default::default(),
+                invite_only: false,
+                revenue_share_percent: 0,
+                referee_reward_amount: 0,
+                referee_rewards_locked: false,
+                reserve_bps: 0,
+                max_referrals_per_window: 2,
+                referral_window_seconds: WINDOW,
+                rate_limit_strict: strict,
+                required_collection: None,
+                collection_gates_credits: false,
+                transfers_enabled: false,
+                dispute_window_seconds: 0,
+                rent_payer_mode: 0,
+                min_joiner_balance: 0,
+                min_account_age_seconds: 0,
+                alert_thresholds: [0; 3],
+                referrer_requirement: None,
+                referee_requirement: None,
+                direct_claims_only: false,
+                trailing_commission_bps: 0,
+                link_signer: None,
+                dormancy_period_seconds: 0,
+                region_attestor: None,
+                embargoed_regions: [0; 8],
+                region_checked_on_claim: false,
+                bridge_source_program: None,
+                bridge_bps: 0,
+                cleanup_bounty_bps: 0,
+                event_mode: 0,
+                attribution_grace_seconds: 0,
+                tier1_reward: 0,
+                tier2_reward: 0,
+                auto_downgrade: false,
+                min_remaining_credits: 0,
+                seed_reclaim_seconds: 0,
+                early_redemption_fee: 0,
+            },
+        )
+        .await;
+        deposit_sol(&mut context, &owner, referral_program, vault, 10 * REFERRAL_REWARD).await;
+        let referrer_participant = join_referral_program(&mut context, &referrer, referral_program).await;
+
+        for _ in 0..2 {
+            let referee = create_funded_user(&mut context).await;
+            join_through_referral(&mut context, &referee, referral_program, referrer_participant).await;
+        }
+
+        // The third join within the window credits nothing, or is rejected in strict mode
+        let referee = create_funded_user(&mut context).await;
+        let (result, _) =
+            try_join_through_referral(&mut context, &referee, referral_program, referrer_participant).await;
+        if strict {
+            assert_referral_error(result, ReferralError::ReferralRateLimited);
+        } else {
+            result.unwrap();
+
+            // The uncredited referral has nothing to claw back
+            let clawback_ix = program_instruction(
+                accounts::ClawbackReferral {
+                    referral_program,
+                    referee_receipt: get_referee_receipt_pda(referral_program, referee.pubkey(), solrefer::ID),
+                    referrer: referrer_participant,
+                    authority: owner.pubkey(),
+                },
+                instruction::ClawbackReferral { idempotency_key: None },
+            );
+            let result = process(&mut context, &[clawback_ix], &[&owner]).await;
+            assert_referral_error(result, ReferralError::InvalidReferralRecord);
+        }
+        let participant: Participant = get_account(&mut context, referrer_participant).await;
+        assert_eq!(participant.total_referrals, 2, "strict: {strict}");
+        assert_eq!(participant.pending_rewards, 2 * REFERRAL_REWARD, "strict: {strict}");
+        assert_eq!(participant.referrals_in_window, 2, "strict: {strict}");
+
+        // A join after the window has elapsed is credited normally
+        advance_clock(&mut context, WINDOW).await;
+        let referee = create_funded_user(&mut context).await;
+        join_through_referral(&mut context, &referee, referral_program, referrer_participant).await;
+        let participant: Participant = get_account(&mut context, referrer_participant).await;
+        assert_eq!(participant.total_referrals, 3, "strict: {strict}");
+        assert_eq!(participant.pending_rewards, 3 * REFERRAL_REWARD, "strict: {strict}");
+        assert_eq!(participant.referrals_in_window, 1, "strict: {strict}");
+    }
+}
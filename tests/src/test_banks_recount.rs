@@ -0,0 +1,114 @@
+//! Recounting a referrer's referrals from their referee receipts.
+//!
+//! Alice refers three wallets and one referral is clawed back. After her counter drifts, a recount over all of her
+//! receipts restores the number that still count, while foreign or repeated receipts are rejected.
+
+use anchor_client::solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signer::Signer,
+};
+use solrefer::{
+    accounts,
+    error::ReferralError,
+    events::ReferralsRecounted,
+    instruction,
+    instructions::test_utils,
+    state::{Participant, RefereeReceipt},
+};
+
+use crate::{
+    banks_util::{
+        assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol, get_account,
+        join_referral_program, join_through_referral, process, program_instruction, setup, simulate_events,
+    },
+    test_util::get_referee_receipt_pda,
+};
+
+const REWARD: u64 = 1_000_000;
+
+/// Builds a `recount_referrals` of `participant` over the given receipts
+fn recount_ix(referral_program: Pubkey, participant: Pubkey, receipts: &[Pubkey]) -> Instruction {
+    let mut ix = program_instruction(
+        accounts::RecountReferrals { referral_program, participant },
+        instruction::RecountReferrals {},
+    );
+    ix.accounts.extend(receipts.iter().map(|receipt| AccountMeta::new_readonly(*receipt, false)));
+    ix
+}
+
+#[tokio::test]
+async fn test_recount_corrects_skewed_count() {
+    let (mut context, owner, alice, _) = setup().await;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, None).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    let mut receipts = Vec::new();
+    for _ in 0..3 {
+        let referee = create_funded_user(&mut context).await;
+        join_through_referral(&mut context, &referee, referral_program, alice_participant).await;
+        receipts.push(get_referee_receipt_pda(referral_program, referee.pubkey(), solrefer::ID));
+    }
+    let clawback_ix = program_instruction(
+        accounts::ClawbackReferral {
+            referral_program,
+            referee_receipt: receipts[2],
+            referrer: alice_participant,
+            authority: owner.pubkey(),
+        },
+        instruction::ClawbackReferral { idempotency_key: None },
+    );
+    process(&mut context, &[clawback_ix], &[&owner]).await.unwrap();
+    let account: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!((account.total_referrals, account.record_count), (2, 3));
+    let receipt: RefereeReceipt = get_account(&mut context, receipts[0]).await;
+    assert!(receipt.counted);
+
+    // Drift the counter away from the receipts
+    let skew_ix = program_instruction(
+        test_utils::accounts::SkewReferralCount {
+            referral_program,
+            participant: alice_participant,
+            authority: owner.pubkey(),
+        },
+        test_utils::instruction::SkewReferralCount { total_referrals: 7 },
+    );
+    process(&mut context, &[skew_ix], &[&owner]).await.unwrap();
+
+    // A partial batch is checked but corrects nothing
+    process(&mut context, &[recount_ix(referral_program, alice_participant, &receipts[..2])], &[]).await.unwrap();
+    let account: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!(account.total_referrals, 7);
+
+    // The full batch restores the count of receipts that were not clawed back
+    let ix = recount_ix(referral_program, alice_participant, &receipts);
+    let events: Vec<ReferralsRecounted> = simulate_events(&mut context, std::slice::from_ref(&ix), &[]).await;
+    assert_eq!(events.len(), 1);
+    assert_eq!((events[0].participant, events[0].old, events[0].new), (alice_participant, 7, 2));
+    process(&mut context, &[ix], &[]).await.unwrap();
+    let account: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!(account.total_referrals, 2);
+}
+
+#[tokio::test]
+async fn test_recount_rejects_foreign_and_repeated_receipts() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let carol = create_funded_user(&mut context).await;
+    let dave = create_funded_user(&mut context).await;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, None).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    let bob_participant = join_referral_program(&mut context, &bob, referral_program).await;
+    join_through_referral(&mut context, &carol, referral_program, alice_participant).await;
+    join_through_referral(&mut context, &dave, referral_program, bob_participant).await;
+    let carol_receipt = get_referee_receipt_pda(referral_program, carol.pubkey(), solrefer::ID);
+    let dave_receipt = get_referee_receipt_pda(referral_program, dave.pubkey(), solrefer::ID);
+
+    let ix = recount_ix(referral_program, alice_participant, &[carol_receipt, carol_receipt]);
+    assert_referral_error(process(&mut context, &[ix], &[]).await, ReferralError::InvalidRecountBatch);
+    let ix = recount_ix(referral_program, alice_participant, &[dave_receipt]);
+    assert_referral_error(process(&mut context, &[ix], &[]).await, ReferralError::InvalidReferralRecord);
+
+    let account: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!(account.total_referrals, 1);
+}
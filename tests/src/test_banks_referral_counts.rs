@@ -0,0 +1,198 @@
+//! Credited and raw referral counts.
+//!
+//! A referrer collects a mix of credited and zero-credit joins, and every consumer of referral counts must use the
+//! credited figure while the raw counters keep every join.
+
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+        signature::Keypair,
+        signer::Signer,
+    },
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::{CONTEST_ESCROW_SEED, CONTEST_SEED, MAX_MILESTONES, MIN_LOCKED_PERIOD},
+    error::ReferralError,
+    instruction,
+    instructions::ProgramSettings,
+    state::{Milestone, Participant, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol,
+        get_account, get_clock_time, join_referral_program, join_through_referral, process, program_instruction, setup,
+        update_program_settings,
+    },
+    test_util::{get_eligibility_criteria_pda, get_referee_receipt_pda},
+};
+
+const REWARD: u64 = 1_000_000;
+const BONUS: u64 = REWARD / 2;
+const THIRTY_DAYS: i64 = 30 * 86400;
+
+/// Creates a program crediting direct referrals only, at most three per referrer in the program's lifetime, with a
+/// milestone bonus at three referrals
+async fn create_program(context: &mut ProgramTestContext, owner: &Keypair) -> Pubkey {
+    let end_time = get_clock_time(context).await + THIRTY_DAYS;
+    let (referral_program, vault) = create_sol_referral_program(context, owner, REWARD, Some(end_time)).await;
+    let mut milestones = [Milestone::default(); MAX_MILESTONES];
+    milestones[0] = Milestone { threshold: 3, bonus: BONUS };
+    update_program_settings(
+        context,
+        owner,
+        referral_program,
+        ProgramSettings {
+            fixed_reward_amount: REWARD,
+            locked_period: MIN_LOCKED_PERIOD,
+            program_end_time: Some(end_time),
+            base_reward: REWARD,
+            max_reward_cap: 100 * REWARD,
+            max_depth: 1,
+            milestones,
+            invite_only: false,
+            revenue_share_percent: 0,
+            referee_reward_amount: 0,
+            referee_rewards_locked: false,
+            reserve_bps: 0,
+            max_referrals_per_window: 3,
+            referral_window_seconds: THIRTY_DAYS,
+            rate_limit_strict: false,
+            required_collection: None,
+            collection_gates_credits: false,
+            transfers_enabled: false,
+            dispute_window_seconds: 0,
+            rent_payer_mode: 0,
+            min_joiner_balance: 0,
+            min_account_age_seconds: 0,
+            alert_thresholds: [0; 3],
+            referrer_requirement: None,
+            referee_requirement: None,
+            direct_claims_only: false,
+            trailing_commission_bps: 0,
+            link_signer: None,
+            dormancy_period_seconds: 0,
+            region_attestor: None,
+            embargoed_regions: [0; 8],
+            region_checked_on_claim: false,
+            bridge_source_program: None,
+            bridge_bps: 0,
+            cleanup_bounty_bps: 0,
+            event_mode: 0,
+            attribution_grace_seconds: 0,
+            tier1_reward: 0,
+            tier2_reward: 0,
+            auto_downgrade: false,
+            min_remaining_credits: 0,
+            seed_reclaim_seconds: 0,
+            early_redemption_fee: 0,
+        },
+    )
+    .await;
+    deposit_sol(context, owner, referral_program, vault, 10 * REWARD).await;
+    referral_program
+}
+
+/// Joins `count` new wallets through `referrer` and returns the first of them
+async fn refer(context: &mut ProgramTestContext, referral_program: Pubkey, referrer: Pubkey, count: usize) -> Keypair {
+    let first = create_funded_user(context).await;
+    join_through_referral(context, &first, referral_program, referrer).await;
+    for _ in 1..count {
+        let referee = create_funded_user(context).await;
+        join_through_referral(context, &referee, referral_program, referrer).await;
+    }
+    first
+}
+
+fn finalize_contest_ix(owner: &Keypair, referral_program: Pubkey, contest: Pubkey, ranking: &[Pubkey]) -> Instruction {
+    let mut ix = program_instruction(
+        accounts::FinalizeContest {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            contest,
+            authority: owner.pubkey(),
+        },
+        instruction::FinalizeContest {},
+    );
+    ix.accounts.extend(ranking.iter().map(|participant| AccountMeta::new_readonly(*participant, false)));
+    ix
+}
+
+#[tokio::test]
+async fn test_only_credited_referrals_count() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let referral_program = create_program(&mut context, &owner).await;
+
+    // Alice's first three referrals credit her; her fourth is over the rate limit
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    let bob_participant = join_through_referral(&mut context, &bob, referral_program, alice_participant).await;
+    let clawed_back_referee = refer(&mut context, referral_program, alice_participant, 3).await;
+    // Bob's five referrals are past the max depth and credit nothing
+    refer(&mut context, referral_program, bob_participant, 5).await;
+
+    let alice_account: Participant = get_account(&mut context, alice_participant).await;
+    let bob_account: Participant = get_account(&mut context, bob_participant).await;
+    assert_eq!((alice_account.total_referrals, alice_account.raw_referrals), (3, 4));
+    assert_eq!((bob_account.total_referrals, bob_account.raw_referrals), (0, 5));
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!((program.total_referrals_credited, program.total_referrals_raw), (3, 9));
+
+    // Milestones are reached on credited referrals: Alice's third earned the bonus, Bob's five did not
+    assert_eq!(alice_account.milestones_claimed_bitmap, 1);
+    assert_eq!(alice_account.pending_rewards, 3 * REWARD + BONUS);
+    assert_eq!((bob_account.milestones_claimed_bitmap, bob_account.pending_rewards), (0, 0));
+
+    // A clawback takes the referral out of the credited counts only
+    let clawback_ix = program_instruction(
+        accounts::ClawbackReferral {
+            referral_program,
+            referee_receipt: get_referee_receipt_pda(referral_program, clawed_back_referee.pubkey(), solrefer::ID),
+            referrer: alice_participant,
+            authority: owner.pubkey(),
+        },
+        instruction::ClawbackReferral { idempotency_key: None },
+    );
+    process(&mut context, &[clawback_ix], &[&owner]).await.unwrap();
+    let alice_account: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!((alice_account.total_referrals, alice_account.raw_referrals), (2, 4));
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!((program.total_referrals_credited, program.total_referrals_raw), (2, 9));
+
+    // Contests rank on credited referrals, so Bob's larger raw count cannot put him first
+    let contest = Pubkey::find_program_address(&[CONTEST_SEED, referral_program.as_ref()], &solrefer::ID).0;
+    let escrow = Pubkey::find_program_address(&[CONTEST_ESCROW_SEED, referral_program.as_ref()], &solrefer::ID).0;
+    let configure_ix = program_instruction(
+        accounts::ConfigureContest {
+            referral_program,
+            contest,
+            authority: owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::ConfigureContest { prizes: [2 * REWARD, REWARD, 0, 0, 0], dispute_window: 0 },
+    );
+    let fund_ix = program_instruction(
+        accounts::FundContest {
+            referral_program,
+            contest,
+            escrow,
+            authority: owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::FundContest { amount: 3 * REWARD },
+    );
+    process(&mut context, &[configure_ix, fund_ix], &[&owner]).await.unwrap();
+    advance_clock(&mut context, THIRTY_DAYS).await;
+
+    let raw_ranking = [bob_participant, alice_participant];
+    let result =
+        process(&mut context, &[finalize_contest_ix(&owner, referral_program, contest, &raw_ranking)], &[&owner]).await;
+    assert_referral_error(result, ReferralError::InvalidContestRanking);
+    let credited_ranking = [alice_participant, bob_participant];
+    process(&mut context, &[finalize_contest_ix(&owner, referral_program, contest, &credited_ranking)], &[&owner])
+        .await
+        .unwrap();
+}
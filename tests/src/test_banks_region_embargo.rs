@@ -0,0 +1,263 @@
+//! Region embargoes enforced through a region attestor's attestations.
+//!
+//! The attestor is mocked by writing attestation accounts owned by its program id straight into the bank: Alice is
+//! attested in Germany, and Bob is turned away until attested outside the embargoed regions.
+
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{
+        account::Account, instruction::Instruction, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signature::Keypair,
+        signer::Signer,
+    },
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::MIN_LOCKED_PERIOD,
+    error::ReferralError,
+    instruction,
+    instructions::{ProgramSettings, RegionAttestation},
+    state::Participant,
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, create_sol_referral_program, deposit_sol, get_account, get_balance,
+        get_clock_time, process, program_instruction, setup, update_program_settings,
+    },
+    test_util::{get_eligibility_criteria_pda, get_participant_pda, get_referee_receipt_pda},
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+const GERMANY: u16 = 276;
+const IRAN: u16 = 364;
+const NORTH_KOREA: u16 = 408;
+
+fn settings(end_time: i64, region_attestor: Option<Pubkey>, region_checked_on_claim: bool) -> ProgramSettings {
+    ProgramSettings {
+        fixed_reward_amount: REWARD,
+        locked_period: MIN_LOCKED_PERIOD,
+        program_end_time: Some(end_time),
+        base_reward: REWARD,
+        max_reward_cap: 100 * REWARD,
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor,
+        embargoed_regions: [IRAN, NORTH_KOREA, 0, 0, 0, 0, 0, 0],
+        region_checked_on_claim,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
+    }
+}
+
+fn attestation_data(wallet: Pubkey, region: u16) -> Vec<u8> {
+    let mut data = wallet.to_bytes().to_vec();
+    data.extend_from_slice(&region.to_le_bytes());
+    data
+}
+
+/// Writes an attestation of `wallet` in `region`, owned by `attestor`, straight into the bank and returns its address
+fn set_region_attestation(context: &mut ProgramTestContext, attestor: Pubkey, wallet: Pubkey, region: u16) -> Pubkey {
+    let address = Pubkey::new_unique();
+    let account = Account {
+        lamports: LAMPORTS_PER_SOL,
+        data: attestation_data(wallet, region),
+        owner: attestor,
+        executable: false,
+        rent_epoch: 0,
+    };
+    context.set_account(&address, &account.into());
+    address
+}
+
+fn join_ix(user: &Keypair, referral_program: Pubkey, region_attestation: Option<Pubkey>) -> Instruction {
+    program_instruction(
+        accounts::JoinReferralProgram {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+            invite: None,
+            collection_metadata: None,
+            collection_nft: None,
+            age_reference: None,
+            region_attestation,
+            referrer_token_account: None,
+            bridge_participant: None,
+            bridge_referrer: None,
+            user: user.pubkey(),
+            event_queue: None,
+            system_program: system_program::ID,
+        },
+        instruction::JoinReferralProgram { accepted_terms_hash: [0u8; 32] },
+    )
+}
+
+fn join_through_referral_ix(
+    user: &Keypair,
+    referral_program: Pubkey,
+    referrer: Pubkey,
+    region_attestation: Option<Pubkey>,
+) -> Instruction {
+    program_instruction(
+        accounts::JoinThroughReferral {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+            referrer,
+            rotated_referrer: None,
+            split_recipient: None,
+            boost_escrow: None,
+            match_offer: None,
+            referrer_wallet: None,
+            referee_receipt: get_referee_receipt_pda(referral_program, user.pubkey(), solrefer::ID),
+            sponsor_vault: None,
+            invite: None,
+            referrer_collection_metadata: None,
+            referrer_collection_nft: None,
+            age_reference: None,
+            region_attestation,
+            referrer_token_account: None,
+            referee_token_account: None,
+            referrer_upline: None,
+            user: user.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
+            system_program: system_program::ID,
+        },
+        instruction::JoinThroughReferral { source_tag: None, accepted_terms_hash: [0u8; 32], link_proof: None },
+    )
+}
+
+fn claim_ix(
+    user: &Keypair,
+    referral_program: Pubkey,
+    vault: Pubkey,
+    region_attestation: Option<Pubkey>,
+) -> Instruction {
+    program_instruction(
+        accounts::ClaimRewards {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+            claim_splitter: None,
+            vault,
+            user: user.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
+            region_attestation,
+            system_program: system_program::ID,
+        },
+        instruction::ClaimRewards {},
+    )
+}
+
+#[test]
+fn test_region_attestation_reads_wallet_and_region() {
+    let wallet = Pubkey::new_unique();
+    let mut data = attestation_data(wallet, GERMANY);
+    assert_eq!(RegionAttestation::parse(&data), Some(RegionAttestation { wallet, region: GERMANY }));
+
+    // Whatever the issuer appends is ignored, while truncated data is not read
+    data.extend_from_slice(&[7; 16]);
+    assert_eq!(RegionAttestation::parse(&data), Some(RegionAttestation { wallet, region: GERMANY }));
+    assert_eq!(RegionAttestation::parse(&data[..33]), None);
+}
+
+#[tokio::test]
+async fn test_join_requires_attestation_outside_embargo() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let attestor = Pubkey::new_unique();
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    update_program_settings(&mut context, &owner, referral_program, settings(end_time, Some(attestor), false)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+
+    // Alice is admitted on an attestation in an open region
+    let alice_attestation = set_region_attestation(&mut context, attestor, alice.pubkey(), GERMANY);
+    let ix = join_ix(&alice, referral_program, Some(alice_attestation));
+    process(&mut context, &[ix], &[&alice]).await.unwrap();
+    let alice_participant = get_participant_pda(referral_program, alice.pubkey(), solrefer::ID);
+
+    // Bob needs the attestor's attestation of Bob's own wallet, naming a region outside the embargo
+    let ix = join_through_referral_ix(&bob, referral_program, alice_participant, None);
+    assert_referral_error(process(&mut context, &[ix], &[&bob]).await, ReferralError::RegionAttestationRequired);
+    let ix = join_through_referral_ix(&bob, referral_program, alice_participant, Some(alice_attestation));
+    assert_referral_error(process(&mut context, &[ix], &[&bob]).await, ReferralError::InvalidRegionAttestation);
+    let forged = set_region_attestation(&mut context, Pubkey::new_unique(), bob.pubkey(), GERMANY);
+    let ix = join_through_referral_ix(&bob, referral_program, alice_participant, Some(forged));
+    assert_referral_error(process(&mut context, &[ix], &[&bob]).await, ReferralError::InvalidRegionAttestation);
+    let no_region = set_region_attestation(&mut context, attestor, bob.pubkey(), 0);
+    let ix = join_through_referral_ix(&bob, referral_program, alice_participant, Some(no_region));
+    assert_referral_error(process(&mut context, &[ix], &[&bob]).await, ReferralError::InvalidRegionAttestation);
+    let embargoed = set_region_attestation(&mut context, attestor, bob.pubkey(), NORTH_KOREA);
+    let ix = join_through_referral_ix(&bob, referral_program, alice_participant, Some(embargoed));
+    assert_referral_error(process(&mut context, &[ix], &[&bob]).await, ReferralError::RegionEmbargoed);
+
+    let bob_attestation = set_region_attestation(&mut context, attestor, bob.pubkey(), GERMANY);
+    let ix = join_through_referral_ix(&bob, referral_program, alice_participant, Some(bob_attestation));
+    process(&mut context, &[ix], &[&bob]).await.unwrap();
+    let alice_account: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!(alice_account.total_referrals, 1);
+}
+
+#[tokio::test]
+async fn test_claims_recheck_region_only_when_enabled() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let attestor = Pubkey::new_unique();
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    update_program_settings(&mut context, &owner, referral_program, settings(end_time, Some(attestor), true)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let alice_attestation = set_region_attestation(&mut context, attestor, alice.pubkey(), GERMANY);
+    process(&mut context, &[join_ix(&alice, referral_program, Some(alice_attestation))], &[&alice]).await.unwrap();
+    let alice_participant = get_participant_pda(referral_program, alice.pubkey(), solrefer::ID);
+    let bob_attestation = set_region_attestation(&mut context, attestor, bob.pubkey(), GERMANY);
+    let ix = join_through_referral_ix(&bob, referral_program, alice_participant, Some(bob_attestation));
+    process(&mut context, &[ix], &[&bob]).await.unwrap();
+    advance_clock(&mut context, MIN_LOCKED_PERIOD).await;
+
+    // Alice's region is re-attested as embargoed after joining
+    let alice_embargoed = set_region_attestation(&mut context, attestor, alice.pubkey(), IRAN);
+    let ix = claim_ix(&alice, referral_program, vault, None);
+    assert_referral_error(process(&mut context, &[ix], &[&alice]).await, ReferralError::RegionAttestationRequired);
+    let ix = claim_ix(&alice, referral_program, vault, Some(alice_embargoed));
+    assert_referral_error(process(&mut context, &[ix], &[&alice]).await, ReferralError::RegionEmbargoed);
+
+    // Without claim-time checks, what Alice earned stays claimable
+    update_program_settings(&mut context, &owner, referral_program, settings(end_time, Some(attestor), false)).await;
+    let before = get_balance(&mut context, alice.pubkey()).await;
+    process(&mut context, &[claim_ix(&alice, referral_program, vault, None)], &[&alice]).await.unwrap();
+    assert_eq!(get_balance(&mut context, alice.pubkey()).await, before + REWARD);
+}
@@ -0,0 +1,117 @@
+//! Reserve share held back from deposits.
+//!
+//! A program with a reserve ring-fences that share of every deposit; claims and new referrals can only draw on
+//! the rest. The reserve is released into the available funds once the program has ended.
+
+use anchor_client::{anchor_lang::system_program, solana_sdk::signer::Signer};
+use solrefer::{
+    accounts,
+    constants::MIN_LOCKED_PERIOD,
+    error::ReferralError,
+    instruction,
+    instructions::ProgramSettings,
+    state::{Participant, ReferralProgram},
+};
+
+use crate::banks_util::{
+    advance_clock, assert_referral_error, claim_rewards, create_funded_user, create_sol_referral_program, deposit_sol,
+    get_account, get_balance, get_clock_time, join_referral_program, join_through_referral, process,
+    program_instruction, read_program_settings, setup, try_join_through_referral, update_program_settings,
+};
+
+const REFERRAL_REWARD: u64 = 9_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+#[test]
+fn test_credit_deposit_ring_fences_reserve() {
+    let mut program = ReferralProgram { reserve_bps: 1_000, ..Default::default() };
+    assert_eq!(program.credit_deposit(20_000_000).unwrap(), 2_000_000);
+    assert_eq!(program.total_available, 18_000_000);
+    assert_eq!(program.reserved_balance, 2_000_000);
+
+    // Changing the share only affects later deposits; fractions round in favour of the available funds
+    program.reserve_bps = 0;
+    assert_eq!(program.credit_deposit(10_000_000).unwrap(), 0);
+    program.reserve_bps = 3;
+    assert_eq!(program.credit_deposit(1_000).unwrap(), 0);
+    assert_eq!(program.total_available, 28_001_000);
+    assert_eq!(program.reserved_balance, 2_000_000);
+
+    program.total_available = u64::MAX;
+    assert_eq!(program.credit_deposit(10_000).unwrap_err(), ReferralError::NumericOverflow.into());
+    assert_eq!(program.reserved_balance, 2_000_000);
+}
+
+#[tokio::test]
+async fn test_reserve_is_out_of_reach_until_released() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) =
+        create_sol_referral_program(&mut context, &owner, REFERRAL_REWARD, Some(end_time)).await;
+    let settings = ProgramSettings {
+        locked_period: MIN_LOCKED_PERIOD,
+        base_reward: REFERRAL_REWARD,
+        max_reward_cap: 100 * REFERRAL_REWARD,
+        reserve_bps: 1_000,
+        ..read_program_settings(&mut context, referral_program).await
+    };
+    update_program_settings(&mut context, &owner, referral_program, settings.clone()).await;
+    let release_reserve_ix = program_instruction(
+        accounts::ReleaseReserve {
+            referral_program,
+            vault,
+            token_vault: None,
+            destination_token_account: None,
+            destination: None,
+            authority: owner.pubkey(),
+            system_program: system_program::ID,
+            token_program: None,
+        },
+        instruction::ReleaseReserve { to_authority: false },
+    );
+
+    // 10% of the deposit is held back
+    deposit_sol(&mut context, &owner, referral_program, vault, 20_000_000).await;
+    let program_account: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program_account.total_available, 18_000_000);
+    assert_eq!(program_account.reserved_balance, 2_000_000);
+    let funded_vault_balance = get_balance(&mut context, vault).await;
+
+    // Claims can consume the 90% but not the reserve behind it
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    join_through_referral(&mut context, &bob, referral_program, alice_participant).await;
+    let carol = create_funded_user(&mut context).await;
+    join_through_referral(&mut context, &carol, referral_program, alice_participant).await;
+    advance_clock(&mut context, MIN_LOCKED_PERIOD).await;
+    claim_rewards(&mut context, &alice, referral_program, alice_participant, vault).await.unwrap();
+    let participant: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!(participant.total_rewards, 2 * REFERRAL_REWARD);
+
+    // The reserve does not count toward funding new referrals, so the next one is turned away
+    let program_account: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert!(!program_account.accepting_referrals);
+    let dave = create_funded_user(&mut context).await;
+    let (result, _) = try_join_through_referral(&mut context, &dave, referral_program, alice_participant).await;
+    assert_referral_error(result, ReferralError::ProgramUnderfunded);
+    assert_eq!(get_balance(&mut context, vault).await, funded_vault_balance - 18_000_000);
+
+    // A new share applies to later deposits only
+    let settings = ProgramSettings { reserve_bps: 2_000, ..settings };
+    update_program_settings(&mut context, &owner, referral_program, settings).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10_000_000).await;
+    let program_account: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program_account.total_available, 8_000_000);
+    assert_eq!(program_account.reserved_balance, 4_000_000);
+
+    // The reserve stays locked until the program ends, then becomes available
+    let result = process(&mut context, std::slice::from_ref(&release_reserve_ix), &[&owner]).await;
+    assert_referral_error(result, ReferralError::ReserveLocked);
+    advance_clock(&mut context, ONE_YEAR).await;
+    process(&mut context, &[release_reserve_ix], &[&owner]).await.unwrap();
+
+    let program_account: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program_account.total_available, 12_000_000);
+    assert_eq!(program_account.reserved_balance, 0);
+    assert!(program_account.accepting_referrals);
+    assert_eq!(get_balance(&mut context, vault).await, funded_vault_balance - 18_000_000 + 10_000_000);
+}
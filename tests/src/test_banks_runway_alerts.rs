@@ -0,0 +1,183 @@
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer},
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts, constants::MIN_LOCKED_PERIOD, error::ReferralError, events::RunwayAlert, instruction,
+    instructions::ProgramSettings, state::ReferralProgram,
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol,
+        get_account, get_clock_time, join_referral_program, join_through_referral, process, process_with_events,
+        program_instruction, setup, update_program_settings, update_program_settings_ix,
+    },
+    test_util::get_eligibility_criteria_pda,
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+const THRESHOLDS: [u8; 3] = [50, 25, 10];
+
+fn settings(end_time: i64, alert_thresholds: [u8; 3]) -> ProgramSettings {
+    ProgramSettings {
+        fixed_reward_amount: REWARD,
+        locked_period: MIN_LOCKED_PERIOD,
+        program_end_time: Some(end_time),
+        base_reward: REWARD,
+        max_reward_cap: 100 * REWARD,
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds,
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
+    }
+}
+
+#[test]
+fn test_runway_alert_bits() {
+    let mut program = ReferralProgram { alert_thresholds: [50, 0, 10], ..Default::default() };
+    program.credit_deposit(100).unwrap();
+    assert_eq!(program.peak_total_available, 100);
+    assert_eq!(program.take_runway_alerts(), 0);
+
+    // Exactly at a threshold is not below it, and a disabled slot never fires
+    program.total_available = 50;
+    assert_eq!(program.take_runway_alerts(), 0);
+    program.total_available = 5;
+    assert_eq!(program.take_runway_alerts(), 0b101);
+    assert_eq!(program.take_runway_alerts(), 0);
+
+    // A deposit only re-arms the thresholds it lifts the funds back to
+    program.credit_deposit(20).unwrap();
+    assert_eq!(program.alerts_fired, 0b001);
+    assert_eq!(program.peak_total_available, 100);
+    program.credit_deposit(200).unwrap();
+    assert_eq!((program.alerts_fired, program.peak_total_available), (0, 225));
+
+    // Replacing the thresholds only re-arms the slots that changed
+    program.total_available = 5;
+    assert_eq!(program.take_runway_alerts(), 0b101);
+    program.set_alert_thresholds([50, 0, 10]);
+    assert_eq!(program.alerts_fired, 0b101);
+    program.set_alert_thresholds([50, 20, 5]);
+    assert_eq!(program.alerts_fired, 0b001);
+    assert_eq!(program.take_runway_alerts(), 0b110);
+}
+
+/// Joins `count` referees through `participant` and claims the rewards they earned, returning the alerts the
+/// claim emitted
+async fn refer_and_claim(
+    context: &mut ProgramTestContext,
+    referrer: &Keypair,
+    participant: Pubkey,
+    referral_program: Pubkey,
+    vault: Pubkey,
+    count: usize,
+) -> Vec<RunwayAlert> {
+    for _ in 0..count {
+        let referee = create_funded_user(context).await;
+        join_through_referral(context, &referee, referral_program, participant).await;
+    }
+    let claim_ix = program_instruction(
+        accounts::ClaimRewards {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant,
+            claim_splitter: None,
+            vault,
+            user: referrer.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
+            region_attestation: None,
+            system_program: system_program::ID,
+        },
+        instruction::ClaimRewards {},
+    );
+    process_with_events(context, &[claim_ix], &[referrer]).await
+}
+
+fn assert_alert(alerts: &[RunwayAlert], threshold_pct: u8, total_available: u64) {
+    assert_eq!(alerts.len(), 1, "expected exactly one alert");
+    assert_eq!((alerts[0].threshold_pct, alerts[0].total_available), (threshold_pct, total_available));
+    assert_eq!(alerts[0].estimated_referrals_remaining, total_available / REWARD);
+}
+
+#[tokio::test]
+async fn test_runway_alerts_fire_once_per_refill() {
+    let (mut context, owner, referrer, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    update_program_settings(&mut context, &owner, referral_program, settings(end_time, THRESHOLDS)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let participant = join_referral_program(&mut context, &referrer, referral_program).await;
+    advance_clock(&mut context, MIN_LOCKED_PERIOD + 1).await;
+
+    // Each claim reports only the line it crossed, and a claim that crosses none reports nothing
+    let alerts = refer_and_claim(&mut context, &referrer, participant, referral_program, vault, 6).await;
+    assert_alert(&alerts, 50, 4 * REWARD);
+    let alerts = refer_and_claim(&mut context, &referrer, participant, referral_program, vault, 2).await;
+    assert_alert(&alerts, 25, 2 * REWARD);
+    let alerts = refer_and_claim(&mut context, &referrer, participant, referral_program, vault, 1).await;
+    assert!(alerts.is_empty());
+
+    // Updating other settings leaves the fired alerts alone, so draining the rest only reports the 10% line
+    let unchanged = ProgramSettings { dispute_window_seconds: 60, ..settings(end_time, THRESHOLDS) };
+    update_program_settings(&mut context, &owner, referral_program, unchanged).await;
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.alerts_fired, 0b011);
+    let alerts = refer_and_claim(&mut context, &referrer, participant, referral_program, vault, 1).await;
+    assert_alert(&alerts, 10, 0);
+
+    // A refill above both lines re-arms them, and draining again fires the 50% alert a second time
+    deposit_sol(&mut context, &owner, referral_program, vault, 9 * REWARD).await;
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!((program.peak_total_available, program.alerts_fired), (10 * REWARD, 0));
+    let alerts = refer_and_claim(&mut context, &referrer, participant, referral_program, vault, 6).await;
+    assert_alert(&alerts, 50, 3 * REWARD);
+}
+
+#[tokio::test]
+async fn test_alert_thresholds_above_100_are_rejected() {
+    let (mut context, owner, _, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, _) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    let ix = update_program_settings_ix(&mut context, &owner, referral_program, settings(end_time, [101, 0, 0])).await;
+    let result = process(&mut context, &[ix], &[&owner]).await;
+    assert_referral_error(result, ReferralError::InvalidAlertThresholds);
+}
@@ -0,0 +1,173 @@
+//! Participants seeded by the authority for partner wallets.
+//!
+//! The authority seeds Alice's account before she has signed anything: Bob joins through her link at once and
+//! she is credited, but she can only claim once she activates the account with her own key. Seeded accounts their
+//! partners never use are reclaimed by the authority once the program's reclaim period has passed.
+
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer},
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::{MAX_SEED_RECLAIM_PERIOD, MIN_LOCKED_PERIOD},
+    error::ReferralError,
+    instruction,
+    instructions::{current_settings, ProgramSettings},
+    state::{EligibilityCriteria, Participant, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, claim_rewards, create_sol_referral_program, deposit_sol, get_account,
+        get_balance, get_clock_time, join_through_referral, process, program_instruction, setup,
+        update_program_settings, update_program_settings_ix,
+    },
+    test_util::{get_eligibility_criteria_pda, get_participant_pda},
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_DAY: i64 = 86400;
+const ONE_YEAR: i64 = 365 * ONE_DAY;
+const RECLAIM_PERIOD: i64 = 30 * ONE_DAY;
+
+/// Returns the settings of `referral_program` with `REWARD` per referral and `seed_reclaim_seconds`
+async fn settings(
+    context: &mut ProgramTestContext,
+    referral_program: Pubkey,
+    seed_reclaim_seconds: i64,
+) -> ProgramSettings {
+    let program: ReferralProgram = get_account(context, referral_program).await;
+    let criteria: EligibilityCriteria =
+        get_account(context, get_eligibility_criteria_pda(referral_program, solrefer::ID)).await;
+    ProgramSettings {
+        locked_period: MIN_LOCKED_PERIOD,
+        base_reward: REWARD,
+        max_reward_cap: 100 * REWARD,
+        seed_reclaim_seconds,
+        ..current_settings(&program, &criteria)
+    }
+}
+
+/// Seeds the participant account of `owner` in `referral_program`, returning its PDA
+async fn seed_participant(
+    context: &mut ProgramTestContext,
+    authority: &Keypair,
+    referral_program: Pubkey,
+    owner: Pubkey,
+) -> Pubkey {
+    let participant = get_participant_pda(referral_program, owner, solrefer::ID);
+    let ix = program_instruction(
+        accounts::SeedParticipant {
+            referral_program,
+            participant,
+            authority: authority.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::SeedParticipant { owner },
+    );
+    process(context, &[ix], &[authority]).await.expect("Failed to seed participant");
+    participant
+}
+
+/// Builds `user` activating its seeded participant account in `referral_program`
+fn activate_seeded_ix(user: &Keypair, referral_program: Pubkey) -> Instruction {
+    program_instruction(
+        accounts::ActivateSeeded {
+            referral_program,
+            participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+            user: user.pubkey(),
+        },
+        instruction::ActivateSeeded {},
+    )
+}
+
+/// Builds the authority reclaiming the seeded `participant` of `referral_program`
+fn reclaim_seeded_ix(authority: &Keypair, referral_program: Pubkey, participant: Pubkey) -> Instruction {
+    program_instruction(
+        accounts::ReclaimSeededParticipant { referral_program, participant, authority: authority.pubkey() },
+        instruction::ReclaimSeededParticipant {},
+    )
+}
+
+#[tokio::test]
+async fn test_seeded_participant_earns_and_claims_once_activated() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    let program_settings = settings(&mut context, referral_program, RECLAIM_PERIOD).await;
+    update_program_settings(&mut context, &owner, referral_program, program_settings).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+
+    // Alice's account exists before she signs anything, and Bob joins through it at once
+    let alice_participant = seed_participant(&mut context, &owner, referral_program, alice.pubkey()).await;
+    let alice_account: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!(alice_account.owner, alice.pubkey());
+    assert!(alice_account.seeded && !alice_account.activated);
+    assert!(!alice_account.stored_link().is_empty());
+    join_through_referral(&mut context, &bob, referral_program, alice_participant).await;
+    let alice_account: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!((alice_account.total_referrals, alice_account.pending_rewards), (1, REWARD));
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.total_participants, 2);
+
+    // Her rewards unlock, but she must activate the account before claiming them
+    advance_clock(&mut context, MIN_LOCKED_PERIOD + 1).await;
+    let result = claim_rewards(&mut context, &alice, referral_program, alice_participant, vault).await;
+    assert_referral_error(result, ReferralError::ParticipantNotActivated);
+
+    process(&mut context, &[activate_seeded_ix(&alice, referral_program)], &[&alice]).await.unwrap();
+    let before = get_balance(&mut context, alice.pubkey()).await;
+    claim_rewards(&mut context, &alice, referral_program, alice_participant, vault).await.unwrap();
+    assert_eq!(get_balance(&mut context, alice.pubkey()).await, before + REWARD);
+
+    // Activation is once only, and only for seeded accounts
+    let ix = activate_seeded_ix(&alice, referral_program);
+    assert_referral_error(process(&mut context, &[ix], &[&alice]).await, ReferralError::NotAwaitingActivation);
+    let ix = activate_seeded_ix(&bob, referral_program);
+    assert_referral_error(process(&mut context, &[ix], &[&bob]).await, ReferralError::NotAwaitingActivation);
+}
+
+#[tokio::test]
+async fn test_untouched_seeded_participant_is_reclaimed() {
+    let (mut context, owner, _, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    let program_settings = settings(&mut context, referral_program, RECLAIM_PERIOD).await;
+    update_program_settings(&mut context, &owner, referral_program, program_settings).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+
+    // Carol never shows up; Dave's account is used by a referee
+    let carol = Keypair::new();
+    let dave = Keypair::new();
+    let carol_participant = seed_participant(&mut context, &owner, referral_program, carol.pubkey()).await;
+    let dave_participant = seed_participant(&mut context, &owner, referral_program, dave.pubkey()).await;
+    join_through_referral(&mut context, &bob, referral_program, dave_participant).await;
+
+    // Nothing is reclaimed before the period has passed
+    let ix = reclaim_seeded_ix(&owner, referral_program, carol_participant);
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::SeedNotReclaimable);
+
+    advance_clock(&mut context, RECLAIM_PERIOD).await;
+    let rent = get_balance(&mut context, carol_participant).await;
+    let before = get_balance(&mut context, owner.pubkey()).await;
+    let ix = reclaim_seeded_ix(&owner, referral_program, carol_participant);
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    assert!(context.banks_client.get_account(carol_participant).await.unwrap().is_none());
+    assert_eq!(get_balance(&mut context, owner.pubkey()).await, before + rent);
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.total_participants, 2);
+
+    // Dave's account has earned, and accounts that joined themselves were never seeded
+    let ix = reclaim_seeded_ix(&owner, referral_program, dave_participant);
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::SeedNotReclaimable);
+    let bob_participant = get_participant_pda(referral_program, bob.pubkey(), solrefer::ID);
+    let ix = reclaim_seeded_ix(&owner, referral_program, bob_participant);
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::NotAwaitingActivation);
+
+    // The authority can only make partners wait so long
+    let too_long = settings(&mut context, referral_program, MAX_SEED_RECLAIM_PERIOD + 1).await;
+    let ix = update_program_settings_ix(&mut context, &owner, referral_program, too_long).await;
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::InvalidSeedReclaimPeriod);
+}
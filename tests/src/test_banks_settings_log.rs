@@ -0,0 +1,172 @@
+//! The on-chain log of settings changes.
+
+use anchor_client::{anchor_lang::system_program, solana_sdk::signer::Signer};
+use solrefer::{
+    accounts,
+    constants::{MAX_MILESTONES, MIN_LOCKED_PERIOD},
+    events::ProgramField,
+    instruction,
+    instructions::{settings_changes, ProgramSettings},
+    state::{FieldChange, Milestone, ReferralProgram, SettingsChangeRecord},
+};
+
+use crate::{
+    banks_util::{
+        create_sol_referral_program, get_account, get_clock_time, next_settings_change_pda, process,
+        program_instruction, setup, update_program_settings,
+    },
+    test_util::{get_eligibility_criteria_pda, get_network_config_pda, get_settings_change_pda},
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_DAY: i64 = 86400;
+const ONE_YEAR: i64 = 365 * ONE_DAY;
+
+fn settings(end_time: i64, fixed_reward_amount: u64, reserve_bps: u64) -> ProgramSettings {
+    ProgramSettings {
+        fixed_reward_amount,
+        locked_period: MIN_LOCKED_PERIOD,
+        program_end_time: Some(end_time),
+        base_reward: REWARD,
+        max_reward_cap: 100 * REWARD,
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
+    }
+}
+
+fn change(field: ProgramField, old_value: u64, new_value: u64) -> FieldChange {
+    FieldChange { field: field as u8, old_value, new_value }
+}
+
+#[test]
+fn test_settings_changes_lists_changed_fields_in_order() {
+    let old = settings(1_000, REWARD, 0);
+    assert!(settings_changes(&old, &old.clone()).unwrap().is_empty());
+
+    let mut milestones = [Milestone::default(); MAX_MILESTONES];
+    milestones[0] = Milestone { threshold: 5, bonus: REWARD };
+    let new = ProgramSettings {
+        program_end_time: None,
+        milestones,
+        reserve_bps: 500,
+        transfers_enabled: true,
+        ..settings(1_000, 2 * REWARD, 0)
+    };
+    let changes = settings_changes(&old, &new).unwrap();
+    let fields: Vec<u8> = changes.iter().map(|change| change.field).collect();
+    let expected = [
+        ProgramField::FixedRewardAmount,
+        ProgramField::ProgramEndTime,
+        ProgramField::Milestones,
+        ProgramField::ReserveBps,
+        ProgramField::TransfersEnabled,
+    ];
+    assert_eq!(fields, expected.map(|field| field as u8));
+
+    // Scalars are recorded as is, an open end as 0 and a flag as 0 or 1; grouped fields only differ
+    assert_eq!(changes[0], change(ProgramField::FixedRewardAmount, REWARD, 2 * REWARD));
+    assert_eq!(changes[1], change(ProgramField::ProgramEndTime, 1_000, 0));
+    assert_ne!(changes[2].old_value, changes[2].new_value);
+    assert_eq!(changes[3], change(ProgramField::ReserveBps, 0, 500));
+    assert_eq!(changes[4], change(ProgramField::TransfersEnabled, 0, 1));
+}
+
+#[tokio::test]
+async fn test_settings_changes_are_recorded_on_chain() {
+    let (mut context, owner, _, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, _) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    let record = |index: u64| get_settings_change_pda(referral_program, index, solrefer::ID);
+    update_program_settings(&mut context, &owner, referral_program, settings(end_time, REWARD, 0)).await;
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.settings_change_count, 1);
+
+    // Raising the reward and the reserve records just those two
+    update_program_settings(&mut context, &owner, referral_program, settings(end_time, 2 * REWARD, 500)).await;
+    let now = get_clock_time(&mut context).await;
+    let first: SettingsChangeRecord = get_account(&mut context, record(1)).await;
+    assert_eq!((first.referral_program, first.index, first.authority), (referral_program, 1, owner.pubkey()));
+    assert_eq!(first.changed_at, now);
+    assert_eq!(
+        first.changes,
+        [change(ProgramField::FixedRewardAmount, REWARD, 2 * REWARD), change(ProgramField::ReserveBps, 0, 500)]
+    );
+
+    // Submitting the same settings again changes nothing and records nothing
+    update_program_settings(&mut context, &owner, referral_program, settings(end_time, 2 * REWARD, 500)).await;
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.settings_change_count, 2);
+    assert!(context.banks_client.get_account(record(2)).await.unwrap().is_none());
+
+    // Moving the end time is recorded too
+    let extend_ix = program_instruction(
+        accounts::ExtendProgram {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            network_config: get_network_config_pda(solrefer::ID),
+            authority: owner.pubkey(),
+            settings_change: next_settings_change_pda(&mut context, referral_program).await,
+            system_program: system_program::ID,
+        },
+        instruction::ExtendProgram { program_end_time: Some(end_time + ONE_DAY) },
+    );
+    process(&mut context, &[extend_ix], &[&owner]).await.unwrap();
+    let second: SettingsChangeRecord = get_account(&mut context, record(2)).await;
+    let (old_end, new_end) = (end_time as u64, (end_time + ONE_DAY) as u64);
+    assert_eq!(second.changes, [change(ProgramField::ProgramEndTime, old_end, new_end)]);
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.settings_change_count, 3);
+    assert_eq!(SettingsChangeRecord::address(&referral_program, 2), record(2));
+
+    // A record cannot be written anywhere but the next index
+    let ix = program_instruction(
+        accounts::UpdateProgramSettings {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            network_config: get_network_config_pda(solrefer::ID),
+            authority: owner.pubkey(),
+            settings_change: record(0),
+            pending_change: None,
+            event_queue: None,
+            system_program: system_program::ID,
+        },
+        instruction::UpdateProgramSettings { new_settings: settings(end_time, REWARD, 0), idempotency_key: None },
+    );
+    assert!(process(&mut context, &[ix], &[&owner]).await.is_err());
+}
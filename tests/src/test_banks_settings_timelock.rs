@@ -0,0 +1,253 @@
+//! Settings time-locked by the creator for a launch window.
+//!
+//! A program created with a settings lock rejects every authority change to its settings, terms, end time,
+//! funds, withdrawal destinations and referrals until the lock lapses, while deposits and the guardian's freeze keep working. Once the
+//! clock passes the lock the same instructions succeed.
+
+use anchor_client::{
+    anchor_lang::{system_program, InstructionData},
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer},
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::{MAX_SETTINGS_LOCK, MAX_WITHDRAWAL_DESTINATIONS, MIN_LOCKED_PERIOD, REWARD_DENOMINATION_RAW},
+    error::ReferralError,
+    instruction,
+    instructions::{current_settings, validate_settings_lock, GuardianConfig, ProgramSettings},
+    state::{EligibilityCriteria, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, create_funded_user, create_referral_program_ix, deposit_sol, get_account,
+        get_clock_time, get_setup_state, join_referral_program, join_through_referral, next_settings_change_pda,
+        process, program_instruction, referral_program_pdas, setup, update_program_settings_ix,
+    },
+    test_util::{
+        get_authority_meta_pda, get_eligibility_criteria_pda, get_final_report_pda, get_network_config_pda,
+        get_referee_receipt_pda, get_sponsor_vault_pda, get_withdrawal_request_pda,
+    },
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_DAY: i64 = 86400;
+const ONE_YEAR: i64 = 365 * ONE_DAY;
+const LOCK: i64 = 30 * ONE_DAY;
+
+#[test]
+fn test_settings_lock_bounds() {
+    let now = 1_000_000;
+    let end_time = now + ONE_YEAR;
+    assert!(validate_settings_lock(now + LOCK, Some(end_time), now).is_ok());
+    assert!(validate_settings_lock(now + MAX_SETTINGS_LOCK, None, now).is_ok());
+    assert!(validate_settings_lock(now + LOCK, Some(now + LOCK), now).is_ok());
+    for (locked_until, program_end_time) in
+        [(now, Some(end_time)), (now + MAX_SETTINGS_LOCK + 1, None), (now + LOCK + 1, Some(now + LOCK))]
+    {
+        assert_eq!(
+            validate_settings_lock(locked_until, program_end_time, now).unwrap_err(),
+            ReferralError::InvalidSettingsLock.into()
+        );
+    }
+
+    let program = ReferralProgram { settings_locked_until: now + LOCK, ..Default::default() };
+    assert!(program.require_settings_unlocked(now + LOCK - 1).is_err());
+    assert!(program.require_settings_unlocked(now + LOCK).is_ok());
+    assert!(ReferralProgram::default().require_settings_unlocked(now).is_ok());
+}
+
+/// Builds a creation of a guarded SOL program whose settings are locked until `settings_locked_until`
+fn create_locked_program_ix(
+    owner: &Keypair,
+    guardian: Pubkey,
+    end_time: i64,
+    settings_locked_until: i64,
+) -> Instruction {
+    let mut create_ix = create_referral_program_ix(owner, None, REWARD, Some(end_time), false);
+    create_ix.data = instruction::CreateReferralProgram {
+        program_index: 0,
+        token_mint: None,
+        fixed_reward_amount: REWARD,
+        program_end_time: Some(end_time),
+        reward_denomination: REWARD_DENOMINATION_RAW,
+        start_inactive: false,
+        terms_hash: [0u8; 32],
+        guardian: Some(GuardianConfig { guardian, withdrawal_delay_threshold: 10 * REWARD }),
+        withdrawal_destinations: Default::default(),
+        settings_locked_until: Some(settings_locked_until),
+        settings: None,
+    }
+    .data();
+    create_ix
+}
+
+/// Builds every authority instruction the settings lock holds back, in an order they succeed in once it lapses
+async fn gated_instructions(
+    context: &mut ProgramTestContext,
+    owner: &Keypair,
+    referral_program: Pubkey,
+    vault: Pubkey,
+    referee: Pubkey,
+    referrer: Pubkey,
+) -> Vec<Instruction> {
+    let program: ReferralProgram = get_account(context, referral_program).await;
+    let criteria: EligibilityCriteria =
+        get_account(context, get_eligibility_criteria_pda(referral_program, solrefer::ID)).await;
+    let settings = ProgramSettings {
+        locked_period: MIN_LOCKED_PERIOD,
+        base_reward: REWARD,
+        max_reward_cap: 100 * REWARD,
+        ..current_settings(&program, &criteria)
+    };
+    let end_time = settings.program_end_time.unwrap();
+    let withdraw = accounts::WithdrawFunds {
+        referral_program,
+        vault,
+        token_vault: None,
+        destination_token_account: None,
+        destination: None,
+        authority: owner.pubkey(),
+        system_program: system_program::ID,
+        token_program: None,
+    };
+    vec![
+        update_program_settings_ix(context, owner, referral_program, settings).await,
+        program_instruction(
+            accounts::UpdateTerms { referral_program, authority: owner.pubkey() },
+            instruction::UpdateTerms { terms_hash: [1u8; 32] },
+        ),
+        program_instruction(
+            accounts::ExtendProgram {
+                referral_program,
+                eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+                network_config: get_network_config_pda(solrefer::ID),
+                authority: owner.pubkey(),
+                settings_change: next_settings_change_pda(context, referral_program).await,
+                system_program: system_program::ID,
+            },
+            instruction::ExtendProgram { program_end_time: Some(end_time + ONE_DAY) },
+        ),
+        program_instruction(withdraw, instruction::WithdrawFunds { amount: REWARD }),
+        program_instruction(
+            accounts::QueueWithdrawal {
+                referral_program,
+                withdrawal_request: get_withdrawal_request_pda(referral_program, solrefer::ID),
+                destination: None,
+                authority: owner.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::QueueWithdrawal { amount: REWARD },
+        ),
+        program_instruction(
+            accounts::ClawbackReferral {
+                referral_program,
+                referee_receipt: get_referee_receipt_pda(referral_program, referee, solrefer::ID),
+                referrer,
+                authority: owner.pubkey(),
+            },
+            instruction::ClawbackReferral { idempotency_key: None },
+        ),
+        program_instruction(
+            accounts::CloseReferralProgram {
+                referral_program,
+                eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+                vault,
+                sponsor_vault: get_sponsor_vault_pda(referral_program, solrefer::ID),
+                authority_meta: get_authority_meta_pda(owner.pubkey(), solrefer::ID),
+                final_report: get_final_report_pda(referral_program, solrefer::ID),
+                authority: owner.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::CloseReferralProgram {},
+        ),
+    ]
+}
+
+#[tokio::test]
+async fn test_settings_lock_holds_authority_changes_until_it_lapses() {
+    let (mut context, owner, guardian, referrer) = setup().await;
+    let now = get_clock_time(&mut context).await;
+    let end_time = now + ONE_YEAR;
+
+    // The lock must end within the program and the maximum lock
+    for locked_until in [now, end_time + 1, now + MAX_SETTINGS_LOCK + 1] {
+        let create_ix = create_locked_program_ix(&owner, guardian.pubkey(), end_time, locked_until);
+        assert_referral_error(process(&mut context, &[create_ix], &[&owner]).await, ReferralError::InvalidSettingsLock);
+    }
+    let create_ix = create_locked_program_ix(&owner, guardian.pubkey(), end_time, now + LOCK);
+    process(&mut context, &[create_ix], &[&owner]).await.unwrap();
+    let (referral_program, vault, _) = referral_program_pdas(owner.pubkey());
+    assert_eq!(get_setup_state(&mut context, referral_program).await.settings_locked_until, now + LOCK);
+
+    // Deposits and referrals run as usual during the lock
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let referrer_participant = join_referral_program(&mut context, &referrer, referral_program).await;
+    let referee = create_funded_user(&mut context).await;
+    join_through_referral(&mut context, &referee, referral_program, referrer_participant).await;
+
+    let gated =
+        gated_instructions(&mut context, &owner, referral_program, vault, referee.pubkey(), referrer_participant).await;
+    let gated_count = gated.len();
+    for ix in gated {
+        let result = process(&mut context, &[ix], &[&owner]).await;
+        assert_referral_error(result, ReferralError::SettingsTimelocked);
+    }
+
+    // The guardian can still freeze the program in an emergency, and lift the freeze with the authority
+    let freeze_ix = program_instruction(
+        accounts::GuardianFreeze { referral_program, guardian: guardian.pubkey() },
+        instruction::GuardianFreeze,
+    );
+    process(&mut context, &[freeze_ix], &[&guardian]).await.unwrap();
+    let unfreeze_ix = program_instruction(
+        accounts::UnfreezeProgram { referral_program, authority: owner.pubkey(), guardian: guardian.pubkey() },
+        instruction::UnfreezeProgram,
+    );
+    process(&mut context, &[unfreeze_ix], &[&owner, &guardian]).await.unwrap();
+
+    // Once the lock lapses every held instruction goes through, each built against the state its predecessors left
+    advance_clock(&mut context, LOCK).await;
+    for index in 0..gated_count {
+        let ix =
+            gated_instructions(&mut context, &owner, referral_program, vault, referee.pubkey(), referrer_participant)
+                .await
+                .swap_remove(index);
+        process(&mut context, &[ix], &[&owner]).await.unwrap();
+    }
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.settings_locked_until, now + LOCK);
+    assert!(program.is_closing());
+}
+
+#[tokio::test]
+async fn test_settings_lock_holds_withdrawal_destinations() {
+    let (mut context, owner, guardian, treasury) = setup().await;
+    let now = get_clock_time(&mut context).await;
+    let create_ix = create_locked_program_ix(&owner, guardian.pubkey(), now + ONE_YEAR, now + LOCK);
+    process(&mut context, &[create_ix], &[&owner]).await.unwrap();
+    let (referral_program, _, _) = referral_program_pdas(owner.pubkey());
+
+    let mut destinations = [Pubkey::default(); MAX_WITHDRAWAL_DESTINATIONS];
+    destinations[0] = treasury.pubkey();
+    let set_destinations = [program_instruction(
+        accounts::SetWithdrawalDestinations {
+            referral_program,
+            authority: owner.pubkey(),
+            guardian: guardian.pubkey(),
+        },
+        instruction::SetWithdrawalDestinations { destinations },
+    )];
+
+    // Even with the guardian's signature the allow-list stays put until the last second of the lock
+    advance_clock(&mut context, LOCK - 1).await;
+    let result = process(&mut context, &set_destinations, &[&owner, &guardian]).await;
+    assert_referral_error(result, ReferralError::SettingsTimelocked);
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.allowed_withdrawal_destinations, [Pubkey::default(); MAX_WITHDRAWAL_DESTINATIONS]);
+
+    advance_clock(&mut context, 1).await;
+    process(&mut context, &set_destinations, &[&owner, &guardian]).await.unwrap();
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.allowed_withdrawal_destinations, destinations);
+}
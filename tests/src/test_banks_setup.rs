@@ -0,0 +1,232 @@
+use anchor_client::{
+    anchor_lang::InstructionData,
+    solana_sdk::{instruction::Instruction, signature::Keypair, signer::Signer},
+};
+use solrefer::{
+    constants::{
+        MAX_EARLY_REDEMPTION_FEE, MAX_FEE_PERCENTAGE, MAX_LOCKED_PERIOD, MIN_LOCKED_PERIOD, REWARD_DENOMINATION_RAW,
+    },
+    error::ReferralError,
+    instruction,
+    instructions::ProgramSettings,
+    state::{EligibilityCriteria, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        activate_program_ix, assert_referral_error, create_funded_token_account, create_mint,
+        create_referral_program_ix, get_account, get_clock_time, get_setup_state, process, referral_program_pdas,
+        resume_setup, setup, try_join_referral_program, update_program_settings, SetupPlan,
+    },
+    test_util::get_eligibility_criteria_pda,
+};
+
+const REFERRAL_REWARD: u64 = 1_000_000;
+const DEPOSIT: u64 = 10 * REFERRAL_REWARD;
+const ONE_YEAR: i64 = 365 * 86400;
+
+fn settings(program_end_time: i64) -> ProgramSettings {
+    ProgramSettings {
+        fixed_reward_amount: REFERRAL_REWARD,
+        locked_period: MIN_LOCKED_PERIOD,
+        program_end_time: Some(program_end_time),
+        base_reward: REFERRAL_REWARD,
+        max_reward_cap: 10 * REFERRAL_REWARD,
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
+    }
+}
+
+#[tokio::test]
+async fn test_resume_interrupted_token_setup() {
+    let (mut context, owner, _, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let mint = create_mint(&mut context, &owner).await;
+    let depositor_token_account = create_funded_token_account(&mut context, &owner, mint, DEPOSIT).await;
+    let plan =
+        SetupPlan { token: Some((mint, depositor_token_account)), settings: settings(end_time), deposit: DEPOSIT };
+
+    // Activation needs the criteria of a program created inactive configured first
+    let create_ix = create_referral_program_ix(&owner, Some(mint), REFERRAL_REWARD, Some(end_time), true);
+    process(&mut context, &[create_ix], &[&owner]).await.unwrap();
+    let (referral_program, _, _) = referral_program_pdas(owner.pubkey());
+    let status = get_setup_state(&mut context, referral_program).await;
+    assert_eq!(status.setup_state, ReferralProgram::SETUP_CREATED);
+    assert_eq!(status.next_step, ReferralProgram::SETUP_CRITERIA_SET);
+    let result = process(&mut context, &[activate_program_ix(&owner, plan.token, DEPOSIT)], &[&owner]).await;
+    assert_referral_error(result, ReferralError::CriteriaNotSet);
+    update_program_settings(&mut context, &owner, referral_program, settings(end_time)).await;
+
+    let status = get_setup_state(&mut context, referral_program).await;
+    assert_eq!(status.setup_state, ReferralProgram::SETUP_CREATED | ReferralProgram::SETUP_CRITERIA_SET);
+    assert_eq!(status.next_step, ReferralProgram::SETUP_VAULT_INITIALIZED);
+    let result = process(&mut context, &[activate_program_ix(&owner, plan.token, DEPOSIT)], &[&owner]).await;
+    assert_referral_error(result, ReferralError::TokenVaultNotInitialized);
+
+    // The setup is interrupted here, before the token vault is initialized; resuming performs only the missing steps
+    let (resumed, submitted) = resume_setup(&mut context, &owner, &plan).await;
+    assert_eq!(resumed, referral_program);
+    assert_eq!(submitted, ["initialize_token_vault", "activate_program"]);
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert!(program.is_active);
+    assert_eq!(program.total_available, DEPOSIT);
+    assert_eq!(get_setup_state(&mut context, referral_program).await.next_step, 0);
+
+    // A completed setup resumes to nothing
+    let (_, submitted) = resume_setup(&mut context, &owner, &plan).await;
+    assert!(submitted.is_empty());
+}
+
+#[tokio::test]
+async fn test_resume_setup_from_scratch() {
+    let (mut context, owner, _, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let plan = SetupPlan { token: None, settings: settings(end_time), deposit: DEPOSIT };
+
+    let (referral_program, submitted) = resume_setup(&mut context, &owner, &plan).await;
+    assert_eq!(submitted, ["create_referral_program", "update_program_settings", "activate_program"]);
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert!(program.is_active);
+    assert_eq!(program.total_available, DEPOSIT);
+}
+
+#[tokio::test]
+async fn test_setting_criteria_keeps_program_inactive() {
+    let (mut context, owner, user, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let create_ix = create_referral_program_ix(&owner, None, REFERRAL_REWARD, Some(end_time), true);
+    process(&mut context, &[create_ix], &[&owner]).await.unwrap();
+    let (referral_program, _, _) = referral_program_pdas(owner.pubkey());
+    let eligibility_criteria = get_eligibility_criteria_pda(referral_program, solrefer::ID);
+
+    // Configuring the criteria leaves the program as it was; the program's flag alone says whether it is active
+    update_program_settings(&mut context, &owner, referral_program, settings(end_time)).await;
+
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert!(!program.is_active);
+    let criteria: EligibilityCriteria = get_account(&mut context, eligibility_criteria).await;
+    assert!(!criteria.reserved_is_active);
+    let (result, _) = try_join_referral_program(&mut context, &user, referral_program).await;
+    assert_referral_error(result, ReferralError::ProgramInactive);
+}
+
+/// Builds `owner` creating an active SOL program with `settings`, paying `REFERRAL_REWARD` until `end_time`
+fn create_with_settings_ix(owner: &Keypair, end_time: i64, settings: ProgramSettings) -> Instruction {
+    let mut create_ix = create_referral_program_ix(owner, None, REFERRAL_REWARD, Some(end_time), false);
+    create_ix.data = instruction::CreateReferralProgram {
+        program_index: 0,
+        token_mint: None,
+        fixed_reward_amount: REFERRAL_REWARD,
+        program_end_time: Some(end_time),
+        reward_denomination: REWARD_DENOMINATION_RAW,
+        start_inactive: false,
+        terms_hash: [0u8; 32],
+        guardian: None,
+        withdrawal_destinations: Default::default(),
+        settings_locked_until: None,
+        settings: Some(settings),
+    }
+    .data();
+    create_ix
+}
+
+#[tokio::test]
+async fn test_create_with_settings() {
+    let (mut context, owner, _, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let settings = ProgramSettings {
+        locked_period: 2 * MIN_LOCKED_PERIOD,
+        early_redemption_fee: MAX_EARLY_REDEMPTION_FEE,
+        tier1_reward: 2 * REFERRAL_REWARD,
+        revenue_share_percent: MAX_FEE_PERCENTAGE,
+        ..settings(end_time)
+    };
+    let create_ix = create_with_settings_ix(&owner, end_time, settings);
+    process(&mut context, &[create_ix], &[&owner]).await.unwrap();
+
+    // The settings land in both accounts at once, so the program is live with them from the start
+    let (referral_program, _, _) = referral_program_pdas(owner.pubkey());
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(
+        (program.locked_period, program.early_redemption_fee),
+        (2 * MIN_LOCKED_PERIOD, MAX_EARLY_REDEMPTION_FEE)
+    );
+    assert_eq!(program.program_end_time, Some(end_time));
+    let criteria: EligibilityCriteria =
+        get_account(&mut context, get_eligibility_criteria_pda(referral_program, solrefer::ID)).await;
+    assert_eq!((criteria.base_reward, criteria.tier1_reward), (REFERRAL_REWARD, 2 * REFERRAL_REWARD));
+    assert_eq!((criteria.max_reward_cap, criteria.revenue_share_percent), (10 * REFERRAL_REWARD, MAX_FEE_PERCENTAGE));
+    assert_eq!(criteria.program_end_time, Some(end_time));
+    let status = get_setup_state(&mut context, referral_program).await;
+    assert_ne!(status.setup_state & ReferralProgram::SETUP_CRITERIA_SET, 0);
+}
+
+#[tokio::test]
+async fn test_create_rejects_invalid_settings() {
+    let (mut context, owner, _, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let valid = settings(end_time);
+    let cases = [
+        (ProgramSettings { locked_period: MIN_LOCKED_PERIOD - 1, ..valid.clone() }, ReferralError::InvalidLockedPeriod),
+        (ProgramSettings { locked_period: MAX_LOCKED_PERIOD + 1, ..valid.clone() }, ReferralError::InvalidLockedPeriod),
+        (
+            ProgramSettings { early_redemption_fee: MAX_EARLY_REDEMPTION_FEE + 1, ..valid.clone() },
+            ReferralError::InvalidEarlyRedemptionFee,
+        ),
+        (
+            ProgramSettings { revenue_share_percent: MAX_FEE_PERCENTAGE + 1, ..valid.clone() },
+            ReferralError::InvalidFeeAmount,
+        ),
+        (ProgramSettings { tier2_reward: REFERRAL_REWARD / 2, ..valid.clone() }, ReferralError::InvalidTierReward),
+        (ProgramSettings { max_reward_cap: REFERRAL_REWARD / 2, ..valid.clone() }, ReferralError::InvalidRewardCap),
+        (
+            ProgramSettings { fixed_reward_amount: 2 * REFERRAL_REWARD, ..valid.clone() },
+            ReferralError::CreationSettingsMismatch,
+        ),
+        (ProgramSettings { program_end_time: None, ..valid.clone() }, ReferralError::CreationSettingsMismatch),
+    ];
+    for (settings, error) in cases {
+        let create_ix = create_with_settings_ix(&owner, end_time, settings);
+        assert_referral_error(process(&mut context, &[create_ix], &[&owner]).await, error);
+    }
+
+    // Nothing was created
+    let (referral_program, _, _) = referral_program_pdas(owner.pubkey());
+    assert!(context.banks_client.get_account(referral_program).await.unwrap().is_none());
+}
@@ -0,0 +1,125 @@
+//! Source tags recorded on joins through a referral.
+//!
+//! A referee may tag where they came from; the program counts joins per tag in a fixed number of slots, folding
+//! tags past the last slot into a shared counter, and stores the tag on the participant and referee receipt.
+
+use anchor_client::solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer};
+use solrefer::{
+    constants::{MAX_SOURCE_TAG_SLOTS, SOURCE_TAG_LEN},
+    error::ReferralError,
+    instruction,
+    state::{validate_source_tag, Participant, RefereeReceipt, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol, get_account,
+        join_referral_program, join_through_referral_accounts, process, program_instruction, setup,
+    },
+    test_util::{get_participant_pda, get_referee_receipt_pda},
+};
+
+const REWARD: u64 = 1_000_000;
+
+fn tag(name: &str) -> [u8; SOURCE_TAG_LEN] {
+    let mut tag = [0u8; SOURCE_TAG_LEN];
+    tag[..name.len()].copy_from_slice(name.as_bytes());
+    tag
+}
+
+#[test]
+fn test_record_source_tag_counts_per_tag() {
+    let mut program = ReferralProgram::default();
+
+    program.record_source_tag(&tag("twitter"));
+    program.record_source_tag(&tag("discord"));
+    program.record_source_tag(&tag("twitter"));
+    program.record_source_tag(&[0u8; SOURCE_TAG_LEN]);
+
+    assert_eq!(program.source_tag_counts[0].tag, tag("twitter"));
+    assert_eq!(program.source_tag_counts[0].count, 2);
+    assert_eq!(program.source_tag_counts[1].tag, tag("discord"));
+    assert_eq!(program.source_tag_counts[1].count, 1);
+    assert!(!program.source_tag_counts[2].is_set());
+    assert_eq!(program.untagged_joins, 1);
+    assert_eq!(program.other_tag_joins, 0);
+}
+
+#[test]
+fn test_record_source_tag_overflows_into_other() {
+    let mut program = ReferralProgram::default();
+
+    for index in 0..=MAX_SOURCE_TAG_SLOTS {
+        program.record_source_tag(&tag(&format!("campaign-{}", index)));
+    }
+    assert!(program.source_tag_counts.iter().all(|slot| slot.is_set() && slot.count == 1));
+    assert_eq!(program.other_tag_joins, 1);
+
+    // Tags that already hold a slot keep counting there
+    program.record_source_tag(&tag("campaign-0"));
+    assert_eq!(program.source_tag_counts[0].count, 2);
+    assert_eq!(program.other_tag_joins, 1);
+}
+
+#[test]
+fn test_validate_source_tag() {
+    assert!(validate_source_tag(&tag("email")).is_ok());
+    assert!(validate_source_tag(&[0u8; SOURCE_TAG_LEN]).is_ok());
+
+    let mut invalid = tag("email");
+    invalid[5] = 0xc3;
+    assert_eq!(validate_source_tag(&invalid).unwrap_err(), ReferralError::InvalidSourceTag.into());
+}
+
+/// Builds `user`'s join through `referrer` carrying `source_tag`
+fn tagged_join_ix(
+    user: &Keypair,
+    referral_program: Pubkey,
+    referrer: Pubkey,
+    source_tag: Option<[u8; SOURCE_TAG_LEN]>,
+) -> Instruction {
+    program_instruction(
+        join_through_referral_accounts(user, referral_program, referrer),
+        instruction::JoinThroughReferral { source_tag, accepted_terms_hash: [0u8; 32], link_proof: None },
+    )
+}
+
+#[tokio::test]
+async fn test_tagged_joins_through_referral() {
+    let (mut context, owner, alice, _) = setup().await;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, None).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let referrer = join_referral_program(&mut context, &alice, referral_program).await;
+
+    // Non-ASCII tags are rejected
+    let mut invalid = tag("twitter");
+    invalid[0] = 0xff;
+    let rejected = create_funded_user(&mut context).await;
+    let ix = tagged_join_ix(&rejected, referral_program, referrer, Some(invalid));
+    assert_referral_error(process(&mut context, &[ix], &[&rejected]).await, ReferralError::InvalidSourceTag);
+
+    let mut users = Vec::new();
+    for source_tag in [Some(tag("twitter")), Some(tag("discord")), None] {
+        let user = create_funded_user(&mut context).await;
+        let ix = tagged_join_ix(&user, referral_program, referrer, source_tag);
+        process(&mut context, &[ix], &[&user]).await.unwrap();
+        users.push(user);
+    }
+
+    let program_account: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program_account.source_tag_counts[0].tag, tag("twitter"));
+    assert_eq!(program_account.source_tag_counts[0].count, 1);
+    assert_eq!(program_account.source_tag_counts[1].tag, tag("discord"));
+    assert_eq!(program_account.source_tag_counts[1].count, 1);
+    assert_eq!(program_account.untagged_joins, 1);
+
+    let participant: Participant =
+        get_account(&mut context, get_participant_pda(referral_program, users[0].pubkey(), solrefer::ID)).await;
+    assert_eq!(participant.source_tag, tag("twitter"));
+    let receipt: RefereeReceipt =
+        get_account(&mut context, get_referee_receipt_pda(referral_program, users[1].pubkey(), solrefer::ID)).await;
+    assert_eq!(receipt.source_tag, tag("discord"));
+    let untagged: Participant =
+        get_account(&mut context, get_participant_pda(referral_program, users[2].pubkey(), solrefer::ID)).await;
+    assert_eq!(untagged.source_tag, [0u8; SOURCE_TAG_LEN]);
+}
@@ -0,0 +1,168 @@
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer},
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    error::ReferralError,
+    events::RewardMatched,
+    instruction,
+    state::{MatchOffer, Participant, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol, get_account, get_balance,
+        get_clock_time, join_referral_program, process, process_with_events, program_instruction, setup,
+    },
+    test_util::{get_eligibility_criteria_pda, get_match_offer_pda, get_participant_pda, get_referee_receipt_pda},
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+#[test]
+fn test_match_is_capped_by_the_budget() {
+    let offer = MatchOffer { match_bps: 5_000, balance: 3 * REWARD, ..Default::default() };
+    assert_eq!(offer.next_match(REWARD), REWARD / 2);
+    assert_eq!(offer.next_match(3), 1);
+    let offer = MatchOffer { balance: REWARD / 4, ..offer };
+    assert_eq!(offer.next_match(REWARD), REWARD / 4);
+    let offer = MatchOffer { match_bps: 10_000, balance: u64::MAX, ..offer };
+    assert_eq!(offer.next_match(u64::MAX), u64::MAX);
+}
+
+#[tokio::test]
+async fn test_sponsor_matches_referral_rewards_until_its_budget_runs_out() {
+    let (mut context, owner, referrer, sponsor) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let referrer_participant = join_referral_program(&mut context, &referrer, referral_program).await;
+
+    // A match needs a share of the reward to pay
+    let result =
+        process(&mut context, &[create_match_offer_ix(&sponsor, referral_program, 0, REWARD)], &[&sponsor]).await;
+    assert_referral_error(result, ReferralError::InvalidMatchBps);
+    let create_ix = create_match_offer_ix(&sponsor, referral_program, 10_000, 3 * REWARD / 2);
+    process(&mut context, &[create_ix], &[&sponsor]).await.unwrap();
+    let match_offer = get_match_offer_pda(referral_program, sponsor.pubkey(), solrefer::ID);
+
+    // The match is paid only to the credited referrer's own wallet
+    let referee = create_funded_user(&mut context).await;
+    let stranger = Keypair::new();
+    let ix = join_ix(&referee, referral_program, referrer_participant, Some((match_offer, stranger.pubkey())));
+    let result = process(&mut context, &[ix], &[&referee]).await;
+    assert_referral_error(result, ReferralError::InvalidMatchOffer);
+
+    // The referral credits the base reward as usual and the sponsor's match on top of it, straight to the wallet
+    let wallet_before = get_balance(&mut context, referrer.pubkey()).await;
+    let offer_before = get_balance(&mut context, match_offer).await;
+    let matched = join_with_match(&mut context, referral_program, referrer_participant, &referrer, match_offer).await;
+    assert_eq!(matched.len(), 1);
+    assert_eq!((matched[0].sponsor, matched[0].referrer), (sponsor.pubkey(), referrer_participant));
+    assert_eq!((matched[0].amount, matched[0].remaining), (REWARD, REWARD / 2));
+    assert_eq!(get_balance(&mut context, referrer.pubkey()).await, wallet_before + REWARD);
+    assert_eq!(get_balance(&mut context, match_offer).await, offer_before - REWARD);
+    let participant: Participant = get_account(&mut context, referrer_participant).await;
+    assert_eq!((participant.pending_rewards, participant.match_received), (REWARD, REWARD));
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.total_committed, REWARD);
+
+    // The last of the budget pays a partial match, after which referrals only earn the base reward
+    let matched = join_with_match(&mut context, referral_program, referrer_participant, &referrer, match_offer).await;
+    assert_eq!((matched[0].amount, matched[0].remaining), (REWARD / 2, 0));
+    let wallet_before = get_balance(&mut context, referrer.pubkey()).await;
+    let matched = join_with_match(&mut context, referral_program, referrer_participant, &referrer, match_offer).await;
+    assert!(matched.is_empty());
+    assert_eq!(get_balance(&mut context, referrer.pubkey()).await, wallet_before);
+    let participant: Participant = get_account(&mut context, referrer_participant).await;
+    assert_eq!((participant.pending_rewards, participant.match_received), (3 * REWARD, 3 * REWARD / 2));
+    let offer: MatchOffer = get_account(&mut context, match_offer).await;
+    assert_eq!((offer.balance, offer.total_matched, offer.referrals_matched), (0, 3 * REWARD / 2, 2));
+
+    // Topping up replaces the match, and cancelling returns what is left of the budget with the rent
+    let top_up_ix = create_match_offer_ix(&sponsor, referral_program, 5_000, REWARD);
+    process(&mut context, &[top_up_ix], &[&sponsor]).await.unwrap();
+    let matched = join_with_match(&mut context, referral_program, referrer_participant, &referrer, match_offer).await;
+    assert_eq!((matched[0].amount, matched[0].remaining), (REWARD / 2, REWARD / 2));
+    let sponsor_before = get_balance(&mut context, sponsor.pubkey()).await;
+    let offer_lamports = get_balance(&mut context, match_offer).await;
+    process(&mut context, &[cancel_match_offer_ix(&sponsor, referral_program)], &[&sponsor]).await.unwrap();
+    assert_eq!(get_balance(&mut context, sponsor.pubkey()).await, sponsor_before + offer_lamports);
+    assert!(context.banks_client.get_account(match_offer).await.unwrap().is_none());
+}
+
+/// Joins a new referee through `referrer` with the match offer, returning the matches it paid
+async fn join_with_match(
+    context: &mut ProgramTestContext,
+    referral_program: Pubkey,
+    referrer_participant: Pubkey,
+    referrer: &Keypair,
+    match_offer: Pubkey,
+) -> Vec<RewardMatched> {
+    let referee = create_funded_user(context).await;
+    let ix = join_ix(&referee, referral_program, referrer_participant, Some((match_offer, referrer.pubkey())));
+    process_with_events(context, &[ix], &[&referee]).await
+}
+
+fn create_match_offer_ix(sponsor: &Keypair, referral_program: Pubkey, match_bps: u16, budget: u64) -> Instruction {
+    program_instruction(
+        accounts::CreateMatchOffer {
+            referral_program,
+            match_offer: get_match_offer_pda(referral_program, sponsor.pubkey(), solrefer::ID),
+            sponsor: sponsor.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::CreateMatchOffer { match_bps, budget },
+    )
+}
+
+fn cancel_match_offer_ix(sponsor: &Keypair, referral_program: Pubkey) -> Instruction {
+    program_instruction(
+        accounts::CancelMatchOffer {
+            referral_program,
+            match_offer: get_match_offer_pda(referral_program, sponsor.pubkey(), solrefer::ID),
+            sponsor: sponsor.pubkey(),
+        },
+        instruction::CancelMatchOffer,
+    )
+}
+
+/// Joins `user` through `referrer`, passing a match offer with the wallet it should pay when given
+fn join_ix(
+    user: &Keypair,
+    referral_program: Pubkey,
+    referrer: Pubkey,
+    matched: Option<(Pubkey, Pubkey)>,
+) -> Instruction {
+    program_instruction(
+        accounts::JoinThroughReferral {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+            referrer,
+            rotated_referrer: None,
+            split_recipient: None,
+            boost_escrow: None,
+            match_offer: matched.map(|(match_offer, _)| match_offer),
+            referrer_wallet: matched.map(|(_, wallet)| wallet),
+            referee_receipt: get_referee_receipt_pda(referral_program, user.pubkey(), solrefer::ID),
+            sponsor_vault: None,
+            invite: None,
+            referrer_collection_metadata: None,
+            referrer_collection_nft: None,
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account: None,
+            referee_token_account: None,
+            referrer_upline: None,
+            user: user.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
+            system_program: system_program::ID,
+        },
+        instruction::JoinThroughReferral { source_tag: None, accepted_terms_hash: [0u8; 32], link_proof: None },
+    )
+}
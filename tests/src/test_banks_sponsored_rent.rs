@@ -0,0 +1,232 @@
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer, system_instruction},
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::{MIN_LOCKED_PERIOD, RENT_PAYER_SPONSOR, RENT_PAYER_USER},
+    error::ReferralError,
+    instruction,
+    instructions::ProgramSettings,
+    state::{Participant, RefereeReceipt},
+};
+
+use crate::{
+    banks_util::{
+        assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol, get_account, get_balance,
+        get_clock_time, join_referral_program, process, program_instruction, setup, update_program_settings,
+    },
+    test_util::{get_eligibility_criteria_pda, get_participant_pda, get_referee_receipt_pda, get_sponsor_vault_pda},
+};
+
+const REFERRAL_REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+struct Program {
+    referral_program: Pubkey,
+    referrer_participant: Pubkey,
+    sponsor_vault: Pubkey,
+}
+
+/// Creates a funded program whose auxiliary accounts are paid for as `rent_payer_mode` says, and joins a referrer
+async fn create_program(context: &mut ProgramTestContext, owner: &Keypair, rent_payer_mode: u8) -> Program {
+    let end_time = get_clock_time(context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(context, owner, REFERRAL_REWARD, Some(end_time)).await;
+    update_program_settings(
+        context,
+        owner,
+        referral_program,
+        ProgramSettings {
+            fixed_reward_amount: REFERRAL_REWARD,
+            locked_period: MIN_LOCKED_PERIOD,
+            program_end_time: Some(end_time),
+            base_reward: REFERRAL_REWARD,
+            max_reward_cap: 10 * REFERRAL_REWARD,
+            max_depth: 0,
+            milestones: Default::default(),
+            invite_only: false,
+            revenue_share_percent: 0,
+            referee_reward_amount: 0,
+            referee_rewards_locked: false,
+            reserve_bps: 0,
+            max_referrals_per_window: 0,
+            referral_window_seconds: 0,
+            rate_limit_strict: false,
+            required_collection: None,
+            collection_gates_credits: false,
+            transfers_enabled: false,
+            dispute_window_seconds: 0,
+            rent_payer_mode,
+            min_joiner_balance: 0,
+            min_account_age_seconds: 0,
+            alert_thresholds: [0; 3],
+            referrer_requirement: None,
+            referee_requirement: None,
+            direct_claims_only: false,
+            trailing_commission_bps: 0,
+            link_signer: None,
+            dormancy_period_seconds: 0,
+            region_attestor: None,
+            embargoed_regions: [0; 8],
+            region_checked_on_claim: false,
+            bridge_source_program: None,
+            bridge_bps: 0,
+            cleanup_bounty_bps: 0,
+            event_mode: 0,
+            attribution_grace_seconds: 0,
+            tier1_reward: 0,
+            tier2_reward: 0,
+            auto_downgrade: false,
+            min_remaining_credits: 0,
+            seed_reclaim_seconds: 0,
+            early_redemption_fee: 0,
+        },
+    )
+    .await;
+    deposit_sol(context, owner, referral_program, vault, 10 * REFERRAL_REWARD).await;
+
+    let referrer = create_funded_user(context).await;
+    let referrer_participant = join_referral_program(context, &referrer, referral_program).await;
+    let sponsor_vault = get_sponsor_vault_pda(referral_program, solrefer::ID);
+    Program { referral_program, referrer_participant, sponsor_vault }
+}
+
+impl Program {
+    fn join_ix(&self, user: &Keypair, sponsor_vault: Option<Pubkey>) -> Instruction {
+        program_instruction(
+            accounts::JoinThroughReferral {
+                referral_program: self.referral_program,
+                eligibility_criteria: get_eligibility_criteria_pda(self.referral_program, solrefer::ID),
+                participant: get_participant_pda(self.referral_program, user.pubkey(), solrefer::ID),
+                referrer: self.referrer_participant,
+                rotated_referrer: None,
+                split_recipient: None,
+                boost_escrow: None,
+                match_offer: None,
+                referrer_wallet: None,
+                referee_receipt: self.receipt(user),
+                sponsor_vault,
+                invite: None,
+                referrer_collection_metadata: None,
+                referrer_collection_nft: None,
+                age_reference: None,
+                region_attestation: None,
+                referrer_token_account: None,
+                referee_token_account: None,
+                referrer_upline: None,
+                user: user.pubkey(),
+                event_queue: None,
+                instructions_sysvar: None,
+                system_program: system_program::ID,
+            },
+            instruction::JoinThroughReferral { source_tag: None, accepted_terms_hash: [0u8; 32], link_proof: None },
+        )
+    }
+
+    fn fund_ix(&self, funder: &Keypair, amount: u64) -> Instruction {
+        program_instruction(
+            accounts::FundSponsorVault {
+                referral_program: self.referral_program,
+                sponsor_vault: self.sponsor_vault,
+                funder: funder.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::FundSponsorVault { amount },
+        )
+    }
+
+    fn receipt(&self, user: &Keypair) -> Pubkey {
+        get_referee_receipt_pda(self.referral_program, user.pubkey(), solrefer::ID)
+    }
+}
+
+/// The rent of a new participant account and of a referee receipt
+async fn join_rents(context: &mut ProgramTestContext) -> (u64, u64) {
+    let rent = context.banks_client.get_rent().await.unwrap();
+    (rent.minimum_balance(8 + Participant::SIZE), rent.minimum_balance(8 + RefereeReceipt::SIZE))
+}
+
+#[tokio::test]
+async fn test_user_pays_receipt_rent_by_default() {
+    let (mut context, owner, user, _) = setup().await;
+    let program = create_program(&mut context, &owner, RENT_PAYER_USER).await;
+    let (participant_rent, receipt_rent) = join_rents(&mut context).await;
+
+    // Fees go to the bank's payer, so the user is only debited the rent of its new accounts
+    let before = get_balance(&mut context, user.pubkey()).await;
+    process(&mut context, &[program.join_ix(&user, None)], &[&user]).await.unwrap();
+    assert_eq!(get_balance(&mut context, user.pubkey()).await, before - participant_rent - receipt_rent);
+    let receipt: RefereeReceipt = get_account(&mut context, program.receipt(&user)).await;
+    assert_eq!(receipt.referrer, program.referrer_participant);
+}
+
+#[tokio::test]
+async fn test_sponsor_vault_pays_receipt_rent() {
+    let (mut context, owner, user, _) = setup().await;
+    let program = create_program(&mut context, &owner, RENT_PAYER_SPONSOR).await;
+    let (participant_rent, receipt_rent) = join_rents(&mut context).await;
+    process(&mut context, &[program.fund_ix(&owner, 10 * receipt_rent)], &[&owner]).await.unwrap();
+
+    let user_before = get_balance(&mut context, user.pubkey()).await;
+    let vault_before = get_balance(&mut context, program.sponsor_vault).await;
+    process(&mut context, &[program.join_ix(&user, Some(program.sponsor_vault))], &[&user]).await.unwrap();
+
+    // The user still pays for its own participant account, but not for the receipt
+    assert_eq!(get_balance(&mut context, program.sponsor_vault).await, vault_before - receipt_rent);
+    assert_eq!(get_balance(&mut context, user.pubkey()).await, user_before - participant_rent);
+    let receipt: RefereeReceipt = get_account(&mut context, program.receipt(&user)).await;
+    assert_eq!(receipt.referrer, program.referrer_participant);
+
+    // The authority can take back what the vault has left
+    let withdraw_ix = program_instruction(
+        accounts::WithdrawSponsorVault {
+            referral_program: program.referral_program,
+            sponsor_vault: program.sponsor_vault,
+            authority: owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::WithdrawSponsorVault { amount: vault_before - receipt_rent },
+    );
+    process(&mut context, &[withdraw_ix], &[&owner]).await.unwrap();
+    assert_eq!(get_balance(&mut context, program.sponsor_vault).await, 0);
+}
+
+#[tokio::test]
+async fn test_underfunded_sponsor_vault_rejects_join() {
+    let (mut context, owner, user, _) = setup().await;
+    let program = create_program(&mut context, &owner, RENT_PAYER_SPONSOR).await;
+    let (_, receipt_rent) = join_rents(&mut context).await;
+
+    // Nothing falls back to charging the user, whether the vault is empty or missing
+    let result = process(&mut context, &[program.join_ix(&user, Some(program.sponsor_vault))], &[&user]).await;
+    assert_referral_error(result, ReferralError::SponsorVaultUnderfunded);
+    let result = process(&mut context, &[program.join_ix(&user, None)], &[&user]).await;
+    assert_referral_error(result, ReferralError::SponsorVaultUnderfunded);
+
+    // A vault that would be left below the rent-exempt minimum cannot pay either
+    process(&mut context, &[program.fund_ix(&owner, receipt_rent + 1)], &[&owner]).await.unwrap();
+    let result = process(&mut context, &[program.join_ix(&user, Some(program.sponsor_vault))], &[&user]).await;
+    assert_referral_error(result, ReferralError::SponsorVaultUnderfunded);
+}
+
+#[tokio::test]
+async fn test_prefunded_receipt_address_is_still_created() {
+    let (mut context, owner, user, stranger) = setup().await;
+    let program = create_program(&mut context, &owner, RENT_PAYER_USER).await;
+    let (participant_rent, receipt_rent) = join_rents(&mut context).await;
+
+    // Lamports sent to the receipt address beforehand count toward its rent instead of blocking the join
+    let prefund_ix = system_instruction::transfer(&stranger.pubkey(), &program.receipt(&user), receipt_rent / 2);
+    process(&mut context, &[prefund_ix], &[&stranger]).await.unwrap();
+    let before = get_balance(&mut context, user.pubkey()).await;
+    process(&mut context, &[program.join_ix(&user, None)], &[&user]).await.unwrap();
+
+    assert_eq!(get_balance(&mut context, program.receipt(&user)).await, receipt_rent);
+    assert_eq!(
+        get_balance(&mut context, user.pubkey()).await,
+        before - participant_rent - (receipt_rent - receipt_rent / 2)
+    );
+    let receipt: RefereeReceipt = get_account(&mut context, program.receipt(&user)).await;
+    assert_eq!(receipt.referrer, program.referrer_participant);
+}
@@ -0,0 +1,199 @@
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{native_token::LAMPORTS_PER_SOL, signer::Signer},
+};
+use solrefer::{
+    accounts,
+    constants::MIN_LOCKED_PERIOD,
+    instruction,
+    instructions::{reward_statement, ProgramSettings, RewardStatementV1},
+    state::{Participant, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, claim_rewards, create_funded_user, create_sol_referral_program, deposit_sol, get_clock_time,
+        get_reward_statement, join_referral_program, join_through_referral, join_through_referral_with_boost, process,
+        program_instruction, setup, update_program_settings,
+    },
+    test_util::{get_boost_escrow_pda, get_eligibility_criteria_pda, get_referee_receipt_pda},
+};
+
+const REFERRAL_REWARD: u64 = LAMPORTS_PER_SOL / 10;
+const REFEREE_REWARD: u64 = LAMPORTS_PER_SOL / 50;
+const BOOST: u64 = LAMPORTS_PER_SOL / 100;
+const ONE_YEAR: i64 = 365 * 86400;
+
+#[test]
+fn test_reward_statement_splits_pending_by_lock() {
+    const NOW: i64 = 1_000_000;
+    let program = ReferralProgram { is_active: true, locked_period: 100, ..Default::default() };
+    let participant = Participant {
+        join_time: NOW - 50,
+        gross_credited: 1_000,
+        cap_clamped: 100,
+        clawed_back: 200,
+        total_rewards: 300,
+        pending_rewards: 400,
+        boost_received: 50,
+        ..Default::default()
+    };
+
+    let statement = reward_statement(&program, &participant, NOW);
+    assert_eq!((statement.locked, statement.claimable), (400, 0));
+    assert_eq!(statement.boost_received, 50);
+    assert!(statement.is_balanced());
+
+    let statement = reward_statement(&program, &participant, NOW + 50);
+    assert_eq!((statement.locked, statement.claimable), (0, 400));
+    assert!(statement.is_balanced());
+
+    // The boost is paid straight to the wallet, so it is not part of the identity
+    assert!(!RewardStatementV1 { gross_credited: 1, ..Default::default() }.is_balanced());
+    assert!(RewardStatementV1 { boost_received: 1, ..Default::default() }.is_balanced());
+}
+
+#[tokio::test]
+async fn test_reward_statement_explains_balance() {
+    let (mut context, owner, referrer, referee) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) =
+        create_sol_referral_program(&mut context, &owner, REFERRAL_REWARD, Some(end_time)).await;
+    update_program_settings(
+        &mut context,
+        &owner,
+        referral_program,
+        ProgramSettings {
+            fixed_reward_amount: REFERRAL_REWARD,
+            locked_period: MIN_LOCKED_PERIOD,
+            program_end_time: Some(end_time),
+            base_reward: REFERRAL_REWARD,
+            max_reward_cap: 2 * REFERRAL_REWARD + REFERRAL_REWARD / 2,
+            max_depth: 0,
+            milestones: Default::default(),
+            invite_only: false,
+            revenue_share_percent: 1_000,
+            referee_reward_amount: REFEREE_REWARD,
+            referee_rewards_locked: false,
+            reserve_bps: 0,
+            max_referrals_per_window: 0,
+            referral_window_seconds: 0,
+            rate_limit_strict: false,
+            required_collection: None,
+            collection_gates_credits: false,
+            transfers_enabled: false,
+            dispute_window_seconds: 0,
+            rent_payer_mode: 0,
+            min_joiner_balance: 0,
+            min_account_age_seconds: 0,
+            alert_thresholds: [0; 3],
+            referrer_requirement: None,
+            referee_requirement: None,
+            direct_claims_only: false,
+            trailing_commission_bps: 0,
+            link_signer: None,
+            dormancy_period_seconds: 0,
+            region_attestor: None,
+            embargoed_regions: [0; 8],
+            region_checked_on_claim: false,
+            bridge_source_program: None,
+            bridge_bps: 0,
+            cleanup_bounty_bps: 0,
+            event_mode: 0,
+            attribution_grace_seconds: 0,
+            tier1_reward: 0,
+            tier2_reward: 0,
+            auto_downgrade: false,
+            min_remaining_credits: 0,
+            seed_reclaim_seconds: 0,
+            early_redemption_fee: 0,
+        },
+    )
+    .await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REFERRAL_REWARD).await;
+
+    // The referrer funds a boost for its first referee and is credited two referrals
+    let referrer_participant = join_referral_program(&mut context, &referrer, referral_program).await;
+    let boost_escrow = get_boost_escrow_pda(referral_program, referrer_participant, solrefer::ID);
+    let fund_ix = program_instruction(
+        accounts::FundRefereeBoost {
+            referral_program,
+            participant: referrer_participant,
+            boost_escrow,
+            user: referrer.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::FundRefereeBoost { amount: BOOST, boost_per_referee: BOOST },
+    );
+    process(&mut context, &[fund_ix], &[&referrer]).await.unwrap();
+    let referee_participant = join_through_referral_with_boost(
+        &mut context,
+        &referee,
+        referral_program,
+        referrer_participant,
+        Some(boost_escrow),
+    )
+    .await;
+    let second_referee = create_funded_user(&mut context).await;
+    join_through_referral(&mut context, &second_referee, referral_program, referrer_participant).await;
+
+    // A purchase worth a full referral reward only fits half a reward under the cap
+    let purchase_ix = program_instruction(
+        accounts::RecordPurchase {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            buyer: referee_participant,
+            referrer: referrer_participant,
+            referrer_upline: None,
+            authority: owner.pubkey(),
+        },
+        instruction::RecordPurchase { amount: 10 * REFERRAL_REWARD, idempotency_key: None },
+    );
+    process(&mut context, &[purchase_ix], &[&owner]).await.unwrap();
+
+    // The second referral is reversed
+    let clawback_ix = program_instruction(
+        accounts::ClawbackReferral {
+            referral_program,
+            referee_receipt: get_referee_receipt_pda(referral_program, second_referee.pubkey(), solrefer::ID),
+            referrer: referrer_participant,
+            authority: owner.pubkey(),
+        },
+        instruction::ClawbackReferral { idempotency_key: None },
+    );
+    process(&mut context, &[clawback_ix], &[&owner]).await.unwrap();
+
+    let pending = 2 * REFERRAL_REWARD + REFERRAL_REWARD / 2 - REFERRAL_REWARD;
+    let statement = get_reward_statement(&mut context, referral_program, referrer_participant).await;
+    assert_eq!(
+        statement,
+        RewardStatementV1 {
+            gross_credited: 3 * REFERRAL_REWARD,
+            cap_clamped: REFERRAL_REWARD / 2,
+            clawed_back: REFERRAL_REWARD,
+            locked: pending,
+            ..Default::default()
+        }
+    );
+    assert!(statement.is_balanced());
+
+    let statement = get_reward_statement(&mut context, referral_program, referee_participant).await;
+    assert_eq!(
+        statement,
+        RewardStatementV1 {
+            gross_credited: REFEREE_REWARD,
+            boost_received: BOOST,
+            locked: REFEREE_REWARD,
+            ..Default::default()
+        }
+    );
+
+    // Past the locked period the pending rewards become claimable, and claiming moves them to claimed
+    advance_clock(&mut context, MIN_LOCKED_PERIOD).await;
+    let statement = get_reward_statement(&mut context, referral_program, referrer_participant).await;
+    assert_eq!((statement.locked, statement.claimable), (0, pending));
+    claim_rewards(&mut context, &referrer, referral_program, referrer_participant, vault).await.unwrap();
+    let statement = get_reward_statement(&mut context, referral_program, referrer_participant).await;
+    assert_eq!((statement.claimed, statement.locked, statement.claimable), (pending, 0, 0));
+    assert!(statement.is_balanced());
+}
@@ -0,0 +1,111 @@
+//! Program terms accepted on join.
+//!
+//! Joins must carry the hash of the program's current terms and record the version accepted. New terms leave
+//! existing participants on the version they accepted until they re-accept the current one.
+
+use anchor_client::solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer};
+use solrefer::{
+    accounts,
+    error::ReferralError,
+    instruction,
+    state::{Participant, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol, get_account,
+        join_referral_program_accounts, join_through_referral_accounts, process, program_instruction, setup,
+    },
+    test_util::get_participant_pda,
+};
+
+const REWARD: u64 = 1_000_000;
+const TERMS_V1: [u8; 32] = [1u8; 32];
+const TERMS_V2: [u8; 32] = [2u8; 32];
+
+fn join_ix(user: &Keypair, referral_program: Pubkey, accepted_terms_hash: [u8; 32]) -> Instruction {
+    program_instruction(
+        join_referral_program_accounts(user, referral_program),
+        instruction::JoinReferralProgram { accepted_terms_hash },
+    )
+}
+
+fn join_through_ix(
+    user: &Keypair,
+    referral_program: Pubkey,
+    referrer: Pubkey,
+    accepted_terms_hash: [u8; 32],
+) -> Instruction {
+    program_instruction(
+        join_through_referral_accounts(user, referral_program, referrer),
+        instruction::JoinThroughReferral { source_tag: None, accepted_terms_hash, link_proof: None },
+    )
+}
+
+#[test]
+fn test_require_current_terms() {
+    let program = ReferralProgram { terms_hash: TERMS_V1, terms_version: 1, ..Default::default() };
+    assert!(program.require_current_terms(&TERMS_V1).is_ok());
+    assert_eq!(program.require_current_terms(&TERMS_V2).unwrap_err(), ReferralError::TermsMismatch.into());
+}
+
+#[tokio::test]
+async fn test_joins_record_accepted_terms() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, None).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let update_terms_ix = |terms_hash: [u8; 32]| {
+        program_instruction(
+            accounts::UpdateTerms { referral_program, authority: owner.pubkey() },
+            instruction::UpdateTerms { terms_hash },
+        )
+    };
+
+    process(&mut context, &[update_terms_ix(TERMS_V1)], &[&owner]).await.unwrap();
+    let program_account: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program_account.terms_hash, TERMS_V1);
+    assert_eq!(program_account.terms_version, 1);
+
+    // Stale terms are rejected by both joins
+    let ix = join_ix(&alice, referral_program, [0u8; 32]);
+    assert_referral_error(process(&mut context, &[ix], &[&alice]).await, ReferralError::TermsMismatch);
+
+    process(&mut context, &[join_ix(&alice, referral_program, TERMS_V1)], &[&alice]).await.unwrap();
+    let alice_participant = get_participant_pda(referral_program, alice.pubkey(), solrefer::ID);
+    let participant: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!(participant.accepted_terms_hash, TERMS_V1);
+    assert_eq!(participant.accepted_terms_version, 1);
+
+    let ix = join_through_ix(&bob, referral_program, alice_participant, TERMS_V2);
+    assert_referral_error(process(&mut context, &[ix], &[&bob]).await, ReferralError::TermsMismatch);
+    let ix = join_through_ix(&bob, referral_program, alice_participant, TERMS_V1);
+    process(&mut context, &[ix], &[&bob]).await.unwrap();
+    let participant: Participant =
+        get_account(&mut context, get_participant_pda(referral_program, bob.pubkey(), solrefer::ID)).await;
+    assert_eq!(participant.accepted_terms_hash, TERMS_V1);
+    assert_eq!(participant.accepted_terms_version, 1);
+
+    // New terms leave existing participants on the version they accepted
+    process(&mut context, &[update_terms_ix(TERMS_V2)], &[&owner]).await.unwrap();
+    let participant: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!(participant.accepted_terms_version, 1);
+    let carol = create_funded_user(&mut context).await;
+    let ix = join_ix(&carol, referral_program, TERMS_V1);
+    assert_referral_error(process(&mut context, &[ix], &[&carol]).await, ReferralError::TermsMismatch);
+
+    let reaccept_ix = |accepted_terms_hash: [u8; 32]| {
+        program_instruction(
+            accounts::ReacceptTerms { referral_program, participant: alice_participant, user: alice.pubkey() },
+            instruction::ReacceptTerms { accepted_terms_hash },
+        )
+    };
+    assert_referral_error(
+        process(&mut context, &[reaccept_ix(TERMS_V1)], &[&alice]).await,
+        ReferralError::TermsMismatch,
+    );
+    process(&mut context, &[reaccept_ix(TERMS_V2)], &[&alice]).await.unwrap();
+
+    let participant: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!(participant.accepted_terms_hash, TERMS_V2);
+    assert_eq!(participant.accepted_terms_version, 2);
+}
@@ -0,0 +1,305 @@
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer},
+};
+use anchor_spl::token::spl_token;
+use solana_program_test::{BanksClientError, ProgramTestContext};
+use solrefer::{
+    accounts,
+    constants::MIN_LOCKED_PERIOD,
+    error::ReferralError,
+    instruction,
+    instructions::{ProgramSettings, ReferralPreview},
+    state::TokenRequirement,
+};
+
+use crate::{
+    banks_util::{
+        assert_referral_error, create_funded_user, create_mint, create_sol_referral_program, create_token_account,
+        deposit_sol, get_clock_time, process, program_instruction, setup, simulate_return, update_program_settings,
+        update_program_settings_ix,
+    },
+    test_util::{get_eligibility_criteria_pda, get_participant_pda, get_referee_receipt_pda},
+};
+
+const REFERRAL_REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+/// The token amount each requirement asks for
+const MIN_AMOUNT: u64 = 1_000;
+
+fn settings(
+    program_end_time: i64,
+    referrer_requirement: Option<TokenRequirement>,
+    referee_requirement: Option<TokenRequirement>,
+) -> ProgramSettings {
+    ProgramSettings {
+        fixed_reward_amount: REFERRAL_REWARD,
+        locked_period: MIN_LOCKED_PERIOD,
+        program_end_time: Some(program_end_time),
+        base_reward: REFERRAL_REWARD,
+        max_reward_cap: 10 * REFERRAL_REWARD,
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement,
+        referee_requirement,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
+    }
+}
+
+/// A wallet with one token account of the required mint holding `MIN_AMOUNT` and one holding a unit less
+struct Holder {
+    wallet: Keypair,
+    enough: Pubkey,
+    too_little: Pubkey,
+}
+
+async fn create_holder(context: &mut ProgramTestContext, issuer: &Keypair, mint: Pubkey) -> Holder {
+    let wallet = create_funded_user(context).await;
+    let enough = create_token_account(context, wallet.pubkey(), mint).await;
+    let too_little = create_token_account(context, wallet.pubkey(), mint).await;
+    let mint_ixs = [
+        spl_token::instruction::mint_to(&spl_token::id(), &mint, &enough, &issuer.pubkey(), &[], MIN_AMOUNT).unwrap(),
+        spl_token::instruction::mint_to(&spl_token::id(), &mint, &too_little, &issuer.pubkey(), &[], MIN_AMOUNT - 1)
+            .unwrap(),
+    ];
+    process(context, &mint_ixs, &[issuer]).await.expect("Failed to mint tokens");
+    Holder { wallet, enough, too_little }
+}
+
+fn join_ix(user: &Keypair, referral_program: Pubkey, referrer_token_account: Option<Pubkey>) -> Instruction {
+    program_instruction(
+        accounts::JoinReferralProgram {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+            invite: None,
+            collection_metadata: None,
+            collection_nft: None,
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account,
+            bridge_participant: None,
+            bridge_referrer: None,
+            user: user.pubkey(),
+            event_queue: None,
+            system_program: system_program::ID,
+        },
+        instruction::JoinReferralProgram { accepted_terms_hash: [0u8; 32] },
+    )
+}
+
+fn join_through_referral_ix(
+    user: &Keypair,
+    referral_program: Pubkey,
+    referrer: Pubkey,
+    referrer_token_account: Option<Pubkey>,
+    referee_token_account: Option<Pubkey>,
+) -> Instruction {
+    program_instruction(
+        accounts::JoinThroughReferral {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+            referrer,
+            rotated_referrer: None,
+            split_recipient: None,
+            boost_escrow: None,
+            match_offer: None,
+            referrer_wallet: None,
+            referee_receipt: get_referee_receipt_pda(referral_program, user.pubkey(), solrefer::ID),
+            sponsor_vault: None,
+            invite: None,
+            referrer_collection_metadata: None,
+            referrer_collection_nft: None,
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account,
+            referee_token_account,
+            referrer_upline: None,
+            user: user.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
+            system_program: system_program::ID,
+        },
+        instruction::JoinThroughReferral { source_tag: None, accepted_terms_hash: [0u8; 32], link_proof: None },
+    )
+}
+
+/// Expects `result` to fail with `error` when `fails` is set and to succeed otherwise
+fn assert_outcome(result: Result<(), BanksClientError>, fails: bool, error: ReferralError) {
+    if fails {
+        assert_referral_error(result, error);
+    } else {
+        result.unwrap();
+    }
+}
+
+/// Runs every join against a program requiring `MIN_AMOUNT` of a token of referrers, referees or both, as set
+async fn check_requirements(context: &mut ProgramTestContext, referrer_required: bool, referee_required: bool) {
+    let owner = create_funded_user(context).await;
+    let mint = create_mint(context, &owner).await;
+    let requirement = TokenRequirement { mint, min_amount: MIN_AMOUNT };
+    let end_time = get_clock_time(context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(context, &owner, REFERRAL_REWARD, Some(end_time)).await;
+    let referrer_requirement = referrer_required.then_some(requirement);
+    let referee_requirement = referee_required.then_some(requirement);
+    update_program_settings(
+        context,
+        &owner,
+        referral_program,
+        settings(end_time, referrer_requirement, referee_requirement),
+    )
+    .await;
+    deposit_sol(context, &owner, referral_program, vault, 10 * REFERRAL_REWARD).await;
+
+    // Joining as a referrer
+    let referrer = create_holder(context, &owner, mint).await;
+    let result = process(
+        context,
+        &[join_ix(&referrer.wallet, referral_program, Some(referrer.too_little))],
+        &[&referrer.wallet],
+    )
+    .await;
+    assert_outcome(result, referrer_required, ReferralError::ReferrerRequirementNotMet);
+    let referrer_participant = get_participant_pda(referral_program, referrer.wallet.pubkey(), solrefer::ID);
+    if referrer_required {
+        process(context, &[join_ix(&referrer.wallet, referral_program, Some(referrer.enough))], &[&referrer.wallet])
+            .await
+            .unwrap();
+    }
+
+    // The preview names the side that falls short
+    let short_referee = create_holder(context, &owner, mint).await;
+    let preview_ix = program_instruction(
+        accounts::PreviewReferral {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            referrer: referrer_participant,
+            boost_escrow: None,
+            referrer_token_account: Some(referrer.too_little),
+            referee_token_account: Some(short_referee.too_little),
+        },
+        instruction::PreviewReferral,
+    );
+    let preview: ReferralPreview = simulate_return(context, preview_ix).await;
+    assert_eq!(
+        (preview.referrer_requirement_failed, preview.referee_requirement_failed),
+        (referrer_required, referee_required)
+    );
+
+    // Joining through a referral checks the referee, and the referrer again since it may have sold its tokens
+    let ix = join_through_referral_ix(
+        &short_referee.wallet,
+        referral_program,
+        referrer_participant,
+        Some(referrer.enough),
+        Some(short_referee.too_little),
+    );
+    let result = process(context, &[ix], &[&short_referee.wallet]).await;
+    assert_outcome(result, referee_required, ReferralError::RefereeRequirementNotMet);
+
+    let referee = create_holder(context, &owner, mint).await;
+    let ix = join_through_referral_ix(
+        &referee.wallet,
+        referral_program,
+        referrer_participant,
+        Some(referrer.too_little),
+        Some(referee.enough),
+    );
+    let result = process(context, &[ix], &[&referee.wallet]).await;
+    assert_outcome(result, referrer_required, ReferralError::ReferrerRequirementNotMet);
+
+    // Another wallet's tokens never count for the referee
+    let borrower = create_holder(context, &owner, mint).await;
+    let ix = join_through_referral_ix(
+        &borrower.wallet,
+        referral_program,
+        referrer_participant,
+        Some(referrer.enough),
+        Some(referee.enough),
+    );
+    let result = process(context, &[ix], &[&borrower.wallet]).await;
+    assert_outcome(result, referee_required, ReferralError::RefereeRequirementNotMet);
+
+    // A side without a requirement needs no token account at all
+    let last = create_holder(context, &owner, mint).await;
+    let ix = join_through_referral_ix(
+        &last.wallet,
+        referral_program,
+        referrer_participant,
+        referrer_required.then_some(referrer.enough),
+        referee_required.then_some(last.enough),
+    );
+    process(context, &[ix], &[&last.wallet]).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_no_token_requirements() {
+    let (mut context, _, _, _) = setup().await;
+    check_requirements(&mut context, false, false).await;
+}
+
+#[tokio::test]
+async fn test_referrer_token_requirement() {
+    let (mut context, _, _, _) = setup().await;
+    check_requirements(&mut context, true, false).await;
+}
+
+#[tokio::test]
+async fn test_referee_token_requirement() {
+    let (mut context, _, _, _) = setup().await;
+    check_requirements(&mut context, false, true).await;
+}
+
+#[tokio::test]
+async fn test_referrer_and_referee_token_requirements() {
+    let (mut context, _, _, _) = setup().await;
+    check_requirements(&mut context, true, true).await;
+}
+
+#[tokio::test]
+async fn test_zero_token_requirement_is_rejected() {
+    let (mut context, owner, _, _) = setup().await;
+    let mint = create_mint(&mut context, &owner).await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, _) =
+        create_sol_referral_program(&mut context, &owner, REFERRAL_REWARD, Some(end_time)).await;
+    let requirement = Some(TokenRequirement { mint, min_amount: 0 });
+    let ix =
+        update_program_settings_ix(&mut context, &owner, referral_program, settings(end_time, None, requirement)).await;
+    let result = process(&mut context, &[ix], &[&owner]).await;
+    assert_referral_error(result, ReferralError::InvalidTokenRequirement);
+}
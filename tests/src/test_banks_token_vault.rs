@@ -0,0 +1,64 @@
+//! A wound-down token program closing its token vault.
+//!
+//! The program keeps its mint and decimals once its vault is closed, so its totals stay in token units. It
+//! still rejects SOL claims, and neither reopening the vault nor unpausing the program brings it back.
+
+use anchor_client::solana_sdk::{pubkey::Pubkey, signer::Signer};
+use anchor_spl::token::TokenAccount;
+use solrefer::{constants::REFERRAL_PROGRAM_SEED, error::ReferralError, state::ReferralProgram};
+
+use crate::banks_util::{
+    assert_referral_error, claim_rewards, close_token_vault_ix, create_funded_token_account, create_mint,
+    create_token_referral_program, deposit_token_ix, get_account, get_clock_time, initialize_token_vault_ix,
+    join_referral_program, process, referral_program_pdas, set_program_status_ix, setup,
+};
+
+const REWARD: u64 = 1_000_000;
+const DEPOSIT: u64 = 10 * REWARD;
+const ONE_YEAR: i64 = 365 * 86400;
+
+#[tokio::test]
+async fn test_closed_token_vault_keeps_the_mint() {
+    let (mut context, owner, alice, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let mint = create_mint(&mut context, &owner).await;
+    let owner_token_account = create_funded_token_account(&mut context, &owner, mint, DEPOSIT).await;
+    let (referral_program, token_vault) =
+        create_token_referral_program(&mut context, &owner, mint, REWARD, Some(end_time)).await;
+    let (_, vault, _) = referral_program_pdas(owner.pubkey());
+    let deposit_ix = deposit_token_ix(&owner, referral_program, token_vault, mint, owner_token_account, DEPOSIT);
+    process(&mut context, &[deposit_ix], &[&owner]).await.unwrap();
+    let participant = join_referral_program(&mut context, &alice, referral_program).await;
+
+    let pause_ix = set_program_status_ix(&owner, referral_program, false);
+    let close_vault_ix = close_token_vault_ix(&owner, referral_program, token_vault, Some(owner_token_account));
+    process(&mut context, &[pause_ix, close_vault_ix], &[&owner]).await.unwrap();
+
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!((program.token_mint, program.token_decimals), (mint, 9));
+    assert!(program.token_vault_closed && !program.token_vault_initialized);
+    assert_eq!((program.total_available, program.total_deposited), (0, DEPOSIT));
+
+    // It is still a token program to the SOL paths, and stays wound down
+    let result = claim_rewards(&mut context, &alice, referral_program, participant, vault).await;
+    assert_referral_error(result, ReferralError::InvalidTokenMint);
+    let ix = initialize_token_vault_ix(&owner, mint);
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::TokenVaultClosed);
+    let ix = set_program_status_ix(&owner, referral_program, true);
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::TokenVaultClosed);
+}
+
+#[tokio::test]
+async fn test_token_vault_authority_keeps_the_unindexed_address() {
+    let (mut context, owner, _, _) = setup().await;
+    let mint = create_mint(&mut context, &owner).await;
+    let (referral_program, token_vault) = create_token_referral_program(&mut context, &owner, mint, REWARD, None).await;
+
+    // The vault's authority is the program account at the address it had before programs were indexed, so no
+    // vault needs its authority rotated
+    let (legacy_address, _) =
+        Pubkey::find_program_address(&[REFERRAL_PROGRAM_SEED, owner.pubkey().as_ref()], &solrefer::ID);
+    assert_eq!(referral_program, legacy_address);
+    let vault: TokenAccount = get_account(&mut context, token_vault).await;
+    assert_eq!(vault.owner, legacy_address);
+}
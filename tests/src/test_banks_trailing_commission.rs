@@ -0,0 +1,239 @@
+//! Trailing commissions.
+//!
+//! Alice refers Bob, who refers Carol. Whatever Bob and Carol earn past the referral reward itself pays their
+//! referrer a commission on top, and an instruction crediting a commission fails unless it is given the account.
+
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer},
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    accounts,
+    constants::{MAX_MILESTONES, MAX_TRAILING_COMMISSION_BPS, MIN_LOCKED_PERIOD},
+    error::ReferralError,
+    instruction,
+    instructions::ProgramSettings,
+    state::{EligibilityCriteria, Milestone, Participant},
+};
+
+use crate::{
+    banks_util::{
+        assert_referral_error, create_funded_user, create_sol_referral_program, deposit_sol, get_account,
+        get_clock_time, join_referral_program, join_through_referral, process, program_instruction, setup,
+        update_program_settings, update_program_settings_ix,
+    },
+    test_util::{get_eligibility_criteria_pda, get_participant_pda, get_referee_receipt_pda},
+};
+
+const REWARD: u64 = 1_000_000;
+const REFEREE_REWARD: u64 = REWARD / 2;
+const BONUS: u64 = REWARD;
+const PURCHASE: u64 = 10 * REWARD;
+/// 10%, for both the revenue share and the commission
+const BPS: u64 = 1_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+fn settings(
+    end_time: i64,
+    referee_reward_amount: u64,
+    milestones: [Milestone; MAX_MILESTONES],
+    trailing_commission_bps: u64,
+) -> ProgramSettings {
+    ProgramSettings {
+        fixed_reward_amount: REWARD,
+        locked_period: MIN_LOCKED_PERIOD,
+        program_end_time: Some(end_time),
+        base_reward: REWARD,
+        max_reward_cap: 100 * REWARD,
+        max_depth: 0,
+        milestones,
+        invite_only: false,
+        revenue_share_percent: BPS,
+        referee_reward_amount,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
+    }
+}
+
+/// Creates a funded program with the given settings and joins Alice directly and Bob through Alice
+async fn create_program(
+    context: &mut ProgramTestContext,
+    owner: &Keypair,
+    alice: &Keypair,
+    bob: &Keypair,
+    referee_reward_amount: u64,
+    milestones: [Milestone; MAX_MILESTONES],
+) -> (Pubkey, Pubkey, Pubkey) {
+    let end_time = get_clock_time(context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(context, owner, REWARD, Some(end_time)).await;
+    let settings = settings(end_time, referee_reward_amount, milestones, BPS);
+    update_program_settings(context, owner, referral_program, settings).await;
+    deposit_sol(context, owner, referral_program, vault, 100 * REWARD).await;
+
+    let alice_participant = join_referral_program(context, alice, referral_program).await;
+    let bob_participant = join_through_referral(context, bob, referral_program, alice_participant).await;
+    (referral_program, alice_participant, bob_participant)
+}
+
+fn join_ix(user: &Keypair, referral_program: Pubkey, referrer: Pubkey, referrer_upline: Option<Pubkey>) -> Instruction {
+    program_instruction(
+        accounts::JoinThroughReferral {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+            referrer,
+            rotated_referrer: None,
+            split_recipient: None,
+            boost_escrow: None,
+            match_offer: None,
+            referrer_wallet: None,
+            referee_receipt: get_referee_receipt_pda(referral_program, user.pubkey(), solrefer::ID),
+            sponsor_vault: None,
+            invite: None,
+            referrer_collection_metadata: None,
+            referrer_collection_nft: None,
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account: None,
+            referee_token_account: None,
+            referrer_upline,
+            user: user.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
+            system_program: system_program::ID,
+        },
+        instruction::JoinThroughReferral { source_tag: None, accepted_terms_hash: [0u8; 32], link_proof: None },
+    )
+}
+
+fn purchase_ix(
+    owner: &Keypair,
+    referral_program: Pubkey,
+    buyer: Pubkey,
+    referrer: Pubkey,
+    referrer_upline: Option<Pubkey>,
+) -> Instruction {
+    program_instruction(
+        accounts::RecordPurchase {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            buyer,
+            referrer,
+            referrer_upline,
+            authority: owner.pubkey(),
+        },
+        instruction::RecordPurchase { amount: PURCHASE, idempotency_key: None },
+    )
+}
+
+async fn pending_rewards(context: &mut ProgramTestContext, participant: Pubkey) -> u64 {
+    let participant: Participant = get_account(context, participant).await;
+    participant.pending_rewards
+}
+
+#[test]
+fn test_trailing_commission_rounds_down_without_overflow() {
+    let criteria = EligibilityCriteria { trailing_commission_bps: BPS, ..Default::default() };
+    assert_eq!(criteria.trailing_commission(REWARD).unwrap(), REWARD / 10);
+    assert_eq!(criteria.trailing_commission(9).unwrap(), 0);
+    assert_eq!(criteria.trailing_commission(u64::MAX).unwrap(), u64::MAX / 10);
+}
+
+#[tokio::test]
+async fn test_commission_on_referee_bonus_and_purchase_rewards() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let (referral_program, alice_participant, bob_participant) =
+        create_program(&mut context, &owner, &alice, &bob, REFEREE_REWARD, Default::default()).await;
+
+    // Alice earns the referral reward for Bob plus a tenth of his sign-up bonus
+    assert_eq!(pending_rewards(&mut context, alice_participant).await, REWARD + REFEREE_REWARD / 10);
+    assert_eq!(pending_rewards(&mut context, bob_participant).await, REFEREE_REWARD);
+
+    // Carol's join pays Bob the same way and leaves Alice untouched, the commission stopping one level up
+    let carol = create_funded_user(&mut context).await;
+    let carol_participant = join_through_referral(&mut context, &carol, referral_program, bob_participant).await;
+    assert_eq!(pending_rewards(&mut context, alice_participant).await, REWARD + REFEREE_REWARD / 10);
+    assert_eq!(pending_rewards(&mut context, bob_participant).await, REFEREE_REWARD + REWARD + REFEREE_REWARD / 10);
+
+    // Bob's purchase credits Alice, who has no referrer to pay a commission to
+    let ix = purchase_ix(&owner, referral_program, bob_participant, alice_participant, None);
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    assert_eq!(pending_rewards(&mut context, alice_participant).await, 2 * REWARD + REFEREE_REWARD / 10);
+
+    // Carol's purchase credits Bob, and cannot leave out Alice's commission on it
+    let ix = purchase_ix(&owner, referral_program, carol_participant, bob_participant, None);
+    let result = process(&mut context, &[ix], &[&owner]).await;
+    assert_referral_error(result, ReferralError::ReferrerAccountRequired);
+    let ix = purchase_ix(&owner, referral_program, carol_participant, bob_participant, Some(carol_participant));
+    let result = process(&mut context, &[ix], &[&owner]).await;
+    assert_referral_error(result, ReferralError::ReferrerAccountRequired);
+
+    let bob_before = pending_rewards(&mut context, bob_participant).await;
+    let ix = purchase_ix(&owner, referral_program, carol_participant, bob_participant, Some(alice_participant));
+    process(&mut context, &[ix], &[&owner]).await.unwrap();
+    assert_eq!(pending_rewards(&mut context, bob_participant).await, bob_before + REWARD);
+    assert_eq!(pending_rewards(&mut context, alice_participant).await, 2 * REWARD + REFEREE_REWARD / 10 + REWARD / 10);
+}
+
+#[tokio::test]
+async fn test_commission_on_milestone_bonus() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let mut milestones = [Milestone::default(); MAX_MILESTONES];
+    milestones[0] = Milestone { threshold: 1, bonus: BONUS };
+    let (referral_program, alice_participant, bob_participant) =
+        create_program(&mut context, &owner, &alice, &bob, 0, milestones).await;
+    assert_eq!(pending_rewards(&mut context, alice_participant).await, REWARD + BONUS);
+
+    // Carol's join brings Bob to the milestone, so it needs Alice's account for her commission on the bonus
+    let carol = create_funded_user(&mut context).await;
+    let result = process(&mut context, &[join_ix(&carol, referral_program, bob_participant, None)], &[&carol]).await;
+    assert_referral_error(result, ReferralError::ReferrerAccountRequired);
+
+    let ix = join_ix(&carol, referral_program, bob_participant, Some(alice_participant));
+    process(&mut context, &[ix], &[&carol]).await.unwrap();
+    assert_eq!(pending_rewards(&mut context, bob_participant).await, REWARD + BONUS);
+    assert_eq!(pending_rewards(&mut context, alice_participant).await, REWARD + BONUS + BONUS / 10);
+}
+
+#[tokio::test]
+async fn test_trailing_commission_above_max_is_rejected() {
+    let (mut context, owner, _, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, _) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    let settings = settings(end_time, 0, Default::default(), MAX_TRAILING_COMMISSION_BPS + 1);
+    let ix = update_program_settings_ix(&mut context, &owner, referral_program, settings).await;
+    let result = process(&mut context, &[ix], &[&owner]).await;
+    assert_referral_error(result, ReferralError::InvalidTrailingCommission);
+}
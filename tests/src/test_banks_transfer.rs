@@ -0,0 +1,154 @@
+use anchor_client::solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer};
+use solrefer::{
+    accounts,
+    constants::MIN_LOCKED_PERIOD,
+    error::ReferralError,
+    instruction,
+    instructions::{ProgramSettings, RewardStatementV1},
+    state::Participant,
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, assert_referral_error, claim_rewards, create_funded_user, create_sol_referral_program,
+        deposit_sol, get_account, get_clock_time, get_reward_statement, join_referral_program, join_through_referral,
+        process, program_instruction, setup, update_program_settings,
+    },
+    test_util::{get_eligibility_criteria_pda, get_participant_pda},
+};
+
+const REFERRAL_REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+fn settings(program_end_time: i64, transfers_enabled: bool) -> ProgramSettings {
+    ProgramSettings {
+        fixed_reward_amount: REFERRAL_REWARD,
+        locked_period: MIN_LOCKED_PERIOD,
+        program_end_time: Some(program_end_time),
+        base_reward: REFERRAL_REWARD,
+        max_reward_cap: 2 * REFERRAL_REWARD,
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
+    }
+}
+
+#[test]
+fn test_unlocks_at_keeps_the_later_lock() {
+    let participant = Participant { join_time: 1_000, locked_until: 5_000, ..Default::default() };
+    assert_eq!(participant.unlocks_at(100), 5_000);
+    assert_eq!(participant.unlocks_at(10_000), 11_000);
+}
+
+#[tokio::test]
+async fn test_transfer_pending_keeps_source_lock() {
+    let (mut context, owner, company, employee) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) =
+        create_sol_referral_program(&mut context, &owner, REFERRAL_REWARD, Some(end_time)).await;
+    update_program_settings(&mut context, &owner, referral_program, settings(end_time, false)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REFERRAL_REWARD).await;
+
+    // The company joins well before the employee and earns a referral of its own
+    let company_participant = join_referral_program(&mut context, &company, referral_program).await;
+    let referee = create_funded_user(&mut context).await;
+    join_through_referral(&mut context, &referee, referral_program, company_participant).await;
+    advance_clock(&mut context, MIN_LOCKED_PERIOD / 2).await;
+    let employee_participant = join_referral_program(&mut context, &employee, referral_program).await;
+    for _ in 0..2 {
+        let referee = create_funded_user(&mut context).await;
+        join_through_referral(&mut context, &referee, referral_program, employee_participant).await;
+    }
+    let source: Participant = get_account(&mut context, employee_participant).await;
+    let employee_unlocks_at = source.join_time + MIN_LOCKED_PERIOD;
+
+    let transfer_ix = |amount: u64| transfer_pending_ix(&employee, referral_program, company_participant, amount);
+    let result = process(&mut context, &[transfer_ix(REFERRAL_REWARD)], &[&employee]).await;
+    assert_referral_error(result, ReferralError::TransfersDisabled);
+    update_program_settings(&mut context, &owner, referral_program, settings(end_time, true)).await;
+
+    // The employee leaves and assigns half of its locked rewards to the company some time later
+    advance_clock(&mut context, MIN_LOCKED_PERIOD / 4).await;
+    let result = process(&mut context, &[transfer_ix(3 * REFERRAL_REWARD)], &[&employee]).await;
+    assert_referral_error(result, ReferralError::InvalidTransferAmount);
+    process(&mut context, &[transfer_ix(REFERRAL_REWARD)], &[&employee]).await.unwrap();
+    let source: Participant = get_account(&mut context, employee_participant).await;
+    assert_eq!((source.pending_rewards, source.transferred_out), (REFERRAL_REWARD, REFERRAL_REWARD));
+    let destination: Participant = get_account(&mut context, company_participant).await;
+    assert_eq!(destination.pending_rewards, 2 * REFERRAL_REWARD);
+    assert_eq!(destination.locked_until, employee_unlocks_at);
+    let statement = get_reward_statement(&mut context, referral_program, employee_participant).await;
+    assert_eq!(
+        statement,
+        RewardStatementV1 {
+            gross_credited: 2 * REFERRAL_REWARD,
+            locked: REFERRAL_REWARD,
+            transferred_out: REFERRAL_REWARD,
+            ..Default::default()
+        }
+    );
+
+    // The company is at the reward cap, so the rest cannot follow
+    let result = process(&mut context, &[transfer_ix(REFERRAL_REWARD)], &[&employee]).await;
+    assert_referral_error(result, ReferralError::RewardCapExceeded);
+
+    // The company's own lock has elapsed but the transferred rewards are still within the employee's
+    advance_clock(&mut context, MIN_LOCKED_PERIOD / 4 + 1).await;
+    let result = claim_rewards(&mut context, &company, referral_program, company_participant, vault).await;
+    assert_referral_error(result, ReferralError::RewardsLocked);
+
+    // Once the employee's original lock elapses, not a full locked period after the transfer, the company claims
+    let now = get_clock_time(&mut context).await;
+    advance_clock(&mut context, employee_unlocks_at - now).await;
+    claim_rewards(&mut context, &company, referral_program, company_participant, vault).await.unwrap();
+    let destination: Participant = get_account(&mut context, company_participant).await;
+    assert_eq!((destination.pending_rewards, destination.total_rewards), (0, 2 * REFERRAL_REWARD));
+}
+
+fn transfer_pending_ix(user: &Keypair, referral_program: Pubkey, destination: Pubkey, amount: u64) -> Instruction {
+    program_instruction(
+        accounts::TransferPending {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            source: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+            destination,
+            user: user.pubkey(),
+        },
+        instruction::TransferPending { amount },
+    )
+}
@@ -0,0 +1,104 @@
+use anchor_client::solana_sdk::signer::Signer;
+use anchor_spl::token::spl_token;
+use solrefer::{
+    accounts,
+    constants::MIN_LOCKED_PERIOD,
+    instruction,
+    state::{to_ui, ReferralProgram},
+};
+
+use crate::{
+    banks_util::{
+        advance_clock, create_funded_token_account, create_mint_with_decimals, create_sol_referral_program,
+        create_token_account, create_token_referral_program, deposit_sol, get_account, get_clock_time,
+        join_referral_program, join_through_referral, process, program_instruction, setup,
+    },
+    test_util::get_eligibility_criteria_pda,
+};
+
+/// 1.5 tokens of a 6-decimal mint
+const REWARD: u64 = 1_500_000;
+/// 10 tokens of a 6-decimal mint
+const DEPOSIT: u64 = 10_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+#[test]
+fn test_to_ui() {
+    assert_eq!(to_ui(0, 6), 0);
+    assert_eq!(to_ui(1_500_000, 6), 150);
+    assert_eq!(to_ui(1_234_567, 6), 123);
+    assert_eq!(to_ui(9_999, 6), 0);
+    assert_eq!(to_ui(1_500_000_000, 9), 150);
+    assert_eq!(to_ui(u64::MAX, 9), u64::MAX / 10_000_000);
+    assert_eq!(to_ui(15, 0), 1_500);
+
+    // Large raw amounts of a mint without decimals saturate, and absurd decimals round everything down to zero
+    assert_eq!(to_ui(u64::MAX / 100, 0), u64::MAX / 100 * 100);
+    assert_eq!(to_ui(u64::MAX / 100 + 1, 0), u64::MAX);
+    assert_eq!(to_ui(u64::MAX, 0), u64::MAX);
+    assert_eq!(to_ui(u64::MAX, u8::MAX), 0);
+}
+
+#[tokio::test]
+async fn test_ui_totals_track_deposit_and_claim() {
+    let (mut context, owner, referrer, referee) = setup().await;
+    let mint = create_mint_with_decimals(&mut context, &owner, 6).await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, token_vault) =
+        create_token_referral_program(&mut context, &owner, mint, REWARD, Some(end_time)).await;
+
+    let depositor_token_account = create_funded_token_account(&mut context, &owner, mint, DEPOSIT).await;
+    let deposit_ix = program_instruction(
+        accounts::DepositToken {
+            referral_program,
+            token_vault,
+            token_mint: mint,
+            depositor_token_account,
+            authority: owner.pubkey(),
+            event_queue: None,
+            token_program: spl_token::id(),
+        },
+        instruction::DepositToken { amount: DEPOSIT },
+    );
+    process(&mut context, &[deposit_ix], &[&owner]).await.unwrap();
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!((program.total_available, program.total_available_ui), (DEPOSIT, 1_000));
+    assert_eq!(program.total_rewards_distributed_ui, 0);
+
+    let participant = join_referral_program(&mut context, &referrer, referral_program).await;
+    join_through_referral(&mut context, &referee, referral_program, participant).await;
+    advance_clock(&mut context, MIN_LOCKED_PERIOD).await;
+    let destination = create_token_account(&mut context, referrer.pubkey(), mint).await;
+    let claim_ix = program_instruction(
+        accounts::ClaimTokenRewards {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant,
+            claim_splitter: None,
+            token_vault,
+            user_token_account: destination,
+            user: referrer.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
+            region_attestation: None,
+            token_program: spl_token::id(),
+        },
+        instruction::ClaimTokenRewards {},
+    );
+    process(&mut context, &[claim_ix], &[&referrer]).await.unwrap();
+
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!((program.total_available, program.total_available_ui), (DEPOSIT - REWARD, 850));
+    assert_eq!((program.total_rewards_distributed, program.total_rewards_distributed_ui), (REWARD, 150));
+}
+
+#[tokio::test]
+async fn test_sol_ui_totals_use_sol_decimals() {
+    let (mut context, owner, _, _) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) =
+        create_sol_referral_program(&mut context, &owner, 500_000_000, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 1_250_000_000).await;
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.total_available_ui, 125);
+}
@@ -0,0 +1,109 @@
+//! Rewards set in US cents on a token program.
+//!
+//! A program paying a USD stable mint can price its reward in cents; it is converted with the mint's decimals
+//! when Alice is credited for referring Bob. A raw reward on the same mint is credited as is, and a mint too
+//! coarse to hold a cent is rejected at creation.
+
+use anchor_client::{
+    anchor_lang::InstructionData,
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer},
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    constants::{REWARD_DENOMINATION_RAW, REWARD_DENOMINATION_USD_CENTS},
+    error::ReferralError,
+    instruction,
+    state::{usd_cents_to_raw, Participant, ReferralProgram},
+};
+
+use crate::banks_util::{
+    assert_referral_error, create_funded_token_account, create_mint_with_decimals, create_referral_program_ix,
+    deposit_token_ix, get_account, initialize_token_vault_ix, join_referral_program, join_through_referral, process,
+    referral_program_pdas, setup,
+};
+
+/// $5.00 per referral
+const REWARD_CENTS: u64 = 500;
+
+/// Builds `owner`'s open-ended program paying in `mint`, with its reward in `reward_denomination`
+fn create_denominated_program_ix(owner: &Keypair, mint: Pubkey, reward_denomination: u8) -> Instruction {
+    let mut create_ix = create_referral_program_ix(owner, Some(mint), REWARD_CENTS, None, false);
+    create_ix.data = instruction::CreateReferralProgram {
+        program_index: 0,
+        token_mint: Some(mint),
+        fixed_reward_amount: REWARD_CENTS,
+        program_end_time: None,
+        reward_denomination,
+        start_inactive: false,
+        terms_hash: [0u8; 32],
+        guardian: None,
+        withdrawal_destinations: Default::default(),
+        settings_locked_until: None,
+        settings: None,
+    }
+    .data();
+    create_ix
+}
+
+/// Creates and funds a program on a 6-decimal mint, then credits Alice for referring Bob; returns the program
+/// and her participant
+async fn credit_one_referral(
+    context: &mut ProgramTestContext,
+    owner: &Keypair,
+    alice: &Keypair,
+    bob: &Keypair,
+    reward_denomination: u8,
+) -> (Pubkey, Pubkey) {
+    let mint = create_mint_with_decimals(context, owner, 6).await;
+    let ixs = [create_denominated_program_ix(owner, mint, reward_denomination), initialize_token_vault_ix(owner, mint)];
+    process(context, &ixs, &[owner]).await.unwrap();
+    let (referral_program, _, token_vault) = referral_program_pdas(owner.pubkey());
+    let deposit = 100 * usd_cents_to_raw(REWARD_CENTS, 6).unwrap();
+    let owner_token_account = create_funded_token_account(context, owner, mint, deposit).await;
+    let deposit_ix = deposit_token_ix(owner, referral_program, token_vault, mint, owner_token_account, deposit);
+    process(context, &[deposit_ix], &[owner]).await.unwrap();
+
+    let participant = join_referral_program(context, alice, referral_program).await;
+    join_through_referral(context, bob, referral_program, participant).await;
+    (referral_program, participant)
+}
+
+#[test]
+fn test_usd_cents_to_raw() {
+    assert_eq!(usd_cents_to_raw(500, 6).unwrap(), 5_000_000);
+    assert_eq!(usd_cents_to_raw(500, 9).unwrap(), 5_000_000_000);
+    assert_eq!(usd_cents_to_raw(500, 2).unwrap(), 500);
+    assert_eq!(usd_cents_to_raw(500, 1).unwrap_err(), ReferralError::InsufficientMintDecimals.into());
+    assert_eq!(usd_cents_to_raw(500, 0).unwrap_err(), ReferralError::InsufficientMintDecimals.into());
+    assert_eq!(usd_cents_to_raw(u64::MAX, 6).unwrap_err(), ReferralError::NumericOverflow.into());
+}
+
+#[tokio::test]
+async fn test_usd_cents_reward_credits_raw_units() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let (referral_program, participant) =
+        credit_one_referral(&mut context, &owner, &alice, &bob, REWARD_DENOMINATION_USD_CENTS).await;
+
+    let program: ReferralProgram = get_account(&mut context, referral_program).await;
+    assert_eq!(program.reward_denomination, REWARD_DENOMINATION_USD_CENTS);
+    assert_eq!((program.token_decimals, program.fixed_reward_amount), (6, REWARD_CENTS));
+    let alice_account: Participant = get_account(&mut context, participant).await;
+    assert_eq!(alice_account.pending_rewards, 5_000_000);
+}
+
+#[tokio::test]
+async fn test_raw_reward_token_program_untouched() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let (_, participant) = credit_one_referral(&mut context, &owner, &alice, &bob, REWARD_DENOMINATION_RAW).await;
+
+    let alice_account: Participant = get_account(&mut context, participant).await;
+    assert_eq!(alice_account.pending_rewards, REWARD_CENTS);
+}
+
+#[tokio::test]
+async fn test_usd_cents_reward_rejects_zero_decimal_mint() {
+    let (mut context, owner, _, _) = setup().await;
+    let mint = create_mint_with_decimals(&mut context, &owner, 0).await;
+    let ix = create_denominated_program_ix(&owner, mint, REWARD_DENOMINATION_USD_CENTS);
+    assert_referral_error(process(&mut context, &[ix], &[&owner]).await, ReferralError::InsufficientMintDecimals);
+}
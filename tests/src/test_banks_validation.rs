@@ -0,0 +1,91 @@
+//! Validation failures that name the offending field.
+//!
+//! Rejected creations and settings updates emit a `ValidationFailure` identifying the field and why it failed,
+//! so a client can point at the input to fix instead of parsing the error.
+
+use anchor_client::{
+    anchor_lang::InstructionData,
+    solana_sdk::{pubkey::Pubkey, signature::Keypair},
+};
+use solana_program_test::ProgramTestContext;
+use solrefer::{
+    constants::{MAX_LOCKED_PERIOD, REWARD_DENOMINATION_RAW},
+    events::{ProgramField, ValidationCode, ValidationFailure},
+    instruction,
+    instructions::ProgramSettings,
+};
+
+use crate::banks_util::{
+    create_funded_user, create_referral_program_ix, create_sol_referral_program, read_program_settings, setup,
+    simulate_events, update_program_settings_ix,
+};
+
+const REWARD: u64 = 1_000_000;
+
+fn assert_failure(events: Vec<ValidationFailure>, field: ProgramField, code: ValidationCode) {
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].field, field as u8);
+    assert_eq!(events[0].code, code as u8);
+}
+
+/// Simulates `owner` updating the program's settings and returns the validation failures it reported
+async fn simulate_update(
+    context: &mut ProgramTestContext,
+    owner: &Keypair,
+    referral_program: Pubkey,
+    new_settings: ProgramSettings,
+) -> Vec<ValidationFailure> {
+    let ix = update_program_settings_ix(context, owner, referral_program, new_settings).await;
+    simulate_events(context, &[ix], &[owner]).await
+}
+
+#[tokio::test]
+async fn test_validation_failures_identify_field() {
+    let (mut context, owner, _, _) = setup().await;
+
+    // A zero reward is too low
+    let new_owner = create_funded_user(&mut context).await;
+    let mut create_ix = create_referral_program_ix(&new_owner, None, 0, None, false);
+    create_ix.data = instruction::CreateReferralProgram {
+        program_index: 0,
+        token_mint: None,
+        fixed_reward_amount: 0,
+        program_end_time: None,
+        reward_denomination: REWARD_DENOMINATION_RAW,
+        start_inactive: false,
+        terms_hash: [0u8; 32],
+        guardian: None,
+        withdrawal_destinations: Default::default(),
+        settings_locked_until: None,
+        settings: None,
+    }
+    .data();
+    let events = simulate_events(&mut context, &[create_ix], &[&new_owner]).await;
+    assert_failure(events, ProgramField::FixedRewardAmount, ValidationCode::TooLow);
+
+    let (referral_program, _) = create_sol_referral_program(&mut context, &owner, REWARD, None).await;
+    let valid_settings = ProgramSettings {
+        locked_period: 86400,
+        base_reward: REWARD,
+        max_reward_cap: 1_000 * REWARD,
+        ..read_program_settings(&mut context, referral_program).await
+    };
+
+    // A cap below the fixed reward conflicts with it
+    let settings = ProgramSettings { max_reward_cap: 1_000, ..valid_settings.clone() };
+    let events = simulate_update(&mut context, &owner, referral_program, settings).await;
+    assert_failure(events, ProgramField::MaxRewardCap, ValidationCode::Relationship);
+
+    // A locked period past the maximum is too high
+    let settings = ProgramSettings { locked_period: MAX_LOCKED_PERIOD + 1, ..valid_settings.clone() };
+    let events = simulate_update(&mut context, &owner, referral_program, settings).await;
+    assert_failure(events, ProgramField::LockedPeriod, ValidationCode::TooHigh);
+
+    // A per-window cap without a window cannot be enforced
+    let settings = ProgramSettings { max_referrals_per_window: 2, ..valid_settings.clone() };
+    let events = simulate_update(&mut context, &owner, referral_program, settings).await;
+    assert_failure(events, ProgramField::ReferralRateLimit, ValidationCode::Relationship);
+
+    // Valid settings report nothing
+    assert!(simulate_update(&mut context, &owner, referral_program, valid_settings).await.is_empty());
+}
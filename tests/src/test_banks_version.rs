@@ -0,0 +1,44 @@
+use solrefer::{
+    accounts,
+    constants::{FEATURE_REFEREE_BOOSTS, FEATURE_TEST_UTILS, FEATURE_TOKEN_PROGRAMS, SUPPORTED_FEATURES},
+    instruction,
+    instructions::{semver_part, ProgramVersion},
+    state::Participant,
+};
+
+use crate::banks_util::{program_instruction, setup, simulate_return};
+
+/// The program crate's version, read from its manifest
+fn manifest_version() -> (u16, u16, u16) {
+    let manifest = include_str!("../../programs/solrefer/Cargo.toml");
+    let version = manifest
+        .lines()
+        .find_map(|line| line.strip_prefix("version = \""))
+        .and_then(|rest| rest.strip_suffix('"'))
+        .expect("Manifest has no version");
+    let mut parts = version.split('.').map(|part| part.parse().expect("Version part is not a number"));
+    (parts.next().unwrap(), parts.next().unwrap(), parts.next().unwrap())
+}
+
+#[test]
+fn test_semver_part() {
+    assert_eq!(semver_part("1.22.333", 0), 1);
+    assert_eq!(semver_part("1.22.333", 1), 22);
+    assert_eq!(semver_part("1.22.333", 2), 333);
+    assert_eq!(semver_part("2.0.1-beta.3+build.7", 2), 1);
+}
+
+#[tokio::test]
+async fn test_get_program_version() {
+    let (mut context, _, _, _) = setup().await;
+    let ix = program_instruction(accounts::GetProgramVersion {}, instruction::GetProgramVersion);
+    let version: ProgramVersion = simulate_return(&mut context, ix).await;
+
+    assert_eq!((version.major, version.minor, version.patch), manifest_version());
+    assert_eq!(version.layouts.participant, Participant::LAYOUT_VERSION);
+    assert_eq!(version.features, SUPPORTED_FEATURES);
+    assert_eq!(version.features & FEATURE_TOKEN_PROGRAMS, FEATURE_TOKEN_PROGRAMS);
+    assert_eq!(version.features & FEATURE_REFEREE_BOOSTS, FEATURE_REFEREE_BOOSTS);
+    // The tests build the program with its test-only instructions
+    assert_eq!(version.features & FEATURE_TEST_UTILS, FEATURE_TEST_UTILS);
+}
@@ -0,0 +1,89 @@
+//! Claiming SOL rewards as wrapped SOL.
+//!
+//! Alice refers Bob and claims her reward into her wrapped SOL account, which the claim creates. Pointing the
+//! claim at a token account of another mint is rejected.
+
+use anchor_client::{
+    anchor_lang::{error::ErrorCode, system_program},
+    solana_sdk::{
+        instruction::{Instruction, InstructionError},
+        pubkey::Pubkey,
+        signature::Keypair,
+        signer::Signer,
+        transaction::TransactionError,
+    },
+};
+use anchor_spl::{
+    associated_token::{self, get_associated_token_address},
+    token::{spl_token, spl_token::native_mint, TokenAccount},
+};
+use solrefer::{accounts, constants::MIN_LOCKED_PERIOD, instruction, state::Participant};
+
+use crate::{
+    banks_util::{
+        advance_clock, create_mint, create_sol_referral_program, create_token_account, deposit_sol, get_account,
+        get_balance, get_clock_time, join_referral_program, join_through_referral, process, program_instruction, setup,
+    },
+    test_util::{get_eligibility_criteria_pda, get_participant_pda},
+};
+
+const REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+#[tokio::test]
+async fn test_claim_rewards_as_wrapped_sol() {
+    let (mut context, owner, alice, bob) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) = create_sol_referral_program(&mut context, &owner, REWARD, Some(end_time)).await;
+    deposit_sol(&mut context, &owner, referral_program, vault, 10 * REWARD).await;
+    let alice_participant = join_referral_program(&mut context, &alice, referral_program).await;
+    join_through_referral(&mut context, &bob, referral_program, alice_participant).await;
+    advance_clock(&mut context, MIN_LOCKED_PERIOD).await;
+
+    // A token account of any other mint cannot receive the claim
+    let mint = create_mint(&mut context, &owner).await;
+    let other_account = create_token_account(&mut context, alice.pubkey(), mint).await;
+    let result =
+        process(&mut context, &[claim_wrapped_ix(&alice, referral_program, vault, other_account)], &[&alice]).await;
+    let err = result.expect_err("Claim into another mint's account unexpectedly succeeded").unwrap();
+    let mint_mismatch = InstructionError::Custom(ErrorCode::ConstraintTokenMint.into());
+    assert_eq!(err, TransactionError::InstructionError(0, mint_mismatch));
+
+    // The claim creates Alice's wrapped SOL account and credits it with exactly the reward
+    let wsol_account = get_associated_token_address(&alice.pubkey(), &native_mint::ID);
+    let vault_before = get_balance(&mut context, vault).await;
+    process(&mut context, &[claim_wrapped_ix(&alice, referral_program, vault, wsol_account)], &[&alice]).await.unwrap();
+    let token_account: TokenAccount = get_account(&mut context, wsol_account).await;
+    assert_eq!((token_account.mint, token_account.owner), (native_mint::ID, alice.pubkey()));
+    assert_eq!(token_account.amount, REWARD);
+    assert!(token_account.is_native());
+    assert_eq!(get_balance(&mut context, vault).await, vault_before - REWARD);
+    let rent = get_balance(&mut context, wsol_account).await - REWARD;
+    assert_eq!(Option::from(token_account.is_native), Some(rent));
+
+    // The claim is booked like any other
+    let participant: Participant = get_account(&mut context, alice_participant).await;
+    assert_eq!((participant.pending_rewards, participant.total_rewards), (0, REWARD));
+}
+
+fn claim_wrapped_ix(user: &Keypair, referral_program: Pubkey, vault: Pubkey, user_wsol_account: Pubkey) -> Instruction {
+    program_instruction(
+        accounts::ClaimRewardsWrapped {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+            participant: get_participant_pda(referral_program, user.pubkey(), solrefer::ID),
+            claim_splitter: None,
+            vault,
+            native_mint: native_mint::ID,
+            user_wsol_account,
+            user: user.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
+            region_attestation: None,
+            token_program: spl_token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        },
+        instruction::ClaimRewardsWrapped {},
+    )
+}
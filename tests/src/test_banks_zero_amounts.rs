@@ -0,0 +1,197 @@
+use anchor_client::{
+    anchor_lang::system_program,
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction, InstructionError},
+        native_token::LAMPORTS_PER_SOL,
+        pubkey::Pubkey,
+        signature::Keypair,
+        signer::Signer,
+        transaction::TransactionError,
+    },
+};
+use anchor_spl::token::spl_token;
+use solrefer::{
+    accounts,
+    constants::{CONTEST_ESCROW_SEED, CONTEST_SEED, MAX_CONTEST_PRIZES},
+    error::ReferralError,
+    instruction,
+    instructions::PurchaseEntry,
+};
+
+use crate::{
+    banks_util::{
+        create_funded_token_account, create_funded_user, create_mint, create_sol_referral_program,
+        create_token_referral_program, deposit_sol, get_clock_time, join_referral_program, join_through_referral,
+        process, program_instruction, setup,
+    },
+    test_util::get_eligibility_criteria_pda,
+};
+
+const REFERRAL_REWARD: u64 = 1_000_000;
+const ONE_YEAR: i64 = 365 * 86400;
+
+/// Every fund-moving instruction with the error it rejects a zero amount with
+type Case<'a> = (&'static str, &'a Keypair, Box<dyn Fn(u64) -> Instruction>, ReferralError);
+
+#[tokio::test]
+async fn test_fund_moving_instructions_reject_zero_amounts() {
+    let (mut context, owner, referrer, buyer) = setup().await;
+    let end_time = get_clock_time(&mut context).await + ONE_YEAR;
+    let (referral_program, vault) =
+        create_sol_referral_program(&mut context, &owner, REFERRAL_REWARD, Some(end_time)).await;
+    // A lamport is below the rent-exempt minimum of an empty account, so the vault is seeded first; that also
+    // funds the referral
+    deposit_sol(&mut context, &owner, referral_program, vault, LAMPORTS_PER_SOL).await;
+    let referrer_participant = join_referral_program(&mut context, &referrer, referral_program).await;
+    let buyer_participant = join_through_referral(&mut context, &buyer, referral_program, referrer_participant).await;
+
+    let (contest, _) = Pubkey::find_program_address(&[CONTEST_SEED, referral_program.as_ref()], &solrefer::ID);
+    let (escrow, _) = Pubkey::find_program_address(&[CONTEST_ESCROW_SEED, referral_program.as_ref()], &solrefer::ID);
+    let mut prizes = [0u64; MAX_CONTEST_PRIZES];
+    prizes[0] = LAMPORTS_PER_SOL / 10;
+    let configure_ix = program_instruction(
+        accounts::ConfigureContest {
+            referral_program,
+            contest,
+            authority: owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::ConfigureContest { prizes, dispute_window: 0 },
+    );
+    process(&mut context, &[configure_ix], &[&owner]).await.expect("Failed to configure contest");
+
+    let token_owner = create_funded_user(&mut context).await;
+    let mint = create_mint(&mut context, &token_owner).await;
+    let depositor_token_account = create_funded_token_account(&mut context, &token_owner, mint, 10).await;
+    let (token_program, token_vault) =
+        create_token_referral_program(&mut context, &token_owner, mint, REFERRAL_REWARD, Some(end_time)).await;
+
+    let owner_key = owner.pubkey();
+    let token_owner_key = token_owner.pubkey();
+    let fund_contest = move |amount| {
+        program_instruction(
+            accounts::FundContest {
+                referral_program,
+                contest,
+                escrow,
+                authority: owner_key,
+                system_program: system_program::ID,
+            },
+            instruction::FundContest { amount },
+        )
+    };
+
+    // The escrow is seeded for the same reason
+    process(&mut context, &[fund_contest(prizes[0])], &[&owner]).await.expect("Failed to fund contest");
+
+    let cases: Vec<Case> = vec![
+        (
+            "deposit_sol",
+            &owner,
+            Box::new(move |amount| {
+                program_instruction(
+                    accounts::DepositSol {
+                        referral_program,
+                        vault,
+                        authority: owner_key,
+                        event_queue: None,
+                        system_program: system_program::ID,
+                    },
+                    instruction::DepositSol { amount },
+                )
+            }),
+            ReferralError::InsufficientDeposit,
+        ),
+        (
+            "deposit_token",
+            &token_owner,
+            Box::new(move |amount| {
+                program_instruction(
+                    accounts::DepositToken {
+                        referral_program: token_program,
+                        token_vault,
+                        token_mint: mint,
+                        depositor_token_account,
+                        authority: token_owner_key,
+                        event_queue: None,
+                        token_program: spl_token::id(),
+                    },
+                    instruction::DepositToken { amount },
+                )
+            }),
+            ReferralError::InsufficientDeposit,
+        ),
+        ("fund_contest", &owner, Box::new(fund_contest), ReferralError::InsufficientDeposit),
+        (
+            "record_purchase",
+            &owner,
+            Box::new(move |amount| {
+                program_instruction(
+                    accounts::RecordPurchase {
+                        referral_program,
+                        eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+                        buyer: buyer_participant,
+                        referrer: referrer_participant,
+                        referrer_upline: None,
+                        authority: owner_key,
+                    },
+                    instruction::RecordPurchase { amount, idempotency_key: None },
+                )
+            }),
+            ReferralError::InvalidPurchaseAmount,
+        ),
+        (
+            "record_purchases_batch",
+            &owner,
+            Box::new(move |amount| {
+                let mut ix = program_instruction(
+                    accounts::RecordPurchasesBatch {
+                        referral_program,
+                        eligibility_criteria: get_eligibility_criteria_pda(referral_program, solrefer::ID),
+                        vault,
+                        authority: owner_key,
+                        system_program: system_program::ID,
+                    },
+                    instruction::RecordPurchasesBatch {
+                        purchases: vec![PurchaseEntry { amount, buyer_index: 0 }],
+                        deposit: amount,
+                    },
+                );
+                ix.accounts.push(AccountMeta::new_readonly(buyer_participant, false));
+                ix.accounts.push(AccountMeta::new(referrer_participant, false));
+                ix
+            }),
+            ReferralError::InvalidPurchaseAmount,
+        ),
+        (
+            "withdraw_funds",
+            &owner,
+            Box::new(move |amount| {
+                program_instruction(
+                    accounts::WithdrawFunds {
+                        referral_program,
+                        vault,
+                        token_vault: None,
+                        destination_token_account: None,
+                        destination: None,
+                        authority: owner_key,
+                        system_program: system_program::ID,
+                        token_program: None,
+                    },
+                    instruction::WithdrawFunds { amount },
+                )
+            }),
+            ReferralError::InvalidWithdrawalAmount,
+        ),
+    ];
+
+    for (name, signer, build, error) in cases {
+        let err = process(&mut context, &[build(0)], &[signer])
+            .await
+            .expect_err(&format!("{name} accepted a zero amount"))
+            .unwrap();
+        assert_eq!(err, TransactionError::InstructionError(0, InstructionError::Custom(u32::from(error))), "{name}");
+
+        process(&mut context, &[build(1)], &[signer]).await.unwrap_or_else(|err| panic!("{name} rejected 1: {err}"));
+    }
+}
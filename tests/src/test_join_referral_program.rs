@@ -1,10 +1,21 @@
 use anchor_client::solana_sdk::{
     pubkey::Pubkey, signature::Keypair, signer::Signer, system_instruction, system_program,
 };
-use solrefer::state::Participant;
-use std::{i64, str};
+use solrefer::{
+    constants::LINK_FORMAT_SLUG,
+    instructions::ProgramSettings,
+    state::{referral_url, Participant, RefereeReceipt, ReferralProgram},
+};
+use std::str;
+
+use crate::test_util::{
+    create_funded_user, create_sol_referral_program, far_future_end_time, get_eligibility_criteria_pda,
+    get_participant_pda, get_referee_receipt_pda, join_referral_program, join_through_referral, setup,
+    simulate_units_consumed, update_program_settings,
+};
 
-use crate::test_util::{create_sol_referral_program, setup};
+/// Compute budget for either join instruction; raising it should be a deliberate decision
+const JOIN_CU_BUDGET: u64 = 30_000;
 
 #[test]
 fn test_join_referral_program_sucesss() {
@@ -16,7 +27,7 @@ fn test_join_referral_program_sucesss() {
         &client,
         program_id,
         1_000_000, // 1 SOL max reward cap
-        i64::MAX,  // No end time
+        far_future_end_time(),
     );
 
     // Calculate PDA for participant account
@@ -31,12 +42,21 @@ fn test_join_referral_program_sucesss() {
         .request()
         .accounts(solrefer::accounts::JoinReferralProgram {
             referral_program: referral_program_pubkey,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program_pubkey, program_id),
             participant: participant_pubkey,
+            invite: None,
+            collection_metadata: None,
+            collection_nft: None,
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account: None,
+            bridge_participant: None,
+            bridge_referrer: None,
             user: alice.pubkey(),
+            event_queue: None,
             system_program: system_program::ID,
-            rent: anchor_client::solana_sdk::sysvar::rent::ID,
         })
-        .args(solrefer::instruction::JoinReferralProgram {})
+        .args(solrefer::instruction::JoinReferralProgram { accepted_terms_hash: [0u8; 32] })
         .signer(&alice)
         .send()
         .unwrap();
@@ -49,9 +69,12 @@ fn test_join_referral_program_sucesss() {
     assert_eq!(participant_account.total_rewards, 0);
     assert_eq!(participant_account.referrer, None);
 
-    // Convert bytes to string, trimming null bytes
-    let referral_link = str::from_utf8(&participant_account.referral_link).unwrap().trim_matches(char::from(0));
-    assert_eq!(referral_link, format!("https://solrefer.io/ref/{}", alice.pubkey()));
+    // New programs store only the slug, and the URL is composed from it off-chain
+    let referral_program: ReferralProgram = program.account(referral_program_pubkey).unwrap();
+    assert_eq!(participant_account.link_format, LINK_FORMAT_SLUG);
+    assert!(participant_account.referral_link.iter().all(|byte| *byte == 0));
+    let url = referral_url(&referral_program, &participant_account);
+    assert_eq!(url, format!("https://solrefer.io/ref/{}", alice.pubkey()));
 }
 
 #[test]
@@ -64,7 +87,7 @@ fn test_join_through_referral_success() {
         &client,
         program_id,
         1_000_000, // 1 SOL max reward cap
-        i64::MAX,
+        far_future_end_time(),
     );
 
     // Calculate PDA for referrer's participant account
@@ -79,12 +102,21 @@ fn test_join_through_referral_success() {
         .request()
         .accounts(solrefer::accounts::JoinReferralProgram {
             referral_program: referral_program_pubkey,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program_pubkey, program_id),
             participant: referrer_participant_pubkey,
+            invite: None,
+            collection_metadata: None,
+            collection_nft: None,
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account: None,
+            bridge_participant: None,
+            bridge_referrer: None,
             user: alice.pubkey(),
+            event_queue: None,
             system_program: system_program::ID,
-            rent: anchor_client::solana_sdk::sysvar::rent::ID,
         })
-        .args(solrefer::instruction::JoinReferralProgram {})
+        .args(solrefer::instruction::JoinReferralProgram { accepted_terms_hash: [0u8; 32] })
         .signer(&alice)
         .send()
         .unwrap();
@@ -100,13 +132,34 @@ fn test_join_through_referral_success() {
         .request()
         .accounts(solrefer::accounts::JoinThroughReferral {
             referral_program: referral_program_pubkey,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program_pubkey, program_id),
             participant: participant_pubkey,
             referrer: referrer_participant_pubkey,
+            rotated_referrer: None,
+            split_recipient: None,
+            boost_escrow: None,
+            match_offer: None,
+            referrer_wallet: None,
+            referee_receipt: get_referee_receipt_pda(referral_program_pubkey, bob.pubkey(), program_id),
+            sponsor_vault: None,
+            invite: None,
+            referrer_collection_metadata: None,
+            referrer_collection_nft: None,
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account: None,
+            referee_token_account: None,
+            referrer_upline: None,
             user: bob.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
             system_program: system_program::ID,
-            rent: anchor_client::solana_sdk::sysvar::rent::ID,
         })
-        .args(solrefer::instruction::JoinThroughReferral {})
+        .args(solrefer::instruction::JoinThroughReferral {
+            source_tag: None,
+            accepted_terms_hash: [0u8; 32],
+            link_proof: None,
+        })
         .signer(&bob)
         .send()
         .unwrap();
@@ -119,13 +172,25 @@ fn test_join_through_referral_success() {
     assert_eq!(participant_account.total_rewards, 0);
     assert_eq!(participant_account.referrer, Some(referrer_participant_pubkey));
 
-    // Convert bytes to string, trimming null bytes
-    let referral_link = str::from_utf8(&participant_account.referral_link).unwrap().trim_matches(char::from(0));
-    assert_eq!(referral_link, format!("https://solrefer.io/ref/{}", bob.pubkey()));
+    // New programs store only the slug, and the URL is composed from it off-chain
+    let referral_program: ReferralProgram = program.account(referral_program_pubkey).unwrap();
+    assert_eq!(participant_account.link_format, LINK_FORMAT_SLUG);
+    assert!(participant_account.referral_link.iter().all(|byte| *byte == 0));
+    let url = referral_url(&referral_program, &participant_account);
+    assert_eq!(url, format!("https://solrefer.io/ref/{}", bob.pubkey()));
 
     // Verify Alice's stats were updated
     let referrer_account: Participant = program.account(referrer_participant_pubkey).unwrap();
     assert_eq!(referrer_account.total_referrals, 1);
+    assert_eq!(referrer_account.pending_rewards, 1_000_000);
+
+    // Verify Bob's referee receipt names Alice as the credited referrer
+    let receipt: RefereeReceipt =
+        program.account(get_referee_receipt_pda(referral_program_pubkey, bob.pubkey(), program_id)).unwrap();
+    assert_eq!(receipt.program, referral_program_pubkey);
+    assert_eq!(receipt.referee, bob.pubkey());
+    assert_eq!(receipt.referrer, referrer_participant_pubkey);
+    assert!(receipt.credited_at > 0);
 }
 
 #[test]
@@ -134,7 +199,8 @@ fn test_join_through_invalid_referral() {
     let (owner, _, bob, program_id, client) = setup();
 
     // Create a SOL referral program
-    let (referral_program_pubkey, _) = create_sol_referral_program(&owner, &client, program_id, 1_000_000, i64::MAX);
+    let (referral_program_pubkey, _) =
+        create_sol_referral_program(&owner, &client, program_id, 1_000_000, far_future_end_time());
 
     // Create a keypair for the invalid account
     let invalid_account = Keypair::new();
@@ -166,16 +232,215 @@ fn test_join_through_invalid_referral() {
         .request()
         .accounts(solrefer::accounts::JoinThroughReferral {
             referral_program: referral_program_pubkey,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program_pubkey, program_id),
             participant: participant_pubkey,
             referrer: invalid_account.pubkey(),
+            rotated_referrer: None,
+            split_recipient: None,
+            boost_escrow: None,
+            match_offer: None,
+            referrer_wallet: None,
+            referee_receipt: get_referee_receipt_pda(referral_program_pubkey, bob.pubkey(), program_id),
+            sponsor_vault: None,
+            invite: None,
+            referrer_collection_metadata: None,
+            referrer_collection_nft: None,
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account: None,
+            referee_token_account: None,
+            referrer_upline: None,
             user: bob.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
             system_program: system_program::ID,
-            rent: anchor_client::solana_sdk::sysvar::rent::ID,
         })
-        .args(solrefer::instruction::JoinThroughReferral {})
+        .args(solrefer::instruction::JoinThroughReferral {
+            source_tag: None,
+            accepted_terms_hash: [0u8; 32],
+            link_proof: None,
+        })
         .signer(&bob)
         .send()
         .unwrap_err();
 
     assert!(err.to_string().contains("InvalidReferrer"));
 }
+
+#[test]
+fn test_referral_depth_and_max_depth() {
+    let (owner, alice, bob, program_id, client) = setup();
+    let carol = create_funded_user();
+    let dave = create_funded_user();
+
+    let (referral_program_pubkey, _) =
+        create_sol_referral_program(&owner, &client, program_id, 1_000_000, far_future_end_time());
+
+    // Build a 3-deep chain: alice -> bob -> carol
+    let alice_participant = join_referral_program(&alice, referral_program_pubkey, &client, program_id);
+    let bob_participant = join_through_referral(&bob, referral_program_pubkey, alice_participant, &client, program_id);
+    let carol_participant =
+        join_through_referral(&carol, referral_program_pubkey, bob_participant, &client, program_id);
+
+    let program = client.program(program_id).unwrap();
+    let depths: Vec<u16> = [alice_participant, bob_participant, carol_participant]
+        .iter()
+        .map(|pubkey| program.account::<Participant>(*pubkey).unwrap().referral_depth)
+        .collect();
+    assert_eq!(depths, vec![0, 1, 2]);
+    let referral_program: ReferralProgram = program.account(referral_program_pubkey).unwrap();
+    assert_eq!(referral_program.max_observed_depth, 2);
+
+    update_program_settings(
+        &owner,
+        referral_program_pubkey,
+        ProgramSettings {
+            fixed_reward_amount: 1_000_000,
+            locked_period: 86400,
+            program_end_time: Some(far_future_end_time()),
+            base_reward: 1_000_000,
+            max_reward_cap: 1_000_000_000,
+            max_depth: 2,
+            milestones: Default::default(),
+            invite_only: false,
+            revenue_share_percent: 0,
+            referee_reward_amount: 0,
+            referee_rewards_locked: false,
+            reserve_bps: 0,
+            max_referrals_per_window: 0,
+            referral_window_seconds: 0,
+            rate_limit_strict: false,
+            required_collection: None,
+            collection_gates_credits: false,
+            transfers_enabled: false,
+            dispute_window_seconds: 0,
+            rent_payer_mode: 0,
+            min_joiner_balance: 0,
+            min_account_age_seconds: 0,
+            alert_thresholds: [0; 3],
+            referrer_requirement: None,
+            referee_requirement: None,
+            direct_claims_only: false,
+            trailing_commission_bps: 0,
+            link_signer: None,
+            dormancy_period_seconds: 0,
+            region_attestor: None,
+            embargoed_regions: [0; 8],
+            region_checked_on_claim: false,
+            bridge_source_program: None,
+            bridge_bps: 0,
+            cleanup_bounty_bps: 0,
+            event_mode: 0,
+            attribution_grace_seconds: 0,
+            tier1_reward: 0,
+            tier2_reward: 0,
+            auto_downgrade: false,
+            min_remaining_credits: 0,
+            seed_reclaim_seconds: 0,
+            early_redemption_fee: 0,
+        },
+        &client,
+        program_id,
+    );
+
+    // Dave would sit at depth 3: the join succeeds but carol earns nothing
+    let dave_participant =
+        join_through_referral(&dave, referral_program_pubkey, carol_participant, &client, program_id);
+    let dave_account: Participant = program.account(dave_participant).unwrap();
+    assert_eq!(dave_account.referral_depth, 3);
+    assert_eq!(dave_account.referrer, Some(carol_participant));
+
+    let carol_account: Participant = program.account(carol_participant).unwrap();
+    assert_eq!(carol_account.total_referrals, 0);
+    assert_eq!(carol_account.pending_rewards, 0);
+    let referral_program: ReferralProgram = program.account(referral_program_pubkey).unwrap();
+    assert_eq!(referral_program.max_observed_depth, 3);
+    assert_eq!(referral_program.total_committed, 2_000_000);
+}
+
+#[test]
+fn test_referral_link_matches_display() {
+    let mut keys = vec![Pubkey::default(), Pubkey::new_from_array([0xff; 32])];
+    let mut leading_zeros = [7u8; 32];
+    leading_zeros[..3].fill(0);
+    keys.push(Pubkey::new_from_array(leading_zeros));
+    keys.extend((0..64).map(|_| Pubkey::new_unique()));
+    keys.extend((0..64).map(|_| Keypair::new().pubkey()));
+
+    for key in keys {
+        let (link, len) = Participant::referral_link_for(&key);
+        assert_eq!(str::from_utf8(&link[..len]).unwrap(), format!("https://solrefer.io/ref/{}", key));
+        assert!(link[len..].iter().all(|byte| *byte == 0));
+    }
+}
+
+#[test]
+fn test_join_compute_budget() {
+    let (owner, alice, bob, program_id, client) = setup();
+
+    let (referral_program_pubkey, _) =
+        create_sol_referral_program(&owner, &client, program_id, 1_000_000, far_future_end_time());
+    let program = client.program(program_id).unwrap();
+
+    let direct_join = program
+        .request()
+        .accounts(solrefer::accounts::JoinReferralProgram {
+            referral_program: referral_program_pubkey,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program_pubkey, program_id),
+            participant: get_participant_pda(referral_program_pubkey, alice.pubkey(), program_id),
+            invite: None,
+            collection_metadata: None,
+            collection_nft: None,
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account: None,
+            bridge_participant: None,
+            bridge_referrer: None,
+            user: alice.pubkey(),
+            event_queue: None,
+            system_program: system_program::ID,
+        })
+        .args(solrefer::instruction::JoinReferralProgram { accepted_terms_hash: [0u8; 32] })
+        .instructions()
+        .unwrap();
+    let units = simulate_units_consumed(&direct_join, &alice, &client, program_id);
+    assert!(units <= JOIN_CU_BUDGET, "join_referral_program used {} CU, budget is {}", units, JOIN_CU_BUDGET);
+
+    let alice_participant = join_referral_program(&alice, referral_program_pubkey, &client, program_id);
+    let referred_join = program
+        .request()
+        .accounts(solrefer::accounts::JoinThroughReferral {
+            referral_program: referral_program_pubkey,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program_pubkey, program_id),
+            participant: get_participant_pda(referral_program_pubkey, bob.pubkey(), program_id),
+            referrer: alice_participant,
+            rotated_referrer: None,
+            split_recipient: None,
+            boost_escrow: None,
+            match_offer: None,
+            referrer_wallet: None,
+            referee_receipt: get_referee_receipt_pda(referral_program_pubkey, bob.pubkey(), program_id),
+            sponsor_vault: None,
+            invite: None,
+            referrer_collection_metadata: None,
+            referrer_collection_nft: None,
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account: None,
+            referee_token_account: None,
+            referrer_upline: None,
+            user: bob.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
+            system_program: system_program::ID,
+        })
+        .args(solrefer::instruction::JoinThroughReferral {
+            source_tag: None,
+            accepted_terms_hash: [0u8; 32],
+            link_proof: None,
+        })
+        .instructions()
+        .unwrap();
+    let units = simulate_units_consumed(&referred_join, &bob, &client, program_id);
+    assert!(units <= JOIN_CU_BUDGET, "join_through_referral used {} CU, budget is {}", units, JOIN_CU_BUDGET);
+}
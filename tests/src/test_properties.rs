@@ -0,0 +1,305 @@
+use anchor_client::anchor_lang::Result;
+use proptest::{prelude::*, sample::Index};
+use solrefer::{
+    constants::{MAX_FEE_PERCENTAGE, MAX_MILESTONES, MAX_RESERVE_BPS, MIN_LOCKED_PERIOD},
+    error::ReferralError,
+    instructions::{
+        claim_eligibility, settle_claim, validate_program_settings, ClaimAccounts, PayoutKind, ProgramSettings,
+    },
+    state::{newly_reached_milestones, EligibilityCriteria, Milestone, NetworkLimits, Participant, ReferralProgram},
+};
+
+/// One instruction applied to the model
+#[derive(Clone, Debug)]
+enum Op {
+    Deposit(u64),
+    Join { referrer: Index },
+    Purchase { buyer: Index, amount: u64 },
+    Claim(Index),
+    Advance(i64),
+}
+
+/// In-memory model of a SOL referral program.
+///
+/// Each step mirrors the handler of its instruction on top of the same pure functions, and a rejected step
+/// leaves the model untouched just as a failed transaction leaves the chain untouched.
+#[derive(Clone)]
+struct Model {
+    program: ReferralProgram,
+    criteria: EligibilityCriteria,
+    participants: Vec<Participant>,
+    /// Index of each participant's referrer; the first participant joined directly
+    referrers: Vec<Option<usize>>,
+    vault_balance: u64,
+    deposited: u128,
+    now: i64,
+}
+
+impl Model {
+    fn new(settings: &ProgramSettings) -> Self {
+        let program = ReferralProgram {
+            fixed_reward_amount: settings.fixed_reward_amount,
+            locked_period: settings.locked_period,
+            is_active: true,
+            referee_reward_amount: settings.referee_reward_amount,
+            referee_rewards_locked: settings.referee_rewards_locked,
+            program_end_time: settings.program_end_time,
+            reserve_bps: settings.reserve_bps,
+            ..Default::default()
+        };
+        let criteria = EligibilityCriteria {
+            base_reward: settings.base_reward,
+            max_reward_cap: settings.max_reward_cap,
+            revenue_share_percent: settings.revenue_share_percent,
+            program_end_time: settings.program_end_time,
+            milestones: settings.milestones,
+            ..Default::default()
+        };
+        Self {
+            program,
+            criteria,
+            participants: vec![Participant::default()],
+            referrers: vec![None],
+            vault_balance: 0,
+            deposited: 0,
+            now: 0,
+        }
+    }
+
+    /// Applies `op`, rolling the model back if it is rejected
+    fn apply(&mut self, op: &Op) -> Result<()> {
+        let before = self.clone();
+        let result = match op {
+            Op::Deposit(amount) => self.deposit(*amount),
+            Op::Join { referrer } => self.join(referrer.index(self.participants.len())),
+            Op::Purchase { buyer, amount } => self.purchase(buyer.index(self.participants.len()), *amount),
+            Op::Claim(participant) => self.claim(participant.index(self.participants.len())),
+            Op::Advance(seconds) => {
+                self.now += seconds;
+                Ok(())
+            }
+        };
+        if result.is_err() {
+            *self = before;
+        }
+        result
+    }
+
+    fn deposit(&mut self, amount: u64) -> Result<()> {
+        self.program.credit_deposit(amount)?;
+        self.vault_balance = self.vault_balance.checked_add(amount).ok_or(ReferralError::NumericOverflow)?;
+        self.deposited += u128::from(amount);
+        Ok(())
+    }
+
+    fn join(&mut self, referrer_index: usize) -> Result<()> {
+        let program = &mut self.program;
+        if program.has_ended(self.now) {
+            return Err(ReferralError::ProgramEnded.into());
+        }
+
+        let reward_amount = program.referral_reward_amount()?;
+        let referrer = &mut self.participants[referrer_index];
+        referrer.total_referrals = referrer.total_referrals.checked_add(1).ok_or(ReferralError::NumericOverflow)?;
+        referrer.pending_rewards =
+            referrer.pending_rewards.checked_add(reward_amount).ok_or(ReferralError::NumericOverflow)?;
+        program.total_committed =
+            program.total_committed.checked_add(reward_amount).ok_or(ReferralError::NumericOverflow)?;
+
+        let milestones = self.criteria.milestones;
+        for index in newly_reached_milestones(&milestones, referrer.total_referrals, referrer.milestones_claimed_bitmap)
+        {
+            let bonus = milestones[index].bonus;
+            if bonus > program.total_available.saturating_sub(program.total_committed) {
+                continue;
+            }
+            referrer.pending_rewards =
+                referrer.pending_rewards.checked_add(bonus).ok_or(ReferralError::NumericOverflow)?;
+            referrer.milestones_claimed_bitmap |= 1 << index;
+            program.total_committed =
+                program.total_committed.checked_add(bonus).ok_or(ReferralError::NumericOverflow)?;
+        }
+
+        let referee_reward = program.referee_reward_amount;
+        program.total_committed =
+            program.total_committed.checked_add(referee_reward).ok_or(ReferralError::NumericOverflow)?;
+        self.participants.push(Participant {
+            join_time: self.now,
+            pending_rewards: referee_reward,
+            ..Default::default()
+        });
+        self.referrers.push(Some(referrer_index));
+        Ok(())
+    }
+
+    fn purchase(&mut self, buyer: usize, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Err(ReferralError::InvalidPurchaseAmount.into());
+        }
+        let referrer_index = self.referrers[buyer].ok_or(ReferralError::InvalidReferrer)?;
+        let program = &mut self.program;
+        if program.has_ended(self.now) {
+            return Err(ReferralError::ProgramEnded.into());
+        }
+
+        let referrer = &mut self.participants[referrer_index];
+        let earned =
+            referrer.total_rewards.checked_add(referrer.pending_rewards).ok_or(ReferralError::NumericOverflow)?;
+        let headroom = program.total_available.saturating_sub(program.total_committed);
+        let reward = self.criteria.purchase_reward(amount, earned)?.min(headroom);
+
+        referrer.pending_rewards =
+            referrer.pending_rewards.checked_add(reward).ok_or(ReferralError::NumericOverflow)?;
+        referrer.total_attributed_volume =
+            referrer.total_attributed_volume.checked_add(amount).ok_or(ReferralError::NumericOverflow)?;
+        program.total_committed = program.total_committed.checked_add(reward).ok_or(ReferralError::NumericOverflow)?;
+        Ok(())
+    }
+
+    fn claim(&mut self, index: usize) -> Result<()> {
+        let participant = &mut self.participants[index];
+        claim_eligibility(&self.program, participant, self.now, ClaimAccounts::default()).require_claimable()?;
+
+        let vault_balance = &mut self.vault_balance;
+        settle_claim(&mut self.program, participant, *vault_balance, None, PayoutKind::Sol, |amount| {
+            *vault_balance -= amount;
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    /// Total rewards credited to the referrer of `buyer` so far, claimed or not
+    fn referrer_earnings(&self, buyer: usize) -> Option<u64> {
+        let referrer = &self.participants[self.referrers[buyer]?];
+        Some(referrer.total_rewards + referrer.pending_rewards)
+    }
+}
+
+fn settings() -> impl Strategy<Value = ProgramSettings> {
+    (
+        (1u64..=1_000_000_000, 1u64..=1_000_000_000, 0u64..=1_000_000_000),
+        (0..=MAX_FEE_PERCENTAGE, MIN_LOCKED_PERIOD..=7 * MIN_LOCKED_PERIOD, prop::option::of(1i64..=30 * 86400)),
+        (0u64..=100_000_000, any::<bool>(), 0..=MAX_RESERVE_BPS),
+        prop::collection::vec((1u64..=20, 0u64..=100_000_000), 0..=MAX_MILESTONES),
+    )
+        .prop_map(|(rewards, terms, funding, milestone_entries)| {
+            let (fixed_reward_amount, base_reward, extra_cap) = rewards;
+            let (revenue_share_percent, locked_period, run_after_lock) = terms;
+            let (referee_reward_amount, referee_rewards_locked, reserve_bps) = funding;
+
+            // Thresholds must strictly ascend
+            let mut entries = milestone_entries;
+            entries.sort_by_key(|(threshold, _)| *threshold);
+            entries.dedup_by_key(|(threshold, _)| *threshold);
+            let mut milestones = [Milestone::default(); MAX_MILESTONES];
+            for (slot, (threshold, bonus)) in milestones.iter_mut().zip(entries) {
+                *slot = Milestone { threshold, bonus };
+            }
+
+            ProgramSettings {
+                fixed_reward_amount,
+                locked_period,
+                program_end_time: run_after_lock.map(|run| locked_period + run),
+                base_reward,
+                max_reward_cap: fixed_reward_amount.max(base_reward) + extra_cap,
+                max_depth: 0,
+                milestones,
+                invite_only: false,
+                revenue_share_percent,
+                referee_reward_amount,
+                referee_rewards_locked,
+                reserve_bps,
+                max_referrals_per_window: 0,
+                referral_window_seconds: 0,
+                rate_limit_strict: false,
+                required_collection: None,
+                collection_gates_credits: false,
+                transfers_enabled: false,
+                dispute_window_seconds: 0,
+                rent_payer_mode: 0,
+                min_joiner_balance: 0,
+                min_account_age_seconds: 0,
+                alert_thresholds: [0; 3],
+                referrer_requirement: None,
+                referee_requirement: None,
+                direct_claims_only: false,
+                trailing_commission_bps: 0,
+                link_signer: None,
+                dormancy_period_seconds: 0,
+                region_attestor: None,
+                embargoed_regions: [0; 8],
+                region_checked_on_claim: false,
+                bridge_source_program: None,
+                bridge_bps: 0,
+                cleanup_bounty_bps: 0,
+                event_mode: 0,
+                attribution_grace_seconds: 0,
+                tier1_reward: 0,
+                tier2_reward: 0,
+                auto_downgrade: false,
+                min_remaining_credits: 0,
+                seed_reclaim_seconds: 0,
+                early_redemption_fee: 0,
+            }
+        })
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        (1u64..=1_000_000_000_000).prop_map(Op::Deposit),
+        any::<Index>().prop_map(|referrer| Op::Join { referrer }),
+        (any::<Index>(), any::<u64>()).prop_map(|(buyer, amount)| Op::Purchase { buyer, amount }),
+        any::<Index>().prop_map(Op::Claim),
+        (0i64..=2 * 86400).prop_map(Op::Advance),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn test_model_invariants(settings in settings(), ops in prop::collection::vec(op(), 1..48)) {
+        prop_assert!(validate_program_settings(&settings, 0, &NetworkLimits::default()).is_ok());
+        let mut model = Model::new(&settings);
+
+        for op in &ops {
+            let before = model.clone();
+            let accepted = model.apply(op).is_ok();
+
+            // Funds only move between the vault and claimants; nothing is paid out that was not deposited
+            let program = &model.program;
+            let held = u128::from(program.total_available) + u128::from(program.reserved_balance);
+            prop_assert_eq!(held + u128::from(program.total_rewards_distributed), model.deposited);
+            prop_assert_eq!(u128::from(model.vault_balance), held);
+
+            // The reserve only grows: no claim can reach it
+            prop_assert!(program.reserved_balance >= before.program.reserved_balance);
+
+            // The program's counters agree with its participants
+            let pending: u128 = model.participants.iter().map(|p| u128::from(p.pending_rewards)).sum();
+            let claimed: u128 = model.participants.iter().map(|p| u128::from(p.total_rewards)).sum();
+            prop_assert_eq!(pending, u128::from(program.total_committed));
+            prop_assert_eq!(claimed, u128::from(program.total_rewards_distributed));
+
+            // Pending rewards only shrink through a claim
+            let claimed_now = accepted && matches!(op, Op::Claim(_));
+            for (old, new) in before.participants.iter().zip(&model.participants) {
+                prop_assert!(claimed_now || new.pending_rewards >= old.pending_rewards);
+            }
+
+            // An ended program credits nothing
+            if before.program.has_ended(before.now) {
+                prop_assert!(model.program.total_committed <= before.program.total_committed);
+                prop_assert_eq!(model.participants.len(), before.participants.len());
+            }
+
+            // A purchase never lifts the referrer past the cap nor commits more than the vault's headroom
+            if let (Op::Purchase { buyer, .. }, true) = (op, accepted) {
+                let buyer = buyer.index(before.participants.len());
+                let earned_before = before.referrer_earnings(buyer).unwrap();
+                let reward = model.referrer_earnings(buyer).unwrap() - earned_before;
+                let headroom = before.program.total_available.saturating_sub(before.program.total_committed);
+                prop_assert!(reward <= headroom);
+                prop_assert!(earned_before + reward <= settings.max_reward_cap.max(earned_before));
+            }
+        }
+    }
+}
@@ -1,14 +1,17 @@
-use std::i64;
-
 use anchor_client::solana_sdk::{pubkey::Pubkey, signer::Signer, system_program};
 use anchor_spl::token::spl_token;
 use solrefer::{
-    instructions::ProgramSettings,
-    state::{EligibilityCriteria, ReferralProgram},
+    constants::MAX_PROGRAM_DURATION,
+    error::ReferralError,
+    instructions::{validate_program_duration, ProgramSettings},
+    state::{EligibilityCriteria, NetworkLimits, Participant, ReferralProgram},
 };
 
 use crate::test_util::{
-    create_mint, create_sol_referral_program, create_token_account, deposit_sol, mint_tokens, setup,
+    create_mint, create_sol_referral_program, create_sol_referral_program_with_status, create_token_account,
+    deposit_sol, far_future_end_time, get_cluster_time, get_eligibility_criteria_pda, get_network_config_pda,
+    get_next_settings_change_pda, get_participant_pda, join_referral_program, mint_tokens, setup,
+    update_program_settings, wait_for_cluster_time,
 };
 
 #[test]
@@ -19,13 +22,8 @@ fn test_create_sol_referral_program() {
     let fixed_reward_amount = 1000000; // 1 SOL
 
     // Create SOL referral program
-    let (referral_program_pubkey, _) = create_sol_referral_program(
-        &owner,
-        &client,
-        program_id,
-        fixed_reward_amount,
-        i64::MAX, // 0.05 SOL base reward
-    );
+    let (referral_program_pubkey, _) =
+        create_sol_referral_program(&owner, &client, program_id, fixed_reward_amount, far_future_end_time());
 
     // Verify the created program
     let referral_program: ReferralProgram = client
@@ -37,7 +35,7 @@ fn test_create_sol_referral_program() {
     assert_eq!(referral_program.authority, owner.pubkey());
     assert_eq!(referral_program.token_mint, Pubkey::default()); // Default pubkey means SOL
     assert_eq!(referral_program.fixed_reward_amount, fixed_reward_amount);
-    assert_eq!(referral_program.total_referrals, 0);
+    assert_eq!(referral_program.total_referrals_credited, 0);
     assert_eq!(referral_program.total_rewards_distributed, 0);
     assert!(referral_program.is_active);
 
@@ -64,7 +62,7 @@ fn test_sol_referral_program_not_sol_deposit() {
 
     // Create a SOL referral program
     let (referral_program_pubkey, vault) =
-        create_sol_referral_program(&owner, &client, program_id, 1_000_000, i64::MAX);
+        create_sol_referral_program(&owner, &client, program_id, 1_000_000, far_future_end_time());
 
     // Create a token mint and account to test invalid deposits
     let mint = create_mint(&owner, &client, program_id);
@@ -88,6 +86,7 @@ fn test_sol_referral_program_not_sol_deposit() {
             token_mint: mint.pubkey(),
             depositor_token_account: owner_token_account,
             authority: owner.pubkey(),
+            event_queue: None,
             token_program: spl_token::id(),
         })
         .args(solrefer::instruction::DepositToken { amount: 1_000_000 })
@@ -101,7 +100,8 @@ fn test_update_program_settings_success() {
     let (owner, _, _, program_id, client) = setup();
 
     // Create a SOL referral program
-    let (referral_program_pubkey, _) = create_sol_referral_program(&owner, &client, program_id, 1_000_000, i64::MAX);
+    let (referral_program_pubkey, _) =
+        create_sol_referral_program(&owner, &client, program_id, 1_000_000, far_future_end_time());
 
     // Find eligibility criteria PDA
     let (eligibility_criteria_pubkey, _) =
@@ -109,11 +109,49 @@ fn test_update_program_settings_success() {
 
     // New settings to update
     let new_settings = ProgramSettings {
-        fixed_reward_amount: 2_000_000, // 0.002 SOL fixed reward
-        locked_period: 86400,           // 1 day locked period (minimum allowed)
-        program_end_time: i64::MAX,     // Set end time to max
-        base_reward: 75_000_000,        // 0.075 SOL base reward
-        max_reward_cap: 1_000_000_000,  // 1 SOL max reward cap
+        fixed_reward_amount: 2_000_000,                // 0.002 SOL fixed reward
+        locked_period: 86400,                          // 1 day locked period (minimum allowed)
+        program_end_time: Some(far_future_end_time()), // One year from now
+        base_reward: 75_000_000,                       // 0.075 SOL base reward
+        max_reward_cap: 1_000_000_000,                 // 1 SOL max reward cap
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
     };
 
     // Update program settings
@@ -124,10 +162,17 @@ fn test_update_program_settings_success() {
         .accounts(solrefer::accounts::UpdateProgramSettings {
             referral_program: referral_program_pubkey,
             eligibility_criteria: eligibility_criteria_pubkey,
+            network_config: get_network_config_pda(program_id),
             authority: owner.pubkey(),
+            settings_change: get_next_settings_change_pda(referral_program_pubkey, &client, program_id),
+            pending_change: None,
+            event_queue: None,
             system_program: system_program::ID,
         })
-        .args(solrefer::instruction::UpdateProgramSettings { new_settings: new_settings.clone() })
+        .args(solrefer::instruction::UpdateProgramSettings {
+            new_settings: new_settings.clone(),
+            idempotency_key: None,
+        })
         .signer(&owner)
         .send()
         .expect("Failed to update program settings");
@@ -153,6 +198,8 @@ fn test_update_program_settings_success() {
     assert_eq!(eligibility_criteria.base_reward, new_settings.base_reward);
     assert_eq!(eligibility_criteria.max_reward_cap, new_settings.max_reward_cap);
     assert_eq!(eligibility_criteria.program_end_time, new_settings.clone().program_end_time);
+    // The program caches the end time set through the criteria
+    assert_eq!(referral_program.program_end_time, eligibility_criteria.program_end_time);
 }
 
 #[test]
@@ -165,7 +212,7 @@ fn test_update_program_settings_invalid_reward_amount() {
         &client,
         program_id,
         1_000_000, // 0.001 SOL fixed reward
-        i64::MAX,
+        far_future_end_time(),
     );
 
     // Find eligibility criteria PDA
@@ -174,11 +221,49 @@ fn test_update_program_settings_invalid_reward_amount() {
 
     // Test case 1: Zero fixed reward amount
     let invalid_settings_1 = ProgramSettings {
-        fixed_reward_amount: 0,        // Invalid: Zero reward
-        locked_period: 86400,          // 1 day
-        program_end_time: i64::MAX,    // Set end time to max
-        base_reward: 50_000_000,       // 0.05 SOL
-        max_reward_cap: 1_000_000_000, // 1 SOL
+        fixed_reward_amount: 0,                        // Invalid: Zero reward
+        locked_period: 86400,                          // 1 day
+        program_end_time: Some(far_future_end_time()), // One year from now
+        base_reward: 50_000_000,                       // 0.05 SOL
+        max_reward_cap: 1_000_000_000,                 // 1 SOL
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
     };
 
     let result = client
@@ -188,10 +273,17 @@ fn test_update_program_settings_invalid_reward_amount() {
         .accounts(solrefer::accounts::UpdateProgramSettings {
             referral_program: referral_program_pubkey,
             eligibility_criteria: eligibility_criteria_pubkey,
+            network_config: get_network_config_pda(program_id),
             authority: owner.pubkey(),
+            settings_change: get_next_settings_change_pda(referral_program_pubkey, &client, program_id),
+            pending_change: None,
+            event_queue: None,
             system_program: system_program::ID,
         })
-        .args(solrefer::instruction::UpdateProgramSettings { new_settings: invalid_settings_1.clone() })
+        .args(solrefer::instruction::UpdateProgramSettings {
+            new_settings: invalid_settings_1.clone(),
+            idempotency_key: None,
+        })
         .signer(&owner)
         .send();
 
@@ -199,11 +291,49 @@ fn test_update_program_settings_invalid_reward_amount() {
 
     // Test case 2: Base reward greater than max reward cap
     let invalid_settings_2 = ProgramSettings {
-        fixed_reward_amount: 1_000_000, // 0.001 SOL
-        locked_period: 86400,           // 1 day
-        program_end_time: i64::MAX,     // Set end time to max
-        base_reward: 2_000_000_000,     // Invalid: 2 SOL base reward > 1 SOL max cap
-        max_reward_cap: 1_000_000_000,  // 1 SOL
+        fixed_reward_amount: 1_000_000,                // 0.001 SOL
+        locked_period: 86400,                          // 1 day
+        program_end_time: Some(far_future_end_time()), // One year from now
+        base_reward: 2_000_000_000,                    // Invalid: 2 SOL base reward > 1 SOL max cap
+        max_reward_cap: 1_000_000_000,                 // 1 SOL
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
     };
 
     let result = client
@@ -213,10 +343,17 @@ fn test_update_program_settings_invalid_reward_amount() {
         .accounts(solrefer::accounts::UpdateProgramSettings {
             referral_program: referral_program_pubkey,
             eligibility_criteria: eligibility_criteria_pubkey,
+            network_config: get_network_config_pda(program_id),
             authority: owner.pubkey(),
+            settings_change: get_next_settings_change_pda(referral_program_pubkey, &client, program_id),
+            pending_change: None,
+            event_queue: None,
             system_program: system_program::ID,
         })
-        .args(solrefer::instruction::UpdateProgramSettings { new_settings: invalid_settings_2.clone() })
+        .args(solrefer::instruction::UpdateProgramSettings {
+            new_settings: invalid_settings_2.clone(),
+            idempotency_key: None,
+        })
         .signer(&owner)
         .send();
 
@@ -233,7 +370,7 @@ fn test_update_program_settings_invalid_end_time() {
         &client,
         program_id,
         1_000_000, // 0.001 SOL fixed reward
-        i64::MAX,
+        far_future_end_time(),
     );
 
     // Find eligibility criteria PDA
@@ -245,11 +382,49 @@ fn test_update_program_settings_invalid_end_time() {
 
     // Test case 1: End time in the past
     let invalid_settings_1 = ProgramSettings {
-        fixed_reward_amount: 1_000_000,     // 0.001 SOL
-        locked_period: 86400,               // 1 day
-        program_end_time: current_time - 1, // Invalid: End time in the past
-        base_reward: 50_000_000,            // 0.05 SOL
-        max_reward_cap: 1_000_000_000,      // 1 SOL
+        fixed_reward_amount: 1_000_000,           // 0.001 SOL
+        locked_period: 86400,                     // 1 day
+        program_end_time: Some(current_time - 1), // Invalid: End time in the past
+        base_reward: 50_000_000,                  // 0.05 SOL
+        max_reward_cap: 1_000_000_000,            // 1 SOL
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
     };
 
     let result = client
@@ -259,10 +434,17 @@ fn test_update_program_settings_invalid_end_time() {
         .accounts(solrefer::accounts::UpdateProgramSettings {
             referral_program: referral_program_pubkey,
             eligibility_criteria: eligibility_criteria_pubkey,
+            network_config: get_network_config_pda(program_id),
             authority: owner.pubkey(),
+            settings_change: get_next_settings_change_pda(referral_program_pubkey, &client, program_id),
+            pending_change: None,
+            event_queue: None,
             system_program: system_program::ID,
         })
-        .args(solrefer::instruction::UpdateProgramSettings { new_settings: invalid_settings_1.clone() })
+        .args(solrefer::instruction::UpdateProgramSettings {
+            new_settings: invalid_settings_1.clone(),
+            idempotency_key: None,
+        })
         .signer(&owner)
         .send();
 
@@ -270,11 +452,49 @@ fn test_update_program_settings_invalid_end_time() {
 
     // Test case 2: End time before locked period ends
     let invalid_settings_2 = ProgramSettings {
-        fixed_reward_amount: 1_000_000,        // 0.001 SOL
-        locked_period: 86400,                  // 1 day
-        program_end_time: current_time + 3600, // Invalid: End time only 1 hour in future (less than locked period)
-        base_reward: 50_000_000,               // 0.05 SOL
-        max_reward_cap: 1_000_000_000,         // 1 SOL
+        fixed_reward_amount: 1_000_000,              // 0.001 SOL
+        locked_period: 86400,                        // 1 day
+        program_end_time: Some(current_time + 3600), // Invalid: Ends 1 hour out, within the locked period
+        base_reward: 50_000_000,                     // 0.05 SOL
+        max_reward_cap: 1_000_000_000,               // 1 SOL
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
     };
 
     let result = client
@@ -284,10 +504,17 @@ fn test_update_program_settings_invalid_end_time() {
         .accounts(solrefer::accounts::UpdateProgramSettings {
             referral_program: referral_program_pubkey,
             eligibility_criteria: eligibility_criteria_pubkey,
+            network_config: get_network_config_pda(program_id),
             authority: owner.pubkey(),
+            settings_change: get_next_settings_change_pda(referral_program_pubkey, &client, program_id),
+            pending_change: None,
+            event_queue: None,
             system_program: system_program::ID,
         })
-        .args(solrefer::instruction::UpdateProgramSettings { new_settings: invalid_settings_2.clone() })
+        .args(solrefer::instruction::UpdateProgramSettings {
+            new_settings: invalid_settings_2.clone(),
+            idempotency_key: None,
+        })
         .signer(&owner)
         .send();
 
@@ -304,7 +531,7 @@ fn test_update_program_settings_invalid_locked_period() {
         &client,
         program_id,
         1_000_000, // 0.001 SOL fixed reward
-        i64::MAX,
+        far_future_end_time(),
     );
 
     // Find eligibility criteria PDA
@@ -313,11 +540,49 @@ fn test_update_program_settings_invalid_locked_period() {
 
     // Test case 1: Locked period too short (less than 1 day)
     let invalid_settings_1 = ProgramSettings {
-        fixed_reward_amount: 1_000_000, // 0.001 SOL
-        locked_period: 3600,            // Invalid: Only 1 hour (minimum is 1 day)
-        program_end_time: i64::MAX,     // Set end time to max
-        base_reward: 50_000_000,        // 0.05 SOL
-        max_reward_cap: 1_000_000_000,  // 1 SOL
+        fixed_reward_amount: 1_000_000,                // 0.001 SOL
+        locked_period: 3600,                           // Invalid: Only 1 hour (minimum is 1 day)
+        program_end_time: Some(far_future_end_time()), // One year from now
+        base_reward: 50_000_000,                       // 0.05 SOL
+        max_reward_cap: 1_000_000_000,                 // 1 SOL
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
     };
 
     let result = client
@@ -327,10 +592,17 @@ fn test_update_program_settings_invalid_locked_period() {
         .accounts(solrefer::accounts::UpdateProgramSettings {
             referral_program: referral_program_pubkey,
             eligibility_criteria: eligibility_criteria_pubkey,
+            network_config: get_network_config_pda(program_id),
             authority: owner.pubkey(),
+            settings_change: get_next_settings_change_pda(referral_program_pubkey, &client, program_id),
+            pending_change: None,
+            event_queue: None,
             system_program: system_program::ID,
         })
-        .args(solrefer::instruction::UpdateProgramSettings { new_settings: invalid_settings_1.clone() })
+        .args(solrefer::instruction::UpdateProgramSettings {
+            new_settings: invalid_settings_1.clone(),
+            idempotency_key: None,
+        })
         .signer(&owner)
         .send();
 
@@ -338,11 +610,49 @@ fn test_update_program_settings_invalid_locked_period() {
 
     // Test case 2: Locked period too long (more than 365 days)
     let invalid_settings_2 = ProgramSettings {
-        fixed_reward_amount: 1_000_000,  // 0.001 SOL
-        locked_period: 31536000 + 86400, // Invalid: 366 days (maximum is 365 days)
-        program_end_time: i64::MAX,      // Set end time to max
-        base_reward: 50_000_000,         // 0.05 SOL
-        max_reward_cap: 1_000_000_000,   // 1 SOL
+        fixed_reward_amount: 1_000_000,                // 0.001 SOL
+        locked_period: 31536000 + 86400,               // Invalid: 366 days (maximum is 365 days)
+        program_end_time: Some(far_future_end_time()), // One year from now
+        base_reward: 50_000_000,                       // 0.05 SOL
+        max_reward_cap: 1_000_000_000,                 // 1 SOL
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
     };
 
     let result = client
@@ -352,12 +662,338 @@ fn test_update_program_settings_invalid_locked_period() {
         .accounts(solrefer::accounts::UpdateProgramSettings {
             referral_program: referral_program_pubkey,
             eligibility_criteria: eligibility_criteria_pubkey,
+            network_config: get_network_config_pda(program_id),
             authority: owner.pubkey(),
+            settings_change: get_next_settings_change_pda(referral_program_pubkey, &client, program_id),
+            pending_change: None,
+            event_queue: None,
             system_program: system_program::ID,
         })
-        .args(solrefer::instruction::UpdateProgramSettings { new_settings: invalid_settings_2.clone() })
+        .args(solrefer::instruction::UpdateProgramSettings {
+            new_settings: invalid_settings_2.clone(),
+            idempotency_key: None,
+        })
         .signer(&owner)
         .send();
 
     assert!(result.is_err(), "Expected error for locked period more than 365 days");
 }
+
+#[test]
+fn test_validate_program_duration_boundaries() {
+    let now = 1_700_000_000;
+    let limits = NetworkLimits::default();
+
+    // Exactly at the maximum duration is accepted
+    assert!(validate_program_duration(now + MAX_PROGRAM_DURATION, now, &limits).is_ok());
+
+    // One second over the maximum duration is rejected
+    assert_eq!(
+        validate_program_duration(now + MAX_PROGRAM_DURATION + 1, now, &limits).unwrap_err(),
+        ReferralError::ProgramDurationTooLong.into()
+    );
+
+    // An end time of i64::MAX is rejected
+    assert_eq!(
+        validate_program_duration(i64::MAX, now, &limits).unwrap_err(),
+        ReferralError::ProgramDurationTooLong.into()
+    );
+
+    // A timestamp so large that the maximum end time overflows is rejected without panicking
+    assert_eq!(
+        validate_program_duration(i64::MAX, i64::MAX, &limits).unwrap_err(),
+        ReferralError::NumericOverflow.into()
+    );
+}
+
+#[test]
+fn test_create_referral_program_at_max_duration() {
+    let (owner, _, _, program_id, client) = setup();
+
+    // The cluster clock only moves forward, so an end time exactly MAX_PROGRAM_DURATION from the current cluster
+    // time is still within bounds when the transaction executes
+    let program_end_time = get_cluster_time(&client, program_id) + MAX_PROGRAM_DURATION;
+    let (referral_program_pubkey, _) =
+        create_sol_referral_program(&owner, &client, program_id, 1_000_000, program_end_time);
+
+    let eligibility_criteria: EligibilityCriteria = client
+        .program(program_id)
+        .unwrap()
+        .account(get_eligibility_criteria_pda(referral_program_pubkey, program_id))
+        .expect("Failed to fetch eligibility criteria account");
+    assert_eq!(eligibility_criteria.program_end_time, Some(program_end_time));
+}
+
+#[test]
+#[should_panic(expected = "ProgramDurationTooLong")]
+fn test_create_referral_program_duration_too_long() {
+    let (owner, _, _, program_id, client) = setup();
+
+    // One second over the maximum duration, with an hour of margin for clock drift between the fetch and execution
+    let program_end_time = get_cluster_time(&client, program_id) + MAX_PROGRAM_DURATION + 3600 + 1;
+    create_sol_referral_program(&owner, &client, program_id, 1_000_000, program_end_time);
+}
+
+#[test]
+fn test_update_program_settings_duration_too_long() {
+    let (owner, _, _, program_id, client) = setup();
+
+    let (referral_program_pubkey, _) =
+        create_sol_referral_program(&owner, &client, program_id, 1_000_000, far_future_end_time());
+
+    let (eligibility_criteria_pubkey, _) =
+        Pubkey::find_program_address(&[b"eligibility_criteria", referral_program_pubkey.as_ref()], &program_id);
+
+    let invalid_settings = ProgramSettings {
+        fixed_reward_amount: 1_000_000,
+        locked_period: 86400,
+        program_end_time: Some(i64::MAX), // Invalid: far beyond MAX_PROGRAM_DURATION
+        base_reward: 50_000_000,
+        max_reward_cap: 1_000_000_000,
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
+    };
+
+    let result = client
+        .program(program_id)
+        .unwrap()
+        .request()
+        .accounts(solrefer::accounts::UpdateProgramSettings {
+            referral_program: referral_program_pubkey,
+            eligibility_criteria: eligibility_criteria_pubkey,
+            network_config: get_network_config_pda(program_id),
+            authority: owner.pubkey(),
+            settings_change: get_next_settings_change_pda(referral_program_pubkey, &client, program_id),
+            pending_change: None,
+            event_queue: None,
+            system_program: system_program::ID,
+        })
+        .args(solrefer::instruction::UpdateProgramSettings { new_settings: invalid_settings, idempotency_key: None })
+        .signer(&owner)
+        .send();
+
+    assert!(result.unwrap_err().to_string().contains("ProgramDurationTooLong"));
+}
+
+#[test]
+fn test_activate_inactive_program_with_deposit() {
+    let (owner, alice, _, program_id, client) = setup();
+
+    let (referral_program_pubkey, vault) = create_sol_referral_program_with_status(
+        &owner,
+        &client,
+        program_id,
+        1_000_000,
+        Some(far_future_end_time()),
+        true,
+    );
+    let program = client.program(program_id).unwrap();
+    let referral_program: ReferralProgram = program.account(referral_program_pubkey).unwrap();
+    assert!(!referral_program.is_active);
+
+    // Participants cannot join the unfunded shell
+    let result = program
+        .request()
+        .accounts(solrefer::accounts::JoinReferralProgram {
+            referral_program: referral_program_pubkey,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program_pubkey, program_id),
+            participant: get_participant_pda(referral_program_pubkey, alice.pubkey(), program_id),
+            invite: None,
+            collection_metadata: None,
+            collection_nft: None,
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account: None,
+            bridge_participant: None,
+            bridge_referrer: None,
+            user: alice.pubkey(),
+            event_queue: None,
+            system_program: system_program::ID,
+        })
+        .args(solrefer::instruction::JoinReferralProgram { accepted_terms_hash: [0u8; 32] })
+        .signer(&alice)
+        .send();
+    assert!(result.unwrap_err().to_string().contains("ProgramInactive"));
+
+    // Activation waits for the criteria to be configured
+    let activate = || {
+        program
+            .request()
+            .accounts(solrefer::accounts::ActivateProgram {
+                referral_program: referral_program_pubkey,
+                eligibility_criteria: get_eligibility_criteria_pda(referral_program_pubkey, program_id),
+                vault,
+                token_vault: None,
+                depositor_token_account: None,
+                authority: owner.pubkey(),
+                system_program: system_program::ID,
+                token_program: None,
+            })
+            .args(solrefer::instruction::ActivateProgram { initial_deposit: 10_000_000 })
+            .signer(&owner)
+            .send()
+            .map_err(Box::new)
+    };
+    assert!(activate().unwrap_err().to_string().contains("CriteriaNotSet"));
+    let settings = ProgramSettings {
+        fixed_reward_amount: 1_000_000,
+        locked_period: 86400,
+        program_end_time: Some(far_future_end_time()),
+        base_reward: 1_000_000,
+        max_reward_cap: 100_000_000,
+        max_depth: 0,
+        milestones: Default::default(),
+        invite_only: false,
+        revenue_share_percent: 0,
+        referee_reward_amount: 0,
+        referee_rewards_locked: false,
+        reserve_bps: 0,
+        max_referrals_per_window: 0,
+        referral_window_seconds: 0,
+        rate_limit_strict: false,
+        required_collection: None,
+        collection_gates_credits: false,
+        transfers_enabled: false,
+        dispute_window_seconds: 0,
+        rent_payer_mode: 0,
+        min_joiner_balance: 0,
+        min_account_age_seconds: 0,
+        alert_thresholds: [0; 3],
+        referrer_requirement: None,
+        referee_requirement: None,
+        direct_claims_only: false,
+        trailing_commission_bps: 0,
+        link_signer: None,
+        dormancy_period_seconds: 0,
+        region_attestor: None,
+        embargoed_regions: [0; 8],
+        region_checked_on_claim: false,
+        bridge_source_program: None,
+        bridge_bps: 0,
+        cleanup_bounty_bps: 0,
+        event_mode: 0,
+        attribution_grace_seconds: 0,
+        tier1_reward: 0,
+        tier2_reward: 0,
+        auto_downgrade: false,
+        min_remaining_credits: 0,
+        seed_reclaim_seconds: 0,
+        early_redemption_fee: 0,
+    };
+    update_program_settings(&owner, referral_program_pubkey, settings, &client, program_id);
+
+    // Fund and activate in one transaction
+    let vault_balance_before = program.rpc().get_balance(&vault).unwrap();
+    activate().unwrap();
+
+    let referral_program: ReferralProgram = program.account(referral_program_pubkey).unwrap();
+    assert!(referral_program.is_active);
+    assert_eq!(referral_program.next_setup_step(), 0);
+    assert_eq!(referral_program.total_available, 10_000_000);
+    assert_eq!(program.rpc().get_balance(&vault).unwrap(), vault_balance_before + 10_000_000);
+
+    // Joining now succeeds
+    let participant = join_referral_program(&alice, referral_program_pubkey, &client, program_id);
+    let participant: Participant = program.account(participant).unwrap();
+    assert_eq!(participant.owner, alice.pubkey());
+}
+
+#[test]
+fn test_program_has_ended() {
+    let program = ReferralProgram { program_end_time: Some(1_000), ..Default::default() };
+    assert!(!program.has_ended(999));
+    assert!(program.has_ended(1_000));
+    assert!(program.has_ended(1_001));
+}
+
+#[test]
+fn test_next_setup_step() {
+    let mut program = ReferralProgram::default();
+    for step in ReferralProgram::SETUP_STEPS {
+        assert_eq!(program.next_setup_step(), step);
+        program.setup_state |= step;
+    }
+    assert_eq!(program.next_setup_step(), 0);
+
+    // A SOL program created active is only missing its funding
+    program.setup_state = ReferralProgram::SETUP_STEPS.iter().sum::<u8>() & !ReferralProgram::SETUP_FUNDED;
+    assert_eq!(program.next_setup_step(), ReferralProgram::SETUP_FUNDED);
+    program.credit_deposit(0).unwrap();
+    assert_eq!(program.next_setup_step(), ReferralProgram::SETUP_FUNDED);
+    program.credit_deposit(1).unwrap();
+    assert_eq!(program.next_setup_step(), 0);
+}
+
+#[test]
+fn test_join_rejected_after_program_end() {
+    let (owner, alice, _, program_id, client) = setup();
+
+    let end_time = get_cluster_time(&client, program_id) + 10;
+    let (referral_program, _) = create_sol_referral_program(&owner, &client, program_id, 1_000_000, end_time);
+    let program = client.program(program_id).unwrap();
+    let program_account: ReferralProgram = program.account(referral_program).unwrap();
+    assert_eq!(program_account.program_end_time, Some(end_time));
+
+    wait_for_cluster_time(&client, program_id, end_time);
+
+    // Joining directly never loads the eligibility criteria; the cached end time alone rejects it
+    let err = program
+        .request()
+        .accounts(solrefer::accounts::JoinReferralProgram {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, program_id),
+            participant: get_participant_pda(referral_program, alice.pubkey(), program_id),
+            invite: None,
+            collection_metadata: None,
+            collection_nft: None,
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account: None,
+            bridge_participant: None,
+            bridge_referrer: None,
+            user: alice.pubkey(),
+            event_queue: None,
+            system_program: system_program::ID,
+        })
+        .args(solrefer::instruction::JoinReferralProgram { accepted_terms_hash: [0u8; 32] })
+        .signer(&alice)
+        .send()
+        .unwrap_err();
+    assert!(err.to_string().contains("ProgramEnded"));
+}
@@ -1,21 +1,28 @@
-use crate::test_util::{create_sol_referral_program, deposit_sol, setup};
+use crate::test_util::{
+    create_funded_user, create_sol_referral_program, deposit_sol, far_future_end_time, get_eligibility_criteria_pda,
+    get_referee_receipt_pda, join_referral_program, join_through_referral, setup,
+};
 use anchor_client::solana_sdk::{pubkey::Pubkey, signer::Signer, system_program};
-use solrefer::{instructions::VAULT_SEED, state::{Participant, ReferralProgram}};
+use solrefer::{
+    error::ReferralError,
+    instructions::{record_payout, settle_claim, PayoutKind, ProgramStats, VAULT_SEED},
+    state::{EventQueue, EventRecord, Participant, ReferralProgram},
+};
 
 #[test]
 fn test_reward_claim() {
     // Setup test environment
     let (owner, referrer, referee, program_id, client) = setup();
-    
+
     // Create referral program with rewards
     let fixed_reward_amount = 1_000_000_000; // 1 SOL
-    
+
     let (referral_program_pubkey, _) = create_sol_referral_program(
         &owner,
         &client,
         program_id,
-        fixed_reward_amount,    // 1 SOL fixed reward
-        i64::MAX,            // Program end time
+        fixed_reward_amount,   // 1 SOL fixed reward
+        far_future_end_time(), // Program end time
     );
 
     // Find PDA for vault
@@ -23,14 +30,7 @@ fn test_reward_claim() {
 
     // Fund vault
     let deposit_amount = 1_000_000_000; // 1 SOL
-    deposit_sol(
-        deposit_amount,
-        referral_program_pubkey,
-        &owner,
-        &client,
-        program_id,
-        vault
-    );
+    deposit_sol(deposit_amount, referral_program_pubkey, &owner, &client, program_id, vault);
 
     // Join program and create referrals
     // Calculate PDA for participant account
@@ -44,12 +44,21 @@ fn test_reward_claim() {
         .request()
         .accounts(solrefer::accounts::JoinReferralProgram {
             referral_program: referral_program_pubkey,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program_pubkey, program_id),
             participant: referrer_participant_pubkey,
+            invite: None,
+            collection_metadata: None,
+            collection_nft: None,
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account: None,
+            bridge_participant: None,
+            bridge_referrer: None,
             user: referrer.pubkey(),
+            event_queue: None,
             system_program: system_program::ID,
-            rent: anchor_client::solana_sdk::sysvar::rent::ID,
         })
-        .args(solrefer::instruction::JoinReferralProgram {})
+        .args(solrefer::instruction::JoinReferralProgram { accepted_terms_hash: [0u8; 32] })
         .signer(&referrer)
         .send()
         .unwrap();
@@ -66,13 +75,34 @@ fn test_reward_claim() {
         .request()
         .accounts(solrefer::accounts::JoinThroughReferral {
             referral_program: referral_program_pubkey,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program_pubkey, program_id),
             participant: referee_participant_pubkey,
             referrer: referrer_participant_pubkey,
+            rotated_referrer: None,
+            split_recipient: None,
+            boost_escrow: None,
+            match_offer: None,
+            referrer_wallet: None,
+            referee_receipt: get_referee_receipt_pda(referral_program_pubkey, referee.pubkey(), program_id),
+            sponsor_vault: None,
+            invite: None,
+            referrer_collection_metadata: None,
+            referrer_collection_nft: None,
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account: None,
+            referee_token_account: None,
+            referrer_upline: None,
             user: referee.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
             system_program: system_program::ID,
-            rent: anchor_client::solana_sdk::sysvar::rent::ID,
         })
-        .args(solrefer::instruction::JoinThroughReferral {})
+        .args(solrefer::instruction::JoinThroughReferral {
+            source_tag: None,
+            accepted_terms_hash: [0u8; 32],
+            link_proof: None,
+        })
         .signer(&referee)
         .send()
         .unwrap();
@@ -84,13 +114,18 @@ fn test_reward_claim() {
     let referrer_balance_before = client.program(program_id).unwrap().rpc().get_balance(&referrer.pubkey()).unwrap();
 
     // Claim rewards
-    let tx = program
+    program
         .request()
         .accounts(solrefer::accounts::ClaimRewards {
             referral_program: referral_program_pubkey,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program_pubkey, program_id),
             participant: referrer_participant_pubkey,
+            claim_splitter: None,
             vault,
             user: referrer.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
+            region_attestation: None,
             system_program: system_program::ID,
         })
         .args(solrefer::instruction::ClaimRewards {})
@@ -123,3 +158,257 @@ fn test_reward_claim() {
     assert_eq!(program_state.total_rewards_distributed, fixed_reward_amount);
     assert_eq!(program_state.total_available, deposit_amount - fixed_reward_amount);
 }
+
+#[test]
+fn test_settle_claim_validates_before_transfer() {
+    let mut program = ReferralProgram { total_available: 10_000_000, total_committed: 3_000_000, ..Default::default() };
+    let mut participant = Participant { pending_rewards: 3_000_000, ..Default::default() };
+
+    // A vault holding less than the claim fails before the transfer runs or any accounting changes
+    let mut transferred = None;
+    let err = settle_claim(&mut program, &mut participant, 2_000_000, None, PayoutKind::Sol, |amount| {
+        transferred = Some(amount);
+        Ok(())
+    })
+    .unwrap_err();
+    assert_eq!(err, ReferralError::InsufficientVaultBalance.into());
+    assert_eq!(transferred, None);
+    assert_eq!(participant.pending_rewards, 3_000_000);
+    assert_eq!(program.total_available, 10_000_000);
+
+    // So does a claim above the program's recorded funds
+    program.total_available = 1_000_000;
+    let err = settle_claim(&mut program, &mut participant, 10_000_000, None, PayoutKind::Sol, |amount| {
+        transferred = Some(amount);
+        Ok(())
+    })
+    .unwrap_err();
+    assert_eq!(err, ReferralError::InsufficientVaultBalance.into());
+    assert_eq!(transferred, None);
+
+    // A valid claim updates the accounting and transfers exactly the pending amount
+    program.total_available = 10_000_000;
+    let paid = settle_claim(&mut program, &mut participant, 10_000_000, None, PayoutKind::Sol, |amount| {
+        transferred = Some(amount);
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(paid, 3_000_000);
+    assert_eq!(transferred, Some(3_000_000));
+    assert_eq!(participant.pending_rewards, 0);
+    assert_eq!(participant.total_rewards, 3_000_000);
+    assert_eq!(program.total_available, 7_000_000);
+    assert_eq!(program.total_committed, 0);
+    assert_eq!(program.total_rewards_distributed, 3_000_000);
+}
+
+/// Requires the program to be built with the `test-utils` feature
+#[test]
+fn test_claim_drain_attempt_moves_nothing() {
+    let (owner, referrer, referee, program_id, client) = setup();
+    let fixed_reward_amount = 1_000_000_000;
+
+    let (referral_program, vault) =
+        create_sol_referral_program(&owner, &client, program_id, fixed_reward_amount, far_future_end_time());
+    deposit_sol(fixed_reward_amount / 2, referral_program, &owner, &client, program_id, vault);
+
+    let referrer_participant = join_referral_program(&referrer, referral_program, &client, program_id);
+    join_through_referral(&referee, referral_program, referrer_participant, &client, program_id);
+
+    // Inflate the recorded funds past what the vault actually holds
+    let program = client.program(program_id).unwrap();
+    program
+        .request()
+        .accounts(solrefer::instructions::test_utils::accounts::CorruptTotalAvailable {
+            referral_program,
+            authority: owner.pubkey(),
+        })
+        .args(solrefer::instructions::test_utils::instruction::CorruptTotalAvailable {
+            total_available: 10 * fixed_reward_amount,
+        })
+        .signer(&owner)
+        .send()
+        .unwrap();
+
+    let vault_balance_before = program.rpc().get_balance(&vault).unwrap();
+    let err = program
+        .request()
+        .accounts(solrefer::accounts::ClaimRewards {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, program_id),
+            participant: referrer_participant,
+            claim_splitter: None,
+            vault,
+            user: referrer.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
+            region_attestation: None,
+            system_program: system_program::ID,
+        })
+        .args(solrefer::instruction::ClaimRewards {})
+        .signer(&referrer)
+        .send()
+        .unwrap_err();
+    assert!(err.to_string().contains("InsufficientVaultBalance"));
+
+    // Nothing left the vault and the participant keeps the pending reward
+    assert_eq!(program.rpc().get_balance(&vault).unwrap(), vault_balance_before);
+    let participant: Participant = program.account(referrer_participant).unwrap();
+    assert_eq!(participant.pending_rewards, fixed_reward_amount);
+    assert_eq!(participant.total_rewards, 0);
+}
+
+/// Requires the program to be built with the `test-utils` feature
+#[test]
+fn test_claim_guard_rejects_misrouted_payout() {
+    let (owner, referrer, referee, program_id, client) = setup();
+    // Kept below the participant account's rent so the misrouted payout itself can be made
+    let fixed_reward_amount = 100_000;
+
+    let (referral_program, vault) =
+        create_sol_referral_program(&owner, &client, program_id, fixed_reward_amount, far_future_end_time());
+    deposit_sol(fixed_reward_amount, referral_program, &owner, &client, program_id, vault);
+
+    let referrer_participant = join_referral_program(&referrer, referral_program, &client, program_id);
+    join_through_referral(&referee, referral_program, referrer_participant, &client, program_id);
+
+    let program = client.program(program_id).unwrap();
+    let vault_balance_before = program.rpc().get_balance(&vault).unwrap();
+    let participant_balance_before = program.rpc().get_balance(&referrer_participant).unwrap();
+    let err = program
+        .request()
+        .accounts(solrefer::accounts::ClaimRewards {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, program_id),
+            participant: referrer_participant,
+            claim_splitter: None,
+            vault,
+            user: referrer.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
+            region_attestation: None,
+            system_program: system_program::ID,
+        })
+        .args(solrefer::instructions::test_utils::instruction::MisroutedClaim {})
+        .signer(&referrer)
+        .send()
+        .unwrap_err();
+    assert!(err.to_string().contains("ClaimGuardViolation"));
+
+    // The whole transaction is rolled back
+    assert_eq!(program.rpc().get_balance(&vault).unwrap(), vault_balance_before);
+    assert_eq!(program.rpc().get_balance(&referrer_participant).unwrap(), participant_balance_before);
+    let participant: Participant = program.account(referrer_participant).unwrap();
+    assert_eq!(participant.pending_rewards, fixed_reward_amount);
+}
+
+#[test]
+fn test_record_payout() {
+    let mut program = ReferralProgram {
+        total_available: 10_000_000,
+        total_committed: 3_000_000,
+        total_rewards_distributed: 1_000_000,
+        total_deposited: 11_000_000,
+        ..Default::default()
+    };
+    let owner = Pubkey::new_unique();
+    let mut participant =
+        Participant { owner, pending_rewards: 3_000_000, total_rewards: 500_000, ..Default::default() };
+    let mut event_queue = EventQueue { program: Pubkey::default(), head: 0, records: Default::default(), bump: 0 };
+    let mut stats = ProgramStats { event_queue: &mut event_queue, now: 42 };
+
+    record_payout(&mut program, &mut participant, Some(&mut stats), 2_000_000, PayoutKind::Sol).unwrap();
+    assert_eq!(participant.pending_rewards, 1_000_000);
+    assert_eq!(participant.total_rewards, 2_500_000);
+    assert_eq!(program.total_available, 8_000_000);
+    assert_eq!(program.total_committed, 1_000_000);
+    assert_eq!(program.total_rewards_distributed, 3_000_000);
+    assert_eq!(
+        stats.event_queue.decode_events(0),
+        vec![EventRecord { kind: EventRecord::KIND_CLAIM, actor: owner, amount: 2_000_000, ts: 42 }]
+    );
+
+    // A zero payout touches nothing, not even the stats
+    record_payout(&mut program, &mut participant, Some(&mut stats), 0, PayoutKind::Sol).unwrap();
+    assert_eq!(participant.pending_rewards, 1_000_000);
+    assert_eq!(participant.total_rewards, 2_500_000);
+    assert_eq!(program.total_available, 8_000_000);
+    assert_eq!(program.total_committed, 1_000_000);
+    assert_eq!(program.total_rewards_distributed, 3_000_000);
+    assert_eq!(stats.event_queue.head, 1);
+
+    // A SOL program cannot pay out tokens
+    let err = record_payout(&mut program, &mut participant, None, 1_000_000, PayoutKind::Token).unwrap_err();
+    assert_eq!(err, ReferralError::InvalidTokenMint.into());
+    assert_eq!(participant.pending_rewards, 1_000_000);
+
+    // An overflowing counter leaves every counter untouched
+    participant.total_rewards = u64::MAX;
+    let err = record_payout(&mut program, &mut participant, Some(&mut stats), 1_000_000, PayoutKind::Sol).unwrap_err();
+    assert_eq!(err, ReferralError::NumericOverflow.into());
+    assert_eq!(participant.pending_rewards, 1_000_000);
+    assert_eq!(program.total_available, 8_000_000);
+    assert_eq!(program.total_rewards_distributed, 3_000_000);
+    assert_eq!(stats.event_queue.head, 1);
+
+    // Paying out more than is pending is rejected
+    participant.total_rewards = 0;
+    let err = record_payout(&mut program, &mut participant, None, 2_000_000, PayoutKind::Sol).unwrap_err();
+    assert_eq!(err, ReferralError::NumericOverflow.into());
+
+    // A token program pays out tokens only
+    let mut token_program = ReferralProgram { token_mint: Pubkey::new_unique(), ..program };
+    let err = record_payout(&mut token_program, &mut participant, None, 1_000_000, PayoutKind::Sol).unwrap_err();
+    assert_eq!(err, ReferralError::InvalidTokenMint.into());
+    record_payout(&mut token_program, &mut participant, None, 1_000_000, PayoutKind::Token).unwrap();
+    assert_eq!(token_program.total_rewards_distributed, 4_000_000);
+}
+
+#[test]
+fn test_claims_conserve_program_funds() {
+    let (owner, referrer, referee, program_id, client) = setup();
+    let fixed_reward_amount = 1_000_000_000;
+    let deposit_amount = 3 * fixed_reward_amount;
+
+    let (referral_program, vault) =
+        create_sol_referral_program(&owner, &client, program_id, fixed_reward_amount, far_future_end_time());
+    deposit_sol(deposit_amount, referral_program, &owner, &client, program_id, vault);
+
+    let referrer_participant = join_referral_program(&referrer, referral_program, &client, program_id);
+    let referee_participant =
+        join_through_referral(&referee, referral_program, referrer_participant, &client, program_id);
+    let third = create_funded_user();
+    join_through_referral(&third, referral_program, referee_participant, &client, program_id);
+
+    let program = client.program(program_id).unwrap();
+    for (user, participant) in [(&referrer, referrer_participant), (&referee, referee_participant)] {
+        program
+            .request()
+            .accounts(solrefer::accounts::ClaimRewards {
+                referral_program,
+                eligibility_criteria: get_eligibility_criteria_pda(referral_program, program_id),
+                participant,
+                claim_splitter: None,
+                vault,
+                user: user.pubkey(),
+                event_queue: None,
+                instructions_sysvar: None,
+                region_attestation: None,
+                system_program: system_program::ID,
+            })
+            .args(solrefer::instruction::ClaimRewards {})
+            .signer(user)
+            .send()
+            .unwrap();
+    }
+
+    let program_state: ReferralProgram = program.account(referral_program).unwrap();
+    assert_eq!(program_state.total_rewards_distributed, 2 * fixed_reward_amount);
+    assert_eq!(program_state.total_available + program_state.total_rewards_distributed, deposit_amount);
+    assert_eq!(program_state.total_committed, 0);
+    for participant in [referrer_participant, referee_participant] {
+        let participant: Participant = program.account(participant).unwrap();
+        assert_eq!(participant.total_rewards, fixed_reward_amount);
+        assert_eq!(participant.pending_rewards, 0);
+    }
+}
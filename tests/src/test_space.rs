@@ -0,0 +1,99 @@
+//! Account layouts against what accounts actually serialize to.
+//!
+//! Every fixed-size account is serialized with each `Option` set, the most bytes it can take, and must fit the
+//! space its layout allocates. The accounts checked are exactly those with a space budget, so an account added to
+//! one list but not the other fails too.
+
+use anchor_client::{anchor_lang::AccountSerialize, solana_sdk::pubkey::Pubkey};
+use solrefer::{
+    constants::MAX_MILESTONES,
+    space::{layout_size, space_report, ACCOUNTS},
+    state::*,
+};
+use std::collections::BTreeSet;
+
+/// Asserts that `account` serializes into the space its layout allocates, returning its name
+fn assert_fits<T: AccountSerialize>(name: &'static str, account: T, size: usize) -> &'static str {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+    assert!(data.len() <= 8 + size, "{name} serializes to {} bytes but its layout allocates {}", data.len(), 8 + size);
+    name
+}
+
+#[test]
+fn test_maximal_accounts_fit_their_layouts() {
+    let key = Pubkey::new_unique();
+    let requirement = Some(TokenRequirement { mint: key, min_amount: u64::MAX });
+    let checked = [
+        assert_fits(
+            "ReferralProgram",
+            ReferralProgram { program_end_time: Some(i64::MAX), ..Default::default() },
+            ReferralProgram::SIZE,
+        ),
+        assert_fits(
+            "EligibilityCriteria",
+            EligibilityCriteria {
+                referrer_requirement: requirement,
+                referee_requirement: requirement,
+                program_end_time: Some(i64::MAX),
+                milestones: [Milestone { threshold: u64::MAX, bonus: u64::MAX }; MAX_MILESTONES],
+                required_collection: Some(key),
+                link_signer: Some(key),
+                region_attestor: Some(key),
+                bridge_source_program: Some(key),
+                ..Default::default()
+            },
+            EligibilityCriteria::SIZE,
+        ),
+        assert_fits(
+            "Participant",
+            Participant {
+                referrer: Some(key),
+                payout_split: Some(PayoutSplit { recipient: key, bps: u16::MAX }),
+                pending_owner: Some(key),
+                rotated_to: Some(key),
+                rotated_from: Some(key),
+                ..Default::default()
+            },
+            Participant::SIZE,
+        ),
+        assert_fits("RefereeReceipt", RefereeReceipt::default(), RefereeReceipt::SIZE),
+        assert_fits("Invite", Invite::default(), Invite::SIZE),
+        assert_fits(
+            "NetworkConfig",
+            NetworkConfig { admin: key, limits: NetworkLimits::default(), bump: u8::MAX },
+            NetworkConfig::SIZE,
+        ),
+        assert_fits("FeeConfig", FeeConfig::default(), FeeConfig::SIZE),
+        assert_fits("AuthorityMeta", AuthorityMeta::default(), AuthorityMeta::SIZE),
+        assert_fits("WithdrawalRequest", WithdrawalRequest::default(), WithdrawalRequest::SIZE),
+        assert_fits("MatchOffer", MatchOffer::default(), MatchOffer::SIZE),
+        assert_fits("BoostEscrow", BoostEscrow::default(), BoostEscrow::SIZE),
+        assert_fits("Contest", Contest::default(), Contest::SIZE),
+        assert_fits("ClaimSplitter", ClaimSplitter::default(), ClaimSplitter::SIZE),
+        assert_fits("ChangeVote", ChangeVote::default(), ChangeVote::SIZE),
+        assert_fits(
+            "FinalReport",
+            FinalReport { program_end_time: Some(i64::MAX), ..Default::default() },
+            FinalReport::SIZE,
+        ),
+        assert_fits(
+            "EventQueue",
+            EventQueue { program: key, head: u64::MAX, records: Default::default(), bump: u8::MAX },
+            EventQueue::SIZE,
+        ),
+    ];
+
+    let budgeted: BTreeSet<_> = ACCOUNTS.iter().map(|account| account.name).collect();
+    assert_eq!(BTreeSet::from(checked), budgeted);
+}
+
+#[test]
+fn test_space_report_lists_every_field() {
+    let report = space_report();
+    for account in ACCOUNTS {
+        let space = 8 + layout_size(account.layout);
+        assert!(report.contains(&format!("{}: {} of {} bytes\n", account.name, space, account.budget)));
+        assert!(account.layout.iter().all(|(name, _)| report.contains(name)));
+    }
+}
@@ -1,11 +1,18 @@
-use anchor_client::{
-    anchor_lang,
-    solana_sdk::{pubkey::Pubkey, signer::Signer, system_program},
-};
+use anchor_client::solana_sdk::{pubkey::Pubkey, signer::Signer, system_program};
 use anchor_spl::token::spl_token;
-use solrefer::state::ReferralProgram;
+use solrefer::{
+    constants::{REFERRAL_PROGRAM_SEED, REWARD_DENOMINATION_RAW},
+    error::ReferralError,
+    instructions::check_token_vault_closable,
+    state::ReferralProgram,
+};
 
-use crate::test_util::{create_mint, create_token_account, deposit_tokens, mint_tokens, setup};
+use crate::test_util::{
+    create_mint, create_token_account, create_token_referral_program, create_token_referral_program_ending_at,
+    deposit_tokens, far_future_end_time, get_authority_meta_pda, get_cluster_time, get_eligibility_criteria_pda,
+    get_fee_config_pda, get_fee_treasury, get_final_report_pda, get_network_config_pda, get_referral_program_pda,
+    initialize_token_vault, mint_tokens, setup, wait_for_cluster_time,
+};
 #[test]
 fn test_create_referral_program_with_token_mint() {
     let (owner, _, _, program_id, client) = setup();
@@ -17,9 +24,7 @@ fn test_create_referral_program_with_token_mint() {
     let fixed_reward_amount = 1_000_000_000; // 1 token
 
     // Find PDA for referral program
-    let binding = owner.pubkey();
-    let seeds = [b"referral_program".as_ref(), binding.as_ref()];
-    let (referral_program_pubkey, _) = Pubkey::find_program_address(&seeds, &program_id);
+    let referral_program_pubkey = get_referral_program_pda(owner.pubkey(), 0, program_id);
 
     // Find PDA for eligibility criteria
     let (eligibility_criteria, _bump) =
@@ -33,15 +38,28 @@ fn test_create_referral_program_with_token_mint() {
         .accounts(solrefer::accounts::CreateReferralProgram {
             referral_program: referral_program_pubkey,
             eligibility_criteria,
+            final_report: get_final_report_pda(referral_program_pubkey, program_id),
             authority: owner.pubkey(),
             token_mint_info: Some(mint.pubkey()),
+            fee_config: get_fee_config_pda(program_id),
+            network_config: get_network_config_pda(program_id),
+            authority_meta: get_authority_meta_pda(owner.pubkey(), program_id),
+            treasury: get_fee_treasury(&client, program_id),
             system_program: system_program::ID,
             token_program: Some(spl_token::id()),
         })
         .args(solrefer::instruction::CreateReferralProgram {
+            program_index: 0,
             token_mint: Some(mint.pubkey()),
             fixed_reward_amount,
-            program_end_time: i64::MAX,
+            program_end_time: Some(far_future_end_time()),
+            reward_denomination: REWARD_DENOMINATION_RAW,
+            start_inactive: false,
+            terms_hash: [0u8; 32],
+            guardian: None,
+            withdrawal_destinations: Default::default(),
+            settings_locked_until: None,
+            settings: None,
         })
         .signer(&owner)
         .send()
@@ -59,7 +77,7 @@ fn test_create_referral_program_with_token_mint() {
     assert_eq!(referral_program.authority, owner.pubkey());
     assert_eq!(referral_program.token_mint, mint.pubkey());
     assert_eq!(referral_program.fixed_reward_amount, fixed_reward_amount);
-    assert_eq!(referral_program.total_referrals, 0);
+    assert_eq!(referral_program.total_referrals_credited, 0);
     assert_eq!(referral_program.total_rewards_distributed, 0);
     assert!(referral_program.is_active);
 
@@ -79,7 +97,6 @@ fn test_create_referral_program_with_token_mint() {
             authority: owner.pubkey(),
             system_program: system_program::ID,
             token_program: spl_token::id(),
-            rent: anchor_lang::solana_program::sysvar::rent::ID,
         })
         .args(solrefer::instruction::InitializeTokenVault)
         .signer(&owner)
@@ -140,3 +157,255 @@ fn test_create_referral_program_with_token_mint() {
         "Owner token balance should be reduced by deposit amount"
     );
 }
+
+#[test]
+fn test_signer_seeds_derive_program_address() {
+    let authority = Pubkey::new_unique();
+    for program_index in [0u64, 7] {
+        let (address, bump) = Pubkey::find_program_address(
+            &[REFERRAL_PROGRAM_SEED, authority.as_ref(), ReferralProgram::index_seed(&program_index.to_le_bytes())],
+            &solrefer::ID,
+        );
+        let program = ReferralProgram { authority, program_index, bump, ..Default::default() };
+        let index_bytes = program.program_index.to_le_bytes();
+        let seeds = program.signer_seeds(&index_bytes);
+        assert_eq!(Pubkey::create_program_address(&seeds, &solrefer::ID).unwrap(), address);
+        assert_eq!(get_referral_program_pda(authority, program_index, solrefer::ID), address);
+    }
+
+    // An authority's first program keeps the address it had before programs were indexed
+    let (legacy, _) = Pubkey::find_program_address(&[REFERRAL_PROGRAM_SEED, authority.as_ref()], &solrefer::ID);
+    assert_eq!(get_referral_program_pda(authority, 0, solrefer::ID), legacy);
+}
+
+#[test]
+fn test_check_token_vault_closable() {
+    let ended = ReferralProgram { is_active: true, ..Default::default() };
+    assert!(check_token_vault_closable(&ended, Some(100), 100, 0, false).is_ok());
+    assert!(check_token_vault_closable(&ended, Some(100), 100, 5, true).is_ok());
+
+    assert_eq!(
+        check_token_vault_closable(&ended, Some(101), 100, 0, false).unwrap_err(),
+        ReferralError::ProgramStillActive.into()
+    );
+    let inactive = ReferralProgram { is_active: false, ..Default::default() };
+    assert!(check_token_vault_closable(&inactive, Some(101), 100, 0, false).is_ok());
+
+    // An open-ended program never ends, so it must be deactivated first
+    assert_eq!(
+        check_token_vault_closable(&ended, None, 100, 0, false).unwrap_err(),
+        ReferralError::ProgramStillActive.into()
+    );
+    assert!(check_token_vault_closable(&inactive, None, 100, 0, false).is_ok());
+
+    let owing = ReferralProgram { total_committed: 1, ..Default::default() };
+    assert_eq!(
+        check_token_vault_closable(&owing, Some(100), 100, 0, false).unwrap_err(),
+        ReferralError::OutstandingRewards.into()
+    );
+    assert_eq!(
+        check_token_vault_closable(&ended, Some(100), 100, 5, false).unwrap_err(),
+        ReferralError::TokenVaultNotEmpty.into()
+    );
+}
+
+#[test]
+fn test_close_token_vault_after_wind_down() {
+    let (owner, _, _, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+    let rpc = program.rpc();
+
+    let mint = create_mint(&owner, &client, program_id);
+    let end_time = get_cluster_time(&client, program_id) + 10;
+    let referral_program = create_token_referral_program_ending_at(
+        &owner,
+        mint.pubkey(),
+        1_000_000,
+        REWARD_DENOMINATION_RAW,
+        end_time,
+        &client,
+        program_id,
+    );
+    let token_vault = initialize_token_vault(&owner, referral_program, mint.pubkey(), &client, program_id);
+    let owner_token_account = create_token_account(&owner, &mint.pubkey(), &client, program_id);
+    mint_tokens(&mint, &owner_token_account, &owner, 1_000_000_000, &client, program_id);
+    deposit_tokens(
+        400_000_000,
+        referral_program,
+        token_vault,
+        mint.pubkey(),
+        owner_token_account,
+        &owner,
+        &client,
+        program_id,
+    );
+
+    let close = |destination_token_account: Option<Pubkey>| {
+        program
+            .request()
+            .accounts(solrefer::accounts::CloseTokenVault {
+                referral_program,
+                eligibility_criteria: get_eligibility_criteria_pda(referral_program, program_id),
+                token_vault,
+                destination_token_account,
+                authority: owner.pubkey(),
+                token_program: spl_token::id(),
+            })
+            .args(solrefer::instruction::CloseTokenVault {})
+            .signer(&owner)
+            .send()
+            .map_err(Box::new)
+    };
+
+    // The vault stays open while the program runs
+    let err = close(Some(owner_token_account)).unwrap_err();
+    assert!(err.to_string().contains("ProgramStillActive"));
+    wait_for_cluster_time(&client, program_id, end_time);
+
+    // Remaining tokens need somewhere to go
+    let err = close(None).unwrap_err();
+    assert!(err.to_string().contains("TokenVaultNotEmpty"));
+
+    let vault_rent = rpc.get_balance(&token_vault).unwrap();
+    let owner_lamports = rpc.get_balance(&owner.pubkey()).unwrap();
+    close(Some(owner_token_account)).unwrap();
+
+    assert!(rpc.get_account(&token_vault).is_err());
+    let owner_tokens = rpc.get_token_account_balance(&owner_token_account).unwrap().amount.parse::<u64>().unwrap();
+    assert_eq!(owner_tokens, 1_000_000_000);
+    assert_eq!(rpc.get_balance(&owner.pubkey()).unwrap() + 5_000, owner_lamports + vault_rent);
+
+    let program_account: ReferralProgram = program.account(referral_program).unwrap();
+    assert_eq!(program_account.token_mint, mint.pubkey());
+    assert!(program_account.token_vault_closed);
+    assert_eq!(program_account.total_available, 0);
+    assert!(!program_account.is_active);
+}
+
+#[test]
+fn test_deposit_token_requires_initialized_vault() {
+    let (owner, _, _, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+
+    let mint = create_mint(&owner, &client, program_id);
+    let referral_program = create_token_referral_program_ending_at(
+        &owner,
+        mint.pubkey(),
+        1_000_000,
+        REWARD_DENOMINATION_RAW,
+        far_future_end_time(),
+        &client,
+        program_id,
+    );
+    let owner_token_account = create_token_account(&owner, &mint.pubkey(), &client, program_id);
+    mint_tokens(&mint, &owner_token_account, &owner, 1_000_000_000, &client, program_id);
+
+    let program_account: ReferralProgram = program.account(referral_program).unwrap();
+    assert!(!program_account.token_vault_initialized);
+
+    // Depositing before the vault exists fails with a setup error rather than an account error
+    let (token_vault, _) = Pubkey::find_program_address(&[b"token_vault", referral_program.as_ref()], &program_id);
+    let err = program
+        .request()
+        .accounts(solrefer::accounts::DepositToken {
+            referral_program,
+            token_vault,
+            token_mint: mint.pubkey(),
+            depositor_token_account: owner_token_account,
+            authority: owner.pubkey(),
+            event_queue: None,
+            token_program: spl_token::id(),
+        })
+        .args(solrefer::instruction::DepositToken { amount: 100_000_000 })
+        .signer(&owner)
+        .send()
+        .unwrap_err();
+    assert!(err.to_string().contains("TokenVaultNotInitialized"));
+
+    assert_eq!(initialize_token_vault(&owner, referral_program, mint.pubkey(), &client, program_id), token_vault);
+    let program_account: ReferralProgram = program.account(referral_program).unwrap();
+    assert!(program_account.token_vault_initialized);
+
+    deposit_tokens(
+        100_000_000,
+        referral_program,
+        token_vault,
+        mint.pubkey(),
+        owner_token_account,
+        &owner,
+        &client,
+        program_id,
+    );
+    let program_account: ReferralProgram = program.account(referral_program).unwrap();
+    assert_eq!(program_account.total_available, 100_000_000);
+}
+
+#[test]
+fn test_withdraw_tokens_from_token_vault() {
+    let (owner, _, _, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+    let rpc = program.rpc();
+
+    let mint = create_mint(&owner, &client, program_id);
+    let referral_program =
+        create_token_referral_program(&owner, mint.pubkey(), 1_000_000, REWARD_DENOMINATION_RAW, &client, program_id);
+    let token_vault = initialize_token_vault(&owner, referral_program, mint.pubkey(), &client, program_id);
+    let owner_token_account = create_token_account(&owner, &mint.pubkey(), &client, program_id);
+    mint_tokens(&mint, &owner_token_account, &owner, 1_000_000_000, &client, program_id);
+    deposit_tokens(
+        400_000_000,
+        referral_program,
+        token_vault,
+        mint.pubkey(),
+        owner_token_account,
+        &owner,
+        &client,
+        program_id,
+    );
+
+    let (vault, _) = Pubkey::find_program_address(&[b"vault", referral_program.as_ref()], &program_id);
+    let withdraw = |amount: u64, with_token_accounts: bool| {
+        program
+            .request()
+            .accounts(solrefer::accounts::WithdrawFunds {
+                referral_program,
+                vault,
+                token_vault: with_token_accounts.then_some(token_vault),
+                destination_token_account: with_token_accounts.then_some(owner_token_account),
+                destination: None,
+                authority: owner.pubkey(),
+                system_program: system_program::ID,
+                token_program: with_token_accounts.then_some(spl_token::id()),
+            })
+            .args(solrefer::instruction::WithdrawFunds { amount })
+            .signer(&owner)
+            .send()
+            .map_err(Box::new)
+    };
+    let token_balance =
+        |account: &Pubkey| rpc.get_token_account_balance(account).unwrap().amount.parse::<u64>().unwrap();
+
+    // A token program's funds only leave through its token vault
+    let err = withdraw(100_000_000, false).unwrap_err();
+    assert!(err.to_string().contains("InvalidTokenAccounts"));
+
+    // No more than the deposited tokens can be withdrawn
+    let err = withdraw(400_000_001, true).unwrap_err();
+    assert!(err.to_string().contains("InsufficientFunds"));
+    assert_eq!(token_balance(&token_vault), 400_000_000);
+
+    withdraw(150_000_000, true).unwrap();
+    assert_eq!(token_balance(&token_vault), 250_000_000);
+    assert_eq!(token_balance(&owner_token_account), 750_000_000);
+    let program_account: ReferralProgram = program.account(referral_program).unwrap();
+    assert_eq!(program_account.total_available, 250_000_000);
+
+    // The rest can be drained, after which nothing more comes out
+    withdraw(250_000_000, true).unwrap();
+    assert_eq!(token_balance(&token_vault), 0);
+    assert_eq!(token_balance(&owner_token_account), 1_000_000_000);
+    let program_account: ReferralProgram = program.account(referral_program).unwrap();
+    assert_eq!(program_account.total_available, 0);
+    let err = withdraw(1, true).unwrap_err();
+    assert!(err.to_string().contains("InsufficientFunds"));
+}
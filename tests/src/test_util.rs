@@ -1,18 +1,30 @@
 use anchor_client::{
-    anchor_lang::system_program,
+    anchor_lang::{
+        __private::base64::{engine::general_purpose::STANDARD, Engine},
+        system_program, AnchorDeserialize, Discriminator,
+    },
     solana_client::rpc_client::RpcClient,
     solana_sdk::{
+        account::from_account,
+        clock::Clock,
         commitment_config::CommitmentConfig,
+        instruction::Instruction,
         native_token::LAMPORTS_PER_SOL,
         pubkey::Pubkey,
         signature::{read_keypair_file, Keypair},
         signer::Signer,
-        system_instruction,
+        system_instruction, sysvar,
+        transaction::Transaction,
     },
     Client, Cluster,
 };
 use anchor_spl::token::spl_token;
-use solrefer::{accounts, instruction};
+use solrefer::{
+    accounts,
+    constants::{REFERRAL_PROGRAM_SEED, REWARD_DENOMINATION_RAW},
+    instruction,
+    state::{FeeConfig, ReferralProgram},
+};
 use std::{process::Command, str::FromStr, sync::Arc};
 
 pub fn ensure_test_validator() -> RpcClient {
@@ -117,6 +129,15 @@ pub fn setup() -> (Keypair, Keypair, Keypair, Pubkey, Client<Arc<Keypair>>) {
 }
 
 pub fn create_mint(owner: &Keypair, client: &Client<Arc<Keypair>>, program_id: Pubkey) -> Keypair {
+    create_mint_with_decimals(owner, 9, client, program_id)
+}
+
+pub fn create_mint_with_decimals(
+    owner: &Keypair,
+    decimals: u8,
+    client: &Client<Arc<Keypair>>,
+    program_id: Pubkey,
+) -> Keypair {
     // Create new token mint
     let mint = Keypair::new();
     let mint_authority = owner;
@@ -143,7 +164,7 @@ pub fn create_mint(owner: &Keypair, client: &Client<Arc<Keypair>>, program_id: P
         &mint.pubkey(),
         &mint_authority.pubkey(),
         Some(&mint_authority.pubkey()),
-        9,
+        decimals,
     )
     .unwrap();
 
@@ -232,6 +253,7 @@ pub fn deposit_sol(
             referral_program: referral_program_pubkey,
             vault,
             authority: authority.pubkey(),
+            event_queue: None,
             system_program: system_program::ID,
         })
         .args(instruction::DepositSol { amount })
@@ -265,6 +287,7 @@ pub fn deposit_tokens(
             token_mint,
             depositor_token_account,
             authority: authority.pubkey(),
+            event_queue: None,
             token_program: spl_token::id(),
         })
         .args(instruction::DepositToken { amount })
@@ -284,10 +307,29 @@ pub fn create_sol_referral_program(
     program_id: Pubkey,
     fixed_reward_amount: u64,
     program_end_time: i64,
+) -> (Pubkey, Pubkey) {
+    create_sol_referral_program_with_status(
+        owner,
+        client,
+        program_id,
+        fixed_reward_amount,
+        Some(program_end_time),
+        false,
+    )
+}
+
+/// Creates a SOL referral program, open-ended if `program_end_time` is `None` and inactive pending
+/// `activate_program` if `start_inactive` is set
+pub fn create_sol_referral_program_with_status(
+    owner: &Keypair,
+    client: &Client<Arc<Keypair>>,
+    program_id: Pubkey,
+    fixed_reward_amount: u64,
+    program_end_time: Option<i64>,
+    start_inactive: bool,
 ) -> (Pubkey, Pubkey) {
     // Find the PDA for referral program
-    let (referral_program, _) =
-        Pubkey::find_program_address(&[b"referral_program", owner.pubkey().as_ref()], &program_id);
+    let referral_program = get_referral_program_pda(owner.pubkey(), 0, program_id);
 
     let (vault, _) = Pubkey::find_program_address(&[b"vault", referral_program.as_ref()], &program_id);
 
@@ -298,12 +340,29 @@ pub fn create_sol_referral_program(
         .accounts(solrefer::accounts::CreateReferralProgram {
             referral_program,
             eligibility_criteria: get_eligibility_criteria_pda(referral_program, program_id),
+            final_report: get_final_report_pda(referral_program, program_id),
             authority: owner.pubkey(),
             token_mint_info: None,
+            fee_config: get_fee_config_pda(program_id),
+            network_config: get_network_config_pda(program_id),
+            authority_meta: get_authority_meta_pda(owner.pubkey(), program_id),
+            treasury: get_fee_treasury(client, program_id),
             token_program: None,
             system_program: system_program::ID,
         })
-        .args(solrefer::instruction::CreateReferralProgram { token_mint: None, fixed_reward_amount, program_end_time })
+        .args(solrefer::instruction::CreateReferralProgram {
+            program_index: 0,
+            token_mint: None,
+            fixed_reward_amount,
+            program_end_time,
+            reward_denomination: REWARD_DENOMINATION_RAW,
+            start_inactive,
+            terms_hash: [0u8; 32],
+            guardian: None,
+            withdrawal_destinations: Default::default(),
+            settings_locked_until: None,
+            settings: None,
+        })
         .signer(owner)
         .send()
         .expect("Failed to create SOL referral program");
@@ -312,8 +371,413 @@ pub fn create_sol_referral_program(
     (referral_program, vault)
 }
 
+/// Returns a realistic far-future program end time (one year from now) that stays within `MAX_PROGRAM_DURATION`
+pub fn far_future_end_time() -> i64 {
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+    now + 365 * 86400
+}
+
+/// Returns the current unix timestamp as seen by the cluster
+pub fn get_cluster_time(client: &Client<Arc<Keypair>>, program_id: Pubkey) -> i64 {
+    let rpc_client = client.program(program_id).unwrap().rpc();
+    let account = rpc_client.get_account(&sysvar::clock::ID).expect("Failed to fetch clock sysvar");
+    let clock: Clock = from_account(&account).expect("Failed to deserialize clock sysvar");
+    clock.unix_timestamp
+}
+
+/// Blocks until the cluster clock reaches `timestamp`
+pub fn wait_for_cluster_time(client: &Client<Arc<Keypair>>, program_id: Pubkey, timestamp: i64) {
+    while get_cluster_time(client, program_id) < timestamp {
+        std::thread::sleep(std::time::Duration::from_millis(500));
+    }
+}
+
+/// Creates a token referral program for the given mint and returns the referral program PDA
+pub fn create_token_referral_program(
+    owner: &Keypair,
+    mint: Pubkey,
+    fixed_reward_amount: u64,
+    reward_denomination: u8,
+    client: &Client<Arc<Keypair>>,
+    program_id: Pubkey,
+) -> Pubkey {
+    create_token_referral_program_ending_at(
+        owner,
+        mint,
+        fixed_reward_amount,
+        reward_denomination,
+        far_future_end_time(),
+        client,
+        program_id,
+    )
+}
+
+/// Creates a token referral program that ends at `program_end_time` and returns the referral program PDA
+pub fn create_token_referral_program_ending_at(
+    owner: &Keypair,
+    mint: Pubkey,
+    fixed_reward_amount: u64,
+    reward_denomination: u8,
+    program_end_time: i64,
+    client: &Client<Arc<Keypair>>,
+    program_id: Pubkey,
+) -> Pubkey {
+    let referral_program = get_referral_program_pda(owner.pubkey(), 0, program_id);
+
+    client
+        .program(program_id)
+        .unwrap()
+        .request()
+        .accounts(accounts::CreateReferralProgram {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, program_id),
+            final_report: get_final_report_pda(referral_program, program_id),
+            authority: owner.pubkey(),
+            token_mint_info: Some(mint),
+            fee_config: get_fee_config_pda(program_id),
+            network_config: get_network_config_pda(program_id),
+            authority_meta: get_authority_meta_pda(owner.pubkey(), program_id),
+            treasury: get_fee_treasury(client, program_id),
+            token_program: Some(spl_token::id()),
+            system_program: system_program::ID,
+        })
+        .args(instruction::CreateReferralProgram {
+            program_index: 0,
+            token_mint: Some(mint),
+            fixed_reward_amount,
+            program_end_time: Some(program_end_time),
+            reward_denomination,
+            start_inactive: false,
+            terms_hash: [0u8; 32],
+            guardian: None,
+            withdrawal_destinations: Default::default(),
+            settings_locked_until: None,
+            settings: None,
+        })
+        .signer(owner)
+        .send()
+        .expect("Failed to create token referral program");
+
+    referral_program
+}
+
+/// Initializes the token vault of a token referral program and returns its PDA
+pub fn initialize_token_vault(
+    owner: &Keypair,
+    referral_program: Pubkey,
+    mint: Pubkey,
+    client: &Client<Arc<Keypair>>,
+    program_id: Pubkey,
+) -> Pubkey {
+    let (token_vault, _) = Pubkey::find_program_address(&[b"token_vault", referral_program.as_ref()], &program_id);
+    client
+        .program(program_id)
+        .unwrap()
+        .request()
+        .accounts(accounts::InitializeTokenVault {
+            referral_program,
+            token_vault,
+            token_mint: mint,
+            authority: owner.pubkey(),
+            system_program: system_program::ID,
+            token_program: spl_token::id(),
+        })
+        .args(instruction::InitializeTokenVault)
+        .signer(owner)
+        .send()
+        .expect("Failed to initialize token vault");
+    token_vault
+}
+
+// Helper function to get the PDA of an authority's referral program at `program_index`
+pub fn get_referral_program_pda(authority: Pubkey, program_index: u64, program_id: Pubkey) -> Pubkey {
+    let (pda, _) = Pubkey::find_program_address(
+        &[REFERRAL_PROGRAM_SEED, authority.as_ref(), ReferralProgram::index_seed(&program_index.to_le_bytes())],
+        &program_id,
+    );
+    pda
+}
+
 // Helper function to get eligibility criteria PDA
 pub fn get_eligibility_criteria_pda(referral_program: Pubkey, program_id: Pubkey) -> Pubkey {
     let (pda, _) = Pubkey::find_program_address(&[b"eligibility_criteria", referral_program.as_ref()], &program_id);
     pda
 }
+
+/// Derives the referee receipt PDA for a wallet in a referral program
+pub fn get_referee_receipt_pda(referral_program: Pubkey, user: Pubkey, program_id: Pubkey) -> Pubkey {
+    let (pda, _) = Pubkey::find_program_address(&[b"referee", referral_program.as_ref(), user.as_ref()], &program_id);
+    pda
+}
+
+/// Derives the boost escrow PDA of a referrer's participant account
+pub fn get_boost_escrow_pda(referral_program: Pubkey, participant: Pubkey, program_id: Pubkey) -> Pubkey {
+    let (pda, _) =
+        Pubkey::find_program_address(&[b"boost", referral_program.as_ref(), participant.as_ref()], &program_id);
+    pda
+}
+
+/// Derives the match offer PDA of a sponsor in a referral program
+pub fn get_match_offer_pda(referral_program: Pubkey, sponsor: Pubkey, program_id: Pubkey) -> Pubkey {
+    let (pda, _) = Pubkey::find_program_address(&[b"match", referral_program.as_ref(), sponsor.as_ref()], &program_id);
+    pda
+}
+
+/// Derives the queued withdrawal PDA of a referral program
+pub fn get_withdrawal_request_pda(referral_program: Pubkey, program_id: Pubkey) -> Pubkey {
+    let (pda, _) = Pubkey::find_program_address(&[b"withdrawal", referral_program.as_ref()], &program_id);
+    pda
+}
+
+/// Derives the final report PDA a closed referral program leaves behind
+pub fn get_final_report_pda(referral_program: Pubkey, program_id: Pubkey) -> Pubkey {
+    let (pda, _) = Pubkey::find_program_address(&[b"final_report", referral_program.as_ref()], &program_id);
+    pda
+}
+
+/// Derives the sponsor vault PDA of a referral program
+pub fn get_sponsor_vault_pda(referral_program: Pubkey, program_id: Pubkey) -> Pubkey {
+    let (pda, _) = Pubkey::find_program_address(&[b"sponsor_vault", referral_program.as_ref()], &program_id);
+    pda
+}
+
+/// Derives the participant PDA for a wallet in a referral program
+pub fn get_participant_pda(referral_program: Pubkey, user: Pubkey, program_id: Pubkey) -> Pubkey {
+    let (pda, _) =
+        Pubkey::find_program_address(&[b"participant", referral_program.as_ref(), user.as_ref()], &program_id);
+    pda
+}
+
+/// Derives the event queue PDA of a referral program
+pub fn get_event_queue_pda(referral_program: Pubkey, program_id: Pubkey) -> Pubkey {
+    let (pda, _) = Pubkey::find_program_address(&[b"events", referral_program.as_ref()], &program_id);
+    pda
+}
+
+/// Derives the protocol fee config PDA
+pub fn get_fee_config_pda(program_id: Pubkey) -> Pubkey {
+    let (pda, _) = Pubkey::find_program_address(&[b"fee_config"], &program_id);
+    pda
+}
+
+/// Derives the network config PDA
+pub fn get_network_config_pda(program_id: Pubkey) -> Pubkey {
+    let (pda, _) = Pubkey::find_program_address(&[b"network_config"], &program_id);
+    pda
+}
+
+/// Derives the program-creation counter PDA of an authority
+pub fn get_authority_meta_pda(authority: Pubkey, program_id: Pubkey) -> Pubkey {
+    let (pda, _) = Pubkey::find_program_address(&[b"authority_meta", authority.as_ref()], &program_id);
+    pda
+}
+
+/// Returns the fee config treasury once the fee config exists, so program creation can pay its fee
+pub fn get_fee_treasury(client: &Client<Arc<Keypair>>, program_id: Pubkey) -> Option<Pubkey> {
+    let program = client.program(program_id).unwrap();
+    program.account::<FeeConfig>(get_fee_config_pda(program_id)).ok().map(|fee_config| fee_config.treasury)
+}
+
+/// Derives the PDA of a referral program's settings change record at `index`
+pub fn get_settings_change_pda(referral_program: Pubkey, index: u64, program_id: Pubkey) -> Pubkey {
+    let seeds: &[&[u8]] = &[b"settings_change", referral_program.as_ref(), &index.to_le_bytes()];
+    let (pda, _) = Pubkey::find_program_address(seeds, &program_id);
+    pda
+}
+
+/// Returns the PDA the next settings change of a referral program is recorded at
+pub fn get_next_settings_change_pda(
+    referral_program: Pubkey,
+    client: &Client<Arc<Keypair>>,
+    program_id: Pubkey,
+) -> Pubkey {
+    let program = client.program(program_id).unwrap();
+    let index = program.account::<ReferralProgram>(referral_program).unwrap().settings_change_count;
+    get_settings_change_pda(referral_program, index, program_id)
+}
+
+/// Derives the PDA of a referral program's invite at `index`
+pub fn get_invite_pda(referral_program: Pubkey, index: u64, program_id: Pubkey) -> Pubkey {
+    let (pda, _) =
+        Pubkey::find_program_address(&[b"invite", referral_program.as_ref(), &index.to_le_bytes()], &program_id);
+    pda
+}
+
+/// Derives the pending settings change PDA of a referral program
+pub fn get_pending_change_pda(referral_program: Pubkey, program_id: Pubkey) -> Pubkey {
+    let (pda, _) = Pubkey::find_program_address(&[b"pending_change", referral_program.as_ref()], &program_id);
+    pda
+}
+
+/// Derives the PDA of a participant's vote on a referral program's pending change at `index`
+pub fn get_change_vote_pda(referral_program: Pubkey, index: u64, participant: Pubkey, program_id: Pubkey) -> Pubkey {
+    let seeds: &[&[u8]] = &[b"change_vote", referral_program.as_ref(), &index.to_le_bytes(), participant.as_ref()];
+    let (pda, _) = Pubkey::find_program_address(seeds, &program_id);
+    pda
+}
+
+/// Joins a referral program directly and returns the new participant PDA
+pub fn join_referral_program(
+    user: &Keypair,
+    referral_program: Pubkey,
+    client: &Client<Arc<Keypair>>,
+    program_id: Pubkey,
+) -> Pubkey {
+    let participant = get_participant_pda(referral_program, user.pubkey(), program_id);
+    client
+        .program(program_id)
+        .unwrap()
+        .request()
+        .accounts(accounts::JoinReferralProgram {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, program_id),
+            participant,
+            invite: None,
+            collection_metadata: None,
+            collection_nft: None,
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account: None,
+            bridge_participant: None,
+            bridge_referrer: None,
+            user: user.pubkey(),
+            event_queue: None,
+            system_program: system_program::ID,
+        })
+        .args(instruction::JoinReferralProgram { accepted_terms_hash: [0u8; 32] })
+        .signer(user)
+        .send()
+        .expect("Failed to join referral program");
+    participant
+}
+
+/// Joins a referral program through a referrer's participant account and returns the new participant PDA
+pub fn join_through_referral(
+    user: &Keypair,
+    referral_program: Pubkey,
+    referrer: Pubkey,
+    client: &Client<Arc<Keypair>>,
+    program_id: Pubkey,
+) -> Pubkey {
+    join_through_referral_with_split(user, referral_program, referrer, None, client, program_id)
+}
+
+/// Joins through a referrer whose payout split routes a share to `split_recipient` (a participant PDA)
+pub fn join_through_referral_with_split(
+    user: &Keypair,
+    referral_program: Pubkey,
+    referrer: Pubkey,
+    split_recipient: Option<Pubkey>,
+    client: &Client<Arc<Keypair>>,
+    program_id: Pubkey,
+) -> Pubkey {
+    let participant = get_participant_pda(referral_program, user.pubkey(), program_id);
+    client
+        .program(program_id)
+        .unwrap()
+        .request()
+        .accounts(accounts::JoinThroughReferral {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, program_id),
+            participant,
+            referrer,
+            rotated_referrer: None,
+            split_recipient,
+            boost_escrow: None,
+            match_offer: None,
+            referrer_wallet: None,
+            referee_receipt: get_referee_receipt_pda(referral_program, user.pubkey(), program_id),
+            sponsor_vault: None,
+            invite: None,
+            referrer_collection_metadata: None,
+            referrer_collection_nft: None,
+            age_reference: None,
+            region_attestation: None,
+            referrer_token_account: None,
+            referee_token_account: None,
+            referrer_upline: None,
+            user: user.pubkey(),
+            event_queue: None,
+            instructions_sysvar: None,
+            system_program: system_program::ID,
+        })
+        .args(instruction::JoinThroughReferral { source_tag: None, accepted_terms_hash: [0u8; 32], link_proof: None })
+        .signer(user)
+        .send()
+        .expect("Failed to join through referral");
+    participant
+}
+
+/// Creates and funds an extra wallet for tests that need more users than `setup` provides
+pub fn create_funded_user() -> Keypair {
+    let user = Keypair::new();
+    let rpc_client = ensure_test_validator();
+    request_airdrop_with_retries(&rpc_client, &user.pubkey(), LAMPORTS_PER_SOL * 2).expect("Failed to fund user");
+    user
+}
+
+/// Simulates the given instructions, successful or not, and returns the events of type `T` they emitted
+pub fn simulate_events<T: AnchorDeserialize + Discriminator>(
+    instructions: &[Instruction],
+    payer: &Keypair,
+    client: &Client<Arc<Keypair>>,
+    program_id: Pubkey,
+) -> Vec<T> {
+    let rpc_client = client.program(program_id).unwrap().rpc();
+    let blockhash = rpc_client.get_latest_blockhash().expect("Failed to get blockhash");
+    let tx = Transaction::new_signed_with_payer(instructions, Some(&payer.pubkey()), &[payer], blockhash);
+    let result = rpc_client.simulate_transaction(&tx).expect("Failed to simulate transaction");
+    result
+        .value
+        .logs
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|log| log.strip_prefix("Program data: "))
+        .filter_map(|data| STANDARD.decode(data).ok())
+        .filter(|bytes| bytes.starts_with(&T::DISCRIMINATOR))
+        .map(|bytes| T::deserialize(&mut &bytes[8..]).expect("Failed to deserialize event"))
+        .collect()
+}
+
+/// Simulates the given instructions and returns the compute units they consumed
+pub fn simulate_units_consumed(
+    instructions: &[Instruction],
+    payer: &Keypair,
+    client: &Client<Arc<Keypair>>,
+    program_id: Pubkey,
+) -> u64 {
+    let rpc_client = client.program(program_id).unwrap().rpc();
+    let blockhash = rpc_client.get_latest_blockhash().expect("Failed to get blockhash");
+    let tx = Transaction::new_signed_with_payer(instructions, Some(&payer.pubkey()), &[payer], blockhash);
+    let result = rpc_client.simulate_transaction(&tx).expect("Failed to simulate transaction");
+    assert!(result.value.err.is_none(), "Simulation failed: {:?}", result.value.logs);
+    result.value.units_consumed.expect("Simulation reported no compute units")
+}
+
+/// Updates the settings of a referral program
+pub fn update_program_settings(
+    authority: &Keypair,
+    referral_program: Pubkey,
+    new_settings: solrefer::instructions::ProgramSettings,
+    client: &Client<Arc<Keypair>>,
+    program_id: Pubkey,
+) {
+    client
+        .program(program_id)
+        .unwrap()
+        .request()
+        .accounts(accounts::UpdateProgramSettings {
+            referral_program,
+            eligibility_criteria: get_eligibility_criteria_pda(referral_program, program_id),
+            network_config: get_network_config_pda(program_id),
+            authority: authority.pubkey(),
+            settings_change: get_next_settings_change_pda(referral_program, client, program_id),
+            pending_change: None,
+            event_queue: None,
+            system_program: system_program::ID,
+        })
+        .args(instruction::UpdateProgramSettings { new_settings, idempotency_key: None })
+        .signer(authority)
+        .send()
+        .expect("Failed to update program settings");
+}